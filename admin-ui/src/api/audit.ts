@@ -26,6 +26,60 @@ export interface AuditLogEntry {
   created_at: string
   status: 'success' | 'error' | 'denied'
   error_message: string | null
+  served_by: string | null
+  shadow_outcomes: ShadowOutcome[] | null
+  epsilon_spent: number | null
+  rows_returned: number | null
+  bytes_returned: number | null
+  impersonated_by: string | null
+  purpose: string | null
+  justification: string | null
+  policy_conflicts: PolicyConflict[] | null
+  rows_affected: number | null
+  /** Rows a governed write touched: inserted/updated rows as written, deleted rows before removal. */
+  affected_rows: Record<string, unknown>[] | null
+}
+
+export interface PolicyConflict {
+  policy_type: string
+  /** `schema.table`, or `schema.table.column` for column_mask */
+  target: string
+  strategy: string
+  applied: string[]
+  overridden?: string[]
+}
+
+export interface ShadowOutcome {
+  policy_id: string
+  name: string
+  version: number
+  policy_type: string
+  outcome:
+    | 'would_deny'
+    | 'would_deny_columns'
+    | 'would_filter'
+    | 'would_mask'
+    | 'would_allow'
+    | 'would_require_aggregate'
+    | 'would_add_noise'
+    | 'would_limit_results'
+    | 'would_block_query'
+  tables: string[]
+  columns?: string[]
+  expression?: string
+}
+
+export interface ShadowReportEntry {
+  policy_id: string
+  policy_name: string
+  policy_type: string
+  hits: number
+  would_deny: number
+  would_filter: number
+  would_mask: number
+  users: number
+  first_hit_at: string
+  last_hit_at: string
 }
 
 export async function listAuditLogs(params?: {
@@ -40,3 +94,12 @@ export async function listAuditLogs(params?: {
   const { data } = await client.get<PaginatedResponse<AuditLogEntry>>('/audit/queries', { params })
   return data
 }
+
+export async function getShadowReport(params?: {
+  from?: string
+  to?: string
+  datasource_id?: string
+}): Promise<ShadowReportEntry[]> {
+  const { data } = await client.get<ShadowReportEntry[]>('/audit/shadow', { params })
+  return data
+}
@@ -2,6 +2,7 @@ import { fetchEventSource } from '@microsoft/fetch-event-source'
 import { client } from './client'
 import type {
   CatalogResponse,
+  CatalogTagsResponse,
   ColumnAnchor,
   CreateColumnAnchorRequest,
   CreateTableRelationshipRequest,
@@ -9,6 +10,7 @@ import type {
   DiscoveryRequest,
   FkSuggestion,
   JobStatusResponse,
+  SetCatalogTagsRequest,
   SubmitDiscoveryResponse,
   TableRelationship,
 } from '../types/catalog'
@@ -89,6 +91,18 @@ export async function getCatalog(datasourceId: string): Promise<CatalogResponse>
   return data
 }
 
+// Replace the tags on one catalog object; tags flow down schema → table → column.
+export async function setCatalogTags(
+  datasourceId: string,
+  body: SetCatalogTagsRequest,
+): Promise<CatalogTagsResponse> {
+  const { data } = await client.put<CatalogTagsResponse>(
+    `/datasources/${datasourceId}/catalog/tags`,
+    body,
+  )
+  return data
+}
+
 // ---------- Relationships + column anchors ----------
 
 export async function listRelationships(
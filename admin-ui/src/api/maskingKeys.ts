@@ -0,0 +1,52 @@
+import { client } from './client'
+import type {
+  MaskingKeyResponse,
+  CreateMaskingKeyPayload,
+  UpdateMaskingKeyPayload,
+} from '../types/maskingKey'
+
+export async function listMaskingKeys(): Promise<MaskingKeyResponse[]> {
+  const { data } = await client.get<MaskingKeyResponse[]>('/masking-keys')
+  return data
+}
+
+export async function createMaskingKey(
+  payload: CreateMaskingKeyPayload,
+): Promise<MaskingKeyResponse> {
+  const { data } = await client.post<MaskingKeyResponse>('/masking-keys', payload)
+  return data
+}
+
+export async function updateMaskingKey(
+  id: string,
+  payload: UpdateMaskingKeyPayload,
+): Promise<MaskingKeyResponse> {
+  const { data } = await client.put<MaskingKeyResponse>(`/masking-keys/${id}`, payload)
+  return data
+}
+
+export async function deleteMaskingKey(id: string): Promise<void> {
+  await client.delete(`/masking-keys/${id}`)
+}
+
+export async function revealMaskedValues(
+  id: string,
+  values: string[],
+): Promise<(string | null)[]> {
+  const { data } = await client.post<{ values: (string | null)[] }>(
+    `/masking-keys/${id}/reveal`,
+    { values },
+  )
+  return data.values
+}
+
+export async function reidentifyTokens(
+  id: string,
+  tokens: string[],
+): Promise<(string | null)[]> {
+  const { data } = await client.post<{ values: (string | null)[] }>(
+    `/masking-keys/${id}/reidentify`,
+    { tokens },
+  )
+  return data.values
+}
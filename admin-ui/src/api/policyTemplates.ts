@@ -0,0 +1,37 @@
+import { client } from './client'
+import type {
+  PolicyTemplateResponse,
+  CreatePolicyTemplatePayload,
+  UpdatePolicyTemplatePayload,
+} from '../types/policyTemplate'
+
+export async function listPolicyTemplates(): Promise<PolicyTemplateResponse[]> {
+  const { data } = await client.get<{ data: PolicyTemplateResponse[] }>('/policy-templates', {
+    params: { page_size: 200 },
+  })
+  return data.data
+}
+
+export async function createPolicyTemplate(
+  payload: CreatePolicyTemplatePayload,
+): Promise<PolicyTemplateResponse> {
+  const { data } = await client.post<PolicyTemplateResponse>('/policy-templates', payload)
+  return data
+}
+
+export async function getPolicyTemplate(id: string): Promise<PolicyTemplateResponse> {
+  const { data } = await client.get<PolicyTemplateResponse>(`/policy-templates/${id}`)
+  return data
+}
+
+export async function updatePolicyTemplate(
+  id: string,
+  payload: UpdatePolicyTemplatePayload,
+): Promise<PolicyTemplateResponse> {
+  const { data } = await client.put<PolicyTemplateResponse>(`/policy-templates/${id}`, payload)
+  return data
+}
+
+export async function deletePolicyTemplate(id: string): Promise<void> {
+  await client.delete(`/policy-templates/${id}`)
+}
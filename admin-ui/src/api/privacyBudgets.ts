@@ -0,0 +1,21 @@
+import { client } from './client'
+
+export interface PrivacyBudgetEntry {
+  user_id: string
+  username: string
+  epsilon_spent: number
+  query_count: number
+  created_at: string
+  updated_at: string
+}
+
+export async function listPrivacyBudgets(datasourceId: string): Promise<PrivacyBudgetEntry[]> {
+  const { data } = await client.get<PrivacyBudgetEntry[]>(
+    `/datasources/${datasourceId}/privacy-budgets`,
+  )
+  return data
+}
+
+export async function resetPrivacyBudget(datasourceId: string, userId: string): Promise<void> {
+  await client.delete(`/datasources/${datasourceId}/privacy-budgets/${userId}`)
+}
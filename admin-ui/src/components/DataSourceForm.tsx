@@ -15,6 +15,7 @@ interface DataSourceFormProps {
     config?: Record<string, unknown>
     is_active?: boolean
     access_mode?: string
+    write_mode?: string
   }
   onSubmit: (values: {
     name: string
@@ -22,6 +23,7 @@ interface DataSourceFormProps {
     config: Record<string, unknown>
     is_active: boolean
     access_mode?: string
+    write_mode?: string
   }) => Promise<void>
   submitLabel?: string
   isSubmitting?: boolean
@@ -46,6 +48,7 @@ export function DataSourceForm({
   const [dsType, setDsType] = useState(initialValues?.ds_type ?? '')
   const [isActive, setIsActive] = useState(initialValues?.is_active ?? true)
   const [accessMode, setAccessMode] = useState(initialValues?.access_mode ?? 'open')
+  const [writeMode, setWriteMode] = useState(initialValues?.write_mode ?? 'read_only')
   const [fieldValues, setFieldValues] = useState<Record<string, string>>(() => {
     const init: Record<string, string> = {}
     if (initialValues?.config) {
@@ -126,7 +129,14 @@ export function DataSourceForm({
     }
 
     try {
-      await onSubmit({ name: name.trim(), ds_type: dsType, config, is_active: isActive, access_mode: isEdit ? accessMode : undefined })
+      await onSubmit({
+        name: name.trim(),
+        ds_type: dsType,
+        config,
+        is_active: isActive,
+        access_mode: isEdit ? accessMode : undefined,
+        write_mode: isEdit ? writeMode : undefined,
+      })
     } catch (err: unknown) {
       const msg =
         (err as { response?: { data?: { error?: string } } })?.response?.data?.error ??
@@ -269,6 +279,42 @@ export function DataSourceForm({
         </div>
       )}
 
+      {/* write_mode (edit mode only) */}
+      {isEdit && (
+        <div>
+          <label className="block text-sm font-medium text-gray-700 mb-1">Write Mode</label>
+          <div className="flex gap-4">
+            <label className="flex items-center gap-2 cursor-pointer">
+              <input
+                type="radio"
+                name="write_mode"
+                value="read_only"
+                checked={writeMode === 'read_only'}
+                onChange={() => setWriteMode('read_only')}
+                className="text-blue-600 focus:ring-blue-500"
+              />
+              <span className="text-sm text-gray-700">Read Only</span>
+            </label>
+            <label className="flex items-center gap-2 cursor-pointer">
+              <input
+                type="radio"
+                name="write_mode"
+                value="read_write"
+                checked={writeMode === 'read_write'}
+                onChange={() => setWriteMode('read_write')}
+                className="text-blue-600 focus:ring-blue-500"
+              />
+              <span className="text-sm text-gray-700">Read Write</span>
+            </label>
+          </div>
+          <p className="text-xs text-gray-500 mt-1">
+            {writeMode === 'read_write'
+              ? 'INSERT, UPDATE and DELETE run upstream only where a write_allow policy grants them. DDL is always refused.'
+              : 'All writes are refused.'}
+          </p>
+        </div>
+      )}
+
       {/* Actions */}
       <div className="flex items-center gap-3 pt-1">
         <button
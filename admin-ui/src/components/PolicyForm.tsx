@@ -1,5 +1,13 @@
 import { useState, useEffect, useMemo } from 'react'
-import type { PolicyResponse, PolicyType, TargetEntry } from '../types/policy'
+import type {
+  ActionStatus,
+  LimitAction,
+  NoiseMechanism,
+  PolicyResponse,
+  PolicyType,
+  TargetEntry,
+  WriteOperation,
+} from '../types/policy'
 import type { DecisionFunctionResponse, DecisionFunctionSummary } from '../types/decisionFunction'
 import type { AttributeDefinition } from '../types/attributeDefinition'
 import { listDecisionFunctions, getDecisionFunction } from '../api/decisionFunctions'
@@ -15,9 +23,17 @@ const POLICY_TYPES: { value: PolicyType; label: string }[] = [
   { value: 'column_allow', label: 'Column Allow' },
   { value: 'column_deny', label: 'Column Deny' },
   { value: 'table_deny', label: 'Table Deny' },
+  { value: 'aggregate_only', label: 'Aggregate Only' },
+  { value: 'differential_privacy', label: 'Differential Privacy' },
+  { value: 'result_limit', label: 'Result Limit' },
+  { value: 'query_guard', label: 'Query Guard' },
+  { value: 'join_only', label: 'Join Only' },
+  { value: 'write_allow', label: 'Write Allow' },
+  { value: 'write_deny', label: 'Write Deny' },
 ]
 
-const DENY_TYPES: PolicyType[] = ['column_deny', 'table_deny']
+const DENY_TYPES: PolicyType[] = ['column_deny', 'table_deny', 'write_deny']
+const WRITE_OPERATIONS: WriteOperation[] = ['insert', 'update', 'delete']
 const CHIP_DISPLAY_LIMIT = 20
 const FILTER_THRESHOLD = 15
 
@@ -40,12 +56,100 @@ export interface PolicyFormValues {
   description: string
   policy_type: PolicyType
   is_enabled: boolean
+  action_status: ActionStatus
   targets: TargetEntry[]
   filter_expression: string
   mask_expression: string
+  min_group_size: number
+  dp_epsilon: number
+  dp_budget: number
+  dp_mechanism: NoiseMechanism
+  dp_delta: number
+  dp_bounds: Record<string, [number, number]>
+  limit_max_rows: number | null
+  limit_max_bytes: number | null
+  limit_window_rows: number | null
+  limit_window_secs: number | null
+  limit_on_exceed: LimitAction
+  guard_forbid_select_star: boolean
+  guard_select_star_min_columns: number | null
+  guard_require_predicate_on: string[]
+  guard_forbid_cross_join: boolean
+  guard_max_joins: number | null
+  write_operations: WriteOperation[]
   decision_function_id?: string | null
 }
 
+/** The `definition` JSON for the form's policy type (`null` for types without one). */
+export function policyDefinition(values: PolicyFormValues): Record<string, unknown> | null {
+  switch (values.policy_type) {
+    case 'row_filter':
+      return { filter_expression: values.filter_expression }
+    case 'column_mask':
+      return { mask_expression: values.mask_expression }
+    case 'aggregate_only':
+      return { min_group_size: values.min_group_size }
+    case 'differential_privacy':
+      return {
+        epsilon: values.dp_epsilon,
+        budget: values.dp_budget,
+        mechanism: values.dp_mechanism,
+        ...(values.dp_mechanism === 'gaussian' ? { delta: values.dp_delta } : {}),
+        bounds: values.dp_bounds,
+      }
+    case 'result_limit':
+      return {
+        ...(values.limit_max_rows != null ? { max_rows: values.limit_max_rows } : {}),
+        ...(values.limit_max_bytes != null ? { max_bytes: values.limit_max_bytes } : {}),
+        ...(values.limit_window_rows != null
+          ? { window_rows: values.limit_window_rows, window_secs: values.limit_window_secs }
+          : {}),
+        on_exceed: values.limit_on_exceed,
+      }
+    case 'query_guard':
+      return {
+        forbid_select_star: values.guard_forbid_select_star,
+        ...(values.guard_forbid_select_star && values.guard_select_star_min_columns != null
+          ? { select_star_min_columns: values.guard_select_star_min_columns }
+          : {}),
+        require_predicate_on: values.guard_require_predicate_on,
+        forbid_cross_join: values.guard_forbid_cross_join,
+        ...(values.guard_max_joins != null ? { max_joins: values.guard_max_joins } : {}),
+      }
+    case 'write_allow':
+      return { operations: values.write_operations }
+    default:
+      return null
+  }
+}
+
+/** Optional positive integer from a number input (`null` when empty). */
+function optionalCount(value: string): number | null {
+  const n = parseInt(value, 10)
+  return Number.isFinite(n) && n > 0 ? n : null
+}
+
+/** Format `bounds` as one `column: lower, upper` line per column. */
+function formatBounds(bounds: unknown): string {
+  if (!bounds || typeof bounds !== 'object') return ''
+  return Object.entries(bounds as Record<string, [number, number]>)
+    .map(([col, [lower, upper]]) => `${col}: ${lower}, ${upper}`)
+    .join('\n')
+}
+
+/** Parse `column: lower, upper` lines; returns an error message for the first bad line. */
+function parseBounds(text: string): Record<string, [number, number]> | string {
+  const bounds: Record<string, [number, number]> = {}
+  for (const line of text.split('\n').map((l) => l.trim()).filter(Boolean)) {
+    const match = line.match(/^([^:]+):\s*(-?[\d.]+)\s*,\s*(-?[\d.]+)$/)
+    if (!match) return `Invalid bounds line "${line}" — expected "column: lower, upper"`
+    const [lower, upper] = [Number(match[2]), Number(match[3])]
+    if (!(lower < upper)) return `Bounds for "${match[1].trim()}" must have lower < upper`
+    bounds[match[1].trim()] = [lower, upper]
+  }
+  return bounds
+}
+
 interface PolicyFormProps {
   initial?: PolicyResponse
   onSubmit: (values: PolicyFormValues) => Promise<void>
@@ -254,6 +358,7 @@ export function PolicyForm({ initial, onSubmit, submitLabel, isSubmitting, error
     (initial?.policy_type as PolicyType) ?? 'row_filter',
   )
   const [isEnabled, setIsEnabled] = useState(initial?.is_enabled ?? true)
+  const [actionStatus, setActionStatus] = useState<ActionStatus>(initial?.action_status ?? 'enforce')
 
   const initialTargets = initial ? targetsFromPolicy(initial) : [emptyTarget()]
   const [targets, setTargets] = useState<TargetEntry[]>(initialTargets)
@@ -266,10 +371,50 @@ export function PolicyForm({ initial, onSubmit, submitLabel, isSubmitting, error
   )
 
   const [filterExpression, setFilterExpression] = useState(
-    initial?.definition?.filter_expression ?? '',
+    String(initial?.definition?.filter_expression ?? ''),
   )
   const [maskExpression, setMaskExpression] = useState(
-    initial?.definition?.mask_expression ?? '',
+    String(initial?.definition?.mask_expression ?? ''),
+  )
+  const [minGroupSize, setMinGroupSize] = useState(
+    Number(initial?.definition?.min_group_size ?? 5),
+  )
+  const [dpEpsilon, setDpEpsilon] = useState(Number(initial?.definition?.epsilon ?? 0.5))
+  const [dpBudget, setDpBudget] = useState(Number(initial?.definition?.budget ?? 10))
+  const [dpMechanism, setDpMechanism] = useState<NoiseMechanism>(
+    (initial?.definition?.mechanism as NoiseMechanism | undefined) ?? 'laplace',
+  )
+  const [dpDelta, setDpDelta] = useState(Number(initial?.definition?.delta ?? 1e-6))
+  const [dpBounds, setDpBounds] = useState(formatBounds(initial?.definition?.bounds))
+  const [dpBoundsError, setDpBoundsError] = useState<string | null>(null)
+  const [limitMaxRows, setLimitMaxRows] = useState(
+    String(initial?.definition?.max_rows ?? (initial ? '' : 1000)),
+  )
+  const [limitMaxBytes, setLimitMaxBytes] = useState(String(initial?.definition?.max_bytes ?? ''))
+  const [limitWindowRows, setLimitWindowRows] = useState(
+    String(initial?.definition?.window_rows ?? ''),
+  )
+  const [limitWindowSecs, setLimitWindowSecs] = useState(
+    String(initial?.definition?.window_secs ?? 3600),
+  )
+  const [limitOnExceed, setLimitOnExceed] = useState<LimitAction>(
+    (initial?.definition?.on_exceed as LimitAction | undefined) ?? 'truncate',
+  )
+  const [guardForbidSelectStar, setGuardForbidSelectStar] = useState(
+    Boolean(initial?.definition?.forbid_select_star ?? !initial),
+  )
+  const [guardSelectStarMinColumns, setGuardSelectStarMinColumns] = useState(
+    String(initial?.definition?.select_star_min_columns ?? ''),
+  )
+  const [guardRequirePredicateOn, setGuardRequirePredicateOn] = useState(
+    ((initial?.definition?.require_predicate_on as string[] | undefined) ?? []).join(', '),
+  )
+  const [guardForbidCrossJoin, setGuardForbidCrossJoin] = useState(
+    Boolean(initial?.definition?.forbid_cross_join ?? !initial),
+  )
+  const [guardMaxJoins, setGuardMaxJoins] = useState(String(initial?.definition?.max_joins ?? ''))
+  const [writeOperations, setWriteOperations] = useState<WriteOperation[]>(
+    (initial?.definition?.operations as WriteOperation[] | undefined) ?? ['insert', 'update'],
   )
 
   // Attribute definitions for {user.*} autocomplete in expression editors
@@ -303,9 +448,19 @@ export function PolicyForm({ initial, onSubmit, submitLabel, isSubmitting, error
   const [existingFunctions, setExistingFunctions] = useState<DecisionFunctionResponse[]>([])
   const [loadingExisting, setLoadingExisting] = useState(false)
 
-  const needsColumns = policyType === 'column_mask' || policyType === 'column_allow' || policyType === 'column_deny'
+  const needsColumns = policyType === 'column_mask' ||
+    policyType === 'column_allow' ||
+    policyType === 'column_deny' ||
+    policyType === 'join_only' ||
+    policyType === 'write_allow' ||
+    policyType === 'write_deny'
   const needsFilter = policyType === 'row_filter'
   const needsMask = policyType === 'column_mask'
+  const needsGroupSize = policyType === 'aggregate_only'
+  const needsPrivacy = policyType === 'differential_privacy'
+  const needsLimits = policyType === 'result_limit'
+  const needsGuards = policyType === 'query_guard'
+  const needsOperations = policyType === 'write_allow'
   const isDeny = DENY_TYPES.includes(policyType)
 
   function addTarget() {
@@ -510,15 +665,38 @@ export function PolicyForm({ initial, onSubmit, submitLabel, isSubmitting, error
       setAttachedFnError('The attached decision function no longer exists. Detach it or select a new one.')
       return
     }
+    const parsedBounds = needsPrivacy ? parseBounds(dpBounds) : {}
+    if (typeof parsedBounds === 'string') {
+      setDpBoundsError(parsedBounds)
+      return
+    }
 
     const values: PolicyFormValues = {
       name,
       description,
       policy_type: policyType,
       is_enabled: isEnabled,
+      action_status: actionStatus,
       targets: buildTargets(),
       filter_expression: filterExpression,
       mask_expression: maskExpression,
+      min_group_size: minGroupSize,
+      dp_epsilon: dpEpsilon,
+      dp_budget: dpBudget,
+      dp_mechanism: dpMechanism,
+      dp_delta: dpDelta,
+      dp_bounds: parsedBounds,
+      limit_max_rows: optionalCount(limitMaxRows),
+      limit_max_bytes: optionalCount(limitMaxBytes),
+      limit_window_rows: optionalCount(limitWindowRows),
+      limit_window_secs: optionalCount(limitWindowSecs),
+      limit_on_exceed: limitOnExceed,
+      guard_forbid_select_star: guardForbidSelectStar,
+      guard_select_star_min_columns: optionalCount(guardSelectStarMinColumns),
+      guard_require_predicate_on: stringToArray(guardRequirePredicateOn),
+      guard_forbid_cross_join: guardForbidCrossJoin,
+      guard_max_joins: guardMaxJoins.trim() === '' ? null : Math.max(0, parseInt(guardMaxJoins, 10) || 0),
+      write_operations: WRITE_OPERATIONS.filter((op) => writeOperations.includes(op)),
       decision_function_id: useDecisionFn && attachedFnId ? attachedFnId : null,
     }
 
@@ -577,6 +755,15 @@ export function PolicyForm({ initial, onSubmit, submitLabel, isSubmitting, error
               />
             </button>
             <span className="text-sm text-gray-700">{isEnabled ? 'Enabled' : 'Disabled'}</span>
+            <select
+              aria-label="Action status"
+              value={actionStatus}
+              onChange={(e) => setActionStatus(e.target.value as ActionStatus)}
+              className="ml-4 border border-gray-300 rounded-lg px-2 py-1 text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+            >
+              <option value="enforce">Enforce</option>
+              <option value="shadow">Shadow (log only)</option>
+            </select>
           </div>
         </div>
       </div>
@@ -641,6 +828,255 @@ export function PolicyForm({ initial, onSubmit, submitLabel, isSubmitting, error
             </p>
           </div>
         )}
+
+        {needsGroupSize && (
+          <div>
+            <label className="block text-sm font-medium text-gray-700 mb-1">Minimum Group Size</label>
+            <input
+              type="number"
+              min={2}
+              value={minGroupSize}
+              onChange={(e) => setMinGroupSize(parseInt(e.target.value, 10) || 2)}
+              className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+            />
+            <p className="text-xs text-gray-400 mt-1">
+              Target tables can only be read through aggregate queries. Groups with fewer rows
+              than this are dropped from the result.
+            </p>
+          </div>
+        )}
+
+        {needsPrivacy && (
+          <div className="space-y-4">
+            <div className="flex flex-wrap gap-4">
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Epsilon per aggregate</label>
+                <input
+                  type="number"
+                  min={0}
+                  max={10}
+                  step="any"
+                  value={dpEpsilon}
+                  onChange={(e) => setDpEpsilon(parseFloat(e.target.value) || 0)}
+                  className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                />
+              </div>
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Budget per user</label>
+                <input
+                  type="number"
+                  min={0}
+                  step="any"
+                  value={dpBudget}
+                  onChange={(e) => setDpBudget(parseFloat(e.target.value) || 0)}
+                  className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                />
+              </div>
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Mechanism</label>
+                <select
+                  value={dpMechanism}
+                  onChange={(e) => setDpMechanism(e.target.value as NoiseMechanism)}
+                  className="px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                >
+                  <option value="laplace">Laplace</option>
+                  <option value="gaussian">Gaussian</option>
+                </select>
+              </div>
+              {dpMechanism === 'gaussian' && (
+                <div>
+                  <label className="block text-sm font-medium text-gray-700 mb-1">Delta</label>
+                  <input
+                    type="number"
+                    min={0}
+                    max={1}
+                    step="any"
+                    value={dpDelta}
+                    onChange={(e) => setDpDelta(parseFloat(e.target.value) || 0)}
+                    className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                  />
+                </div>
+              )}
+            </div>
+            <div>
+              <label className="block text-sm font-medium text-gray-700 mb-1">Contribution bounds</label>
+              <textarea
+                value={dpBounds}
+                onChange={(e) => { setDpBounds(e.target.value); setDpBoundsError(null) }}
+                rows={3}
+                placeholder="salary: 0, 200000"
+                className="w-full px-3 py-2 border border-gray-300 rounded-md text-sm font-mono focus:outline-none focus:ring-2 focus:ring-blue-500"
+              />
+              {dpBoundsError ? (
+                <p className="text-xs text-red-600 mt-1">{dpBoundsError}</p>
+              ) : (
+                <p className="text-xs text-gray-400 mt-1">
+                  One <code className="bg-gray-100 px-1 rounded">column: lower, upper</code> per line.
+                  Only these columns can be used in SUM and AVG; values are clamped to the range.
+                  COUNT, SUM and AVG get noise, and each one spends epsilon from the user&apos;s budget.
+                </p>
+              )}
+            </div>
+          </div>
+        )}
+
+        {needsLimits && (
+          <div className="space-y-4">
+            <div className="flex flex-wrap gap-4">
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Max rows per query</label>
+                <input
+                  type="number"
+                  min={1}
+                  value={limitMaxRows}
+                  onChange={(e) => setLimitMaxRows(e.target.value)}
+                  className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                />
+              </div>
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Max bytes per query</label>
+                <input
+                  type="number"
+                  min={1}
+                  value={limitMaxBytes}
+                  onChange={(e) => setLimitMaxBytes(e.target.value)}
+                  className="w-36 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                />
+              </div>
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">When exceeded</label>
+                <select
+                  value={limitOnExceed}
+                  onChange={(e) => setLimitOnExceed(e.target.value as LimitAction)}
+                  className="px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                >
+                  <option value="truncate">Truncate</option>
+                  <option value="deny">Deny</option>
+                </select>
+              </div>
+            </div>
+            <div className="flex flex-wrap gap-4">
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Rows per window</label>
+                <input
+                  type="number"
+                  min={1}
+                  value={limitWindowRows}
+                  onChange={(e) => setLimitWindowRows(e.target.value)}
+                  className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                />
+              </div>
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Window (seconds)</label>
+                <input
+                  type="number"
+                  min={60}
+                  value={limitWindowSecs}
+                  onChange={(e) => setLimitWindowSecs(e.target.value)}
+                  className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                />
+              </div>
+            </div>
+            <p className="text-xs text-gray-400">
+              Leave a cap empty to disable it. Truncate returns the first rows that fit; Deny fails
+              the query instead. Rows per window is a per-user quota across all queries; once it is
+              used up, queries on the target tables are refused until the window rolls over.
+            </p>
+          </div>
+        )}
+
+        {needsGuards && (
+          <div className="space-y-4">
+            <div className="flex flex-wrap items-end gap-4">
+              <label className="flex items-center gap-2 text-sm text-gray-700">
+                <input
+                  type="checkbox"
+                  checked={guardForbidSelectStar}
+                  onChange={(e) => setGuardForbidSelectStar(e.target.checked)}
+                  className="rounded border-gray-300 text-blue-600"
+                />
+                Forbid SELECT *
+              </label>
+              {guardForbidSelectStar && (
+                <div>
+                  <label className="block text-sm font-medium text-gray-700 mb-1">
+                    Only on tables with at least (columns)
+                  </label>
+                  <input
+                    type="number"
+                    min={1}
+                    value={guardSelectStarMinColumns}
+                    onChange={(e) => setGuardSelectStarMinColumns(e.target.value)}
+                    className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                  />
+                </div>
+              )}
+            </div>
+            <div>
+              <label className="block text-sm font-medium text-gray-700 mb-1">Require a predicate on</label>
+              <input
+                type="text"
+                value={guardRequirePredicateOn}
+                onChange={(e) => setGuardRequirePredicateOn(e.target.value)}
+                placeholder="event_date, region"
+                className="w-full px-3 py-2 border border-gray-300 rounded-md text-sm font-mono focus:outline-none focus:ring-2 focus:ring-blue-500"
+              />
+            </div>
+            <div className="flex flex-wrap items-end gap-4">
+              <label className="flex items-center gap-2 text-sm text-gray-700">
+                <input
+                  type="checkbox"
+                  checked={guardForbidCrossJoin}
+                  onChange={(e) => setGuardForbidCrossJoin(e.target.checked)}
+                  className="rounded border-gray-300 text-blue-600"
+                />
+                Forbid cross joins
+              </label>
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Max joins</label>
+                <input
+                  type="number"
+                  min={0}
+                  value={guardMaxJoins}
+                  onChange={(e) => setGuardMaxJoins(e.target.value)}
+                  className="w-32 px-3 py-2 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                />
+              </div>
+            </div>
+            <p className="text-xs text-gray-400">
+              Queries on the target tables that break a rule are refused with the rule&apos;s name.
+              Required predicates apply to the listed columns that exist on each target table. Leave
+              Max joins empty for no limit.
+            </p>
+          </div>
+        )}
+
+        {needsOperations && (
+          <div className="space-y-2">
+            <label className="block text-sm font-medium text-gray-700">Operations</label>
+            <div className="flex flex-wrap gap-4">
+              {WRITE_OPERATIONS.map((op) => (
+                <label key={op} className="flex items-center gap-2 text-sm text-gray-700">
+                  <input
+                    type="checkbox"
+                    checked={writeOperations.includes(op)}
+                    onChange={(e) =>
+                      setWriteOperations((prev) =>
+                        e.target.checked ? [...prev, op] : prev.filter((o) => o !== op),
+                      )
+                    }
+                    className="rounded border-gray-300 text-blue-600"
+                  />
+                  {op.toUpperCase()}
+                </label>
+              ))}
+            </div>
+            <p className="text-xs text-gray-400">
+              Granted only on data sources in read_write mode. INSERT and UPDATE may set only the
+              target columns; DELETE removes whole rows.
+            </p>
+          </div>
+        )}
       </div>
 
       {/* Section 3: Targets — where it applies */}
@@ -0,0 +1,83 @@
+import { useQuery, useMutation, useQueryClient } from '@tanstack/react-query'
+import toast from 'react-hot-toast'
+import { listPrivacyBudgets, resetPrivacyBudget } from '../api/privacyBudgets'
+
+interface PrivacyBudgetPanelProps {
+  datasourceId: string
+}
+
+export function PrivacyBudgetPanel({ datasourceId }: PrivacyBudgetPanelProps) {
+  const queryClient = useQueryClient()
+
+  const { data: budgets, isLoading } = useQuery({
+    queryKey: ['privacy-budgets', datasourceId],
+    queryFn: () => listPrivacyBudgets(datasourceId),
+  })
+
+  const resetMutation = useMutation({
+    mutationFn: (userId: string) => resetPrivacyBudget(datasourceId, userId),
+    onSuccess: () => {
+      toast.success('Privacy budget reset')
+      queryClient.invalidateQueries({ queryKey: ['privacy-budgets', datasourceId] })
+    },
+    onError: (err: unknown) => {
+      const msg =
+        (err as { response?: { data?: { error?: string } } })?.response?.data?.error ??
+        'Failed to reset privacy budget'
+      toast.error(msg)
+    },
+  })
+
+  if (isLoading) {
+    return <div className="text-sm text-gray-400">Loading...</div>
+  }
+
+  return (
+    <div>
+      <div className="mb-3">
+        <h2 className="text-base font-semibold text-gray-900">Privacy Budgets</h2>
+        <p className="text-xs text-gray-400 mt-0.5">
+          Epsilon spent by each user under differential privacy policies on this datasource.
+          Resetting a budget lets the user query again.
+        </p>
+      </div>
+
+      {(budgets ?? []).length === 0 ? (
+        <p className="text-sm text-gray-400">No privacy budget has been spent yet.</p>
+      ) : (
+        <table className="w-full text-sm">
+          <thead>
+            <tr className="text-left text-xs text-gray-500 border-b border-gray-200">
+              <th className="py-2 font-medium">User</th>
+              <th className="py-2 font-medium">Epsilon spent</th>
+              <th className="py-2 font-medium">Queries</th>
+              <th className="py-2 font-medium">Last query</th>
+              <th className="py-2" />
+            </tr>
+          </thead>
+          <tbody>
+            {budgets!.map((b) => (
+              <tr key={b.user_id} className="border-b border-gray-100">
+                <td className="py-2 text-gray-900">{b.username}</td>
+                <td className="py-2 font-mono text-gray-700">{b.epsilon_spent.toFixed(2)}</td>
+                <td className="py-2 text-gray-700">{b.query_count}</td>
+                <td className="py-2 text-xs text-gray-500">
+                  {new Date(b.updated_at).toLocaleString()}
+                </td>
+                <td className="py-2 text-right">
+                  <button
+                    onClick={() => resetMutation.mutate(b.user_id)}
+                    disabled={resetMutation.isPending}
+                    className="text-xs text-red-600 hover:text-red-800 disabled:opacity-50"
+                  >
+                    Reset
+                  </button>
+                </td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+      )}
+    </div>
+  )
+}
@@ -50,12 +50,14 @@ function makeCatalog(): CatalogResponse {
         schema_name: 'postgres',
         schema_alias: 'pg',
         is_selected: true,
+        tags: [],
         tables: [
           {
             id: 't-payments',
             table_name: 'payments',
             table_type: 'table',
             is_selected: true,
+            tags: [],
             columns: [
               {
                 id: 'c-payments-id',
@@ -66,6 +68,8 @@ function makeCatalog(): CatalogResponse {
                 column_default: null,
                 arrow_type: 'Int64',
                 is_selected: true,
+                tags: [],
+                effective_tags: [],
               },
               {
                 id: 'c-payments-order',
@@ -76,6 +80,8 @@ function makeCatalog(): CatalogResponse {
                 column_default: null,
                 arrow_type: 'Int64',
                 is_selected: true,
+                tags: [],
+                effective_tags: [],
               },
             ],
           },
@@ -84,6 +90,7 @@ function makeCatalog(): CatalogResponse {
             table_name: 'orders',
             table_type: 'table',
             is_selected: true,
+            tags: [],
             columns: [
               {
                 id: 'c-orders-id',
@@ -94,6 +101,8 @@ function makeCatalog(): CatalogResponse {
                 column_default: null,
                 arrow_type: 'Int64',
                 is_selected: true,
+                tags: [],
+                effective_tags: [],
               },
               {
                 id: 'c-orders-tenant',
@@ -104,6 +113,8 @@ function makeCatalog(): CatalogResponse {
                 column_default: null,
                 arrow_type: 'Int64',
                 is_selected: true,
+                tags: [],
+                effective_tags: [],
               },
             ],
           },
@@ -112,6 +123,7 @@ function makeCatalog(): CatalogResponse {
             table_name: 'customers',
             table_type: 'table',
             is_selected: true,
+            tags: [],
             columns: [
               {
                 id: 'c-customers-id',
@@ -122,6 +134,8 @@ function makeCatalog(): CatalogResponse {
                 column_default: null,
                 arrow_type: 'Int64',
                 is_selected: true,
+                tags: [],
+                effective_tags: [],
               },
               // Note: no tenant_id here — used for "non-viable candidate" test.
               {
@@ -133,6 +147,8 @@ function makeCatalog(): CatalogResponse {
                 column_default: null,
                 arrow_type: 'Utf8',
                 is_selected: true,
+                tags: [],
+                effective_tags: [],
               },
             ],
           },
@@ -6,6 +6,7 @@ import { getDataSource, updateDataSource } from '../api/datasources'
 import { DataSourceForm } from '../components/DataSourceForm'
 import { UserAssignmentPanel } from '../components/UserAssignmentPanel'
 import { RoleAccessPanel } from '../components/RoleAccessPanel'
+import { PrivacyBudgetPanel } from '../components/PrivacyBudgetPanel'
 import {
   ColumnAnchorsSection,
   RelationshipsSection,
@@ -26,6 +27,7 @@ type SectionId =
   | 'users'
   | 'roles'
   | 'policies'
+  | 'privacy'
   | 'catalog'
   | 'relationships'
   | 'anchors'
@@ -40,6 +42,7 @@ const SECTIONS: readonly DsSection[] = [
   { id: 'users', label: 'Users', group: 'Access', width: 'wide' },
   { id: 'roles', label: 'Roles', group: 'Access', width: 'wide' },
   { id: 'policies', label: 'Policies', group: 'Access', width: 'wide' },
+  { id: 'privacy', label: 'Privacy budgets', group: 'Access', width: 'wide' },
   { id: 'catalog', label: 'Catalog', group: 'Schema', width: 'wide' },
   { id: 'relationships', label: 'Relationships', group: 'Schema', width: 'wide' },
   { id: 'anchors', label: 'Column anchors', group: 'Schema', width: 'wide' },
@@ -68,6 +71,7 @@ export function DataSourceEditPage() {
     config: Record<string, unknown>
     is_active: boolean
     access_mode?: string
+    write_mode?: string
   }) {
     // Rename is a separate flow in the Danger Zone (see
     // `docs/permission-system.md` → "Rename fragility and label-based
@@ -78,6 +82,7 @@ export function DataSourceEditPage() {
         is_active: values.is_active,
         config: values.config,
         access_mode: values.access_mode,
+        write_mode: values.write_mode,
       })
       queryClient.invalidateQueries({ queryKey: ['datasources'] })
       queryClient.invalidateQueries({ queryKey: ['datasource', dsId] })
@@ -137,6 +142,7 @@ export function DataSourceEditPage() {
                         config: ds.config as Record<string, unknown>,
                         is_active: ds.is_active,
                         access_mode: ds.access_mode,
+                        write_mode: ds.write_mode,
                       }}
                       onSubmit={handleSubmit}
                       submitLabel="Save changes"
@@ -165,6 +171,12 @@ export function DataSourceEditPage() {
                 </div>
               )}
 
+              {s.id === 'privacy' && (
+                <div className="bg-white rounded-xl border border-gray-200 p-6">
+                  <PrivacyBudgetPanel datasourceId={dsId} />
+                </div>
+              )}
+
               {s.id === 'catalog' && (
                 <div className="bg-white rounded-xl border border-gray-200 p-6">
                   <CatalogSection ds={ds} />
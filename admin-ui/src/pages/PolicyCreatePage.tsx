@@ -4,7 +4,7 @@ import { useQuery, useQueryClient } from '@tanstack/react-query'
 import { createPolicy, assignPolicy } from '../api/policies'
 import { listDataSources } from '../api/datasources'
 import { listUsers } from '../api/users'
-import { PolicyForm } from '../components/PolicyForm'
+import { PolicyForm, policyDefinition } from '../components/PolicyForm'
 import type { PolicyFormValues } from '../components/PolicyForm'
 import { useCatalogHints } from '../hooks/useCatalogHints'
 import { PageHeader } from '../components/layout/PageHeader'
@@ -42,13 +42,9 @@ export function PolicyCreatePage() {
         description: values.description || undefined,
         policy_type: values.policy_type,
         is_enabled: values.is_enabled,
+        action_status: values.action_status,
         targets: values.targets,
-        definition:
-          values.policy_type === 'row_filter'
-            ? { filter_expression: values.filter_expression }
-            : values.policy_type === 'column_mask'
-              ? { mask_expression: values.mask_expression }
-              : null,
+        definition: policyDefinition(values),
         decision_function_id: values.decision_function_id,
       })
       try {
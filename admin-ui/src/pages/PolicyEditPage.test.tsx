@@ -355,6 +355,7 @@ describe('PolicyEditPage', () => {
           role_name: null,
           assignment_scope: 'all',
           priority: 100,
+          action_status: 'enforce',
           created_at: '2024-01-01T00:00:00Z',
         },
       ],
@@ -367,12 +368,14 @@ describe('PolicyEditPage', () => {
           schema_name: 'analytics',
           schema_alias: null,
           is_selected: true,
+          tags: [],
           tables: [
             {
               id: 't-1',
               table_name: 'events',
               table_type: 'TABLE',
               is_selected: true,
+              tags: [],
               columns: [
                 {
                   id: 'c-1',
@@ -383,6 +386,8 @@ describe('PolicyEditPage', () => {
                   column_default: null,
                   arrow_type: 'Int64',
                   is_selected: true,
+                  tags: [],
+                  effective_tags: [],
                 },
               ],
             },
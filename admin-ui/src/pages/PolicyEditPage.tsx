@@ -3,7 +3,7 @@ import { useNavigate, useParams } from 'react-router-dom'
 import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query'
 import toast from 'react-hot-toast'
 import { deletePolicy, getPolicy, getPolicyAnchorCoverage, updatePolicy } from '../api/policies'
-import { PolicyForm } from '../components/PolicyForm'
+import { PolicyForm, policyDefinition } from '../components/PolicyForm'
 import type { PolicyFormValues } from '../components/PolicyForm'
 import { PolicyAssignmentEditPanel } from '../components/PolicyAssignmentPanel'
 import { PolicyAnchorCoveragePanel } from '../components/PolicyAnchorCoveragePanel'
@@ -119,13 +119,9 @@ export function PolicyEditPage() {
         description: values.description || undefined,
         policy_type: values.policy_type,
         is_enabled: values.is_enabled,
+        action_status: values.action_status,
         targets: values.targets,
-        definition:
-          values.policy_type === 'row_filter'
-            ? { filter_expression: values.filter_expression }
-            : values.policy_type === 'column_mask'
-              ? { mask_expression: values.mask_expression }
-              : null,
+        definition: policyDefinition(values),
         decision_function_id: values.decision_function_id,
         version: policy.version,
       })
@@ -299,8 +299,62 @@ export function QueryAuditPage() {
                               </ul>
                             </div>
                           )}
+                          {(entry.shadow_outcomes?.length ?? 0) > 0 && (
+                            <div>
+                              <p className="text-xs font-semibold text-gray-600 mb-1">Shadow policies (not enforced)</p>
+                              <ul className="space-y-1">
+                                {entry.shadow_outcomes!.map((o) => (
+                                  <li key={o.policy_id} className="text-xs text-gray-700">
+                                    <span className="font-medium">{o.name}</span>
+                                    <span className="text-amber-700 ml-1">{o.outcome.replace(/_/g, ' ')}</span>
+                                    <span className="text-gray-400 ml-1">
+                                      {(o.columns?.length ? o.columns : o.tables).join(', ')}
+                                    </span>
+                                    {o.expression && (
+                                      <code className="ml-1 font-mono text-gray-500">{o.expression}</code>
+                                    )}
+                                  </li>
+                                ))}
+                              </ul>
+                            </div>
+                          )}
+                          {(entry.policy_conflicts?.length ?? 0) > 0 && (
+                            <div>
+                              <p className="text-xs font-semibold text-gray-600 mb-1">Policy conflicts</p>
+                              <ul className="space-y-1">
+                                {entry.policy_conflicts!.map((c) => (
+                                  <li key={`${c.policy_type}:${c.target}`} className="text-xs text-gray-700">
+                                    <span className="font-medium">{c.target}</span>
+                                    <span className="text-gray-400 ml-1">
+                                      {c.policy_type} · {c.strategy.replace(/_/g, ' ')}
+                                    </span>
+                                    <span className="ml-1">applied {c.applied.join(', ')}</span>
+                                    {(c.overridden?.length ?? 0) > 0 && (
+                                      <span className="text-amber-700 ml-1">over {c.overridden!.join(', ')}</span>
+                                    )}
+                                  </li>
+                                ))}
+                              </ul>
+                            </div>
+                          )}
+                          {(entry.affected_rows?.length ?? 0) > 0 && (
+                            <div>
+                              <p className="text-xs font-semibold text-gray-600 mb-1">Affected rows</p>
+                              <pre className="text-xs font-mono text-gray-800 bg-white border border-gray-200 rounded p-3 overflow-auto whitespace-pre-wrap max-h-48">
+                                {entry.affected_rows!.map((r) => JSON.stringify(r)).join('\n')}
+                              </pre>
+                            </div>
+                          )}
                           <div className="flex gap-6 text-xs text-gray-500">
                             {entry.client_info && <span>App: {entry.client_info}</span>}
+                            {entry.served_by && <span>Upstream: {entry.served_by}</span>}
+                            {entry.epsilon_spent != null && <span>Epsilon spent: {entry.epsilon_spent}</span>}
+                            {entry.rows_returned != null && <span>Rows returned: {entry.rows_returned}</span>}
+                            {entry.rows_affected != null && <span>Rows affected: {entry.rows_affected}</span>}
+                            {entry.bytes_returned != null && <span>Bytes returned: {entry.bytes_returned}</span>}
+                            {entry.impersonated_by && <span>Impersonated by: {entry.impersonated_by}</span>}
+                            {entry.purpose && <span>Purpose: {entry.purpose}</span>}
+                            {entry.justification && <span>Justification: {entry.justification}</span>}
                           </div>
                         </div>
                       </td>
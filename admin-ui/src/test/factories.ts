@@ -76,6 +76,14 @@ export function makeDataSource(overrides: Partial<DataSource> = {}): DataSource
     config: { host: 'localhost', port: 5432, db: 'mydb', user: 'postgres' },
     is_active: true,
     access_mode: 'policy_required',
+    write_mode: 'read_only',
+    policy_combining: {
+      row_filter: 'intersection',
+      column_mask: 'first_match',
+      column_allow: 'union',
+      aggregate_only: 'most_restrictive',
+      differential_privacy: 'most_restrictive',
+    },
     last_sync_at: null,
     last_sync_result: null,
     created_at: '2024-01-01T00:00:00Z',
@@ -137,6 +145,7 @@ export function makePolicyAssignment(
     role_name: null,
     assignment_scope: 'all',
     priority: 100,
+    action_status: 'enforce',
     created_at: '2024-01-01T00:00:00Z',
     ...overrides,
   }
@@ -173,6 +182,7 @@ export function makePolicy(overrides: Partial<PolicyResponse> = {}): PolicyRespo
     targets: [{ schemas: ['public'], tables: ['orders'] }],
     definition: { filter_expression: 'tenant = 1' },
     is_enabled: true,
+    action_status: 'enforce',
     version: 1,
     assignment_count: 0,
     created_by: 'user-1',
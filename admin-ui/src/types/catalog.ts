@@ -36,6 +36,10 @@ export interface CatalogColumnResponse {
   column_default: string | null
   arrow_type: string | null
   is_selected: boolean
+  /// Tags set on this column.
+  tags: string[]
+  /// Tags after inheritance from the table and schema (what `tag:` targets match).
+  effective_tags: string[]
 }
 
 export interface CatalogTableResponse {
@@ -43,6 +47,7 @@ export interface CatalogTableResponse {
   table_name: string
   table_type: string
   is_selected: boolean
+  tags: string[]
   columns: CatalogColumnResponse[]
 }
 
@@ -51,9 +56,26 @@ export interface CatalogSchemaResponse {
   schema_name: string
   schema_alias: string | null
   is_selected: boolean
+  tags: string[]
   tables: CatalogTableResponse[]
 }
 
+/// Replace the tags on a schema, table (`table`) or column (`table` + `column`).
+export interface SetCatalogTagsRequest {
+  schema: string
+  table?: string
+  column?: string
+  tags: string[]
+}
+
+export interface CatalogTagsResponse {
+  schema: string
+  table?: string
+  column?: string
+  tags: string[]
+  effective_tags: string[]
+}
+
 export interface CatalogResponse {
   schemas: CatalogSchemaResponse[]
 }
@@ -25,6 +25,10 @@ export interface DataSource {
   is_active: boolean
   /** "open" = no policies required; "policy_required" = policies must be assigned */
   access_mode: string
+  /** "read_only" = writes refused; "read_write" = writes governed by write policies */
+  write_mode: string
+  /** Combination strategy per permit policy type, defaults filled in. */
+  policy_combining: PolicyCombining
   created_at: string
   updated_at: string
 }
@@ -42,6 +46,25 @@ export interface UpdateDataSourcePayload {
   /** Partial config update. Absent fields preserved. Empty string = keep secret. */
   config?: Record<string, unknown>
   access_mode?: string
+  write_mode?: string
+  /** Types left out keep their default; `{}` restores all defaults. */
+  policy_combining?: Partial<PolicyCombining>
+}
+
+export type CombineStrategy =
+  | 'first_match'
+  | 'most_specific'
+  | 'most_restrictive'
+  | 'least_restrictive'
+  | 'intersection'
+  | 'union'
+
+export interface PolicyCombining {
+  row_filter: CombineStrategy
+  column_mask: CombineStrategy
+  column_allow: CombineStrategy
+  aggregate_only: CombineStrategy
+  differential_privacy: CombineStrategy
 }
 
 export interface TestConnectionResponse {
@@ -0,0 +1,26 @@
+export interface MaskingKeyResponse {
+  id: string
+  name: string
+  description: string | null
+  reversible: boolean
+  version: number
+  created_by: string
+  updated_by: string
+  created_at: string
+  updated_at: string
+}
+
+export interface CreateMaskingKeyPayload {
+  name: string
+  description?: string
+  /** Base64-encoded 32-byte key. Generated server-side when omitted. */
+  secret?: string
+  reversible?: boolean
+}
+
+export interface UpdateMaskingKeyPayload {
+  description?: string
+  reversible?: boolean
+  rotate?: boolean
+  version: number
+}
@@ -1,9 +1,30 @@
 import type { DecisionFunctionSummary } from './decisionFunction'
+import type { PolicyTemplateSummary } from './policyTemplate'
 
-export type PolicyType = 'row_filter' | 'column_mask' | 'column_allow' | 'column_deny' | 'table_deny'
+export type PolicyType =
+  | 'row_filter'
+  | 'column_mask'
+  | 'column_allow'
+  | 'column_deny'
+  | 'table_deny'
+  | 'aggregate_only'
+  | 'differential_privacy'
+  | 'result_limit'
+  | 'query_guard'
+  | 'join_only'
+  | 'write_allow'
+  | 'write_deny'
+
+export type NoiseMechanism = 'laplace' | 'gaussian'
+
+export type LimitAction = 'truncate' | 'deny'
+
+export type WriteOperation = 'insert' | 'update' | 'delete'
 
 export type AssignmentScope = 'all' | 'user' | 'role'
 
+export type ActionStatus = 'enforce' | 'shadow'
+
 export interface TargetEntry {
   schemas: string[]
   tables: string[]
@@ -22,6 +43,7 @@ export interface PolicyAssignmentResponse {
   role_name: string | null
   assignment_scope: AssignmentScope
   priority: number
+  action_status: ActionStatus
   created_at: string
 }
 
@@ -31,11 +53,14 @@ export interface PolicyResponse {
   description: string | null
   policy_type: string
   targets: TargetEntry[]
-  definition: Record<string, string> | null
+  definition: Record<string, unknown> | null
   is_enabled: boolean
+  action_status: ActionStatus
   version: number
   decision_function_id?: string | null
   decision_function?: DecisionFunctionSummary | null
+  template_id?: string | null
+  template?: PolicyTemplateSummary | null
   assignment_count: number
   created_by: string
   updated_by: string
@@ -49,9 +74,11 @@ export interface CreatePolicyPayload {
   description?: string
   policy_type: PolicyType
   is_enabled: boolean
+  action_status?: ActionStatus
   targets: TargetEntry[]
-  definition?: Record<string, string> | null
+  definition?: Record<string, unknown> | null
   decision_function_id?: string | null
+  template_id?: string | null
 }
 
 export interface UpdatePolicyPayload {
@@ -59,9 +86,11 @@ export interface UpdatePolicyPayload {
   description?: string
   policy_type?: PolicyType
   is_enabled?: boolean
+  action_status?: ActionStatus
   targets?: TargetEntry[]
-  definition?: Record<string, string> | null
+  definition?: Record<string, unknown> | null
   decision_function_id?: string | null
+  template_id?: string | null
   version: number
 }
 
@@ -71,6 +100,7 @@ export interface AssignPolicyPayload {
   role_id?: string | null
   scope?: AssignmentScope
   priority: number
+  action_status?: ActionStatus
 }
 
 // ---------- Anchor coverage (edit-time silent-deny warning) ----------
@@ -0,0 +1,48 @@
+export type PolicyTemplateType = 'row_filter' | 'column_mask'
+
+export type TemplateParamType = 'string' | 'integer' | 'float' | 'boolean' | 'column'
+
+export interface TemplateParam {
+  name: string
+  type: TemplateParamType
+  default?: unknown
+  description?: string
+}
+
+export interface PolicyTemplateResponse {
+  id: string
+  name: string
+  description: string | null
+  template_type: PolicyTemplateType
+  expression: string
+  parameters: TemplateParam[]
+  version: number
+  policy_count: number
+  created_by: string
+  updated_by: string
+  created_at: string
+  updated_at: string
+}
+
+export interface PolicyTemplateSummary {
+  id: string
+  name: string
+  template_type: PolicyTemplateType
+  version: number
+}
+
+export interface CreatePolicyTemplatePayload {
+  name: string
+  description?: string
+  template_type: PolicyTemplateType
+  expression: string
+  parameters?: TemplateParam[]
+}
+
+export interface UpdatePolicyTemplatePayload {
+  name?: string
+  description?: string
+  expression?: string
+  parameters?: TemplateParam[]
+  version: number
+}
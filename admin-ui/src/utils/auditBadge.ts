@@ -10,6 +10,9 @@ export function actionBadgeClass(action: string): string {
       return 'bg-amber-100 text-amber-700'
     case 'reactivate':
       return 'bg-teal-100 text-teal-700'
+    case 'reveal':
+    case 'impersonate':
+      return 'bg-amber-100 text-amber-700'
     case 'add_member':
     case 'add_inheritance':
     case 'assign':
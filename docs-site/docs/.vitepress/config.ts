@@ -150,6 +150,27 @@ export default defineConfig({
                       link: '/guides/policies/column-allow-deny',
                     },
                     { text: 'Table Deny', link: '/guides/policies/table-deny' },
+                    {
+                      text: 'Aggregate Only',
+                      link: '/guides/policies/aggregate-only',
+                    },
+                    {
+                      text: 'Differential Privacy',
+                      link: '/guides/policies/differential-privacy',
+                    },
+                    {
+                      text: 'Result Limits',
+                      link: '/guides/policies/result-limits',
+                    },
+                    {
+                      text: 'Query Guards',
+                      link: '/guides/policies/query-guards',
+                    },
+                    { text: 'Join Only', link: '/guides/policies/join-only' },
+                    {
+                      text: 'Governed Writes',
+                      link: '/guides/policies/governed-writes',
+                    },
                   ],
                 },
                 {
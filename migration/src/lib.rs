@@ -56,6 +56,33 @@ mod m20260421_000059_create_column_anchor;
 mod m20260421_000060_idx_column_anchor_unique;
 mod m20260421_000061_column_anchor_add_actual_column;
 mod m20260421_000062_column_anchor_nullable_relationship_id;
+mod m20261018_000063_create_api_key;
+mod m20261018_000064_idx_api_key_prefix;
+mod m20261018_000065_add_scopes_to_api_key;
+mod m20261018_000066_add_served_by_to_query_audit_log;
+mod m20261018_000067_add_action_status_to_policy;
+mod m20261018_000068_add_action_status_to_policy_assignment;
+mod m20261018_000069_add_shadow_outcomes_to_query_audit_log;
+mod m20261018_000070_add_tags_to_discovered_schema;
+mod m20261018_000071_add_tags_to_discovered_table;
+mod m20261018_000072_add_tags_to_discovered_column;
+mod m20261018_000073_create_policy_template;
+mod m20261018_000074_add_template_id_to_policy;
+mod m20261018_000075_create_masking_key;
+mod m20261018_000076_create_pseudonym_vault;
+mod m20261018_000077_create_privacy_budget;
+mod m20261018_000078_add_epsilon_spent_to_query_audit_log;
+mod m20261018_000079_create_result_quota;
+mod m20261018_000080_add_rows_returned_to_query_audit_log;
+mod m20261018_000081_add_impersonated_by_to_query_audit_log;
+mod m20261019_000082_create_data_source_purpose;
+mod m20261019_000083_add_purpose_to_policy;
+mod m20261019_000084_add_purpose_to_policy_assignment;
+mod m20261019_000085_add_purpose_to_query_audit_log;
+mod m20261019_000086_add_policy_combining_to_data_source;
+mod m20261019_000087_add_policy_conflicts_to_query_audit_log;
+mod m20261019_000088_add_write_mode_to_data_source;
+mod m20261019_000089_add_rows_affected_to_query_audit_log;
 
 pub struct Migrator;
 
@@ -119,6 +146,33 @@ impl MigratorTrait for Migrator {
             Box::new(m20260421_000060_idx_column_anchor_unique::Migration),
             Box::new(m20260421_000061_column_anchor_add_actual_column::Migration),
             Box::new(m20260421_000062_column_anchor_nullable_relationship_id::Migration),
+            Box::new(m20261018_000063_create_api_key::Migration),
+            Box::new(m20261018_000064_idx_api_key_prefix::Migration),
+            Box::new(m20261018_000065_add_scopes_to_api_key::Migration),
+            Box::new(m20261018_000066_add_served_by_to_query_audit_log::Migration),
+            Box::new(m20261018_000067_add_action_status_to_policy::Migration),
+            Box::new(m20261018_000068_add_action_status_to_policy_assignment::Migration),
+            Box::new(m20261018_000069_add_shadow_outcomes_to_query_audit_log::Migration),
+            Box::new(m20261018_000070_add_tags_to_discovered_schema::Migration),
+            Box::new(m20261018_000071_add_tags_to_discovered_table::Migration),
+            Box::new(m20261018_000072_add_tags_to_discovered_column::Migration),
+            Box::new(m20261018_000073_create_policy_template::Migration),
+            Box::new(m20261018_000074_add_template_id_to_policy::Migration),
+            Box::new(m20261018_000075_create_masking_key::Migration),
+            Box::new(m20261018_000076_create_pseudonym_vault::Migration),
+            Box::new(m20261018_000077_create_privacy_budget::Migration),
+            Box::new(m20261018_000078_add_epsilon_spent_to_query_audit_log::Migration),
+            Box::new(m20261018_000079_create_result_quota::Migration),
+            Box::new(m20261018_000080_add_rows_returned_to_query_audit_log::Migration),
+            Box::new(m20261018_000081_add_impersonated_by_to_query_audit_log::Migration),
+            Box::new(m20261019_000082_create_data_source_purpose::Migration),
+            Box::new(m20261019_000083_add_purpose_to_policy::Migration),
+            Box::new(m20261019_000084_add_purpose_to_policy_assignment::Migration),
+            Box::new(m20261019_000085_add_purpose_to_query_audit_log::Migration),
+            Box::new(m20261019_000086_add_policy_combining_to_data_source::Migration),
+            Box::new(m20261019_000087_add_policy_conflicts_to_query_audit_log::Migration),
+            Box::new(m20261019_000088_add_write_mode_to_data_source::Migration),
+            Box::new(m20261019_000089_add_rows_affected_to_query_audit_log::Migration),
         ]
     }
 }
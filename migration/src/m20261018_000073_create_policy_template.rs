@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PolicyTemplate::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PolicyTemplate::Id)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PolicyTemplate::Name)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(PolicyTemplate::Description).text().null())
+                    .col(
+                        ColumnDef::new(PolicyTemplate::TemplateType)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PolicyTemplate::Expression).text().not_null())
+                    .col(
+                        ColumnDef::new(PolicyTemplate::Parameters)
+                            .text()
+                            .not_null()
+                            .default("[]"),
+                    )
+                    .col(
+                        ColumnDef::new(PolicyTemplate::Version)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .col(ColumnDef::new(PolicyTemplate::CreatedBy).text().not_null())
+                    .col(ColumnDef::new(PolicyTemplate::UpdatedBy).text().not_null())
+                    .col(ColumnDef::new(PolicyTemplate::CreatedAt).text().not_null())
+                    .col(ColumnDef::new(PolicyTemplate::UpdatedAt).text().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PolicyTemplate::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum PolicyTemplate {
+    Table,
+    Id,
+    Name,
+    Description,
+    TemplateType,
+    Expression,
+    Parameters,
+    Version,
+    CreatedBy,
+    UpdatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
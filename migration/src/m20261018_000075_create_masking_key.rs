@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MaskingKey::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MaskingKey::Id)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MaskingKey::Name)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(MaskingKey::Description).text().null())
+                    .col(
+                        ColumnDef::new(MaskingKey::SecretEncrypted)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MaskingKey::Reversible)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(MaskingKey::Version)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .col(ColumnDef::new(MaskingKey::CreatedBy).text().not_null())
+                    .col(ColumnDef::new(MaskingKey::UpdatedBy).text().not_null())
+                    .col(ColumnDef::new(MaskingKey::CreatedAt).text().not_null())
+                    .col(ColumnDef::new(MaskingKey::UpdatedAt).text().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MaskingKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum MaskingKey {
+    Table,
+    Id,
+    Name,
+    Description,
+    SecretEncrypted,
+    Reversible,
+    Version,
+    CreatedBy,
+    UpdatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
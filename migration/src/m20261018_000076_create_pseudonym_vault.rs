@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PseudonymVault::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PseudonymVault::Id)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PseudonymVault::MaskingKeyId)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PseudonymVault::KeyVersion)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PseudonymVault::Token).text().not_null())
+                    .col(
+                        ColumnDef::new(PseudonymVault::ValueEncrypted)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PseudonymVault::CreatedAt).text().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_pseudonym_vault_masking_key")
+                            .from(PseudonymVault::Table, PseudonymVault::MaskingKeyId)
+                            .to(MaskingKey::Table, MaskingKey::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_pseudonym_vault_key_token")
+                    .table(PseudonymVault::Table)
+                    .col(PseudonymVault::MaskingKeyId)
+                    .col(PseudonymVault::Token)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PseudonymVault::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum PseudonymVault {
+    Table,
+    Id,
+    MaskingKeyId,
+    KeyVersion,
+    Token,
+    ValueEncrypted,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum MaskingKey {
+    Table,
+    Id,
+}
@@ -0,0 +1,100 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PrivacyBudget::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PrivacyBudget::Id)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PrivacyBudget::UserId).text().not_null())
+                    .col(
+                        ColumnDef::new(PrivacyBudget::DataSourceId)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PrivacyBudget::EpsilonSpent)
+                            .double()
+                            .not_null()
+                            .default(0.0),
+                    )
+                    .col(
+                        ColumnDef::new(PrivacyBudget::QueryCount)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(PrivacyBudget::CreatedAt).text().not_null())
+                    .col(ColumnDef::new(PrivacyBudget::UpdatedAt).text().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_privacy_budget_user")
+                            .from(PrivacyBudget::Table, PrivacyBudget::UserId)
+                            .to(ProxyUser::Table, ProxyUser::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_privacy_budget_data_source")
+                            .from(PrivacyBudget::Table, PrivacyBudget::DataSourceId)
+                            .to(DataSource::Table, DataSource::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_privacy_budget_user_ds")
+                    .table(PrivacyBudget::Table)
+                    .col(PrivacyBudget::UserId)
+                    .col(PrivacyBudget::DataSourceId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PrivacyBudget::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum PrivacyBudget {
+    Table,
+    Id,
+    UserId,
+    DataSourceId,
+    EpsilonSpent,
+    QueryCount,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+enum ProxyUser {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum DataSource {
+    Table,
+    Id,
+}
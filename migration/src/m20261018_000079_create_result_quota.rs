@@ -0,0 +1,89 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ResultQuota::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ResultQuota::Id)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ResultQuota::PolicyId).text().not_null())
+                    .col(ColumnDef::new(ResultQuota::UserId).text().not_null())
+                    .col(ColumnDef::new(ResultQuota::WindowStart).text().not_null())
+                    .col(
+                        ColumnDef::new(ResultQuota::RowsUsed)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(ResultQuota::UpdatedAt).text().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_result_quota_policy")
+                            .from(ResultQuota::Table, ResultQuota::PolicyId)
+                            .to(Policy::Table, Policy::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_result_quota_user")
+                            .from(ResultQuota::Table, ResultQuota::UserId)
+                            .to(ProxyUser::Table, ProxyUser::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_result_quota_policy_user")
+                    .table(ResultQuota::Table)
+                    .col(ResultQuota::PolicyId)
+                    .col(ResultQuota::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ResultQuota::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ResultQuota {
+    Table,
+    Id,
+    PolicyId,
+    UserId,
+    WindowStart,
+    RowsUsed,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+enum Policy {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum ProxyUser {
+    Table,
+    Id,
+}
@@ -0,0 +1,62 @@
+// WARNING: ALTER TABLE ADD COLUMN is not idempotent. Do not interrupt this migration.
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One column per ALTER: SQLite cannot add several in one statement.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QueryAuditLog::Table)
+                    .add_column(
+                        ColumnDef::new(QueryAuditLog::RowsReturned)
+                            .big_integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QueryAuditLog::Table)
+                    .add_column(
+                        ColumnDef::new(QueryAuditLog::BytesReturned)
+                            .big_integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QueryAuditLog::Table)
+                    .drop_column(QueryAuditLog::BytesReturned)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QueryAuditLog::Table)
+                    .drop_column(QueryAuditLog::RowsReturned)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum QueryAuditLog {
+    Table,
+    RowsReturned,
+    BytesReturned,
+}
@@ -0,0 +1,96 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DataSourcePurpose::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DataSourcePurpose::Id)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DataSourcePurpose::DataSourceId)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(DataSourcePurpose::Name).text().not_null())
+                    .col(ColumnDef::new(DataSourcePurpose::Description).text().null())
+                    .col(
+                        ColumnDef::new(DataSourcePurpose::RequireJustification)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(DataSourcePurpose::JustificationValidator)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(DataSourcePurpose::CreatedAt)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DataSourcePurpose::UpdatedAt)
+                            .text()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_data_source_purpose_data_source")
+                            .from(DataSourcePurpose::Table, DataSourcePurpose::DataSourceId)
+                            .to(DataSource::Table, DataSource::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_data_source_purpose_ds_name")
+                    .table(DataSourcePurpose::Table)
+                    .col(DataSourcePurpose::DataSourceId)
+                    .col(DataSourcePurpose::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DataSourcePurpose::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum DataSourcePurpose {
+    Table,
+    Id,
+    DataSourceId,
+    Name,
+    Description,
+    RequireJustification,
+    JustificationValidator,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+enum DataSource {
+    Table,
+    Id,
+}
@@ -41,6 +41,8 @@ pub enum AuditAction {
     RemoveInheritance,
     Assign,
     Unassign,
+    Reveal,
+    Impersonate,
 }
 
 impl AuditAction {
@@ -57,6 +59,8 @@ impl AuditAction {
             Self::RemoveInheritance => "remove_inheritance",
             Self::Assign => "assign",
             Self::Unassign => "unassign",
+            Self::Reveal => "reveal",
+            Self::Impersonate => "impersonate",
         }
     }
 }
@@ -0,0 +1,368 @@
+//! Admin API handlers for per-user API keys used by the HTTP query API.
+//!
+//! Keys look like `br_<12 hex prefix><48 hex secret>`. Only the prefix (the lookup
+//! handle) and a SHA-256 hash of the full key are stored; the raw key is returned
+//! exactly once, from `create_api_key`. Keys authenticate as the owning proxy user —
+//! never as an admin — and are accepted only by the `QueryUser` extractor.
+//!
+//! A key may additionally carry admin scopes ([`ADMIN_SCOPES`]). These never grant
+//! access to the admin REST API itself; they only unlock the matching admin tools
+//! of the MCP server, and only while the owning user is still an admin.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Utc;
+use rand_core::{OsRng, RngCore};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    Set,
+};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::admin::admin_audit::{AuditAction, AuditedTxn};
+use crate::admin::dto::{ApiKeyResponse, CreateApiKeyRequest, CreatedApiKeyResponse};
+use crate::admin::jwt::AdminClaims;
+use crate::admin::{AdminState, ApiErr};
+use crate::entity::{api_key, proxy_user};
+
+const KEY_MARKER: &str = "br_";
+/// Hex characters of the key that form the stored, indexed lookup prefix.
+const PREFIX_HEX_LEN: usize = 12;
+const SECRET_HEX_LEN: usize = 48;
+
+pub const SCOPE_ADMIN_POLICIES: &str = "admin:policies";
+pub const SCOPE_ADMIN_DISCOVERY: &str = "admin:discovery";
+pub const SCOPE_ADMIN_AUDIT: &str = "admin:audit";
+
+/// Every scope an API key may be granted.
+pub const ADMIN_SCOPES: &[&str] = &[
+    SCOPE_ADMIN_POLICIES,
+    SCOPE_ADMIN_DISCOVERY,
+    SCOPE_ADMIN_AUDIT,
+];
+
+// ---------- key material ----------
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len / 2];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// SHA-256 of the full raw key, hex-encoded.
+fn hash_api_key(raw: &str) -> String {
+    Sha256::digest(raw.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Generate a new key, returning `(raw_key, key_prefix, key_hash)`.
+fn generate_api_key() -> (String, String, String) {
+    let prefix = format!("{KEY_MARKER}{}", random_hex(PREFIX_HEX_LEN));
+    let raw = format!("{prefix}{}", random_hex(SECRET_HEX_LEN));
+    let hash = hash_api_key(&raw);
+    (raw, prefix, hash)
+}
+
+/// Compare two equal-length strings without short-circuiting on the first difference.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// `true` if a bearer value has the shape of an API key (as opposed to a JWT).
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(KEY_MARKER)
+}
+
+/// Resolve a raw API key to its stored row.
+///
+/// Returns `Ok(None)` for unknown, malformed, mismatched or expired keys. On success
+/// `last_used_at` is refreshed in the background.
+pub async fn verify_api_key(
+    db: &DatabaseConnection,
+    raw: &str,
+) -> Result<Option<api_key::Model>, DbErr> {
+    let prefix_len = KEY_MARKER.len() + PREFIX_HEX_LEN;
+    if raw.len() != prefix_len + SECRET_HEX_LEN || !raw.is_char_boundary(prefix_len) {
+        return Ok(None);
+    }
+    let Some(key) = api_key::Entity::find()
+        .filter(api_key::Column::KeyPrefix.eq(&raw[..prefix_len]))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    if !constant_time_eq(&key.key_hash, &hash_api_key(raw)) {
+        return Ok(None);
+    }
+    let now = Utc::now().naive_utc();
+    if key.expires_at.is_some_and(|exp| exp <= now) {
+        return Ok(None);
+    }
+
+    let db = db.clone();
+    let mut active: api_key::ActiveModel = key.clone().into();
+    tokio::spawn(async move {
+        active.last_used_at = Set(Some(now));
+        if let Err(e) = active.update(&db).await {
+            tracing::warn!(error = %e, "Failed to update api_key.last_used_at");
+        }
+    });
+    Ok(Some(key))
+}
+
+// ---------- handlers ----------
+
+async fn require_user(state: &AdminState, user_id: Uuid) -> Result<proxy_user::Model, ApiErr> {
+    proxy_user::Entity::find_by_id(user_id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("User not found"))
+}
+
+pub async fn list_api_keys(
+    AdminClaims(_claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<ApiKeyResponse>>, ApiErr> {
+    require_user(&state, user_id).await?;
+    let keys = api_key::Entity::find()
+        .filter(api_key::Column::UserId.eq(user_id))
+        .order_by_asc(api_key::Column::CreatedAt)
+        .all(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    Ok(Json(keys.into_iter().map(ApiKeyResponse::from).collect()))
+}
+
+pub async fn create_api_key(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(user_id): Path<Uuid>,
+    Json(body): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedApiKeyResponse>), ApiErr> {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "API key name must not be empty",
+        ));
+    }
+    let user = require_user(&state, user_id).await?;
+
+    let mut scopes = body.scopes;
+    scopes.sort();
+    scopes.dedup();
+    if let Some(bad) = scopes.iter().find(|s| !ADMIN_SCOPES.contains(&s.as_str())) {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!(
+                "Unknown scope '{bad}' (allowed: {})",
+                ADMIN_SCOPES.join(", ")
+            ),
+        ));
+    }
+    if !scopes.is_empty() && !user.is_admin {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Admin scopes can only be granted to keys of admin users",
+        ));
+    }
+    let scopes_json = serde_json::to_string(&scopes).map_err(ApiErr::internal)?;
+
+    let (raw, key_prefix, key_hash) = generate_api_key();
+    let id = Uuid::now_v7();
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let model = api_key::ActiveModel {
+        id: Set(id),
+        user_id: Set(user.id),
+        name: Set(name.to_string()),
+        key_prefix: Set(key_prefix.clone()),
+        key_hash: Set(key_hash),
+        created_at: Set(Utc::now().naive_utc()),
+        last_used_at: Set(None),
+        expires_at: Set(body.expires_at),
+        scopes: Set(scopes_json),
+    }
+    .insert(&*txn)
+    .await
+    .map_err(ApiErr::internal)?;
+
+    txn.audit(
+        "api_key",
+        id,
+        AuditAction::Create,
+        claims.sub,
+        serde_json::json!({
+            "after": {
+                "user_id": user.id,
+                "username": user.username,
+                "name": name,
+                "key_prefix": key_prefix,
+                "expires_at": body.expires_at,
+                "scopes": scopes,
+            }
+        }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKeyResponse {
+            key: ApiKeyResponse::from(model),
+            api_key: raw,
+        }),
+    ))
+}
+
+pub async fn delete_api_key(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path((user_id, key_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiErr> {
+    let key = api_key::Entity::find_by_id(key_id)
+        .filter(api_key::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("API key not found"))?;
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    txn.audit(
+        "api_key",
+        key_id,
+        AuditAction::Delete,
+        claims.sub,
+        serde_json::json!({
+            "before": {
+                "user_id": key.user_id,
+                "name": key.name,
+                "key_prefix": key.key_prefix,
+            }
+        }),
+    );
+
+    let active: api_key::ActiveModel = key.into();
+    active.delete(&*txn).await.map_err(ApiErr::internal)?;
+
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use migration::MigratorTrait as _;
+    use sea_orm::Database;
+
+    async fn setup_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        migration::Migrator::up(&db, None).await.unwrap();
+        db
+    }
+
+    async fn insert_key(
+        db: &DatabaseConnection,
+        expires_at: Option<chrono::NaiveDateTime>,
+    ) -> (Uuid, String) {
+        let now = Utc::now().naive_utc();
+        let user_id = Uuid::now_v7();
+        proxy_user::ActiveModel {
+            id: Set(user_id),
+            username: Set(format!("u{}", user_id.simple())),
+            password_hash: Set("hash".to_string()),
+            is_admin: Set(false),
+            is_active: Set(true),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+
+        let (raw, key_prefix, key_hash) = generate_api_key();
+        api_key::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            user_id: Set(user_id),
+            name: Set("ci".to_string()),
+            key_prefix: Set(key_prefix),
+            key_hash: Set(key_hash),
+            created_at: Set(now),
+            last_used_at: Set(None),
+            expires_at: Set(expires_at),
+            scopes: Set("[]".to_string()),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+        (user_id, raw)
+    }
+
+    #[test]
+    fn generated_key_has_expected_shape() {
+        let (raw, prefix, hash) = generate_api_key();
+        assert!(is_api_key(&raw));
+        assert!(raw.starts_with(&prefix));
+        assert_eq!(
+            raw.len(),
+            KEY_MARKER.len() + PREFIX_HEX_LEN + SECRET_HEX_LEN
+        );
+        assert_eq!(hash, hash_api_key(&raw));
+        assert_eq!(hash.len(), 64);
+        assert!(!is_api_key("eyJhbGciOiJIUzI1NiJ9.x.y"));
+    }
+
+    #[test]
+    fn constant_time_eq_compares_content_and_length() {
+        assert!(constant_time_eq("abc", "abc"));
+        assert!(!constant_time_eq("abc", "abd"));
+        assert!(!constant_time_eq("abc", "abcd"));
+    }
+
+    #[tokio::test]
+    async fn verify_accepts_valid_key() {
+        let db = setup_db().await;
+        let (user_id, raw) = insert_key(&db, None).await;
+        let key = verify_api_key(&db, &raw).await.unwrap().unwrap();
+        assert_eq!(key.user_id, user_id);
+        assert!(key.scope_list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn verify_rejects_tampered_secret() {
+        let db = setup_db().await;
+        let (_, raw) = insert_key(&db, None).await;
+        let last = if raw.ends_with('0') { "1" } else { "0" };
+        let tampered = format!("{}{last}", &raw[..raw.len() - 1]);
+        assert!(verify_api_key(&db, &tampered).await.unwrap().is_none());
+        assert!(verify_api_key(&db, "br_short").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn verify_rejects_expired_key() {
+        let db = setup_db().await;
+        let past = Utc::now().naive_utc() - chrono::Duration::minutes(1);
+        let (_, raw) = insert_key(&db, Some(past)).await;
+        assert!(verify_api_key(&db, &raw).await.unwrap().is_none());
+    }
+}
@@ -3,6 +3,8 @@ use axum::{
     http::StatusCode,
     response::Json,
 };
+use std::collections::{HashMap, HashSet};
+
 use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -11,7 +13,10 @@ use crate::entity::{admin_audit_log, query_audit_log};
 
 use super::{
     AdminState, ApiErr,
-    dto::{AuditLogResponse, ListAuditLogQuery, PaginatedResponse},
+    dto::{
+        AuditLogResponse, ListAuditLogQuery, PaginatedResponse, ShadowReportEntry,
+        ShadowReportQuery,
+    },
     jwt::AdminClaims,
 };
 
@@ -83,6 +88,26 @@ pub async fn list_audit_logs(
                 created_at: m.created_at,
                 status: m.status,
                 error_message: m.error_message,
+                served_by: m.served_by,
+                shadow_outcomes: m
+                    .shadow_outcomes
+                    .as_deref()
+                    .and_then(|s| serde_json::from_str(s).ok()),
+                epsilon_spent: m.epsilon_spent,
+                rows_returned: m.rows_returned,
+                bytes_returned: m.bytes_returned,
+                impersonated_by: m.impersonated_by,
+                purpose: m.purpose,
+                justification: m.justification,
+                policy_conflicts: m
+                    .policy_conflicts
+                    .as_deref()
+                    .and_then(|s| serde_json::from_str(s).ok()),
+                rows_affected: m.rows_affected,
+                affected_rows: m
+                    .affected_rows
+                    .as_deref()
+                    .and_then(|s| serde_json::from_str(s).ok()),
             })
         })
         .collect();
@@ -95,6 +120,110 @@ pub async fn list_audit_logs(
     }))
 }
 
+// ---------- GET /audit/shadow ----------
+
+/// Shadow hits per policy: how often each `shadow` policy matched a query in
+/// the time range, and what it would have done. Aggregated from the
+/// `shadow_outcomes` recorded on query audit entries.
+pub async fn shadow_report(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Query(params): Query<ShadowReportQuery>,
+) -> Result<Json<Vec<ShadowReportEntry>>, ApiErr> {
+    let mut query = query_audit_log::Entity::find()
+        .filter(query_audit_log::Column::ShadowOutcomes.is_not_null());
+
+    if let Some(ds_id) = params.datasource_id {
+        query = query.filter(query_audit_log::Column::DataSourceId.eq(ds_id));
+    }
+    if let Some(ref from) = params.from {
+        let dt =
+            chrono::NaiveDateTime::parse_from_str(from, "%Y-%m-%dT%H:%M:%S").map_err(|_| {
+                ApiErr::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid 'from' datetime: {from}"),
+                )
+            })?;
+        query = query.filter(query_audit_log::Column::CreatedAt.gte(dt));
+    }
+    if let Some(ref to) = params.to {
+        let dt = chrono::NaiveDateTime::parse_from_str(to, "%Y-%m-%dT%H:%M:%S").map_err(|_| {
+            ApiErr::new(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid 'to' datetime: {to}"),
+            )
+        })?;
+        query = query.filter(query_audit_log::Column::CreatedAt.lte(dt));
+    }
+
+    let rows = query.all(&state.db).await.map_err(ApiErr::internal)?;
+    Ok(Json(aggregate_shadow_hits(&rows)))
+}
+
+fn aggregate_shadow_hits(rows: &[query_audit_log::Model]) -> Vec<ShadowReportEntry> {
+    let mut by_policy: HashMap<Uuid, (ShadowReportEntry, HashSet<Uuid>)> = HashMap::new();
+    for row in rows {
+        let Some(outcomes) = row
+            .shadow_outcomes
+            .as_deref()
+            .and_then(|s| serde_json::from_str::<Vec<serde_json::Value>>(s).ok())
+        else {
+            continue;
+        };
+        for outcome in outcomes {
+            let Some(policy_id) = outcome["policy_id"]
+                .as_str()
+                .and_then(|s| Uuid::parse_str(s).ok())
+            else {
+                continue;
+            };
+            let (entry, users) = by_policy.entry(policy_id).or_insert_with(|| {
+                (
+                    ShadowReportEntry {
+                        policy_id,
+                        policy_name: outcome["name"].as_str().unwrap_or_default().to_string(),
+                        policy_type: outcome["policy_type"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        hits: 0,
+                        would_deny: 0,
+                        would_filter: 0,
+                        would_mask: 0,
+                        users: 0,
+                        first_hit_at: row.created_at,
+                        last_hit_at: row.created_at,
+                    },
+                    HashSet::new(),
+                )
+            });
+            entry.hits += 1;
+            match outcome["outcome"].as_str() {
+                Some("would_deny" | "would_deny_columns") => entry.would_deny += 1,
+                Some("would_filter") => entry.would_filter += 1,
+                Some("would_mask") => entry.would_mask += 1,
+                _ => {}
+            }
+            entry.first_hit_at = entry.first_hit_at.min(row.created_at);
+            entry.last_hit_at = entry.last_hit_at.max(row.created_at);
+            users.insert(row.user_id);
+        }
+    }
+    let mut report: Vec<ShadowReportEntry> = by_policy
+        .into_values()
+        .map(|(mut entry, users)| {
+            entry.users = users.len() as u64;
+            entry
+        })
+        .collect();
+    report.sort_by(|a, b| {
+        b.hits
+            .cmp(&a.hits)
+            .then_with(|| a.policy_name.cmp(&b.policy_name))
+    });
+    report
+}
+
 // ---------- GET /audit/admin ----------
 
 #[derive(Debug, Deserialize)]
@@ -194,3 +323,63 @@ pub async fn list_admin_audit_logs(
         page_size,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(user_id: Uuid, at: &str, outcomes: serde_json::Value) -> query_audit_log::Model {
+        query_audit_log::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            username: "u".into(),
+            data_source_id: Uuid::nil(),
+            datasource_name: "ds".into(),
+            original_query: "SELECT 1".into(),
+            rewritten_query: None,
+            policies_applied: "[]".into(),
+            execution_time_ms: None,
+            client_ip: None,
+            client_info: None,
+            created_at: chrono::NaiveDateTime::parse_from_str(at, "%Y-%m-%dT%H:%M:%S").unwrap(),
+            status: "success".into(),
+            error_message: None,
+            served_by: None,
+            shadow_outcomes: Some(outcomes.to_string()),
+            epsilon_spent: None,
+            rows_returned: None,
+            bytes_returned: None,
+            impersonated_by: None,
+            purpose: None,
+            justification: None,
+            policy_conflicts: None,
+            rows_affected: None,
+            affected_rows: None,
+        }
+    }
+
+    #[test]
+    fn shadow_hits_are_counted_per_policy() {
+        let (p1, p2) = (Uuid::now_v7(), Uuid::now_v7());
+        let (alice, bob) = (Uuid::now_v7(), Uuid::now_v7());
+        let filter = json!({"policy_id": p1, "name": "tenant", "policy_type": "row_filter", "outcome": "would_filter"});
+        let deny = json!({"policy_id": p2, "name": "no-ssn", "policy_type": "column_deny", "outcome": "would_deny_columns"});
+        let rows = vec![
+            row(alice, "2026-10-01T10:00:00", json!([filter, deny])),
+            row(bob, "2026-10-02T10:00:00", json!([filter])),
+            row(alice, "2026-10-03T10:00:00", json!([filter])),
+        ];
+        let report = aggregate_shadow_hits(&rows);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].policy_id, p1);
+        assert_eq!(report[0].hits, 3);
+        assert_eq!(report[0].would_filter, 3);
+        assert_eq!(report[0].users, 2);
+        assert_eq!(report[0].first_hit_at.to_string(), "2026-10-01 10:00:00");
+        assert_eq!(report[0].last_hit_at.to_string(), "2026-10-03 10:00:00");
+        assert_eq!(report[1].policy_id, p2);
+        assert_eq!(report[1].would_deny, 1);
+        assert_eq!(report[1].users, 1);
+    }
+}
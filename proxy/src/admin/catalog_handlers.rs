@@ -14,6 +14,7 @@ use tokio_stream::StreamExt;
 use tokio_stream::wrappers::BroadcastStream;
 use uuid::Uuid;
 
+use crate::admin::admin_audit::{AuditAction, AuditedTxn};
 use crate::admin::discovery_job::{
     DiscoveryEvent, DiscoveryJob, DiscoveryRequest, SaveSchemaSelection,
 };
@@ -23,6 +24,7 @@ use crate::admin::{AdminState, ApiErr};
 use crate::discovery;
 use crate::engine::DataSourceConfig;
 use crate::entity::{data_source, discovered_column, discovered_schema, discovered_table};
+use crate::policy_match::{CatalogTags, parse_tags, validate_tag};
 
 /// Return the effective (user-visible) name for a schema selection.
 ///
@@ -389,6 +391,7 @@ async fn run_inner(
                         schema_alias: Set(alias),
                         is_selected: Set(schema_sel.is_selected),
                         discovered_at: Set(now),
+                        tags: Set(None),
                     }
                     .insert(&txn)
                     .await?;
@@ -415,6 +418,7 @@ async fn run_inner(
                             table_type: Set(table_sel.table_type.clone()),
                             is_selected: Set(table_sel.is_selected),
                             discovered_at: Set(now),
+                            tags: Set(None),
                         }
                         .insert(&txn)
                         .await?;
@@ -558,6 +562,7 @@ async fn run_inner(
                                         arrow_type: Set(col.arrow_type),
                                         is_selected: Set(is_selected),
                                         discovered_at: Set(now2),
+                                        tags: Set(None),
                                     }
                                     .insert(&state.db)
                                     .await?;
@@ -748,6 +753,7 @@ async fn run_inner(
                                     arrow_type: Set(live_col.arrow_type.clone()),
                                     is_selected: Set(true),
                                     discovered_at: Set(now),
+                                    tags: Set(None),
                                 };
                                 new_col.insert(&state.db).await?;
                                 changed_columns.push(format!(
@@ -934,9 +940,13 @@ pub async fn get_catalog(
             .map_err(ApiErr::internal)?;
 
     let mut schema_responses = Vec::new();
+    // Filled top-down so each column's effective tags see its table and schema.
+    let mut catalog_tags = CatalogTags::default();
 
     for (schema, tables) in schemas_with_tables {
         let mut table_responses = Vec::new();
+        let schema_tags = parse_tags(schema.tags.as_deref());
+        catalog_tags.set_schema(&schema.schema_name, schema_tags.clone());
 
         for table in tables {
             let columns: Vec<discovered_column::Model> = discovered_column::Entity::find()
@@ -945,17 +955,36 @@ pub async fn get_catalog(
                 .await
                 .map_err(ApiErr::internal)?;
 
+            let table_tags = parse_tags(table.tags.as_deref());
+            catalog_tags.set_table(&schema.schema_name, &table.table_name, table_tags.clone());
+
             let column_responses: Vec<CatalogColumnResponse> = columns
                 .into_iter()
-                .map(|c| CatalogColumnResponse {
-                    id: c.id,
-                    column_name: c.column_name,
-                    ordinal_position: c.ordinal_position,
-                    data_type: c.data_type,
-                    is_nullable: c.is_nullable,
-                    column_default: c.column_default,
-                    arrow_type: c.arrow_type,
-                    is_selected: c.is_selected,
+                .map(|c| {
+                    let tags = parse_tags(c.tags.as_deref());
+                    catalog_tags.set_column(
+                        &schema.schema_name,
+                        &table.table_name,
+                        &c.column_name,
+                        tags.clone(),
+                    );
+                    let effective_tags = catalog_tags.column_tags(
+                        &schema.schema_name,
+                        &table.table_name,
+                        &c.column_name,
+                    );
+                    CatalogColumnResponse {
+                        id: c.id,
+                        column_name: c.column_name,
+                        ordinal_position: c.ordinal_position,
+                        data_type: c.data_type,
+                        is_nullable: c.is_nullable,
+                        column_default: c.column_default,
+                        arrow_type: c.arrow_type,
+                        is_selected: c.is_selected,
+                        tags,
+                        effective_tags,
+                    }
                 })
                 .collect();
 
@@ -964,6 +993,7 @@ pub async fn get_catalog(
                 table_name: table.table_name,
                 table_type: table.table_type,
                 is_selected: table.is_selected,
+                tags: table_tags,
                 columns: column_responses,
             });
         }
@@ -973,6 +1003,7 @@ pub async fn get_catalog(
             schema_name: schema.schema_name,
             schema_alias: schema.schema_alias,
             is_selected: schema.is_selected,
+            tags: schema_tags,
             tables: table_responses,
         });
     }
@@ -982,6 +1013,158 @@ pub async fn get_catalog(
     }))
 }
 
+// ---------- PUT /datasources/{id}/catalog/tags — tag a schema, table or column ----------
+
+pub async fn set_catalog_tags(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<SetCatalogTagsRequest>,
+) -> Result<Json<CatalogTagsResponse>, ApiErr> {
+    let ds = data_source::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Data source not found"))?;
+
+    if body.column.is_some() && body.table.is_none() {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "'column' requires 'table'",
+        ));
+    }
+    let tags =
+        normalize_tags(&body.tags).map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let stored = (!tags.is_empty())
+        .then(|| serde_json::to_string(&tags))
+        .transpose()
+        .map_err(ApiErr::internal)?;
+
+    let schema = discovered_schema::Entity::find()
+        .filter(discovered_schema::Column::DataSourceId.eq(id))
+        .filter(discovered_schema::Column::SchemaName.eq(&body.schema))
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found(format!("Schema '{}' not found", body.schema)))?;
+    let table = match &body.table {
+        Some(name) => Some(
+            discovered_table::Entity::find()
+                .filter(discovered_table::Column::DiscoveredSchemaId.eq(schema.id))
+                .filter(discovered_table::Column::TableName.eq(name))
+                .one(&state.db)
+                .await
+                .map_err(ApiErr::internal)?
+                .ok_or_else(|| {
+                    ApiErr::not_found(format!("Table '{}.{name}' not found", body.schema))
+                })?,
+        ),
+        None => None,
+    };
+
+    let column = match (&table, &body.column) {
+        (Some(table), Some(name)) => Some(
+            discovered_column::Entity::find()
+                .filter(discovered_column::Column::DiscoveredTableId.eq(table.id))
+                .filter(discovered_column::Column::ColumnName.eq(name))
+                .one(&state.db)
+                .await
+                .map_err(ApiErr::internal)?
+                .ok_or_else(|| {
+                    ApiErr::not_found(format!(
+                        "Column '{}.{}.{name}' not found",
+                        body.schema, table.table_name
+                    ))
+                })?,
+        ),
+        _ => None,
+    };
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    let (resource_type, resource_id, before) = match (column, table) {
+        (Some(column), _) => {
+            let (column_id, before) = (column.id, column.tags.clone());
+            let mut active: discovered_column::ActiveModel = column.into();
+            active.tags = Set(stored);
+            active.update(&*txn).await.map_err(ApiErr::internal)?;
+            ("discovered_column", column_id, before)
+        }
+        (None, Some(table)) => {
+            let (table_id, before) = (table.id, table.tags.clone());
+            let mut active: discovered_table::ActiveModel = table.into();
+            active.tags = Set(stored);
+            active.update(&*txn).await.map_err(ApiErr::internal)?;
+            ("discovered_table", table_id, before)
+        }
+        (None, None) => {
+            let (schema_id, before) = (schema.id, schema.tags.clone());
+            let mut active: discovered_schema::ActiveModel = schema.into();
+            active.tags = Set(stored);
+            active.update(&*txn).await.map_err(ApiErr::internal)?;
+            ("discovered_schema", schema_id, before)
+        }
+    };
+    txn.audit(
+        resource_type,
+        resource_id,
+        AuditAction::Update,
+        claims.sub,
+        serde_json::json!({
+            "data_source_id": id,
+            "before": { "tags": parse_tags(before.as_deref()) },
+            "after": { "tags": &tags },
+        }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    // Tags change which objects `tag:` targets match: drop the cached catalog,
+    // policy sessions and per-connection contexts for this datasource.
+    state.engine_cache.invalidate(&ds.name).await;
+    if let Some(hook) = &state.policy_hook {
+        hook.invalidate_datasource(&ds.name).await;
+    }
+    if let Some(ph) = &state.proxy_handler {
+        ph.rebuild_contexts_for_datasource(&ds.name);
+    }
+
+    let schemas = discovered_schema::Entity::find()
+        .filter(discovered_schema::Column::DataSourceId.eq(id))
+        .all(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    let catalog_tags = crate::hooks::policy::load_catalog_tags(&state.db, &schemas)
+        .await
+        .map_err(ApiErr::internal)?;
+    let effective_tags = match (&body.table, &body.column) {
+        (Some(t), Some(c)) => catalog_tags.column_tags(&body.schema, t, c),
+        (Some(t), None) => catalog_tags.table_tags(&body.schema, t),
+        _ => catalog_tags.schema_tags(&body.schema),
+    };
+
+    Ok(Json(CatalogTagsResponse {
+        schema: body.schema,
+        table: body.table,
+        column: body.column,
+        tags,
+        effective_tags,
+    }))
+}
+
+/// Validate, trim and de-duplicate tags, keeping their order.
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut out: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim();
+        validate_tag(tag)?;
+        if !out.iter().any(|t| t == tag) {
+            out.push(tag.to_string());
+        }
+    }
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1081,4 +1264,22 @@ mod tests {
     fn test_empty_list_passes() {
         assert!(validate_alias_uniqueness(&[]).is_ok());
     }
+
+    // --- normalize_tags ---
+
+    #[test]
+    fn test_normalize_tags_trims_and_dedups() {
+        let tags = vec![
+            " pii".to_string(),
+            "pii".to_string(),
+            "!financial".to_string(),
+        ];
+        assert_eq!(normalize_tags(&tags).unwrap(), vec!["pii", "!financial"]);
+    }
+
+    #[test]
+    fn test_normalize_tags_rejects_invalid() {
+        let err = normalize_tags(&["bad tag".to_string()]).unwrap_err();
+        assert!(err.contains("bad tag"), "Expected tag in error: {err}");
+    }
 }
@@ -12,6 +12,7 @@ use uuid::Uuid;
 use std::collections::HashSet;
 
 use crate::entity::{data_source, data_source_access, proxy_user};
+use crate::policy_combining::CombiningRules;
 
 use super::{
     AdminState, ApiErr,
@@ -20,7 +21,7 @@ use super::{
     dto::{
         CreateDataSourceRequest, DataSourceResponse, ListDataSourcesQuery, PaginatedResponse,
         SetDataSourceUsersRequest, TestConnectionResponse, UpdateDataSourceRequest, UserResponse,
-        validate_access_mode, validate_datasource_name,
+        validate_access_mode, validate_datasource_name, validate_write_mode,
     },
     jwt::AdminClaims,
     role_handlers::invalidate_user,
@@ -43,6 +44,8 @@ fn ds_response(model: data_source::Model) -> Result<DataSourceResponse, ApiErr>
         config,
         is_active: model.is_active,
         access_mode: model.access_mode,
+        policy_combining: CombiningRules::parse(model.policy_combining.as_deref()),
+        write_mode: model.write_mode,
         last_sync_at: model.last_sync_at,
         last_sync_result,
         created_at: model.created_at,
@@ -118,6 +121,20 @@ pub async fn create_datasource(
             "access_mode must be 'open' or 'policy_required'",
         ));
     }
+    if !validate_write_mode(&body.write_mode) {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "write_mode must be 'read_only' or 'read_write'",
+        ));
+    }
+    let policy_combining = match &body.policy_combining {
+        Some(value) => {
+            let rules = CombiningRules::from_json(value)
+                .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+            Some(serde_json::to_string(&rules).map_err(ApiErr::internal)?)
+        }
+        None => None,
+    };
 
     // Validate and split config using type registry
     let (config_json, secure_json) = datasource_types::split_config(&body.ds_type, body.config)
@@ -144,6 +161,8 @@ pub async fn create_datasource(
         secure_config: Set(secure_str),
         is_active: Set(true),
         access_mode: Set(body.access_mode),
+        policy_combining: Set(policy_combining),
+        write_mode: Set(body.write_mode),
         last_sync_at: Set(None),
         last_sync_result: Set(None),
         created_at: Set(now),
@@ -183,6 +202,8 @@ pub async fn create_datasource(
                 "name": &model.name,
                 "ds_type": &model.ds_type,
                 "access_mode": &model.access_mode,
+                "policy_combining": CombiningRules::parse(model.policy_combining.as_deref()),
+                "write_mode": &model.write_mode,
                 "is_active": model.is_active,
             }
         }),
@@ -251,6 +272,29 @@ pub async fn update_datasource(
         changes_after.insert("access_mode".into(), serde_json::json!(access_mode));
         active.access_mode = Set(access_mode.clone());
     }
+    if let Some(ref value) = body.policy_combining {
+        let rules = CombiningRules::from_json(value)
+            .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+        changes_before.insert(
+            "policy_combining".into(),
+            serde_json::json!(CombiningRules::parse(model.policy_combining.as_deref())),
+        );
+        changes_after.insert("policy_combining".into(), serde_json::json!(rules));
+        active.policy_combining = Set(Some(
+            serde_json::to_string(&rules).map_err(ApiErr::internal)?,
+        ));
+    }
+    if let Some(ref write_mode) = body.write_mode {
+        if !validate_write_mode(write_mode) {
+            return Err(ApiErr::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "write_mode must be 'read_only' or 'read_write'",
+            ));
+        }
+        changes_before.insert("write_mode".into(), serde_json::json!(model.write_mode));
+        changes_after.insert("write_mode".into(), serde_json::json!(write_mode));
+        active.write_mode = Set(write_mode.clone());
+    }
 
     if let Some(config_input) = body.config {
         changes_after.insert("config_changed".into(), serde_json::json!(true));
@@ -371,6 +415,29 @@ pub async fn delete_datasource(
     Ok(StatusCode::NO_CONTENT)
 }
 
+// ---------- GET /datasources/{id}/upstreams ----------
+
+/// Health, observed role and replica lag of each upstream endpoint, plus whether
+/// reads may currently be routed there.
+pub async fn get_datasource_upstreams(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<crate::engine::EndpointStatus>>, ApiErr> {
+    let model = data_source::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Data source not found"))?;
+
+    let cfg = crate::engine::DataSourceConfig::from_model(&model, &state.master_key)
+        .map_err(ApiErr::internal)?;
+
+    Ok(Json(
+        state.engine_cache.upstream_status(&model.name, &cfg).await,
+    ))
+}
+
 // ---------- POST /datasources/{id}/test ----------
 
 pub async fn test_datasource(
@@ -575,6 +642,8 @@ mod tests {
             secure_config: Set(secure_enc),
             is_active: Set(true),
             access_mode: Set("open".to_string()),
+            policy_combining: Set(None),
+            write_mode: Set("read_only".to_string()),
             last_sync_at: Set(None),
             last_sync_result: Set(None),
             created_at: Set(now),
@@ -611,6 +680,8 @@ mod tests {
             secure_config: Set(secure_enc.clone()),
             is_active: Set(true),
             access_mode: Set("open".to_string()),
+            policy_combining: Set(None),
+            write_mode: Set("read_only".to_string()),
             last_sync_at: Set(None),
             last_sync_result: Set(None),
             created_at: Set(now),
@@ -676,6 +747,8 @@ mod tests {
             secure_config: Set("".to_string()),
             is_active: Set(true),
             access_mode: Set("open".to_string()),
+            policy_combining: Set(None),
+            write_mode: Set("read_only".to_string()),
             last_sync_at: Set(None),
             last_sync_result: Set(None),
             created_at: Set(now),
@@ -814,6 +887,7 @@ mod tests {
             policy_hook: None,
             proxy_handler: None,
             wasm_runtime,
+            query_api: Arc::new(crate::admin::query_handlers::QueryApi::default()),
         }
     }
 
@@ -81,11 +81,78 @@ pub fn get_type_defs() -> &'static [DataSourceTypeDef] {
                     is_secret: false,
                     default_value: Some("require"),
                 },
+                FieldDef {
+                    key: "replicas",
+                    label: "Read replicas (host[:port], one per line)",
+                    field_type: FieldType::TextArea,
+                    required: false,
+                    is_secret: false,
+                    default_value: None,
+                },
+                FieldDef {
+                    key: "read_preference",
+                    label: "Read preference",
+                    field_type: FieldType::Select(vec!["replica", "primary"]),
+                    required: false,
+                    is_secret: false,
+                    default_value: Some("replica"),
+                },
+                FieldDef {
+                    key: "max_replica_lag_secs",
+                    label: "Max replica lag (seconds)",
+                    field_type: FieldType::Number,
+                    required: false,
+                    is_secret: false,
+                    default_value: None,
+                },
+                FieldDef {
+                    key: "max_pool_size",
+                    label: "Max pool size (connections per endpoint)",
+                    field_type: FieldType::Number,
+                    required: false,
+                    is_secret: false,
+                    default_value: None,
+                },
+                FieldDef {
+                    key: "max_connection_lifetime_secs",
+                    label: "Max connection lifetime (seconds)",
+                    field_type: FieldType::Number,
+                    required: false,
+                    is_secret: false,
+                    default_value: None,
+                },
+                FieldDef {
+                    key: "idle_timeout_secs",
+                    label: "Idle timeout (seconds)",
+                    field_type: FieldType::Number,
+                    required: false,
+                    is_secret: false,
+                    default_value: None,
+                },
+                FieldDef {
+                    key: "statement_timeout_ms",
+                    label: "Upstream statement timeout (ms)",
+                    field_type: FieldType::Number,
+                    required: false,
+                    is_secret: false,
+                    default_value: None,
+                },
+                FieldDef {
+                    key: "application_name",
+                    label: "Application name",
+                    field_type: FieldType::Text,
+                    required: false,
+                    is_secret: false,
+                    default_value: None,
+                },
             ],
         }]
     })
 }
 
+/// Upper bound for `max_pool_size`, per endpoint.
+pub const MAX_POOL_SIZE: u64 = 1000;
+
 pub fn get_type_def(ds_type: &str) -> Option<&'static DataSourceTypeDef> {
     get_type_defs().iter().find(|d| d.ds_type == ds_type)
 }
@@ -109,6 +176,77 @@ impl std::fmt::Display for ConfigError {
 
 impl std::error::Error for ConfigError {}
 
+/// Parse the `replicas` field: `host` or `host:port` entries separated by newlines
+/// or commas. Entries without a port use `default_port` (the primary's port).
+pub fn parse_endpoint_list(text: &str, default_port: u16) -> Result<Vec<(String, u16)>, String> {
+    text.split(['\n', ','])
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() => port
+                .parse::<u16>()
+                .map(|port| (host.to_string(), port))
+                .map_err(|_| format!("invalid port in replica endpoint '{entry}'")),
+            Some(_) => Err(format!("invalid replica endpoint '{entry}'")),
+            None => Ok((entry.to_string(), default_port)),
+        })
+        .collect()
+}
+
+/// Read an optional non-negative integer field. Number fields may arrive as
+/// strings from form inputs; an empty string or `null` means unset.
+pub fn optional_u64(config: &serde_json::Value, key: &str) -> Result<Option<u64>, String> {
+    match config.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(v) if v.is_u64() => Ok(v.as_u64()),
+        Some(serde_json::Value::String(s)) if s.trim().is_empty() => Ok(None),
+        Some(serde_json::Value::String(s)) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{key} must be a non-negative integer")),
+        Some(_) => Err(format!("{key} must be a non-negative integer")),
+    }
+}
+
+/// Cross-field checks that the per-field loop in `split_config`/`merge_config`
+/// cannot express.
+fn validate_config(config: &serde_json::Value) -> Result<(), ConfigError> {
+    if let Some(replicas) = config.get("replicas").and_then(|v| v.as_str()) {
+        let port = config.get("port").and_then(|v| v.as_u64()).unwrap_or(5432) as u16;
+        parse_endpoint_list(replicas, port).map_err(ConfigError::InvalidInput)?;
+    }
+    optional_u64(config, "max_replica_lag_secs").map_err(ConfigError::InvalidInput)?;
+    for key in [
+        "max_pool_size",
+        "max_connection_lifetime_secs",
+        "idle_timeout_secs",
+        "statement_timeout_ms",
+    ] {
+        if optional_u64(config, key).map_err(ConfigError::InvalidInput)? == Some(0) {
+            return Err(ConfigError::InvalidInput(format!(
+                "{key} must be greater than zero (leave it empty for the default)"
+            )));
+        }
+    }
+    if optional_u64(config, "max_pool_size").map_err(ConfigError::InvalidInput)?
+        > Some(MAX_POOL_SIZE)
+    {
+        return Err(ConfigError::InvalidInput(format!(
+            "max_pool_size must be at most {MAX_POOL_SIZE}"
+        )));
+    }
+    if let Some(name) = config.get("application_name").and_then(|v| v.as_str())
+        && (name.len() > 63 || !name.chars().all(|c| c.is_ascii_graphic() || c == ' '))
+    {
+        // Postgres truncates at NAMEDATALEN - 1 and mangles non-ASCII.
+        return Err(ConfigError::InvalidInput(
+            "application_name must be at most 63 printable ASCII characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 /// Split a flat config input into (non_secret_config, secret_config).
 /// Validates required fields are present (with defaults applied).
 pub fn split_config(
@@ -161,10 +299,9 @@ pub fn split_config(
         }
     }
 
-    Ok((
-        serde_json::Value::Object(config),
-        serde_json::Value::Object(secure),
-    ))
+    let config = serde_json::Value::Object(config);
+    validate_config(&config)?;
+    Ok((config, serde_json::Value::Object(secure)))
 }
 
 /// Merge an update input with existing config + secure_config.
@@ -213,10 +350,9 @@ pub fn merge_config(
         }
     }
 
-    Ok((
-        serde_json::Value::Object(config),
-        serde_json::Value::Object(secure),
-    ))
+    let config = serde_json::Value::Object(config);
+    validate_config(&config)?;
+    Ok((config, serde_json::Value::Object(secure)))
 }
 
 // ---------- API response types ----------
@@ -442,4 +578,97 @@ mod tests {
             "Empty password should preserve existing"
         );
     }
+
+    #[test]
+    fn test_parse_endpoint_list() {
+        let endpoints =
+            parse_endpoint_list("replica-1\nreplica-2:5433, 10.0.0.9:6432\n\n", 5432).unwrap();
+        assert_eq!(
+            endpoints,
+            vec![
+                ("replica-1".to_string(), 5432),
+                ("replica-2".to_string(), 5433),
+                ("10.0.0.9".to_string(), 6432),
+            ]
+        );
+        assert!(parse_endpoint_list("replica-1:abc", 5432).is_err());
+        assert!(parse_endpoint_list(":5432", 5432).is_err());
+        assert!(parse_endpoint_list("", 5432).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_config_rejects_invalid_replicas() {
+        let input = serde_json::json!({
+            "host": "localhost",
+            "database": "mydb",
+            "username": "alice",
+            "password": "s3cret",
+            "replicas": "replica-1:99999",
+        });
+        let err = split_config("postgres", input).unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_split_config_defaults_read_preference() {
+        let input = serde_json::json!({
+            "host": "localhost",
+            "database": "mydb",
+            "username": "alice",
+            "password": "s3cret",
+        });
+        let (config, _) = split_config("postgres", input).unwrap();
+        assert_eq!(config["read_preference"], "replica");
+        assert!(config.get("replicas").is_none());
+    }
+
+    #[test]
+    fn test_optional_u64() {
+        let config = serde_json::json!({"a": 5, "b": " 7 ", "c": "", "d": null, "e": "x", "f": -1});
+        assert_eq!(optional_u64(&config, "a"), Ok(Some(5)));
+        assert_eq!(optional_u64(&config, "b"), Ok(Some(7)));
+        assert_eq!(optional_u64(&config, "c"), Ok(None));
+        assert_eq!(optional_u64(&config, "d"), Ok(None));
+        assert_eq!(optional_u64(&config, "missing"), Ok(None));
+        assert!(optional_u64(&config, "e").is_err());
+        assert!(optional_u64(&config, "f").is_err());
+    }
+
+    #[test]
+    fn test_merge_config_validates_pool_limits() {
+        let existing = serde_json::json!({
+            "host": "localhost",
+            "port": 5432,
+            "database": "mydb",
+            "username": "alice",
+            "sslmode": "require",
+        });
+        let secure = serde_json::json!({"password": "s3cret"});
+        let merge = |update: serde_json::Value| {
+            merge_config("postgres", existing.clone(), secure.clone(), update)
+        };
+
+        let (config, _) = merge(serde_json::json!({
+            "max_pool_size": "20",
+            "idle_timeout_secs": 300,
+            "statement_timeout_ms": "",
+            "application_name": "betweenrows prod",
+        }))
+        .unwrap();
+        assert_eq!(config["max_pool_size"], "20");
+        assert_eq!(config["application_name"], "betweenrows prod");
+
+        for bad in [
+            serde_json::json!({"max_pool_size": 0}),
+            serde_json::json!({"max_pool_size": MAX_POOL_SIZE + 1}),
+            serde_json::json!({"max_connection_lifetime_secs": "never"}),
+            serde_json::json!({"application_name": "caf\u{e9}"}),
+            serde_json::json!({"application_name": "x".repeat(64)}),
+        ] {
+            assert!(
+                matches!(merge(bad.clone()), Err(ConfigError::InvalidInput(_))),
+                "{bad} should be rejected"
+            );
+        }
+    }
 }
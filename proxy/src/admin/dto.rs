@@ -6,7 +6,9 @@ use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
 use crate::entity::proxy_user;
-use crate::policy_match::{PolicyType, TargetEntry};
+use crate::policy_match::{ActionStatus, PolicyType, TAG_SELECTOR_PREFIX, TargetEntry};
+use crate::policy_template::TemplateParam;
+use crate::purpose::JustificationValidator;
 
 /// Deserialize `Option<Option<T>>` with 3-state semantics:
 /// - absent → `None` (no change) — handled by `#[serde(default)]`
@@ -328,6 +330,12 @@ pub struct CreateDataSourceRequest {
     /// "open" or "policy_required" (default "policy_required")
     #[serde(default = "default_access_mode")]
     pub access_mode: String,
+    /// Combination strategy per permit policy type; types left out keep their default.
+    #[serde(default)]
+    pub policy_combining: Option<serde_json::Value>,
+    /// "read_only" or "read_write" (default "read_only")
+    #[serde(default = "default_write_mode")]
+    pub write_mode: String,
 }
 
 fn default_access_mode() -> String {
@@ -338,6 +346,14 @@ pub fn validate_access_mode(mode: &str) -> bool {
     matches!(mode, "open" | "policy_required")
 }
 
+fn default_write_mode() -> String {
+    "read_only".to_string()
+}
+
+pub fn validate_write_mode(mode: &str) -> bool {
+    matches!(mode, "read_only" | "read_write")
+}
+
 /// Username: 3–50 chars, starts with a letter, only [a-zA-Z0-9_.-]
 pub fn validate_username(name: &str) -> Result<(), &'static str> {
     if name.len() < 3 || name.len() > 50 {
@@ -397,6 +413,9 @@ pub struct UpdateDataSourceRequest {
     /// Flat config update — absent fields are preserved, empty-string secret fields kept as-is.
     pub config: Option<serde_json::Value>,
     pub access_mode: Option<String>,
+    /// Replaces the combination strategies; `{}` restores the defaults.
+    pub policy_combining: Option<serde_json::Value>,
+    pub write_mode: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -422,6 +441,9 @@ pub struct DataSourceResponse {
     pub config: serde_json::Value,
     pub is_active: bool,
     pub access_mode: String,
+    /// Effective combination strategy for every configurable policy type.
+    pub policy_combining: crate::policy_combining::CombiningRules,
+    pub write_mode: String,
     pub last_sync_at: Option<NaiveDateTime>,
     pub last_sync_result: Option<serde_json::Value>,
     pub created_at: NaiveDateTime,
@@ -528,6 +550,8 @@ pub struct CatalogSchemaResponse {
     /// `None` means the raw `schema_name` is used directly.
     pub schema_alias: Option<String>,
     pub is_selected: bool,
+    /// Tags set on this schema; inherited by its tables and columns.
+    pub tags: Vec<String>,
     pub tables: Vec<CatalogTableResponse>,
 }
 
@@ -537,6 +561,8 @@ pub struct CatalogTableResponse {
     pub table_name: String,
     pub table_type: String,
     pub is_selected: bool,
+    /// Tags set on this table; inherited by its columns.
+    pub tags: Vec<String>,
     pub columns: Vec<CatalogColumnResponse>,
 }
 
@@ -550,14 +576,194 @@ pub struct CatalogColumnResponse {
     pub column_default: Option<String>,
     pub arrow_type: Option<String>,
     pub is_selected: bool,
+    /// Tags set on this column.
+    pub tags: Vec<String>,
+    /// Tags after inheritance from the table and schema — what `tag:` selectors match.
+    pub effective_tags: Vec<String>,
+}
+
+// ---------- catalog tags ----------
+
+/// Replace the tags on one catalog object, addressed by upstream names:
+/// the schema, a table (`table`), or a column (`table` + `column`).
+#[derive(Debug, Deserialize)]
+pub struct SetCatalogTagsRequest {
+    pub schema: String,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    /// `name`, `key:value`, or `!name` to drop an inherited tag. Empty clears the tags.
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CatalogTagsResponse {
+    pub schema: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    pub tags: Vec<String>,
+    pub effective_tags: Vec<String>,
+}
+
+// ---------- masking key requests ----------
+
+/// Masking key names are referenced from SQL string literals (`br_hash(col, 'name')`),
+/// so they are limited to characters that never need escaping.
+pub fn validate_masking_key_name(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() || name.len() > 100 {
+        return Err("Masking key name must be between 1 and 100 characters");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err("Masking key name may only contain letters, digits, '_', '-' and '.'");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMaskingKeyRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Base64-encoded 32-byte key material. Generated when omitted.
+    pub secret: Option<String>,
+    /// Allow `br_fpe_encrypt` output to be revealed, and `br_pseudonymize` tokens
+    /// re-identified, by admins.
+    #[serde(default)]
+    pub reversible: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMaskingKeyRequest {
+    pub description: Option<String>,
+    pub reversible: Option<bool>,
+    /// Replace the key material with freshly generated bytes.
+    #[serde(default)]
+    pub rotate: bool,
+    pub version: i32,
+}
+
+/// Key metadata only — the key material is write-only.
+#[derive(Debug, Serialize)]
+pub struct MaskingKeyResponse {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub reversible: bool,
+    pub version: i32,
+    pub created_by: uuid::Uuid,
+    pub updated_by: uuid::Uuid,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevealMaskedValuesRequest {
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReidentifyTokensRequest {
+    pub tokens: Vec<String>,
+}
+
+/// `null` where a value has too few digits to have been FPE-encrypted, or where
+/// a token is unknown to the pseudonym vault.
+#[derive(Debug, Serialize)]
+pub struct RevealMaskedValuesResponse {
+    pub values: Vec<Option<String>>,
+}
+
+// ---------- privacy budgets ----------
+
+/// Epsilon one user has spent on a datasource under `differential_privacy` policies.
+#[derive(Debug, Serialize)]
+pub struct PrivacyBudgetResponse {
+    pub user_id: uuid::Uuid,
+    pub username: String,
+    pub epsilon_spent: f64,
+    pub query_count: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// ---------- purposes ----------
+
+/// Purpose names are declared by clients in startup parameters and `SET`
+/// statements, so they are limited to characters that never need quoting.
+pub fn validate_purpose_name(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() || name.len() > 100 {
+        return Err("Purpose name must be between 1 and 100 characters");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err("Purpose name may only contain letters, digits, '_', '-' and '.'");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePurposeRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Refuse declarations of this purpose without a justification.
+    #[serde(default)]
+    pub require_justification: bool,
+    /// Check the justification; implies `require_justification`.
+    pub justification_validator: Option<JustificationValidator>,
+}
+
+/// The name is fixed: policies and assignments refer to it.
+#[derive(Debug, Deserialize)]
+pub struct UpdatePurposeRequest {
+    pub description: Option<String>,
+    pub require_justification: Option<bool>,
+    /// 3-state nullable: absent=no change, null=remove, validator=replace.
+    #[serde(default, deserialize_with = "deserialize_optional_nullable")]
+    pub justification_validator: Option<Option<JustificationValidator>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurposeResponse {
+    pub id: uuid::Uuid,
+    pub data_source_id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub require_justification: bool,
+    pub justification_validator: Option<JustificationValidator>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
 }
 
 // ---------- policy requests ----------
 
+/// Upper bound on an `aggregate_only` policy's `min_group_size`.
+pub const MAX_MIN_GROUP_SIZE: i64 = 1_000_000;
+
+/// Upper bound on a `differential_privacy` policy's per-aggregate `epsilon`.
+pub const MAX_DP_EPSILON: f64 = 10.0;
+
+/// Shortest and longest `window_secs` a `result_limit` policy may count rows over.
+pub const MIN_LIMIT_WINDOW_SECS: u64 = 60;
+pub const MAX_LIMIT_WINDOW_SECS: u64 = 31 * 24 * 3600;
+
 /// Validate a policy's `definition` JSON for a given `policy_type`.
 ///
 /// - `row_filter`: requires `filter_expression` (string)
-/// - `column_mask`: requires `mask_expression` (string)
+/// - `column_mask`: requires `mask_expression` (string), or `pseudonymize: {key}` naming
+///   a masking key
+/// - `aggregate_only`: requires `min_group_size` (integer, 2 to [`MAX_MIN_GROUP_SIZE`])
+/// - `differential_privacy`: requires `epsilon` (0 to [`MAX_DP_EPSILON`]) and `budget`
+///   (at least `epsilon`); `gaussian` also requires `delta` in (0, 1)
+/// - `result_limit`: requires at least one of `max_rows`, `max_bytes`, `window_rows`
+///   (each at least 1); `window_rows` and `window_secs` go together
+/// - `query_guard`: requires at least one rule; `select_star_min_columns` only with
+///   `forbid_select_star`
+/// - `write_allow`: requires a non-empty `operations` list (`insert`, `update`, `delete`)
 /// - Others: definition must be absent or null
 pub fn validate_definition(
     policy_type: PolicyType,
@@ -582,6 +788,20 @@ pub fn validate_definition(
             let def = definition
                 .as_ref()
                 .ok_or("column_mask policy requires a 'definition' with 'mask_expression'")?;
+            if let Some(mode) = def.get("pseudonymize") {
+                if def.get("mask_expression").is_some() {
+                    return Err(
+                        "column_mask: 'mask_expression' and 'pseudonymize' are mutually exclusive"
+                            .to_string(),
+                    );
+                }
+                let key = mode
+                    .get("key")
+                    .and_then(|k| k.as_str())
+                    .ok_or("column_mask: 'pseudonymize' requires a string 'key'")?;
+                validate_masking_key_name(key).map_err(|e| format!("column_mask: {e}"))?;
+                return Ok(());
+            }
             match def.get("mask_expression") {
                 Some(v) if v.is_string() => {
                     let expr = v.as_str().unwrap();
@@ -592,15 +812,188 @@ pub fn validate_definition(
                 None => Err("column_mask: missing required field 'mask_expression'".to_string()),
             }
         }
-        PolicyType::ColumnAllow | PolicyType::ColumnDeny | PolicyType::TableDeny => Ok(()),
+        PolicyType::AggregateOnly => {
+            let def = definition
+                .as_ref()
+                .ok_or("aggregate_only policy requires a 'definition' with 'min_group_size'")?;
+            let def: crate::policy_match::AggregateOnlyDef = serde_json::from_value(def.clone())
+                .map_err(|_| "aggregate_only: 'min_group_size' must be an integer".to_string())?;
+            if !(2..=MAX_MIN_GROUP_SIZE).contains(&def.min_group_size) {
+                return Err(format!(
+                    "aggregate_only: 'min_group_size' must be between 2 and {MAX_MIN_GROUP_SIZE}"
+                ));
+            }
+            Ok(())
+        }
+        PolicyType::DifferentialPrivacy => {
+            let def = definition.as_ref().ok_or(
+                "differential_privacy policy requires a 'definition' with 'epsilon' and 'budget'",
+            )?;
+            let def: crate::policy_match::DifferentialPrivacyDef =
+                serde_json::from_value(def.clone())
+                    .map_err(|e| format!("differential_privacy: {e}"))?;
+            if !(def.epsilon > 0.0 && def.epsilon <= MAX_DP_EPSILON) {
+                return Err(format!(
+                    "differential_privacy: 'epsilon' must be greater than 0 and at most {MAX_DP_EPSILON}"
+                ));
+            }
+            if !(def.budget.is_finite() && def.budget >= def.epsilon) {
+                return Err("differential_privacy: 'budget' must be at least 'epsilon'".to_string());
+            }
+            match (def.mechanism, def.delta) {
+                (crate::policy_match::NoiseMechanism::Gaussian, None) => {
+                    return Err(
+                        "differential_privacy: the gaussian mechanism requires 'delta'".to_string(),
+                    );
+                }
+                (_, Some(delta)) if !(delta > 0.0 && delta < 1.0) => {
+                    return Err(
+                        "differential_privacy: 'delta' must be between 0 and 1 (exclusive)"
+                            .to_string(),
+                    );
+                }
+                _ => {}
+            }
+            for (column, [lower, upper]) in &def.bounds {
+                if !(lower.is_finite() && upper.is_finite() && lower < upper) {
+                    return Err(format!(
+                        "differential_privacy: bounds for '{column}' must be finite with lower < upper"
+                    ));
+                }
+            }
+            Ok(())
+        }
+        PolicyType::ResultLimit => {
+            let def = definition.as_ref().ok_or(
+                "result_limit policy requires a 'definition' with 'max_rows', 'max_bytes' or 'window_rows'",
+            )?;
+            let def: crate::policy_match::ResultLimitDef =
+                serde_json::from_value(def.clone()).map_err(|e| format!("result_limit: {e}"))?;
+            if def.max_rows.is_none() && def.max_bytes.is_none() && def.window_rows.is_none() {
+                return Err(
+                    "result_limit: set at least one of 'max_rows', 'max_bytes' or 'window_rows'"
+                        .to_string(),
+                );
+            }
+            for (field, value) in [
+                ("max_rows", def.max_rows),
+                ("max_bytes", def.max_bytes),
+                ("window_rows", def.window_rows),
+            ] {
+                if value == Some(0) {
+                    return Err(format!("result_limit: '{field}' must be at least 1"));
+                }
+            }
+            match (def.window_rows, def.window_secs) {
+                (Some(_), None) | (None, Some(_)) => {
+                    return Err(
+                        "result_limit: 'window_rows' and 'window_secs' must be set together"
+                            .to_string(),
+                    );
+                }
+                (_, Some(secs))
+                    if !(MIN_LIMIT_WINDOW_SECS..=MAX_LIMIT_WINDOW_SECS).contains(&secs) =>
+                {
+                    return Err(format!(
+                        "result_limit: 'window_secs' must be between {MIN_LIMIT_WINDOW_SECS} and {MAX_LIMIT_WINDOW_SECS}"
+                    ));
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+        PolicyType::QueryGuard => {
+            let def = definition
+                .as_ref()
+                .ok_or("query_guard policy requires a 'definition' with at least one rule")?;
+            let def: crate::policy_match::QueryGuardDef =
+                serde_json::from_value(def.clone()).map_err(|e| format!("query_guard: {e}"))?;
+            if !def.forbid_select_star
+                && def.require_predicate_on.is_empty()
+                && !def.forbid_cross_join
+                && def.max_joins.is_none()
+            {
+                return Err("query_guard: set at least one of 'forbid_select_star', \
+                     'require_predicate_on', 'forbid_cross_join' or 'max_joins'"
+                    .to_string());
+            }
+            match def.select_star_min_columns {
+                Some(_) if !def.forbid_select_star => {
+                    return Err(
+                        "query_guard: 'select_star_min_columns' requires 'forbid_select_star'"
+                            .to_string(),
+                    );
+                }
+                Some(0) => {
+                    return Err(
+                        "query_guard: 'select_star_min_columns' must be at least 1".to_string()
+                    );
+                }
+                _ => {}
+            }
+            if def.require_predicate_on.iter().any(|c| c.trim().is_empty()) {
+                return Err(
+                    "query_guard: 'require_predicate_on' entries must not be empty".to_string(),
+                );
+            }
+            Ok(())
+        }
+        PolicyType::WriteAllow => {
+            let def = definition
+                .as_ref()
+                .ok_or("write_allow policy requires a 'definition' with 'operations'")?;
+            let def: crate::policy_match::WriteAllowDef =
+                serde_json::from_value(def.clone()).map_err(|e| format!("write_allow: {e}"))?;
+            if def.operations.is_empty() {
+                return Err("write_allow: 'operations' must not be empty".to_string());
+            }
+            Ok(())
+        }
+        PolicyType::ColumnAllow
+        | PolicyType::ColumnDeny
+        | PolicyType::TableDeny
+        | PolicyType::JoinOnly
+        | PolicyType::WriteDeny => Ok(()),
+    }
+}
+
+/// Validate the `definition` of a policy backed by a template.
+///
+/// The definition may only carry `params` for the template; the bound expression
+/// must parse like an inline `filter_expression` / `mask_expression` would.
+pub fn validate_template_definition(
+    policy_type: PolicyType,
+    definition: &Option<serde_json::Value>,
+    template: &crate::entity::policy_template::Model,
+) -> Result<(), String> {
+    if let Some(def) = definition {
+        let obj = def
+            .as_object()
+            .ok_or("'definition' must be an object with optional 'params'")?;
+        if let Some(key) = obj.keys().find(|k| k.as_str() != "params") {
+            return Err(format!(
+                "'{key}' is not allowed when the policy uses a template; pass template values in 'params'"
+            ));
+        }
     }
+    let bound =
+        crate::policy_template::resolve_definition(policy_type, template, definition.as_ref())?;
+    let expression_key = crate::policy_template::expression_key(policy_type)
+        .ok_or_else(|| format!("'{policy_type}' policies cannot use a template"))?;
+    let expression = bound
+        .get(expression_key)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    crate::hooks::policy::validate_expression(expression, policy_type == PolicyType::ColumnMask)
 }
 
 /// Validate the `targets` array for a given `policy_type`.
 ///
 /// - All types require at least one resource entry.
-/// - `column_mask`, `column_allow`, `column_deny`: each entry must have non-empty `columns`.
-/// - `row_filter`, `table_deny`: `columns` must be absent.
+/// - `column_mask`, `column_allow`, `column_deny`, `join_only`, `write_allow`, `write_deny`:
+///   each entry must have non-empty `columns`.
+/// - `row_filter`, `table_deny`, `aggregate_only`, `differential_privacy`, `result_limit`:
+///   `columns` must be absent.
 pub fn validate_targets(policy_type: PolicyType, targets: &[TargetEntry]) -> Result<(), String> {
     if targets.is_empty() {
         return Err("'targets' must not be empty".to_string());
@@ -612,23 +1005,43 @@ pub fn validate_targets(policy_type: PolicyType, targets: &[TargetEntry]) -> Res
         if entry.tables.is_empty() {
             return Err(format!("targets[{i}]: 'tables' must not be empty"));
         }
+        let selectors = entry
+            .schemas
+            .iter()
+            .chain(&entry.tables)
+            .chain(entry.columns.iter().flatten());
+        for selector in selectors {
+            if selector.strip_prefix(TAG_SELECTOR_PREFIX) == Some("") {
+                return Err(format!(
+                    "targets[{i}]: '{TAG_SELECTOR_PREFIX}' selector must name a tag"
+                ));
+            }
+        }
         match policy_type {
-            PolicyType::ColumnMask | PolicyType::ColumnAllow | PolicyType::ColumnDeny => {
-                match &entry.columns {
-                    None => {
-                        return Err(format!(
-                            "targets[{i}]: '{policy_type}' requires non-empty 'columns'"
-                        ));
-                    }
-                    Some(cols) if cols.is_empty() => {
-                        return Err(format!(
-                            "targets[{i}]: '{policy_type}' requires non-empty 'columns'"
-                        ));
-                    }
-                    _ => {}
+            PolicyType::ColumnMask
+            | PolicyType::ColumnAllow
+            | PolicyType::ColumnDeny
+            | PolicyType::JoinOnly
+            | PolicyType::WriteAllow
+            | PolicyType::WriteDeny => match &entry.columns {
+                None => {
+                    return Err(format!(
+                        "targets[{i}]: '{policy_type}' requires non-empty 'columns'"
+                    ));
                 }
-            }
-            PolicyType::RowFilter | PolicyType::TableDeny => {
+                Some(cols) if cols.is_empty() => {
+                    return Err(format!(
+                        "targets[{i}]: '{policy_type}' requires non-empty 'columns'"
+                    ));
+                }
+                _ => {}
+            },
+            PolicyType::RowFilter
+            | PolicyType::TableDeny
+            | PolicyType::AggregateOnly
+            | PolicyType::DifferentialPrivacy
+            | PolicyType::ResultLimit
+            | PolicyType::QueryGuard => {
                 if entry.columns.is_some() {
                     return Err(format!(
                         "targets[{i}]: '{policy_type}' must not have 'columns'"
@@ -742,8 +1155,8 @@ pub struct DecisionFunctionResponse {
     pub policy_count: usize,
     pub created_by: uuid::Uuid,
     pub updated_by: uuid::Uuid,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: chrono::NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
 }
 
 /// Summary embedded in PolicyResponse.
@@ -755,6 +1168,64 @@ pub struct DecisionFunctionSummary {
     pub evaluate_context: String,
 }
 
+// ---------- policy template requests ----------
+
+#[derive(Debug, Deserialize)]
+pub struct ListPolicyTemplatesQuery {
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+    pub search: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePolicyTemplateRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// `column_mask` or `row_filter` — the policy type this template can back.
+    pub template_type: PolicyType,
+    /// Expression with `{col}` (mask templates) and `{param.NAME}` placeholders.
+    pub expression: String,
+    #[serde(default)]
+    pub parameters: Vec<TemplateParam>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePolicyTemplateRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub expression: Option<String>,
+    pub parameters: Option<Vec<TemplateParam>>,
+    /// Optimistic concurrency
+    pub version: i32,
+}
+
+// ---------- policy template responses ----------
+
+#[derive(Debug, Serialize)]
+pub struct PolicyTemplateResponse {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub template_type: String,
+    pub expression: String,
+    pub parameters: Vec<TemplateParam>,
+    pub version: i32,
+    pub policy_count: usize,
+    pub created_by: uuid::Uuid,
+    pub updated_by: uuid::Uuid,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Summary embedded in PolicyResponse.
+#[derive(Debug, Serialize, Clone)]
+pub struct PolicyTemplateSummary {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub template_type: String,
+    pub version: i32,
+}
+
 // ---------- policy requests ----------
 
 #[derive(Debug, Deserialize)]
@@ -764,10 +1235,17 @@ pub struct CreatePolicyRequest {
     pub description: Option<String>,
     #[serde(default = "default_true")]
     pub is_enabled: bool,
+    /// `shadow` evaluates and audits the policy without applying it.
+    #[serde(default)]
+    pub action_status: ActionStatus,
     pub targets: Vec<TargetEntry>,
     pub definition: Option<serde_json::Value>,
     /// Optional FK to an existing decision_function.
     pub decision_function_id: Option<uuid::Uuid>,
+    /// Optional FK to a policy_template; `definition` then holds only `params`.
+    pub template_id: Option<uuid::Uuid>,
+    /// Only apply the policy to sessions that declared this purpose.
+    pub purpose: Option<String>,
 }
 
 fn default_true() -> bool {
@@ -780,11 +1258,18 @@ pub struct UpdatePolicyRequest {
     pub policy_type: Option<PolicyType>,
     pub description: Option<String>,
     pub is_enabled: Option<bool>,
+    pub action_status: Option<ActionStatus>,
     pub targets: Option<Vec<TargetEntry>>,
     pub definition: Option<serde_json::Value>,
     /// 3-state nullable: absent=no change, null=detach, uuid=attach.
     #[serde(default, deserialize_with = "deserialize_optional_nullable_uuid")]
     pub decision_function_id: Option<Option<uuid::Uuid>>,
+    /// 3-state nullable: absent=no change, null=detach, uuid=attach.
+    #[serde(default, deserialize_with = "deserialize_optional_nullable_uuid")]
+    pub template_id: Option<Option<uuid::Uuid>>,
+    /// 3-state nullable: absent=no change, null=unrestricted, name=restrict.
+    #[serde(default, deserialize_with = "deserialize_optional_nullable")]
+    pub purpose: Option<Option<String>>,
     /// Optimistic concurrency: client must send the current version
     pub version: i32,
 }
@@ -820,12 +1305,76 @@ pub struct AssignPolicyRequest {
     pub scope: Option<String>,
     #[serde(default = "default_priority")]
     pub priority: i32,
+    /// `shadow` brings the policy into scope for evaluation only.
+    #[serde(default)]
+    pub action_status: ActionStatus,
+    /// Only apply the assignment to sessions that declared this purpose, which
+    /// must be in the datasource's purpose catalog.
+    pub purpose: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAssignmentRequest {
+    pub priority: Option<i32>,
+    pub action_status: Option<ActionStatus>,
+    /// 3-state nullable: absent=no change, null=unrestricted, name=restrict.
+    #[serde(default, deserialize_with = "deserialize_optional_nullable")]
+    pub purpose: Option<Option<String>>,
 }
 
 fn default_priority() -> i32 {
     100
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SimulatePolicyChangeRequest {
+    /// The policy to edit; absent to simulate a new policy.
+    pub policy_id: Option<uuid::Uuid>,
+    /// Fields to change. For a new policy, `policy_type` and `targets` are required.
+    #[serde(default)]
+    pub policy: ProposedPolicy,
+    /// A proposed assignment of the policy to this datasource. Required for a new policy.
+    pub assignment: Option<ProposedAssignment>,
+    /// Query audit entries to replay, most recent first. Defaults to 200, at most 1000.
+    pub sample_size: Option<u64>,
+    /// Only replay queries sent at or after this time.
+    pub since: Option<NaiveDateTime>,
+}
+
+/// A policy as in [`UpdatePolicyRequest`], without the version check.
+#[derive(Debug, Default, Deserialize)]
+pub struct ProposedPolicy {
+    pub name: Option<String>,
+    pub policy_type: Option<PolicyType>,
+    pub description: Option<String>,
+    pub is_enabled: Option<bool>,
+    pub action_status: Option<ActionStatus>,
+    pub targets: Option<Vec<TargetEntry>>,
+    pub definition: Option<serde_json::Value>,
+    /// 3-state nullable: absent=no change, null=detach, uuid=attach.
+    #[serde(default, deserialize_with = "deserialize_optional_nullable_uuid")]
+    pub decision_function_id: Option<Option<uuid::Uuid>>,
+    /// 3-state nullable: absent=no change, null=detach, uuid=attach.
+    #[serde(default, deserialize_with = "deserialize_optional_nullable_uuid")]
+    pub template_id: Option<Option<uuid::Uuid>>,
+    /// 3-state nullable: absent=no change, null=unrestricted, name=restrict.
+    #[serde(default, deserialize_with = "deserialize_optional_nullable")]
+    pub purpose: Option<Option<String>>,
+}
+
+/// An assignment as in [`AssignPolicyRequest`], for the simulated policy.
+#[derive(Debug, Deserialize)]
+pub struct ProposedAssignment {
+    pub user_id: Option<uuid::Uuid>,
+    pub role_id: Option<uuid::Uuid>,
+    pub scope: Option<String>,
+    #[serde(default = "default_priority")]
+    pub priority: i32,
+    #[serde(default)]
+    pub action_status: ActionStatus,
+    pub purpose: Option<String>,
+}
+
 // ---------- policy responses ----------
 
 #[derive(Debug, Serialize)]
@@ -837,16 +1386,23 @@ pub struct PolicyResponse {
     pub targets: serde_json::Value,
     pub definition: Option<serde_json::Value>,
     pub is_enabled: bool,
+    pub action_status: String,
     pub version: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub decision_function_id: Option<uuid::Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub decision_function: Option<DecisionFunctionSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_id: Option<uuid::Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<PolicyTemplateSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
     pub assignment_count: usize,
     pub created_by: uuid::Uuid,
     pub updated_by: uuid::Uuid,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: chrono::NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub assignments: Option<Vec<PolicyAssignmentResponse>>,
 }
@@ -864,8 +1420,11 @@ pub struct PolicyAssignmentResponse {
     pub role_name: Option<String>,
     pub assignment_scope: String,
     pub priority: i32,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: chrono::NaiveDateTime,
+    pub action_status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
 }
 
 // ---------- audit log requests/responses ----------
@@ -896,6 +1455,41 @@ pub struct AuditLogResponse {
     pub created_at: chrono::NaiveDateTime,
     pub status: String,
     pub error_message: Option<String>,
+    pub served_by: Option<String>,
+    pub shadow_outcomes: Option<serde_json::Value>,
+    pub epsilon_spent: Option<f64>,
+    pub rows_returned: Option<i64>,
+    pub bytes_returned: Option<i64>,
+    pub impersonated_by: Option<uuid::Uuid>,
+    pub purpose: Option<String>,
+    pub justification: Option<String>,
+    pub policy_conflicts: Option<serde_json::Value>,
+    pub rows_affected: Option<i64>,
+    pub affected_rows: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShadowReportQuery {
+    pub from: Option<String>, // ISO datetime string
+    pub to: Option<String>,
+    pub datasource_id: Option<uuid::Uuid>,
+}
+
+/// Shadow hits of one policy over the requested time range.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ShadowReportEntry {
+    pub policy_id: uuid::Uuid,
+    pub policy_name: String,
+    pub policy_type: String,
+    /// Queries the shadow policy matched.
+    pub hits: u64,
+    pub would_deny: u64,
+    pub would_filter: u64,
+    pub would_mask: u64,
+    /// Distinct users whose queries it matched.
+    pub users: u64,
+    pub first_hit_at: chrono::NaiveDateTime,
+    pub last_hit_at: chrono::NaiveDateTime,
 }
 
 // ---------- decision function test ----------
@@ -951,6 +1545,53 @@ pub struct ValidateExpressionResponse {
     pub error: Option<String>,
 }
 
+// ---------- API keys ----------
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub expires_at: Option<NaiveDateTime>,
+    /// Admin scopes (see `api_key_handlers::ADMIN_SCOPES`). Only admin users may hold them.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub scopes: Vec<String>,
+}
+
+impl From<crate::entity::api_key::Model> for ApiKeyResponse {
+    fn from(m: crate::entity::api_key::Model) -> Self {
+        let scopes = m.scope_list();
+        Self {
+            id: m.id,
+            user_id: m.user_id,
+            name: m.name,
+            key_prefix: m.key_prefix,
+            created_at: m.created_at,
+            last_used_at: m.last_used_at,
+            expires_at: m.expires_at,
+            scopes,
+        }
+    }
+}
+
+/// Returned once, on creation — the only time the raw key is ever shown.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKeyResponse {
+    #[serde(flatten)]
+    pub key: ApiKeyResponse,
+    pub api_key: String,
+}
+
 // ---------- table_relationship + column_anchor ----------
 
 #[derive(Debug, Deserialize)]
@@ -1250,8 +1891,45 @@ mod tests {
 
     #[test]
     fn validate_filter_expression_bad_syntax() {
+        for expr in ["tenant = = 1", "SELECT 1", "no_such_fn(col) = 1"] {
+            assert!(
+                crate::hooks::policy::validate_expression(expr, false).is_err(),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_filter_expression_datafusion_sql_ok() {
+        for expr in [
+            "EXTRACT(HOUR FROM created_at) < 18",
+            "name ILIKE {user.pattern}",
+            "created_at >= DATE '2024-01-01' + INTERVAL '1 month'",
+            "EXISTS (SELECT 1 FROM teams t WHERE t.lead = {user.username})",
+        ] {
+            assert!(
+                crate::hooks::policy::validate_expression(expr, false).is_ok(),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_mask_rejects_predicate_subqueries() {
+        assert!(
+            crate::hooks::policy::validate_expression(
+                "CASE WHEN EXISTS (SELECT 1 FROM vip v WHERE v.id = id) THEN '***' ELSE {col} END",
+                true,
+            )
+            .is_err()
+        );
         assert!(
-            crate::hooks::policy::validate_expression("EXTRACT(HOUR FROM col)", false).is_err()
+            crate::hooks::policy::validate_expression(
+                "CASE WHEN (SELECT max(level) FROM vip v WHERE v.id = id) > 2 \
+                 THEN '***' ELSE {col} END",
+                true,
+            )
+            .is_ok()
         );
     }
 
@@ -1263,6 +1941,140 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validate_mask_br_functions_ok() {
+        for expr in [
+            "br_mask_email({col})",
+            "br_mask_partial({col}, 0, 4, '#')",
+            "br_hash({col}, 'crm_salt')",
+            "br_fpe_encrypt({col}, 'ssn_key')",
+            "br_generalize_number({col}, 10)",
+            "br_pseudonymize({col}, 'crm')",
+        ] {
+            assert!(
+                crate::hooks::policy::validate_expression(expr, true).is_ok(),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_aggregate_only_definition() {
+        let check = |v: serde_json::Value| validate_definition(PolicyType::AggregateOnly, &Some(v));
+        assert!(check(serde_json::json!({"min_group_size": 5})).is_ok());
+        assert!(check(serde_json::json!({"min_group_size": 1})).is_err());
+        assert!(check(serde_json::json!({"min_group_size": "5"})).is_err());
+        assert!(check(serde_json::json!({})).is_err());
+        assert!(validate_definition(PolicyType::AggregateOnly, &None).is_err());
+    }
+
+    #[test]
+    fn validate_differential_privacy_definition() {
+        let check =
+            |v: serde_json::Value| validate_definition(PolicyType::DifferentialPrivacy, &Some(v));
+        assert!(check(serde_json::json!({"epsilon": 0.5, "budget": 10})).is_ok());
+        assert!(
+            check(serde_json::json!({
+                "epsilon": 0.5,
+                "budget": 10,
+                "mechanism": "gaussian",
+                "delta": 1e-6,
+                "bounds": {"salary": [0, 200000]}
+            }))
+            .is_ok()
+        );
+        for bad in [
+            serde_json::json!({"epsilon": 0, "budget": 10}),
+            serde_json::json!({"epsilon": 11, "budget": 100}),
+            serde_json::json!({"epsilon": 1, "budget": 0.5}),
+            serde_json::json!({"epsilon": 1, "budget": 5, "mechanism": "gaussian"}),
+            serde_json::json!({"epsilon": 1, "budget": 5, "mechanism": "exponential"}),
+            serde_json::json!({"epsilon": 1, "budget": 5, "delta": 1.5}),
+            serde_json::json!({"epsilon": 1, "budget": 5, "bounds": {"salary": [10, 0]}}),
+            serde_json::json!({"budget": 5}),
+        ] {
+            assert!(check(bad.clone()).is_err(), "{bad}");
+        }
+        assert!(validate_definition(PolicyType::DifferentialPrivacy, &None).is_err());
+    }
+
+    #[test]
+    fn validate_result_limit_definition() {
+        let check = |v: serde_json::Value| validate_definition(PolicyType::ResultLimit, &Some(v));
+        assert!(check(serde_json::json!({"max_rows": 1000})).is_ok());
+        assert!(
+            check(serde_json::json!({
+                "max_bytes": 1048576,
+                "window_rows": 100000,
+                "window_secs": 86400,
+                "on_exceed": "deny"
+            }))
+            .is_ok()
+        );
+        for bad in [
+            serde_json::json!({}),
+            serde_json::json!({"on_exceed": "deny"}),
+            serde_json::json!({"max_rows": 0}),
+            serde_json::json!({"max_rows": -1}),
+            serde_json::json!({"window_rows": 100}),
+            serde_json::json!({"max_rows": 10, "window_secs": 3600}),
+            serde_json::json!({"window_rows": 100, "window_secs": 10}),
+            serde_json::json!({"max_rows": 10, "on_exceed": "warn"}),
+        ] {
+            assert!(check(bad.clone()).is_err(), "{bad}");
+        }
+        assert!(validate_definition(PolicyType::ResultLimit, &None).is_err());
+    }
+
+    #[test]
+    fn validate_query_guard_definition() {
+        let check = |v: serde_json::Value| validate_definition(PolicyType::QueryGuard, &Some(v));
+        assert!(check(serde_json::json!({"forbid_cross_join": true})).is_ok());
+        assert!(check(serde_json::json!({"max_joins": 0})).is_ok());
+        assert!(
+            check(serde_json::json!({
+                "forbid_select_star": true,
+                "select_star_min_columns": 20,
+                "require_predicate_on": ["event_date"],
+                "max_joins": 3
+            }))
+            .is_ok()
+        );
+        for bad in [
+            serde_json::json!({}),
+            serde_json::json!({"forbid_select_star": false, "forbid_cross_join": false}),
+            serde_json::json!({"select_star_min_columns": 20, "max_joins": 2}),
+            serde_json::json!({"forbid_select_star": true, "select_star_min_columns": 0}),
+            serde_json::json!({"require_predicate_on": [""]}),
+            serde_json::json!({"max_joins": -1}),
+        ] {
+            assert!(check(bad.clone()).is_err(), "{bad}");
+        }
+        assert!(validate_definition(PolicyType::QueryGuard, &None).is_err());
+    }
+
+    #[test]
+    fn validate_pseudonymize_mode() {
+        let def = |v: serde_json::Value| Some(v);
+        assert!(
+            validate_definition(
+                PolicyType::ColumnMask,
+                &def(serde_json::json!({"pseudonymize": {"key": "crm"}}))
+            )
+            .is_ok()
+        );
+        for bad in [
+            serde_json::json!({"pseudonymize": {}}),
+            serde_json::json!({"pseudonymize": {"key": "it's"}}),
+            serde_json::json!({"pseudonymize": {"key": "crm"}, "mask_expression": "'x'"}),
+        ] {
+            assert!(
+                validate_definition(PolicyType::ColumnMask, &def(bad.clone())).is_err(),
+                "{bad}"
+            );
+        }
+    }
+
     #[test]
     fn validate_mask_case_when_ok() {
         assert!(
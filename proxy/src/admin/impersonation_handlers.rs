@@ -0,0 +1,505 @@
+//! Impersonation preview: run a query as another user, or as a hypothetical one.
+//!
+//! `POST /impersonate` lets an admin check what a user would see — the rows, the
+//! rewritten SQL, the visible catalog and every policy decision — without
+//! knowing their credentials. The target is either a stored user (`user_id`) or
+//! a user who does not exist yet, described by `role_ids` and `attributes`.
+//!
+//! The query goes through [`PolicyHook::preview`], the same enforcement path as
+//! every front-end, but never counts against the target's row quota or privacy
+//! budget. Every preview is audited twice: an `impersonate` entry in the admin
+//! audit log under the admin, and a query audit row under the target user with
+//! `impersonated_by` set to the admin.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::execution::SendableRecordBatchStream;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+use crate::entity::{data_source, data_source_purpose, role};
+use crate::subject::Subject;
+
+use super::{
+    AdminState, ApiErr,
+    admin_audit::{AuditAction, AuditedTxn},
+    jwt::AdminClaims,
+    query_handlers::{
+        ResultColumn, SchemaDescription, collect_capped, describe_catalog, json_rows,
+        pgwire_error_to_api, result_columns,
+    },
+    user_handlers::validate_user_attributes,
+};
+
+/// Rows returned when the request sets no `max_rows`.
+const DEFAULT_PREVIEW_ROWS: usize = 100;
+
+/// Upper bound on `max_rows`; a preview is a sample, not an export.
+const MAX_PREVIEW_ROWS: usize = 1000;
+
+/// Username given to a hypothetical user when the request sets none.
+const DEFAULT_HYPOTHETICAL_USERNAME: &str = "hypothetical";
+
+/// `client_info` recorded in the query audit log when the request sets no `application_name`.
+const DEFAULT_CLIENT_INFO: &str = "impersonation-preview";
+
+#[derive(Debug, Deserialize)]
+pub struct ImpersonationRequest {
+    /// Data source name.
+    pub datasource: String,
+    pub sql: String,
+    /// Run as this stored user. Mutually exclusive with the hypothetical fields.
+    pub user_id: Option<Uuid>,
+    /// Direct roles of a hypothetical user; inherited roles are added.
+    #[serde(default)]
+    pub role_ids: Vec<Uuid>,
+    /// Attributes of a hypothetical user, validated like `PUT /users/{id}`.
+    #[serde(default)]
+    pub attributes: HashMap<String, serde_json::Value>,
+    /// Username of a hypothetical user, for `{user.username}` and decision functions.
+    pub username: Option<String>,
+    /// Rows to return (default 100, at most 1000 and the server's `BR_QUERY_MAX_ROWS`).
+    pub max_rows: Option<usize>,
+    pub application_name: Option<String>,
+    /// Preview a session that declared this purpose, from the datasource's
+    /// purpose catalog. No justification is checked.
+    pub purpose: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    /// The target user, or the nil UUID for a hypothetical user.
+    pub user_id: Uuid,
+    pub username: String,
+    pub hypothetical: bool,
+    /// Active roles of the target, direct and inherited.
+    pub roles: Vec<String>,
+    pub datasource: String,
+    pub columns: Vec<ResultColumn>,
+    /// One JSON object per row, keyed by column name.
+    pub rows: serde_json::Value,
+    pub row_count: usize,
+    /// `true` when the result had more than `max_rows` rows.
+    pub truncated: bool,
+    /// Why the query was refused or failed. The decisions taken up to that
+    /// point are still reported.
+    pub error: Option<String>,
+    pub notices: Vec<String>,
+    /// The SQL sent upstream after policy rewriting.
+    pub rewritten_query: Option<String>,
+    /// Per policy, the decision function results — as in the query audit log.
+    pub policies_applied: Vec<serde_json::Value>,
+    /// Targets where several permit policies met, and which of them applied.
+    pub policy_conflicts: Vec<crate::policy_combining::PolicyConflict>,
+    /// The catalog the target can see on `datasource`.
+    pub catalog: Vec<SchemaDescription>,
+}
+
+/// A preview's result sample, or why none was collected.
+type Sample = (
+    SchemaRef,
+    Result<(VecDeque<RecordBatch>, bool), String>,
+    Vec<String>,
+);
+
+// ---------- POST /impersonate ----------
+
+pub async fn preview_as_user(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Json(body): Json<ImpersonationRequest>,
+) -> Result<Json<ImpersonationResponse>, ApiErr> {
+    let hypothetical = body.user_id.is_none();
+    if !hypothetical
+        && (!body.role_ids.is_empty() || !body.attributes.is_empty() || body.username.is_some())
+    {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Set either user_id or role_ids/attributes/username, not both",
+        ));
+    }
+
+    let ds = data_source::Entity::find()
+        .filter(data_source::Column::Name.eq(&body.datasource))
+        .filter(data_source::Column::IsActive.eq(true))
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Data source not found"))?;
+
+    let subject = match body.user_id {
+        Some(user_id) => Subject::load(&state.db, user_id)
+            .await
+            .map_err(ApiErr::internal)?
+            .ok_or_else(|| ApiErr::not_found("User not found"))?,
+        None => hypothetical_subject(&state, &body).await?,
+    };
+    if let Some(ref purpose) = body.purpose {
+        data_source_purpose::Entity::find()
+            .filter(data_source_purpose::Column::DataSourceId.eq(ds.id))
+            .filter(data_source_purpose::Column::Name.eq(purpose))
+            .one(&state.db)
+            .await
+            .map_err(ApiErr::internal)?
+            .ok_or_else(|| {
+                ApiErr::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("Purpose '{purpose}' is not in this data source's purpose catalog"),
+                )
+            })?;
+    }
+    let subject = subject.with_purpose(body.purpose.clone());
+
+    if !subject
+        .has_datasource_access(&state.db, ds.id)
+        .await
+        .map_err(ApiErr::internal)?
+    {
+        return Err(ApiErr::new(
+            StatusCode::FORBIDDEN,
+            format!(
+                "'{}' has no access to data source '{}'",
+                subject.username, ds.name
+            ),
+        ));
+    }
+
+    let policy_hook = state.policy_hook.as_deref().ok_or_else(|| {
+        ApiErr::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Impersonation preview is not available on this server",
+        )
+    })?;
+
+    let api = &state.query_api;
+    let max_rows = body
+        .max_rows
+        .unwrap_or(DEFAULT_PREVIEW_ROWS)
+        .min(MAX_PREVIEW_ROWS)
+        .min(api.max_rows);
+    let timeout = api.timeout;
+
+    // Audited before running, so a preview that fails or times out is still on record.
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    txn.audit(
+        "user",
+        subject.user_id,
+        AuditAction::Impersonate,
+        claims.sub,
+        serde_json::json!({
+            "username": subject.username,
+            "hypothetical": hypothetical,
+            "role_ids": body.role_ids,
+            "attributes": body.attributes,
+            "datasource": ds.name,
+            "purpose": body.purpose,
+            "sql": body.sql,
+        }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    let ctx = state
+        .engine_cache
+        .build_subject_context(&subject, &ds.name, None)
+        .await
+        .map_err(ApiErr::internal)?;
+    let catalog = describe_catalog(&ctx, &ds.name).await?;
+    let roles = subject
+        .role_names(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let client_info = body
+        .application_name
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_CLIENT_INFO.to_string());
+    let consume = |stream: SendableRecordBatchStream, notices: Vec<String>| async move {
+        let schema = stream.schema();
+        let collected = match tokio::time::timeout(timeout, collect_capped(stream, max_rows)).await
+        {
+            Ok(Ok(collected)) => Ok(collected),
+            Ok(Err(e)) => Err(e.message().to_string()),
+            Err(_) => Err(format!(
+                "Query exceeded the {} ms timeout",
+                timeout.as_millis()
+            )),
+        };
+        Ok::<Sample, _>((schema, collected, notices))
+    };
+    let (result, report) = policy_hook
+        .preview(
+            &subject,
+            claims.sub,
+            &ds.name,
+            &body.sql,
+            &ctx,
+            Some(client_info),
+            consume,
+        )
+        .await;
+
+    let (columns, batches, truncated, error, notices) = match result {
+        Ok((schema, Ok((batches, truncated)), notices)) => {
+            (result_columns(&schema), batches, truncated, None, notices)
+        }
+        Ok((schema, Err(e), notices)) => (
+            result_columns(&schema),
+            VecDeque::new(),
+            false,
+            Some(e),
+            notices,
+        ),
+        Err(e) => {
+            let message = pgwire_error_to_api(e).message().to_string();
+            (vec![], VecDeque::new(), false, Some(message), vec![])
+        }
+    };
+
+    Ok(Json(ImpersonationResponse {
+        user_id: subject.user_id,
+        username: subject.username,
+        hypothetical,
+        roles,
+        datasource: ds.name,
+        columns,
+        rows: json_rows(&batches)?,
+        row_count: batches.iter().map(RecordBatch::num_rows).sum(),
+        truncated,
+        error,
+        notices,
+        rewritten_query: report.rewritten_query,
+        policies_applied: report.policies_applied,
+        policy_conflicts: report.policy_conflicts,
+        catalog,
+    }))
+}
+
+/// The hypothetical user described by the request, after checking that every
+/// role exists and every attribute matches its definition.
+async fn hypothetical_subject(
+    state: &AdminState,
+    body: &ImpersonationRequest,
+) -> Result<Subject, ApiErr> {
+    if !body.role_ids.is_empty() {
+        let found = role::Entity::find()
+            .filter(role::Column::Id.is_in(body.role_ids.clone()))
+            .all(&state.db)
+            .await
+            .map_err(ApiErr::internal)?;
+        if let Some(missing) = body
+            .role_ids
+            .iter()
+            .find(|id| !found.iter().any(|r| r.id == **id))
+        {
+            return Err(ApiErr::not_found(format!("Role {missing} not found")));
+        }
+    }
+    validate_user_attributes(&state.db, &body.attributes).await?;
+
+    let username = body
+        .username
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_HYPOTHETICAL_USERNAME.to_string());
+    let attributes = serde_json::to_string(&body.attributes).map_err(ApiErr::internal)?;
+    Subject::hypothetical(&state.db, username, &body.role_ids, attributes)
+        .await
+        .map_err(ApiErr::internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        admin::{discovery_job, jwt},
+        auth::Auth,
+        engine::EngineCache,
+        entity::proxy_user,
+    };
+    use axum::{
+        Router,
+        body::Body,
+        http::{Method, Request},
+        routing::post,
+    };
+    use chrono::Utc;
+    use migration::MigratorTrait as _;
+    use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, Set};
+    use std::sync::{Arc, OnceLock};
+    use tokio::sync::Mutex;
+    use tower::ServiceExt;
+
+    const JWT_SECRET: &str = "test-jwt-secret-key-32-chars-pad";
+
+    async fn setup_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        migration::Migrator::up(&db, None).await.unwrap();
+        db
+    }
+
+    fn shared_wasm_runtime() -> Arc<crate::decision::wasm::WasmDecisionRuntime> {
+        static RUNTIME: OnceLock<Arc<crate::decision::wasm::WasmDecisionRuntime>> = OnceLock::new();
+        RUNTIME
+            .get_or_init(|| Arc::new(crate::decision::wasm::WasmDecisionRuntime::new().unwrap()))
+            .clone()
+    }
+
+    fn make_router(db: DatabaseConnection) -> Router {
+        let wasm_runtime = shared_wasm_runtime();
+        let engine_cache = EngineCache::new(db.clone(), [0u8; 32], wasm_runtime.clone());
+        let state = AdminState {
+            auth: Arc::new(Auth::new(db.clone())),
+            db,
+            jwt_secret: JWT_SECRET.to_string(),
+            jwt_expiry_hours: 1,
+            engine_cache,
+            master_key: [0u8; 32],
+            job_store: Arc::new(Mutex::new(discovery_job::JobStore::new())),
+            policy_hook: None,
+            proxy_handler: None,
+            wasm_runtime,
+            query_api: Arc::new(crate::admin::query_handlers::QueryApi::default()),
+        };
+        Router::new()
+            .route("/impersonate", post(preview_as_user))
+            .with_state(state)
+    }
+
+    fn admin_token() -> String {
+        let claims = jwt::Claims {
+            sub: Uuid::new_v4(),
+            username: "admin".to_string(),
+            is_admin: true,
+            exp: (Utc::now().timestamp() as u64) + 3600,
+        };
+        jwt::encode_jwt(&claims, JWT_SECRET).unwrap()
+    }
+
+    async fn insert_user(db: &DatabaseConnection, username: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = Utc::now().naive_utc();
+        proxy_user::ActiveModel {
+            id: Set(id),
+            username: Set(username.to_string()),
+            password_hash: Set("hash".to_string()),
+            is_admin: Set(false),
+            is_active: Set(true),
+            email: Set(None),
+            display_name: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn insert_datasource(db: &DatabaseConnection, name: &str) {
+        let now = Utc::now().naive_utc();
+        data_source::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            name: Set(name.to_string()),
+            ds_type: Set("postgres".to_string()),
+            config: Set("{}".to_string()),
+            secure_config: Set(String::new()),
+            is_active: Set(true),
+            access_mode: Set("open".to_string()),
+            policy_combining: Set(None),
+            write_mode: Set("read_only".to_string()),
+            last_sync_at: Set(None),
+            last_sync_result: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    async fn post_preview(db: DatabaseConnection, body: serde_json::Value) -> StatusCode {
+        make_router(db)
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/impersonate")
+                    .header("Authorization", format!("Bearer {}", admin_token()))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn user_id_and_hypothetical_fields_are_exclusive() {
+        let db = setup_db().await;
+        insert_datasource(&db, "ds").await;
+        let user_id = insert_user(&db, "alice").await;
+        let status = post_preview(
+            db,
+            serde_json::json!({
+                "datasource": "ds",
+                "sql": "SELECT 1",
+                "user_id": user_id,
+                "role_ids": [Uuid::new_v4()],
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn unknown_user_role_or_datasource_is_not_found() {
+        let db = setup_db().await;
+        insert_datasource(&db, "ds").await;
+        for body in [
+            serde_json::json!({"datasource": "ds", "sql": "SELECT 1", "user_id": Uuid::new_v4()}),
+            serde_json::json!({"datasource": "ds", "sql": "SELECT 1", "role_ids": [Uuid::new_v4()]}),
+            serde_json::json!({"datasource": "nope", "sql": "SELECT 1"}),
+        ] {
+            assert_eq!(
+                post_preview(db.clone(), body.clone()).await,
+                StatusCode::NOT_FOUND,
+                "{body}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn undefined_hypothetical_attribute_is_rejected() {
+        let db = setup_db().await;
+        insert_datasource(&db, "ds").await;
+        let status = post_preview(
+            db,
+            serde_json::json!({
+                "datasource": "ds",
+                "sql": "SELECT 1",
+                "attributes": {"tenant": "acme"},
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn target_without_datasource_access_is_forbidden() {
+        let db = setup_db().await;
+        insert_datasource(&db, "ds").await;
+        let user_id = insert_user(&db, "alice").await;
+        let status = post_preview(
+            db,
+            serde_json::json!({"datasource": "ds", "sql": "SELECT 1", "user_id": user_id}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -3,10 +3,12 @@ use axum::{
     http::{StatusCode, request::Parts},
 };
 use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use sea_orm::EntityTrait;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::AdminState;
+use crate::entity::proxy_user;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -35,6 +37,44 @@ pub fn decode_jwt(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::err
     Ok(data.claims)
 }
 
+/// Claims for a query-API token (`POST /query/token`).
+///
+/// Deliberately shaped differently from [`Claims`] (no `is_admin`, required `scope`)
+/// so a query token never decodes as an admin token and vice versa.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueryClaims {
+    pub sub: Uuid,
+    pub username: String,
+    /// Always [`QUERY_SCOPE`].
+    pub scope: String,
+    pub exp: u64,
+}
+
+pub const QUERY_SCOPE: &str = "query";
+
+pub fn encode_query_jwt(
+    claims: &QueryClaims,
+    secret: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    encode(
+        &Header::default(),
+        claims,
+        &EncodingKey::from_secret(secret.as_ref()),
+    )
+}
+
+pub fn decode_query_jwt(
+    token: &str,
+    secret: &str,
+) -> Result<QueryClaims, jsonwebtoken::errors::Error> {
+    let data = decode::<QueryClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_ref()),
+        &Validation::new(Algorithm::HS256),
+    )?;
+    Ok(data.claims)
+}
+
 fn extract_bearer(parts: &Parts) -> Option<&str> {
     parts
         .headers
@@ -96,3 +136,100 @@ where
         Ok(AuthClaims(claims))
     }
 }
+
+/// Extractor: authenticates a proxy user for the HTTP query API and MCP server.
+///
+/// Accepts either an API key (`Bearer br_…`, see `api_key_handlers`) or a query token
+/// issued by `POST /query/token`. Admin tokens are not accepted. The user must be active.
+pub struct QueryUser {
+    pub user: proxy_user::Model,
+    /// Scopes of the API key used; always empty for query tokens.
+    pub scopes: Vec<String>,
+}
+
+impl<S> FromRequestParts<S> for QueryUser
+where
+    S: Send + Sync,
+    AdminState: FromRef<S>,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let state = AdminState::from_ref(state);
+
+        let token = extract_bearer(parts).ok_or((
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid Authorization header",
+        ))?;
+
+        let (user_id, scopes) = if super::api_key_handlers::is_api_key(token) {
+            let key = super::api_key_handlers::verify_api_key(&state.db, token)
+                .await
+                .map_err(|_| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to verify API key",
+                    )
+                })?
+                .ok_or((StatusCode::UNAUTHORIZED, "Invalid or expired API key"))?;
+            (key.user_id, key.scope_list())
+        } else {
+            let claims = decode_query_jwt(token, &state.jwt_secret)
+                .ok()
+                .filter(|c| c.scope == QUERY_SCOPE)
+                .ok_or((StatusCode::UNAUTHORIZED, "Invalid or expired token"))?;
+            (claims.sub, Vec::new())
+        };
+
+        let user = proxy_user::Entity::find_by_id(user_id)
+            .one(&state.db)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load user"))?
+            .filter(|u| u.is_active)
+            .ok_or((StatusCode::UNAUTHORIZED, "User not found or inactive"))?;
+
+        Ok(QueryUser { user, scopes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret";
+
+    fn far_future() -> u64 {
+        (chrono::Utc::now().timestamp() as u64) + 3600
+    }
+
+    #[test]
+    fn query_token_does_not_decode_as_admin_token() {
+        let token = encode_query_jwt(
+            &QueryClaims {
+                sub: Uuid::now_v7(),
+                username: "alice".into(),
+                scope: QUERY_SCOPE.into(),
+                exp: far_future(),
+            },
+            SECRET,
+        )
+        .unwrap();
+        assert!(decode_jwt(&token, SECRET).is_err());
+        assert_eq!(decode_query_jwt(&token, SECRET).unwrap().scope, QUERY_SCOPE);
+    }
+
+    #[test]
+    fn admin_token_does_not_decode_as_query_token() {
+        let token = encode_jwt(
+            &Claims {
+                sub: Uuid::now_v7(),
+                username: "admin".into(),
+                is_admin: true,
+                exp: far_future(),
+            },
+            SECRET,
+        )
+        .unwrap();
+        assert!(decode_query_jwt(&token, SECRET).is_err());
+    }
+}
@@ -0,0 +1,402 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    Set,
+};
+use uuid::Uuid;
+
+use crate::entity::{masking_key, policy, policy_template, pseudonym_vault};
+use crate::masking;
+
+use super::{
+    AdminState, ApiErr,
+    admin_audit::{AuditAction, AuditedTxn},
+    dto::{
+        CreateMaskingKeyRequest, MaskingKeyResponse, ReidentifyTokensRequest,
+        RevealMaskedValuesRequest, RevealMaskedValuesResponse, UpdateMaskingKeyRequest,
+        validate_masking_key_name,
+    },
+    jwt::AdminClaims,
+};
+
+/// Upper bound on values per reveal or re-identify request.
+const MAX_REVEAL_VALUES: usize = 1000;
+
+// ---------- helpers ----------
+
+fn key_response(k: &masking_key::Model) -> MaskingKeyResponse {
+    MaskingKeyResponse {
+        id: k.id,
+        name: k.name.clone(),
+        description: k.description.clone(),
+        reversible: k.reversible,
+        version: k.version,
+        created_by: k.created_by,
+        updated_by: k.updated_by,
+        created_at: k.created_at,
+        updated_at: k.updated_at,
+    }
+}
+
+async fn find_key(state: &AdminState, id: Uuid) -> Result<masking_key::Model, ApiErr> {
+    masking_key::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Masking key not found"))
+}
+
+/// Push key changes to the shared keyring so running sessions see them on their next query.
+async fn reload_keyring(state: &AdminState) {
+    if let Err(e) = state.engine_cache.reload_masking_keys().await {
+        tracing::error!(error = %e, "Failed to reload masking keys");
+    }
+}
+
+fn unique_name_err(e: sea_orm::DbErr) -> ApiErr {
+    let msg = e.to_string();
+    if msg.contains("UNIQUE") || msg.contains("unique") {
+        ApiErr::conflict("Masking key name already exists")
+    } else {
+        ApiErr::internal(e)
+    }
+}
+
+// ---------- GET /masking-keys ----------
+
+pub async fn list_masking_keys(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+) -> Result<Json<Vec<MaskingKeyResponse>>, ApiErr> {
+    let keys = masking_key::Entity::find()
+        .order_by_asc(masking_key::Column::Name)
+        .all(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    Ok(Json(keys.iter().map(key_response).collect()))
+}
+
+// ---------- POST /masking-keys ----------
+
+pub async fn create_masking_key(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Json(body): Json<CreateMaskingKeyRequest>,
+) -> Result<(StatusCode, Json<MaskingKeyResponse>), ApiErr> {
+    validate_masking_key_name(&body.name)
+        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let secret = match &body.secret {
+        Some(encoded) => masking::decode_secret(encoded)
+            .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?,
+        None => masking::generate_secret(),
+    };
+    let secret_encrypted =
+        masking::encrypt_secret(&secret, &state.master_key).map_err(ApiErr::internal)?;
+
+    let now = Utc::now().naive_utc();
+    let key_id = Uuid::now_v7();
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let model = masking_key::ActiveModel {
+        id: Set(key_id),
+        name: Set(body.name.clone()),
+        description: Set(body.description.clone()),
+        secret_encrypted: Set(secret_encrypted),
+        reversible: Set(body.reversible),
+        version: Set(1),
+        created_by: Set(claims.sub),
+        updated_by: Set(claims.sub),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&*txn)
+    .await
+    .map_err(unique_name_err)?;
+
+    txn.audit(
+        "masking_key",
+        key_id,
+        AuditAction::Create,
+        claims.sub,
+        serde_json::json!({
+            "after": {
+                "name": model.name,
+                "description": model.description,
+                "reversible": model.reversible,
+                "secret_source": if body.secret.is_some() { "provided" } else { "generated" },
+            }
+        }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    reload_keyring(&state).await;
+    Ok((StatusCode::CREATED, Json(key_response(&model))))
+}
+
+// ---------- GET /masking-keys/{id} ----------
+
+pub async fn get_masking_key(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<MaskingKeyResponse>, ApiErr> {
+    Ok(Json(key_response(&find_key(&state, id).await?)))
+}
+
+// ---------- PUT /masking-keys/{id} ----------
+//
+// Rotating replaces the key material: hashes, FPE ciphertexts, pseudonyms and
+// date shifts computed under the old key change, old FPE output can no longer be
+// revealed, and the key's pseudonym vault is purged.
+
+pub async fn update_masking_key(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<UpdateMaskingKeyRequest>,
+) -> Result<Json<MaskingKeyResponse>, ApiErr> {
+    let k = find_key(&state, id).await?;
+    if k.version != body.version {
+        return Err(ApiErr::conflict(format!(
+            "Version conflict: expected {}, got {}",
+            k.version, body.version
+        )));
+    }
+
+    let mut changes_before = serde_json::Map::new();
+    let mut changes_after = serde_json::Map::new();
+    let mut active: masking_key::ActiveModel = k.clone().into();
+    if let Some(ref desc) = body.description {
+        changes_before.insert("description".into(), serde_json::json!(k.description));
+        changes_after.insert("description".into(), serde_json::json!(desc));
+        active.description = Set(Some(desc.clone()));
+    }
+    if let Some(reversible) = body.reversible {
+        changes_before.insert("reversible".into(), serde_json::json!(k.reversible));
+        changes_after.insert("reversible".into(), serde_json::json!(reversible));
+        active.reversible = Set(reversible);
+    }
+    if body.rotate {
+        let secret = masking::generate_secret();
+        active.secret_encrypted =
+            Set(masking::encrypt_secret(&secret, &state.master_key).map_err(ApiErr::internal)?);
+        changes_after.insert("rotated".into(), serde_json::json!(true));
+    }
+    changes_before.insert("version".into(), serde_json::json!(k.version));
+    changes_after.insert("version".into(), serde_json::json!(k.version + 1));
+    active.version = Set(k.version + 1);
+    active.updated_by = Set(claims.sub);
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    let updated = active.update(&*txn).await.map_err(ApiErr::internal)?;
+    if body.rotate {
+        pseudonym_vault::Entity::delete_many()
+            .filter(pseudonym_vault::Column::MaskingKeyId.eq(id))
+            .exec(&*txn)
+            .await
+            .map_err(ApiErr::internal)?;
+    }
+    txn.audit(
+        "masking_key",
+        id,
+        AuditAction::Update,
+        claims.sub,
+        serde_json::json!({ "before": changes_before, "after": changes_after }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    reload_keyring(&state).await;
+    Ok(Json(key_response(&updated)))
+}
+
+// ---------- DELETE /masking-keys/{id} ----------
+
+pub async fn delete_masking_key(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiErr> {
+    let k = find_key(&state, id).await?;
+
+    // Expressions reference keys by quoted name and `pseudonymize` masks by
+    // `"key"`; block deletion while any does.
+    let quoted = format!("'{}'", k.name);
+    let mode_ref = format!("\"key\":\"{}\"", k.name);
+    let policy_refs = policy::Entity::find()
+        .filter(
+            Condition::any()
+                .add(policy::Column::Definition.contains(&quoted))
+                .add(policy::Column::Definition.contains(&mode_ref)),
+        )
+        .count(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    let template_refs = policy_template::Entity::find()
+        .filter(policy_template::Column::Expression.contains(&quoted))
+        .count(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    if policy_refs + template_refs > 0 {
+        return Err(ApiErr::conflict(format!(
+            "Cannot delete: masking key '{}' is referenced by {policy_refs} polic{} and {template_refs} template{}.",
+            k.name,
+            if policy_refs == 1 { "y" } else { "ies" },
+            if template_refs == 1 { "" } else { "s" },
+        )));
+    }
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    txn.audit(
+        "masking_key",
+        id,
+        AuditAction::Delete,
+        claims.sub,
+        serde_json::json!({
+            "before": {
+                "name": k.name,
+                "description": k.description,
+                "reversible": k.reversible,
+                "version": k.version,
+            }
+        }),
+    );
+    pseudonym_vault::Entity::delete_many()
+        .filter(pseudonym_vault::Column::MaskingKeyId.eq(id))
+        .exec(&*txn)
+        .await
+        .map_err(ApiErr::internal)?;
+    let active: masking_key::ActiveModel = k.into();
+    active.delete(&*txn).await.map_err(ApiErr::internal)?;
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    reload_keyring(&state).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ---------- POST /masking-keys/{id}/reveal ----------
+//
+// Reverses `br_fpe_encrypt` output. Only keys created or updated with
+// `reversible: true` allow it, and every call is audited (the count, never the values).
+
+pub async fn reveal_masked_values(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<RevealMaskedValuesRequest>,
+) -> Result<Json<RevealMaskedValuesResponse>, ApiErr> {
+    let k = find_key(&state, id).await?;
+    if !k.reversible {
+        return Err(ApiErr::new(
+            StatusCode::FORBIDDEN,
+            format!("Masking key '{}' is not reversible", k.name),
+        ));
+    }
+    if body.values.len() > MAX_REVEAL_VALUES {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("At most {MAX_REVEAL_VALUES} values can be revealed per request"),
+        ));
+    }
+
+    let secret = masking::decrypt_secret(&k.secret_encrypted, &state.master_key)
+        .map_err(ApiErr::internal)?;
+    let values = body
+        .values
+        .iter()
+        .map(|v| masking::fpe_decrypt(&secret, v))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    txn.audit(
+        "masking_key",
+        id,
+        AuditAction::Reveal,
+        claims.sub,
+        serde_json::json!({ "name": k.name, "count": values.len() }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok(Json(RevealMaskedValuesResponse { values }))
+}
+
+// ---------- POST /masking-keys/{id}/reidentify ----------
+//
+// Resolves `br_pseudonymize` tokens back to the original values recorded in the
+// pseudonym vault. Only reversible keys record (and so re-identify) tokens; a
+// token the vault has not seen under the key's current version resolves to `null`.
+// Every call is audited with the token count, never the values.
+
+pub async fn reidentify_tokens(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<ReidentifyTokensRequest>,
+) -> Result<Json<RevealMaskedValuesResponse>, ApiErr> {
+    let k = find_key(&state, id).await?;
+    if !k.reversible {
+        return Err(ApiErr::new(
+            StatusCode::FORBIDDEN,
+            format!("Masking key '{}' is not reversible", k.name),
+        ));
+    }
+    if body.tokens.len() > MAX_REVEAL_VALUES {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("At most {MAX_REVEAL_VALUES} tokens can be re-identified per request"),
+        ));
+    }
+
+    let rows = pseudonym_vault::Entity::find()
+        .filter(pseudonym_vault::Column::MaskingKeyId.eq(id))
+        .filter(pseudonym_vault::Column::KeyVersion.eq(k.version))
+        .filter(pseudonym_vault::Column::Token.is_in(body.tokens.iter().cloned()))
+        .all(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    let mut found = std::collections::HashMap::with_capacity(rows.len());
+    for row in rows {
+        let value = crate::crypto::decrypt_json(&row.value_encrypted, &state.master_key)
+            .map_err(ApiErr::internal)?;
+        found.insert(row.token, value.as_str().map(str::to_string));
+    }
+    let values: Vec<Option<String>> = body
+        .tokens
+        .iter()
+        .map(|t| found.get(t).cloned().flatten())
+        .collect();
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    txn.audit(
+        "masking_key",
+        id,
+        AuditAction::Reveal,
+        claims.sub,
+        serde_json::json!({
+            "name": k.name,
+            "operation": "reidentify",
+            "count": values.len(),
+            "resolved": values.iter().filter(|v| v.is_some()).count(),
+        }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok(Json(RevealMaskedValuesResponse { values }))
+}
@@ -18,6 +18,7 @@ use crate::handler::ProxyHandler;
 use crate::hooks::policy::PolicyHook;
 
 pub mod admin_audit;
+pub mod api_key_handlers;
 pub mod attribute_definition_handlers;
 pub mod audit_handlers;
 pub mod auth_handlers;
@@ -27,8 +28,14 @@ pub mod datasource_types;
 pub mod decision_function_handlers;
 pub mod discovery_job;
 pub mod dto;
+pub mod impersonation_handlers;
 pub mod jwt;
+pub mod masking_key_handlers;
 pub mod policy_handlers;
+pub mod policy_template_handlers;
+pub mod privacy_budget_handlers;
+pub mod purpose_handlers;
+pub mod query_handlers;
 pub mod relationship_handlers;
 pub mod role_handlers;
 pub mod user_handlers;
@@ -51,11 +58,14 @@ pub struct AdminState {
     pub proxy_handler: Option<Arc<ProxyHandler>>,
     /// Shared WASM runtime for the admin test endpoint.
     pub wasm_runtime: Arc<crate::decision::wasm::WasmDecisionRuntime>,
+    /// Limits and cursor store for the HTTP query API.
+    pub query_api: Arc<query_handlers::QueryApi>,
 }
 
 // ---------- error type ----------
 
 /// A JSON error response: `{"error": "..."}` with an HTTP status.
+#[derive(Debug)]
 pub struct ApiErr(StatusCode, String);
 
 impl ApiErr {
@@ -74,6 +84,14 @@ impl ApiErr {
     pub fn conflict(msg: impl Into<String>) -> Self {
         Self(StatusCode::CONFLICT, msg.into())
     }
+
+    pub fn status(&self) -> StatusCode {
+        self.0
+    }
+
+    pub fn message(&self) -> &str {
+        &self.1
+    }
 }
 
 impl IntoResponse for ApiErr {
@@ -134,6 +152,11 @@ pub fn admin_router(state: AdminState) -> Router {
         .with_state(state)
 }
 
+/// The `/api/v1` routes without the outer layers, for in-process dispatch (MCP admin tools).
+pub(crate) fn api_v1_router(state: AdminState) -> Router {
+    api_v1().with_state(state)
+}
+
 fn api_v1() -> Router<AdminState> {
     Router::new()
         // auth
@@ -151,6 +174,14 @@ fn api_v1() -> Router<AdminState> {
                 .delete(user_handlers::delete_user),
         )
         .route("/users/{id}/password", put(user_handlers::change_password))
+        .route(
+            "/users/{id}/api-keys",
+            get(api_key_handlers::list_api_keys).post(api_key_handlers::create_api_key),
+        )
+        .route(
+            "/users/{id}/api-keys/{key_id}",
+            delete(api_key_handlers::delete_api_key),
+        )
         // data source types
         .route(
             "/datasource-types",
@@ -171,6 +202,10 @@ fn api_v1() -> Router<AdminState> {
             "/datasources/{id}/test",
             post(datasource_handlers::test_datasource),
         )
+        .route(
+            "/datasources/{id}/upstreams",
+            get(datasource_handlers::get_datasource_upstreams),
+        )
         .route(
             "/datasources/{id}/users",
             get(datasource_handlers::get_datasource_users)
@@ -183,7 +218,15 @@ fn api_v1() -> Router<AdminState> {
         )
         .route(
             "/datasources/{id}/policies/{assignment_id}",
-            delete(policy_handlers::remove_assignment),
+            put(policy_handlers::update_assignment).delete(policy_handlers::remove_assignment),
+        )
+        .route(
+            "/datasources/{id}/policy-analysis",
+            get(policy_handlers::get_policy_analysis),
+        )
+        .route(
+            "/datasources/{id}/policy-simulation",
+            post(policy_handlers::simulate_policy_change),
         )
         // async discovery jobs
         .route(
@@ -203,6 +246,28 @@ fn api_v1() -> Router<AdminState> {
             "/datasources/{id}/catalog",
             get(catalog_handlers::get_catalog),
         )
+        .route(
+            "/datasources/{id}/catalog/tags",
+            put(catalog_handlers::set_catalog_tags),
+        )
+        // differential privacy budget ledger
+        .route(
+            "/datasources/{id}/privacy-budgets",
+            get(privacy_budget_handlers::list_privacy_budgets),
+        )
+        .route(
+            "/datasources/{id}/privacy-budgets/{user_id}",
+            delete(privacy_budget_handlers::reset_privacy_budget),
+        )
+        // purpose catalog
+        .route(
+            "/datasources/{id}/purposes",
+            get(purpose_handlers::list_purposes).post(purpose_handlers::create_purpose),
+        )
+        .route(
+            "/datasources/{id}/purposes/{purpose_id}",
+            put(purpose_handlers::update_purpose).delete(purpose_handlers::delete_purpose),
+        )
         // relationships (admin-curated) and fk suggestions (live)
         .route(
             "/datasources/{id}/relationships",
@@ -245,6 +310,38 @@ fn api_v1() -> Router<AdminState> {
             "/policies/{id}/anchor-coverage",
             get(policy_handlers::get_policy_anchor_coverage),
         )
+        // policy templates
+        .route(
+            "/policy-templates",
+            get(policy_template_handlers::list_policy_templates)
+                .post(policy_template_handlers::create_policy_template),
+        )
+        .route(
+            "/policy-templates/{id}",
+            get(policy_template_handlers::get_policy_template)
+                .put(policy_template_handlers::update_policy_template)
+                .delete(policy_template_handlers::delete_policy_template),
+        )
+        // masking keys (br_* UDFs)
+        .route(
+            "/masking-keys",
+            get(masking_key_handlers::list_masking_keys)
+                .post(masking_key_handlers::create_masking_key),
+        )
+        .route(
+            "/masking-keys/{id}",
+            get(masking_key_handlers::get_masking_key)
+                .put(masking_key_handlers::update_masking_key)
+                .delete(masking_key_handlers::delete_masking_key),
+        )
+        .route(
+            "/masking-keys/{id}/reveal",
+            post(masking_key_handlers::reveal_masked_values),
+        )
+        .route(
+            "/masking-keys/{id}/reidentify",
+            post(masking_key_handlers::reidentify_tokens),
+        )
         // attribute definitions
         .route(
             "/attribute-definitions",
@@ -308,9 +405,22 @@ fn api_v1() -> Router<AdminState> {
         // audit log
         .route("/audit/queries", get(audit_handlers::list_audit_logs))
         .route("/audit/admin", get(audit_handlers::list_admin_audit_logs))
+        .route("/audit/shadow", get(audit_handlers::shadow_report))
+        // HTTP query API (proxy-user auth, not admin)
+        .route("/query/token", post(query_handlers::issue_query_token))
+        .route("/query", post(query_handlers::run_query))
+        .route("/query/cursors/{cursor}", get(query_handlers::fetch_cursor))
+        .route("/query/schema", get(query_handlers::describe_schema))
+        // MCP streamable HTTP transport (proxy-user auth)
+        .route("/mcp", post(crate::mcp::handle_http))
         // effective policies
         .route(
             "/users/{id}/effective-policies",
             get(policy_handlers::get_effective_policies),
         )
+        // impersonation preview
+        .route(
+            "/impersonate",
+            post(impersonation_handlers::preview_as_user),
+        )
 }
@@ -11,10 +11,12 @@ use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::entity::{
-    data_source, decision_function, discovered_schema, policy, policy_assignment, policy_version,
-    proxy_user, role,
+    data_source, data_source_purpose, decision_function, discovered_schema, policy,
+    policy_assignment, policy_template, policy_version, proxy_user, role,
 };
-use crate::policy_match::{PolicyType, TargetEntry};
+use crate::policy_analysis;
+use crate::policy_match::{ActionStatus, PolicyType, TargetEntry};
+use crate::policy_simulation;
 use crate::resolution::graph::{AnchorShape, expr_column_names};
 use crate::role_resolver;
 
@@ -26,13 +28,156 @@ use super::{
         AnchorCoverageTableEntry, AnchorCoverageVerdict, AssignPolicyRequest, CreatePolicyRequest,
         DecisionFunctionSummary, ListPoliciesQuery, PaginatedResponse,
         PolicyAnchorCoverageResponse, PolicyAssignmentResponse, PolicyResponse,
-        UpdatePolicyRequest, validate_definition, validate_policy_name, validate_targets,
+        PolicyTemplateSummary, SimulatePolicyChangeRequest, UpdateAssignmentRequest,
+        UpdatePolicyRequest, validate_definition, validate_policy_name, validate_purpose_name,
+        validate_targets, validate_template_definition,
     },
     jwt::AdminClaims,
+    policy_template_handlers::template_summary,
 };
 
 // ---------- helpers ----------
 
+/// Check a policy's targets, and its definition — against its template's
+/// parameters when it has one. A template supplies the expression; the
+/// definition then only carries its params. Returns the template.
+async fn validate_policy_content(
+    db: &impl sea_orm::ConnectionTrait,
+    policy_type: PolicyType,
+    targets: &[TargetEntry],
+    definition: &Option<serde_json::Value>,
+    template_id: Option<Uuid>,
+) -> Result<Option<policy_template::Model>, ApiErr> {
+    validate_targets(policy_type, targets)
+        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    match template_id {
+        Some(template_id) => {
+            let t = find_template(db, template_id).await?;
+            validate_template_definition(policy_type, definition, &t)
+                .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+            Ok(Some(t))
+        }
+        None => {
+            validate_definition(policy_type, definition)
+                .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+            Ok(None)
+        }
+    }
+}
+
+async fn find_decision_function(
+    db: &impl sea_orm::ConnectionTrait,
+    id: Uuid,
+) -> Result<decision_function::Model, ApiErr> {
+    decision_function::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| {
+            ApiErr::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "decision_function_id references a non-existent decision function",
+            )
+        })
+}
+
+/// The scope of an assignment to `user_id` or `role_id` (inferred when
+/// `scope` is unset), after checking that the ids fit it and the role exists.
+async fn assignment_scope(
+    db: &impl sea_orm::ConnectionTrait,
+    scope: Option<&str>,
+    user_id: Option<Uuid>,
+    role_id: Option<Uuid>,
+) -> Result<String, ApiErr> {
+    // Infer scope if not provided
+    let scope = match scope {
+        Some(s) => s.to_string(),
+        None => {
+            if role_id.is_some() {
+                "role".to_string()
+            } else if user_id.is_some() {
+                "user".to_string()
+            } else {
+                "all".to_string()
+            }
+        }
+    };
+
+    // Validate scope/field constraints
+    match scope.as_str() {
+        "user" => {
+            if user_id.is_none() {
+                return Err(ApiErr::new(
+                    StatusCode::BAD_REQUEST,
+                    "scope 'user' requires user_id",
+                ));
+            }
+            if role_id.is_some() {
+                return Err(ApiErr::new(
+                    StatusCode::BAD_REQUEST,
+                    "scope 'user' must not have role_id",
+                ));
+            }
+        }
+        "role" => {
+            if role_id.is_none() {
+                return Err(ApiErr::new(
+                    StatusCode::BAD_REQUEST,
+                    "scope 'role' requires role_id",
+                ));
+            }
+            if user_id.is_some() {
+                return Err(ApiErr::new(
+                    StatusCode::BAD_REQUEST,
+                    "scope 'role' must not have user_id",
+                ));
+            }
+            // Validate role exists
+            role::Entity::find_by_id(role_id.unwrap())
+                .one(db)
+                .await
+                .map_err(ApiErr::internal)?
+                .ok_or_else(|| ApiErr::not_found("Role not found"))?;
+        }
+        "all" => {
+            if user_id.is_some() || role_id.is_some() {
+                return Err(ApiErr::new(
+                    StatusCode::BAD_REQUEST,
+                    "scope 'all' must not have user_id or role_id",
+                ));
+            }
+        }
+        _ => {
+            return Err(ApiErr::new(
+                StatusCode::BAD_REQUEST,
+                "scope must be 'user', 'role', or 'all'",
+            ));
+        }
+    }
+    Ok(scope)
+}
+
+/// Check that `purpose` is in the datasource's purpose catalog.
+async fn ensure_purpose(
+    db: &impl sea_orm::ConnectionTrait,
+    ds_id: Uuid,
+    purpose: &str,
+) -> Result<(), ApiErr> {
+    data_source_purpose::Entity::find()
+        .filter(data_source_purpose::Column::DataSourceId.eq(ds_id))
+        .filter(data_source_purpose::Column::Name.eq(purpose))
+        .one(db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| {
+            ApiErr::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Purpose '{purpose}' is not in this data source's purpose catalog"),
+            )
+        })?;
+    Ok(())
+}
+
 fn assignment_response(
     m: &policy_assignment::Model,
     policy_names: &HashMap<Uuid, String>,
@@ -52,6 +197,8 @@ fn assignment_response(
         role_name: m.role_id.and_then(|rid| role_names.get(&rid).cloned()),
         assignment_scope: m.assignment_scope.clone(),
         priority: m.priority,
+        action_status: m.action_status.clone(),
+        purpose: m.purpose.clone(),
         created_at: m.created_at,
         updated_at: m.updated_at,
     }
@@ -140,10 +287,46 @@ async fn load_decision_function_summaries(
     Ok(dfs.iter().map(|df| (df.id, df_summary(df))).collect())
 }
 
+async fn load_template_summaries(
+    db: &impl sea_orm::ConnectionTrait,
+    ids: &[Uuid],
+) -> Result<HashMap<Uuid, PolicyTemplateSummary>, ApiErr> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let templates = policy_template::Entity::find()
+        .filter(policy_template::Column::Id.is_in(ids.to_vec()))
+        .all(db)
+        .await
+        .map_err(ApiErr::internal)?;
+    Ok(templates
+        .iter()
+        .map(|t| (t.id, template_summary(t)))
+        .collect())
+}
+
+/// Load the template a policy references, or 422 if it does not exist.
+async fn find_template(
+    db: &impl sea_orm::ConnectionTrait,
+    template_id: Uuid,
+) -> Result<policy_template::Model, ApiErr> {
+    policy_template::Entity::find_by_id(template_id)
+        .one(db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| {
+            ApiErr::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "template_id references a non-existent policy template",
+            )
+        })
+}
+
 fn policy_response_basic(
     p: &policy::Model,
     assignment_count: usize,
     df_summary: Option<DecisionFunctionSummary>,
+    template: Option<PolicyTemplateSummary>,
 ) -> PolicyResponse {
     let targets: serde_json::Value =
         serde_json::from_str(&p.targets).unwrap_or(serde_json::Value::Array(vec![]));
@@ -159,9 +342,13 @@ fn policy_response_basic(
         targets,
         definition,
         is_enabled: p.is_enabled,
+        action_status: p.action_status.clone(),
         version: p.version,
         decision_function_id: p.decision_function_id,
         decision_function: df_summary,
+        template_id: p.template_id,
+        template,
+        purpose: p.purpose.clone(),
         assignment_count,
         created_by: p.created_by,
         updated_by: p.updated_by,
@@ -172,7 +359,7 @@ fn policy_response_basic(
 }
 
 /// Create a policy_version snapshot in the same transaction.
-async fn create_snapshot<C: sea_orm::ConnectionTrait>(
+pub(super) async fn create_snapshot<C: sea_orm::ConnectionTrait>(
     txn: &C,
     policy_id: Uuid,
     version: i32,
@@ -192,9 +379,12 @@ async fn create_snapshot<C: sea_orm::ConnectionTrait>(
     let snapshot = serde_json::json!({
         "name": p.name,
         "policy_type": p.policy_type,
+        "action_status": p.action_status,
         "targets": targets,
         "definition": definition,
         "decision_function_id": p.decision_function_id.map(|id| id.to_string()),
+        "template_id": p.template_id.map(|id| id.to_string()),
+        "purpose": p.purpose,
         "assignments": assignments.iter().map(|a| {
             serde_json::json!({
                 "id": a.id.to_string(),
@@ -203,6 +393,8 @@ async fn create_snapshot<C: sea_orm::ConnectionTrait>(
                 "role_id": a.role_id.map(|r| r.to_string()),
                 "assignment_scope": &a.assignment_scope,
                 "priority": a.priority,
+                "action_status": &a.action_status,
+                "purpose": &a.purpose,
             })
         }).collect::<Vec<_>>(),
     });
@@ -286,6 +478,8 @@ pub async fn list_policies(
         .filter_map(|p| p.decision_function_id)
         .collect();
     let df_map = load_decision_function_summaries(&state.db, &df_ids).await?;
+    let template_ids: Vec<Uuid> = items.iter().filter_map(|p| p.template_id).collect();
+    let template_map = load_template_summaries(&state.db, &template_ids).await?;
 
     let data = items
         .iter()
@@ -293,7 +487,8 @@ pub async fn list_policies(
             let df = p
                 .decision_function_id
                 .and_then(|id| df_map.get(&id).cloned());
-            policy_response_basic(p, *asgn_counts.get(&p.id).unwrap_or(&0), df)
+            let template = p.template_id.and_then(|id| template_map.get(&id).cloned());
+            policy_response_basic(p, *asgn_counts.get(&p.id).unwrap_or(&0), df, template)
         })
         .collect();
 
@@ -314,29 +509,24 @@ pub async fn create_policy(
 ) -> Result<(StatusCode, Json<PolicyResponse>), ApiErr> {
     validate_policy_name(&body.name)
         .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    if let Some(ref purpose) = body.purpose {
+        validate_purpose_name(purpose)
+            .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
 
-    validate_targets(body.policy_type, &body.targets)
-        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
-
-    validate_definition(body.policy_type, &body.definition)
-        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let template = validate_policy_content(
+        &state.db,
+        body.policy_type,
+        &body.targets,
+        &body.definition,
+        body.template_id,
+    )
+    .await?;
 
     // Validate decision_function_id if provided
-    let df = if let Some(df_id) = body.decision_function_id {
-        Some(
-            decision_function::Entity::find_by_id(df_id)
-                .one(&state.db)
-                .await
-                .map_err(ApiErr::internal)?
-                .ok_or_else(|| {
-                    ApiErr::new(
-                        StatusCode::UNPROCESSABLE_ENTITY,
-                        "decision_function_id references a non-existent decision function",
-                    )
-                })?,
-        )
-    } else {
-        None
+    let df = match body.decision_function_id {
+        Some(df_id) => Some(find_decision_function(&state.db, df_id).await?),
+        None => None,
     };
 
     let now = Utc::now().naive_utc();
@@ -362,8 +552,11 @@ pub async fn create_policy(
         targets: Set(targets_json),
         definition: Set(definition_json),
         is_enabled: Set(body.is_enabled),
+        action_status: Set(body.action_status.to_string()),
         version: Set(1),
         decision_function_id: Set(body.decision_function_id),
+        template_id: Set(body.template_id),
+        purpose: Set(body.purpose.clone()),
         created_by: Set(claims.sub),
         updated_by: Set(claims.sub),
         created_at: Set(now),
@@ -403,7 +596,11 @@ pub async fn create_policy(
                 "policy_type": body.policy_type.to_string(),
                 "targets": &body.targets,
                 "is_enabled": body.is_enabled,
+                "action_status": body.action_status.to_string(),
                 "decision_function_id": body.decision_function_id.map(|id| id.to_string()),
+                "template_id": body.template_id.map(|id| id.to_string()),
+                "template_version": template.as_ref().map(|t| t.version),
+                "purpose": &body.purpose,
             }
         }),
     );
@@ -413,7 +610,12 @@ pub async fn create_policy(
     let df_sum = df.as_ref().map(df_summary);
     Ok((
         StatusCode::CREATED,
-        Json(policy_response_basic(&policy_model, 0, df_sum)),
+        Json(policy_response_basic(
+            &policy_model,
+            0,
+            df_sum,
+            template.as_ref().map(template_summary),
+        )),
     ))
 }
 
@@ -455,8 +657,14 @@ pub async fn get_policy(
         None
     };
 
+    let template_sum =
+        load_template_summaries(&state.db, &p.template_id.into_iter().collect::<Vec<_>>())
+            .await?
+            .into_values()
+            .next();
+
     let asgn_count = assignments.len();
-    let mut resp = policy_response_basic(&p, asgn_count, df_sum);
+    let mut resp = policy_response_basic(&p, asgn_count, df_sum, template_sum);
     resp.assignments = Some(
         assignments
             .iter()
@@ -503,10 +711,22 @@ pub async fn get_policy_anchor_coverage(
     }
 
     let targets: Vec<TargetEntry> = serde_json::from_str(&p.targets).unwrap_or_default();
-    let definition: Option<serde_json::Value> = p
+    let mut definition: Option<serde_json::Value> = p
         .definition
         .as_deref()
         .and_then(|s| serde_json::from_str(s).ok());
+    // Template-backed filters are checked with the template's current bound expression.
+    if let Some(template_id) = p.template_id {
+        let t = find_template(&state.db, template_id).await?;
+        definition = Some(
+            crate::policy_template::resolve_definition(
+                PolicyType::RowFilter,
+                &t,
+                definition.as_ref(),
+            )
+            .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?,
+        );
+    }
     let filter_expression = definition
         .as_ref()
         .and_then(|d| d.get("filter_expression"))
@@ -584,6 +804,9 @@ pub async fn get_policy_anchor_coverage(
                 (alias, s.schema_name.clone())
             })
             .collect();
+        let catalog_tags = crate::hooks::policy::load_catalog_tags(&state.db, &schemas)
+            .await
+            .map_err(ApiErr::internal)?;
 
         let ds_name = ds_names
             .get(&ds_id)
@@ -596,7 +819,7 @@ pub async fn get_policy_anchor_coverage(
         for ((df_schema, table), columns) in snapshot.columns_by_table.iter() {
             let matched = targets
                 .iter()
-                .any(|t| t.matches_table(df_schema, table, &df_to_upstream));
+                .any(|t| t.matches_table(df_schema, table, &df_to_upstream, &catalog_tags));
             if !matched {
                 continue;
             }
@@ -713,6 +936,10 @@ pub async fn update_policy(
     if let Some(ref name) = body.name {
         validate_policy_name(name).map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
     }
+    if let Some(Some(ref purpose)) = body.purpose {
+        validate_purpose_name(purpose)
+            .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
 
     if p.version != body.version {
         return Err(ApiErr::conflict(format!(
@@ -733,9 +960,6 @@ pub async fn update_policy(
         None => serde_json::from_str(&p.targets).unwrap_or_default(),
     };
 
-    validate_targets(final_policy_type, &final_targets)
-        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
-
     // For validation, use the incoming definition if provided, else the existing DB definition.
     let final_definition: Option<serde_json::Value> = if body.definition.is_some() {
         body.definition.clone()
@@ -744,21 +968,23 @@ pub async fn update_policy(
             .as_deref()
             .and_then(|s| serde_json::from_str(s).ok())
     };
-    validate_definition(final_policy_type, &final_definition)
-        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    // Template FK: 3-state — absent=no change, null=detach, uuid=attach
+    let final_template_id = match body.template_id {
+        Some(t) => t,
+        None => p.template_id,
+    };
+    let template = validate_policy_content(
+        &state.db,
+        final_policy_type,
+        &final_targets,
+        &final_definition,
+        final_template_id,
+    )
+    .await?;
 
     // Validate decision_function_id if changing
     if let Some(Some(df_id)) = body.decision_function_id {
-        decision_function::Entity::find_by_id(df_id)
-            .one(&state.db)
-            .await
-            .map_err(ApiErr::internal)?
-            .ok_or_else(|| {
-                ApiErr::new(
-                    StatusCode::UNPROCESSABLE_ENTITY,
-                    "decision_function_id references a non-existent decision function",
-                )
-            })?;
+        find_decision_function(&state.db, df_id).await?;
     }
 
     let now = Utc::now().naive_utc();
@@ -792,6 +1018,14 @@ pub async fn update_policy(
         changes_after.insert("is_enabled".into(), serde_json::json!(enabled));
         active.is_enabled = Set(enabled);
     }
+    if let Some(status) = body.action_status {
+        changes_before.insert("action_status".into(), serde_json::json!(p.action_status));
+        changes_after.insert(
+            "action_status".into(),
+            serde_json::json!(status.to_string()),
+        );
+        active.action_status = Set(status.to_string());
+    }
     if let Some(ref targets) = body.targets {
         changes_before.insert("targets".into(), serde_json::json!(p.targets));
         changes_after.insert("targets".into(), serde_json::json!(targets));
@@ -801,14 +1035,24 @@ pub async fn update_policy(
     // Definition is type-driven: clear it for types that don't use one so that a type
     // change never leaves a stale filter_expression / mask_expression in the DB.
     match final_policy_type {
-        PolicyType::RowFilter | PolicyType::ColumnMask => {
+        PolicyType::RowFilter
+        | PolicyType::ColumnMask
+        | PolicyType::AggregateOnly
+        | PolicyType::DifferentialPrivacy
+        | PolicyType::ResultLimit
+        | PolicyType::QueryGuard
+        | PolicyType::WriteAllow => {
             if let Some(ref definition) = body.definition {
                 changes_after.insert("definition_changed".into(), serde_json::json!(true));
                 let json = serde_json::to_string(definition).map_err(ApiErr::internal)?;
                 active.definition = Set(Some(json));
             }
         }
-        PolicyType::ColumnAllow | PolicyType::ColumnDeny | PolicyType::TableDeny => {
+        PolicyType::ColumnAllow
+        | PolicyType::ColumnDeny
+        | PolicyType::TableDeny
+        | PolicyType::JoinOnly
+        | PolicyType::WriteDeny => {
             active.definition = Set(None);
         }
     }
@@ -824,6 +1068,25 @@ pub async fn update_policy(
         );
         active.decision_function_id = Set(df_id_val);
     }
+    if let Some(template_id_val) = body.template_id {
+        changes_before.insert(
+            "template_id".into(),
+            serde_json::json!(p.template_id.map(|id| id.to_string())),
+        );
+        changes_after.insert(
+            "template_id".into(),
+            serde_json::json!(template_id_val.map(|id| id.to_string())),
+        );
+        active.template_id = Set(template_id_val);
+    }
+    if let Some(ref purpose) = body.purpose {
+        changes_before.insert("purpose".into(), serde_json::json!(p.purpose));
+        changes_after.insert("purpose".into(), serde_json::json!(purpose));
+        active.purpose = Set(purpose.clone());
+    }
+    if let Some(t) = &template {
+        changes_after.insert("template_version".into(), serde_json::json!(t.version));
+    }
     changes_before.insert("version".into(), serde_json::json!(p.version));
     changes_after.insert("version".into(), serde_json::json!(new_version));
     active.version = Set(new_version);
@@ -901,8 +1164,16 @@ pub async fn update_policy(
         None
     };
 
+    let template_sum = load_template_summaries(
+        &state.db,
+        &updated.template_id.into_iter().collect::<Vec<_>>(),
+    )
+    .await?
+    .into_values()
+    .next();
+
     let asgn_count = assignments.len();
-    let mut resp = policy_response_basic(&updated, asgn_count, df_sum);
+    let mut resp = policy_response_basic(&updated, asgn_count, df_sum, template_sum);
     resp.assignments = Some(
         assignments
             .iter()
@@ -958,6 +1229,7 @@ pub async fn delete_policy(
                 "description": &p.description,
                 "policy_type": &p.policy_type,
                 "is_enabled": p.is_enabled,
+                "action_status": &p.action_status,
                 "version": p.version,
                 "decision_function_id": p.decision_function_id.map(|id| id.to_string()),
             }
@@ -1044,70 +1316,10 @@ pub async fn assign_policy(
         .map_err(ApiErr::internal)?
         .ok_or_else(|| ApiErr::not_found("Policy not found"))?;
 
-    // Infer scope if not provided
-    let scope = match body.scope.as_deref() {
-        Some(s) => s.to_string(),
-        None => {
-            if body.role_id.is_some() {
-                "role".to_string()
-            } else if body.user_id.is_some() {
-                "user".to_string()
-            } else {
-                "all".to_string()
-            }
-        }
-    };
-
-    // Validate scope/field constraints
-    match scope.as_str() {
-        "user" => {
-            if body.user_id.is_none() {
-                return Err(ApiErr::new(
-                    StatusCode::BAD_REQUEST,
-                    "scope 'user' requires user_id",
-                ));
-            }
-            if body.role_id.is_some() {
-                return Err(ApiErr::new(
-                    StatusCode::BAD_REQUEST,
-                    "scope 'user' must not have role_id",
-                ));
-            }
-        }
-        "role" => {
-            if body.role_id.is_none() {
-                return Err(ApiErr::new(
-                    StatusCode::BAD_REQUEST,
-                    "scope 'role' requires role_id",
-                ));
-            }
-            if body.user_id.is_some() {
-                return Err(ApiErr::new(
-                    StatusCode::BAD_REQUEST,
-                    "scope 'role' must not have user_id",
-                ));
-            }
-            // Validate role exists
-            role::Entity::find_by_id(body.role_id.unwrap())
-                .one(&state.db)
-                .await
-                .map_err(ApiErr::internal)?
-                .ok_or_else(|| ApiErr::not_found("Role not found"))?;
-        }
-        "all" => {
-            if body.user_id.is_some() || body.role_id.is_some() {
-                return Err(ApiErr::new(
-                    StatusCode::BAD_REQUEST,
-                    "scope 'all' must not have user_id or role_id",
-                ));
-            }
-        }
-        _ => {
-            return Err(ApiErr::new(
-                StatusCode::BAD_REQUEST,
-                "scope must be 'user', 'role', or 'all'",
-            ));
-        }
+    let scope =
+        assignment_scope(&state.db, body.scope.as_deref(), body.user_id, body.role_id).await?;
+    if let Some(ref purpose) = body.purpose {
+        ensure_purpose(&state.db, ds_id, purpose).await?;
     }
 
     // Duplicate check for scope='all' (SQLite NULL != NULL in unique indexes)
@@ -1139,6 +1351,8 @@ pub async fn assign_policy(
         role_id: Set(body.role_id),
         assignment_scope: Set(scope.clone()),
         priority: Set(body.priority),
+        action_status: Set(body.action_status.to_string()),
+        purpose: Set(body.purpose.clone()),
         created_at: Set(now),
         updated_at: Set(now),
     }
@@ -1189,6 +1403,8 @@ pub async fn assign_policy(
             "user_id": body.user_id.map(|id| id.to_string()),
             "role_id": body.role_id.map(|id| id.to_string()),
             "priority": body.priority,
+            "action_status": body.action_status.to_string(),
+            "purpose": &body.purpose,
         }),
     );
 
@@ -1237,6 +1453,132 @@ pub async fn assign_policy(
     ))
 }
 
+// ---------- PUT /datasources/{id}/policies/{assignment_id} ----------
+
+pub async fn update_assignment(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path((ds_id, assignment_id)): Path<(Uuid, Uuid)>,
+    Json(body): Json<UpdateAssignmentRequest>,
+) -> Result<Json<PolicyAssignmentResponse>, ApiErr> {
+    let ds = data_source::Entity::find_by_id(ds_id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Data source not found"))?;
+
+    let assignment = policy_assignment::Entity::find_by_id(assignment_id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .filter(|a| a.data_source_id == ds_id)
+        .ok_or_else(|| ApiErr::not_found("Assignment not found"))?;
+
+    let p = policy::Entity::find_by_id(assignment.policy_id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Policy not found"))?;
+
+    let mut changes_before = serde_json::Map::new();
+    let mut changes_after = serde_json::Map::new();
+    let now = Utc::now().naive_utc();
+    let mut active: policy_assignment::ActiveModel = assignment.clone().into();
+    if let Some(priority) = body.priority {
+        changes_before.insert("priority".into(), serde_json::json!(assignment.priority));
+        changes_after.insert("priority".into(), serde_json::json!(priority));
+        active.priority = Set(priority);
+    }
+    if let Some(status) = body.action_status {
+        changes_before.insert(
+            "action_status".into(),
+            serde_json::json!(assignment.action_status),
+        );
+        changes_after.insert(
+            "action_status".into(),
+            serde_json::json!(status.to_string()),
+        );
+        active.action_status = Set(status.to_string());
+    }
+    if let Some(ref purpose) = body.purpose {
+        if let Some(name) = purpose {
+            ensure_purpose(&state.db, ds_id, name).await?;
+        }
+        changes_before.insert("purpose".into(), serde_json::json!(assignment.purpose));
+        changes_after.insert("purpose".into(), serde_json::json!(purpose));
+        active.purpose = Set(purpose.clone());
+    }
+    active.updated_at = Set(now);
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let updated = active.update(&*txn).await.map_err(ApiErr::internal)?;
+
+    let new_version = p.version + 1;
+    let mut policy_active: policy::ActiveModel = p.clone().into();
+    policy_active.version = Set(new_version);
+    policy_active.updated_by = Set(claims.sub);
+    policy_active.updated_at = Set(now);
+    let updated_p = policy_active
+        .update(&*txn)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let all_assignments = policy_assignment::Entity::find()
+        .filter(policy_assignment::Column::PolicyId.eq(p.id))
+        .all(&*txn)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    create_snapshot(
+        &*txn,
+        p.id,
+        new_version,
+        claims.sub,
+        "assignment_change",
+        &updated_p,
+        &all_assignments,
+    )
+    .await?;
+
+    txn.audit(
+        "policy",
+        p.id,
+        AuditAction::Update,
+        claims.sub,
+        serde_json::json!({
+            "assignment_id": assignment_id.to_string(),
+            "datasource_id": ds_id.to_string(),
+            "before": changes_before,
+            "after": changes_after,
+        }),
+    );
+
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    if let Some(hook) = &state.policy_hook {
+        hook.invalidate_datasource(&ds.name).await;
+    }
+    if let Some(ph) = &state.proxy_handler {
+        ph.rebuild_contexts_for_datasource(&ds.name);
+    }
+
+    let policy_names: HashMap<Uuid, String> = [(p.id, p.name.clone())].into_iter().collect();
+    let ds_names: HashMap<Uuid, String> = [(ds_id, ds.name.clone())].into_iter().collect();
+    let user_names = fetch_user_names(&state.db, updated.user_id.into_iter().collect()).await?;
+    let role_names = fetch_role_names(&state.db, updated.role_id.into_iter().collect()).await?;
+
+    Ok(Json(assignment_response(
+        &updated,
+        &policy_names,
+        &ds_names,
+        &user_names,
+        &role_names,
+    )))
+}
+
 // ---------- DELETE /datasources/{id}/policies/{assignment_id} ----------
 
 pub async fn remove_assignment(
@@ -1367,6 +1709,198 @@ pub async fn get_effective_policies(
     Ok(Json(entries))
 }
 
+// ---------- GET /datasources/{id}/policy-analysis ----------
+
+pub async fn get_policy_analysis(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Path(ds_id): Path<Uuid>,
+) -> Result<Json<policy_analysis::AnalysisReport>, ApiErr> {
+    let report = policy_analysis::analyze_datasource(&state.db, ds_id)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Data source not found"))?;
+
+    Ok(Json(report))
+}
+
+// ---------- POST /datasources/{id}/policy-simulation ----------
+
+/// Query audit entries replayed when the request does not say.
+const DEFAULT_SIMULATION_SAMPLE: u64 = 200;
+
+/// Upper bound on `sample_size`; each replayed query is planned twice.
+const MAX_SIMULATION_SAMPLE: u64 = 1000;
+
+/// What-if analysis: how a proposed policy edit, new policy, or new assignment
+/// would have changed recent queries on the datasource. Plans only — see
+/// [`policy_simulation`].
+pub async fn simulate_policy_change(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Path(ds_id): Path<Uuid>,
+    Json(body): Json<SimulatePolicyChangeRequest>,
+) -> Result<Json<policy_simulation::SimulationReport>, ApiErr> {
+    let ds = data_source::Entity::find_by_id(ds_id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Data source not found"))?;
+
+    let existing = match body.policy_id {
+        Some(id) => Some(
+            policy::Entity::find_by_id(id)
+                .one(&state.db)
+                .await
+                .map_err(ApiErr::internal)?
+                .ok_or_else(|| ApiErr::not_found("Policy not found"))?,
+        ),
+        None => None,
+    };
+    if existing.is_none() && body.assignment.is_none() {
+        return Err(ApiErr::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "A new policy needs an assignment to affect any query",
+        ));
+    }
+
+    let proposal = body.policy;
+    if let Some(ref name) = proposal.name {
+        validate_policy_name(name).map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
+    if let Some(Some(ref purpose)) = proposal.purpose {
+        validate_purpose_name(purpose)
+            .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
+    let policy_type = match (proposal.policy_type, &existing) {
+        (Some(pt), _) => pt,
+        (None, Some(p)) => p.policy_type.parse().unwrap_or(PolicyType::RowFilter),
+        (None, None) => {
+            return Err(ApiErr::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "policy_type is required for a new policy",
+            ));
+        }
+    };
+    let targets: Vec<TargetEntry> = match (proposal.targets, &existing) {
+        (Some(t), _) => t,
+        (None, Some(p)) => serde_json::from_str(&p.targets).unwrap_or_default(),
+        (None, None) => {
+            return Err(ApiErr::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "targets is required for a new policy",
+            ));
+        }
+    };
+    let definition: Option<serde_json::Value> = match (proposal.definition, &existing) {
+        (Some(d), _) => Some(d),
+        (None, Some(p)) => p
+            .definition
+            .as_deref()
+            .and_then(|s| serde_json::from_str(s).ok()),
+        (None, None) => None,
+    };
+    let template_id = match proposal.template_id {
+        Some(t) => t,
+        None => existing.as_ref().and_then(|p| p.template_id),
+    };
+    let decision_function_id = match proposal.decision_function_id {
+        Some(d) => d,
+        None => existing.as_ref().and_then(|p| p.decision_function_id),
+    };
+    validate_policy_content(&state.db, policy_type, &targets, &definition, template_id).await?;
+    if let Some(df_id) = decision_function_id {
+        find_decision_function(&state.db, df_id).await?;
+    }
+
+    let now = Utc::now().naive_utc();
+    let policy = policy::Model {
+        id: existing.as_ref().map_or_else(Uuid::now_v7, |p| p.id),
+        name: proposal
+            .name
+            .or_else(|| existing.as_ref().map(|p| p.name.clone()))
+            .unwrap_or_else(|| "proposed policy".to_string()),
+        description: proposal
+            .description
+            .or_else(|| existing.as_ref().and_then(|p| p.description.clone())),
+        policy_type: policy_type.to_string(),
+        targets: serde_json::to_string(&targets).map_err(ApiErr::internal)?,
+        definition: definition
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(ApiErr::internal)?,
+        is_enabled: proposal
+            .is_enabled
+            .or_else(|| existing.as_ref().map(|p| p.is_enabled))
+            .unwrap_or(true),
+        action_status: match (proposal.action_status, &existing) {
+            (Some(status), _) => status.to_string(),
+            (None, Some(p)) => p.action_status.clone(),
+            (None, None) => ActionStatus::default().to_string(),
+        },
+        version: existing.as_ref().map_or(1, |p| p.version),
+        decision_function_id,
+        template_id,
+        purpose: match proposal.purpose {
+            Some(purpose) => purpose,
+            None => existing.as_ref().and_then(|p| p.purpose.clone()),
+        },
+        created_by: existing.as_ref().map_or(Uuid::nil(), |p| p.created_by),
+        updated_by: existing.as_ref().map_or(Uuid::nil(), |p| p.updated_by),
+        created_at: existing.as_ref().map_or(now, |p| p.created_at),
+        updated_at: now,
+    };
+
+    if let Some(purpose) = body.assignment.as_ref().and_then(|a| a.purpose.as_deref()) {
+        ensure_purpose(&state.db, ds_id, purpose).await?;
+    }
+    let assignment = match body.assignment {
+        Some(a) => Some(policy_assignment::Model {
+            id: Uuid::now_v7(),
+            policy_id: policy.id,
+            data_source_id: ds_id,
+            user_id: a.user_id,
+            role_id: a.role_id,
+            assignment_scope: assignment_scope(&state.db, a.scope.as_deref(), a.user_id, a.role_id)
+                .await?,
+            priority: a.priority,
+            action_status: a.action_status.to_string(),
+            purpose: a.purpose,
+            created_at: now,
+            updated_at: now,
+        }),
+        None => None,
+    };
+
+    let sample_size = body
+        .sample_size
+        .unwrap_or(DEFAULT_SIMULATION_SAMPLE)
+        .clamp(1, MAX_SIMULATION_SAMPLE);
+
+    let policy_hook = state.policy_hook.as_deref().ok_or_else(|| {
+        ApiErr::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Policy simulation is not available on this server",
+        )
+    })?;
+
+    let change = policy_simulation::PolicyChange { policy, assignment };
+    let report = policy_simulation::simulate_datasource(
+        &state.db,
+        &state.engine_cache,
+        policy_hook,
+        &ds,
+        &change,
+        sample_size,
+        body.since,
+    )
+    .await
+    .map_err(ApiErr::internal)?;
+
+    Ok(Json(report))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1380,7 +1914,7 @@ mod tests {
         Router,
         body::Body,
         http::{Method, Request, StatusCode},
-        routing::{delete, get},
+        routing::{get, put},
     };
     use chrono::Utc;
     use migration::MigratorTrait as _;
@@ -1419,6 +1953,7 @@ mod tests {
             policy_hook: None,
             proxy_handler: None,
             wasm_runtime,
+            query_api: Arc::new(crate::admin::query_handlers::QueryApi::default()),
         }
     }
 
@@ -1461,6 +1996,8 @@ mod tests {
             secure_config: Set(String::new()),
             is_active: Set(true),
             access_mode: Set("open".to_string()),
+            policy_combining: Set(None),
+            write_mode: Set("read_only".to_string()),
             last_sync_at: Set(None),
             last_sync_result: Set(None),
             created_at: Set(now),
@@ -1484,7 +2021,15 @@ mod tests {
             )
             .route(
                 "/datasources/{id}/policies/{assignment_id}",
-                delete(remove_assignment),
+                put(update_assignment).delete(remove_assignment),
+            )
+            .route(
+                "/datasources/{id}/policy-analysis",
+                get(get_policy_analysis),
+            )
+            .route(
+                "/datasources/{id}/policy-simulation",
+                axum::routing::post(simulate_policy_change),
             )
             .with_state(state)
     }
@@ -1896,6 +2441,69 @@ mod tests {
         assert_eq!(list.as_array().unwrap().len(), 1);
     }
 
+    #[tokio::test]
+    async fn assignment_purpose_must_be_in_the_catalog() {
+        let db = setup_db().await;
+        let admin_id = Uuid::now_v7();
+        let ds_id = Uuid::now_v7();
+        insert_user(&db, admin_id, "admin").await;
+        insert_datasource(&db, ds_id, "my-ds").await;
+        let token = admin_token(admin_id);
+
+        let create_res = make_router(make_state(db.clone()))
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/policies")
+                    .header("Authorization", format!("Bearer {token}"))
+                    .header("Content-Type", "application/json")
+                    .body(json_body(row_filter_payload("fraud-lens")))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let policy_id = body_json(create_res).await["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let assign = |db: DatabaseConnection| {
+            make_router(make_state(db)).oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(format!("/datasources/{ds_id}/policies"))
+                    .header("Authorization", format!("Bearer {token}"))
+                    .header("Content-Type", "application/json")
+                    .body(json_body(serde_json::json!({
+                        "policy_id": policy_id,
+                        "purpose": "fraud_investigation",
+                    })))
+                    .unwrap(),
+            )
+        };
+        let res = assign(db.clone()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let now = Utc::now().naive_utc();
+        data_source_purpose::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            data_source_id: Set(ds_id),
+            name: Set("fraud_investigation".to_string()),
+            description: Set(None),
+            require_justification: Set(false),
+            justification_validator: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        let res = assign(db).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(body_json(res).await["purpose"], "fraud_investigation");
+    }
+
     #[tokio::test]
     async fn remove_assignment_returns_204() {
         let db = setup_db().await;
@@ -2296,4 +2904,143 @@ mod tests {
         assert_eq!(changes["datasource_id"], ds_id.to_string());
         assert_eq!(changes["scope"], "all");
     }
+
+    // ===== Policy analysis =====
+
+    #[tokio::test]
+    async fn policy_analysis_reports_dead_policy() {
+        let db = setup_db().await;
+        let admin_id = Uuid::now_v7();
+        let ds_id = Uuid::now_v7();
+        insert_user(&db, admin_id, "admin").await;
+        insert_datasource(&db, ds_id, "my-ds").await;
+        let token = admin_token(admin_id);
+
+        let create_res = make_router(make_state(db.clone()))
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/policies")
+                    .header("Authorization", format!("Bearer {token}"))
+                    .header("Content-Type", "application/json")
+                    .body(json_body(row_filter_payload("orphan")))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let policy_id = body_json(create_res).await["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        make_router(make_state(db.clone()))
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(format!("/datasources/{ds_id}/policies"))
+                    .header("Authorization", format!("Bearer {token}"))
+                    .header("Content-Type", "application/json")
+                    .body(json_body(serde_json::json!({ "policy_id": policy_id })))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // No catalog has been discovered, so the policy's table does not exist.
+        let res = make_router(make_state(db.clone()))
+            .oneshot(
+                Request::builder()
+                    .method(Method::GET)
+                    .uri(format!("/datasources/{ds_id}/policy-analysis"))
+                    .header("Authorization", format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let report = body_json(res).await;
+        assert_eq!(report["datasource_name"], "my-ds");
+        let findings = report["findings"].as_array().unwrap();
+        assert_eq!(findings.len(), 1, "{findings:?}");
+        assert_eq!(findings[0]["kind"], "dead_policy");
+        assert_eq!(findings[0]["severity"], "warning");
+        assert_eq!(findings[0]["policies"][0]["name"], "orphan");
+
+        let missing = make_router(make_state(db))
+            .oneshot(
+                Request::builder()
+                    .method(Method::GET)
+                    .uri(format!("/datasources/{}/policy-analysis", Uuid::now_v7()))
+                    .header("Authorization", format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn policy_simulation_validates_the_proposal() {
+        let db = setup_db().await;
+        let admin_id = Uuid::now_v7();
+        let ds_id = Uuid::now_v7();
+        insert_user(&db, admin_id, "admin").await;
+        insert_datasource(&db, ds_id, "my-ds").await;
+        let token = admin_token(admin_id);
+
+        let simulate = |body: serde_json::Value| {
+            let db = db.clone();
+            let token = token.clone();
+            async move {
+                let res = make_router(make_state(db))
+                    .oneshot(
+                        Request::builder()
+                            .method(Method::POST)
+                            .uri(format!("/datasources/{ds_id}/policy-simulation"))
+                            .header("Authorization", format!("Bearer {token}"))
+                            .header("Content-Type", "application/json")
+                            .body(json_body(body))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = res.status();
+                (status, body_json(res).await)
+            }
+        };
+
+        // A new policy changes nothing until it is assigned.
+        let (status, body) =
+            simulate(serde_json::json!({ "policy": row_filter_payload("new") })).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+        let (status, body) = simulate(serde_json::json!({
+            "policy": { "targets": row_filter_payload("new")["targets"] },
+            "assignment": {},
+        }))
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+        assert!(
+            body["error"].as_str().unwrap().contains("policy_type"),
+            "{body}"
+        );
+
+        let mut invalid = row_filter_payload("new");
+        invalid["definition"] = serde_json::json!({});
+        let (status, _) =
+            simulate(serde_json::json!({ "policy": invalid, "assignment": {} })).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, _) = simulate(serde_json::json!({ "policy_id": Uuid::now_v7() })).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // A valid proposal reaches the replay, which needs the proxy's policy hook.
+        let (status, _) = simulate(serde_json::json!({
+            "policy": row_filter_payload("new"),
+            "assignment": { "scope": "all" },
+        }))
+        .await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
 }
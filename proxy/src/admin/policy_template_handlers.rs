@@ -0,0 +1,439 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+use uuid::Uuid;
+
+use crate::entity::{data_source, policy, policy_assignment, policy_template};
+use crate::policy_match::PolicyType;
+use crate::policy_template::{parse_parameters, validate_template};
+
+use super::{
+    AdminState, ApiErr,
+    admin_audit::{AuditAction, AuditedTxn},
+    dto::{
+        CreatePolicyTemplateRequest, ListPolicyTemplatesQuery, PaginatedResponse,
+        PolicyTemplateResponse, PolicyTemplateSummary, UpdatePolicyTemplateRequest,
+        validate_policy_name, validate_template_definition,
+    },
+    jwt::AdminClaims,
+    policy_handlers::create_snapshot,
+};
+
+// ---------- helpers ----------
+
+async fn template_response(
+    db: &impl sea_orm::ConnectionTrait,
+    t: &policy_template::Model,
+) -> Result<PolicyTemplateResponse, ApiErr> {
+    let policy_count = policy::Entity::find()
+        .filter(policy::Column::TemplateId.eq(Some(t.id)))
+        .count(db)
+        .await
+        .map_err(ApiErr::internal)? as usize;
+
+    Ok(PolicyTemplateResponse {
+        id: t.id,
+        name: t.name.clone(),
+        description: t.description.clone(),
+        template_type: t.template_type.clone(),
+        expression: t.expression.clone(),
+        parameters: parse_parameters(&t.parameters),
+        version: t.version,
+        policy_count,
+        created_by: t.created_by,
+        updated_by: t.updated_by,
+        created_at: t.created_at,
+        updated_at: t.updated_at,
+    })
+}
+
+pub fn template_summary(t: &policy_template::Model) -> PolicyTemplateSummary {
+    PolicyTemplateSummary {
+        id: t.id,
+        name: t.name.clone(),
+        template_type: t.template_type.clone(),
+        version: t.version,
+    }
+}
+
+/// Invalidate caches for all datasources that have any of these policies assigned.
+async fn invalidate_for_policies(state: &AdminState, policy_ids: Vec<Uuid>) {
+    if policy_ids.is_empty() {
+        return;
+    }
+    let assignments = match policy_assignment::Entity::find()
+        .filter(policy_assignment::Column::PolicyId.is_in(policy_ids))
+        .all(&state.db)
+        .await
+    {
+        Ok(a) => a,
+        Err(_) => return,
+    };
+
+    let ds_ids: std::collections::HashSet<Uuid> =
+        assignments.iter().map(|a| a.data_source_id).collect();
+    for ds_id in ds_ids {
+        if let Ok(Some(ds)) = data_source::Entity::find_by_id(ds_id).one(&state.db).await {
+            if let Some(hook) = &state.policy_hook {
+                hook.invalidate_datasource(&ds.name).await;
+            }
+            if let Some(ph) = &state.proxy_handler {
+                ph.rebuild_contexts_for_datasource(&ds.name);
+            }
+        }
+    }
+}
+
+fn unique_name_err(e: sea_orm::DbErr) -> ApiErr {
+    let msg = e.to_string();
+    if msg.contains("UNIQUE") || msg.contains("unique") {
+        ApiErr::conflict("Policy template name already exists")
+    } else {
+        ApiErr::internal(e)
+    }
+}
+
+// ---------- GET /policy-templates ----------
+
+pub async fn list_policy_templates(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Query(params): Query<ListPolicyTemplatesQuery>,
+) -> Result<Json<PaginatedResponse<PolicyTemplateResponse>>, ApiErr> {
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(20).clamp(1, 100);
+
+    let mut query = policy_template::Entity::find();
+    if let Some(ref search) = params.search {
+        query = query.filter(policy_template::Column::Name.contains(search));
+    }
+
+    let total = query
+        .clone()
+        .count(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let items = query
+        .order_by_asc(policy_template::Column::Name)
+        .offset((page - 1) * page_size)
+        .limit(page_size)
+        .all(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let mut data = Vec::with_capacity(items.len());
+    for t in &items {
+        data.push(template_response(&state.db, t).await?);
+    }
+
+    Ok(Json(PaginatedResponse {
+        data,
+        total,
+        page,
+        page_size,
+    }))
+}
+
+// ---------- POST /policy-templates ----------
+
+pub async fn create_policy_template(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Json(body): Json<CreatePolicyTemplateRequest>,
+) -> Result<(StatusCode, Json<PolicyTemplateResponse>), ApiErr> {
+    validate_policy_name(&body.name)
+        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    validate_template(body.template_type, &body.expression, &body.parameters)
+        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    let now = Utc::now().naive_utc();
+    let template_id = Uuid::now_v7();
+    let parameters_json = serde_json::to_string(&body.parameters).map_err(ApiErr::internal)?;
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let model = policy_template::ActiveModel {
+        id: Set(template_id),
+        name: Set(body.name.clone()),
+        description: Set(body.description.clone()),
+        template_type: Set(body.template_type.to_string()),
+        expression: Set(body.expression.clone()),
+        parameters: Set(parameters_json),
+        version: Set(1),
+        created_by: Set(claims.sub),
+        updated_by: Set(claims.sub),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&*txn)
+    .await
+    .map_err(unique_name_err)?;
+
+    txn.audit(
+        "policy_template",
+        template_id,
+        AuditAction::Create,
+        claims.sub,
+        serde_json::json!({
+            "after": {
+                "name": model.name,
+                "description": model.description,
+                "template_type": model.template_type,
+                "expression": model.expression,
+                "parameters": &body.parameters,
+                "version": model.version,
+            }
+        }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    let resp = template_response(&state.db, &model).await?;
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+// ---------- GET /policy-templates/{id} ----------
+
+pub async fn get_policy_template(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PolicyTemplateResponse>, ApiErr> {
+    let t = policy_template::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Policy template not found"))?;
+
+    Ok(Json(template_response(&state.db, &t).await?))
+}
+
+// ---------- PUT /policy-templates/{id} ----------
+//
+// A new template version changes the expression of every policy built on it:
+// each dependent policy gets a new version (and policy_version snapshot) in the
+// same transaction, and their datasources' sessions are invalidated.
+
+pub async fn update_policy_template(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<UpdatePolicyTemplateRequest>,
+) -> Result<Json<PolicyTemplateResponse>, ApiErr> {
+    let t = policy_template::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Policy template not found"))?;
+
+    if t.version != body.version {
+        return Err(ApiErr::conflict(format!(
+            "Version conflict: expected {}, got {}",
+            t.version, body.version
+        )));
+    }
+
+    if let Some(ref name) = body.name {
+        validate_policy_name(name).map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
+
+    let template_type = t
+        .template_type
+        .parse::<PolicyType>()
+        .map_err(ApiErr::internal)?;
+    let final_expression = body.expression.as_deref().unwrap_or(&t.expression);
+    let final_parameters = match &body.parameters {
+        Some(p) => p.clone(),
+        None => parse_parameters(&t.parameters),
+    };
+    validate_template(template_type, final_expression, &final_parameters)
+        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    let now = Utc::now().naive_utc();
+    let new_version = t.version + 1;
+    let parameters_json = serde_json::to_string(&final_parameters).map_err(ApiErr::internal)?;
+
+    let mut changes_before = serde_json::Map::new();
+    let mut changes_after = serde_json::Map::new();
+
+    let mut active: policy_template::ActiveModel = t.clone().into();
+    if let Some(ref name) = body.name {
+        changes_before.insert("name".into(), serde_json::json!(t.name));
+        changes_after.insert("name".into(), serde_json::json!(name));
+        active.name = Set(name.clone());
+    }
+    if let Some(ref desc) = body.description {
+        changes_before.insert("description".into(), serde_json::json!(t.description));
+        changes_after.insert("description".into(), serde_json::json!(desc));
+        active.description = Set(Some(desc.clone()));
+    }
+    if let Some(ref expression) = body.expression {
+        changes_before.insert("expression".into(), serde_json::json!(t.expression));
+        changes_after.insert("expression".into(), serde_json::json!(expression));
+        active.expression = Set(expression.clone());
+    }
+    if body.parameters.is_some() {
+        changes_before.insert(
+            "parameters".into(),
+            serde_json::json!(parse_parameters(&t.parameters)),
+        );
+        changes_after.insert("parameters".into(), serde_json::json!(final_parameters));
+        active.parameters = Set(parameters_json);
+    }
+    active.version = Set(new_version);
+    active.updated_by = Set(claims.sub);
+    active.updated_at = Set(now);
+
+    // Every dependent policy must still bind against the new version.
+    let dependents = policy::Entity::find()
+        .filter(policy::Column::TemplateId.eq(Some(id)))
+        .all(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    let candidate = policy_template::Model {
+        expression: final_expression.to_string(),
+        parameters: serde_json::to_string(&final_parameters).map_err(ApiErr::internal)?,
+        version: new_version,
+        ..t.clone()
+    };
+    for p in &dependents {
+        let policy_type = p
+            .policy_type
+            .parse::<PolicyType>()
+            .map_err(ApiErr::internal)?;
+        let definition: Option<serde_json::Value> = p
+            .definition
+            .as_deref()
+            .and_then(|s| serde_json::from_str(s).ok());
+        validate_template_definition(policy_type, &definition, &candidate).map_err(|e| {
+            ApiErr::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Policy '{}' would no longer be valid: {e}", p.name),
+            )
+        })?;
+    }
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let updated = active.update(&*txn).await.map_err(unique_name_err)?;
+
+    changes_before.insert("version".into(), serde_json::json!(t.version));
+    changes_after.insert("version".into(), serde_json::json!(new_version));
+    txn.audit(
+        "policy_template",
+        id,
+        AuditAction::Update,
+        claims.sub,
+        serde_json::json!({ "before": changes_before, "after": changes_after }),
+    );
+
+    for p in &dependents {
+        let policy_version = p.version + 1;
+        let mut pa: policy::ActiveModel = p.clone().into();
+        pa.version = Set(policy_version);
+        pa.updated_by = Set(claims.sub);
+        pa.updated_at = Set(now);
+        let bumped = pa.update(&*txn).await.map_err(ApiErr::internal)?;
+
+        let assignments = policy_assignment::Entity::find()
+            .filter(policy_assignment::Column::PolicyId.eq(p.id))
+            .all(&*txn)
+            .await
+            .map_err(ApiErr::internal)?;
+        create_snapshot(
+            &*txn,
+            p.id,
+            policy_version,
+            claims.sub,
+            "template_update",
+            &bumped,
+            &assignments,
+        )
+        .await?;
+
+        txn.audit(
+            "policy",
+            p.id,
+            AuditAction::Update,
+            claims.sub,
+            serde_json::json!({
+                "before": { "version": p.version, "template_version": t.version },
+                "after": { "version": policy_version, "template_version": new_version },
+            }),
+        );
+    }
+
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    invalidate_for_policies(&state, dependents.iter().map(|p| p.id).collect()).await;
+
+    Ok(Json(template_response(&state.db, &updated).await?))
+}
+
+// ---------- DELETE /policy-templates/{id} ----------
+
+pub async fn delete_policy_template(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiErr> {
+    let t = policy_template::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Policy template not found"))?;
+
+    // Block deletion if any policy references it
+    let ref_count = policy::Entity::find()
+        .filter(policy::Column::TemplateId.eq(Some(id)))
+        .count(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    if ref_count > 0 {
+        return Err(ApiErr::conflict(format!(
+            "Cannot delete: {} polic{} still reference this template. Detach them first.",
+            ref_count,
+            if ref_count == 1 { "y" } else { "ies" }
+        )));
+    }
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    txn.audit(
+        "policy_template",
+        id,
+        AuditAction::Delete,
+        claims.sub,
+        serde_json::json!({
+            "before": {
+                "name": t.name,
+                "description": t.description,
+                "template_type": t.template_type,
+                "expression": t.expression,
+                "parameters": parse_parameters(&t.parameters),
+                "version": t.version,
+            }
+        }),
+    );
+
+    let active: policy_template::ActiveModel = t.into();
+    active.delete(&*txn).await.map_err(ApiErr::internal)?;
+
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,93 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use sea_orm::{ColumnTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder};
+use uuid::Uuid;
+
+use crate::entity::{data_source, privacy_budget, proxy_user};
+
+use super::{
+    AdminState, ApiErr,
+    admin_audit::{AuditAction, AuditedTxn},
+    dto::PrivacyBudgetResponse,
+    jwt::AdminClaims,
+};
+
+async fn ensure_datasource(state: &AdminState, id: Uuid) -> Result<(), ApiErr> {
+    data_source::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Data source not found"))?;
+    Ok(())
+}
+
+// ---------- GET /datasources/{id}/privacy-budgets ----------
+
+pub async fn list_privacy_budgets(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<PrivacyBudgetResponse>>, ApiErr> {
+    ensure_datasource(&state, id).await?;
+    let rows = privacy_budget::Entity::find()
+        .filter(privacy_budget::Column::DataSourceId.eq(id))
+        .order_by_desc(privacy_budget::Column::EpsilonSpent)
+        .find_also_related(proxy_user::Entity)
+        .all(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    Ok(Json(
+        rows.into_iter()
+            .map(|(b, user)| PrivacyBudgetResponse {
+                user_id: b.user_id,
+                username: user.map(|u| u.username).unwrap_or_default(),
+                epsilon_spent: b.epsilon_spent,
+                query_count: b.query_count,
+                created_at: b.created_at,
+                updated_at: b.updated_at,
+            })
+            .collect(),
+    ))
+}
+
+// ---------- DELETE /datasources/{id}/privacy-budgets/{user_id} ----------
+
+/// Reset a user's spent epsilon on a datasource.
+pub async fn reset_privacy_budget(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiErr> {
+    let row = privacy_budget::Entity::find()
+        .filter(privacy_budget::Column::DataSourceId.eq(id))
+        .filter(privacy_budget::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("No privacy budget spent by this user"))?;
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    txn.audit(
+        "privacy_budget",
+        row.id,
+        AuditAction::Delete,
+        claims.sub,
+        serde_json::json!({
+            "before": {
+                "user_id": row.user_id,
+                "data_source_id": row.data_source_id,
+                "epsilon_spent": row.epsilon_spent,
+                "query_count": row.query_count,
+            }
+        }),
+    );
+    row.delete(&*txn).await.map_err(ApiErr::internal)?;
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,254 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+};
+use uuid::Uuid;
+
+use crate::entity::{data_source, data_source_purpose, policy_assignment};
+use crate::purpose::JustificationValidator;
+
+use super::{
+    AdminState, ApiErr,
+    admin_audit::{AuditAction, AuditedTxn},
+    dto::{CreatePurposeRequest, PurposeResponse, UpdatePurposeRequest, validate_purpose_name},
+    jwt::AdminClaims,
+};
+
+// ---------- helpers ----------
+
+fn purpose_response(p: &data_source_purpose::Model) -> PurposeResponse {
+    PurposeResponse {
+        id: p.id,
+        data_source_id: p.data_source_id,
+        name: p.name.clone(),
+        description: p.description.clone(),
+        require_justification: p.require_justification,
+        justification_validator: p
+            .justification_validator
+            .as_deref()
+            .and_then(|v| serde_json::from_str(v).ok()),
+        created_at: p.created_at,
+        updated_at: p.updated_at,
+    }
+}
+
+async fn ensure_datasource(state: &AdminState, id: Uuid) -> Result<(), ApiErr> {
+    data_source::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Data source not found"))?;
+    Ok(())
+}
+
+async fn find_purpose(
+    state: &AdminState,
+    ds_id: Uuid,
+    purpose_id: Uuid,
+) -> Result<data_source_purpose::Model, ApiErr> {
+    data_source_purpose::Entity::find_by_id(purpose_id)
+        .filter(data_source_purpose::Column::DataSourceId.eq(ds_id))
+        .one(&state.db)
+        .await
+        .map_err(ApiErr::internal)?
+        .ok_or_else(|| ApiErr::not_found("Purpose not found"))
+}
+
+/// Check a validator and serialize it for storage.
+fn validator_json(validator: &JustificationValidator) -> Result<String, ApiErr> {
+    validator
+        .check()
+        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    serde_json::to_string(validator).map_err(ApiErr::internal)
+}
+
+// ---------- GET /datasources/{id}/purposes ----------
+
+pub async fn list_purposes(
+    AdminClaims(_): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<PurposeResponse>>, ApiErr> {
+    ensure_datasource(&state, id).await?;
+    let purposes = data_source_purpose::Entity::find()
+        .filter(data_source_purpose::Column::DataSourceId.eq(id))
+        .order_by_asc(data_source_purpose::Column::Name)
+        .all(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    Ok(Json(purposes.iter().map(purpose_response).collect()))
+}
+
+// ---------- POST /datasources/{id}/purposes ----------
+
+pub async fn create_purpose(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<CreatePurposeRequest>,
+) -> Result<(StatusCode, Json<PurposeResponse>), ApiErr> {
+    ensure_datasource(&state, id).await?;
+    validate_purpose_name(&body.name)
+        .map_err(|e| ApiErr::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let validator = body
+        .justification_validator
+        .as_ref()
+        .map(validator_json)
+        .transpose()?;
+
+    let now = Utc::now().naive_utc();
+    let purpose_id = Uuid::now_v7();
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+
+    let model = data_source_purpose::ActiveModel {
+        id: Set(purpose_id),
+        data_source_id: Set(id),
+        name: Set(body.name.clone()),
+        description: Set(body.description.clone()),
+        require_justification: Set(body.require_justification),
+        justification_validator: Set(validator),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&*txn)
+    .await
+    .map_err(|e| {
+        let msg = e.to_string();
+        if msg.contains("UNIQUE") || msg.contains("unique") {
+            ApiErr::conflict("Purpose name already exists on this data source")
+        } else {
+            ApiErr::internal(e)
+        }
+    })?;
+
+    txn.audit(
+        "purpose",
+        purpose_id,
+        AuditAction::Create,
+        claims.sub,
+        serde_json::json!({
+            "after": {
+                "data_source_id": id,
+                "name": model.name,
+                "description": model.description,
+                "require_justification": model.require_justification,
+                "justification_validator": body.justification_validator,
+            }
+        }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok((StatusCode::CREATED, Json(purpose_response(&model))))
+}
+
+// ---------- PUT /datasources/{id}/purposes/{purpose_id} ----------
+//
+// Sessions that already declared the purpose keep it; the new rules apply to
+// the next declaration.
+
+pub async fn update_purpose(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path((id, purpose_id)): Path<(Uuid, Uuid)>,
+    Json(body): Json<UpdatePurposeRequest>,
+) -> Result<Json<PurposeResponse>, ApiErr> {
+    let p = find_purpose(&state, id, purpose_id).await?;
+
+    let mut changes_before = serde_json::Map::new();
+    let mut changes_after = serde_json::Map::new();
+    let mut active: data_source_purpose::ActiveModel = p.clone().into();
+    if let Some(ref desc) = body.description {
+        changes_before.insert("description".into(), serde_json::json!(p.description));
+        changes_after.insert("description".into(), serde_json::json!(desc));
+        active.description = Set(Some(desc.clone()));
+    }
+    if let Some(required) = body.require_justification {
+        changes_before.insert(
+            "require_justification".into(),
+            serde_json::json!(p.require_justification),
+        );
+        changes_after.insert("require_justification".into(), serde_json::json!(required));
+        active.require_justification = Set(required);
+    }
+    if let Some(ref validator) = body.justification_validator {
+        changes_before.insert(
+            "justification_validator".into(),
+            serde_json::json!(purpose_response(&p).justification_validator),
+        );
+        changes_after.insert(
+            "justification_validator".into(),
+            serde_json::json!(validator),
+        );
+        active.justification_validator = Set(validator.as_ref().map(validator_json).transpose()?);
+    }
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    let updated = active.update(&*txn).await.map_err(ApiErr::internal)?;
+    txn.audit(
+        "purpose",
+        purpose_id,
+        AuditAction::Update,
+        claims.sub,
+        serde_json::json!({ "before": changes_before, "after": changes_after }),
+    );
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok(Json(purpose_response(&updated)))
+}
+
+// ---------- DELETE /datasources/{id}/purposes/{purpose_id} ----------
+
+pub async fn delete_purpose(
+    AdminClaims(claims): AdminClaims,
+    State(state): State<AdminState>,
+    Path((id, purpose_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiErr> {
+    let p = find_purpose(&state, id, purpose_id).await?;
+
+    // Block delete while policy assignments on this datasource are restricted to it.
+    let referencing = policy_assignment::Entity::find()
+        .filter(policy_assignment::Column::DataSourceId.eq(id))
+        .filter(policy_assignment::Column::Purpose.eq(p.name.clone()))
+        .count(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    if referencing > 0 {
+        return Err(ApiErr::conflict(format!(
+            "This purpose is used by {referencing} policy assignment(s); remove or change them first."
+        )));
+    }
+
+    let mut txn = AuditedTxn::begin(&state.db)
+        .await
+        .map_err(ApiErr::internal)?;
+    txn.audit(
+        "purpose",
+        purpose_id,
+        AuditAction::Delete,
+        claims.sub,
+        serde_json::json!({
+            "before": {
+                "data_source_id": p.data_source_id,
+                "name": p.name,
+                "description": p.description,
+                "require_justification": p.require_justification,
+            }
+        }),
+    );
+    let active: data_source_purpose::ActiveModel = p.into();
+    active.delete(&*txn).await.map_err(ApiErr::internal)?;
+    txn.commit().await.map_err(ApiErr::internal)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
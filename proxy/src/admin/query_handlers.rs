@@ -0,0 +1,819 @@
+//! HTTP query API: run SQL as a proxy user over the admin listener.
+//!
+//! Intended for scripts and AI agents that can't speak pgwire or Flight. Callers
+//! authenticate as a *proxy user* (never an admin) via [`QueryUser`] — either an
+//! API key or a token from `POST /query/token` — and every statement goes through
+//! [`PolicyHook::stream_sql`], the same enforcement and audit path as pgwire and
+//! Flight SQL.
+//!
+//! Results are capped at `max_rows` (server limit `BR_QUERY_MAX_ROWS`, which a
+//! request may only lower), materialised, and paged: the first page is returned
+//! inline and the rest is held under an opaque cursor for `GET /query/cursors/{id}`.
+//! Output is JSON (default), CSV, or Arrow IPC stream.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::Utc;
+use dashmap::DashMap;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::json::{WriterBuilder as JsonWriterBuilder, writer::JsonArray};
+use datafusion::arrow::{csv, ipc};
+use datafusion::execution::SendableRecordBatchStream;
+use futures::TryStreamExt;
+use pgwire::error::PgWireError;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::admin::dto::LoginRequest;
+use crate::admin::jwt::{QUERY_SCOPE, QueryClaims, QueryUser, encode_query_jwt};
+use crate::admin::{AdminState, ApiErr};
+use crate::auth::AuthApiError;
+use crate::entity::proxy_user;
+use crate::hooks::policy::{PolicyHook, QueryCaller};
+use crate::purpose::{Declaration, DeclarationError};
+
+/// How long an unread cursor stays redeemable.
+const CURSOR_TTL: Duration = Duration::from_secs(300);
+
+const DEFAULT_PAGE_SIZE: usize = 1000;
+
+/// `client_info` recorded in the audit log when the request sets no `application_name`.
+const DEFAULT_CLIENT_INFO: &str = "http-api";
+
+const ARROW_STREAM_CONTENT_TYPE: &str = "application/vnd.apache.arrow.stream";
+
+// ---------- state ----------
+
+/// Server-side limits and the cursor store for paged results.
+pub struct QueryApi {
+    /// Hard cap on rows materialised per query (`BR_QUERY_MAX_ROWS`).
+    pub max_rows: usize,
+    /// Hard cap on query execution time (`BR_QUERY_TIMEOUT_SECS`).
+    pub timeout: Duration,
+    cursors: DashMap<String, QueryCursor>,
+}
+
+/// The unread remainder of a query result.
+struct QueryCursor {
+    /// Only the user who ran the query may page through it.
+    user_id: Uuid,
+    format: ResultFormat,
+    page_size: usize,
+    schema: SchemaRef,
+    batches: VecDeque<RecordBatch>,
+    truncated: bool,
+    created_at: Instant,
+}
+
+impl QueryApi {
+    pub fn new(max_rows: usize, timeout: Duration) -> Self {
+        Self {
+            max_rows,
+            timeout,
+            cursors: DashMap::new(),
+        }
+    }
+
+    /// Drop cursors older than `CURSOR_TTL`. Called on every cursor write, so the
+    /// store never needs a background sweeper.
+    fn sweep_expired(&self) {
+        self.cursors
+            .retain(|_, cursor| cursor.created_at.elapsed() < CURSOR_TTL);
+    }
+}
+
+impl Default for QueryApi {
+    fn default() -> Self {
+        Self::new(10_000, Duration::from_secs(30))
+    }
+}
+
+// ---------- request / response types ----------
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultFormat {
+    #[default]
+    Json,
+    Csv,
+    Arrow,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueryRequest {
+    pub datasource: String,
+    pub sql: String,
+    #[serde(default)]
+    pub format: ResultFormat,
+    pub page_size: Option<usize>,
+    /// Lowers the server-side row cap for this request; cannot raise it.
+    pub max_rows: Option<usize>,
+    /// Lowers the server-side timeout for this request; cannot raise it.
+    pub timeout_ms: Option<u64>,
+    /// Recorded as `client_info` in the query audit log.
+    pub application_name: Option<String>,
+    /// Purpose declared for the query; must be in the datasource's purpose catalog.
+    pub purpose: Option<String>,
+    /// Justification for `purpose`, checked by the catalog entry's validator.
+    pub justification: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryTokenResponse {
+    pub token: String,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResultColumn {
+    pub name: String,
+    pub data_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryResultResponse {
+    pub columns: Vec<ResultColumn>,
+    /// One JSON object per row, keyed by column name.
+    pub rows: serde_json::Value,
+    pub row_count: usize,
+    /// `true` when the result hit `max_rows` and later rows were discarded.
+    pub truncated: bool,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DescribeSchemaQuery {
+    pub datasource: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DescribeSchemaResponse {
+    pub datasource: String,
+    pub schemas: Vec<SchemaDescription>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaDescription {
+    pub name: String,
+    pub tables: Vec<TableDescription>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableDescription {
+    pub name: String,
+    pub columns: Vec<ColumnDescription>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnDescription {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+// ---------- handlers ----------
+
+/// Exchange proxy-user credentials for a query token. Unlike `/auth/login`, any
+/// active user may call this; the token is only accepted by the query endpoints.
+pub async fn issue_query_token(
+    State(state): State<AdminState>,
+    Json(body): Json<LoginRequest>,
+) -> Result<Json<QueryTokenResponse>, ApiErr> {
+    let user = state
+        .auth
+        .authenticate_for_api(&body.username, &body.password)
+        .await
+        .map_err(|e| match e {
+            AuthApiError::Db(e) => ApiErr::internal(e),
+            _ => ApiErr::new(StatusCode::UNAUTHORIZED, "Invalid credentials"),
+        })?;
+
+    let expires_at = (Utc::now().timestamp() as u64) + state.jwt_expiry_hours * 3600;
+    let claims = QueryClaims {
+        sub: user.id,
+        username: user.username,
+        scope: QUERY_SCOPE.to_string(),
+        exp: expires_at,
+    };
+    let token = encode_query_jwt(&claims, &state.jwt_secret).map_err(ApiErr::internal)?;
+
+    Ok(Json(QueryTokenResponse { token, expires_at }))
+}
+
+pub async fn run_query(
+    QueryUser { user, .. }: QueryUser,
+    State(state): State<AdminState>,
+    Json(body): Json<QueryRequest>,
+) -> Result<Response, ApiErr> {
+    let client_info = body
+        .application_name
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_CLIENT_INFO.to_string());
+    let result = execute_capped(
+        &state,
+        &user,
+        &body.datasource,
+        &body.sql,
+        client_info,
+        body.purpose,
+        body.justification,
+        body.max_rows,
+        body.timeout_ms,
+    )
+    .await?;
+    let page_size = body
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, result.max_rows.max(1));
+
+    let mut cursor = QueryCursor {
+        user_id: user.id,
+        format: body.format,
+        page_size,
+        schema: result.schema,
+        batches: result.batches,
+        truncated: result.truncated,
+        created_at: Instant::now(),
+    };
+    render_next_page(&state.query_api, &mut cursor, None)
+}
+
+pub async fn fetch_cursor(
+    QueryUser { user, .. }: QueryUser,
+    State(state): State<AdminState>,
+    Path(cursor_id): Path<String>,
+) -> Result<Response, ApiErr> {
+    let api = &state.query_api;
+    api.sweep_expired();
+    let (_, mut cursor) = api
+        .cursors
+        .remove_if(&cursor_id, |_, c| c.user_id == user.id)
+        .ok_or_else(|| ApiErr::not_found("Cursor not found or expired"))?;
+    render_next_page(api, &mut cursor, Some(cursor_id))
+}
+
+/// The caller's catalog after visibility filtering: denied schemas, tables and
+/// columns are absent, exactly as they are to pgwire clients.
+pub async fn describe_schema(
+    QueryUser { user, .. }: QueryUser,
+    State(state): State<AdminState>,
+    Query(params): Query<DescribeSchemaQuery>,
+) -> Result<Json<DescribeSchemaResponse>, ApiErr> {
+    describe_visible_schema(&state, user.id, params.datasource)
+        .await
+        .map(Json)
+}
+
+// ---------- shared with the MCP server ----------
+
+/// A materialised, row-capped query result.
+pub struct CappedResult {
+    pub schema: SchemaRef,
+    pub batches: VecDeque<RecordBatch>,
+    /// `true` when rows past `max_rows` were dropped.
+    pub truncated: bool,
+    /// The effective row cap (request value clamped to the server limit).
+    pub max_rows: usize,
+}
+
+/// Run `sql` as `user` through `PolicyHook::stream_sql` and collect at most
+/// `max_rows` rows within `timeout_ms`. Both limits are clamped to the server's.
+/// A declared `purpose` is validated before the session is built around it.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn execute_capped(
+    state: &AdminState,
+    user: &proxy_user::Model,
+    datasource: &str,
+    sql: &str,
+    client_info: String,
+    purpose: Option<String>,
+    justification: Option<String>,
+    max_rows: Option<usize>,
+    timeout_ms: Option<u64>,
+) -> Result<CappedResult, ApiErr> {
+    let policy_hook = policy_hook(state)?;
+    let api = &state.query_api;
+
+    let max_rows = max_rows.map_or(api.max_rows, |n| n.min(api.max_rows));
+    let timeout = timeout_ms.map_or(api.timeout, |ms| Duration::from_millis(ms).min(api.timeout));
+
+    let declaration = purpose.as_deref().map(|purpose| Declaration {
+        user_id: user.id,
+        username: &user.username,
+        datasource,
+        purpose,
+        justification: justification.as_deref(),
+    });
+    let ctx = user_context(state, user.id, datasource, declaration.as_ref()).await?;
+    let caller = QueryCaller {
+        user_id: user.id,
+        username: user.username.clone(),
+        datasource: datasource.to_string(),
+        client_info: Some(client_info),
+        purpose,
+        justification,
+    };
+
+    // Planning, policy rewriting and the audit write happen inside `stream_sql`;
+    // only batch collection is bounded by the timeout so the audit row is never lost.
+    let stream = policy_hook
+        .stream_sql(sql, &ctx, &caller)
+        .await
+        .map_err(pgwire_error_to_api)?;
+    let schema = stream.schema();
+    let (batches, truncated) = tokio::time::timeout(timeout, collect_capped(stream, max_rows))
+        .await
+        .map_err(|_| {
+            ApiErr::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("Query exceeded the {} ms timeout", timeout.as_millis()),
+            )
+        })??;
+
+    Ok(CappedResult {
+        schema,
+        batches,
+        truncated,
+        max_rows,
+    })
+}
+
+/// Describe the catalog `user_id` can see on `datasource`.
+pub(crate) async fn describe_visible_schema(
+    state: &AdminState,
+    user_id: Uuid,
+    datasource: String,
+) -> Result<DescribeSchemaResponse, ApiErr> {
+    let ctx = user_context(state, user_id, &datasource, None).await?;
+    let schemas = describe_catalog(&ctx, &datasource).await?;
+    Ok(DescribeSchemaResponse {
+        datasource,
+        schemas,
+    })
+}
+
+/// Walk the `datasource` catalog registered in `ctx`, skipping the system schemas.
+pub(crate) async fn describe_catalog(
+    ctx: &datafusion::prelude::SessionContext,
+    datasource: &str,
+) -> Result<Vec<SchemaDescription>, ApiErr> {
+    let catalog = ctx
+        .catalog(datasource)
+        .ok_or_else(|| ApiErr::internal("Datasource catalog not registered"))?;
+
+    let mut schema_names = catalog.schema_names();
+    schema_names.retain(|s| s != "information_schema" && s != "pg_catalog");
+    schema_names.sort();
+
+    let mut schemas = Vec::with_capacity(schema_names.len());
+    for schema_name in schema_names {
+        let Some(provider) = catalog.schema(&schema_name) else {
+            continue;
+        };
+        let mut table_names = provider.table_names();
+        table_names.sort();
+        let mut tables = Vec::with_capacity(table_names.len());
+        for table_name in table_names {
+            let Some(table) = provider
+                .table(&table_name)
+                .await
+                .map_err(ApiErr::internal)?
+            else {
+                continue;
+            };
+            let columns = table
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| ColumnDescription {
+                    name: f.name().clone(),
+                    data_type: f.data_type().to_string(),
+                    nullable: f.is_nullable(),
+                })
+                .collect();
+            tables.push(TableDescription {
+                name: table_name,
+                columns,
+            });
+        }
+        schemas.push(SchemaDescription {
+            name: schema_name,
+            tables,
+        });
+    }
+    Ok(schemas)
+}
+
+// ---------- helpers ----------
+
+pub(crate) fn policy_hook(state: &AdminState) -> Result<&PolicyHook, ApiErr> {
+    state.policy_hook.as_deref().ok_or_else(|| {
+        ApiErr::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Query API is not available on this server",
+        )
+    })
+}
+
+/// Check datasource existence and access, then build the user's filtered `SessionContext`.
+async fn user_context(
+    state: &AdminState,
+    user_id: Uuid,
+    datasource: &str,
+    declaration: Option<&Declaration<'_>>,
+) -> Result<std::sync::Arc<datafusion::prelude::SessionContext>, ApiErr> {
+    state
+        .engine_cache
+        .validate_data_source(datasource)
+        .await
+        .map_err(|e| ApiErr::not_found(e.to_string()))?;
+    let has_access = state
+        .engine_cache
+        .check_access(user_id, datasource)
+        .await
+        .map_err(ApiErr::internal)?;
+    if !has_access {
+        return Err(ApiErr::new(
+            StatusCode::FORBIDDEN,
+            format!("Access denied to data source '{datasource}'"),
+        ));
+    }
+    if let Some(declaration) = declaration {
+        match state.engine_cache.validate_purpose(declaration).await {
+            Ok(()) => {}
+            Err(DeclarationError::Rejected(msg)) => {
+                tracing::info!(
+                    username = %declaration.username,
+                    datasource = %datasource,
+                    purpose = %declaration.purpose,
+                    reason = %msg,
+                    "Purpose declaration refused"
+                );
+                return Err(ApiErr::new(StatusCode::FORBIDDEN, msg));
+            }
+            Err(e @ DeclarationError::Internal(_)) => return Err(ApiErr::internal(e)),
+        }
+    }
+    state
+        .engine_cache
+        .build_user_context(user_id, datasource, declaration.map(|d| d.purpose))
+        .await
+        .map_err(ApiErr::internal)
+}
+
+/// Map a pgwire error from the shared enforcement path onto an HTTP status,
+/// keeping the SQLSTATE-level distinction between denials and failures.
+pub(crate) fn pgwire_error_to_api(e: PgWireError) -> ApiErr {
+    match e {
+        PgWireError::UserError(info) => {
+            let status = match info.code.as_str() {
+                "42501" | "25006" => StatusCode::FORBIDDEN,
+                code if code.starts_with("28") => StatusCode::UNAUTHORIZED,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            ApiErr::new(status, info.message)
+        }
+        other => ApiErr::new(StatusCode::BAD_REQUEST, other.to_string()),
+    }
+}
+
+/// Pull batches until the stream ends or more than `max_rows` rows were seen.
+/// Returns the (at most `max_rows`) rows kept and whether anything was dropped.
+pub(crate) async fn collect_capped(
+    mut stream: SendableRecordBatchStream,
+    max_rows: usize,
+) -> Result<(VecDeque<RecordBatch>, bool), ApiErr> {
+    let mut batches = VecDeque::new();
+    let mut rows = 0usize;
+    while let Some(batch) = stream
+        .try_next()
+        .await
+        .map_err(|e| ApiErr::new(StatusCode::BAD_REQUEST, e.to_string()))?
+    {
+        if rows + batch.num_rows() > max_rows {
+            let keep = max_rows - rows;
+            if keep > 0 {
+                batches.push_back(batch.slice(0, keep));
+            }
+            return Ok((batches, true));
+        }
+        rows += batch.num_rows();
+        batches.push_back(batch);
+    }
+    Ok((batches, false))
+}
+
+/// Remove up to `n` rows from the front of `batches`.
+fn take_rows(batches: &mut VecDeque<RecordBatch>, n: usize) -> Vec<RecordBatch> {
+    let mut page = Vec::new();
+    let mut remaining = n;
+    while remaining > 0 {
+        let Some(batch) = batches.pop_front() else {
+            break;
+        };
+        if batch.num_rows() > remaining {
+            page.push(batch.slice(0, remaining));
+            batches.push_front(batch.slice(remaining, batch.num_rows() - remaining));
+            remaining = 0;
+        } else {
+            remaining -= batch.num_rows();
+            page.push(batch);
+        }
+    }
+    page
+}
+
+fn random_cursor_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Emit the next page of `cursor`, storing the remainder (if any) under
+/// `cursor_id` (reused when paging, freshly generated for the first page).
+fn render_next_page(
+    api: &QueryApi,
+    cursor: &mut QueryCursor,
+    cursor_id: Option<String>,
+) -> Result<Response, ApiErr> {
+    let page = take_rows(&mut cursor.batches, cursor.page_size);
+    let row_count = page.iter().map(RecordBatch::num_rows).sum();
+
+    let next_cursor = if cursor.batches.is_empty() {
+        None
+    } else {
+        let id = cursor_id.unwrap_or_else(random_cursor_id);
+        api.sweep_expired();
+        api.cursors.insert(
+            id.clone(),
+            QueryCursor {
+                user_id: cursor.user_id,
+                format: cursor.format,
+                page_size: cursor.page_size,
+                schema: cursor.schema.clone(),
+                batches: std::mem::take(&mut cursor.batches),
+                truncated: cursor.truncated,
+                created_at: Instant::now(),
+            },
+        );
+        Some(id)
+    };
+
+    render_page(
+        cursor.format,
+        &cursor.schema,
+        &page,
+        row_count,
+        cursor.truncated,
+        next_cursor,
+    )
+}
+
+pub(crate) fn result_columns(schema: &SchemaRef) -> Vec<ResultColumn> {
+    schema
+        .fields()
+        .iter()
+        .map(|f| ResultColumn {
+            name: f.name().clone(),
+            data_type: f.data_type().to_string(),
+        })
+        .collect()
+}
+
+/// Encode batches as a JSON array of row objects (nulls kept explicit).
+pub(crate) fn json_rows<'a>(
+    batches: impl IntoIterator<Item = &'a RecordBatch>,
+) -> Result<serde_json::Value, ApiErr> {
+    let mut writer = JsonWriterBuilder::new()
+        .with_explicit_nulls(true)
+        .build::<_, JsonArray>(Vec::new());
+    let refs: Vec<&RecordBatch> = batches.into_iter().collect();
+    writer.write_batches(&refs).map_err(ApiErr::internal)?;
+    writer.finish().map_err(ApiErr::internal)?;
+    let buf = writer.into_inner();
+    if buf.is_empty() {
+        return Ok(serde_json::Value::Array(Vec::new()));
+    }
+    serde_json::from_slice(&buf).map_err(ApiErr::internal)
+}
+
+fn render_page(
+    format: ResultFormat,
+    schema: &SchemaRef,
+    page: &[RecordBatch],
+    row_count: usize,
+    truncated: bool,
+    next_cursor: Option<String>,
+) -> Result<Response, ApiErr> {
+    if format == ResultFormat::Json {
+        return Ok(Json(QueryResultResponse {
+            columns: result_columns(schema),
+            rows: json_rows(page)?,
+            row_count,
+            truncated,
+            next_cursor,
+        })
+        .into_response());
+    }
+
+    let (content_type, body) = match format {
+        ResultFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .with_header(true)
+                .build(Vec::new());
+            if page.is_empty() {
+                writer
+                    .write(&RecordBatch::new_empty(schema.clone()))
+                    .map_err(ApiErr::internal)?;
+            }
+            for batch in page {
+                writer.write(batch).map_err(ApiErr::internal)?;
+            }
+            ("text/csv; charset=utf-8", writer.into_inner())
+        }
+        ResultFormat::Arrow => {
+            let mut writer =
+                ipc::writer::StreamWriter::try_new(Vec::new(), schema).map_err(ApiErr::internal)?;
+            for batch in page {
+                writer.write(batch).map_err(ApiErr::internal)?;
+            }
+            writer.finish().map_err(ApiErr::internal)?;
+            (
+                ARROW_STREAM_CONTENT_TYPE,
+                writer.into_inner().map_err(ApiErr::internal)?,
+            )
+        }
+        ResultFormat::Json => unreachable!("handled above"),
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert("x-row-count", HeaderValue::from(row_count));
+    headers.insert(
+        "x-truncated",
+        HeaderValue::from_static(if truncated { "true" } else { "false" }),
+    );
+    if let Some(cursor) = next_cursor {
+        let value = HeaderValue::from_str(&cursor).map_err(ApiErr::internal)?;
+        headers.insert("x-next-cursor", value);
+    }
+    Ok((headers, body).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use std::sync::Arc;
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, true)]))
+    }
+
+    fn batch(ids: std::ops::Range<i32>) -> RecordBatch {
+        RecordBatch::try_new(schema(), vec![Arc::new(Int32Array::from_iter_values(ids))]).unwrap()
+    }
+
+    fn stream_of(batches: Vec<RecordBatch>) -> SendableRecordBatchStream {
+        Box::pin(RecordBatchStreamAdapter::new(
+            schema(),
+            futures::stream::iter(batches.into_iter().map(Ok)),
+        ))
+    }
+
+    fn ids(batches: &[RecordBatch]) -> Vec<i32> {
+        batches
+            .iter()
+            .flat_map(|b| {
+                b.column(0)
+                    .as_any()
+                    .downcast_ref::<Int32Array>()
+                    .unwrap()
+                    .values()
+                    .to_vec()
+            })
+            .collect()
+    }
+
+    fn cursor(batches: Vec<RecordBatch>, page_size: usize, format: ResultFormat) -> QueryCursor {
+        QueryCursor {
+            user_id: Uuid::nil(),
+            format,
+            page_size,
+            schema: schema(),
+            batches: batches.into(),
+            truncated: false,
+            created_at: Instant::now(),
+        }
+    }
+
+    async fn body_bytes(resp: Response) -> Vec<u8> {
+        axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn collect_capped_keeps_everything_under_the_cap() {
+        let (batches, truncated) = collect_capped(stream_of(vec![batch(0..3), batch(3..5)]), 5)
+            .await
+            .unwrap();
+        assert!(!truncated);
+        assert_eq!(ids(&Vec::from(batches)), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn collect_capped_slices_at_the_cap() {
+        let (batches, truncated) = collect_capped(stream_of(vec![batch(0..3), batch(3..6)]), 4)
+            .await
+            .unwrap();
+        assert!(truncated);
+        assert_eq!(ids(&Vec::from(batches)), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn take_rows_splits_batches_across_pages() {
+        let mut batches: VecDeque<_> = vec![batch(0..3), batch(3..5)].into();
+        assert_eq!(ids(&take_rows(&mut batches, 2)), vec![0, 1]);
+        assert_eq!(ids(&take_rows(&mut batches, 2)), vec![2, 3]);
+        assert_eq!(ids(&take_rows(&mut batches, 2)), vec![4]);
+        assert!(batches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn json_pages_chain_through_cursor_store() {
+        let api = QueryApi::default();
+        let mut first = cursor(vec![batch(0..5)], 2, ResultFormat::Json);
+        let resp = render_next_page(&api, &mut first, None).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body_bytes(resp).await).unwrap();
+        assert_eq!(body["rows"], serde_json::json!([{"id": 0}, {"id": 1}]));
+        assert_eq!(body["row_count"], 2);
+        assert_eq!(body["columns"][0]["name"], "id");
+        let next = body["next_cursor"].as_str().unwrap().to_string();
+
+        let (_, mut rest) = api.cursors.remove(&next).unwrap();
+        assert_eq!(rest.batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+        render_next_page(&api, &mut rest, Some(next.clone())).unwrap();
+        let (_, mut last) = api.cursors.remove(&next).unwrap();
+        let resp = render_next_page(&api, &mut last, Some(next)).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body_bytes(resp).await).unwrap();
+        assert_eq!(body["rows"], serde_json::json!([{"id": 4}]));
+        assert!(body["next_cursor"].is_null());
+        assert!(api.cursors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn csv_page_has_header_and_paging_headers() {
+        let api = QueryApi::default();
+        let mut c = cursor(vec![batch(0..3)], 2, ResultFormat::Csv);
+        c.truncated = true;
+        let resp = render_next_page(&api, &mut c, None).unwrap();
+        assert_eq!(resp.headers()["x-truncated"], "true");
+        assert_eq!(resp.headers()["x-row-count"], "2");
+        assert!(resp.headers().contains_key("x-next-cursor"));
+        let text = String::from_utf8(body_bytes(resp).await).unwrap();
+        assert_eq!(text, "id\n0\n1\n");
+    }
+
+    #[tokio::test]
+    async fn empty_csv_result_still_has_header() {
+        let api = QueryApi::default();
+        let mut c = cursor(vec![], 10, ResultFormat::Csv);
+        let resp = render_next_page(&api, &mut c, None).unwrap();
+        assert!(!resp.headers().contains_key("x-next-cursor"));
+        assert_eq!(String::from_utf8(body_bytes(resp).await).unwrap(), "id\n");
+    }
+
+    #[tokio::test]
+    async fn arrow_page_round_trips_through_ipc_reader() {
+        let api = QueryApi::default();
+        let mut c = cursor(vec![batch(0..3)], 10, ResultFormat::Arrow);
+        let resp = render_next_page(&api, &mut c, None).unwrap();
+        assert_eq!(resp.headers()["content-type"], ARROW_STREAM_CONTENT_TYPE);
+        let bytes = body_bytes(resp).await;
+        let reader = ipc::reader::StreamReader::try_new(bytes.as_slice(), None).unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        assert_eq!(ids(&batches), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn pgwire_denials_map_to_forbidden() {
+        let err = PgWireError::UserError(Box::new(pgwire::error::ErrorInfo::new(
+            "ERROR".into(),
+            "25006".into(),
+            "only read-only queries are allowed".into(),
+        )));
+        let resp = pgwire_error_to_api(err).into_response();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -8,6 +8,7 @@ use sea_orm::{
     ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
     QueryOrder, Set,
 };
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::{
@@ -222,96 +223,7 @@ pub async fn update_user(
     // Handle attributes (full-replace semantics)
     let mut attributes_changed = false;
     if let Some(ref attrs) = body.attributes {
-        // Validate against attribute definitions
-        if !attrs.is_empty() {
-            let defs = attribute_definition::Entity::find()
-                .filter(attribute_definition::Column::EntityType.eq("user"))
-                .all(&state.db)
-                .await
-                .map_err(ApiErr::internal)?;
-
-            let defs_by_key = attribute_definition::definitions_by_key(&defs);
-
-            for (key, value) in attrs {
-                let def = defs_by_key.get(key.as_str()).ok_or_else(|| {
-                    ApiErr::new(
-                        StatusCode::UNPROCESSABLE_ENTITY,
-                        format!("Unknown attribute key '{key}'. Define it first via attribute definitions."),
-                    )
-                })?;
-
-                if def.value_type == "list" {
-                    // List type: value must be a JSON array of strings
-                    let elems = value.as_array().ok_or_else(|| {
-                        ApiErr::new(
-                            StatusCode::UNPROCESSABLE_ENTITY,
-                            format!(
-                                "Attribute '{key}' has type 'list' — value must be a JSON array"
-                            ),
-                        )
-                    })?;
-
-                    // Validate via validate_value (checks element count, types, lengths)
-                    let json_str = serde_json::to_string(value).unwrap_or_default();
-                    attribute_definition::validate_value(&json_str, "list").map_err(|e| {
-                        ApiErr::new(
-                            StatusCode::UNPROCESSABLE_ENTITY,
-                            format!("Invalid value for attribute '{key}': {e}"),
-                        )
-                    })?;
-
-                    // Check each element against allowed_values
-                    if let Some(ref av_json) = def.allowed_values {
-                        let allowed = attribute_definition::parse_allowed_values(av_json);
-                        if !allowed.is_empty() {
-                            for elem in elems {
-                                let s = elem.as_str().unwrap_or_default();
-                                if !allowed.contains(&s.to_string()) {
-                                    return Err(ApiErr::new(
-                                        StatusCode::UNPROCESSABLE_ENTITY,
-                                        format!(
-                                            "List element '{s}' for attribute '{key}' is not in allowed values: {allowed:?}",
-                                        ),
-                                    ));
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    // Scalar types: value must be a JSON string
-                    let str_value = value.as_str().ok_or_else(|| {
-                        ApiErr::new(
-                            StatusCode::UNPROCESSABLE_ENTITY,
-                            format!(
-                                "Attribute '{key}' has type '{}' — value must be a string",
-                                def.value_type
-                            ),
-                        )
-                    })?;
-
-                    attribute_definition::validate_value(str_value, &def.value_type).map_err(
-                        |e| {
-                            ApiErr::new(
-                                StatusCode::UNPROCESSABLE_ENTITY,
-                                format!("Invalid value for attribute '{key}': {e}"),
-                            )
-                        },
-                    )?;
-
-                    if let Some(ref av_json) = def.allowed_values {
-                        let allowed = attribute_definition::parse_allowed_values(av_json);
-                        if !allowed.is_empty() && !allowed.contains(&str_value.to_string()) {
-                            return Err(ApiErr::new(
-                                StatusCode::UNPROCESSABLE_ENTITY,
-                                format!(
-                                    "Value '{str_value}' for attribute '{key}' is not in allowed values: {allowed:?}",
-                                ),
-                            ));
-                        }
-                    }
-                }
-            }
-        }
+        validate_user_attributes(&state.db, attrs).await?;
 
         changes_before.insert("attributes".into(), serde_json::json!(user.attributes));
         changes_after.insert("attributes".into(), serde_json::json!(attrs));
@@ -445,6 +357,102 @@ pub async fn delete_user(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Check `attrs` against the user attribute definitions: every key must be
+/// defined, and every value must have the defined type and be an allowed value.
+pub(crate) async fn validate_user_attributes(
+    db: &DatabaseConnection,
+    attrs: &HashMap<String, serde_json::Value>,
+) -> Result<(), ApiErr> {
+    if !attrs.is_empty() {
+        let defs = attribute_definition::Entity::find()
+            .filter(attribute_definition::Column::EntityType.eq("user"))
+            .all(db)
+            .await
+            .map_err(ApiErr::internal)?;
+
+        let defs_by_key = attribute_definition::definitions_by_key(&defs);
+
+        for (key, value) in attrs {
+            let def = defs_by_key.get(key.as_str()).ok_or_else(|| {
+                ApiErr::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!(
+                        "Unknown attribute key '{key}'. Define it first via attribute definitions."
+                    ),
+                )
+            })?;
+
+            if def.value_type == "list" {
+                // List type: value must be a JSON array of strings
+                let elems = value.as_array().ok_or_else(|| {
+                    ApiErr::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("Attribute '{key}' has type 'list' — value must be a JSON array"),
+                    )
+                })?;
+
+                // Validate via validate_value (checks element count, types, lengths)
+                let json_str = serde_json::to_string(value).unwrap_or_default();
+                attribute_definition::validate_value(&json_str, "list").map_err(|e| {
+                    ApiErr::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("Invalid value for attribute '{key}': {e}"),
+                    )
+                })?;
+
+                // Check each element against allowed_values
+                if let Some(ref av_json) = def.allowed_values {
+                    let allowed = attribute_definition::parse_allowed_values(av_json);
+                    if !allowed.is_empty() {
+                        for elem in elems {
+                            let s = elem.as_str().unwrap_or_default();
+                            if !allowed.contains(&s.to_string()) {
+                                return Err(ApiErr::new(
+                                    StatusCode::UNPROCESSABLE_ENTITY,
+                                    format!(
+                                        "List element '{s}' for attribute '{key}' is not in allowed values: {allowed:?}",
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+            } else {
+                // Scalar types: value must be a JSON string
+                let str_value = value.as_str().ok_or_else(|| {
+                    ApiErr::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!(
+                            "Attribute '{key}' has type '{}' — value must be a string",
+                            def.value_type
+                        ),
+                    )
+                })?;
+
+                attribute_definition::validate_value(str_value, &def.value_type).map_err(|e| {
+                    ApiErr::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("Invalid value for attribute '{key}': {e}"),
+                    )
+                })?;
+
+                if let Some(ref av_json) = def.allowed_values {
+                    let allowed = attribute_definition::parse_allowed_values(av_json);
+                    if !allowed.is_empty() && !allowed.contains(&str_value.to_string()) {
+                        return Err(ApiErr::new(
+                            StatusCode::UNPROCESSABLE_ENTITY,
+                            format!(
+                                "Value '{str_value}' for attribute '{key}' is not in allowed values: {allowed:?}",
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -496,6 +504,7 @@ mod tests {
             policy_hook: None,
             proxy_handler: None,
             wasm_runtime,
+            query_api: Arc::new(crate::admin::query_handlers::QueryApi::default()),
         }
     }
 
@@ -1,4 +1,9 @@
+pub mod pool;
 pub mod rewrite;
+pub mod upstream;
+
+pub use pool::{PoolSettings, PoolUtilization, WriteConnection};
+pub use upstream::{EndpointStatus, ReadPreference, track_served_by};
 
 use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
 use datafusion::catalog::{CatalogProvider, SchemaProvider};
@@ -9,24 +14,17 @@ use datafusion::sql::unparser::Unparser;
 use datafusion::sql::unparser::dialect::{Dialect, IntervalStyle, PostgreSqlDialect};
 use datafusion_expr::Expr;
 use datafusion_pg_catalog::pg_catalog::{context::PgCatalogContextProvider, setup_pg_catalog};
-use datafusion_table_providers::{
-    UnsupportedTypeAction,
-    postgres::DynPostgresConnectionPool,
-    sql::{
-        db_connection_pool::postgrespool::PostgresConnectionPool, sql_provider_datafusion::SqlTable,
-    },
-    util::secrets::to_secret_map,
-};
+use datafusion_table_providers::sql::sql_provider_datafusion::SqlTable;
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 use std::any::Any;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tokio::sync::RwLock as AsyncRwLock;
 use uuid::Uuid;
 
 use crate::entity::{
     data_source, decision_function, discovered_column, discovered_schema, discovered_table, policy,
-    proxy_user, role,
 };
 
 // ---------- custom dialect for JSON pushdown ----------
@@ -211,6 +209,12 @@ pub struct DataSourceConfig {
     pub username: String,
     pub password: String,
     pub ssl_mode: String,
+    /// Read replicas as (host, port); empty for a single-endpoint datasource.
+    pub replicas: Vec<(String, u16)>,
+    pub read_preference: ReadPreference,
+    /// Replicas lagging further behind than this are not used for reads.
+    pub max_replica_lag_secs: Option<u64>,
+    pub pool: PoolSettings,
 }
 
 impl DataSourceConfig {
@@ -233,12 +237,39 @@ impl DataSourceConfig {
                 .map_err(|e| format!("Failed to decrypt secure_config: {e}"))?
         };
 
+        use crate::admin::datasource_types::optional_u64;
+
+        let port = config["port"].as_u64().ok_or("missing port in config")? as u16;
+        let replicas = match config.get("replicas").and_then(|v| v.as_str()) {
+            Some(text) => crate::admin::datasource_types::parse_endpoint_list(text, port)?,
+            None => Vec::new(),
+        };
+        let read_preference = match config.get("read_preference").and_then(|v| v.as_str()) {
+            Some(s) => {
+                ReadPreference::parse(s).ok_or_else(|| format!("Invalid read_preference: {s}"))?
+            }
+            None => ReadPreference::default(),
+        };
+        let max_replica_lag_secs = optional_u64(&config, "max_replica_lag_secs")?;
+        let pool = PoolSettings {
+            max_size: optional_u64(&config, "max_pool_size")?.map(|n| n as u32),
+            max_lifetime: optional_u64(&config, "max_connection_lifetime_secs")?
+                .map(Duration::from_secs),
+            idle_timeout: optional_u64(&config, "idle_timeout_secs")?.map(Duration::from_secs),
+            statement_timeout_ms: optional_u64(&config, "statement_timeout_ms")?,
+            application_name: config
+                .get("application_name")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(str::to_string),
+        };
+
         Ok(Self {
             host: config["host"]
                 .as_str()
                 .ok_or("missing host in config")?
                 .to_string(),
-            port: config["port"].as_u64().ok_or("missing port in config")? as u16,
+            port,
             database: config["database"]
                 .as_str()
                 .ok_or("missing database in config")?
@@ -256,6 +287,10 @@ impl DataSourceConfig {
                 .and_then(|v| v.as_str())
                 .unwrap_or("require")
                 .to_string(),
+            replicas,
+            read_preference,
+            max_replica_lag_secs,
+            pool,
         })
     }
 }
@@ -268,15 +303,23 @@ impl DataSourceConfig {
 /// - `"db"` (not `"dbname"`) — the database name
 /// - `"pass"` (not `"password"`) — the password
 /// - `"host"`, `"user"`, `"port"`, `"sslmode"` — as-is
+/// - `"connection_pool_size"`, `"application_name"` — only when configured
 pub fn build_postgres_params(cfg: &DataSourceConfig) -> HashMap<String, String> {
-    HashMap::from([
+    let mut params = HashMap::from([
         ("host".to_string(), cfg.host.clone()),
         ("user".to_string(), cfg.username.clone()),
         ("db".to_string(), cfg.database.clone()),
         ("pass".to_string(), cfg.password.clone()),
         ("port".to_string(), cfg.port.to_string()),
         ("sslmode".to_string(), cfg.ssl_mode.clone()),
-    ])
+    ]);
+    if let Some(size) = cfg.pool.max_size {
+        params.insert("connection_pool_size".to_string(), size.to_string());
+    }
+    if let Some(name) = &cfg.pool.application_name {
+        params.insert("application_name".to_string(), name.clone());
+    }
+    params
 }
 
 // ---------- virtual schema layer ----------
@@ -301,6 +344,8 @@ struct CachedCatalog {
     schemas: HashMap<String, VirtualCatalogSchema>,
     default_schema: String,
     access_mode: String,
+    /// Tags on the selected schemas, tables and columns (keyed by upstream names).
+    tags: CatalogTags,
 }
 
 /// Computed per-user visibility derived from policy assignments.
@@ -320,61 +365,10 @@ struct VisibilityFilter {
     denied_tables: HashSet<(String, String)>,
 }
 
-use crate::policy_match::{PolicyType, TargetEntry};
-
-// ---------- lazy pool ----------
-
-/// Lazily-initialized connection pool shared across SessionContext rebuilds.
-///
-/// The pool is not created until the first user-table query, so pg_catalog /
-/// information_schema queries (e.g. TablePlus sidebar population) complete
-/// instantly without an upstream connection.
-struct LazyPool {
-    pool: AsyncRwLock<Option<Arc<DynPostgresConnectionPool>>>,
-    params: HashMap<String, String>,
-}
-
-impl std::fmt::Debug for LazyPool {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("LazyPool { ... }")
-    }
-}
-
-impl LazyPool {
-    fn new(params: HashMap<String, String>) -> Self {
-        Self {
-            pool: AsyncRwLock::new(None),
-            params,
-        }
-    }
-
-    /// Return the shared pool, creating it on first call (one round-trip via bb8).
-    async fn get(&self) -> Result<Arc<DynPostgresConnectionPool>, String> {
-        // Fast path: already initialised
-        {
-            let guard = self.pool.read().await;
-            if let Some(ref p) = *guard {
-                return Ok(p.clone());
-            }
-        }
-
-        // Slow path: create pool (serialised by write lock — double-check inside)
-        let mut guard = self.pool.write().await;
-        if let Some(ref p) = *guard {
-            return Ok(p.clone());
-        }
-
-        tracing::debug!("Creating upstream pool (first user-table query)");
-        let postgres_params = to_secret_map(self.params.clone());
-        let new_pool = PostgresConnectionPool::new(postgres_params)
-            .await
-            .map_err(|e| format!("Failed to create Postgres pool: {e}"))?
-            .with_unsupported_type_action(UnsupportedTypeAction::String);
-        let new_pool: Arc<DynPostgresConnectionPool> = Arc::new(new_pool);
-        *guard = Some(new_pool.clone());
-        Ok(new_pool)
-    }
-}
+use crate::policy_match::{ActionStatus, CatalogTags, PolicyType, TargetEntry, parse_tags};
+use crate::policy_simulation::PolicyChange;
+use crate::subject::Subject;
+use upstream::UpstreamSet;
 
 /// Parse a stored arrow_type string back into an Arrow DataType.
 /// Returns None for unsupported or unrecognized types.
@@ -481,7 +475,7 @@ struct VirtualSchemaProvider {
     schema_name: String,
     /// table_name → Arrow schema
     tables: HashMap<String, SchemaRef>,
-    pool: Arc<LazyPool>,
+    upstreams: Arc<UpstreamSet>,
 }
 
 impl std::fmt::Debug for VirtualSchemaProvider {
@@ -512,8 +506,9 @@ impl SchemaProvider for VirtualSchemaProvider {
             None => return Ok(None),
         };
 
-        // Initialise pool on first call (lazy — no upstream connection until needed)
-        let pool = self.pool.get().await.map_err(|e| {
+        // Route to an upstream endpoint; its pool is initialised on first use
+        // (lazy — no upstream connection until needed).
+        let pool = self.upstreams.route_read().await.map_err(|e| {
             datafusion::error::DataFusionError::External(Box::new(std::io::Error::other(e)))
         })?;
 
@@ -777,7 +772,7 @@ impl datafusion::optimizer::OptimizerRule for EmptyProjectionFixRule {
     }
 }
 
-/// Build a SessionContext from local catalog metadata using a shared LazyPool.
+/// Build a SessionContext from local catalog metadata using the datasource's shared upstreams.
 ///
 /// Pool creation is deferred until the first user-table query, so pg_catalog /
 /// information_schema queries complete instantly without an upstream connection.
@@ -796,11 +791,11 @@ impl datafusion::optimizer::OptimizerRule for EmptyProjectionFixRule {
 /// an identifier users can type.
 async fn create_session_context_from_catalog(
     catalog_schemas: HashMap<String, VirtualCatalogSchema>,
-    lazy_pool: Arc<LazyPool>,
+    upstreams: Arc<UpstreamSet>,
     default_schema: &str,
     datasource_name: &str,
 ) -> Result<SessionContext, Box<dyn std::error::Error + Send + Sync>> {
-    // Build VirtualSchemaProviders — all share the same lazy pool.
+    // Build VirtualSchemaProviders — all share the same upstream set.
     // The HashMap key is the alias (user-facing); schema_name inside the
     // provider is the real upstream name used in TableReference.
     let mut schema_providers: HashMap<String, Arc<VirtualSchemaProvider>> = HashMap::new();
@@ -816,7 +811,7 @@ async fn create_session_context_from_catalog(
             Arc::new(VirtualSchemaProvider {
                 schema_name: catalog_schema.schema_name,
                 tables,
-                pool: Arc::clone(&lazy_pool),
+                upstreams: Arc::clone(&upstreams),
             }),
         );
     }
@@ -828,7 +823,8 @@ async fn create_session_context_from_catalog(
 
     let config = SessionConfig::new()
         .with_information_schema(true)
-        .with_default_catalog_and_schema(datasource_name, default_schema);
+        .with_default_catalog_and_schema(datasource_name, default_schema)
+        .with_extension(Arc::new(UpstreamWriter { upstreams }));
     let mut ctx = SessionContext::new_with_config(config);
     ctx.add_optimizer_rule(Arc::new(ScanFilterProjectionFixRule));
     ctx.add_optimizer_rule(Arc::new(EmptyProjectionFixRule));
@@ -845,6 +841,20 @@ async fn create_session_context_from_catalog(
     Ok(ctx)
 }
 
+/// The datasource's upstreams, as a session-config extension, for statements
+/// the proxy runs upstream itself instead of planning them (governed writes).
+pub struct UpstreamWriter {
+    upstreams: Arc<UpstreamSet>,
+}
+
+impl UpstreamWriter {
+    /// Check out a connection on the current primary, with its `host:port`.
+    pub async fn connect(&self) -> Result<(WriteConnection, String), String> {
+        let (pool, address) = self.upstreams.route_write().await?;
+        Ok((WriteConnection::checkout(&*pool).await?, address))
+    }
+}
+
 // ---------- visibility matching ----------
 // Delegated to crate::policy_match::matches_schema_table (single source of truth).
 
@@ -867,11 +877,13 @@ pub struct EngineCache {
     /// Cached raw catalog per datasource (schema/table/column metadata).
     /// Shared across all connections to the same datasource.
     catalogs: AsyncRwLock<HashMap<String, Arc<CachedCatalog>>>,
-    /// Shared LazyPool per datasource. Survives catalog invalidation so
-    /// re-discovery after catalog changes reuses the existing upstream connection pool.
-    pools: AsyncRwLock<HashMap<String, Arc<LazyPool>>>,
+    /// Shared upstream endpoints (one LazyPool each) per datasource. Survives catalog
+    /// invalidation so re-discovery after catalog changes reuses the existing pools.
+    pools: AsyncRwLock<HashMap<String, Arc<UpstreamSet>>>,
     /// Shared WASM runtime for evaluating decision functions at visibility time.
     wasm_runtime: Arc<crate::decision::wasm::WasmDecisionRuntime>,
+    /// Decrypted masking keys used by the `br_*` UDFs on every session.
+    masking: Arc<crate::masking::MaskingKeyring>,
 }
 
 impl EngineCache {
@@ -886,9 +898,21 @@ impl EngineCache {
             catalogs: AsyncRwLock::new(HashMap::new()),
             pools: AsyncRwLock::new(HashMap::new()),
             wasm_runtime,
+            masking: crate::masking::MaskingKeyring::new(master_key),
         })
     }
 
+    /// Reload masking keys after an admin change. Sessions pick the new keys up
+    /// on their next query; no rebuild needed.
+    pub async fn reload_masking_keys(&self) -> Result<(), String> {
+        self.masking.reload(&self.db).await
+    }
+
+    /// The masking keyring shared by every session's `br_*` UDFs.
+    pub fn masking_keyring(&self) -> &Arc<crate::masking::MaskingKeyring> {
+        &self.masking
+    }
+
     /// Validate a data source exists and is active (DB lookup only, no connection).
     pub async fn validate_data_source(
         &self,
@@ -932,6 +956,14 @@ impl EngineCache {
         )
     }
 
+    /// Check a purpose declaration against the datasource's purpose catalog.
+    pub async fn validate_purpose(
+        &self,
+        declaration: &crate::purpose::Declaration<'_>,
+    ) -> Result<(), crate::purpose::DeclarationError> {
+        crate::purpose::validate_declaration(&self.db, declaration).await
+    }
+
     /// Get (or lazily load) the raw catalog for a named data source.
     /// Loads schema/table/column metadata from the admin DB and caches it.
     /// Per-user visibility filtering happens in `build_user_context()`.
@@ -972,9 +1004,11 @@ impl EngineCache {
                 .map_err(|e| EngineError(format!("DB error loading catalog: {e}")))?;
 
         let mut catalog_schemas: HashMap<String, VirtualCatalogSchema> = HashMap::new();
+        let mut tags = CatalogTags::default();
 
         for (schema, tables) in schemas_with_tables {
             let mut catalog_tables: HashMap<String, VirtualCatalogTable> = HashMap::new();
+            tags.set_schema(&schema.schema_name, parse_tags(schema.tags.as_deref()));
 
             for table in tables.into_iter().filter(|t| t.is_selected) {
                 let columns: Vec<discovered_column::Model> = discovered_column::Entity::find()
@@ -983,6 +1017,20 @@ impl EngineCache {
                     .await
                     .map_err(|e| EngineError(format!("DB error loading columns: {e}")))?;
 
+                tags.set_table(
+                    &schema.schema_name,
+                    &table.table_name,
+                    parse_tags(table.tags.as_deref()),
+                );
+                for c in &columns {
+                    tags.set_column(
+                        &schema.schema_name,
+                        &table.table_name,
+                        &c.column_name,
+                        parse_tags(c.tags.as_deref()),
+                    );
+                }
+
                 let arrow_schema = build_arrow_schema(&columns);
 
                 catalog_tables.insert(
@@ -1016,14 +1064,15 @@ impl EngineCache {
             schemas: catalog_schemas,
             default_schema,
             access_mode: ds.access_mode,
+            tags,
         });
 
         map.insert(name.to_string(), catalog.clone());
         Ok(catalog)
     }
 
-    /// Get or create the shared LazyPool for a datasource.
-    async fn get_or_create_pool(&self, name: &str, cfg: &DataSourceConfig) -> Arc<LazyPool> {
+    /// Get or create the shared upstream set for a datasource.
+    async fn get_or_create_pool(&self, name: &str, cfg: &DataSourceConfig) -> Arc<UpstreamSet> {
         // Fast path
         {
             let pools = self.pools.read().await;
@@ -1035,8 +1084,9 @@ impl EngineCache {
         if let Some(p) = pools.get(name) {
             return p.clone();
         }
-        let params = build_postgres_params(cfg);
-        let p = Arc::new(LazyPool::new(params));
+        let p = UpstreamSet::new(cfg);
+        p.spawn_health_checker();
+        p.spawn_pool_reaper();
         pools.insert(name.to_string(), p.clone());
         p
     }
@@ -1098,8 +1148,9 @@ impl EngineCache {
     /// Compute what tables and columns a user can see, given their policy assignments.
     async fn compute_user_visibility(
         &self,
-        user_id: Uuid,
+        subject: &Subject,
         catalog: &CachedCatalog,
+        change: Option<&PolicyChange>,
     ) -> Result<UserVisibility, Box<dyn std::error::Error + Send + Sync>> {
         // Build df_alias → upstream_name mapping from catalog
         let df_to_upstream: HashMap<String, String> = catalog
@@ -1109,13 +1160,12 @@ impl EngineCache {
             .collect();
 
         // Load policy assignments for this datasource + user (user-specific, role-based, or wildcard)
-        let relevant = crate::role_resolver::resolve_effective_assignments(
-            &self.db,
-            user_id,
-            catalog.datasource_id,
-        )
-        .await
-        .map_err(|e| EngineError(format!("DB error loading assignments: {e}")))?;
+        // Shadow assignments never affect what the user can see.
+        let mut relevant = subject
+            .effective_assignments(&self.db, catalog.datasource_id, change)
+            .await
+            .map_err(|e| EngineError(format!("DB error loading assignments: {e}")))?;
+        relevant.retain(|a| a.action_status != ActionStatus::Shadow.as_str());
 
         if relevant.is_empty() {
             if catalog.access_mode == "policy_required" {
@@ -1139,12 +1189,19 @@ impl EngineCache {
             .into_iter()
             .collect();
 
-        let policies = policy::Entity::find()
+        let mut policies = policy::Entity::find()
             .filter(policy::Column::Id.is_in(policy_ids.clone()))
             .filter(policy::Column::IsEnabled.eq(true))
+            .filter(policy::Column::ActionStatus.ne(ActionStatus::Shadow.as_str()))
             .all(&self.db)
             .await
             .map_err(|e| EngineError(format!("DB error loading policies: {e}")))?;
+        if let Some(change) = change {
+            change.apply_to_policies(&mut policies, &policy_ids);
+            policies.retain(|p| p.action_status != ActionStatus::Shadow.as_str());
+        }
+        policies
+            .retain(|p| crate::purpose::in_scope(p.purpose.as_deref(), subject.purpose.as_deref()));
 
         // Batch-load decision functions referenced by visibility-affecting policies
         let df_ids: Vec<Uuid> = policies
@@ -1175,27 +1232,10 @@ impl EngineCache {
 
         // Build session context for decision function evaluation (only if needed)
         let session_ctx = if !df_map.is_empty() {
-            let user = proxy_user::Entity::find_by_id(user_id)
-                .one(&self.db)
+            let role_names = subject
+                .role_names(&self.db)
                 .await
-                .map_err(|e| EngineError(format!("DB error loading user: {e}")))?
-                .ok_or_else(|| EngineError(format!("User {user_id} not found")))?;
-
-            let role_ids = crate::role_resolver::resolve_user_roles(&self.db, user_id)
-                .await
-                .map_err(|e| EngineError(format!("DB error resolving roles: {e}")))?;
-            let role_names: Vec<String> = if !role_ids.is_empty() {
-                role::Entity::find()
-                    .filter(role::Column::Id.is_in(role_ids))
-                    .all(&self.db)
-                    .await
-                    .map_err(|e| EngineError(format!("DB error loading roles: {e}")))?
-                    .into_iter()
-                    .map(|r| r.name)
-                    .collect()
-            } else {
-                vec![]
-            };
+                .map_err(|e| EngineError(format!("DB error loading roles: {e}")))?;
 
             // Resolve datasource name from catalog (catalog stores it)
             let ds = data_source::Entity::find_by_id(catalog.datasource_id)
@@ -1205,12 +1245,12 @@ impl EngineCache {
                 .ok_or_else(|| EngineError("Datasource not found".to_string()))?;
 
             // Build typed attributes for decision function context
-            let raw_attrs = crate::entity::proxy_user::parse_attributes(&user.attributes);
+            let raw_attrs = crate::entity::proxy_user::parse_attributes(&subject.attributes);
             let typed_attrs = build_typed_json_attributes(&self.db, &raw_attrs).await;
 
             let session_info = crate::decision::context::SessionInfo {
-                user_id,
-                username: user.username,
+                user_id: subject.user_id,
+                username: subject.username.clone(),
                 roles: role_names,
                 datasource_name: ds.name,
                 access_mode: catalog.access_mode.clone(),
@@ -1252,16 +1292,32 @@ impl EngineCache {
                 PolicyType::ColumnAllow => {
                     // Grants table visibility and restricts columns to the allow list.
                     for (df_alias, vs) in &catalog.schemas {
-                        for table_name in vs.tables.keys() {
+                        for (table_name, table) in &vs.tables {
                             for entry in &targets {
-                                if entry.matches_table(df_alias, table_name, &df_to_upstream) {
+                                if entry.matches_table(
+                                    df_alias,
+                                    table_name,
+                                    &df_to_upstream,
+                                    &catalog.tags,
+                                ) {
                                     let key = (df_alias.clone(), table_name.clone());
                                     visible_tables.insert(key.clone());
-                                    if let Some(cols) = &entry.columns {
-                                        column_allow_patterns
-                                            .entry(key)
-                                            .or_default()
-                                            .extend(cols.iter().cloned());
+                                    if entry.columns.is_some() {
+                                        let actual_cols: Vec<&str> = table
+                                            .arrow_schema
+                                            .fields()
+                                            .iter()
+                                            .map(|f| f.name().as_str())
+                                            .collect();
+                                        column_allow_patterns.entry(key).or_default().extend(
+                                            entry.column_patterns(
+                                                df_alias,
+                                                table_name,
+                                                &actual_cols,
+                                                &df_to_upstream,
+                                                &catalog.tags,
+                                            ),
+                                        );
                                     }
                                     break;
                                 }
@@ -1274,16 +1330,28 @@ impl EngineCache {
                     for (df_alias, vs) in &catalog.schemas {
                         for (table_name, table) in &vs.tables {
                             for entry in &targets {
-                                if entry.matches_table(df_alias, table_name, &df_to_upstream) {
-                                    if let Some(cols) = &entry.columns {
+                                if entry.matches_table(
+                                    df_alias,
+                                    table_name,
+                                    &df_to_upstream,
+                                    &catalog.tags,
+                                ) {
+                                    if entry.columns.is_some() {
                                         let actual_cols: Vec<&str> = table
                                             .arrow_schema
                                             .fields()
                                             .iter()
                                             .map(|f| f.name().as_str())
                                             .collect();
+                                        let cols = entry.column_patterns(
+                                            df_alias,
+                                            table_name,
+                                            &actual_cols,
+                                            &df_to_upstream,
+                                            &catalog.tags,
+                                        );
                                         for col_name in crate::policy_match::expand_column_patterns(
-                                            cols,
+                                            &cols,
                                             &actual_cols,
                                         ) {
                                             denied_columns.insert((
@@ -1302,16 +1370,8 @@ impl EngineCache {
                 PolicyType::TableDeny => {
                     // Hides entire tables or schemas.
                     for (df_alias, vs) in &catalog.schemas {
-                        let upstream = df_to_upstream
-                            .get(df_alias)
-                            .map(|s| s.as_str())
-                            .unwrap_or(df_alias.as_str());
                         for entry in &targets {
-                            if !entry
-                                .schemas
-                                .iter()
-                                .any(|sp| crate::policy_match::matches_pattern(sp, upstream))
-                            {
+                            if !entry.matches_schema(df_alias, &df_to_upstream, &catalog.tags) {
                                 continue;
                             }
                             // tables: ["*"] or absent → deny entire schema
@@ -1320,9 +1380,12 @@ impl EngineCache {
                                 denied_schemas.insert(df_alias.clone());
                             } else {
                                 for table_name in vs.tables.keys() {
-                                    if entry.tables.iter().any(|tp| {
-                                        crate::policy_match::matches_pattern(tp, table_name)
-                                    }) {
+                                    if entry.matches_table(
+                                        df_alias,
+                                        table_name,
+                                        &df_to_upstream,
+                                        &catalog.tags,
+                                    ) {
                                         denied_tables
                                             .insert((df_alias.clone(), table_name.clone()));
                                     }
@@ -1332,7 +1395,15 @@ impl EngineCache {
                     }
                 }
                 // RowFilter and ColumnMask don't affect catalog-level visibility.
-                PolicyType::RowFilter | PolicyType::ColumnMask => {}
+                PolicyType::RowFilter
+                | PolicyType::ColumnMask
+                | PolicyType::AggregateOnly
+                | PolicyType::DifferentialPrivacy
+                | PolicyType::ResultLimit
+                | PolicyType::QueryGuard
+                | PolicyType::JoinOnly
+                | PolicyType::WriteAllow
+                | PolicyType::WriteDeny => {}
             }
         }
 
@@ -1396,11 +1467,30 @@ impl EngineCache {
         &self,
         user_id: Uuid,
         datasource_name: &str,
+        purpose: Option<&str>,
+    ) -> Result<Arc<SessionContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let subject = Subject::load(&self.db, user_id)
+            .await
+            .map_err(|e| EngineError(format!("DB error loading user: {e}")))?
+            .ok_or_else(|| EngineError(format!("User {user_id} not found")))?
+            .with_purpose(purpose.map(str::to_string));
+        self.build_subject_context(&subject, datasource_name, None)
+            .await
+    }
+
+    /// [`Self::build_user_context`] for any [`Subject`], including the
+    /// hypothetical user of an impersonation preview. With `change`, the
+    /// catalog is filtered as it would be once the proposed policy change is saved.
+    pub async fn build_subject_context(
+        &self,
+        subject: &Subject,
+        datasource_name: &str,
+        change: Option<&PolicyChange>,
     ) -> Result<Arc<SessionContext>, Box<dyn std::error::Error + Send + Sync>> {
         let catalog = self.get_catalog(datasource_name).await?;
 
         // Load datasource config for pool creation (only queries DB if pool not yet cached)
-        let lazy_pool = {
+        let upstreams = {
             let existing = self.pools.read().await.get(datasource_name).cloned();
             if let Some(p) = existing {
                 p
@@ -1423,7 +1513,9 @@ impl EngineCache {
         };
 
         // Compute per-user visibility from policy assignments
-        let visibility = self.compute_user_visibility(user_id, &catalog).await?;
+        let visibility = self
+            .compute_user_visibility(subject, &catalog, change)
+            .await?;
 
         // Build filtered catalog schemas
         let filtered_schemas: HashMap<String, VirtualCatalogSchema> =
@@ -1538,11 +1630,16 @@ impl EngineCache {
 
         let ctx = create_session_context_from_catalog(
             filtered_schemas,
-            lazy_pool,
+            upstreams,
             &default_schema,
             datasource_name,
         )
         .await?;
+        self.masking
+            .ensure_loaded(&self.db)
+            .await
+            .map_err(EngineError)?;
+        crate::masking::register_udfs(&ctx, &self.masking);
         Ok(Arc::new(ctx))
     }
 
@@ -1559,13 +1656,13 @@ impl EngineCache {
         self.pools.write().await.remove(name);
     }
 
-    /// Eagerly initialise the LazyPool for a datasource.
+    /// Eagerly initialise the upstream pool reads will use for a datasource.
     ///
     /// Call this from a background task after auth to amortise the first-query latency.
     pub async fn warmup(&self, name: &str) {
         let pool = self.pools.read().await.get(name).cloned();
-        if let Some(lazy_pool) = pool {
-            match lazy_pool.get().await {
+        if let Some(upstreams) = pool {
+            match upstreams.route_read().await {
                 Ok(_) => tracing::debug!(datasource = %name, "Pool warmed up"),
                 Err(e) => {
                     tracing::debug!(datasource = %name, error = %e, "Pool warmup failed (non-fatal)")
@@ -1576,15 +1673,23 @@ impl EngineCache {
 
     /// Attempt a test connection for a data source config (no caching).
     ///
-    /// Only creates the connection pool (which internally runs `SELECT 1`).
+    /// Creates a connection pool per endpoint (primary and every replica), which
+    /// internally runs `SELECT 1`.
     pub async fn test_connection(
         cfg: &DataSourceConfig,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let postgres_params = to_secret_map(build_postgres_params(cfg));
-        PostgresConnectionPool::new(postgres_params)
-            .await
-            .map(|_| ())
-            .map_err(|e| format!("Failed to create Postgres pool: {}", e).into())
+        UpstreamSet::new(cfg).test_all().await.map_err(Into::into)
+    }
+
+    /// Health and routing eligibility of each upstream endpoint of a datasource.
+    ///
+    /// Endpoints of a datasource that has not served a session yet are reported
+    /// as `unknown`.
+    pub async fn upstream_status(&self, name: &str, cfg: &DataSourceConfig) -> Vec<EndpointStatus> {
+        match self.pools.read().await.get(name) {
+            Some(upstreams) => upstreams.status().await,
+            None => UpstreamSet::new(cfg).status().await,
+        }
     }
 }
 
@@ -1689,6 +1794,18 @@ mod tests {
             .clone()
     }
 
+    impl EngineCache {
+        /// `compute_user_visibility` for the stored user `user_id`.
+        async fn user_visibility(
+            &self,
+            user_id: Uuid,
+            catalog: &CachedCatalog,
+        ) -> Result<UserVisibility, Box<dyn std::error::Error + Send + Sync>> {
+            let subject = Subject::load(&self.db, user_id).await?.unwrap();
+            self.compute_user_visibility(&subject, catalog, None).await
+        }
+    }
+
     /// Mock CatalogProvider for testing
     #[derive(Debug)]
     struct MockCatalogProvider {
@@ -1899,6 +2016,8 @@ mod tests {
             secure_config: encrypted,
             is_active: true,
             access_mode: "policy_required".to_string(),
+            policy_combining: None,
+            write_mode: "read_only".to_string(),
             last_sync_at: None,
             last_sync_result: None,
             created_at: Utc::now().naive_utc(),
@@ -1913,6 +2032,68 @@ mod tests {
         assert_eq!(cfg.username, "alice");
         assert_eq!(cfg.password, "secret123");
         assert_eq!(cfg.ssl_mode, "require");
+        assert!(cfg.replicas.is_empty());
+        assert_eq!(cfg.read_preference, ReadPreference::Replica);
+        assert_eq!(cfg.max_replica_lag_secs, None);
+    }
+
+    #[test]
+    fn test_data_source_config_replicas() {
+        use crate::entity::data_source;
+        use chrono::Utc;
+
+        let master_key = [42u8; 32];
+        let secure = serde_json::json!({"password": "secret123"});
+        let model = data_source::Model {
+            id: Uuid::new_v4(),
+            name: "test-ds".to_string(),
+            ds_type: "postgres".to_string(),
+            config: serde_json::json!({
+                "host": "primary",
+                "port": 5433,
+                "database": "mydb",
+                "username": "alice",
+                "replicas": "replica-1\nreplica-2:6432",
+                "read_preference": "primary",
+                "max_replica_lag_secs": "15",
+                "max_pool_size": 20,
+                "max_connection_lifetime_secs": "1800",
+                "idle_timeout_secs": "",
+                "statement_timeout_ms": 30000,
+                "application_name": "betweenrows"
+            })
+            .to_string(),
+            secure_config: crate::crypto::encrypt_json(&secure, &master_key).unwrap(),
+            is_active: true,
+            access_mode: "open".to_string(),
+            policy_combining: None,
+            write_mode: "read_only".to_string(),
+            last_sync_at: None,
+            last_sync_result: None,
+            created_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+        };
+
+        let cfg = DataSourceConfig::from_model(&model, &master_key).unwrap();
+        assert_eq!(
+            cfg.replicas,
+            vec![
+                ("replica-1".to_string(), 5433),
+                ("replica-2".to_string(), 6432)
+            ]
+        );
+        assert_eq!(cfg.read_preference, ReadPreference::Primary);
+        assert_eq!(cfg.max_replica_lag_secs, Some(15));
+        assert_eq!(
+            cfg.pool,
+            PoolSettings {
+                max_size: Some(20),
+                max_lifetime: Some(Duration::from_secs(1800)),
+                idle_timeout: None,
+                statement_timeout_ms: Some(30000),
+                application_name: Some("betweenrows".to_string()),
+            }
+        );
     }
 
     #[test]
@@ -1927,6 +2108,10 @@ mod tests {
             username: "alice".to_string(),
             password: "s3cr3t".to_string(),
             ssl_mode: "require".to_string(),
+            replicas: Vec::new(),
+            read_preference: ReadPreference::Replica,
+            max_replica_lag_secs: None,
+            pool: PoolSettings::default(),
         };
 
         let params = build_postgres_params(&cfg);
@@ -1968,6 +2153,31 @@ mod tests {
         assert_eq!(params["sslmode"], "require");
     }
 
+    #[test]
+    fn test_build_postgres_params_pool_settings() {
+        let mut cfg = DataSourceConfig {
+            host: "db.example.com".to_string(),
+            port: 5432,
+            database: "mydb".to_string(),
+            username: "alice".to_string(),
+            password: "s3cr3t".to_string(),
+            ssl_mode: "require".to_string(),
+            replicas: Vec::new(),
+            read_preference: ReadPreference::Replica,
+            max_replica_lag_secs: None,
+            pool: PoolSettings::default(),
+        };
+        let params = build_postgres_params(&cfg);
+        assert!(!params.contains_key("connection_pool_size"));
+        assert!(!params.contains_key("application_name"));
+
+        cfg.pool.max_size = Some(25);
+        cfg.pool.application_name = Some("betweenrows-prod".to_string());
+        let params = build_postgres_params(&cfg);
+        assert_eq!(params["connection_pool_size"], "25");
+        assert_eq!(params["application_name"], "betweenrows-prod");
+    }
+
     #[test]
     fn test_data_source_config_unsupported_type() {
         use crate::entity::data_source;
@@ -1983,6 +2193,8 @@ mod tests {
             secure_config: "".to_string(),
             is_active: true,
             access_mode: "policy_required".to_string(),
+            policy_combining: None,
+            write_mode: "read_only".to_string(),
             last_sync_at: None,
             last_sync_result: None,
             created_at: Utc::now().naive_utc(),
@@ -2109,6 +2321,7 @@ mod tests {
                 arrow_type: Some("Int32".to_string()),
                 is_selected: true,
                 discovered_at: now,
+                tags: None,
             },
             discovered_column::Model {
                 id: Uuid::new_v4(),
@@ -2121,6 +2334,7 @@ mod tests {
                 arrow_type: Some("Utf8".to_string()),
                 is_selected: true,
                 discovered_at: now,
+                tags: None,
             },
             discovered_column::Model {
                 id: Uuid::new_v4(),
@@ -2133,6 +2347,7 @@ mod tests {
                 arrow_type: None, // unsupported — should be skipped
                 is_selected: true,
                 discovered_at: now,
+                tags: None,
             },
         ];
 
@@ -2152,6 +2367,7 @@ mod tests {
                 arrow_type: Some("Int32".to_string()),
                 is_selected: true,
                 discovered_at: now,
+                tags: None,
             },
             discovered_column::Model {
                 id: Uuid::new_v4(),
@@ -2164,6 +2380,7 @@ mod tests {
                 arrow_type: Some("Utf8".to_string()),
                 is_selected: false, // deselected — should be excluded
                 discovered_at: now,
+                tags: None,
             },
         ];
         let schema2 = build_arrow_schema(&columns_with_deselected);
@@ -2361,6 +2578,7 @@ mod tests {
             schemas,
             default_schema: "public".to_string(),
             access_mode: ds_access_mode.to_string(),
+            tags: CatalogTags::default(),
         }
     }
 
@@ -2402,6 +2620,8 @@ mod tests {
             secure_config: sea_orm::Set(String::new()),
             is_active: sea_orm::Set(true),
             access_mode: sea_orm::Set("open".to_string()),
+            policy_combining: sea_orm::Set(None),
+            write_mode: sea_orm::Set("read_only".to_string()),
             last_sync_at: sea_orm::Set(None),
             last_sync_result: sea_orm::Set(None),
             created_at: sea_orm::Set(now),
@@ -2463,8 +2683,11 @@ mod tests {
             targets: sea_orm::Set(targets_json),
             definition: sea_orm::Set(None),
             is_enabled: sea_orm::Set(is_enabled),
+            action_status: sea_orm::Set("enforce".to_string()),
             version: sea_orm::Set(1),
             decision_function_id: sea_orm::Set(None),
+            template_id: sea_orm::Set(None),
+            purpose: sea_orm::Set(None),
             created_by: sea_orm::Set(user_id),
             updated_by: sea_orm::Set(user_id),
             created_at: sea_orm::Set(now),
@@ -2482,6 +2705,8 @@ mod tests {
             role_id: sea_orm::Set(None),
             assignment_scope: sea_orm::Set("user".to_string()),
             priority: sea_orm::Set(100),
+            action_status: sea_orm::Set("enforce".to_string()),
+            purpose: sea_orm::Set(None),
             created_at: sea_orm::Set(now),
             updated_at: sea_orm::Set(now),
         }
@@ -2503,10 +2728,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_catalog(ds_id, "open");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         // No effects from disabled policy → no denied columns
         match vis.filter {
@@ -2519,6 +2741,38 @@ mod tests {
         }
     }
 
+    /// A `column_deny` policy in shadow mode must NOT hide columns.
+    #[tokio::test]
+    async fn test_shadow_policy_column_deny_not_applied() {
+        use crate::entity::policy;
+        use sea_orm::sea_query::Expr;
+
+        let db = setup_visibility_db().await;
+        let ds_id = Uuid::now_v7();
+        let user_id = Uuid::now_v7();
+
+        insert_test_user(&db, user_id).await;
+        insert_test_datasource(&db, ds_id).await;
+        insert_policy_with_column_deny(&db, ds_id, user_id, true, "deny").await;
+        policy::Entity::update_many()
+            .col_expr(policy::Column::ActionStatus, Expr::value("shadow"))
+            .exec(&db)
+            .await
+            .unwrap();
+
+        let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
+        let catalog = make_catalog(ds_id, "open");
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
+
+        if let Some(f) = vis.filter {
+            assert!(
+                f.denied_columns.is_empty(),
+                "Shadow policy should not contribute denied columns, got: {:?}",
+                f.denied_columns
+            );
+        }
+    }
+
     /// Enabled `column_deny` policy MUST appear in denied_columns.
     #[tokio::test]
     async fn test_enabled_policy_column_deny_applied() {
@@ -2532,10 +2786,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_catalog(ds_id, "open");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let denied = vis
             .filter
@@ -2585,10 +2836,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_catalog(ds_id, "open");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let denied = vis
             .filter
@@ -2634,10 +2882,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_catalog(ds_id, "open");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let denied = vis
             .filter
@@ -2690,10 +2935,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_catalog(ds_id, "open");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let denied = vis
             .filter
@@ -2745,10 +2987,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_catalog(ds_id, "open");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let denied = vis
             .filter
@@ -2792,10 +3031,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_catalog(ds_id, "open");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let denied = vis
             .filter
@@ -2831,10 +3067,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_catalog(ds_id, "open");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let denied = vis
             .filter
@@ -2895,13 +3128,11 @@ mod tests {
             schemas,
             default_schema: "public".to_string(),
             access_mode: "open".to_string(),
+            tags: CatalogTags::default(),
         };
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let denied = vis
             .filter
@@ -2932,6 +3163,8 @@ mod tests {
             secure_config: sea_orm::Set(String::new()),
             is_active: sea_orm::Set(true),
             access_mode: sea_orm::Set(access_mode.to_string()),
+            policy_combining: sea_orm::Set(None),
+            write_mode: sea_orm::Set("read_only".to_string()),
             last_sync_at: sea_orm::Set(None),
             last_sync_result: sea_orm::Set(None),
             created_at: sea_orm::Set(now),
@@ -2968,8 +3201,11 @@ mod tests {
             targets: sea_orm::Set(targets_json),
             definition: sea_orm::Set(None),
             is_enabled: sea_orm::Set(true),
+            action_status: sea_orm::Set("enforce".to_string()),
             version: sea_orm::Set(1),
             decision_function_id: sea_orm::Set(None),
+            template_id: sea_orm::Set(None),
+            purpose: sea_orm::Set(None),
             created_by: sea_orm::Set(user_id),
             updated_by: sea_orm::Set(user_id),
             created_at: sea_orm::Set(now),
@@ -2987,6 +3223,8 @@ mod tests {
             role_id: sea_orm::Set(None),
             assignment_scope: sea_orm::Set("all".to_string()),
             priority: sea_orm::Set(100),
+            action_status: sea_orm::Set("enforce".to_string()),
+            purpose: sea_orm::Set(None),
             created_at: sea_orm::Set(now),
             updated_at: sea_orm::Set(now),
         }
@@ -3022,6 +3260,7 @@ mod tests {
             schemas,
             default_schema: "public_alias".to_string(),
             access_mode: access_mode.to_string(),
+            tags: CatalogTags::default(),
         }
     }
 
@@ -3042,10 +3281,7 @@ mod tests {
 
         let cache = EngineCache::new(db, [0u8; 32], shared_wasm_runtime());
         let catalog = make_aliased_catalog(ds_id, "policy_required");
-        let vis = cache
-            .compute_user_visibility(user_id, &catalog)
-            .await
-            .unwrap();
+        let vis = cache.user_visibility(user_id, &catalog).await.unwrap();
 
         let filter = vis
             .filter
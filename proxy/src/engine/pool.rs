@@ -0,0 +1,366 @@
+//! Upstream connection pools with per-datasource limits.
+//!
+//! `PostgresConnectionPool` only takes the pool size and `application_name` as
+//! parameters; its bb8 builder is private. The remaining limits are applied here:
+//! `statement_timeout` is set on each checked-out connection, and a reaper drops
+//! the whole pool once it outlives `max_connection_lifetime_secs` or has been idle
+//! for `idle_timeout_secs` (the next read builds a fresh one). Checkouts go
+//! through [`MeteredPool`], which counts connections in use for the admin API.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use datafusion_table_providers::{
+    UnsupportedTypeAction,
+    postgres::{DynPostgresConnection, DynPostgresConnectionPool},
+    sql::db_connection_pool::{
+        DbConnectionPool, Error as PoolError, JoinPushDown,
+        dbconnection::{
+            AsyncDbConnection, DbConnection,
+            postgresconn::{PostgresConnection, PostgresPooledConnection},
+        },
+        postgrespool::PostgresConnectionPool,
+    },
+    util::secrets::to_secret_map,
+};
+use serde::Serialize;
+use tokio::sync::RwLock as AsyncRwLock;
+use tokio_postgres::types::ToSql;
+
+/// bb8's default `max_size`, used when `max_pool_size` is not set.
+const DEFAULT_MAX_SIZE: u32 = 10;
+
+/// Pool limits configured on a datasource. `None` keeps the library default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolSettings {
+    /// Maximum connections per upstream endpoint.
+    pub max_size: Option<u32>,
+    /// Pools older than this are replaced, closing their connections.
+    pub max_lifetime: Option<Duration>,
+    /// Pools with no checkout for this long are closed.
+    pub idle_timeout: Option<Duration>,
+    /// Upstream `statement_timeout`, in milliseconds.
+    pub statement_timeout_ms: Option<u64>,
+    /// Reported to the upstream as `application_name`.
+    pub application_name: Option<String>,
+}
+
+impl PoolSettings {
+    /// How often the reaper should run, or `None` when nothing expires.
+    pub(super) fn reap_interval(&self) -> Option<Duration> {
+        let shortest = [self.max_lifetime, self.idle_timeout]
+            .into_iter()
+            .flatten()
+            .min()?;
+        Some((shortest / 4).clamp(Duration::from_secs(1), Duration::from_secs(30)))
+    }
+}
+
+/// Pool utilization of one upstream endpoint, as reported by
+/// `GET /datasources/{id}/upstreams`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolUtilization {
+    /// Whether a pool currently exists (pools are created on first use and
+    /// closed by the lifetime / idle limits).
+    pub open: bool,
+    pub max_size: u32,
+    /// Connections currently checked out by queries.
+    pub in_use: usize,
+    /// Highest `in_use` seen since the datasource was last edited.
+    pub peak_in_use: usize,
+    pub checkouts_total: u64,
+    pub checkout_errors: u64,
+    pub age_secs: Option<u64>,
+}
+
+/// Usage counters shared by every pool an endpoint builds over time.
+#[derive(Debug)]
+struct PoolCounters {
+    in_use: AtomicUsize,
+    peak_in_use: AtomicUsize,
+    checkouts: AtomicU64,
+    errors: AtomicU64,
+    last_used: Mutex<Instant>,
+}
+
+impl PoolCounters {
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+}
+
+#[derive(Clone)]
+struct PoolHandle {
+    raw: Arc<PostgresConnectionPool>,
+    metered: Arc<DynPostgresConnectionPool>,
+    created_at: Instant,
+}
+
+/// Lazily-initialized connection pool shared across SessionContext rebuilds.
+///
+/// The pool is not created until the first user-table query, so pg_catalog /
+/// information_schema queries (e.g. TablePlus sidebar population) complete
+/// instantly without an upstream connection.
+pub(super) struct LazyPool {
+    pool: AsyncRwLock<Option<PoolHandle>>,
+    params: HashMap<String, String>,
+    settings: PoolSettings,
+    counters: Arc<PoolCounters>,
+}
+
+impl std::fmt::Debug for LazyPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LazyPool { ... }")
+    }
+}
+
+impl LazyPool {
+    pub(super) fn new(params: HashMap<String, String>, settings: PoolSettings) -> Self {
+        Self {
+            pool: AsyncRwLock::new(None),
+            params,
+            settings,
+            counters: Arc::new(PoolCounters {
+                in_use: AtomicUsize::new(0),
+                peak_in_use: AtomicUsize::new(0),
+                checkouts: AtomicU64::new(0),
+                errors: AtomicU64::new(0),
+                last_used: Mutex::new(Instant::now()),
+            }),
+        }
+    }
+
+    /// Return the shared pool, creating it on first call (one round-trip via bb8).
+    pub(super) async fn get(&self) -> Result<Arc<DynPostgresConnectionPool>, String> {
+        Ok(self.handle().await?.metered)
+    }
+
+    /// Like [`Self::get`], but the unmetered pool, for direct connections
+    /// (upstream health probes).
+    pub(super) async fn get_concrete(&self) -> Result<Arc<PostgresConnectionPool>, String> {
+        Ok(self.handle().await?.raw)
+    }
+
+    async fn handle(&self) -> Result<PoolHandle, String> {
+        // Fast path: already initialised
+        {
+            let guard = self.pool.read().await;
+            if let Some(ref p) = *guard {
+                return Ok(p.clone());
+            }
+        }
+
+        // Slow path: create pool (serialised by write lock — double-check inside)
+        let mut guard = self.pool.write().await;
+        if let Some(ref p) = *guard {
+            return Ok(p.clone());
+        }
+
+        tracing::debug!("Creating upstream pool (first user-table query)");
+        let postgres_params = to_secret_map(self.params.clone());
+        let raw = PostgresConnectionPool::new(postgres_params)
+            .await
+            .map_err(|e| format!("Failed to create Postgres pool: {e}"))?
+            .with_unsupported_type_action(UnsupportedTypeAction::String);
+        let raw = Arc::new(raw);
+        let metered: Arc<DynPostgresConnectionPool> = Arc::new(MeteredPool {
+            inner: raw.clone(),
+            counters: self.counters.clone(),
+            statement_timeout_ms: self.settings.statement_timeout_ms,
+        });
+        self.counters.touch();
+        let handle = PoolHandle {
+            raw,
+            metered,
+            created_at: Instant::now(),
+        };
+        *guard = Some(handle.clone());
+        Ok(handle)
+    }
+
+    /// Drop the pool if it outlived `max_lifetime` or sat idle past
+    /// `idle_timeout`. Queries still holding it finish on the old connections.
+    pub(super) async fn reap(&self, address: &str) {
+        let mut guard = self.pool.write().await;
+        let Some(handle) = guard.as_ref() else {
+            return;
+        };
+        let expired = self
+            .settings
+            .max_lifetime
+            .is_some_and(|lifetime| handle.created_at.elapsed() >= lifetime);
+        let idle = self.settings.idle_timeout.is_some_and(|timeout| {
+            self.counters.in_use.load(Ordering::Relaxed) == 0
+                && self.counters.last_used.lock().unwrap().elapsed() >= timeout
+        });
+        if expired || idle {
+            tracing::debug!(
+                endpoint = %address,
+                reason = if expired { "max lifetime" } else { "idle timeout" },
+                "Closing upstream pool"
+            );
+            *guard = None;
+        }
+    }
+
+    pub(super) async fn utilization(&self) -> PoolUtilization {
+        let age_secs = self
+            .pool
+            .read()
+            .await
+            .as_ref()
+            .map(|h| h.created_at.elapsed().as_secs());
+        PoolUtilization {
+            open: age_secs.is_some(),
+            max_size: self.settings.max_size.unwrap_or(DEFAULT_MAX_SIZE),
+            in_use: self.counters.in_use.load(Ordering::Relaxed),
+            peak_in_use: self.counters.peak_in_use.load(Ordering::Relaxed),
+            checkouts_total: self.counters.checkouts.load(Ordering::Relaxed),
+            checkout_errors: self.counters.errors.load(Ordering::Relaxed),
+            age_secs,
+        }
+    }
+}
+
+/// Wraps the library pool to count checkouts and apply `statement_timeout`.
+struct MeteredPool {
+    inner: Arc<PostgresConnectionPool>,
+    counters: Arc<PoolCounters>,
+    statement_timeout_ms: Option<u64>,
+}
+
+impl MeteredPool {
+    async fn checkout(&self) -> Result<Box<DynPostgresConnection>, PoolError> {
+        let conn = self.inner.connect().await?;
+        if let Some(ms) = self.statement_timeout_ms {
+            let pg = conn
+                .as_any()
+                .downcast_ref::<PostgresConnection>()
+                .ok_or("upstream connection is not a Postgres connection")?;
+            pg.conn
+                .batch_execute(&format!("SET statement_timeout = {ms}"))
+                .await?;
+        }
+        Ok(conn)
+    }
+}
+
+#[async_trait]
+impl DbConnectionPool<PostgresPooledConnection, &'static (dyn ToSql + Sync)> for MeteredPool {
+    async fn connect(&self) -> Result<Box<DynPostgresConnection>, PoolError> {
+        self.counters.touch();
+        self.counters.checkouts.fetch_add(1, Ordering::Relaxed);
+        let conn = match self.checkout().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                self.counters.errors.fetch_add(1, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+        let in_use = self.counters.in_use.fetch_add(1, Ordering::Relaxed) + 1;
+        self.counters
+            .peak_in_use
+            .fetch_max(in_use, Ordering::Relaxed);
+        Ok(Box::new(MeteredConnection {
+            inner: conn,
+            counters: self.counters.clone(),
+        }))
+    }
+
+    fn join_push_down(&self) -> JoinPushDown {
+        self.inner.join_push_down()
+    }
+}
+
+/// A checked-out connection; releases its `in_use` slot when dropped.
+/// Downcasts reach the wrapped `PostgresConnection`.
+struct MeteredConnection {
+    inner: Box<DynPostgresConnection>,
+    counters: Arc<PoolCounters>,
+}
+
+impl Drop for MeteredConnection {
+    fn drop(&mut self) {
+        self.counters.in_use.fetch_sub(1, Ordering::Relaxed);
+        self.counters.touch();
+    }
+}
+
+impl DbConnection<PostgresPooledConnection, &'static (dyn ToSql + Sync)> for MeteredConnection {
+    fn as_any(&self) -> &dyn Any {
+        self.inner.as_any()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self.inner.as_any_mut()
+    }
+
+    fn as_async(
+        &self,
+    ) -> Option<&dyn AsyncDbConnection<PostgresPooledConnection, &'static (dyn ToSql + Sync)>> {
+        self.inner.as_async()
+    }
+}
+
+/// A connection to the primary checked out for a governed write. It counts
+/// against the pool like any other checkout and carries its `statement_timeout`.
+pub struct WriteConnection {
+    conn: Box<DynPostgresConnection>,
+}
+
+impl WriteConnection {
+    pub(super) async fn checkout(pool: &DynPostgresConnectionPool) -> Result<Self, String> {
+        let conn = pool.connect().await.map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    /// The client to open the write's transaction on.
+    pub fn client(&mut self) -> Result<&mut tokio_postgres::Client, String> {
+        let pg = self
+            .conn
+            .as_any_mut()
+            .downcast_mut::<PostgresConnection>()
+            .ok_or("upstream connection is not a Postgres connection")?;
+        Ok(&mut pg.conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reap_interval_follows_shortest_limit() {
+        assert_eq!(PoolSettings::default().reap_interval(), None);
+        let settings = PoolSettings {
+            max_lifetime: Some(Duration::from_secs(600)),
+            idle_timeout: Some(Duration::from_secs(20)),
+            ..Default::default()
+        };
+        assert_eq!(settings.reap_interval(), Some(Duration::from_secs(5)));
+        let settings = PoolSettings {
+            idle_timeout: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        assert_eq!(settings.reap_interval(), Some(Duration::from_secs(1)));
+        let settings = PoolSettings {
+            max_lifetime: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        };
+        assert_eq!(settings.reap_interval(), Some(Duration::from_secs(30)));
+    }
+
+    #[tokio::test]
+    async fn unopened_pool_reports_defaults() {
+        let pool = LazyPool::new(HashMap::new(), PoolSettings::default());
+        let u = pool.utilization().await;
+        assert!(!u.open);
+        assert_eq!(u.max_size, DEFAULT_MAX_SIZE);
+        assert_eq!(u.in_use, 0);
+        assert_eq!(u.age_secs, None);
+    }
+}
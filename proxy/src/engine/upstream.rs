@@ -0,0 +1,552 @@
+//! Upstream endpoint routing for a datasource: the primary plus optional read replicas.
+//!
+//! Every endpoint gets its own [`LazyPool`]. When a datasource has more than one
+//! endpoint, a background task probes each of them with `pg_is_in_recovery()` and
+//! replay lag, so routing follows promotions (an endpoint that leaves recovery is
+//! treated as the primary, whatever its configured role) and skips endpoints that
+//! are down or lag beyond `max_replica_lag_secs`.
+//!
+//! Reads go round-robin over eligible replicas, falling back to the primary (or the
+//! other way round with `read_preference = primary`). If the chosen endpoint cannot
+//! be reached it is marked down and the next candidate is tried, so failover does
+//! not wait for the next probe. The endpoint picked for a query is reused for every
+//! table that query resolves, and reported to the audit log via [`track_served_by`].
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use datafusion_table_providers::postgres::DynPostgresConnectionPool;
+use serde::Serialize;
+
+use super::pool::{LazyPool, PoolSettings, PoolUtilization};
+use super::{DataSourceConfig, build_postgres_params};
+
+/// How often endpoints of a replicated datasource are probed.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Upper bound for one probe or one pool creation while routing.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Role and replay lag in seconds (`NULL` on a primary; `0` when a replica has
+/// replayed everything it received, so an idle primary does not look like lag).
+const PROBE_SQL: &str = "SELECT pg_is_in_recovery(), \
+     CASE WHEN NOT pg_is_in_recovery() THEN NULL \
+          WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0::float8 \
+          ELSE EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp())::float8 END";
+
+/// Where reads should go when replicas are configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadPreference {
+    /// Healthy replicas first, the primary when none is eligible.
+    #[default]
+    Replica,
+    /// The primary first, replicas only when the primary is down.
+    Primary,
+}
+
+impl ReadPreference {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "replica" => Some(Self::Replica),
+            "primary" => Some(Self::Primary),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointRole {
+    Primary,
+    Replica,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Health {
+    /// `None` until the first probe or connection attempt.
+    up: Option<bool>,
+    /// Observed role; `None` until probed.
+    in_recovery: Option<bool>,
+    lag_secs: Option<f64>,
+    last_error: Option<String>,
+    last_checked_at: Option<chrono::NaiveDateTime>,
+}
+
+struct Endpoint {
+    address: String,
+    configured_role: EndpointRole,
+    pool: LazyPool,
+    health: Mutex<Health>,
+}
+
+impl Endpoint {
+    fn health(&self) -> Health {
+        self.health.lock().unwrap().clone()
+    }
+
+    fn is_primary_now(&self, h: &Health) -> bool {
+        match h.in_recovery {
+            Some(in_recovery) => !in_recovery,
+            None => self.configured_role == EndpointRole::Primary,
+        }
+    }
+}
+
+/// Health of one endpoint as reported by `GET /datasources/{id}/upstreams`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointStatus {
+    pub address: String,
+    pub configured_role: EndpointRole,
+    /// Role observed by the last probe, if any.
+    pub current_role: Option<EndpointRole>,
+    /// `"up"`, `"down"` or `"unknown"` (not probed yet).
+    pub state: &'static str,
+    pub lag_secs: Option<f64>,
+    /// Whether reads may currently be routed here.
+    pub eligible: bool,
+    pub last_error: Option<String>,
+    pub last_checked_at: Option<chrono::NaiveDateTime>,
+    pub pool: PoolUtilization,
+}
+
+/// All upstream endpoints of one datasource. Index 0 is the configured primary.
+pub(super) struct UpstreamSet {
+    endpoints: Vec<Endpoint>,
+    read_preference: ReadPreference,
+    max_lag_secs: Option<f64>,
+    pool_settings: PoolSettings,
+    next: AtomicUsize,
+}
+
+impl std::fmt::Debug for UpstreamSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpstreamSet")
+            .field(
+                "endpoints",
+                &self
+                    .endpoints
+                    .iter()
+                    .map(|e| &e.address)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// The endpoint chosen for the query currently being planned on this task.
+#[derive(Clone)]
+struct ServedBy {
+    set: usize,
+    index: usize,
+    address: String,
+}
+
+tokio::task_local! {
+    static SERVED_BY: RefCell<Option<ServedBy>>;
+}
+
+/// Run `fut` (query planning) while recording which upstream endpoint its table
+/// lookups were routed to. All tables of the query share that endpoint.
+pub async fn track_served_by<F: Future>(fut: F) -> (F::Output, Option<String>) {
+    SERVED_BY
+        .scope(RefCell::new(None), async move {
+            let output = fut.await;
+            let served_by = SERVED_BY.with(|s| s.borrow().as_ref().map(|s| s.address.clone()));
+            (output, served_by)
+        })
+        .await
+}
+
+impl UpstreamSet {
+    pub(super) fn new(cfg: &DataSourceConfig) -> Arc<Self> {
+        let primary = build_postgres_params(cfg);
+        let mut endpoints = vec![Endpoint {
+            address: format!("{}:{}", cfg.host, cfg.port),
+            configured_role: EndpointRole::Primary,
+            pool: LazyPool::new(primary.clone(), cfg.pool.clone()),
+            health: Mutex::new(Health::default()),
+        }];
+        for (host, port) in &cfg.replicas {
+            let mut params = primary.clone();
+            params.insert("host".to_string(), host.clone());
+            params.insert("port".to_string(), port.to_string());
+            endpoints.push(Endpoint {
+                address: format!("{host}:{port}"),
+                configured_role: EndpointRole::Replica,
+                pool: LazyPool::new(params, cfg.pool.clone()),
+                health: Mutex::new(Health::default()),
+            });
+        }
+        Arc::new(Self {
+            endpoints,
+            read_preference: cfg.read_preference,
+            max_lag_secs: cfg.max_replica_lag_secs.map(|s| s as f64),
+            pool_settings: cfg.pool.clone(),
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    fn is_replicated(&self) -> bool {
+        self.endpoints.len() > 1
+    }
+
+    /// Start the background prober. It stops once the set is dropped (datasource
+    /// edited or deleted and every session built on it closed).
+    pub(super) fn spawn_health_checker(self: &Arc<Self>) {
+        if !self.is_replicated() {
+            return;
+        }
+        let weak: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let Some(set) = weak.upgrade() else { break };
+                set.probe_all().await;
+                drop(set);
+                tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Start the task that closes pools past their configured lifetime or idle
+    /// timeout. Like the health checker, it stops once the set is dropped.
+    pub(super) fn spawn_pool_reaper(self: &Arc<Self>) {
+        let Some(interval) = self.pool_settings.reap_interval() else {
+            return;
+        };
+        let weak: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(set) = weak.upgrade() else { break };
+                for endpoint in &set.endpoints {
+                    endpoint.pool.reap(&endpoint.address).await;
+                }
+            }
+        });
+    }
+
+    async fn probe_all(&self) {
+        futures::future::join_all((0..self.endpoints.len()).map(|i| self.probe(i))).await;
+    }
+
+    async fn probe(&self, index: usize) {
+        let endpoint = &self.endpoints[index];
+        let result = tokio::time::timeout(CONNECT_TIMEOUT, async {
+            let pool = endpoint.pool.get_concrete().await?;
+            let conn = pool.connect_direct().await.map_err(|e| e.to_string())?;
+            let row = conn
+                .conn
+                .query_one(PROBE_SQL, &[])
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok::<_, String>((row.get::<_, bool>(0), row.get::<_, Option<f64>>(1)))
+        })
+        .await
+        .unwrap_or_else(|_| Err("health check timed out".to_string()));
+
+        let mut health = endpoint.health.lock().unwrap();
+        let was_up = health.up;
+        health.last_checked_at = Some(chrono::Utc::now().naive_utc());
+        match result {
+            Ok((in_recovery, lag_secs)) => {
+                if health.in_recovery == Some(true) && !in_recovery {
+                    tracing::warn!(endpoint = %endpoint.address, "Upstream endpoint promoted to primary");
+                }
+                health.up = Some(true);
+                health.in_recovery = Some(in_recovery);
+                health.lag_secs = lag_secs;
+                health.last_error = None;
+                if was_up == Some(false) {
+                    tracing::info!(endpoint = %endpoint.address, "Upstream endpoint recovered");
+                }
+            }
+            Err(e) => {
+                if was_up != Some(false) {
+                    tracing::warn!(endpoint = %endpoint.address, error = %e, "Upstream endpoint down");
+                }
+                health.up = Some(false);
+                health.last_error = Some(e);
+            }
+        }
+    }
+
+    fn mark_down(&self, index: usize, error: &str) {
+        if !self.is_replicated() {
+            return;
+        }
+        let endpoint = &self.endpoints[index];
+        let mut health = endpoint.health.lock().unwrap();
+        if health.up != Some(false) {
+            tracing::warn!(endpoint = %endpoint.address, error = %error, "Upstream endpoint unreachable; failing over");
+        }
+        health.up = Some(false);
+        health.last_error = Some(error.to_string());
+    }
+
+    fn lag_ok(&self, h: &Health) -> bool {
+        match (self.max_lag_secs, h.lag_secs) {
+            (Some(max), Some(lag)) => lag <= max,
+            _ => true,
+        }
+    }
+
+    /// Endpoint indices in the order reads should try them. Replicas known to lag
+    /// past the threshold are left out; endpoints marked down go last, since a
+    /// connection attempt is also how they come back before the next probe.
+    fn read_candidates(&self) -> Vec<usize> {
+        if !self.is_replicated() {
+            return vec![0];
+        }
+        let health: Vec<Health> = self.endpoints.iter().map(Endpoint::health).collect();
+        let mut primaries = Vec::new();
+        let mut replicas = Vec::new();
+        let mut down = Vec::new();
+        for (i, (endpoint, h)) in self.endpoints.iter().zip(&health).enumerate() {
+            if h.up == Some(false) {
+                down.push(i);
+            } else if endpoint.is_primary_now(h) {
+                primaries.push(i);
+            } else if self.lag_ok(h) {
+                replicas.push(i);
+            }
+        }
+        if !replicas.is_empty() {
+            let start = self.next.fetch_add(1, Ordering::Relaxed) % replicas.len();
+            replicas.rotate_left(start);
+        }
+        let mut order = match self.read_preference {
+            ReadPreference::Replica => [replicas, primaries].concat(),
+            ReadPreference::Primary => [primaries, replicas].concat(),
+        };
+        // Down endpoints: prefer the configured primary, skip known-lagging replicas.
+        down.retain(|&i| {
+            self.endpoints[i].configured_role == EndpointRole::Primary || self.lag_ok(&health[i])
+        });
+        order.extend(down);
+        order
+    }
+
+    /// Pick the upstream pool for a read, failing over across endpoints.
+    pub(super) async fn route_read(
+        self: &Arc<Self>,
+    ) -> Result<Arc<DynPostgresConnectionPool>, String> {
+        let set_id = Arc::as_ptr(self) as usize;
+        let sticky = SERVED_BY
+            .try_with(|s| s.borrow().clone())
+            .ok()
+            .flatten()
+            .filter(|s| s.set == set_id);
+        if let Some(served) = sticky {
+            return self.endpoints[served.index].pool.get().await;
+        }
+
+        let mut last_error = "no upstream endpoint available".to_string();
+        for index in self.read_candidates() {
+            let endpoint = &self.endpoints[index];
+            let attempt = if self.is_replicated() {
+                tokio::time::timeout(CONNECT_TIMEOUT, endpoint.pool.get())
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(format!("connecting to {} timed out", endpoint.address))
+                    })
+            } else {
+                endpoint.pool.get().await
+            };
+            match attempt {
+                Ok(pool) => {
+                    let _ = SERVED_BY.try_with(|s| {
+                        *s.borrow_mut() = Some(ServedBy {
+                            set: set_id,
+                            index,
+                            address: endpoint.address.clone(),
+                        });
+                    });
+                    return Ok(pool);
+                }
+                Err(e) => {
+                    self.mark_down(index, &e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Pick the primary's pool for a write, with its address. Writes never fail
+    /// over to a replica: the endpoint last probed as primary is used, or the
+    /// configured primary before any probe.
+    pub(super) async fn route_write(
+        &self,
+    ) -> Result<(Arc<DynPostgresConnectionPool>, String), String> {
+        let index = self.write_candidate();
+        let endpoint = &self.endpoints[index];
+        match endpoint.pool.get().await {
+            Ok(pool) => Ok((pool, endpoint.address.clone())),
+            Err(e) => {
+                self.mark_down(index, &e);
+                Err(e)
+            }
+        }
+    }
+
+    fn write_candidate(&self) -> usize {
+        self.endpoints
+            .iter()
+            .position(|e| {
+                let h = e.health();
+                h.up != Some(false) && h.in_recovery == Some(false)
+            })
+            .unwrap_or(0)
+    }
+
+    pub(super) async fn status(&self) -> Vec<EndpointStatus> {
+        let eligible = self.read_candidates();
+        let mut statuses = Vec::with_capacity(self.endpoints.len());
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            let h = endpoint.health();
+            let current_role = h.in_recovery.map(|r| {
+                if r {
+                    EndpointRole::Replica
+                } else {
+                    EndpointRole::Primary
+                }
+            });
+            statuses.push(EndpointStatus {
+                address: endpoint.address.clone(),
+                configured_role: endpoint.configured_role,
+                current_role,
+                state: match h.up {
+                    Some(true) => "up",
+                    Some(false) => "down",
+                    None => "unknown",
+                },
+                lag_secs: h.lag_secs,
+                eligible: h.up != Some(false) && eligible.contains(&i),
+                last_error: h.last_error,
+                last_checked_at: h.last_checked_at,
+                pool: endpoint.pool.utilization().await,
+            });
+        }
+        statuses
+    }
+
+    /// Test every endpoint once (admin "test connection").
+    pub(super) async fn test_all(&self) -> Result<(), String> {
+        let mut failures = Vec::new();
+        for endpoint in &self.endpoints {
+            let result = tokio::time::timeout(CONNECT_TIMEOUT * 2, endpoint.pool.get_concrete())
+                .await
+                .unwrap_or_else(|_| Err("timed out".to_string()));
+            if let Err(e) = result {
+                failures.push(format!("{}: {e}", endpoint.address));
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures.join("; "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg(replicas: &[(&str, u16)], read_preference: ReadPreference) -> DataSourceConfig {
+        DataSourceConfig {
+            host: "primary".to_string(),
+            port: 5432,
+            database: "db".to_string(),
+            username: "u".to_string(),
+            password: "p".to_string(),
+            ssl_mode: "disable".to_string(),
+            replicas: replicas.iter().map(|(h, p)| (h.to_string(), *p)).collect(),
+            read_preference,
+            max_replica_lag_secs: Some(30),
+            pool: Default::default(),
+        }
+    }
+
+    fn set_health(set: &UpstreamSet, index: usize, up: bool, in_recovery: bool, lag: Option<f64>) {
+        let mut h = set.endpoints[index].health.lock().unwrap();
+        h.up = Some(up);
+        h.in_recovery = Some(in_recovery);
+        h.lag_secs = lag;
+    }
+
+    #[test]
+    fn single_endpoint_always_routes_to_primary() {
+        let set = UpstreamSet::new(&cfg(&[], ReadPreference::Replica));
+        assert_eq!(set.read_candidates(), vec![0]);
+        set.mark_down(0, "boom");
+        assert_eq!(set.read_candidates(), vec![0]);
+    }
+
+    #[test]
+    fn writes_follow_promotion() {
+        let set = UpstreamSet::new(&cfg(&[("r1", 5432)], ReadPreference::Replica));
+        assert_eq!(
+            set.write_candidate(),
+            0,
+            "configured primary before any probe"
+        );
+        set_health(&set, 0, true, true, Some(0.0));
+        set_health(&set, 1, true, false, None);
+        assert_eq!(set.write_candidate(), 1, "promoted replica takes writes");
+        set_health(&set, 1, false, false, None);
+        assert_eq!(set.write_candidate(), 0, "never a replica that is down");
+    }
+
+    #[test]
+    fn reads_round_robin_over_replicas_then_primary() {
+        let set = UpstreamSet::new(&cfg(&[("r1", 5432), ("r2", 5433)], ReadPreference::Replica));
+        let first = set.read_candidates();
+        let second = set.read_candidates();
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[2], 0, "primary is the fallback");
+        assert_ne!(first[0], second[0], "replicas rotate");
+        assert_eq!(set.endpoints[2].address, "r2:5433");
+    }
+
+    #[test]
+    fn primary_preference_keeps_replicas_as_fallback() {
+        let set = UpstreamSet::new(&cfg(&[("r1", 5432)], ReadPreference::Primary));
+        assert_eq!(set.read_candidates(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn lagging_replicas_are_skipped() {
+        let set = UpstreamSet::new(&cfg(&[("r1", 5432), ("r2", 5432)], ReadPreference::Replica));
+        set_health(&set, 0, true, false, None);
+        set_health(&set, 1, true, true, Some(120.0));
+        set_health(&set, 2, true, true, Some(1.0));
+        assert_eq!(set.read_candidates(), vec![2, 0]);
+        let status = set.status().await;
+        assert!(!status[1].eligible);
+        assert!(status[2].eligible);
+    }
+
+    #[tokio::test]
+    async fn promoted_replica_becomes_primary_and_down_primary_goes_last() {
+        let set = UpstreamSet::new(&cfg(&[("r1", 5432)], ReadPreference::Primary));
+        set_health(&set, 0, false, false, None);
+        set_health(&set, 1, true, false, None);
+        assert_eq!(set.read_candidates(), vec![1, 0]);
+        assert_eq!(
+            set.status().await[1].current_role,
+            Some(EndpointRole::Primary)
+        );
+        assert_eq!(set.status().await[0].state, "down");
+    }
+
+    #[tokio::test]
+    async fn track_served_by_reports_nothing_without_routing() {
+        let (value, served) = track_served_by(async { 7 }).await;
+        assert_eq!(value, 7);
+        assert_eq!(served, None);
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// A long-lived credential that lets a proxy user call the HTTP query API.
+/// Only a SHA-256 hash of the secret is stored; `key_prefix` is the lookup handle.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "api_key")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub created_at: DateTime,
+    pub last_used_at: Option<DateTime>,
+    pub expires_at: Option<DateTime>,
+    /// JSON array of granted scopes (e.g. `["admin:policies"]`). Empty = query-only.
+    #[sea_orm(default_value = "[]")]
+    pub scopes: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::proxy_user::Entity",
+        from = "Column::UserId",
+        to = "super::proxy_user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    ProxyUser,
+}
+
+impl Related<super::proxy_user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProxyUser.def()
+    }
+}
+
+impl Model {
+    /// Parsed `scopes`; malformed JSON is treated as no scopes.
+    pub fn scope_list(&self) -> Vec<String> {
+        serde_json::from_str(&self.scopes).unwrap_or_default()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -16,6 +16,12 @@ pub struct Model {
     pub is_active: bool,
     /// "open" (no policies = full access) or "policy_required" (no policies = empty results)
     pub access_mode: String,
+    /// JSON object: combination strategy per permit policy type
+    /// (see `policy_combining::CombiningRules`). `None` = defaults.
+    pub policy_combining: Option<String>,
+    /// "read_only" (every write is rejected) or "read_write" (INSERT/UPDATE/DELETE run
+    /// upstream under `write_allow` / `write_deny` policies)
+    pub write_mode: String,
     pub last_sync_at: Option<DateTime>,
     pub last_sync_result: Option<String>,
     pub created_at: DateTime,
@@ -0,0 +1,40 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// A purpose users may declare on a datasource's sessions.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "data_source_purpose")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub data_source_id: Uuid,
+    /// Unique per datasource; what clients declare and policies are restricted to.
+    pub name: String,
+    pub description: Option<String>,
+    /// Whether declaring the purpose needs a non-empty justification.
+    pub require_justification: bool,
+    /// JSON `JustificationValidator` the justification must pass. Implies
+    /// `require_justification`.
+    pub justification_validator: Option<String>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::data_source::Entity",
+        from = "Column::DataSourceId",
+        to = "super::data_source::Column::Id",
+        on_delete = "Cascade"
+    )]
+    DataSource,
+}
+
+impl Related<super::data_source::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DataSource.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
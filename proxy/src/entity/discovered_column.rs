@@ -16,6 +16,10 @@ pub struct Model {
     pub arrow_type: Option<String>,
     pub is_selected: bool,
     pub discovered_at: DateTime,
+    /// JSON array of catalog tags set on this object (e.g. `["pii", "sensitivity:high"]`).
+    /// Applied on top of the tags inherited from the table and schema; see
+    /// `policy_match::CatalogTags`. None = no tags.
+    pub tags: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -11,6 +11,9 @@ pub struct Model {
     pub table_type: String,
     pub is_selected: bool,
     pub discovered_at: DateTime,
+    /// JSON array of catalog tags set on this object (e.g. `["pii", "sensitivity:high"]`).
+    /// Inherited by the objects below it; see `policy_match::CatalogTags`. None = no tags.
+    pub tags: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
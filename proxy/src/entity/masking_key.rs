@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "masking_key")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    /// Name that mask expressions use as the key reference, e.g. `br_hash(email, 'crm_salt')`.
+    #[sea_orm(unique)]
+    pub name: String,
+    pub description: Option<String>,
+    /// AES-256-GCM encrypted JSON string holding the base64 key material.
+    pub secret_encrypted: String,
+    /// Whether `br_fpe_encrypt` output under this key may be revealed, and `br_pseudonymize`
+    /// tokens re-identified, through the admin API.
+    pub reversible: bool,
+    pub version: i32,
+    pub created_by: Uuid,
+    pub updated_by: Uuid,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
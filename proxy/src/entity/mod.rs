@@ -1,17 +1,24 @@
 pub mod admin_audit_log;
+pub mod api_key;
 pub mod attribute_definition;
 pub mod column_anchor;
 pub mod data_source;
 pub mod data_source_access;
+pub mod data_source_purpose;
 pub mod decision_function;
 pub mod discovered_column;
 pub mod discovered_schema;
 pub mod discovered_table;
+pub mod masking_key;
 pub mod policy;
 pub mod policy_assignment;
+pub mod policy_template;
 pub mod policy_version;
+pub mod privacy_budget;
 pub mod proxy_user;
+pub mod pseudonym_vault;
 pub mod query_audit_log;
+pub mod result_quota;
 pub mod role;
 pub mod role_inheritance;
 pub mod role_member;
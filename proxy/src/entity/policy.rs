@@ -16,8 +16,14 @@ pub struct Model {
     /// JSON definition (filter_expression or mask_expression). Null for non-expression types.
     pub definition: Option<String>,
     pub is_enabled: bool,
+    /// "enforce" | "shadow" — shadow policies are evaluated and audited but not applied.
+    pub action_status: String,
     pub version: i32,
     pub decision_function_id: Option<Uuid>,
+    /// Template supplying the expression; `definition` then holds only its `params`.
+    pub template_id: Option<Uuid>,
+    /// Declared purpose the policy is restricted to; `None` applies whatever the purpose.
+    pub purpose: Option<String>,
     pub created_by: Uuid,
     pub updated_by: Uuid,
     pub created_at: DateTime,
@@ -36,6 +42,12 @@ pub enum Relation {
         to = "super::decision_function::Column::Id"
     )]
     DecisionFunction,
+    #[sea_orm(
+        belongs_to = "super::policy_template::Entity",
+        from = "Column::TemplateId",
+        to = "super::policy_template::Column::Id"
+    )]
+    PolicyTemplate,
 }
 
 impl Related<super::policy_version::Entity> for Entity {
@@ -56,4 +68,10 @@ impl Related<super::decision_function::Entity> for Entity {
     }
 }
 
+impl Related<super::policy_template::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PolicyTemplate.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -16,6 +16,11 @@ pub struct Model {
     pub assignment_scope: String,
     /// Lower = higher precedence
     pub priority: i32,
+    /// "enforce" | "shadow". An enforcing assignment of the same policy wins.
+    pub action_status: String,
+    /// Declared purpose the assignment is restricted to, from the datasource's
+    /// purpose catalog; `None` applies whatever the purpose.
+    pub purpose: Option<String>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
@@ -0,0 +1,37 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "policy_template")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(unique)]
+    pub name: String,
+    pub description: Option<String>,
+    /// "column_mask" | "row_filter" — the policy type this template can back.
+    pub template_type: String,
+    /// Expression with `{col}` (masks only) and `{param.NAME}` placeholders.
+    pub expression: String,
+    /// JSON array of TemplateParam (name, type, default?).
+    pub parameters: String,
+    pub version: i32,
+    pub created_by: Uuid,
+    pub updated_by: Uuid,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::policy::Entity")]
+    Policy,
+}
+
+impl Related<super::policy::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Policy.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
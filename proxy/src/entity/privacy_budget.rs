@@ -0,0 +1,41 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// Epsilon spent by one user on one datasource under `differential_privacy` policies.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "privacy_budget")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub data_source_id: Uuid,
+    pub epsilon_spent: f64,
+    /// Queries that were charged against the budget.
+    pub query_count: i64,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::proxy_user::Entity",
+        from = "Column::UserId",
+        to = "super::proxy_user::Column::Id"
+    )]
+    ProxyUser,
+    #[sea_orm(
+        belongs_to = "super::data_source::Entity",
+        from = "Column::DataSourceId",
+        to = "super::data_source::Column::Id"
+    )]
+    DataSource,
+}
+
+impl Related<super::proxy_user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProxyUser.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,35 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// Token → original value mappings recorded by `br_pseudonymize` for reversible keys.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "pseudonym_vault")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub masking_key_id: Uuid,
+    /// `masking_key.version` the token was computed under; rotation invalidates older rows.
+    pub key_version: i32,
+    pub token: String,
+    /// AES-256-GCM encrypted JSON string holding the original value.
+    pub value_encrypted: String,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::masking_key::Entity",
+        from = "Column::MaskingKeyId",
+        to = "super::masking_key::Column::Id"
+    )]
+    MaskingKey,
+}
+
+impl Related<super::masking_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MaskingKey.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
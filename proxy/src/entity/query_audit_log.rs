@@ -1,7 +1,7 @@
 use sea_orm::entity::prelude::*;
 use uuid::Uuid;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "query_audit_log")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -27,6 +27,37 @@ pub struct Model {
     /// "success" | "error" | "denied"
     pub status: String,
     pub error_message: Option<String>,
+    /// Upstream endpoint (`host:port`) the query's tables were read from.
+    /// `None` when no upstream table was touched or the query failed before routing.
+    pub served_by: Option<String>,
+    /// JSON array of what `shadow` policies would have done to this query
+    /// (`would_deny`, `would_filter`, `would_mask`, …). `None` when no shadow
+    /// policy matched.
+    pub shadow_outcomes: Option<String>,
+    /// Epsilon charged to the user's privacy budget by `differential_privacy`
+    /// policies. `None` when no noised aggregate ran.
+    pub epsilon_spent: Option<f64>,
+    /// Rows sent to the client. `None` when the query produced no result stream.
+    pub rows_returned: Option<i64>,
+    /// Arrow data size of the rows sent to the client.
+    pub bytes_returned: Option<i64>,
+    /// The admin who ran this query as `user_id` in an impersonation preview.
+    /// For a hypothetical user, `user_id` is the nil UUID.
+    pub impersonated_by: Option<Uuid>,
+    /// Purpose the session declared (`purpose` startup parameter or `SET br.purpose`).
+    pub purpose: Option<String>,
+    /// Justification given for the purpose, as the client sent it.
+    pub justification: Option<String>,
+    /// JSON array of targets where several permit policies of one type met, with
+    /// the strategy that combined them and which policies applied or were
+    /// overridden. `None` when no two permits competed.
+    pub policy_conflicts: Option<String>,
+    /// Rows an INSERT, UPDATE or DELETE changed upstream. `None` for reads.
+    pub rows_affected: Option<i64>,
+    /// JSON array of the changed rows as they were after the write (before it,
+    /// for DELETE): the columns the writer can read, masked, capped at
+    /// `write::MAX_AUDITED_ROWS`.
+    pub affected_rows: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// Rows one user received in the current window of one `result_limit` policy.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "result_quota")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub policy_id: Uuid,
+    pub user_id: Uuid,
+    /// Start of the window `rows_used` counts; older windows are reset on use.
+    pub window_start: DateTime,
+    pub rows_used: i64,
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::policy::Entity",
+        from = "Column::PolicyId",
+        to = "super::policy::Column::Id"
+    )]
+    Policy,
+    #[sea_orm(
+        belongs_to = "super::proxy_user::Entity",
+        from = "Column::UserId",
+        to = "super::proxy_user::Column::Id"
+    )]
+    ProxyUser,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
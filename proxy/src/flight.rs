@@ -0,0 +1,535 @@
+//! Arrow Flight SQL front-end.
+//!
+//! A second data plane next to pgwire for columnar clients (ADBC, pyarrow, Spark).
+//! Authentication, datasource access checks, policy enforcement and auditing are
+//! shared with pgwire: sessions are registered in the `ProxyHandler` connection
+//! registry (so policy mutations rebuild them in place) and every read query runs
+//! through [`PolicyHook::run_governed`]. Results are streamed as Arrow record
+//! batches instead of being text-encoded row by row.
+//!
+//! Protocol flow:
+//! 1. `Handshake` with `authorization: Basic <base64(user:pass)>` and a `database`
+//!    header naming the datasource. Optional `purpose` / `justification` headers
+//!    declare a purpose, validated like the pgwire startup parameters. The response
+//!    carries `authorization: Bearer <token>`.
+//! 2. `GetFlightInfo(CommandStatementQuery)` plans, governs and audits the query and
+//!    returns a single-use ticket.
+//! 3. `DoGet(ticket)` streams the policy-enforced batches.
+
+use crate::auth::{Auth, AuthApiError};
+use crate::engine::EngineCache;
+use crate::handler::ProxyHandler;
+use crate::hooks::policy::{PolicyHook, QueryCaller};
+use crate::purpose::{self, Declaration, DeclarationError};
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::sql::metadata::{SqlInfoData, SqlInfoDataBuilder};
+use arrow_flight::sql::server::FlightSqlService;
+use arrow_flight::sql::{
+    CommandGetSqlInfo, CommandStatementQuery, ProstMessageExt, SqlInfo, TicketStatementQuery,
+};
+use arrow_flight::{
+    FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest, HandshakeResponse, Ticket,
+};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use dashmap::DashMap;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::prelude::SessionContext;
+use futures::{Stream, TryStreamExt, stream};
+use pgwire::error::PgWireError;
+use prost::Message;
+use rand_core::{OsRng, RngCore};
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status, Streaming};
+
+/// How long a ticket returned by `GetFlightInfo` stays redeemable.
+const RESULT_TTL: Duration = Duration::from_secs(300);
+
+/// `client_info` recorded in the audit log when the client sends no `application_name`.
+const DEFAULT_CLIENT_INFO: &str = "flight-sql";
+
+static SQL_INFO: LazyLock<SqlInfoData> = LazyLock::new(|| {
+    let mut builder = SqlInfoDataBuilder::new();
+    builder.append(SqlInfo::FlightSqlServerName, "BetweenRows");
+    builder.append(SqlInfo::FlightSqlServerVersion, env!("CARGO_PKG_VERSION"));
+    builder.append(SqlInfo::FlightSqlServerArrowVersion, "57");
+    builder.append(SqlInfo::FlightSqlServerReadOnly, true);
+    builder.append(SqlInfo::FlightSqlServerSql, true);
+    builder.append(SqlInfo::FlightSqlServerSubstrait, false);
+    builder.append(SqlInfo::FlightSqlServerTransaction, 0i32);
+    builder.append(SqlInfo::FlightSqlServerCancel, false);
+    builder
+        .build()
+        .expect("static SqlInfo values are well-formed")
+});
+
+/// An authenticated Flight SQL session (one per successful handshake).
+struct FlightSession {
+    /// ID in the `ProxyHandler` connection registry holding this session's `SessionContext`.
+    conn_id: u64,
+    caller: QueryCaller,
+    last_seen: Instant,
+}
+
+/// A query planned by `GetFlightInfo`, waiting for its `DoGet`.
+struct PendingResult {
+    /// Bearer token of the session that planned the query — only it may redeem the ticket.
+    token: String,
+    /// Wrapped in a `Mutex` only to make the entry `Sync`; it is taken out exactly once.
+    stream: Mutex<SendableRecordBatchStream>,
+    created_at: Instant,
+}
+
+pub struct FlightSqlServer {
+    auth: Arc<Auth>,
+    engine_cache: Arc<EngineCache>,
+    policy_hook: Arc<PolicyHook>,
+    handler: Arc<ProxyHandler>,
+    /// Bearer token → session.
+    sessions: DashMap<String, FlightSession>,
+    /// Statement handle → planned result stream.
+    results: DashMap<String, PendingResult>,
+    idle_timeout: Duration,
+}
+
+impl FlightSqlServer {
+    pub fn new(
+        auth: Arc<Auth>,
+        engine_cache: Arc<EngineCache>,
+        policy_hook: Arc<PolicyHook>,
+        handler: Arc<ProxyHandler>,
+        idle_timeout: Duration,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            auth,
+            engine_cache,
+            policy_hook,
+            handler,
+            sessions: DashMap::new(),
+            results: DashMap::new(),
+            idle_timeout,
+        })
+    }
+
+    /// Wrap the server in the tonic service used by `tonic::transport::Server`.
+    pub fn into_service(self: Arc<Self>) -> FlightServiceServer<Self> {
+        FlightServiceServer::from_arc(self)
+    }
+
+    /// Drop idle sessions and tickets older than `RESULT_TTL`.
+    ///
+    /// Called periodically from `main.rs`. Expired sessions are released from the
+    /// `ProxyHandler` registry so they stop being rebuilt on policy changes.
+    pub fn sweep_expired(&self) {
+        let handler = &self.handler;
+        self.sessions.retain(|_, session| {
+            let alive = !self.is_idle(session.last_seen);
+            if !alive {
+                handler.cleanup_connection(session.conn_id, None);
+            }
+            alive
+        });
+        self.results
+            .retain(|_, pending| pending.created_at.elapsed() < RESULT_TTL);
+    }
+
+    /// `true` once a session has been idle past `idle_timeout`. A zero timeout disables
+    /// expiry, as documented for `BR_IDLE_TIMEOUT_SECS=0`.
+    fn is_idle(&self, last_seen: Instant) -> bool {
+        !self.idle_timeout.is_zero() && last_seen.elapsed() >= self.idle_timeout
+    }
+
+    /// Resolve the bearer token on `metadata` to a live session, refreshing its idle timer.
+    fn session_for(
+        &self,
+        metadata: &MetadataMap,
+    ) -> Result<(String, QueryCaller, Arc<SessionContext>), Status> {
+        let token = bearer_token(metadata).ok_or_else(|| {
+            Status::unauthenticated("Missing bearer token — call Handshake first")
+        })?;
+        let mut session = self
+            .sessions
+            .get_mut(&token)
+            .ok_or_else(|| Status::unauthenticated("Unknown or expired session token"))?;
+        if self.is_idle(session.last_seen) {
+            let conn_id = session.conn_id;
+            drop(session);
+            self.sessions.remove(&token);
+            self.handler.cleanup_connection(conn_id, None);
+            return Err(Status::unauthenticated(
+                "Session expired — please reconnect",
+            ));
+        }
+        session.last_seen = Instant::now();
+        let ctx = self
+            .handler
+            .session_context(session.conn_id)
+            .ok_or_else(|| Status::unavailable("Session context not found — please reconnect"))?;
+        Ok((token, session.caller.clone(), ctx))
+    }
+}
+
+/// Map a pgwire error from the shared enforcement path onto a gRPC status,
+/// keeping the SQLSTATE-level distinction between denials and failures.
+fn pgwire_error_to_status(e: PgWireError) -> Status {
+    match e {
+        PgWireError::UserError(info) => match info.code.as_str() {
+            "42501" | "25006" => Status::permission_denied(info.message),
+            code if code.starts_with("28") => Status::unauthenticated(info.message),
+            _ => Status::invalid_argument(info.message),
+        },
+        other => Status::invalid_argument(other.to_string()),
+    }
+}
+
+/// Extract the token from an `authorization: Bearer <token>` header.
+fn bearer_token(metadata: &MetadataMap) -> Option<String> {
+    let value = metadata.get("authorization")?.to_str().ok()?;
+    value
+        .strip_prefix("Bearer ")
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// Decode `authorization: Basic <base64(user:pass)>` into `(username, password)`.
+fn basic_credentials(metadata: &MetadataMap) -> Option<(String, String)> {
+    let value = metadata.get("authorization")?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = BASE64_STANDARD.decode(encoded.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, pass) = decoded.split_once(':')?;
+    Some((user.to_string(), pass.to_string()))
+}
+
+/// Read an optional string header.
+fn header(metadata: &MetadataMap, name: &str) -> Option<String> {
+    metadata
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Generate a random 256-bit hex token.
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+type HandshakeStream = Pin<Box<dyn Stream<Item = Result<HandshakeResponse, Status>> + Send>>;
+type DoGetStream = <FlightSqlServer as FlightService>::DoGetStream;
+
+#[tonic::async_trait]
+impl FlightSqlService for FlightSqlServer {
+    type FlightService = FlightSqlServer;
+
+    async fn do_handshake(
+        &self,
+        request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<HandshakeStream>, Status> {
+        let metadata = request.metadata();
+        let (username, password) = basic_credentials(metadata).ok_or_else(|| {
+            Status::unauthenticated("Handshake requires 'authorization: Basic' credentials")
+        })?;
+        let datasource_name = header(metadata, "database").ok_or_else(|| {
+            Status::invalid_argument("No database specified — set the 'database' header")
+        })?;
+        let client_info =
+            header(metadata, "application_name").unwrap_or_else(|| DEFAULT_CLIENT_INFO.into());
+        let declared_purpose = header(metadata, purpose::PURPOSE_PARAM);
+        let justification = header(metadata, purpose::JUSTIFICATION_PARAM);
+
+        let user = self
+            .auth
+            .authenticate_for_api(&username, &password)
+            .await
+            .map_err(|e| match e {
+                AuthApiError::Db(e) => Status::internal(e.to_string()),
+                _ => Status::unauthenticated(format!(
+                    "password authentication failed for user \"{username}\""
+                )),
+            })?;
+
+        self.engine_cache
+            .validate_data_source(&datasource_name)
+            .await
+            .map_err(|e| Status::not_found(e.to_string()))?;
+
+        let has_access = self
+            .engine_cache
+            .check_access(user.id, &datasource_name)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if !has_access {
+            return Err(Status::permission_denied(format!(
+                "Access denied to data source '{datasource_name}'"
+            )));
+        }
+
+        // A declared purpose must be allowed before the session is built around it.
+        if let Some(declared) = &declared_purpose {
+            let declaration = Declaration {
+                user_id: user.id,
+                username: &user.username,
+                datasource: &datasource_name,
+                purpose: declared,
+                justification: justification.as_deref(),
+            };
+            match self.engine_cache.validate_purpose(&declaration).await {
+                Ok(()) => {}
+                Err(DeclarationError::Rejected(msg)) => {
+                    tracing::info!(
+                        username = %user.username,
+                        datasource = %datasource_name,
+                        purpose = %declared,
+                        reason = %msg,
+                        "Purpose declaration refused"
+                    );
+                    return Err(Status::permission_denied(msg));
+                }
+                Err(e @ DeclarationError::Internal(_)) => {
+                    return Err(Status::internal(e.to_string()));
+                }
+            }
+        }
+
+        let ctx = self
+            .engine_cache
+            .build_user_context(user.id, &datasource_name, declared_purpose.as_deref())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let conn_id = self.handler.register_external_session(
+            user.id,
+            &datasource_name,
+            declared_purpose.clone(),
+            ctx,
+        );
+
+        let token = random_token();
+        self.sessions.insert(
+            token.clone(),
+            FlightSession {
+                conn_id,
+                caller: QueryCaller {
+                    user_id: user.id,
+                    username: user.username.clone(),
+                    datasource: datasource_name.clone(),
+                    client_info: Some(client_info),
+                    purpose: declared_purpose,
+                    justification,
+                },
+                last_seen: Instant::now(),
+            },
+        );
+
+        tracing::info!(
+            username = %user.username,
+            datasource = %datasource_name,
+            conn_id = conn_id,
+            "Authenticated Flight SQL session"
+        );
+
+        // Warm up the upstream pool in the background (amortises first-query latency)
+        let cache = self.engine_cache.clone();
+        tokio::spawn(async move {
+            cache.warmup(&datasource_name).await;
+        });
+
+        let result = HandshakeResponse {
+            protocol_version: 0,
+            payload: token.clone().into(),
+        };
+        let output: HandshakeStream = Box::pin(stream::iter(vec![Ok(result)]));
+        let mut response = Response::new(output);
+        let bearer = format!("Bearer {token}")
+            .parse()
+            .map_err(|_| Status::internal("Failed to encode session token"))?;
+        response.metadata_mut().insert("authorization", bearer);
+        Ok(response)
+    }
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let (token, caller, ctx) = self.session_for(request.metadata())?;
+
+        let stream = self
+            .policy_hook
+            .stream_sql(&query.query, &ctx, &caller)
+            .await
+            .map_err(pgwire_error_to_status)?;
+        let schema = stream.schema();
+
+        let handle = random_token();
+        self.results.insert(
+            handle.clone(),
+            PendingResult {
+                token,
+                stream: Mutex::new(stream),
+                created_at: Instant::now(),
+            },
+        );
+
+        let ticket = TicketStatementQuery {
+            statement_handle: handle.into_bytes().into(),
+        };
+        let endpoint =
+            FlightEndpoint::new().with_ticket(Ticket::new(ticket.as_any().encode_to_vec()));
+        let info = FlightInfo::new()
+            .try_with_schema(&schema)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .with_endpoint(endpoint)
+            .with_descriptor(request.into_inner());
+        Ok(Response::new(info))
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let (token, _, _) = self.session_for(request.metadata())?;
+        let handle = String::from_utf8(ticket.statement_handle.to_vec())
+            .map_err(|_| Status::invalid_argument("Malformed statement handle"))?;
+
+        // Tickets are single-use and bound to the session that planned them.
+        let (_, pending) = self
+            .results
+            .remove_if(&handle, |_, pending| pending.token == token)
+            .ok_or_else(|| Status::not_found("Unknown or expired ticket"))?;
+
+        let stream = pending
+            .stream
+            .into_inner()
+            .map_err(|_| Status::internal("Result stream lock poisoned"))?;
+        let schema = stream.schema();
+        let batches = stream.map_err(|e| FlightError::ExternalError(Box::new(e)));
+        let output = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(batches)
+            .map_err(Status::from);
+        Ok(Response::new(Box::pin(output)))
+    }
+
+    async fn get_flight_info_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        self.session_for(request.metadata())?;
+        let endpoint =
+            FlightEndpoint::new().with_ticket(Ticket::new(query.as_any().encode_to_vec()));
+        let info = FlightInfo::new()
+            .try_with_schema(query.into_builder(&SQL_INFO).schema().as_ref())
+            .map_err(|e| Status::internal(e.to_string()))?
+            .with_endpoint(endpoint)
+            .with_descriptor(request.into_inner());
+        Ok(Response::new(info))
+    }
+
+    async fn do_get_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        self.session_for(request.metadata())?;
+        let builder = query.into_builder(&SQL_INFO);
+        let schema = builder.schema();
+        let batch = builder.build();
+        let output = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(stream::iter(vec![batch]))
+            .map_err(Status::from);
+        Ok(Response::new(Box::pin(output)))
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata_with(key: &'static str, value: &str) -> MetadataMap {
+        let mut md = MetadataMap::new();
+        md.insert(key, value.parse().unwrap());
+        md
+    }
+
+    #[test]
+    fn basic_credentials_decodes_user_and_password() {
+        let encoded = BASE64_STANDARD.encode("alice:s3cr:et");
+        let md = metadata_with("authorization", &format!("Basic {encoded}"));
+        assert_eq!(
+            basic_credentials(&md),
+            Some(("alice".to_string(), "s3cr:et".to_string()))
+        );
+    }
+
+    #[test]
+    fn basic_credentials_rejects_bearer_and_garbage() {
+        assert_eq!(
+            basic_credentials(&metadata_with("authorization", "Bearer abc")),
+            None
+        );
+        assert_eq!(
+            basic_credentials(&metadata_with("authorization", "Basic !!!")),
+            None
+        );
+        assert_eq!(basic_credentials(&MetadataMap::new()), None);
+    }
+
+    #[test]
+    fn bearer_token_extracts_non_empty_token() {
+        assert_eq!(
+            bearer_token(&metadata_with("authorization", "Bearer abc123")),
+            Some("abc123".to_string())
+        );
+        assert_eq!(
+            bearer_token(&metadata_with("authorization", "Bearer ")),
+            None
+        );
+        assert_eq!(
+            bearer_token(&metadata_with("authorization", "Basic abc")),
+            None
+        );
+    }
+
+    #[test]
+    fn random_token_is_256_bit_hex() {
+        let a = random_token();
+        let b = random_token();
+        assert_eq!(a.len(), 64);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn pgwire_denial_maps_to_permission_denied() {
+        let err = PgWireError::UserError(Box::new(pgwire::error::ErrorInfo::new(
+            "ERROR".to_owned(),
+            "42501".to_owned(),
+            "Access denied by policy 'p'".to_owned(),
+        )));
+        let status = pgwire_error_to_status(err);
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert_eq!(status.message(), "Access denied by policy 'p'");
+    }
+
+    #[test]
+    fn pgwire_api_error_maps_to_invalid_argument() {
+        let err = PgWireError::ApiError(Box::new(std::io::Error::other("table not found")));
+        assert_eq!(
+            pgwire_error_to_status(err).code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+}
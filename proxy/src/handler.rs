@@ -2,6 +2,7 @@ use crate::auth::Auth;
 use crate::engine::EngineCache;
 use crate::engine::rewrite::rewrite_statement;
 use crate::hooks::{QueryHook, policy::PolicyHook, read_only::ReadOnlyHook};
+use crate::purpose::{self, Declaration, DeclarationError, SessionSetting};
 use arrow_pg::datatypes::arrow_schema_to_pg_fields;
 use arrow_pg::datatypes::df::encode_dataframe;
 use async_trait::async_trait;
@@ -18,7 +19,7 @@ use pgwire::api::portal::{Format, Portal};
 use pgwire::api::query::{ExtendedQueryHandler, SimpleQueryHandler};
 use pgwire::api::results::{
     DataRowEncoder, DescribePortalResponse, DescribeStatementResponse, FieldFormat, FieldInfo,
-    QueryResponse, Response,
+    QueryResponse, Response, Tag,
 };
 use pgwire::api::stmt::{NoopQueryParser, StoredStatement};
 use pgwire::api::{ClientInfo, PgWireConnectionState, PgWireServerHandlers, Type};
@@ -36,6 +37,8 @@ struct ConnectionEntry {
     ctx: Arc<SessionContext>,
     user_id: uuid::Uuid,
     datasource_name: String,
+    /// The purpose the session declared, if any.
+    purpose: Option<String>,
 }
 
 /// Per-connection shared state. Wrapped in Arc so all handler clones share the same maps.
@@ -73,7 +76,8 @@ impl ProxyHandler {
         policy_hook: Arc<PolicyHook>,
     ) -> Self {
         // PolicyHook runs first so it can audit all statements (including writes that
-        // ReadOnlyHook will reject). ReadOnlyHook runs second and enforces the allowlist.
+        // ReadOnlyHook will reject) and run governed writes on `read_write` datasources.
+        // ReadOnlyHook runs second and enforces the allowlist.
         let hooks: Vec<Arc<dyn QueryHook>> = vec![policy_hook, Arc::new(ReadOnlyHook::new())];
 
         tracing::info!(hook_count = hooks.len(), "Initialized query hooks");
@@ -114,6 +118,39 @@ impl ProxyHandler {
         }
     }
 
+    /// Register a session opened by a non-pgwire front-end (Arrow Flight SQL) in the
+    /// shared connection registry, so policy mutations rebuild its `SessionContext`
+    /// exactly like a pgwire connection's — under the session's declared `purpose`, which
+    /// the caller has already validated. Release it with `cleanup_connection(id, None)`.
+    pub fn register_external_session(
+        &self,
+        user_id: uuid::Uuid,
+        datasource_name: &str,
+        purpose: Option<String>,
+        ctx: Arc<SessionContext>,
+    ) -> u64 {
+        let conn_id = self.alloc_connection_id();
+        self.conn_store.connection_contexts.insert(
+            conn_id,
+            ConnectionEntry {
+                ctx,
+                user_id,
+                datasource_name: datasource_name.to_string(),
+                purpose,
+            },
+        );
+        conn_id
+    }
+
+    /// Current `SessionContext` for a registered connection, or `None` if it was
+    /// cleaned up (or dropped after a failed rebuild).
+    pub fn session_context(&self, conn_id: u64) -> Option<Arc<SessionContext>> {
+        self.conn_store
+            .connection_contexts
+            .get(&conn_id)
+            .map(|entry| entry.value().ctx.clone())
+    }
+
     /// Rebuild the per-user `SessionContext` for all active connections on the given datasource.
     ///
     /// Called after a policy mutation so that connected users immediately see the updated schema
@@ -121,7 +158,7 @@ impl ProxyHandler {
     /// to reconnect. Rebuilding is done in the background via `tokio::spawn` so this method
     /// returns immediately.
     pub fn rebuild_contexts_for_datasource(&self, datasource: &str) {
-        let entries: Vec<(u64, uuid::Uuid, String)> = self
+        let entries: Vec<(u64, uuid::Uuid, String, Option<String>)> = self
             .conn_store
             .connection_contexts
             .iter()
@@ -131,15 +168,19 @@ impl ProxyHandler {
                     *e.key(),
                     e.value().user_id,
                     e.value().datasource_name.clone(),
+                    e.value().purpose.clone(),
                 )
             })
             .collect();
 
-        for (conn_id, user_id, ds_name) in entries {
+        for (conn_id, user_id, ds_name, purpose) in entries {
             let engine_cache = self.engine_cache.clone();
             let conn_store = self.conn_store.clone();
             tokio::spawn(async move {
-                match engine_cache.build_user_context(user_id, &ds_name).await {
+                match engine_cache
+                    .build_user_context(user_id, &ds_name, purpose.as_deref())
+                    .await
+                {
                     Ok(new_ctx) => {
                         if let Some(mut entry) = conn_store.connection_contexts.get_mut(&conn_id) {
                             entry.ctx = new_ctx;
@@ -163,7 +204,7 @@ impl ProxyHandler {
     /// Called after role membership/inheritance changes so that the affected user immediately
     /// sees the updated schema without needing to reconnect.
     pub fn rebuild_contexts_for_user(&self, user_id: uuid::Uuid) {
-        let entries: Vec<(u64, uuid::Uuid, String)> = self
+        let entries: Vec<(u64, uuid::Uuid, String, Option<String>)> = self
             .conn_store
             .connection_contexts
             .iter()
@@ -173,15 +214,19 @@ impl ProxyHandler {
                     *e.key(),
                     e.value().user_id,
                     e.value().datasource_name.clone(),
+                    e.value().purpose.clone(),
                 )
             })
             .collect();
 
-        for (conn_id, uid, ds_name) in entries {
+        for (conn_id, uid, ds_name, purpose) in entries {
             let engine_cache = self.engine_cache.clone();
             let conn_store = self.conn_store.clone();
             tokio::spawn(async move {
-                match engine_cache.build_user_context(uid, &ds_name).await {
+                match engine_cache
+                    .build_user_context(uid, &ds_name, purpose.as_deref())
+                    .await
+                {
                     Ok(new_ctx) => {
                         if let Some(mut entry) = conn_store.connection_contexts.get_mut(&conn_id) {
                             entry.ctx = new_ctx;
@@ -238,6 +283,187 @@ impl ProxyHandler {
                 )))
             })
     }
+
+    /// Check a purpose declaration, refusing it with SQLSTATE 42501 at `severity`.
+    async fn check_declaration(
+        &self,
+        declaration: &Declaration<'_>,
+        severity: &str,
+    ) -> PgWireResult<()> {
+        match self.engine_cache.validate_purpose(declaration).await {
+            Ok(()) => Ok(()),
+            Err(DeclarationError::Rejected(msg)) => {
+                tracing::info!(
+                    username = %declaration.username,
+                    datasource = %declaration.datasource,
+                    purpose = %declaration.purpose,
+                    reason = %msg,
+                    "Purpose declaration refused"
+                );
+                Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                    severity.to_owned(),
+                    "42501".to_owned(),
+                    msg,
+                ))))
+            }
+            Err(e @ DeclarationError::Internal(_)) => Err(PgWireError::ApiError(Box::new(e))),
+        }
+    }
+
+    /// Validate the purpose declared with the `purpose` / `justification` startup
+    /// parameters, if any, and return it. Blank parameters are dropped.
+    async fn check_startup_purpose<C: ClientInfo>(
+        &self,
+        client: &mut C,
+        user_id: uuid::Uuid,
+        username: &str,
+        datasource: &str,
+    ) -> PgWireResult<Option<String>> {
+        for key in [purpose::PURPOSE_PARAM, purpose::JUSTIFICATION_PARAM] {
+            match client.metadata().get(key).map(|v| v.trim().to_string()) {
+                Some(v) if v.is_empty() => {
+                    client.metadata_mut().remove(key);
+                }
+                Some(v) => {
+                    client.metadata_mut().insert(key.to_owned(), v);
+                }
+                None => {}
+            }
+        }
+        let Some(declared) = client.metadata().get(purpose::PURPOSE_PARAM).cloned() else {
+            return Ok(None);
+        };
+        let declaration = Declaration {
+            user_id,
+            username,
+            datasource,
+            purpose: &declared,
+            justification: client
+                .metadata()
+                .get(purpose::JUSTIFICATION_PARAM)
+                .map(String::as_str),
+        };
+        self.check_declaration(&declaration, "FATAL").await?;
+        Ok(Some(declared))
+    }
+
+    /// Run a `SET` or `SHOW` of `br.purpose` / `br.justification`.
+    ///
+    /// A new declaration is validated as a whole — set the justification before
+    /// a purpose that requires one — and a refused one leaves the session as it
+    /// was. Changing the purpose rebuilds the connection's `SessionContext`.
+    async fn apply_purpose_setting<C: ClientInfo>(
+        &self,
+        client: &mut C,
+        setting: SessionSetting,
+    ) -> PgWireResult<Response> {
+        let (key, value) = match setting {
+            SessionSetting::Show(key) => {
+                let fields = Arc::new(purpose_setting_fields(&setting));
+                let mut encoder = DataRowEncoder::new(fields.clone());
+                // Unset shows as '', like an unset custom setting in PostgreSQL.
+                encoder.encode_field(&client.metadata().get(key).cloned().unwrap_or_default())?;
+                let row = encoder.take_row();
+                let stream = futures::stream::iter([Ok(row)]);
+                return Ok(Response::Query(QueryResponse::new(fields, stream)));
+            }
+            SessionSetting::Set(key, value) => (key, value),
+        };
+        if let Some(v) = &value
+            && key == purpose::JUSTIFICATION_PARAM
+            && v.len() > purpose::MAX_JUSTIFICATION_LEN
+        {
+            return Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                "ERROR".to_owned(),
+                "22023".to_owned(),
+                format!(
+                    "Justification must be at most {} bytes",
+                    purpose::MAX_JUSTIFICATION_LEN
+                ),
+            ))));
+        }
+
+        let metadata = client.metadata();
+        let current = |k: &str| metadata.get(k).cloned();
+        let (new_purpose, new_justification) = if key == purpose::PURPOSE_PARAM {
+            (value, current(purpose::JUSTIFICATION_PARAM))
+        } else {
+            (current(purpose::PURPOSE_PARAM), value)
+        };
+        let user_id = metadata
+            .get("user_id")
+            .and_then(|id| uuid::Uuid::parse_str(id).ok())
+            .ok_or_else(|| {
+                PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_owned(),
+                    "08000".to_owned(),
+                    "Connection not initialized — authentication may have failed".to_owned(),
+                )))
+            })?;
+        let username = current("user").unwrap_or_default();
+        let datasource = current("datasource").unwrap_or_default();
+        let purpose_changed = new_purpose != current(purpose::PURPOSE_PARAM);
+
+        if let Some(declared) = &new_purpose {
+            let declaration = Declaration {
+                user_id,
+                username: &username,
+                datasource: &datasource,
+                purpose: declared,
+                justification: new_justification.as_deref(),
+            };
+            self.check_declaration(&declaration, "ERROR").await?;
+        }
+
+        if purpose_changed {
+            let ctx = self
+                .engine_cache
+                .build_user_context(user_id, &datasource, new_purpose.as_deref())
+                .await
+                .map_err(|e| {
+                    PgWireError::ApiError(Box::new(std::io::Error::other(e.to_string())))
+                })?;
+            let conn_id = current("conn_id").and_then(|id| id.parse::<u64>().ok());
+            if let Some(mut entry) =
+                conn_id.and_then(|id| self.conn_store.connection_contexts.get_mut(&id))
+            {
+                entry.ctx = ctx;
+                entry.purpose = new_purpose.clone();
+            }
+        }
+
+        for (key, value) in [
+            (purpose::PURPOSE_PARAM, new_purpose),
+            (purpose::JUSTIFICATION_PARAM, new_justification),
+        ] {
+            match value {
+                Some(v) => client.metadata_mut().insert(key.to_owned(), v),
+                None => client.metadata_mut().remove(key),
+            };
+        }
+        Ok(Response::Execution(Tag::new("SET")))
+    }
+}
+
+/// Result columns of a purpose setting statement: one text column for `SHOW`.
+fn purpose_setting_fields(setting: &SessionSetting) -> Vec<FieldInfo> {
+    match setting {
+        SessionSetting::Show(key) => {
+            let name = if *key == purpose::PURPOSE_PARAM {
+                purpose::PURPOSE_SETTING
+            } else {
+                purpose::JUSTIFICATION_SETTING
+            };
+            vec![FieldInfo::new(
+                name.to_string(),
+                None,
+                None,
+                Type::TEXT,
+                FieldFormat::Text,
+            )]
+        }
+        SessionSetting::Set(..) => vec![],
+    }
 }
 
 /// Execute a DataFusion EXPLAIN statement and reformat its output into the single-column
@@ -404,6 +630,12 @@ impl StartupHandler for ProxyHandler {
                             .metadata_mut()
                             .insert("datasource".to_owned(), datasource_name.clone());
 
+                        // A purpose declared in the startup parameters must be allowed
+                        // before the session is built around it.
+                        let purpose = self
+                            .check_startup_purpose(client, user.id, &username, &datasource_name)
+                            .await?;
+
                         // Retrieve the connection ID registered at accept time
                         let peer_addr = client.socket_addr();
                         let conn_id = self
@@ -422,7 +654,7 @@ impl StartupHandler for ProxyHandler {
                         // and that metadata visibility is correct from the first query onward.
                         let ctx = self
                             .engine_cache
-                            .build_user_context(user.id, &datasource_name)
+                            .build_user_context(user.id, &datasource_name, purpose.as_deref())
                             .await
                             .map_err(|e| {
                                 PgWireError::ApiError(Box::new(std::io::Error::other(
@@ -436,6 +668,7 @@ impl StartupHandler for ProxyHandler {
                                 ctx,
                                 user_id: user.id,
                                 datasource_name: datasource_name.clone(),
+                                purpose,
                             },
                         );
                         client
@@ -473,11 +706,13 @@ impl StartupHandler for ProxyHandler {
 impl SimpleQueryHandler for ProxyHandler {
     async fn do_query<C>(&self, client: &mut C, query: &str) -> PgWireResult<Vec<Response>>
     where
-        C: ClientInfo + Unpin + Send + Sync,
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
     {
         tracing::debug!(query = %query, "Received simple query");
 
-        let ctx = self.get_ctx(client).await?;
+        let mut ctx = self.get_ctx(client).await?;
 
         // Parse SQL to Statement
         let statements = Parser::parse_sql(&PostgreSqlDialect {}, query).map_err(|e| {
@@ -491,17 +726,35 @@ impl SimpleQueryHandler for ProxyHandler {
             // Rewrite AST for PostgreSQL compatibility before processing
             rewrite_statement(&mut statement);
 
+            if let Some(setting) = purpose::session_setting(&statement) {
+                responses.push(self.apply_purpose_setting(client, setting).await?);
+                // Later statements run under the new purpose.
+                ctx = self.get_ctx(client).await?;
+                continue;
+            }
+
             // Execute hook pipeline
             let mut hook_response = None;
+            let mut notices = Vec::new();
             for hook in &self.hooks {
                 if let Some(response) = hook
-                    .handle_query(&statement, &ctx, client as &(dyn ClientInfo + Sync))
+                    .handle_query(
+                        &statement,
+                        &ctx,
+                        client as &(dyn ClientInfo + Sync),
+                        &mut notices,
+                    )
                     .await
                 {
                     hook_response = Some(response);
                     break;
                 }
             }
+            for notice in notices {
+                client
+                    .send(PgWireBackendMessage::NoticeResponse(notice.into()))
+                    .await?;
+            }
 
             let response = if let Some(r) = hook_response {
                 r?
@@ -555,7 +808,9 @@ impl ExtendedQueryHandler for ProxyHandler {
         _max_rows: usize,
     ) -> PgWireResult<Response>
     where
-        C: ClientInfo + Unpin + Send + Sync,
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
     {
         let query = &portal.statement.statement;
 
@@ -575,16 +830,31 @@ impl ExtendedQueryHandler for ProxyHandler {
         let mut statement = statements.into_iter().next().unwrap();
         rewrite_statement(&mut statement);
 
+        if let Some(setting) = purpose::session_setting(&statement) {
+            return self.apply_purpose_setting(client, setting).await;
+        }
+
         let mut hook_response = None;
+        let mut notices = Vec::new();
         for hook in &self.hooks {
             if let Some(response) = hook
-                .handle_query(&statement, &ctx, client as &(dyn ClientInfo + Sync))
+                .handle_query(
+                    &statement,
+                    &ctx,
+                    client as &(dyn ClientInfo + Sync),
+                    &mut notices,
+                )
                 .await
             {
                 hook_response = Some(response);
                 break;
             }
         }
+        for notice in notices {
+            client
+                .send(PgWireBackendMessage::NoticeResponse(notice.into()))
+                .await?;
+        }
 
         if let Some(r) = hook_response {
             return r;
@@ -637,6 +907,18 @@ impl ExtendedQueryHandler for ProxyHandler {
         let mut statement = statements.into_iter().next().unwrap();
         rewrite_statement(&mut statement);
 
+        if let Some(setting) = purpose::session_setting(&statement) {
+            return Ok(DescribeStatementResponse::new(
+                vec![],
+                purpose_setting_fields(&setting),
+            ));
+        }
+
+        // A governed write returns a command tag, never rows.
+        if crate::write::is_write(&statement) {
+            return Ok(DescribeStatementResponse::new(vec![], vec![]));
+        }
+
         let sql = statement.to_string();
         let df = ctx
             .sql(&sql)
@@ -671,6 +953,16 @@ impl ExtendedQueryHandler for ProxyHandler {
         let mut statement = statements.into_iter().next().unwrap();
         rewrite_statement(&mut statement);
 
+        if let Some(setting) = purpose::session_setting(&statement) {
+            return Ok(DescribePortalResponse::new(purpose_setting_fields(
+                &setting,
+            )));
+        }
+
+        if crate::write::is_write(&statement) {
+            return Ok(DescribePortalResponse::new(vec![]));
+        }
+
         let sql = statement.to_string();
         let df = ctx
             .sql(&sql)
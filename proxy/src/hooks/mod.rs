@@ -3,7 +3,7 @@ use datafusion::prelude::SessionContext;
 use datafusion::sql::sqlparser::ast::Statement;
 use pgwire::api::ClientInfo;
 use pgwire::api::results::Response;
-use pgwire::error::PgWireResult;
+use pgwire::error::{ErrorInfo, PgWireResult};
 
 pub mod policy;
 pub mod read_only;
@@ -18,10 +18,13 @@ pub trait QueryHook: Send + Sync {
     /// - `None` if this hook doesn't handle the query (pass to next hook)
     /// - `Some(Ok(Response))` if this hook handled the query successfully
     /// - `Some(Err(e))` if this hook encountered an error
+    ///
+    /// Messages pushed to `notices` are sent to the client as `NoticeResponse`s.
     async fn handle_query(
         &self,
         statement: &Statement,
         session_context: &SessionContext,
         client: &(dyn ClientInfo + Sync),
+        notices: &mut Vec<ErrorInfo>,
     ) -> Option<PgWireResult<Response>>;
 }
@@ -1,24 +1,29 @@
-use arrow_pg::datatypes::df::encode_dataframe;
+use arrow_pg::datatypes::{arrow_schema_to_pg_fields, encode_recordbatch};
 use async_trait::async_trait;
 use chrono::Utc;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use datafusion::common::ScalarValue;
-use datafusion::logical_expr::registry::FunctionRegistry;
-use datafusion::logical_expr::{LogicalPlan, LogicalPlanBuilder, TableScan, col, lit};
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::logical_expr::{
+    AggregateUDF, LogicalPlan, LogicalPlanBuilder, ScalarUDF, TableScan, TableSource, WindowUDF,
+    col, lit,
+};
 use datafusion::prelude::SessionContext;
+use datafusion::sql::planner::{ContextProvider, ParserOptions, PlannerContext, SqlToRel};
 use datafusion::sql::sqlparser::ast::{
-    BinaryOperator as SqlBinaryOp, Expr as SqlExpr, FunctionArg, FunctionArgExpr,
-    FunctionArguments, Statement, TableFactor, Visit, Visitor,
+    Expr as SqlExpr, Statement, TableFactor, Visit, Visitor, visit_expressions,
+    visit_expressions_mut, visit_relations,
 };
-use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::dialect::{GenericDialect, PostgreSqlDialect};
 use datafusion::sql::sqlparser::parser::Parser;
 use datafusion::sql::unparser::Unparser;
 use pgwire::api::ClientInfo;
 use pgwire::api::portal::Format;
-use pgwire::api::results::Response;
+use pgwire::api::results::{QueryResponse, Response, Tag};
 use pgwire::error::{ErrorInfo, PgWireError, PgWireResult};
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 use std::collections::{BTreeSet, HashMap, HashSet};
-use std::ops::{ControlFlow, Not};
+use std::ops::ControlFlow;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
@@ -26,13 +31,24 @@ use uuid::Uuid;
 use super::QueryHook;
 use super::read_only::is_allowed_statement;
 use crate::engine::BetweenRowsPostgresDialect;
+use crate::engine::rewrite::rewrite_statement;
 use crate::entity::{
     column_anchor as column_anchor_entity, data_source, decision_function, discovered_column,
-    discovered_schema, discovered_table, policy, query_audit_log,
+    discovered_schema, discovered_table, policy, policy_template, query_audit_log,
     table_relationship as table_relationship_entity,
 };
-use crate::policy_match::{PolicyType, TargetEntry, expand_column_patterns};
+use crate::policy_combining::{
+    AssignmentScope, CombineStrategy, CombiningRules, Contender, PolicyConflict, Precedence,
+    combine,
+};
+use crate::policy_match::{
+    ActionStatus, AggregateOnlyDef, CatalogTags, DifferentialPrivacyDef, PolicyType, QueryGuardDef,
+    ResultLimitDef, TargetEntry, WriteAllowDef, WriteOperation, expand_column_patterns, parse_tags,
+};
+use crate::policy_simulation::PolicyChange;
 use crate::resolution::graph::{self as resolution_graph, RelationshipEdge, RelationshipSnapshot};
+use crate::subject::Subject;
+use crate::write::{PlannedWrite, WriteStatement};
 
 // ---------- system schema detection ----------
 
@@ -286,361 +302,413 @@ fn mangle_vars(template: &str, vars: &UserVars) -> Result<(String, Vec<VarMappin
     Ok((result, mappings))
 }
 
-/// Produce a typed DataFusion literal from a string value and value_type.
-fn typed_lit(value: &str, value_type: &str) -> datafusion::logical_expr::Expr {
-    match value_type {
-        "null" => lit(ScalarValue::Null),
-        "integer" => {
-            if let Ok(n) = value.parse::<i64>() {
-                lit(ScalarValue::Int64(Some(n)))
-            } else {
-                lit(value) // fallback to string if parse fails
-            }
-        }
-        "boolean" => {
-            if let Ok(b) = value.parse::<bool>() {
-                lit(ScalarValue::Boolean(Some(b)))
-            } else {
-                lit(value)
-            }
-        }
-        _ => lit(value), // "string" and unknown types
-    }
+/// Produce the SQL literal a `{user.X}` placeholder stands for, from its value and
+/// value_type. Values that don't parse as their declared type fall back to strings.
+fn typed_value(value: &str, value_type: &str) -> SqlExpr {
+    use datafusion::sql::sqlparser::ast::Value;
+    let value = match value_type {
+        "null" => Value::Null,
+        "integer" => match value.parse::<i64>() {
+            Ok(n) => Value::Number(n.to_string(), false),
+            Err(_) => Value::SingleQuotedString(value.to_string()),
+        },
+        "boolean" => match value.parse::<bool>() {
+            Ok(b) => Value::Boolean(b),
+            Err(_) => Value::SingleQuotedString(value.to_string()),
+        },
+        _ => Value::SingleQuotedString(value.to_string()), // "string" and unknown types
+    };
+    SqlExpr::value(value)
 }
 
-/// Convert a sqlparser AST expression to a DataFusion Expr.
-///
-/// This is a custom converter for expression fragments (filter/mask templates),
-/// not full SQL statements. It handles a subset of SQL syntax:
+/// Parse a filter or mask template into a sqlparser AST with its `{user.X}`
+/// placeholders bound.
 ///
-/// **Supported:** Identifier, CompoundIdentifier, Value (number/string/bool/null),
-/// BinaryOp (+, -, *, /, =, !=, <, >, <=, >=, AND, OR, ||), UnaryOp (NOT, -),
-/// IsNull, IsNotNull, Nested (parentheses), Between, Like, InList, Cast,
-/// Function (via registry), Case (CASE WHEN ... THEN ... ELSE ... END).
-///
-/// **Not yet supported (add as needed):**
-/// - ILike (case-insensitive LIKE)
-/// - IsTrue / IsFalse / IsNotTrue / IsNotFalse
-/// - IsDistinctFrom / IsNotDistinctFrom
-/// - InSubquery (subquery in IN clause)
-/// - Extract (EXTRACT(field FROM expr))
-/// - Substring, Trim, Overlay, Position (SQL string functions — use UDF registry instead)
-/// - Exists / Subquery (correlated subqueries)
-/// - Array, Struct, Map literals
-/// - JsonAccess (-> / ->> operators)
-/// - AtTimeZone
-/// - TypedString (e.g., DATE '2024-01-01')
-/// - Interval
-///
-/// Pass `Some(ctx)` as `registry` to enable full scalar function lookup (required for
-/// column mask expressions). Pass `None` for row filter expressions where only
-/// COALESCE is supported.
-fn sql_ast_to_df_expr(
-    expr: &SqlExpr,
-    var_values: &[VarMapping],
-    registry: Option<&dyn FunctionRegistry>,
-) -> datafusion::error::Result<datafusion::logical_expr::Expr> {
-    use datafusion::logical_expr::Expr;
-    match expr {
-        SqlExpr::Identifier(ident) => {
+/// `mangle_vars` first turns every placeholder into a `__br_…__` identifier, so the
+/// template parses as plain SQL. Each such identifier is then swapped for a literal
+/// AST node holding the user's value. Attribute values never pass through the SQL
+/// parser, so a value like `x' OR '1'='1` stays a single string literal.
+fn bind_template(template: &str, vars: &UserVars) -> datafusion::error::Result<SqlExpr> {
+    let (mangled, var_values) =
+        mangle_vars(template, vars).map_err(datafusion::error::DataFusionError::Plan)?;
+
+    let dialect = GenericDialect {};
+    let mut sql_expr = Parser::new(&dialect)
+        .try_with_sql(&mangled)
+        .and_then(|mut parser| {
+            // The whole template must be one expression: trailing tokens are an
+            // error, not silently dropped.
+            let expr = parser.parse_expr()?;
+            parser.expect_token(&datafusion::sql::sqlparser::tokenizer::Token::EOF)?;
+            Ok(expr)
+        })
+        .map_err(|e| {
+            datafusion::error::DataFusionError::Plan(format!(
+                "Failed to parse expression '{mangled}': {e}"
+            ))
+        })?;
+
+    let _ = visit_expressions_mut(&mut sql_expr, |e| {
+        if let SqlExpr::Identifier(ident) = e {
             let name_lc = ident.value.to_lowercase();
             if let Some(mapping) = var_values.iter().find(|m| m.placeholder == name_lc) {
-                Ok(typed_lit(&mapping.value, &mapping.value_type))
-            } else {
-                Ok(col(&ident.value))
+                *e = typed_value(&mapping.value, &mapping.value_type);
             }
         }
-        SqlExpr::CompoundIdentifier(parts) => {
-            // Parts are Vec<Ident> in newer sqlparser
-            let name = parts
-                .iter()
-                .map(|i| i.value.as_str())
-                .collect::<Vec<_>>()
-                .join(".");
-            Ok(col(name))
-        }
-        SqlExpr::Value(v) => {
-            // In newer sqlparser, Value is wrapped in ValueWithSpan: access .value
-            match &v.value {
-                datafusion::sql::sqlparser::ast::Value::Number(n, _) => {
-                    if let Ok(i) = n.parse::<i64>() {
-                        Ok(lit(i))
-                    } else {
-                        Ok(lit(n.parse::<f64>().unwrap_or(0.0)))
-                    }
-                }
-                datafusion::sql::sqlparser::ast::Value::SingleQuotedString(s)
-                | datafusion::sql::sqlparser::ast::Value::DoubleQuotedString(s) => {
-                    Ok(lit(s.as_str()))
-                }
-                datafusion::sql::sqlparser::ast::Value::Boolean(b) => Ok(lit(*b)),
-                datafusion::sql::sqlparser::ast::Value::Null => Ok(lit(ScalarValue::Null)),
-                other => Err(datafusion::error::DataFusionError::Plan(format!(
-                    "Unsupported value in filter expression: {other:?}"
-                ))),
+        ControlFlow::<()>::Continue(())
+    });
+    Ok(sql_expr)
+}
+
+/// [`ContextProvider`] for planning policy expressions with DataFusion's `SqlToRel`.
+///
+/// Functions, expression planners (e.g. the JSON `->`/`->>` operators) and SQL
+/// options come from the session. `ContextProvider` lookups are synchronous, so the
+/// tables a subquery reads are resolved up front. Any `br_*` masking UDF resolves to
+/// its policy-trusted variant, since policy expressions are admin-authored.
+struct PolicyContextProvider {
+    state: datafusion::execution::SessionState,
+    tables: HashMap<datafusion::common::ResolvedTableReference, Arc<dyn TableSource>>,
+}
+
+impl PolicyContextProvider {
+    /// Resolve the tables `expr` reads against the session's catalog. Names that do
+    /// not resolve (e.g. CTE names) are left for the planner to report.
+    async fn resolve(ctx: &SessionContext, expr: &SqlExpr) -> Self {
+        let state = ctx.state();
+        let mut tables = HashMap::new();
+        for table_ref in Self::table_refs(&state, expr) {
+            if let Ok(provider) = ctx.table_provider(table_ref.clone()).await {
+                tables.insert(
+                    Self::resolve_ref(&state, table_ref),
+                    datafusion::datasource::provider_as_source(provider),
+                );
             }
         }
-        SqlExpr::BinaryOp { left, op, right } => {
-            let l = sql_ast_to_df_expr(left, var_values, registry)?;
-            let r = sql_ast_to_df_expr(right, var_values, registry)?;
-            match op {
-                SqlBinaryOp::Eq => Ok(l.eq(r)),
-                SqlBinaryOp::NotEq => Ok(l.not_eq(r)),
-                SqlBinaryOp::Lt => Ok(l.lt(r)),
-                SqlBinaryOp::Gt => Ok(l.gt(r)),
-                SqlBinaryOp::LtEq => Ok(l.lt_eq(r)),
-                SqlBinaryOp::GtEq => Ok(l.gt_eq(r)),
-                SqlBinaryOp::And => Ok(l.and(r)),
-                SqlBinaryOp::Or => Ok(l.or(r)),
-                SqlBinaryOp::StringConcat => {
-                    Ok(Expr::BinaryExpr(datafusion::logical_expr::BinaryExpr {
-                        left: Box::new(l),
-                        op: datafusion::logical_expr::Operator::StringConcat,
-                        right: Box::new(r),
-                    }))
-                }
-                other => Err(datafusion::error::DataFusionError::Plan(format!(
-                    "Unsupported operator in filter expression: {other:?}"
-                ))),
+        Self { state, tables }
+    }
+
+    /// Stand in for the tables `expr` reads when there is no catalog at hand (save-time
+    /// validation). Each table gets one untyped column per identifier in `expr`, which
+    /// is enough to check that the expression plans.
+    fn synthetic(state: datafusion::execution::SessionState, expr: &SqlExpr) -> Self {
+        let normalizer = datafusion::sql::planner::IdentNormalizer::new(
+            state.config_options().sql_parser.enable_ident_normalization,
+        );
+        let mut names: BTreeSet<String> = BTreeSet::new();
+        let _ = visit_expressions(expr, |e| {
+            let ident = match e {
+                SqlExpr::Identifier(ident) => Some(ident),
+                SqlExpr::CompoundIdentifier(parts) => parts.last(),
+                _ => None,
+            };
+            if let Some(ident) = ident {
+                names.insert(normalizer.normalize(ident.clone()));
             }
-        }
-        SqlExpr::IsNull(inner) => Ok(sql_ast_to_df_expr(inner, var_values, registry)?.is_null()),
-        SqlExpr::IsNotNull(inner) => {
-            Ok(sql_ast_to_df_expr(inner, var_values, registry)?.is_not_null())
-        }
-        SqlExpr::Nested(inner) => sql_ast_to_df_expr(inner, var_values, registry),
-        SqlExpr::UnaryOp { op, expr } => {
-            use datafusion::sql::sqlparser::ast::UnaryOperator;
-            let inner = sql_ast_to_df_expr(expr, var_values, registry)?;
-            match op {
-                UnaryOperator::Not => Ok(inner.not()),
-                UnaryOperator::Minus => Ok(Expr::Negative(Box::new(inner))),
-                other => Err(datafusion::error::DataFusionError::Plan(format!(
-                    "Unsupported unary op: {other:?}"
-                ))),
+            ControlFlow::<()>::Continue(())
+        });
+        let schema = Arc::new(Schema::new(
+            names
+                .into_iter()
+                .map(|name| Field::new(name, DataType::Null, true))
+                .collect::<Vec<_>>(),
+        ));
+        let tables = Self::table_refs(&state, expr)
+            .into_iter()
+            .map(|table_ref| {
+                let source: Arc<dyn TableSource> = Arc::new(
+                    datafusion::logical_expr::LogicalTableSource::new(Arc::clone(&schema)),
+                );
+                (Self::resolve_ref(&state, table_ref), source)
+            })
+            .collect();
+        Self { state, tables }
+    }
+
+    fn table_refs(
+        state: &datafusion::execution::SessionState,
+        expr: &SqlExpr,
+    ) -> Vec<datafusion::common::TableReference> {
+        let normalize = state.config_options().sql_parser.enable_ident_normalization;
+        let mut refs = Vec::new();
+        let _ = visit_relations(expr, |name| {
+            if let Ok(table_ref) =
+                datafusion::sql::planner::object_name_to_table_reference(name.clone(), normalize)
+            {
+                refs.push(table_ref);
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        refs
+    }
+
+    fn resolve_ref(
+        state: &datafusion::execution::SessionState,
+        table_ref: datafusion::common::TableReference,
+    ) -> datafusion::common::ResolvedTableReference {
+        let catalog = &state.config_options().catalog;
+        table_ref.resolve(&catalog.default_catalog, &catalog.default_schema)
+    }
+
+    /// Plan `expr` against `table_schema`, the columns of the table `table` the
+    /// policy applies to.
+    ///
+    /// The table's columns are qualified with its bare name while planning, so a
+    /// correlated subquery can reach them as `table.column`; references outside
+    /// subqueries come back unqualified, as the scan rewriters expect. Columns the
+    /// table lacks are planned untyped rather than rejected: row-filter column
+    /// resolution may still satisfy them through an anchor, and otherwise denies.
+    fn plan(
+        &self,
+        expr: SqlExpr,
+        table: &str,
+        table_schema: &Schema,
+    ) -> datafusion::error::Result<datafusion::logical_expr::Expr> {
+        use datafusion::common::tree_node::{Transformed, TreeNode};
+        use datafusion::common::{Column, DFSchema, TableReference};
+        use datafusion::logical_expr::Expr;
+
+        let qualifier = TableReference::bare(table);
+        let mut schema = DFSchema::try_from_qualified_schema(qualifier.clone(), table_schema)?;
+        let normalizer = datafusion::sql::planner::IdentNormalizer::new(
+            self.state
+                .config_options()
+                .sql_parser
+                .enable_ident_normalization,
+        );
+        let mut outer = OuterIdentifiers::default();
+        let _ = expr.visit(&mut outer);
+        let mut missing: Vec<(Option<TableReference>, Arc<Field>)> = Vec::new();
+        for mut parts in outer.found {
+            let Some(name) = parts.pop().map(|i| normalizer.normalize(i)) else {
+                continue;
+            };
+            let mut parts: Vec<String> =
+                parts.into_iter().map(|i| normalizer.normalize(i)).collect();
+            let relation = match parts.len() {
+                0 => Some(qualifier.clone()),
+                1 => parts.pop().map(TableReference::bare),
+                2 => Some(TableReference::partial(parts[0].clone(), parts[1].clone())),
+                3 => Some(TableReference::full(
+                    parts[0].clone(),
+                    parts[1].clone(),
+                    parts[2].clone(),
+                )),
+                _ => continue,
+            };
+            let column = Column::new(relation.clone(), &name);
+            if !schema.has_column(&column)
+                && !missing
+                    .iter()
+                    .any(|(r, f)| r == &relation && f.name() == &name)
+            {
+                missing.push((relation, Arc::new(Field::new(name, DataType::Null, true))));
             }
         }
-        SqlExpr::Between {
-            expr,
-            negated,
-            low,
-            high,
-        } => {
-            let e = sql_ast_to_df_expr(expr, var_values, registry)?;
-            let lo = sql_ast_to_df_expr(low, var_values, registry)?;
-            let hi = sql_ast_to_df_expr(high, var_values, registry)?;
-            let between = e.clone().gt_eq(lo).and(e.lt_eq(hi));
-            Ok(if *negated { between.not() } else { between })
-        }
-        SqlExpr::Like {
-            negated,
-            expr,
-            pattern,
-            ..
-        } => {
-            let col_expr = sql_ast_to_df_expr(expr, var_values, registry)?;
-            let pat_expr = sql_ast_to_df_expr(pattern, var_values, registry)?;
-            let like_expr = col_expr.like(pat_expr);
-            Ok(if *negated { like_expr.not() } else { like_expr })
+        if !missing.is_empty() {
+            schema.merge(&DFSchema::new_with_metadata(missing, HashMap::new())?);
         }
-        SqlExpr::InList {
+
+        let options = ParserOptions::from(&self.state.config_options().sql_parser);
+        let planned = SqlToRel::new_with_options(self, options).sql_to_expr(
             expr,
-            list,
-            negated,
-        } => {
-            let col_expr = sql_ast_to_df_expr(expr, var_values, registry)?;
-            let list_exprs: Vec<_> = list
-                .iter()
-                .map(|e| sql_ast_to_df_expr(e, var_values, registry))
-                .collect::<datafusion::error::Result<_>>()?;
-            Ok(col_expr.in_list(list_exprs, *negated))
-        }
-        SqlExpr::Function(f) => {
-            let func_name = f
-                .name
-                .0
-                .iter()
-                .filter_map(|p| {
-                    use datafusion::sql::sqlparser::ast::ObjectNamePart;
-                    if let ObjectNamePart::Identifier(i) = p {
-                        Some(i.value.as_str())
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join(".");
+            &schema,
+            &mut PlannerContext::new(),
+        )?;
+        // Planners like the JSON operators alias their output for display; an alias
+        // nested inside a predicate or mask has no meaning.
+        planned
+            .unalias_nested()
+            .data
+            .transform(|e| match e {
+                Expr::Column(c) if c.relation.as_ref() == Some(&qualifier) => Ok(Transformed::yes(
+                    Expr::Column(Column::new_unqualified(c.name)),
+                )),
+                other => Ok(Transformed::no(other)),
+            })
+            .map(|t| t.data)
+    }
+}
 
-            let args = match &f.args {
-                FunctionArguments::List(list) => list
-                    .args
-                    .iter()
-                    .map(|arg| match arg {
-                        FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => {
-                            sql_ast_to_df_expr(e, var_values, registry)
-                        }
-                        other => Err(datafusion::error::DataFusionError::Plan(format!(
-                            "Unsupported function arg: {other:?}"
-                        ))),
-                    })
-                    .collect::<datafusion::error::Result<Vec<_>>>()?,
-                FunctionArguments::None => vec![],
-                other => {
-                    return Err(datafusion::error::DataFusionError::Plan(format!(
-                        "Unsupported function arguments in mask/filter expression: {other:?}"
-                    )));
-                }
-            };
+/// Collects the column identifiers of an expression that sit outside any subquery.
+#[derive(Default)]
+struct OuterIdentifiers {
+    depth: usize,
+    found: Vec<Vec<datafusion::sql::sqlparser::ast::Ident>>,
+}
 
-            if let Some(reg) = registry {
-                // Full function lookup via registry — supports all built-in and user-defined UDFs.
-                let func_name_lower = func_name.to_lowercase();
-                let udf = reg.udf(&func_name_lower).map_err(|_| {
-                    datafusion::error::DataFusionError::Plan(format!(
-                        "Unknown function '{func_name}' in mask expression"
-                    ))
-                })?;
-                Ok(udf.call(args))
-            } else {
-                // Filter expressions: only COALESCE supported.
-                match func_name.to_uppercase().as_str() {
-                    "COALESCE" => Ok(datafusion::functions::expr_fn::coalesce(args)),
-                    other => Err(datafusion::error::DataFusionError::Plan(format!(
-                        "Function '{other}' in filter expressions is not supported. \
-                         For complex expressions, use column masks instead."
-                    ))),
-                }
+impl Visitor for OuterIdentifiers {
+    type Break = ();
+
+    fn pre_visit_query(
+        &mut self,
+        _query: &datafusion::sql::sqlparser::ast::Query,
+    ) -> ControlFlow<()> {
+        self.depth += 1;
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(
+        &mut self,
+        _query: &datafusion::sql::sqlparser::ast::Query,
+    ) -> ControlFlow<()> {
+        self.depth -= 1;
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &SqlExpr) -> ControlFlow<()> {
+        if self.depth == 0 {
+            match expr {
+                SqlExpr::Identifier(ident) => self.found.push(vec![ident.clone()]),
+                SqlExpr::CompoundIdentifier(parts) => self.found.push(parts.clone()),
+                _ => {}
             }
         }
-        SqlExpr::Cast {
-            expr, data_type, ..
-        } => {
-            use datafusion::arrow::datatypes::DataType as ArrowType;
-            use datafusion::sql::sqlparser::ast::DataType as SqlDataType;
-            let inner = sql_ast_to_df_expr(expr, var_values, registry)?;
-            let arrow_type = match data_type {
-                SqlDataType::Varchar(_)
-                | SqlDataType::Text
-                | SqlDataType::Char(_)
-                | SqlDataType::String(_) => ArrowType::Utf8,
-                SqlDataType::SmallInt(_) => ArrowType::Int16,
-                SqlDataType::Integer(_) | SqlDataType::Int(_) => ArrowType::Int32,
-                SqlDataType::BigInt(_) => ArrowType::Int64,
-                SqlDataType::Float(_) | SqlDataType::Float4 | SqlDataType::Real => {
-                    ArrowType::Float32
-                }
-                SqlDataType::Double(_)
-                | SqlDataType::DoublePrecision
-                | SqlDataType::Float8
-                | SqlDataType::Float64 => ArrowType::Float64,
-                SqlDataType::Boolean => ArrowType::Boolean,
-                other => {
-                    return Err(datafusion::error::DataFusionError::Plan(format!(
-                        "Unsupported CAST target type in mask/filter expression: {other:?}"
-                    )));
-                }
-            };
-            Ok(datafusion::logical_expr::cast(inner, arrow_type))
-        }
-        SqlExpr::Case {
-            operand,
-            conditions,
-            else_result,
-            ..
-        } => {
-            // CASE [operand] WHEN cond THEN result [ELSE else_result] END
-            let operand_expr = operand
-                .as_ref()
-                .map(|e| sql_ast_to_df_expr(e, var_values, registry))
-                .transpose()?;
-            let when_then: Vec<(Box<Expr>, Box<Expr>)> = conditions
-                .iter()
-                .map(|cw| {
-                    let cond = sql_ast_to_df_expr(&cw.condition, var_values, registry)?;
-                    let result = sql_ast_to_df_expr(&cw.result, var_values, registry)?;
-                    // For simple CASE (with operand), the condition is compared via Eq
-                    let when = if let Some(ref op) = operand_expr {
-                        Box::new(op.clone().eq(cond))
-                    } else {
-                        Box::new(cond)
-                    };
-                    Ok((when, Box::new(result)))
-                })
-                .collect::<datafusion::error::Result<_>>()?;
-            let else_expr = else_result
-                .as_ref()
-                .map(|e| sql_ast_to_df_expr(e, var_values, registry).map(Box::new))
-                .transpose()?;
-            Ok(Expr::Case(datafusion::logical_expr::Case {
-                expr: None, // conditions already include the comparison
-                when_then_expr: when_then,
-                else_expr,
-            }))
-        }
-        other => Err(datafusion::error::DataFusionError::Plan(format!(
-            "Unsupported expression type in filter: {other:?}"
-        ))),
+        ControlFlow::Continue(())
     }
 }
 
-/// Parse a filter expression template into a DataFusion Expr.
-/// Template variables like {user.tenant} are substituted as literals.
-fn parse_filter_expr(
-    template: &str,
-    vars: &UserVars,
-) -> datafusion::error::Result<datafusion::logical_expr::Expr> {
-    let trimmed = template.trim();
-    if trimmed == "1=1" || trimmed == "true" {
-        return Ok(lit(true));
+impl ContextProvider for PolicyContextProvider {
+    fn get_table_source(
+        &self,
+        name: datafusion::common::TableReference,
+    ) -> datafusion::error::Result<Arc<dyn TableSource>> {
+        let name = Self::resolve_ref(&self.state, name);
+        self.tables.get(&name).cloned().ok_or_else(|| {
+            datafusion::error::DataFusionError::Plan(format!("table '{name}' not found"))
+        })
     }
-    if trimmed == "1=0" || trimmed == "false" {
-        return Ok(lit(false));
+
+    fn get_expr_planners(&self) -> &[Arc<dyn datafusion::logical_expr::planner::ExprPlanner>] {
+        self.state.expr_planners()
     }
 
-    let (mangled, var_values) =
-        mangle_vars(template, vars).map_err(datafusion::error::DataFusionError::Plan)?;
+    fn get_function_meta(&self, name: &str) -> Option<Arc<ScalarUDF>> {
+        self.state
+            .scalar_functions()
+            .get(name)
+            .cloned()
+            .map(crate::masking::for_policy)
+    }
 
-    let dialect = GenericDialect {};
-    let mut parser = Parser::new(&dialect).try_with_sql(&mangled).map_err(|e| {
-        datafusion::error::DataFusionError::Plan(format!(
-            "Failed to parse filter expression '{mangled}': {e}"
-        ))
-    })?;
-    let sql_expr = parser.parse_expr().map_err(|e| {
-        datafusion::error::DataFusionError::Plan(format!(
-            "Failed to parse filter expression '{mangled}': {e}"
-        ))
-    })?;
+    fn get_aggregate_meta(&self, name: &str) -> Option<Arc<AggregateUDF>> {
+        self.state.aggregate_functions().get(name).cloned()
+    }
+
+    fn get_window_meta(&self, name: &str) -> Option<Arc<WindowUDF>> {
+        self.state.window_functions().get(name).cloned()
+    }
+
+    fn get_variable_type(&self, _variable_names: &[String]) -> Option<DataType> {
+        None
+    }
+
+    fn options(&self) -> &datafusion::config::ConfigOptions {
+        self.state.config_options()
+    }
 
-    sql_ast_to_df_expr(&sql_expr, &var_values, None)
+    fn udf_names(&self) -> Vec<String> {
+        self.state.scalar_functions().keys().cloned().collect()
+    }
+
+    fn udaf_names(&self) -> Vec<String> {
+        self.state.aggregate_functions().keys().cloned().collect()
+    }
+
+    fn udwf_names(&self) -> Vec<String> {
+        self.state.window_functions().keys().cloned().collect()
+    }
+}
+
+/// Row filters that are constant — no parsing needed.
+fn constant_filter(template: &str) -> Option<bool> {
+    match template.trim() {
+        "1=1" | "true" => Some(true),
+        "1=0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a filter expression template into a DataFusion Expr, planned over the
+/// columns of `table` with DataFusion's SQL planner. Any expression DataFusion
+/// supports is accepted, including subqueries over tables in the session's catalog.
+/// Template variables like {user.tenant} are substituted as literals.
+async fn parse_filter_expr(
+    ctx: &SessionContext,
+    template: &str,
+    vars: &UserVars,
+    table: &str,
+    table_schema: &Schema,
+) -> datafusion::error::Result<datafusion::logical_expr::Expr> {
+    if let Some(constant) = constant_filter(template) {
+        return Ok(lit(constant));
+    }
+    let sql_expr = bind_template(template, vars)?;
+    PolicyContextProvider::resolve(ctx, &sql_expr)
+        .await
+        .plan(sql_expr, table, table_schema)
 }
 
-/// Parse a column mask expression into a DataFusion Expr.
+/// Parse a column mask expression into a DataFusion Expr, planned over the
+/// columns of `table` like [`parse_filter_expr`].
 ///
-/// Supports all scalar functions registered in the session context (RIGHT, LEFT,
-/// UPPER, LOWER, CONCAT, COALESCE, etc.), string concatenation (`||`), literals,
-/// and column references. Template variables like `{user.tenant}` are substituted
-/// as string literals — never interpolated as raw SQL.
-fn parse_mask_expr(
+/// `{col}` binds to the masked column (how mask templates refer to their target);
+/// other identifiers refer to sibling columns of the same table. Template variables
+/// like `{user.tenant}` are substituted as literals — never interpolated as raw SQL.
+async fn parse_mask_expr(
     ctx: &SessionContext,
     column: &str,
     mask_template: &str,
     vars: &UserVars,
+    table: &str,
+    table_schema: &Schema,
 ) -> datafusion::error::Result<datafusion::logical_expr::Expr> {
-    let (mangled, var_values) =
-        mangle_vars(mask_template, vars).map_err(datafusion::error::DataFusionError::Plan)?;
-    let dialect = GenericDialect {};
-    let mut parser = Parser::new(&dialect).try_with_sql(&mangled).map_err(|e| {
-        datafusion::error::DataFusionError::Plan(format!(
-            "Failed to parse mask expression for column '{column}': {e}"
-        ))
-    })?;
-    let sql_expr = parser.parse_expr().map_err(|e| {
+    let bound = crate::policy_template::bind_column(mask_template, column);
+    let sql_expr = bind_template(&bound, vars).map_err(|e| {
         datafusion::error::DataFusionError::Plan(format!(
             "Failed to parse mask expression for column '{column}': {e}"
         ))
     })?;
-    sql_ast_to_df_expr(&sql_expr, &var_values, Some(ctx))
+    let mask =
+        PolicyContextProvider::resolve(ctx, &sql_expr)
+            .await
+            .plan(sql_expr, table, table_schema)?;
+    check_mask_subqueries(mask)
+}
+
+/// DataFusion runs `EXISTS` and `IN (subquery)` only as predicates, and a mask is a
+/// projection. Reject them up front; a scalar subquery works in either place.
+fn check_mask_subqueries(
+    mask: datafusion::logical_expr::Expr,
+) -> datafusion::error::Result<datafusion::logical_expr::Expr> {
+    use datafusion::common::tree_node::TreeNode;
+    use datafusion::logical_expr::Expr;
+
+    if mask.exists(|e| Ok(matches!(e, Expr::Exists(_) | Expr::InSubquery(_))))? {
+        return Err(datafusion::error::DataFusionError::Plan(
+            "EXISTS and IN (subquery) are not supported in mask expressions; \
+             use a scalar subquery instead"
+                .to_string(),
+        ));
+    }
+    Ok(mask)
+}
+
+/// Plan an expression with no table at hand: columns stay unresolved and subqueries
+/// read synthetic tables (see [`PolicyContextProvider::synthetic`]). The session has
+/// the built-in, JSON and `br_*` functions a real session registers.
+fn parse_expr_detached(
+    template: &str,
+    vars: &UserVars,
+) -> datafusion::error::Result<datafusion::logical_expr::Expr> {
+    if let Some(constant) = constant_filter(template) {
+        return Ok(lit(constant));
+    }
+    let sql_expr = bind_template(template, vars)?;
+    let mut ctx = SessionContext::new();
+    crate::masking::register_udfs(&ctx, &crate::masking::MaskingKeyring::new([0u8; 32]));
+    datafusion_functions_json::register_all(&mut ctx)?;
+    PolicyContextProvider::synthetic(ctx.state(), &sql_expr).plan(sql_expr, "", &Schema::empty())
 }
 
 // ---------- expression validation (used at policy save time) ----------
@@ -650,7 +718,8 @@ fn parse_mask_expr(
 /// instead of failing silently at query time.
 ///
 /// Uses dummy user variables so the parse succeeds regardless of actual user values.
-/// The goal is to catch unsupported SQL syntax, not to evaluate the expression.
+/// No table schema is known at save time, so column types are not checked; the
+/// expression is planned again over the real table at query time.
 pub fn validate_expression(expression: &str, is_mask: bool) -> Result<(), String> {
     let dummy_vars = UserVars {
         username: "__validate__".to_string(),
@@ -660,14 +729,13 @@ pub fn validate_expression(expression: &str, is_mask: bool) -> Result<(), String
     };
 
     if is_mask {
-        // Mask expressions need a function registry for UDF lookup.
-        // Use a bare SessionContext which has all built-in functions registered.
-        let ctx = SessionContext::new();
-        parse_mask_expr(&ctx, "dummy_col", expression, &dummy_vars)
+        let bound = crate::policy_template::bind_column(expression, "dummy_col");
+        parse_expr_detached(&bound, &dummy_vars)
+            .and_then(check_mask_subqueries)
             .map(|_| ())
             .map_err(|e| format!("Invalid mask expression: {e}"))
     } else {
-        parse_filter_expr(expression, &dummy_vars)
+        parse_expr_detached(expression, &dummy_vars)
             .map(|_| ())
             .map_err(|e| format!("Invalid filter expression: {e}"))
     }
@@ -685,7 +753,7 @@ pub(crate) fn parse_filter_expr_for_admin(
         attributes: HashMap::new(),
         attribute_defs: HashMap::new(),
     };
-    parse_filter_expr(expression, &dummy_vars)
+    parse_expr_detached(expression, &dummy_vars)
 }
 
 // ---------- resolved policy data structures ----------
@@ -709,17 +777,51 @@ struct ResolvedPolicy {
     policy_type: PolicyType,
     version: i32,
     priority: i32,
+    /// Scope of the effective assignment — the tie-breaker of `first_match`
+    /// and the ranking of `most_specific` (see [`crate::policy_combining`]).
+    scope: AssignmentScope,
     targets: Vec<TargetEntry>,
     /// Parsed definition JSON (filter_expression or mask_expression). Null for non-expression types.
     definition: Option<serde_json::Value>,
     /// Decision function (loaded from decision_function table via FK).
     decision_function: Option<ResolvedDecisionFunction>,
+    /// Template the definition was bound from, recorded in the audit log.
+    template: Option<AppliedTemplate>,
+}
+
+impl ResolvedPolicy {
+    /// This policy as a contender for a target another policy of its type also hits.
+    fn contender<T>(&self, value: T) -> Contender<T> {
+        Contender {
+            precedence: Precedence {
+                name: self.name.clone(),
+                priority: self.priority,
+                scope: self.scope,
+            },
+            value,
+        }
+    }
+}
+
+/// Which template version produced a policy's expression.
+#[derive(Clone, Debug)]
+struct AppliedTemplate {
+    id: Uuid,
+    name: String,
+    version: i32,
 }
 
 struct SessionData {
     permit_policies: Vec<ResolvedPolicy>,
     deny_policies: Vec<ResolvedPolicy>,
+    /// Policies in shadow mode (policy or effective assignment is `shadow`).
+    /// Evaluated for the audit log only; never applied to the plan.
+    shadow_policies: Vec<ResolvedPolicy>,
     access_mode: String,
+    /// `read_only` or `read_write`: whether INSERT, UPDATE and DELETE run upstream.
+    write_mode: String,
+    /// How permit policies of one type that hit the same target combine.
+    combining: CombiningRules,
     /// DataFusion schema alias → upstream schema name
     df_to_upstream: HashMap<String, String>,
     datasource_id: Uuid,
@@ -734,6 +836,9 @@ struct SessionData {
     /// row-filter rewriter to resolve columns that live on a parent table.
     /// Empty snapshot when no admin has configured anchors.
     relationship_snapshot: Arc<RelationshipSnapshot>,
+    /// Tags on this datasource's schemas, tables and columns, resolved per
+    /// scan column when a target uses a `tag:` selector.
+    catalog_tags: Arc<CatalogTags>,
     /// Cache of parent-table `LogicalPlan`s materialized for anchor
     /// resolution. Populated lazily on first query by
     /// `precompute_parent_scans` and reused across queries for this
@@ -742,29 +847,100 @@ struct SessionData {
     /// `SessionDataClone` via `Arc::clone` so query-side population
     /// benefits subsequent queries hitting the same cache entry.
     parent_scans_cache: Arc<tokio::sync::RwLock<HashMap<(String, String), LogicalPlan>>>,
+    /// Results of uncorrelated policy subqueries, filled by `lookup_values` and
+    /// reused for `LOOKUP_CACHE_TTL_SECS` within this `SessionData`'s lifetime.
+    lookup_cache: Arc<LookupCache>,
     loaded_at: std::time::Instant,
 }
 
 const CACHE_TTL_SECS: u64 = 60;
 
+/// How long the result of an uncorrelated policy subquery is reused.
+const LOOKUP_CACHE_TTL_SECS: u64 = 30;
+
+/// Largest uncorrelated policy subquery result inlined into the plan as literals.
+/// Larger results are left as subqueries and run with every query.
+const MAX_LOOKUP_ROWS: usize = 10_000;
+
+/// How deep policy subqueries may nest: a row filter's subquery reads a table
+/// whose own row filter has a subquery, and so on. Deeper nesting fails closed.
+const MAX_POLICY_SUBQUERY_DEPTH: usize = 3;
+
+/// First column of each uncorrelated policy subquery's rows, keyed by the governed
+/// plan, with the time it was read. `None` marks a result over `MAX_LOOKUP_ROWS`.
+type LookupCache =
+    tokio::sync::RwLock<HashMap<String, (std::time::Instant, Option<Arc<Vec<ScalarValue>>>)>>;
+
 // ---------- PolicyHook ----------
 
+/// Identity a governed query runs under.
+///
+/// pgwire builds this from connection metadata (see [`QueryCaller::from_metadata`]);
+/// other front-ends (Arrow Flight SQL, …) construct it from their own session state.
+#[derive(Debug, Clone)]
+pub struct QueryCaller {
+    pub user_id: Uuid,
+    pub username: String,
+    pub datasource: String,
+    /// Free-form client identifier recorded in `query_audit_log.client_info`.
+    pub client_info: Option<String>,
+    /// The session's validated purpose; selects purpose-restricted policies.
+    pub purpose: Option<String>,
+    /// Why the data is needed, as declared with the purpose.
+    pub justification: Option<String>,
+}
+
+impl QueryCaller {
+    /// Read the caller from pgwire connection metadata populated in `on_startup`.
+    ///
+    /// Returns `None` when no `user_id` is present (connection not authenticated),
+    /// and `Some(Err(_))` when the stored `user_id` is not a valid UUID.
+    pub fn from_metadata(metadata: &HashMap<String, String>) -> Option<PgWireResult<Self>> {
+        let user_id_str = metadata.get("user_id")?;
+        let user_id = match Uuid::parse_str(user_id_str) {
+            Ok(id) => id,
+            Err(_) => {
+                return Some(Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_owned(),
+                    "28000".to_owned(),
+                    "Invalid user_id in connection metadata".to_owned(),
+                )))));
+            }
+        };
+        Some(Ok(Self {
+            user_id,
+            username: metadata.get("user").cloned().unwrap_or_default(),
+            datasource: metadata.get("datasource").cloned().unwrap_or_default(),
+            client_info: metadata.get("application_name").cloned(),
+            purpose: metadata.get(crate::purpose::PURPOSE_PARAM).cloned(),
+            justification: metadata.get(crate::purpose::JUSTIFICATION_PARAM).cloned(),
+        }))
+    }
+}
+
+/// Sessions are cached by user, datasource and declared purpose.
+type SessionKey = (Uuid, String, Option<String>);
+
 pub struct PolicyHook {
     db: DatabaseConnection,
-    cache: Arc<RwLock<HashMap<(Uuid, String), SessionData>>>,
+    cache: Arc<RwLock<HashMap<SessionKey, SessionData>>>,
     /// Shared WASM runtime for evaluating decision functions at query time.
     wasm_runtime: Arc<crate::decision::wasm::WasmDecisionRuntime>,
+    /// What `EXPLAIN (POLICY)` shows to users who are not admins.
+    explain_level: ExplainLevel,
 }
 
 impl PolicyHook {
     pub fn new(
         db: DatabaseConnection,
         wasm_runtime: Arc<crate::decision::wasm::WasmDecisionRuntime>,
+        explain_level: ExplainLevel,
     ) -> Arc<Self> {
         Arc::new(Self {
             db,
             cache: Arc::new(RwLock::new(HashMap::new())),
             wasm_runtime,
+            explain_level,
         })
     }
 
@@ -782,26 +958,29 @@ impl PolicyHook {
     /// Best-effort audit write for a statement that will be rejected by `ReadOnlyHook`.
     /// Skips silently if user context is missing or the session can't be loaded.
     async fn audit_write_rejected(&self, statement: &Statement, client: &(dyn ClientInfo + Sync)) {
-        let metadata = client.metadata();
-        let user_id_str = match metadata.get("user_id") {
-            Some(s) => s.clone(),
-            None => return,
-        };
-        let user_id = match Uuid::parse_str(&user_id_str) {
-            Ok(id) => id,
-            Err(_) => return,
+        let Some(Ok(caller)) = QueryCaller::from_metadata(client.metadata()) else {
+            return;
         };
-        let username = metadata.get("user").cloned().unwrap_or_default();
-        let datasource = metadata.get("datasource").cloned().unwrap_or_default();
-        let client_info = metadata.get("application_name").cloned();
+        self.audit_rejected(statement, &caller).await;
+    }
 
-        let session = match self.get_session(user_id, &datasource).await {
+    /// Best-effort audit write for a non-read statement rejected before planning.
+    ///
+    /// Front-ends other than pgwire call this directly with their own
+    /// [`QueryCaller`]. Skips silently if the session can't be loaded.
+    pub async fn audit_rejected(&self, statement: &Statement, caller: &QueryCaller) {
+        let session = match self.get_session(caller).await {
             Ok(s) => s,
             Err(_) => return,
         };
 
         let db = self.db.clone();
         let original_query = statement.to_string();
+        let user_id = caller.user_id;
+        let username = caller.username.clone();
+        let client_info = caller.client_info.clone();
+        let purpose = caller.purpose.clone();
+        let justification = caller.justification.clone();
 
         tokio::spawn(async move {
             let now = Utc::now().naive_utc();
@@ -820,6 +999,17 @@ impl PolicyHook {
                 created_at: sea_orm::Set(now),
                 status: sea_orm::Set("denied".to_string()),
                 error_message: sea_orm::Set(Some("Only read-only queries are allowed".to_string())),
+                served_by: sea_orm::Set(None),
+                shadow_outcomes: sea_orm::Set(None),
+                epsilon_spent: sea_orm::Set(None),
+                rows_returned: sea_orm::Set(None),
+                bytes_returned: sea_orm::Set(None),
+                impersonated_by: sea_orm::Set(None),
+                purpose: sea_orm::Set(purpose),
+                justification: sea_orm::Set(justification),
+                policy_conflicts: sea_orm::Set(None),
+                rows_affected: sea_orm::Set(None),
+                affected_rows: sea_orm::Set(None),
             };
             if let Err(e) = sea_orm::ActiveModelTrait::insert(entry, &db).await {
                 tracing::error!(error = %e, "Failed to write audit log entry for rejected write");
@@ -829,13 +1019,15 @@ impl PolicyHook {
 
     async fn get_session(
         &self,
-        user_id: Uuid,
-        datasource_name: &str,
+        caller: &QueryCaller,
     ) -> Result<SessionDataRef, Box<dyn std::error::Error + Send + Sync>> {
+        let user_id = caller.user_id;
+        let key = (user_id, caller.datasource.clone(), caller.purpose.clone());
+
         // Try read lock first
         {
             let cache = self.cache.read().await;
-            if let Some(s) = cache.get(&(user_id, datasource_name.to_string()))
+            if let Some(s) = cache.get(&key)
                 && s.loaded_at.elapsed().as_secs() < CACHE_TTL_SECS
             {
                 return Ok(clone_session_data(s));
@@ -844,7 +1036,6 @@ impl PolicyHook {
 
         // Load and cache
         let mut cache = self.cache.write().await;
-        let key = (user_id, datasource_name.to_string());
 
         // Re-check after acquiring write lock
         if let Some(s) = cache.get(&key)
@@ -853,7 +1044,13 @@ impl PolicyHook {
             return Ok(clone_session_data(s));
         }
 
-        let session = self.load_session(user_id, datasource_name).await?;
+        let subject = Subject::load(&self.db, user_id)
+            .await?
+            .ok_or_else(|| format!("User {user_id} not found"))?
+            .with_purpose(caller.purpose.clone());
+        let session = self
+            .load_session(&subject, &caller.datasource, None)
+            .await?;
         let cloned = clone_session_data(&session);
         cache.insert(key, session);
         Ok(cloned)
@@ -861,8 +1058,9 @@ impl PolicyHook {
 
     async fn load_session(
         &self,
-        user_id: Uuid,
+        subject: &Subject,
         datasource_name: &str,
+        change: Option<&PolicyChange>,
     ) -> Result<SessionData, Box<dyn std::error::Error + Send + Sync>> {
         // Load datasource
         let ds = data_source::Entity::find()
@@ -888,26 +1086,16 @@ impl PolicyHook {
         }
 
         // Resolve role names for the user (for decision function context)
-        let role_ids = crate::role_resolver::resolve_user_roles(&self.db, user_id).await?;
-        let role_names: Vec<String> = if !role_ids.is_empty() {
-            use crate::entity::role;
-            role::Entity::find()
-                .filter(role::Column::Id.is_in(role_ids))
-                .all(&self.db)
-                .await?
-                .into_iter()
-                .map(|r| r.name)
-                .collect()
-        } else {
-            vec![]
-        };
+        let role_names = subject.role_names(&self.db).await?;
 
         // Load user attributes (from proxy_user.attributes JSON column)
-        let (user_attributes, attribute_defs) = self.load_user_attributes(user_id).await?;
+        let (user_attributes, attribute_defs) =
+            self.load_user_attributes(&subject.attributes).await?;
 
         // Load policy assignments for this datasource+user (user-specific, role-based, or wildcard)
-        let relevant_assignments =
-            crate::role_resolver::resolve_effective_assignments(&self.db, user_id, ds.id).await?;
+        let relevant_assignments = subject
+            .effective_assignments(&self.db, ds.id, change)
+            .await?;
 
         let policy_ids: Vec<Uuid> = relevant_assignments
             .iter()
@@ -916,22 +1104,36 @@ impl PolicyHook {
             .into_iter()
             .collect();
 
-        // Build priority map: policy_id → min priority (already deduplicated by resolve_effective_assignments)
-        let mut policy_priority: HashMap<Uuid, i32> = HashMap::new();
+        // Build priority map: policy_id → (min priority, its scope) (already deduplicated by
+        // resolve_effective_assignments)
+        let mut policy_priority: HashMap<Uuid, (i32, AssignmentScope)> = HashMap::new();
         for a in &relevant_assignments {
-            let entry = policy_priority.entry(a.policy_id).or_insert(a.priority);
-            if a.priority < *entry {
-                *entry = a.priority;
+            let ranked = (a.priority, AssignmentScope::parse(&a.assignment_scope));
+            let entry = policy_priority.entry(a.policy_id).or_insert(ranked);
+            if ranked < *entry {
+                *entry = ranked;
             }
         }
 
+        // Policies whose effective assignment is in shadow mode (an enforcing
+        // assignment of the same policy already won in resolve_effective_assignments).
+        let shadow_assigned: HashSet<Uuid> = relevant_assignments
+            .iter()
+            .filter(|a| a.action_status == ActionStatus::Shadow.as_str())
+            .map(|a| a.policy_id)
+            .collect();
+
         let relationship_snapshot = Arc::new(load_relationship_snapshot(&self.db, ds.id).await?);
+        let catalog_tags = Arc::new(load_catalog_tags(&self.db, &schemas).await?);
 
         if policy_ids.is_empty() {
             return Ok(SessionData {
                 permit_policies: vec![],
                 deny_policies: vec![],
+                shadow_policies: vec![],
                 access_mode: ds.access_mode.clone(),
+                write_mode: ds.write_mode.clone(),
+                combining: CombiningRules::parse(ds.policy_combining.as_deref()),
                 df_to_upstream,
                 datasource_id: ds.id,
                 datasource_name: ds.name.clone(),
@@ -939,17 +1141,24 @@ impl PolicyHook {
                 user_attributes,
                 attribute_defs,
                 relationship_snapshot,
+                catalog_tags,
                 parent_scans_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+                lookup_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
                 loaded_at: std::time::Instant::now(),
             });
         }
 
         // Load policies (enabled only)
-        let policies = policy::Entity::find()
+        let mut policies = policy::Entity::find()
             .filter(policy::Column::Id.is_in(policy_ids.clone()))
             .filter(policy::Column::IsEnabled.eq(true))
             .all(&self.db)
             .await?;
+        if let Some(change) = change {
+            change.apply_to_policies(&mut policies, &policy_ids);
+        }
+        policies
+            .retain(|p| crate::purpose::in_scope(p.purpose.as_deref(), subject.purpose.as_deref()));
 
         // Batch-load decision functions referenced by these policies
         let df_ids: Vec<Uuid> = policies
@@ -971,8 +1180,29 @@ impl PolicyHook {
             HashMap::new()
         };
 
+        // Batch-load templates referenced by these policies
+        let template_ids: Vec<Uuid> = policies
+            .iter()
+            .filter_map(|p| p.template_id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        let template_map: HashMap<Uuid, policy_template::Model> = if !template_ids.is_empty() {
+            policy_template::Entity::find()
+                .filter(policy_template::Column::Id.is_in(template_ids))
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|t| (t.id, t))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
         let mut permit_policies = Vec::new();
         let mut deny_policies = Vec::new();
+        let mut shadow_policies = Vec::new();
 
         for p in policies {
             let policy_type = match p.policy_type.parse::<PolicyType>() {
@@ -983,11 +1213,42 @@ impl PolicyHook {
                 }
             };
             let targets: Vec<TargetEntry> = serde_json::from_str(&p.targets).unwrap_or_default();
-            let definition: Option<serde_json::Value> = p
+            let mut definition: Option<serde_json::Value> = p
                 .definition
                 .as_deref()
                 .and_then(|s| serde_json::from_str(s).ok());
 
+            // Bind the template's parameters into the same definition shape an
+            // inline policy has. A policy whose template cannot be bound is
+            // dropped, like one with an unparseable expression.
+            let mut template = None;
+            if let Some(template_id) = p.template_id {
+                let Some(t) = template_map.get(&template_id) else {
+                    tracing::warn!(policy = %p.name, %template_id, "Skipping policy with missing template");
+                    continue;
+                };
+                match crate::policy_template::resolve_definition(
+                    policy_type,
+                    t,
+                    definition.as_ref(),
+                ) {
+                    Ok(bound) => definition = Some(bound),
+                    Err(e) => {
+                        tracing::error!(policy = %p.name, template = %t.name, error = %e, "Failed to bind policy template");
+                        continue;
+                    }
+                }
+                template = Some(AppliedTemplate {
+                    id: t.id,
+                    name: t.name.clone(),
+                    version: t.version,
+                });
+            }
+            // A `pseudonymize` mode mask runs as its equivalent inline expression.
+            if policy_type == PolicyType::ColumnMask {
+                definition = definition.map(crate::masking::expand_mask_mode);
+            }
+
             // Resolve decision function from FK
             let decision_function = p.decision_function_id.and_then(|df_id| {
                 df_map.get(&df_id).map(|df| {
@@ -1007,31 +1268,50 @@ impl PolicyHook {
                 })
             });
 
-            let priority = policy_priority.get(&p.id).copied().unwrap_or(100);
+            let (priority, scope) = policy_priority
+                .get(&p.id)
+                .copied()
+                .unwrap_or((100, AssignmentScope::All));
             let resolved = ResolvedPolicy {
                 id: p.id,
                 name: p.name.clone(),
                 policy_type,
                 version: p.version,
                 priority,
+                scope,
                 targets,
                 definition,
                 decision_function,
+                template,
             };
-            if policy_type.is_deny() {
+            if p.action_status == ActionStatus::Shadow.as_str() || shadow_assigned.contains(&p.id) {
+                shadow_policies.push(resolved);
+            } else if policy_type.is_deny() {
                 deny_policies.push(resolved);
             } else {
                 permit_policies.push(resolved);
             }
         }
 
-        permit_policies.sort_by_key(|p| p.priority);
-        deny_policies.sort_by_key(|p| p.priority);
+        // Policies load in no particular order; sort fully so that which policy
+        // comes first never depends on it.
+        for policies in [
+            &mut permit_policies,
+            &mut deny_policies,
+            &mut shadow_policies,
+        ] {
+            policies.sort_by(|a, b| {
+                (a.priority, a.scope, &a.name).cmp(&(b.priority, b.scope, &b.name))
+            });
+        }
 
         Ok(SessionData {
             permit_policies,
             deny_policies,
+            shadow_policies,
             access_mode: ds.access_mode.clone(),
+            write_mode: ds.write_mode.clone(),
+            combining: CombiningRules::parse(ds.policy_combining.as_deref()),
             df_to_upstream,
             datasource_id: ds.id,
             datasource_name: ds.name.clone(),
@@ -1039,17 +1319,19 @@ impl PolicyHook {
             user_attributes,
             attribute_defs,
             relationship_snapshot,
+            catalog_tags,
             parent_scans_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            lookup_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
             loaded_at: std::time::Instant::now(),
         })
     }
 
-    /// Load user attributes from the proxy_user model and pair with types
-    /// from attribute_definition. Also loads ALL user-entity attribute definitions
+    /// Pair attributes (the `proxy_user.attributes` JSON) with types from
+    /// attribute_definition. Also loads ALL user-entity attribute definitions
     /// for default resolution at query time via `resolve_user_attribute_defaults`.
     async fn load_user_attributes(
         &self,
-        user_id: Uuid,
+        attributes: &str,
     ) -> Result<
         (
             HashMap<String, TypedAttribute>,
@@ -1059,12 +1341,7 @@ impl PolicyHook {
     > {
         use crate::entity::{attribute_definition, proxy_user};
 
-        let user = proxy_user::Entity::find_by_id(user_id)
-            .one(&self.db)
-            .await?
-            .ok_or_else(|| format!("User {user_id} not found"))?;
-
-        let raw_attrs = proxy_user::parse_attributes(&user.attributes);
+        let raw_attrs = proxy_user::parse_attributes(attributes);
 
         // Load ALL user-type attribute definitions (not just the user's keys)
         // so we have default_value info for attributes the user does NOT have.
@@ -1258,13 +1535,63 @@ pub(crate) async fn load_relationship_snapshot(
     })
 }
 
-// SessionData doesn't derive Clone, so we clone it manually.
-type SessionDataRef = Box<SessionDataClone>;
+/// Load the tags set on a datasource's catalog into a [`CatalogTags`], keyed by
+/// upstream names. `schemas` are the datasource's `discovered_schema` rows.
+pub(crate) async fn load_catalog_tags(
+    db: &DatabaseConnection,
+    schemas: &[discovered_schema::Model],
+) -> Result<CatalogTags, sea_orm::DbErr> {
+    let mut tags = CatalogTags::default();
+    let schema_names: HashMap<Uuid, &str> = schemas
+        .iter()
+        .map(|s| (s.id, s.schema_name.as_str()))
+        .collect();
+    for s in schemas {
+        tags.set_schema(&s.schema_name, parse_tags(s.tags.as_deref()));
+    }
+    if schema_names.is_empty() {
+        return Ok(tags);
+    }
 
-struct SessionDataClone {
-    permit_policies: Vec<ResolvedPolicy>,
+    // Tables are needed to place tagged columns even when the table itself is untagged.
+    let tables = discovered_table::Entity::find()
+        .filter(discovered_table::Column::DiscoveredSchemaId.is_in(schema_names.keys().copied()))
+        .all(db)
+        .await?;
+    let mut table_keys: HashMap<Uuid, (&str, &str)> = HashMap::new();
+    for t in &tables {
+        if let Some(schema) = schema_names.get(&t.discovered_schema_id) {
+            tags.set_table(schema, &t.table_name, parse_tags(t.tags.as_deref()));
+            table_keys.insert(t.id, (schema, t.table_name.as_str()));
+        }
+    }
+    if table_keys.is_empty() {
+        return Ok(tags);
+    }
+
+    let columns = discovered_column::Entity::find()
+        .filter(discovered_column::Column::DiscoveredTableId.is_in(table_keys.keys().copied()))
+        .filter(discovered_column::Column::Tags.is_not_null())
+        .all(db)
+        .await?;
+    for c in columns {
+        if let Some((schema, table)) = table_keys.get(&c.discovered_table_id) {
+            tags.set_column(schema, table, &c.column_name, parse_tags(c.tags.as_deref()));
+        }
+    }
+    Ok(tags)
+}
+
+// SessionData doesn't derive Clone, so we clone it manually.
+type SessionDataRef = Box<SessionDataClone>;
+
+struct SessionDataClone {
+    permit_policies: Vec<ResolvedPolicy>,
     deny_policies: Vec<ResolvedPolicy>,
+    shadow_policies: Vec<ResolvedPolicy>,
     access_mode: String,
+    write_mode: String,
+    combining: CombiningRules,
     df_to_upstream: HashMap<String, String>,
     datasource_id: Uuid,
     datasource_name: String,
@@ -1272,14 +1599,22 @@ struct SessionDataClone {
     user_attributes: HashMap<String, TypedAttribute>,
     attribute_defs: HashMap<String, AttrDefInfo>,
     relationship_snapshot: Arc<RelationshipSnapshot>,
+    catalog_tags: Arc<CatalogTags>,
     parent_scans_cache: Arc<tokio::sync::RwLock<HashMap<(String, String), LogicalPlan>>>,
+    lookup_cache: Arc<LookupCache>,
+    /// Skip `lookup_values`, leaving policy subqueries in the plan, so that
+    /// planning never reads upstream data (policy simulation).
+    plan_only: bool,
 }
 
 fn clone_session_data(s: &SessionData) -> SessionDataRef {
     Box::new(SessionDataClone {
         permit_policies: s.permit_policies.clone(),
         deny_policies: s.deny_policies.clone(),
+        shadow_policies: s.shadow_policies.clone(),
         access_mode: s.access_mode.clone(),
+        write_mode: s.write_mode.clone(),
+        combining: s.combining,
         df_to_upstream: s.df_to_upstream.clone(),
         datasource_id: s.datasource_id,
         datasource_name: s.datasource_name.clone(),
@@ -1287,7 +1622,10 @@ fn clone_session_data(s: &SessionData) -> SessionDataRef {
         user_attributes: s.user_attributes.clone(),
         attribute_defs: s.attribute_defs.clone(),
         relationship_snapshot: Arc::clone(&s.relationship_snapshot),
+        catalog_tags: Arc::clone(&s.catalog_tags),
         parent_scans_cache: Arc::clone(&s.parent_scans_cache),
+        lookup_cache: Arc::clone(&s.lookup_cache),
+        plan_only: false,
     })
 }
 
@@ -1302,7 +1640,7 @@ fn clone_session_data(s: &SessionData) -> SessionDataRef {
 /// DataFusion with at connect time — so a bare reference resolves against
 /// exactly that one schema and nothing else (`SET search_path` is blocked
 /// upstream by `ReadOnlyHook`).
-fn scan_policy_key(scan: &TableScan, default_schema: &str) -> (String, String) {
+pub(crate) fn scan_policy_key(scan: &TableScan, default_schema: &str) -> (String, String) {
     let schema = scan
         .table_name
         .schema()
@@ -1339,6 +1677,36 @@ fn collect_tables_inner(
     }
 }
 
+/// Collect the full source schema of every user-table `TableScan`, keyed like
+/// [`collect_user_tables`]. Used to resolve `tag:` column selectors, to plan filter
+/// and mask expressions over the table's real columns, and to report which columns
+/// a shadow policy would have denied or masked.
+fn collect_scan_schemas(
+    plan: &LogicalPlan,
+    default_schema: &str,
+) -> Vec<((String, String), SchemaRef)> {
+    fn inner(
+        plan: &LogicalPlan,
+        default_schema: &str,
+        out: &mut Vec<((String, String), SchemaRef)>,
+    ) {
+        if let LogicalPlan::TableScan(scan) = plan {
+            let key = scan_policy_key(scan, default_schema);
+            let is_system = SYSTEM_SCHEMAS.contains(&key.0.as_str()) || key.1.starts_with("pg_");
+            if !is_system && !out.iter().any(|(k, _)| k == &key) {
+                out.push((key, scan.source.schema()));
+            }
+            return;
+        }
+        for input in plan.inputs() {
+            inner(input, default_schema, out);
+        }
+    }
+    let mut out = Vec::new();
+    inner(plan, default_schema, &mut out);
+    out
+}
+
 // ---------- query metadata extraction ----------
 
 /// Extract query metadata from a logical plan for decision function evaluation.
@@ -1454,6 +1822,30 @@ enum PolicyError {
     DeniedByPolicy { policy_name: String },
     /// All columns were denied — nothing left to project (SQLSTATE 42501).
     AllColumnsDenied { columns: Vec<String> },
+    /// An `aggregate_only` policy rejected a row-level read (SQLSTATE 42501).
+    AggregateRequired { policy_name: String, reason: String },
+    /// A `join_only` column was used other than as a join key (SQLSTATE 42501).
+    JoinKeyExposed { policy_name: String, reason: String },
+    /// A `query_guard` policy refused the query's shape (SQLSTATE 42501).
+    QueryBlocked {
+        policy_name: String,
+        violation: crate::query_guard::Violation,
+    },
+    /// The query's epsilon would exceed the user's privacy budget (SQLSTATE 53400).
+    PrivacyBudgetExhausted { cost: f64, spent: f64, budget: f64 },
+    /// A `result_limit` policy's row quota for the current window is used up (SQLSTATE 53400).
+    RowQuotaExhausted {
+        policy_name: String,
+        rows: u64,
+        window_secs: u64,
+    },
+    /// A `write_deny` policy protects a column the write touches (SQLSTATE 42501).
+    WriteDenied { policy_name: String, reason: String },
+    /// The write is not granted by any `write_allow` policy, reads a column the
+    /// user may not read, or leaves a row outside the row filter (SQLSTATE 42501).
+    WriteRefused { reason: String },
+    /// The write's shape cannot be governed (SQLSTATE 0A000).
+    WriteUnsupported(String),
     /// Plan rewriting (filter injection or projection build) failed.
     PlanTransformation(datafusion::error::DataFusionError),
 }
@@ -1472,6 +1864,41 @@ impl std::fmt::Display for PolicyError {
                     columns.join(", ")
                 )
             }
+            PolicyError::AggregateRequired {
+                policy_name,
+                reason,
+            }
+            | PolicyError::JoinKeyExposed {
+                policy_name,
+                reason,
+            }
+            | PolicyError::WriteDenied {
+                policy_name,
+                reason,
+            } => write!(f, "Access denied by policy '{policy_name}': {reason}"),
+            PolicyError::WriteRefused { reason } => write!(f, "Access denied: {reason}"),
+            PolicyError::WriteUnsupported(reason) => write!(f, "Unsupported write: {reason}"),
+            PolicyError::QueryBlocked {
+                policy_name,
+                violation,
+            } => write!(f, "Query blocked by policy '{policy_name}': {violation}"),
+            PolicyError::PrivacyBudgetExhausted {
+                cost,
+                spent,
+                budget,
+            } => write!(
+                f,
+                "Privacy budget exhausted: query needs epsilon {cost}, {:.4} of {budget} remaining",
+                (budget - spent).max(0.0)
+            ),
+            PolicyError::RowQuotaExhausted {
+                policy_name,
+                rows,
+                window_secs,
+            } => write!(
+                f,
+                "Row quota exhausted: policy '{policy_name}' allows {rows} rows per {window_secs} seconds"
+            ),
             PolicyError::PlanTransformation(e) => write!(f, "Plan transformation error: {e}"),
         }
     }
@@ -1498,6 +1925,27 @@ impl PolicyError {
                     ),
                 )))
             }
+            PolicyError::AggregateRequired {
+                policy_name,
+                reason,
+            } => PgWireError::UserError(Box::new(ErrorInfo::new(
+                "ERROR".to_owned(),
+                "42501".to_owned(),
+                format!("Access denied by policy '{policy_name}': {reason}"),
+            ))),
+            e @ (PolicyError::JoinKeyExposed { .. }
+            | PolicyError::QueryBlocked { .. }
+            | PolicyError::WriteDenied { .. }
+            | PolicyError::WriteRefused { .. }) => PgWireError::UserError(Box::new(
+                ErrorInfo::new("ERROR".to_owned(), "42501".to_owned(), e.to_string()),
+            )),
+            e @ PolicyError::WriteUnsupported(_) => PgWireError::UserError(Box::new(
+                ErrorInfo::new("ERROR".to_owned(), "0A000".to_owned(), e.to_string()),
+            )),
+            e @ (PolicyError::PrivacyBudgetExhausted { .. }
+            | PolicyError::RowQuotaExhausted { .. }) => PgWireError::UserError(Box::new(
+                ErrorInfo::new("ERROR".to_owned(), "53400".to_owned(), e.to_string()),
+            )),
             PolicyError::PlanTransformation(e) => PgWireError::ApiError(Box::new(e)),
         }
     }
@@ -1512,22 +1960,78 @@ struct PolicyEffects {
     /// same value `create_session_context_from_catalog` configured DataFusion with
     /// at connect time. See vector #71.
     default_schema: String,
-    /// Combined row filter per (df_schema, table): AND within a policy, AND across policies.
+    /// Combined row filter per (df_schema, table): AND within a policy, then combined
+    /// across policies by the `row_filter` strategy (AND by default).
     row_filters: HashMap<(String, String), datafusion::logical_expr::Expr>,
-    /// Raw column allow patterns per (df_schema, table). Populated by `column_allow` policies.
+    /// Raw column allow patterns per (df_schema, table). Populated by `column_allow` policies,
+    /// combined by the `column_allow` strategy (merged by default).
     /// Expanded against actual column names at TableScan injection time.
     column_allow_patterns: HashMap<(String, String), Vec<String>>,
     /// Raw column deny patterns per (df_schema, table). Expanded at TableScan injection time.
     column_deny_patterns: HashMap<(String, String), Vec<String>>,
-    /// Column mask expressions keyed by (df_schema, table, column). First wins per column.
+    /// Column mask expressions keyed by (df_schema, table, column), picked by the
+    /// `column_mask` strategy (first match by default).
     column_masks: HashMap<(String, String, String), datafusion::logical_expr::Expr>,
     /// Tables that have at least one `column_allow` policy.
     /// `row_filter` and `column_mask` do NOT grant table access (zero-trust model).
     tables_with_permit: HashSet<(String, String)>,
     /// If set, a deny-type policy matched the query — must reject before executing.
     denied_by_policy: Option<String>,
+    /// `aggregate_only` tables: smallest allowed group size and the policy that set it.
+    /// Picked by the `aggregate_only` strategy (the largest `min_group_size` by default).
+    aggregate_only: HashMap<(String, String), (i64, String)>,
+    /// `differential_privacy` tables: the definition and the policy it came from.
+    /// Picked by the `differential_privacy` strategy (the smallest `epsilon` by default).
+    differential_privacy: HashMap<(String, String), (DifferentialPrivacyDef, String)>,
+    /// Combined caps of the `result_limit` policies whose targets the query reads.
+    result_limit: Option<crate::result_limit::ResultLimit>,
+    /// `join_only` columns keyed by (df_schema, table, column), with the policy that
+    /// restricts each. First (highest priority) policy wins per column.
+    join_only: crate::join_only::JoinOnlyColumns,
     /// Decision function evaluation results, keyed by policy ID, for audit logging.
     decision_results: HashMap<Uuid, crate::decision::DecisionResult>,
+    /// What each matching shadow policy would have done. Never applied.
+    shadow_outcomes: Vec<ShadowOutcome>,
+    /// Targets where several permit policies of one type met, and which applied.
+    policy_conflicts: Vec<PolicyConflict>,
+}
+
+/// Permit effects competing for each target, before they are combined.
+type Contenders<K, T> = HashMap<K, Vec<Contender<T>>>;
+
+/// A `NULL` of `column`'s type, for masks that must hide the value entirely.
+fn typed_null(table_schema: &SchemaRef, column: &str) -> datafusion::logical_expr::Expr {
+    let null = table_schema
+        .field_with_name(column)
+        .ok()
+        .and_then(|f| ScalarValue::try_from(f.data_type()).ok())
+        .unwrap_or(ScalarValue::Null);
+    lit(null)
+}
+
+/// Counterfactual effect of one `shadow` policy on a query, recorded in
+/// `query_audit_log.shadow_outcomes`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+struct ShadowOutcome {
+    policy_id: Uuid,
+    name: String,
+    version: i32,
+    policy_type: PolicyType,
+    /// `would_deny`, `would_deny_columns`, `would_filter`, `would_mask`, `would_allow`,
+    /// `would_require_aggregate`, `would_add_noise`, `would_limit_results`,
+    /// `would_restrict_to_joins`, `would_block_query`, `would_deny_write` or
+    /// `would_allow_write`.
+    outcome: &'static str,
+    /// Matched tables as `schema.table`.
+    tables: Vec<String>,
+    /// Denied, masked or allowed columns as `schema.table.column`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    columns: Vec<String>,
+    /// The filter or mask expression that would have been applied, the
+    /// `query_guard` rule that would have blocked the query, or the write
+    /// statement (`UPDATE`, ...) a write policy would have decided.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expression: Option<String>,
 }
 
 /// Optional context for decision function evaluation at query time.
@@ -1643,9 +2147,12 @@ impl PolicyEffects {
     /// via WASM. If a decision function returns `fire: false`, the policy is skipped.
     /// If `decision_eval` is None, policies with decision functions are treated as if they
     /// always fire (backward-compatible behavior for tests).
+    ///
+    /// `scan_schemas` lists every user table in the query with its source schema
+    /// (see [`collect_scan_schemas`]); `tag:` column selectors resolve against it.
     async fn collect(
         session: &SessionDataClone,
-        user_tables: &[(String, String)],
+        scan_schemas: &[((String, String), SchemaRef)],
         user_vars: &UserVars,
         session_context: &SessionContext,
         decision_eval: Option<&DecisionEvalContext<'_>>,
@@ -1670,7 +2177,13 @@ impl PolicyEffects {
             column_masks: HashMap::new(),
             tables_with_permit: HashSet::new(),
             denied_by_policy: None,
+            aggregate_only: HashMap::new(),
+            differential_privacy: HashMap::new(),
+            result_limit: None,
+            join_only: HashMap::new(),
             decision_results: HashMap::new(),
+            shadow_outcomes: Vec::new(),
+            policy_conflicts: Vec::new(),
         };
 
         // Check table_deny policies first (short-circuit on first match).
@@ -1682,9 +2195,14 @@ impl PolicyEffects {
             if !evaluate_decision_fn(policy, decision_eval, &mut effects.decision_results).await {
                 continue;
             }
-            for (df_schema, table) in user_tables {
+            for ((df_schema, table), _) in scan_schemas {
                 for entry in &policy.targets {
-                    if entry.matches_table(df_schema, table, &session.df_to_upstream) {
+                    if entry.matches_table(
+                        df_schema,
+                        table,
+                        &session.df_to_upstream,
+                        &session.catalog_tags,
+                    ) {
                         effects.denied_by_policy = Some(policy.name.clone());
                         break 'deny_check;
                     }
@@ -1701,24 +2219,55 @@ impl PolicyEffects {
             if !evaluate_decision_fn(policy, decision_eval, &mut effects.decision_results).await {
                 continue;
             }
-            for (df_schema, table) in user_tables {
+            for ((df_schema, table), table_schema) in scan_schemas {
+                let all_cols: Vec<&str> = table_schema
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().as_str())
+                    .collect();
                 for entry in &policy.targets {
-                    if entry.matches_table(df_schema, table, &session.df_to_upstream) {
-                        let key = (df_schema.clone(), table.clone());
-                        if let Some(cols) = &entry.columns {
+                    if entry.matches_table(
+                        df_schema,
+                        table,
+                        &session.df_to_upstream,
+                        &session.catalog_tags,
+                    ) {
+                        let patterns = entry.column_patterns(
+                            df_schema,
+                            table,
+                            &all_cols,
+                            &session.df_to_upstream,
+                            &session.catalog_tags,
+                        );
+                        if !patterns.is_empty() {
+                            let key = (df_schema.clone(), table.clone());
                             effects
                                 .column_deny_patterns
                                 .entry(key)
                                 .or_default()
-                                .extend(cols.iter().cloned());
+                                .extend(patterns);
                         }
                     }
                 }
             }
         }
 
-        // Collect permit policy effects.
+        // Collect permit policy effects. Effects that compete for one target are
+        // gathered first and combined below by the datasource's strategies.
+        let mut filters: Contenders<(String, String), datafusion::logical_expr::Expr> =
+            HashMap::new();
+        let mut masks: Contenders<(String, String, String), datafusion::logical_expr::Expr> =
+            HashMap::new();
+        let mut allows: Contenders<(String, String), Vec<String>> = HashMap::new();
+        let mut aggregates: Contenders<(String, String), (i64, String)> = HashMap::new();
+        let mut privacy: Contenders<(String, String), (DifferentialPrivacyDef, String)> =
+            HashMap::new();
         for policy in &session.permit_policies {
+            // query_guard policies are checked by `check_query_guards`, before
+            // the plan is rewritten; write policies only govern write statements.
+            if policy.policy_type == PolicyType::QueryGuard || policy.policy_type.is_write() {
+                continue;
+            }
             // Evaluate decision function if present
             if !evaluate_decision_fn(policy, decision_eval, &mut effects.decision_results).await {
                 continue;
@@ -1728,6 +2277,7 @@ impl PolicyEffects {
                 (String, String),
                 datafusion::logical_expr::Expr,
             > = HashMap::new();
+            let mut policy_allow_patterns: HashMap<(String, String), Vec<String>> = HashMap::new();
 
             match policy.policy_type {
                 PolicyType::RowFilter => {
@@ -1740,27 +2290,42 @@ impl PolicyEffects {
                     if filter_expr.is_empty() {
                         continue;
                     }
-                    for (df_schema, table) in user_tables {
+                    for ((df_schema, table), table_schema) in scan_schemas {
                         for entry in &policy.targets {
-                            if entry.matches_table(df_schema, table, &session.df_to_upstream) {
+                            if entry.matches_table(
+                                df_schema,
+                                table,
+                                &session.df_to_upstream,
+                                &session.catalog_tags,
+                            ) {
                                 let key = (df_schema.clone(), table.clone());
                                 // row_filter does NOT grant table access (zero-trust model).
-                                match parse_filter_expr(filter_expr, user_vars) {
-                                    Ok(filter) => {
-                                        // AND within the same policy, then ANDed globally.
-                                        let e = policy_table_filters
-                                            .entry(key)
-                                            .or_insert_with(|| lit(true));
-                                        *e = e.clone().and(filter);
-                                    }
+                                let filter = match parse_filter_expr(
+                                    session_context,
+                                    filter_expr,
+                                    user_vars,
+                                    table,
+                                    table_schema,
+                                )
+                                .await
+                                {
+                                    Ok(filter) => filter,
                                     Err(e) => {
+                                        // A filter that cannot be planned hides every row
+                                        // (deny-wins) rather than being dropped.
                                         tracing::error!(
                                             error = %e,
                                             policy = %policy.name,
-                                            "Failed to parse row_filter expression"
+                                            table = %table,
+                                            "Failed to parse row_filter expression; denying all rows"
                                         );
+                                        lit(false)
                                     }
-                                }
+                                };
+                                // AND within the same policy, then ANDed globally.
+                                let e =
+                                    policy_table_filters.entry(key).or_insert_with(|| lit(true));
+                                *e = e.clone().and(filter);
                                 break; // one resource entry match is sufficient per table
                             }
                         }
@@ -1776,36 +2341,61 @@ impl PolicyEffects {
                     if mask_expr.is_empty() {
                         continue;
                     }
-                    for (df_schema, table) in user_tables {
+                    for ((df_schema, table), table_schema) in scan_schemas {
+                        let all_cols: Vec<&str> = table_schema
+                            .fields()
+                            .iter()
+                            .map(|f| f.name().as_str())
+                            .collect();
                         for entry in &policy.targets {
-                            if entry.matches_table(df_schema, table, &session.df_to_upstream) {
+                            if entry.matches_table(
+                                df_schema,
+                                table,
+                                &session.df_to_upstream,
+                                &session.catalog_tags,
+                            ) {
                                 // column_mask does NOT grant table access (zero-trust model).
-                                let columns = entry.columns.as_deref().unwrap_or_default();
-                                for col in columns {
-                                    let triple = (df_schema.clone(), table.clone(), col.clone());
-                                    // First (highest priority) mask wins.
-                                    if let std::collections::hash_map::Entry::Vacant(e) =
-                                        effects.column_masks.entry(triple)
+                                let columns = entry.column_patterns(
+                                    df_schema,
+                                    table,
+                                    &all_cols,
+                                    &session.df_to_upstream,
+                                    &session.catalog_tags,
+                                );
+                                for col in &columns {
+                                    let contenders = masks
+                                        .entry((df_schema.clone(), table.clone(), col.clone()))
+                                        .or_default();
+                                    if contenders
+                                        .last()
+                                        .is_some_and(|c| c.precedence.name == policy.name)
                                     {
-                                        match parse_mask_expr(
-                                            session_context,
-                                            col,
-                                            mask_expr,
-                                            user_vars,
-                                        ) {
-                                            Ok(mask) => {
-                                                e.insert(mask);
-                                            }
-                                            Err(err) => {
-                                                tracing::error!(
-                                                    error = %err,
-                                                    policy = %policy.name,
-                                                    column = %col,
-                                                    "Failed to parse column_mask expression"
-                                                );
-                                            }
-                                        }
+                                        continue;
                                     }
+                                    let mask = match parse_mask_expr(
+                                        session_context,
+                                        col,
+                                        mask_expr,
+                                        user_vars,
+                                        table,
+                                        table_schema,
+                                    )
+                                    .await
+                                    {
+                                        Ok(mask) => mask,
+                                        Err(err) => {
+                                            // A mask that cannot be planned yields NULL
+                                            // rather than the raw value.
+                                            tracing::error!(
+                                                error = %err,
+                                                policy = %policy.name,
+                                                column = %col,
+                                                "Failed to parse column_mask expression; masking to NULL"
+                                            );
+                                            typed_null(table_schema, col)
+                                        }
+                                    };
+                                    contenders.push(policy.contender(mask));
                                 }
                             }
                         }
@@ -1813,36 +2403,518 @@ impl PolicyEffects {
                 }
                 PolicyType::ColumnAllow => {
                     // column_allow grants table access and restricts visible columns.
-                    for (df_schema, table) in user_tables {
+                    for ((df_schema, table), table_schema) in scan_schemas {
+                        let all_cols: Vec<&str> = table_schema
+                            .fields()
+                            .iter()
+                            .map(|f| f.name().as_str())
+                            .collect();
                         for entry in &policy.targets {
-                            if entry.matches_table(df_schema, table, &session.df_to_upstream) {
+                            if entry.matches_table(
+                                df_schema,
+                                table,
+                                &session.df_to_upstream,
+                                &session.catalog_tags,
+                            ) {
                                 let key = (df_schema.clone(), table.clone());
                                 effects.tables_with_permit.insert(key.clone());
-                                if let Some(cols) = &entry.columns {
-                                    effects
-                                        .column_allow_patterns
-                                        .entry(key)
-                                        .or_default()
-                                        .extend(cols.iter().cloned());
+                                // An allow list whose tag matches no column stays
+                                // empty, which hides every column (fail closed).
+                                if entry.columns.is_some() {
+                                    policy_allow_patterns.entry(key).or_default().extend(
+                                        entry.column_patterns(
+                                            df_schema,
+                                            table,
+                                            &all_cols,
+                                            &session.df_to_upstream,
+                                            &session.catalog_tags,
+                                        ),
+                                    );
                                 }
                             }
                         }
                     }
                 }
-                // ColumnDeny and TableDeny are handled in the deny_policies loop above.
-                PolicyType::ColumnDeny | PolicyType::TableDeny => {}
+                PolicyType::AggregateOnly => {
+                    // aggregate_only restricts how a table is read; like row_filter it
+                    // does NOT grant table access.
+                    let min_group_size = match policy
+                        .definition
+                        .clone()
+                        .map(serde_json::from_value::<AggregateOnlyDef>)
+                    {
+                        Some(Ok(def)) => def.min_group_size,
+                        _ => {
+                            tracing::error!(
+                                policy = %policy.name,
+                                "Invalid aggregate_only definition"
+                            );
+                            continue;
+                        }
+                    };
+                    for ((df_schema, table), _) in scan_schemas {
+                        let matched = policy.targets.iter().any(|entry| {
+                            entry.matches_table(
+                                df_schema,
+                                table,
+                                &session.df_to_upstream,
+                                &session.catalog_tags,
+                            )
+                        });
+                        if !matched {
+                            continue;
+                        }
+                        aggregates
+                            .entry((df_schema.clone(), table.clone()))
+                            .or_default()
+                            .push(policy.contender((min_group_size, policy.name.clone())));
+                    }
+                }
+                PolicyType::DifferentialPrivacy => {
+                    // Like aggregate_only, this restricts reads without granting access.
+                    let def = match policy
+                        .definition
+                        .clone()
+                        .map(serde_json::from_value::<DifferentialPrivacyDef>)
+                    {
+                        Some(Ok(def)) => def,
+                        _ => {
+                            tracing::error!(
+                                policy = %policy.name,
+                                "Invalid differential_privacy definition"
+                            );
+                            continue;
+                        }
+                    };
+                    for ((df_schema, table), _) in scan_schemas {
+                        let matched = policy.targets.iter().any(|entry| {
+                            entry.matches_table(
+                                df_schema,
+                                table,
+                                &session.df_to_upstream,
+                                &session.catalog_tags,
+                            )
+                        });
+                        if !matched {
+                            continue;
+                        }
+                        privacy
+                            .entry((df_schema.clone(), table.clone()))
+                            .or_default()
+                            .push(policy.contender((def.clone(), policy.name.clone())));
+                    }
+                }
+                PolicyType::ResultLimit => {
+                    // Caps apply to the whole result, once per policy, when the
+                    // query reads any target table. Like row_filter, no access grant.
+                    let def = match policy
+                        .definition
+                        .clone()
+                        .map(serde_json::from_value::<ResultLimitDef>)
+                    {
+                        Some(Ok(def)) => def,
+                        _ => {
+                            tracing::error!(
+                                policy = %policy.name,
+                                "Invalid result_limit definition"
+                            );
+                            continue;
+                        }
+                    };
+                    let matched = scan_schemas.iter().any(|((df_schema, table), _)| {
+                        policy.targets.iter().any(|entry| {
+                            entry.matches_table(
+                                df_schema,
+                                table,
+                                &session.df_to_upstream,
+                                &session.catalog_tags,
+                            )
+                        })
+                    });
+                    if matched {
+                        effects.result_limit.get_or_insert_default().add(
+                            &def,
+                            policy.id,
+                            &policy.name,
+                        );
+                    }
+                }
+                PolicyType::JoinOnly => {
+                    // join_only restricts how columns are used; like column_mask it
+                    // does NOT grant table access.
+                    for ((df_schema, table), table_schema) in scan_schemas {
+                        let all_cols: Vec<&str> = table_schema
+                            .fields()
+                            .iter()
+                            .map(|f| f.name().as_str())
+                            .collect();
+                        let patterns: Vec<String> = policy
+                            .targets
+                            .iter()
+                            .filter(|entry| {
+                                entry.matches_table(
+                                    df_schema,
+                                    table,
+                                    &session.df_to_upstream,
+                                    &session.catalog_tags,
+                                )
+                            })
+                            .flat_map(|entry| {
+                                entry.column_patterns(
+                                    df_schema,
+                                    table,
+                                    &all_cols,
+                                    &session.df_to_upstream,
+                                    &session.catalog_tags,
+                                )
+                            })
+                            .collect();
+                        for column in expand_column_patterns(&patterns, &all_cols) {
+                            effects
+                                .join_only
+                                .entry((df_schema.clone(), table.clone(), column))
+                                .or_insert_with(|| policy.name.clone());
+                        }
+                    }
+                }
+                // ColumnDeny and TableDeny are handled in the deny_policies loop above,
+                // QueryGuard before this function runs, write policies by `check_write_grants`.
+                PolicyType::ColumnDeny
+                | PolicyType::TableDeny
+                | PolicyType::QueryGuard
+                | PolicyType::WriteAllow
+                | PolicyType::WriteDeny => {}
             }
 
-            // AND this policy's per-table filters into the global row_filters map.
             for (key, filter) in policy_table_filters {
-                let entry = effects.row_filters.entry(key).or_insert_with(|| lit(true));
-                *entry = entry.clone().and(filter);
+                filters
+                    .entry(key)
+                    .or_default()
+                    .push(policy.contender(filter));
+            }
+            for (key, patterns) in policy_allow_patterns {
+                allows
+                    .entry(key)
+                    .or_default()
+                    .push(policy.contender(patterns));
             }
         }
 
+        effects.combine_permits(
+            session,
+            scan_schemas,
+            filters,
+            masks,
+            allows,
+            aggregates,
+            privacy,
+        );
         effects
     }
 
+    /// Combine the permit effects that compete for each target under the
+    /// datasource's [`CombiningRules`], and record every target where several
+    /// policies met in `policy_conflicts`.
+    #[allow(clippy::too_many_arguments)]
+    fn combine_permits(
+        &mut self,
+        session: &SessionDataClone,
+        scan_schemas: &[((String, String), SchemaRef)],
+        filters: Contenders<(String, String), datafusion::logical_expr::Expr>,
+        masks: Contenders<(String, String, String), datafusion::logical_expr::Expr>,
+        allows: Contenders<(String, String), Vec<String>>,
+        aggregates: Contenders<(String, String), (i64, String)>,
+        privacy: Contenders<(String, String), (DifferentialPrivacyDef, String)>,
+    ) {
+        let rules = session.combining;
+        let table_schema = |schema: &str, table: &str| {
+            scan_schemas
+                .iter()
+                .find(|((s, t), _)| s == schema && t == table)
+                .map(|(_, table_schema)| table_schema)
+        };
+        let mut conflicts = Vec::new();
+
+        for ((schema, table), contenders) in filters {
+            let combined = combine(
+                PolicyType::RowFilter,
+                format!("{schema}.{table}"),
+                rules.row_filter,
+                contenders,
+                None,
+            );
+            conflicts.extend(combined.conflict);
+            let filter = match rules.row_filter {
+                // A row is visible if any policy's filter lets it through.
+                CombineStrategy::Union => combined.values.into_iter().reduce(|a, b| a.or(b)),
+                _ => combined
+                    .values
+                    .into_iter()
+                    .fold(None, |acc, f| Some(acc.unwrap_or_else(|| lit(true)).and(f))),
+            };
+            if let Some(filter) = filter {
+                self.row_filters.insert((schema, table), filter);
+            }
+        }
+
+        for ((schema, table, column), contenders) in masks {
+            let combined = combine(
+                PolicyType::ColumnMask,
+                format!("{schema}.{table}.{column}"),
+                rules.column_mask,
+                contenders,
+                None,
+            );
+            conflicts.extend(combined.conflict);
+            let mut values = combined.values.into_iter();
+            let Some(first) = values.next() else {
+                continue;
+            };
+            // Under most_restrictive, masks that disagree yield NULL.
+            let mask = if values.all(|m| m == first) {
+                first
+            } else {
+                match table_schema(&schema, &table) {
+                    Some(table_schema) => typed_null(table_schema, &column),
+                    None => lit(ScalarValue::Null),
+                }
+            };
+            self.column_masks.insert((schema, table, column), mask);
+        }
+
+        for ((schema, table), contenders) in allows {
+            let combined = combine(
+                PolicyType::ColumnAllow,
+                format!("{schema}.{table}"),
+                rules.column_allow,
+                contenders,
+                None,
+            );
+            conflicts.extend(combined.conflict);
+            let patterns = if rules.column_allow == CombineStrategy::Intersection {
+                // Only columns every allow list grants.
+                let all_cols: Vec<&str> = table_schema(&schema, &table)
+                    .map(|s| s.fields().iter().map(|f| f.name().as_str()).collect())
+                    .unwrap_or_default();
+                let mut lists = combined
+                    .values
+                    .iter()
+                    .map(|patterns| expand_column_patterns(patterns, &all_cols));
+                let first = lists.next().unwrap_or_default();
+                let rest: Vec<HashSet<String>> = lists.map(|l| l.into_iter().collect()).collect();
+                first
+                    .into_iter()
+                    .filter(|c| rest.iter().all(|l| l.contains(c)))
+                    .collect()
+            } else {
+                combined.values.concat()
+            };
+            self.column_allow_patterns.insert((schema, table), patterns);
+        }
+
+        for ((schema, table), contenders) in aggregates {
+            let combined = combine(
+                PolicyType::AggregateOnly,
+                format!("{schema}.{table}"),
+                rules.aggregate_only,
+                contenders,
+                // A larger minimum group size is stricter.
+                Some(|a, b| a.0.cmp(&b.0)),
+            );
+            conflicts.extend(combined.conflict);
+            if let Some(winner) = combined.values.into_iter().next() {
+                self.aggregate_only.insert((schema, table), winner);
+            }
+        }
+
+        for ((schema, table), contenders) in privacy {
+            let combined = combine(
+                PolicyType::DifferentialPrivacy,
+                format!("{schema}.{table}"),
+                rules.differential_privacy,
+                contenders,
+                // A smaller epsilon is stricter.
+                Some(|a, b| b.0.epsilon.total_cmp(&a.0.epsilon)),
+            );
+            conflicts.extend(combined.conflict);
+            if let Some(winner) = combined.values.into_iter().next() {
+                self.differential_privacy.insert((schema, table), winner);
+            }
+        }
+
+        conflicts.sort_by(|a, b| {
+            (a.policy_type.as_str(), &a.target).cmp(&(b.policy_type.as_str(), &b.target))
+        });
+        self.policy_conflicts = conflicts;
+    }
+
+    /// Evaluate shadow policies against the query and record what they would
+    /// have done. Decision functions run exactly as for enforced policies; the
+    /// plan is not touched.
+    async fn collect_shadow(
+        &mut self,
+        session: &SessionDataClone,
+        scan_schemas: &[((String, String), SchemaRef)],
+        user_vars: &UserVars,
+        decision_eval: Option<&DecisionEvalContext<'_>>,
+    ) {
+        for policy in &session.shadow_policies {
+            // Shadow write policies are reported by the write path.
+            if policy.policy_type == PolicyType::QueryGuard || policy.policy_type.is_write() {
+                continue;
+            }
+            if !evaluate_decision_fn(policy, decision_eval, &mut self.decision_results).await {
+                continue;
+            }
+
+            let mut tables = Vec::new();
+            let mut columns = Vec::new();
+            for ((df_schema, table), table_schema) in scan_schemas {
+                let entries: Vec<&TargetEntry> = policy
+                    .targets
+                    .iter()
+                    .filter(|e| {
+                        e.matches_table(
+                            df_schema,
+                            table,
+                            &session.df_to_upstream,
+                            &session.catalog_tags,
+                        )
+                    })
+                    .collect();
+                if entries.is_empty() {
+                    continue;
+                }
+                let all_cols: Vec<&str> = table_schema
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().as_str())
+                    .collect();
+                let patterns: Vec<String> = entries
+                    .iter()
+                    .flat_map(|e| {
+                        e.column_patterns(
+                            df_schema,
+                            table,
+                            &all_cols,
+                            &session.df_to_upstream,
+                            &session.catalog_tags,
+                        )
+                    })
+                    .collect();
+                let mut matched: Vec<String> = expand_column_patterns(&patterns, &all_cols)
+                    .into_iter()
+                    .collect();
+                matched.sort();
+                if matches!(
+                    policy.policy_type,
+                    PolicyType::ColumnDeny | PolicyType::ColumnMask | PolicyType::JoinOnly
+                ) && matched.is_empty()
+                {
+                    continue;
+                }
+                tables.push(format!("{df_schema}.{table}"));
+                columns.extend(
+                    matched
+                        .into_iter()
+                        .map(|c| format!("{df_schema}.{table}.{c}")),
+                );
+            }
+            if tables.is_empty() {
+                continue;
+            }
+
+            let expression_of = |key: &str| {
+                policy
+                    .definition
+                    .as_ref()
+                    .and_then(|d| d.get(key))
+                    .and_then(|v| v.as_str())
+                    .filter(|e| !e.is_empty())
+                    .map(str::to_string)
+            };
+            let (outcome, expression) = match policy.policy_type {
+                PolicyType::TableDeny => ("would_deny", None),
+                PolicyType::ColumnDeny => ("would_deny_columns", None),
+                PolicyType::ColumnAllow => ("would_allow", None),
+                PolicyType::RowFilter => {
+                    let Some(expr) = expression_of("filter_expression") else {
+                        continue;
+                    };
+                    if let Err(e) = parse_expr_detached(&expr, user_vars) {
+                        tracing::error!(
+                            error = %e,
+                            policy = %policy.name,
+                            "Failed to parse shadow row_filter expression"
+                        );
+                        continue;
+                    }
+                    ("would_filter", Some(expr))
+                }
+                PolicyType::ColumnMask => {
+                    let Some(expr) = expression_of("mask_expression") else {
+                        continue;
+                    };
+                    ("would_mask", Some(expr))
+                }
+                PolicyType::AggregateOnly => {
+                    let Some(k) = policy
+                        .definition
+                        .clone()
+                        .and_then(|d| serde_json::from_value::<AggregateOnlyDef>(d).ok())
+                    else {
+                        continue;
+                    };
+                    (
+                        "would_require_aggregate",
+                        Some(format!("HAVING count(*) >= {}", k.min_group_size)),
+                    )
+                }
+                PolicyType::DifferentialPrivacy => {
+                    let Some(def) = policy
+                        .definition
+                        .clone()
+                        .and_then(|d| serde_json::from_value::<DifferentialPrivacyDef>(d).ok())
+                    else {
+                        continue;
+                    };
+                    (
+                        "would_add_noise",
+                        Some(format!(
+                            "{} noise, epsilon {} per aggregate",
+                            def.mechanism.as_str(),
+                            def.epsilon
+                        )),
+                    )
+                }
+                PolicyType::ResultLimit => {
+                    let Some(def) = policy
+                        .definition
+                        .clone()
+                        .and_then(|d| serde_json::from_value::<ResultLimitDef>(d).ok())
+                    else {
+                        continue;
+                    };
+                    ("would_limit_results", Some(describe_caps(&def)))
+                }
+                PolicyType::JoinOnly => ("would_restrict_to_joins", None),
+                PolicyType::QueryGuard | PolicyType::WriteAllow | PolicyType::WriteDeny => {
+                    continue;
+                }
+            };
+            self.shadow_outcomes.push(ShadowOutcome {
+                policy_id: policy.id,
+                name: policy.name.clone(),
+                version: policy.version,
+                policy_type: policy.policy_type,
+                outcome,
+                tables,
+                columns,
+                expression,
+            });
+        }
+    }
+
     /// Return an error if a deny-effect row_filter matched the query.
     fn check_deny(&self) -> Result<(), PolicyError> {
         if let Some(name) = &self.denied_by_policy {
@@ -1854,44 +2926,523 @@ impl PolicyEffects {
         }
     }
 
-    /// For `access_mode = "policy_required"`: inject `lit(false)` for tables with no permit.
-    fn apply_access_mode(&mut self, access_mode: &str, user_tables: &[(String, String)]) {
-        if access_mode == "policy_required" {
-            for table_key in user_tables {
-                if !self.tables_with_permit.contains(table_key) {
-                    self.row_filters.insert(table_key.clone(), lit(false));
+    /// Reject the query if an `aggregate_only` or `differential_privacy` table is
+    /// read outside an aggregate.
+    ///
+    /// Every `TableScan` of such a table must sit below an `Aggregate` in the same
+    /// query block; a subquery in an expression starts a new block, so
+    /// `WHERE x IN (SELECT ssn FROM t)` is rejected even inside an outer aggregate.
+    /// Collecting aggregates (`array_agg`, `string_agg`, ...) over the table are
+    /// rejected too, since they return every row's value. `has_aggregation` from
+    /// [`extract_query_metadata`] short-circuits plans with no aggregate at all.
+    ///
+    /// Aggregates over `differential_privacy` tables must also be ones the noise
+    /// rewrite supports (see [`PrivateAggregate::classify`]).
+    fn check_aggregate_only(&self, plan: &LogicalPlan) -> Result<(), PolicyError> {
+        let first = self
+            .aggregate_only
+            .iter()
+            .map(|(key, (_, policy_name))| (key, policy_name))
+            .chain(
+                self.differential_privacy
+                    .iter()
+                    .map(|(key, (_, policy_name))| (key, policy_name)),
+            )
+            .next();
+        let Some((first_key, first_policy)) = first else {
+            return Ok(());
+        };
+        if !extract_query_metadata(plan, &self.default_schema, "").has_aggregation {
+            return Err(PolicyError::AggregateRequired {
+                policy_name: first_policy.clone(),
+                reason: aggregate_required_reason(first_key),
+            });
+        }
+        self.check_aggregate_only_inner(plan, false)
+    }
+
+    /// Reject the query if it uses a `join_only` column other than as an equality
+    /// join key (see [`crate::join_only`]).
+    fn check_join_only(&self, plan: &LogicalPlan) -> Result<(), PolicyError> {
+        crate::join_only::check(plan, &self.join_only, &self.default_schema).map_err(|v| {
+            PolicyError::JoinKeyExposed {
+                policy_name: v.policy_name,
+                reason: v.message,
+            }
+        })
+    }
+
+    /// Policy that restricts `key` to aggregate reads, if any.
+    fn aggregate_restriction(&self, key: &(String, String)) -> Option<&str> {
+        self.aggregate_only
+            .get(key)
+            .map(|(_, policy_name)| policy_name.as_str())
+            .or_else(|| {
+                self.differential_privacy
+                    .get(key)
+                    .map(|(_, policy_name)| policy_name.as_str())
+            })
+    }
+
+    fn check_aggregate_only_inner(
+        &self,
+        plan: &LogicalPlan,
+        aggregated: bool,
+    ) -> Result<(), PolicyError> {
+        use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+        use datafusion::logical_expr::Expr;
+
+        let mut aggregated = aggregated;
+        match plan {
+            LogicalPlan::TableScan(scan) => {
+                let key = scan_policy_key(scan, &self.default_schema);
+                if !aggregated && let Some(policy_name) = self.aggregate_restriction(&key) {
+                    return Err(PolicyError::AggregateRequired {
+                        policy_name: policy_name.to_string(),
+                        reason: aggregate_required_reason(&key),
+                    });
+                }
+            }
+            LogicalPlan::Aggregate(agg) => {
+                if let Some((key, policy_name)) = self.aggregate_only_scan_in(&agg.input) {
+                    for expr in &agg.aggr_expr {
+                        let mut collecting = None;
+                        let _ = expr.apply(|e| {
+                            if let Expr::AggregateFunction(f) = e
+                                && COLLECTING_AGGREGATES.contains(&f.func.name())
+                            {
+                                collecting = Some(f.func.name().to_string());
+                                return Ok(TreeNodeRecursion::Stop);
+                            }
+                            Ok(TreeNodeRecursion::Continue)
+                        });
+                        if let Some(func) = collecting {
+                            return Err(PolicyError::AggregateRequired {
+                                policy_name: policy_name.to_string(),
+                                reason: format!(
+                                    "{func} returns individual values and is not allowed on {}.{}",
+                                    key.0, key.1
+                                ),
+                            });
+                        }
+                    }
+                }
+                if let Some(((schema, table), policy_name, through)) =
+                    self.aggregate_only_through(&agg.input, None)
+                {
+                    return Err(PolicyError::AggregateRequired {
+                        policy_name: policy_name.to_string(),
+                        reason: format!(
+                            "aggregates cannot read {schema}.{table} through {through}"
+                        ),
+                    });
+                }
+                if let Some(private) = self.private_scan_in(&agg.input) {
+                    let reject = |reason: String| PolicyError::AggregateRequired {
+                        policy_name: private.policy_name.to_string(),
+                        reason,
+                    };
+                    let (schema, table) = private.key;
+                    if let Some(through) = private.through {
+                        return Err(reject(format!(
+                            "differentially private aggregates cannot read {schema}.{table} through {through}"
+                        )));
+                    }
+                    for expr in &agg.aggr_expr {
+                        PrivateAggregate::classify(expr, private.def)
+                            .map_err(|e| reject(format!("{e} (on {schema}.{table})")))?;
+                    }
                 }
+                aggregated = true;
+            }
+            _ => {}
+        }
+
+        let mut subqueries = Vec::new();
+        let _ = plan.apply_subqueries(|sub| {
+            subqueries.push(sub.clone());
+            Ok(TreeNodeRecursion::Jump)
+        });
+        for sub in &subqueries {
+            self.check_aggregate_only_inner(sub, false)?;
+        }
+        for input in plan.inputs() {
+            self.check_aggregate_only_inner(input, aggregated)?;
+        }
+        Ok(())
+    }
+
+    /// First `aggregate_only` or `differential_privacy` table scanned in `plan`'s
+    /// own query block, not looking below nested aggregates (they are checked
+    /// on their own).
+    fn aggregate_only_scan_in(&self, plan: &LogicalPlan) -> Option<((String, String), &str)> {
+        match plan {
+            LogicalPlan::TableScan(scan) => {
+                let key = scan_policy_key(scan, &self.default_schema);
+                self.aggregate_restriction(&key)
+                    .map(|policy_name| (key, policy_name))
             }
+            LogicalPlan::Aggregate(_) => None,
+            _ => plan
+                .inputs()
+                .into_iter()
+                .find_map(|input| self.aggregate_only_scan_in(input)),
         }
     }
 
-    /// Inject row filter `Filter` nodes below each matching `TableScan` via `transform_up`.
+    /// The `aggregate_only` table an aggregate reads in `plan`'s own query block
+    /// through a join, UNION or `UNNEST` (`through`, once one was passed).
     ///
-    /// Row filters are scoped to their source table, so they can safely reference columns
-    /// that are later stripped by the top-level projection (e.g. `tenant_id` filters).
+    /// The `min_group_size` count is of the rows the aggregate sees, and those
+    /// nodes can repeat a single row until its group passes the threshold.
+    fn aggregate_only_through(
+        &self,
+        plan: &LogicalPlan,
+        through: Option<&'static str>,
+    ) -> Option<((String, String), &str, &'static str)> {
+        match plan {
+            LogicalPlan::TableScan(scan) => {
+                let through = through?;
+                let key = scan_policy_key(scan, &self.default_schema);
+                let (_, policy_name) = self.aggregate_only.get(&key)?;
+                Some((key, policy_name.as_str(), through))
+            }
+            LogicalPlan::Aggregate(_) => None,
+            other => {
+                let through = through.or(match other {
+                    LogicalPlan::Join(_) => Some("a join"),
+                    LogicalPlan::Union(_) => Some("a UNION"),
+                    LogicalPlan::Unnest(_) => Some("UNNEST"),
+                    _ => None,
+                });
+                other
+                    .inputs()
+                    .into_iter()
+                    .find_map(|input| self.aggregate_only_through(input, through))
+            }
+        }
+    }
+
+    /// The `differential_privacy` table an aggregate reads in `plan`'s own query
+    /// block, and the first node on the way that could repeat or combine rows.
     ///
-    /// When a filter references a column that isn't on the target table, the rewriter
-    /// consults the admin-designated `column_anchor` registered for
-    /// `(child_table, resolved_column)`. Two anchor shapes are handled:
-    ///   - **FK walk** (`AnchorShape::Relationship`): walks the `table_relationship`
-    ///     chain up to a parent that carries the column, replacing the `TableScan`
-    ///     subtree with `Project([target.*], Filter(rewritten, InnerJoin(target,
-    ///     parent_chain)))`. Parent scans are pre-planned in `apply_policies`
-    ///     (since `transform_up` is synchronous).
-    ///   - **Same-table alias** (`AnchorShape::Alias`): rewrites the filter
-    ///     expression's column reference (`tenant_id` → `org_id`) in place — no
-    ///     join, no parent scan.
+    /// Noise is calibrated to each row contributing once; joins, unions, window
+    /// functions and `UNNEST` break that, so the check rejects them.
+    fn private_scan_in<'a>(&'a self, plan: &LogicalPlan) -> Option<PrivateScan<'a>> {
+        match plan {
+            LogicalPlan::TableScan(scan) => self
+                .differential_privacy
+                .get_key_value(&scan_policy_key(scan, &self.default_schema))
+                .map(|(key, (def, policy_name))| PrivateScan {
+                    key,
+                    def,
+                    policy_name,
+                    through: None,
+                }),
+            LogicalPlan::Aggregate(_) => None,
+            LogicalPlan::Projection(_)
+            | LogicalPlan::Filter(_)
+            | LogicalPlan::SubqueryAlias(_)
+            | LogicalPlan::Sort(_)
+            | LogicalPlan::Limit(_) => plan
+                .inputs()
+                .into_iter()
+                .find_map(|input| self.private_scan_in(input)),
+            other => {
+                let through = match other {
+                    LogicalPlan::Join(_) => "a join",
+                    LogicalPlan::Union(_) => "a UNION",
+                    LogicalPlan::Window(_) => "a window function",
+                    LogicalPlan::Unnest(_) => "UNNEST",
+                    _ => "this query shape",
+                };
+                other
+                    .inputs()
+                    .into_iter()
+                    .find_map(|input| self.private_scan_in(input))
+                    .map(|scan| PrivateScan {
+                        through: scan.through.or(Some(through)),
+                        ..scan
+                    })
+            }
+        }
+    }
+
+    /// Add calibrated noise to every aggregate directly over a
+    /// `differential_privacy` table: the `Aggregate` is rebuilt with clamped
+    /// inputs and a `Projection` above it replaces each result with a noisy one
+    /// of the same name and type.
     ///
-    /// On resolution failure the filter becomes `lit(false)` (deny-wins) and a
-    /// structured `column_resolution_unresolved` warn log is emitted.
-    fn apply_row_filters(
+    /// Returns the rewritten plan and the epsilon it spends. Runs after
+    /// `check_aggregate_only`, so every such aggregate is supported.
+    fn apply_differential_privacy(
         &self,
         plan: LogicalPlan,
-        snapshot: &RelationshipSnapshot,
-        parent_scans: &HashMap<(String, String), LogicalPlan>,
-        deny_wins: &HashSet<(String, String)>,
-    ) -> Result<LogicalPlan, PolicyError> {
-        if self.row_filters.is_empty() {
+    ) -> Result<(LogicalPlan, Option<PrivacyCharge>), PolicyError> {
+        if self.differential_privacy.is_empty() {
+            return Ok((plan, None));
+        }
+
+        use datafusion::arrow::datatypes::DataType;
+        use datafusion::common::tree_node::Transformed;
+        use datafusion::functions::core::expr_fn::greatest;
+        use datafusion::functions::math::expr_fn::round;
+        use datafusion::functions_aggregate::expr_fn::{count, sum};
+        use datafusion::logical_expr::{Aggregate, Expr, cast};
+
+        let mut charge: Option<PrivacyCharge> = None;
+        let result = plan.transform_up_with_subqueries(|node| {
+            let LogicalPlan::Aggregate(ref agg) = node else {
+                return Ok(Transformed::no(node));
+            };
+            let Some(private) = self.private_scan_in(&agg.input) else {
+                return Ok(Transformed::no(node));
+            };
+            let def = private.def;
+            let schema = Arc::clone(node.schema());
+            let LogicalPlan::Aggregate(agg) = node else {
+                unreachable!("matched above");
+            };
+            let group_outputs = schema.fields().len() - agg.aggr_expr.len();
+            let noisy = |name: &str, sensitivity: f64, epsilon: f64| {
+                crate::privacy::add_noise(
+                    cast(
+                        Expr::Column(datafusion::common::Column::new_unqualified(name)),
+                        DataType::Float64,
+                    ),
+                    crate::privacy::noise_scale(def, sensitivity, epsilon),
+                    def.mechanism,
+                )
+            };
+
+            let mut aggr_expr = Vec::with_capacity(agg.aggr_expr.len());
+            let mut outputs: Vec<Expr> = (0..group_outputs)
+                .map(|i| {
+                    let (qualifier, field) = schema.qualified_field(i);
+                    Expr::Column(datafusion::common::Column::new(
+                        qualifier.cloned(),
+                        field.name(),
+                    ))
+                })
+                .collect();
+            for (i, expr) in agg.aggr_expr.iter().enumerate() {
+                let (qualifier, field) = schema.qualified_field(group_outputs + i);
+                let kind = PrivateAggregate::classify(expr, def)
+                    .map_err(datafusion::error::DataFusionError::Plan)?;
+                let name = format!("{PRIVATE_COLUMN_PREFIX}{i}");
+                let released = match kind {
+                    PrivateAggregate::Count(arg) => {
+                        aggr_expr.push(count(arg).alias(&name));
+                        greatest(vec![noisy(&name, 1.0, def.epsilon), lit(0.0)])
+                    }
+                    PrivateAggregate::Sum(arg, bounds) => {
+                        aggr_expr.push(sum(clamp(arg, bounds)).alias(&name));
+                        noisy(&name, sensitivity(bounds), def.epsilon)
+                    }
+                    PrivateAggregate::Avg(arg, bounds) => {
+                        let (sum_name, count_name) =
+                            (format!("{name}_sum"), format!("{name}_count"));
+                        aggr_expr.push(sum(clamp(arg.clone(), bounds)).alias(&sum_name));
+                        aggr_expr.push(count(arg).alias(&count_name));
+                        noisy(&sum_name, sensitivity(bounds), def.epsilon / 2.0)
+                            / greatest(vec![noisy(&count_name, 1.0, def.epsilon / 2.0), lit(1.0)])
+                    }
+                };
+                let released = if field.data_type().is_integer() {
+                    cast(round(vec![released]), field.data_type().clone())
+                } else {
+                    cast(released, field.data_type().clone())
+                };
+                outputs.push(released.alias_qualified(qualifier.cloned(), field.name()));
+                let c = charge.get_or_insert(PrivacyCharge {
+                    epsilon: 0.0,
+                    budget: def.budget,
+                });
+                c.epsilon += def.epsilon;
+                c.budget = c.budget.min(def.budget);
+            }
+
+            let noised = Aggregate::try_new(agg.input, agg.group_expr, aggr_expr)?;
+            let released = LogicalPlanBuilder::from(LogicalPlan::Aggregate(noised))
+                .project(outputs)?
+                .build()?;
+            Ok(Transformed::yes(released))
+        });
+
+        result
+            .map(|t| (t.data, charge))
+            .map_err(PolicyError::PlanTransformation)
+    }
+
+    /// Suppress small groups: every `Aggregate` directly over an `aggregate_only`
+    /// table gets a `count(*)` column, a `Filter` keeping groups of at least
+    /// `min_group_size` rows (the largest over the tables it reads), and a
+    /// `Projection` restoring the original output schema.
+    ///
+    /// Runs after `check_aggregate_only`, so every such scan is below an aggregate.
+    fn apply_aggregate_only(&self, plan: LogicalPlan) -> Result<LogicalPlan, PolicyError> {
+        if self.aggregate_only.is_empty() {
+            return Ok(plan);
+        }
+
+        use datafusion::common::tree_node::Transformed;
+        use datafusion::functions_aggregate::expr_fn::count;
+        use datafusion::logical_expr::{Aggregate, Expr};
+
+        let result = plan.transform_up_with_subqueries(|node| {
+            let LogicalPlan::Aggregate(ref agg) = node else {
+                return Ok(Transformed::no(node));
+            };
+            let Some(min_group_size) = self.min_group_size_in(&agg.input) else {
+                return Ok(Transformed::no(node));
+            };
+            let schema = Arc::clone(node.schema());
+            let LogicalPlan::Aggregate(agg) = node else {
+                unreachable!("matched above");
+            };
+            let mut aggr_expr = agg.aggr_expr;
+            aggr_expr.push(count(lit(1)).alias(GROUP_SIZE_COLUMN));
+            let grouped = Aggregate::try_new(agg.input, agg.group_expr, aggr_expr)?;
+            let restore: Vec<Expr> = schema
+                .iter()
+                .map(|(qualifier, field)| {
+                    Expr::Column(datafusion::common::Column::new(
+                        qualifier.cloned(),
+                        field.name(),
+                    ))
+                })
+                .collect();
+            let suppressed = LogicalPlanBuilder::from(LogicalPlan::Aggregate(grouped))
+                .filter(
+                    Expr::Column(datafusion::common::Column::new_unqualified(
+                        GROUP_SIZE_COLUMN,
+                    ))
+                    .gt_eq(lit(min_group_size)),
+                )?
+                .project(restore)?
+                .build()?;
+            Ok(Transformed::yes(suppressed))
+        });
+
+        result
+            .map(|t| t.data)
+            .map_err(PolicyError::PlanTransformation)
+    }
+
+    /// Largest `min_group_size` over the `aggregate_only` tables in `plan`'s query block.
+    fn min_group_size_in(&self, plan: &LogicalPlan) -> Option<i64> {
+        match plan {
+            LogicalPlan::TableScan(scan) => self
+                .aggregate_only
+                .get(&scan_policy_key(scan, &self.default_schema))
+                .map(|(k, _)| *k),
+            LogicalPlan::Aggregate(_) => None,
+            _ => plan
+                .inputs()
+                .into_iter()
+                .filter_map(|input| self.min_group_size_in(input))
+                .max(),
+        }
+    }
+
+    /// For `access_mode = "policy_required"`: inject `lit(false)` for tables with no permit.
+    fn apply_access_mode(&mut self, access_mode: &str, user_tables: &[(String, String)]) {
+        if access_mode == "policy_required" {
+            for table_key in user_tables {
+                if !self.tables_with_permit.contains(table_key) {
+                    self.row_filters.insert(table_key.clone(), lit(false));
+                }
+            }
+        }
+    }
+
+    /// Apply the user's policies to the subqueries of every row filter and mask
+    /// (see [`govern_subqueries`]). A row filter whose subqueries cannot be
+    /// governed hides every row; such a mask yields NULL.
+    async fn govern_policy_subqueries(
+        &mut self,
+        scope: &SubqueryScope<'_>,
+        scan_schemas: &[((String, String), SchemaRef)],
+    ) {
+        for (key, filter) in std::mem::take(&mut self.row_filters) {
+            let filter = match govern_subqueries(
+                filter,
+                scope,
+                &mut self.decision_results,
+                &mut self.shadow_outcomes,
+            )
+            .await
+            {
+                Ok(filter) => filter,
+                Err(e) => {
+                    tracing::error!(
+                        error = %e,
+                        table = %key.1,
+                        "Failed to govern row_filter subquery; denying all rows"
+                    );
+                    lit(false)
+                }
+            };
+            self.row_filters.insert(key, filter);
+        }
+        for (key, mask) in std::mem::take(&mut self.column_masks) {
+            let mask = match govern_subqueries(
+                mask,
+                scope,
+                &mut self.decision_results,
+                &mut self.shadow_outcomes,
+            )
+            .await
+            {
+                Ok(mask) => mask,
+                Err(e) => {
+                    let (df_schema, table, column) = &key;
+                    tracing::error!(
+                        error = %e,
+                        table = %table,
+                        column = %column,
+                        "Failed to govern column_mask subquery; masking to NULL"
+                    );
+                    let null = scan_schemas
+                        .iter()
+                        .find(|((s, t), _)| s == df_schema && t == table)
+                        .and_then(|(_, schema)| schema.field_with_name(column).ok())
+                        .and_then(|f| ScalarValue::try_from(f.data_type()).ok())
+                        .unwrap_or(ScalarValue::Null);
+                    lit(null)
+                }
+            };
+            self.column_masks.insert(key, mask);
+        }
+    }
+
+    /// Inject row filter `Filter` nodes below each matching `TableScan` via `transform_up`.
+    ///
+    /// Row filters are scoped to their source table, so they can safely reference columns
+    /// that are later stripped by the top-level projection (e.g. `tenant_id` filters).
+    ///
+    /// When a filter references a column that isn't on the target table, the rewriter
+    /// consults the admin-designated `column_anchor` registered for
+    /// `(child_table, resolved_column)`. Two anchor shapes are handled:
+    ///   - **FK walk** (`AnchorShape::Relationship`): walks the `table_relationship`
+    ///     chain up to a parent that carries the column, replacing the `TableScan`
+    ///     subtree with `Project([target.*], Filter(rewritten, InnerJoin(target,
+    ///     parent_chain)))`. Parent scans are pre-planned in `apply_policies`
+    ///     (since `transform_up` is synchronous).
+    ///   - **Same-table alias** (`AnchorShape::Alias`): rewrites the filter
+    ///     expression's column reference (`tenant_id` → `org_id`) in place — no
+    ///     join, no parent scan.
+    ///
+    /// On resolution failure the filter becomes `lit(false)` (deny-wins) and a
+    /// structured `column_resolution_unresolved` warn log is emitted.
+    fn apply_row_filters(
+        &self,
+        plan: LogicalPlan,
+        snapshot: &RelationshipSnapshot,
+        parent_scans: &HashMap<(String, String), LogicalPlan>,
+        deny_wins: &HashSet<(String, String)>,
+    ) -> Result<LogicalPlan, PolicyError> {
+        if self.row_filters.is_empty() {
             return Ok(plan);
         }
 
@@ -2001,6 +3552,13 @@ impl PolicyEffects {
     /// `SubqueryAlias` or CTE nodes can change the DFSchema qualifier, which would
     /// cause the top-level `apply_projection_qualified` to miss the match.
     ///
+    /// A mask may reference sibling columns of the same table (`CASE WHEN department
+    /// = {user.department} THEN salary END`). They are read raw from the scan, which is
+    /// widened to include them if it was projected, and the `Projection` outputs only
+    /// the scan's original columns, so they never reach the result. A mask that
+    /// references a column the user cannot read (denied, or hidden from the catalog)
+    /// yields NULL instead: deny beats mask.
+    ///
     /// **Architectural invariant:** All column-level policies (deny, mask, and any future
     /// types) MUST be enforced at the `TableScan` level via `transform_up` to prevent
     /// CTE/subquery alias bypass. Top-level projection is defense-in-depth only.
@@ -2036,9 +3594,14 @@ impl PolicyEffects {
             // Use alias_qualified to preserve the table qualifier on masked columns,
             // so downstream nodes (CTEs, subqueries) can still resolve them.
             // Skip mask if the column is also denied (deny beats mask).
-            let schema = node.schema();
+            let schema = Arc::clone(node.schema());
+            let source_schema = scan.source.schema();
             let key = (df_schema.clone(), table.clone());
-            let all_cols: Vec<&str> = schema.iter().map(|(_, f)| f.name().as_str()).collect();
+            let all_cols: Vec<&str> = source_schema
+                .fields()
+                .iter()
+                .map(|f| f.name().as_str())
+                .collect();
             let denied_cols: HashSet<String> = deny_patterns
                 .get(&key)
                 .map_or_else(HashSet::new, |pats| expand_column_patterns(pats, &all_cols));
@@ -2051,16 +3614,38 @@ impl PolicyEffects {
                 .collect();
 
             let mut exprs: Vec<Expr> = Vec::new();
+            // Source columns the applied masks read, for widening a projected scan.
+            let mut referenced: BTreeSet<usize> = BTreeSet::new();
             let mut any_masked = false;
             for (qualifier, field) in schema.iter() {
                 let col_name = field.name().as_str();
                 let is_denied = denied_cols.contains(col_name);
                 if !is_denied && let Some(mask_expr) = table_masks.get(col_name) {
-                    exprs.push(
-                        (*mask_expr)
-                            .clone()
-                            .alias_qualified(qualifier.cloned(), col_name),
-                    );
+                    let refs: Option<Vec<usize>> = mask_expr
+                        .column_refs()
+                        .into_iter()
+                        .map(|c| {
+                            source_schema
+                                .index_of(&c.name)
+                                .ok()
+                                .filter(|_| !denied_cols.contains(&c.name))
+                        })
+                        .collect();
+                    let masked = match refs {
+                        Some(refs) => {
+                            referenced.extend(refs);
+                            (*mask_expr).clone()
+                        }
+                        None => {
+                            tracing::warn!(
+                                table = %scan.table_name,
+                                column = %col_name,
+                                "Mask references a column the user cannot read; masking to NULL"
+                            );
+                            lit(ScalarValue::try_from(field.data_type())?)
+                        }
+                    };
+                    exprs.push(masked.alias_qualified(qualifier.cloned(), col_name));
                     any_masked = true;
                     continue;
                 }
@@ -2084,7 +3669,23 @@ impl PolicyEffects {
                 return Ok(Transformed::no(node));
             }
 
-            let plan_with_mask = LogicalPlanBuilder::from(node)
+            // Read sibling columns a projected scan would otherwise drop.
+            let input = match &scan.projection {
+                Some(projection) if referenced.iter().any(|i| !projection.contains(i)) => {
+                    let mut widened = projection.clone();
+                    widened.extend(referenced.iter().filter(|i| !projection.contains(i)));
+                    LogicalPlan::TableScan(TableScan::try_new(
+                        scan.table_name.clone(),
+                        Arc::clone(&scan.source),
+                        Some(widened),
+                        scan.filters.clone(),
+                        scan.fetch,
+                    )?)
+                }
+                _ => node,
+            };
+
+            let plan_with_mask = LogicalPlanBuilder::from(input)
                 .project(exprs)
                 .and_then(|b| b.build())
                 .map_err(|e| datafusion::error::DataFusionError::Plan(e.to_string()))?;
@@ -2191,15 +3792,134 @@ impl PolicyEffects {
             })
     }
 
-    /// True if any row filters, column patterns, or column masks were collected.
+    /// True if any row filters, column patterns, column masks or aggregate
+    /// thresholds were collected.
     fn has_effects(&self) -> bool {
         !self.row_filters.is_empty()
             || !self.column_allow_patterns.is_empty()
             || !self.column_deny_patterns.is_empty()
             || !self.column_masks.is_empty()
+            || !self.aggregate_only.is_empty()
+            || !self.differential_privacy.is_empty()
     }
 }
 
+/// Output column holding each group's row count while `aggregate_only` filters it.
+const GROUP_SIZE_COLUMN: &str = "__br_group_size";
+
+/// Aggregates that return every input value rather than a summary.
+const COLLECTING_AGGREGATES: &[&str] = &[
+    "array_agg",
+    "string_agg",
+    "first_value",
+    "last_value",
+    "nth_value",
+];
+
+/// Prefix for the rebuilt aggregate outputs that `apply_differential_privacy` noises.
+const PRIVATE_COLUMN_PREFIX: &str = "__br_dp_";
+
+/// Epsilon a query spends under `differential_privacy` policies, and the
+/// smallest budget among them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PrivacyCharge {
+    epsilon: f64,
+    budget: f64,
+}
+
+/// A `differential_privacy` table read by an aggregate (see
+/// [`PolicyEffects::private_scan_in`]).
+struct PrivateScan<'a> {
+    key: &'a (String, String),
+    def: &'a DifferentialPrivacyDef,
+    policy_name: &'a str,
+    /// Set when a row-combining node sits between the aggregate and the scan.
+    through: Option<&'static str>,
+}
+
+/// An aggregate the noise rewrite supports, with its input and, for `SUM` /
+/// `AVG`, the contribution bounds of the summed column.
+enum PrivateAggregate {
+    Count(datafusion::logical_expr::Expr),
+    Sum(datafusion::logical_expr::Expr, [f64; 2]),
+    Avg(datafusion::logical_expr::Expr, [f64; 2]),
+}
+
+impl PrivateAggregate {
+    /// Accept plain `count`, `sum` and `avg` (no `DISTINCT`, no `FILTER`);
+    /// `sum` / `avg` must take a column listed in the policy's `bounds`.
+    fn classify(
+        expr: &datafusion::logical_expr::Expr,
+        def: &DifferentialPrivacyDef,
+    ) -> Result<Self, String> {
+        use datafusion::logical_expr::Expr;
+
+        let mut inner = expr;
+        while let Expr::Alias(alias) = inner {
+            inner = &alias.expr;
+        }
+        let Expr::AggregateFunction(f) = inner else {
+            return Err(format!(
+                "only COUNT, SUM and AVG are allowed, not '{}'",
+                expr.schema_name()
+            ));
+        };
+        let func = f.func.name().to_string();
+        if f.params.distinct || f.params.filter.is_some() || f.params.args.len() != 1 {
+            return Err(format!(
+                "{func} with DISTINCT, FILTER or several arguments is not supported"
+            ));
+        }
+        let arg = f.params.args[0].clone();
+        let bounds = || {
+            let mut column = &arg;
+            while let Expr::Cast(datafusion::logical_expr::Cast { expr, .. })
+            | Expr::TryCast(datafusion::logical_expr::TryCast { expr, .. }) = column
+            {
+                column = expr;
+            }
+            match column {
+                Expr::Column(c) => def.bounds.get(&c.name).copied().ok_or_else(|| {
+                    format!("{func} needs contribution bounds for column '{}'", c.name)
+                }),
+                _ => Err(format!(
+                    "{func} must take a column with contribution bounds"
+                )),
+            }
+        };
+        match func.as_str() {
+            "count" => Ok(Self::Count(arg)),
+            "sum" => Ok(Self::Sum(arg.clone(), bounds()?)),
+            "avg" => Ok(Self::Avg(arg.clone(), bounds()?)),
+            _ => Err(format!("only COUNT, SUM and AVG are allowed, not {func}")),
+        }
+    }
+}
+
+/// `arg` cast to `Float64` and clamped to `[lower, upper]`; NULL stays NULL.
+fn clamp(
+    arg: datafusion::logical_expr::Expr,
+    [lower, upper]: [f64; 2],
+) -> datafusion::logical_expr::Expr {
+    let value =
+        datafusion::logical_expr::cast(arg, datafusion::arrow::datatypes::DataType::Float64);
+    datafusion::logical_expr::when(value.clone().lt(lit(lower)), lit(lower))
+        .when(value.clone().gt(lit(upper)), lit(upper))
+        .otherwise(value)
+        .expect("CASE with WHEN branches")
+}
+
+/// Largest change one row can make to a clamped sum.
+fn sensitivity([lower, upper]: [f64; 2]) -> f64 {
+    lower.abs().max(upper.abs())
+}
+
+fn aggregate_required_reason((schema, table): &(String, String)) -> String {
+    format!(
+        "{schema}.{table} can only be queried through aggregates (GROUP BY or aggregate functions)"
+    )
+}
+
 /// Apply all policy effects to a logical plan.
 ///
 /// Returns `(modified_plan, had_effects)` where `had_effects` is true when any
@@ -2212,7 +3932,9 @@ impl PolicyEffects {
 /// **Enforcement order:**
 /// 1. `apply_column_mask_at_scan` — mask `Projection` injected above each `TableScan` (scan level)
 /// 2. `apply_row_filters` — `Filter` nodes injected below each `TableScan` (scan level)
-/// 3. `apply_projection_qualified` — top-level `Projection` for allow/deny (defense-in-depth for deny)
+/// 3. `apply_differential_privacy` — noise `Projection` above each `Aggregate` over a `differential_privacy` table
+/// 4. `apply_aggregate_only` — `count(*) >= k` `Filter` above each `Aggregate` over an `aggregate_only` table
+/// 5. `apply_projection_qualified` — top-level `Projection` for allow/deny (defense-in-depth for deny)
 ///
 /// Masks run before filters so that `transform_up` places `Filter` between `TableScan` and
 /// the mask `Projection`. This ensures row filters evaluate against raw (unmasked) data.
@@ -2335,1488 +4057,5565 @@ async fn precompute_parent_scans(
     (scans, deny_wins)
 }
 
-async fn apply_policies(
+/// What `check_query_guards` found for a query that may run: decision results,
+/// shadow outcomes, and the NOTICE messages for the client.
+type GuardOutcome = (
+    HashMap<Uuid, crate::decision::DecisionResult>,
+    Vec<ShadowOutcome>,
+    Vec<String>,
+);
+
+/// Check `query_guard` policies against the query as the user wrote it. An
+/// enforced policy whose rule breaks refuses the query; a shadow one records
+/// `would_block_query` and warns the client with a NOTICE.
+async fn check_query_guards(
     session: &SessionDataClone,
-    session_context: &SessionContext,
-    logical_plan: LogicalPlan,
-    user_vars: &UserVars,
+    statement: &Statement,
+    logical_plan: &LogicalPlan,
+    default_schema: &str,
     decision_eval: Option<&DecisionEvalContext<'_>>,
-) -> Result<
-    (
-        LogicalPlan,
-        bool,
-        HashMap<Uuid, crate::decision::DecisionResult>,
-    ),
-    PolicyError,
-> {
-    // Read the session's default schema once — same value used in
-    // `PolicyEffects::collect` and passed to the scan walker. This is
-    // the single schema a bare reference resolves against.
-    let default_schema = session_context
-        .state()
-        .config_options()
-        .catalog
-        .default_schema
-        .clone();
+) -> Result<GuardOutcome, PolicyError> {
+    let mut decision_results = HashMap::new();
+    let mut shadow_outcomes = Vec::new();
+    let mut notices = Vec::new();
 
-    let user_tables = collect_user_tables(&logical_plan, &default_schema);
+    let guards = session
+        .permit_policies
+        .iter()
+        .map(|p| (p, ActionStatus::Enforce))
+        .chain(
+            session
+                .shadow_policies
+                .iter()
+                .map(|p| (p, ActionStatus::Shadow)),
+        )
+        .filter(|(p, _)| p.policy_type == PolicyType::QueryGuard);
+    let mut shape = None;
+    for (policy, action_status) in guards {
+        let def = match policy
+            .definition
+            .clone()
+            .map(serde_json::from_value::<QueryGuardDef>)
+        {
+            Some(Ok(def)) => def,
+            _ => {
+                tracing::error!(policy = %policy.name, "Invalid query_guard definition");
+                continue;
+            }
+        };
+        let is_target = |(df_schema, table): &(String, String)| {
+            policy.targets.iter().any(|entry| {
+                entry.matches_table(
+                    df_schema,
+                    table,
+                    &session.df_to_upstream,
+                    &session.catalog_tags,
+                )
+            })
+        };
+        let shape = shape.get_or_insert_with(|| {
+            crate::query_guard::QueryShape::new(statement, logical_plan, default_schema)
+        });
+        if !shape.tables().any(is_target) {
+            continue;
+        }
+        if !evaluate_decision_fn(policy, decision_eval, &mut decision_results).await {
+            continue;
+        }
+        let Some(violation) = shape.check(&def, is_target) else {
+            continue;
+        };
+        if action_status == ActionStatus::Enforce {
+            return Err(PolicyError::QueryBlocked {
+                policy_name: policy.name.clone(),
+                violation,
+            });
+        }
+        notices.push(format!(
+            "Policy '{}' (shadow) would block this query: {violation}",
+            policy.name
+        ));
+        let tables: BTreeSet<String> = shape
+            .tables()
+            .filter(|key| is_target(key))
+            .map(|(df_schema, table)| format!("{df_schema}.{table}"))
+            .collect();
+        shadow_outcomes.push(ShadowOutcome {
+            policy_id: policy.id,
+            name: policy.name.clone(),
+            version: policy.version,
+            policy_type: policy.policy_type,
+            outcome: "would_block_query",
+            tables: tables.into_iter().collect(),
+            columns: Vec::new(),
+            expression: Some(violation.to_string()),
+        });
+    }
 
-    let mut effects = PolicyEffects::collect(
-        session,
-        &user_tables,
-        user_vars,
-        session_context,
-        decision_eval,
-    )
-    .await;
+    Ok((decision_results, shadow_outcomes, notices))
+}
 
-    effects.check_deny()?;
-    effects.apply_access_mode(&session.access_mode, &user_tables);
+// ---------- policy subqueries ----------
+
+/// What governing the subqueries of a filter or mask expression needs: the
+/// user's session and policies, and the nesting depth of those subqueries.
+struct SubqueryScope<'a> {
+    session: &'a SessionDataClone,
+    session_context: &'a SessionContext,
+    user_vars: &'a UserVars,
+    decision_eval: Option<&'a DecisionEvalContext<'a>>,
+    /// 1 for the subqueries of the query's own filters and masks, 2 for those
+    /// of the policies on the tables they read, and so on.
+    depth: usize,
+}
 
-    let had_effects = effects.has_effects();
+/// A policy subquery after `govern_subqueries`: its plan with the user's
+/// policies applied and, when uncorrelated and small enough, its rows.
+struct GovernedSubquery {
+    subquery: datafusion::logical_expr::Subquery,
+    values: Option<Arc<Vec<ScalarValue>>>,
+}
 
-    // Pre-plan parent scans needed for column resolution. We do this here
-    // (not inside `apply_row_filters`, which must stay synchronous to run
-    // within `transform_up`) so the rewriter has ready-to-use `LogicalPlan`s.
-    let snapshot = Arc::clone(&session.relationship_snapshot);
-    let (parent_scans, deny_wins) = precompute_parent_scans(
-        session_context,
-        &effects.row_filters,
-        &snapshot,
-        &session.parent_scans_cache,
+fn is_subquery(expr: &datafusion::logical_expr::Expr) -> bool {
+    use datafusion::logical_expr::Expr;
+    matches!(
+        expr,
+        Expr::Exists(_) | Expr::InSubquery(_) | Expr::ScalarSubquery(_)
     )
-    .await;
-
-    // Masks must be applied before row filters so that row filters evaluate
-    // against raw (unmasked) data. With transform_up, mask runs first and
-    // inserts Projection above TableScan; then row filter inserts Filter
-    // directly above the same TableScan (below the mask Projection).
-    // Result: TableScan → Filter(raw) → Projection(mask) — correct.
-    let plan = effects.apply_column_mask_at_scan(logical_plan)?;
-    let plan = effects.apply_row_filters(plan, &snapshot, &parent_scans, &deny_wins)?;
-    // Clear masks after scan-level application to prevent double-masking in
-    // apply_projection_qualified (the scan-level mask is the primary enforcement;
-    // the top-level projection is defense-in-depth for allow/deny only).
-    effects.column_masks.clear();
-    let plan = effects.apply_projection_qualified(plan)?;
+}
 
-    Ok((plan, had_effects, effects.decision_results))
+/// Whether any node of `plan` has a subquery in its expressions.
+fn has_subqueries(plan: &LogicalPlan) -> bool {
+    use datafusion::common::tree_node::TreeNode;
+    plan.exists(|node| {
+        Ok(node
+            .expressions()
+            .iter()
+            .any(|e| e.exists(|e| Ok(is_subquery(e))).unwrap_or(true)))
+    })
+    .unwrap_or(true)
 }
 
-#[async_trait]
-impl QueryHook for PolicyHook {
-    async fn handle_query(
-        &self,
-        statement: &Statement,
-        session_context: &SessionContext,
-        client: &(dyn ClientInfo + Sync),
-    ) -> Option<PgWireResult<Response>> {
-        if !matches!(statement, Statement::Query(_)) {
-            // Write statements (INSERT, UPDATE, DELETE, DROP, SET, …) will be rejected
-            // by ReadOnlyHook. Audit them here before passing through, so the denied
-            // attempt is on the record.
-            if !is_allowed_statement(statement) {
-                self.audit_write_rejected(statement, client).await;
+/// Apply the user's policies to the subqueries of a row filter or mask.
+///
+/// A subquery reads its tables on the user's behalf, so it gets the same
+/// treatment as the user's own query: `apply_policies_at` runs over its plan,
+/// and a table the user may not read denies, filters or masks the subquery
+/// exactly as it would a direct `SELECT`. An uncorrelated subquery is then run
+/// once and replaced by its rows — `IN` becomes an `IN` list, `EXISTS` and a
+/// scalar subquery a literal — which `lookup_values` caches for the session.
+/// Correlated subqueries keep their governed plan and run with the query.
+///
+/// Errors when a subquery cannot be governed — its table is denied, it needs
+/// a privacy budget, it nests another subquery of its own, or the policies
+/// nest deeper than `MAX_POLICY_SUBQUERY_DEPTH`. The caller fails closed.
+fn govern_subqueries<'a>(
+    expr: datafusion::logical_expr::Expr,
+    scope: &'a SubqueryScope<'a>,
+    decision_results: &'a mut HashMap<Uuid, crate::decision::DecisionResult>,
+    shadow_outcomes: &'a mut Vec<ShadowOutcome>,
+) -> futures::future::BoxFuture<'a, Result<datafusion::logical_expr::Expr, PolicyError>> {
+    use datafusion::common::tree_node::{Transformed, TreeNode, TreeNodeRecursion};
+    use datafusion::error::DataFusionError;
+    use datafusion::logical_expr::expr::{Exists, InList, InSubquery};
+    use datafusion::logical_expr::{Expr, Subquery};
+
+    Box::pin(async move {
+        let mut subqueries: Vec<Subquery> = Vec::new();
+        expr.apply(|e| {
+            if let Expr::Exists(Exists { subquery, .. })
+            | Expr::InSubquery(InSubquery { subquery, .. })
+            | Expr::ScalarSubquery(subquery) = e
+            {
+                subqueries.push(subquery.clone());
             }
-            return None;
+            Ok(TreeNodeRecursion::Continue)
+        })
+        .map_err(PolicyError::PlanTransformation)?;
+        if subqueries.is_empty() {
+            return Ok(expr);
         }
-        if is_system_only_statement(statement) {
-            return None;
+        if scope.depth > MAX_POLICY_SUBQUERY_DEPTH {
+            return Err(PolicyError::PlanTransformation(DataFusionError::Plan(
+                format!("policy subqueries nest deeper than {MAX_POLICY_SUBQUERY_DEPTH} levels"),
+            )));
         }
 
-        let metadata = client.metadata();
-        let user_id_str = metadata.get("user_id").cloned()?;
-        let user_id = match Uuid::parse_str(&user_id_str) {
-            Ok(id) => id,
-            Err(_) => {
-                return Some(Err(PgWireError::UserError(Box::new(ErrorInfo::new(
-                    "ERROR".to_owned(),
-                    "28000".to_owned(),
-                    "Invalid user_id in connection metadata".to_owned(),
-                )))));
+        let mut governed: HashMap<Subquery, GovernedSubquery> = HashMap::new();
+        for subquery in subqueries {
+            if governed.contains_key(&subquery) {
+                continue;
             }
-        };
-        let username = metadata.get("user").cloned().unwrap_or_default();
-        let datasource = metadata.get("datasource").cloned().unwrap_or_default();
-        let client_info = metadata.get("application_name").cloned();
-
-        // Load session data
-        let session = match self.get_session(user_id, &datasource).await {
-            Ok(s) => s,
-            Err(e) => {
-                tracing::error!(error = %e, "PolicyHook: failed to load session");
-                return Some(Err(PgWireError::ApiError(Box::new(std::io::Error::other(
-                    e.to_string(),
-                )))));
+            // Only the outermost subquery of a policy expression is governed
+            // here; one nested in its plan would be read without policies.
+            if has_subqueries(&subquery.subquery) {
+                return Err(PolicyError::PlanTransformation(DataFusionError::Plan(
+                    "a policy subquery may not contain another subquery".to_string(),
+                )));
             }
-        };
-
-        let user_vars = UserVars {
-            username: username.clone(),
-            user_id: user_id.to_string(),
-            attributes: session.user_attributes.clone(),
-            attribute_defs: session.attribute_defs.clone(),
-        };
-
-        let query_start = std::time::Instant::now();
-        let original_query = statement.to_string();
-
-        // --- labeled block: returns (result, status, error_message, rewritten_query, decision_results) ---
-        // This single block captures all outcome paths so the audit write is in one place.
-        let (result, audit_status, audit_error, audit_rewritten, decision_results): (
-            PgWireResult<Response>,
-            &'static str,
-            Option<String>,
-            Option<String>,
-            HashMap<Uuid, crate::decision::DecisionResult>,
-        ) = 'query: {
-            // Build logical plan
-            let df_stmt =
-                datafusion::sql::parser::Statement::Statement(Box::new(statement.clone()));
-            let logical_plan = match session_context.state().statement_to_plan(df_stmt).await {
-                Ok(p) => p,
-                Err(e) => {
-                    tracing::error!(error = %e, "PolicyHook: failed to build plan");
-                    let msg = e.to_string();
-                    break 'query (
-                        Err(PgWireError::ApiError(Box::new(e))),
-                        "error",
-                        Some(msg),
-                        None,
-                        HashMap::new(),
-                    );
-                }
-            };
-
-            // Build decision evaluation context with session + query metadata.
-            // Use resolve_user_attribute_defaults to include defaults for missing attrs.
-            let resolved_attrs =
-                resolve_user_attribute_defaults(&session.user_attributes, &session.attribute_defs);
-            let json_attrs: HashMap<String, serde_json::Value> = resolved_attrs
-                .iter()
-                .map(|(k, ta)| {
-                    let v = match ta.value_type.as_str() {
-                        "null" => serde_json::Value::Null,
-                        "list" => serde_json::from_str::<Vec<String>>(&ta.value)
-                            .map(|arr| serde_json::json!(arr))
-                            .unwrap_or_else(|_| serde_json::json!(&ta.value)),
-                        "integer" => ta
-                            .value
-                            .parse::<i64>()
-                            .map(|n| serde_json::json!(n))
-                            .unwrap_or_else(|_| serde_json::json!(&ta.value)),
-                        "boolean" => ta
-                            .value
-                            .parse::<bool>()
-                            .map(|b| serde_json::json!(b))
-                            .unwrap_or_else(|_| serde_json::json!(&ta.value)),
-                        _ => serde_json::json!(&ta.value),
-                    };
-                    (k.clone(), v)
-                })
-                .collect();
-            let session_info = crate::decision::context::SessionInfo {
-                user_id,
-                username: username.clone(),
-                roles: session.roles.clone(),
-                datasource_name: session.datasource_name.clone(),
-                access_mode: session.access_mode.clone(),
-                attributes: json_attrs,
-            };
-            // Read the session's default schema for the metadata extraction,
-            // so bare references appear as `public.orders` (not `orders`) in
-            // `ctx.query.tables`. Same value `apply_policies` reads below.
-            let default_schema = session_context
-                .state()
-                .config_options()
-                .catalog
-                .default_schema
-                .clone();
-            let query_meta =
-                extract_query_metadata(&logical_plan, &default_schema, &session.datasource_name);
-            let decision_ctx =
-                crate::decision::context::build_query_context(&session_info, &query_meta);
-            let decision_eval = DecisionEvalContext {
-                wasm_runtime: &self.wasm_runtime,
-                decision_ctx,
-            };
-
-            let (final_plan, had_effects, decision_results) = match apply_policies(
-                &session,
-                session_context,
-                logical_plan,
-                &user_vars,
-                Some(&decision_eval),
+            let AppliedPolicies {
+                plan,
+                decision_results: decisions,
+                shadow_outcomes: shadows,
+                privacy_charge,
+                ..
+            } = apply_policies_at(
+                scope.session,
+                scope.session_context,
+                subquery.subquery.as_ref().clone(),
+                scope.user_vars,
+                scope.decision_eval,
+                scope.depth,
+                None,
             )
-            .await
-            {
-                Ok(result) => result,
-                Err(e) => {
-                    tracing::error!(error = %e, "PolicyHook: policy error");
-                    let (status, msg) = match &e {
-                        PolicyError::DeniedByPolicy { policy_name } => {
-                            ("denied", format!("Access denied by policy '{policy_name}'"))
-                        }
-                        PolicyError::AllColumnsDenied { columns } => (
-                            "denied",
-                            format!(
-                                "Column{} {} restricted by policy",
-                                if columns.len() == 1 { "" } else { "s" },
-                                columns.join(", ")
-                            ),
-                        ),
-                        PolicyError::PlanTransformation(inner) => ("error", inner.to_string()),
-                    };
-                    break 'query (
-                        Err(e.into_pgwire_error()),
-                        status,
-                        Some(msg),
-                        None,
-                        HashMap::new(),
-                    );
-                }
-            };
-
-            // Unparse the rewritten plan back to SQL when policy effects were applied.
-            let rewritten_query = if had_effects {
-                let unparser = Unparser::new(&BetweenRowsPostgresDialect);
-                match unparser.plan_to_sql(&final_plan) {
-                    Ok(sql) => Some(sql.to_string()),
-                    Err(_) => Some(format!("/* plan-to-sql failed */ {original_query}")),
-                }
+            .await?;
+            decision_results.extend(decisions);
+            shadow_outcomes.extend(shadows);
+            if privacy_charge.is_some() {
+                return Err(PolicyError::PlanTransformation(DataFusionError::Plan(
+                    "a policy subquery may not read a differential_privacy table".to_string(),
+                )));
+            }
+            let values = if subquery.outer_ref_columns.is_empty() && !scope.session.plan_only {
+                lookup_values(scope, &plan)
+                    .await
+                    .map_err(PolicyError::PlanTransformation)?
             } else {
                 None
             };
-
-            // Execute the plan.
-            let df = match session_context.execute_logical_plan(final_plan).await {
-                Ok(df) => df,
-                Err(e) => {
-                    tracing::error!(error = %e, "PolicyHook: execution failed");
-                    let msg = e.to_string();
-                    break 'query (
-                        Err(PgWireError::ApiError(Box::new(e))),
-                        "error",
-                        Some(msg),
-                        rewritten_query,
-                        decision_results,
-                    );
-                }
+            let result = GovernedSubquery {
+                subquery: Subquery {
+                    subquery: Arc::new(plan),
+                    ..subquery.clone()
+                },
+                values,
             };
+            governed.insert(subquery, result);
+        }
 
-            // Encode the DataFrame into a pgwire response (this is where rows are pulled).
-            let response = match encode_dataframe(df, &Format::UnifiedText, None).await {
-                Ok(qr) => Response::Query(qr),
-                Err(e) => {
-                    tracing::error!(error = %e, "PolicyHook: encoding error");
-                    let msg = e.to_string();
-                    break 'query (
-                        Err(e),
-                        "error",
-                        Some(msg),
-                        rewritten_query,
-                        decision_results,
-                    );
+        expr.transform_down(|e| {
+            let found = |subquery: &Subquery| {
+                governed.get(subquery).ok_or_else(|| {
+                    DataFusionError::Internal("policy subquery was not governed".to_string())
+                })
+            };
+            let replaced = match e {
+                Expr::Exists(Exists { subquery, negated }) => {
+                    let g = found(&subquery)?;
+                    match &g.values {
+                        Some(values) => lit(values.is_empty() == negated),
+                        None => Expr::Exists(Exists {
+                            subquery: g.subquery.clone(),
+                            negated,
+                        }),
+                    }
+                }
+                Expr::InSubquery(InSubquery {
+                    expr,
+                    subquery,
+                    negated,
+                }) => {
+                    let g = found(&subquery)?;
+                    match &g.values {
+                        // `x IN (<no rows>)` is false and `x NOT IN (<no rows>)`
+                        // true, even for a NULL `x`.
+                        Some(values) if values.is_empty() => lit(negated),
+                        Some(values) => Expr::InList(InList::new(
+                            expr,
+                            values.iter().cloned().map(lit).collect(),
+                            negated,
+                        )),
+                        None => Expr::InSubquery(InSubquery {
+                            expr,
+                            subquery: g.subquery.clone(),
+                            negated,
+                        }),
+                    }
                 }
+                Expr::ScalarSubquery(subquery) => {
+                    let g = found(&subquery)?;
+                    match g.values.as_deref().map(Vec::as_slice) {
+                        Some([]) => {
+                            let field = g.subquery.subquery.schema().field(0).clone();
+                            lit(ScalarValue::try_from(field.data_type())?)
+                        }
+                        Some([value]) => lit(value.clone()),
+                        // More than one row is an error, raised when the
+                        // subquery runs with the query.
+                        _ => Expr::ScalarSubquery(g.subquery.clone()),
+                    }
+                }
+                other => return Ok(Transformed::no(other)),
             };
+            Ok(Transformed::yes(replaced))
+        })
+        .map(|t| t.data)
+        .map_err(PolicyError::PlanTransformation)
+    })
+}
 
-            (
-                Ok(response),
-                "success",
-                None,
-                rewritten_query,
-                decision_results,
-            )
-        };
-
-        // Duration measured after the labeled block — covers planning + execution + encoding.
-        let elapsed_ms = query_start.elapsed().as_millis() as i64;
+/// First column of the rows of a governed, uncorrelated policy subquery, or
+/// `None` when it has more than `MAX_LOOKUP_ROWS`. Results are cached in the
+/// session's `lookup_cache` for `LOOKUP_CACHE_TTL_SECS`, keyed by the governed
+/// plan, so a lookup table is read once per user rather than once per query.
+async fn lookup_values(
+    scope: &SubqueryScope<'_>,
+    plan: &LogicalPlan,
+) -> datafusion::error::Result<Option<Arc<Vec<ScalarValue>>>> {
+    let key = plan.display_indent_schema().to_string();
+    {
+        let cache = scope.session.lookup_cache.read().await;
+        if let Some((read_at, values)) = cache.get(&key)
+            && read_at.elapsed().as_secs() < LOOKUP_CACHE_TTL_SECS
+        {
+            return Ok(values.clone());
+        }
+    }
 
-        // Async audit log — runs on all paths (success, error, denied).
-        // Include both permit and deny policies, plus decision function results.
-        let policies_applied: Vec<serde_json::Value> = session
-            .permit_policies
-            .iter()
-            .chain(session.deny_policies.iter())
-            .map(|p| {
-                let mut entry = serde_json::json!({
-                    "policy_id": p.id.to_string(),
-                    "version": p.version,
-                    "name": p.name,
-                });
-                if let Some(dr) = decision_results.get(&p.id) {
-                    entry["decision"] = serde_json::json!({
-                        "result": {
-                            "fire": dr.fire,
-                            "fuel_consumed": dr.fuel_consumed,
-                            "time_us": dr.time_us,
-                        },
-                        "logs": dr.logs,
-                        "error": dr.error,
-                    });
-                }
-                entry
-            })
-            .collect();
+    let batches = scope
+        .session_context
+        .execute_logical_plan(plan.clone())
+        .await?
+        .limit(0, Some(MAX_LOOKUP_ROWS + 1))?
+        .collect()
+        .await?;
+    let mut values = Vec::new();
+    for batch in &batches {
+        for row in 0..batch.num_rows() {
+            values.push(ScalarValue::try_from_array(batch.column(0), row)?);
+        }
+    }
+    let values = (values.len() <= MAX_LOOKUP_ROWS).then(|| Arc::new(values));
 
-        let db = self.db.clone();
-        let audit_user_id = user_id;
-        let audit_username = username;
-        let audit_ds_id = session.datasource_id;
-        let audit_ds_name = session.datasource_name.clone();
-        let audit_orig_q = original_query;
-        let audit_policies = serde_json::to_string(&policies_applied).unwrap_or_default();
-        let audit_info = client_info;
-        let audit_status_owned = audit_status.to_string();
+    scope
+        .session
+        .lookup_cache
+        .write()
+        .await
+        .insert(key, (std::time::Instant::now(), values.clone()));
+    Ok(values)
+}
 
-        tokio::spawn(async move {
-            let now = Utc::now().naive_utc();
-            let entry = query_audit_log::ActiveModel {
-                id: sea_orm::Set(Uuid::now_v7()),
-                user_id: sea_orm::Set(audit_user_id),
-                username: sea_orm::Set(audit_username),
-                data_source_id: sea_orm::Set(audit_ds_id),
-                datasource_name: sea_orm::Set(audit_ds_name),
-                original_query: sea_orm::Set(audit_orig_q),
-                rewritten_query: sea_orm::Set(audit_rewritten),
-                policies_applied: sea_orm::Set(audit_policies),
-                execution_time_ms: sea_orm::Set(Some(elapsed_ms)),
-                client_ip: sea_orm::Set(None),
-                client_info: sea_orm::Set(audit_info),
-                created_at: sea_orm::Set(now),
-                status: sea_orm::Set(audit_status_owned),
-                error_message: sea_orm::Set(audit_error),
-            };
-            if let Err(e) = sea_orm::ActiveModelTrait::insert(entry, &db).await {
-                tracing::error!(error = %e, "Failed to write audit log entry");
-            }
-        });
+// ---------- policy explanation ----------
+
+/// How much of the policy logic `EXPLAIN (POLICY)` shows to users who are not
+/// admins (`BR_EXPLAIN_POLICY`). Admins always get [`ExplainLevel::Full`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExplainLevel {
+    /// `EXPLAIN (POLICY)` is refused with SQLSTATE 42501.
+    Off,
+    /// Which policies apply to which tables and columns, without expressions,
+    /// decision function output, the names of removed columns, or shadow policies.
+    #[default]
+    Summary,
+    /// Everything, including filter and mask SQL and the rewritten query.
+    Full,
+}
 
-        Some(result)
+impl std::str::FromStr for ExplainLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "summary" => Ok(Self::Summary),
+            "full" => Ok(Self::Full),
+            other => Err(format!(
+                "unknown explain level '{other}' (expected off, summary or full)"
+            )),
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use datafusion::arrow::array::{Array, Int32Array, StringArray};
-    use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
-    use datafusion::arrow::record_batch::RecordBatch;
-    use datafusion::catalog::default_table_source::DefaultTableSource;
-    use datafusion::datasource::MemTable;
-    use datafusion::datasource::empty::EmptyTable;
-    use datafusion::logical_expr::LogicalPlanBuilder;
-    use datafusion::prelude::SessionContext;
-    use datafusion::sql::sqlparser::{dialect::PostgreSqlDialect, parser::Parser as SqlParser};
-    use std::sync::Arc;
+/// One row of `EXPLAIN (POLICY)`: what one policy does to one table or column,
+/// or (with no table) to the query as a whole.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PolicyExplanation {
+    /// `schema.table`, as the query names it.
+    pub(crate) table: Option<String>,
+    pub(crate) column: Option<String>,
+    pub(crate) policy: Option<String>,
+    pub(crate) policy_type: Option<PolicyType>,
+    /// `denied`, `removed`, `allowed`, `filtered`, `masked`, `no_rows`,
+    /// `aggregate_only`, `noised`, `limited`, `guarded`, `overridden`, `skipped`,
+    /// `decision_fired`, `decision_not_fired`, a shadow outcome (`would_*`),
+    /// `rejected` or `rewritten_query`.
+    pub(crate) effect: &'static str,
+    pub(crate) detail: Option<String>,
+}
 
-    // ---------- shared test helpers ----------
+impl PolicyExplanation {
+    fn query(effect: &'static str, detail: String) -> Self {
+        Self {
+            table: None,
+            column: None,
+            policy: None,
+            policy_type: None,
+            effect,
+            detail: Some(detail),
+        }
+    }
+}
 
-    fn make_session(
-        permit_policies: Vec<ResolvedPolicy>,
-        deny_policies: Vec<ResolvedPolicy>,
-        access_mode: &str,
-        df_to_upstream: HashMap<String, String>,
-    ) -> SessionDataClone {
-        SessionDataClone {
-            permit_policies,
-            deny_policies,
-            access_mode: access_mode.to_string(),
-            df_to_upstream,
-            datasource_id: Uuid::nil(),
-            datasource_name: "test_ds".to_string(),
-            roles: vec![],
-            user_attributes: HashMap::new(),
-            attribute_defs: HashMap::new(),
-            relationship_snapshot: Arc::new(RelationshipSnapshot::default()),
-            parent_scans_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+/// The statement `EXPLAIN (POLICY) <statement>` explains, if `statement` is one.
+fn policy_explain_target(statement: &Statement) -> Option<&Statement> {
+    match statement {
+        Statement::Explain {
+            statement,
+            options: Some(options),
+            ..
+        } if options
+            .iter()
+            .any(|o| o.name.value.eq_ignore_ascii_case("policy")) =>
+        {
+            Some(statement)
         }
+        _ => None,
     }
+}
 
-    fn make_row_filter_policy(
-        name: &str,
-        priority: i32,
-        schema: &str,
-        table: &str,
-        filter: &str,
-    ) -> ResolvedPolicy {
-        ResolvedPolicy {
-            id: Uuid::now_v7(),
-            name: name.to_string(),
-            policy_type: PolicyType::RowFilter,
-            version: 1,
-            priority,
-            targets: vec![TargetEntry {
-                schemas: vec![schema.to_string()],
-                tables: vec![table.to_string()],
-                columns: None,
-            }],
-            definition: Some(serde_json::json!({"filter_expression": filter})),
-            decision_function: None,
-        }
+/// `result_limit` caps, as shown in shadow outcomes and explanations.
+fn describe_caps(def: &ResultLimitDef) -> String {
+    let mut caps = Vec::new();
+    if let Some(rows) = def.max_rows {
+        caps.push(format!("max {rows} rows"));
+    }
+    if let Some(bytes) = def.max_bytes {
+        caps.push(format!("max {bytes} bytes"));
     }
+    if let (Some(rows), Some(secs)) = (def.window_rows, def.window_secs) {
+        caps.push(format!("{rows} rows per {secs}s"));
+    }
+    caps.join(", ")
+}
 
-    fn make_column_mask_policy(
-        name: &str,
-        priority: i32,
-        schema: &str,
-        table: &str,
-        column: &str,
-        mask: &str,
-    ) -> ResolvedPolicy {
-        ResolvedPolicy {
-            id: Uuid::now_v7(),
-            name: name.to_string(),
-            policy_type: PolicyType::ColumnMask,
-            version: 1,
-            priority,
-            targets: vec![TargetEntry {
-                schemas: vec![schema.to_string()],
-                tables: vec![table.to_string()],
-                columns: Some(vec![column.to_string()]),
-            }],
-            definition: Some(serde_json::json!({"mask_expression": mask})),
-            decision_function: None,
+impl PolicyEffects {
+    /// Explain, policy by policy, what the collected effects do to each table the
+    /// query reads. Filters and masks are shown as SQL before their subqueries are
+    /// governed.
+    async fn explain(
+        &self,
+        session: &SessionDataClone,
+        scan_schemas: &[((String, String), SchemaRef)],
+        user_vars: &UserVars,
+        session_context: &SessionContext,
+    ) -> Vec<PolicyExplanation> {
+        let sql = |expr: &datafusion::logical_expr::Expr| {
+            Unparser::new(&BetweenRowsPostgresDialect)
+                .expr_to_sql(expr)
+                .ok()
+                .map(|e| e.to_string())
+        };
+        let fired = |p: &ResolvedPolicy| self.decision_results.get(&p.id).is_none_or(|r| r.fire);
+        // Why a policy's effect on `target` was set aside, if it was.
+        let overridden = |p: &ResolvedPolicy, target: &str| {
+            self.policy_conflicts
+                .iter()
+                .find(|c| {
+                    c.policy_type == p.policy_type
+                        && c.target == target
+                        && c.overridden.contains(&p.name)
+                })
+                .map(|c| format!("{} applies ({})", c.applied.join(", "), c.strategy))
+        };
+        let mut rows = Vec::new();
+
+        for ((df_schema, table), table_schema) in scan_schemas {
+            let key = (df_schema.clone(), table.clone());
+            let all_cols: Vec<&str> = table_schema
+                .fields()
+                .iter()
+                .map(|f| f.name().as_str())
+                .collect();
+            let row = |policy: Option<&ResolvedPolicy>,
+                       column: Option<&str>,
+                       effect: &'static str,
+                       detail: Option<String>| PolicyExplanation {
+                table: Some(format!("{df_schema}.{table}")),
+                column: column.map(str::to_string),
+                policy: policy.map(|p| p.name.clone()),
+                policy_type: policy.map(|p| p.policy_type),
+                effect,
+                detail,
+            };
+            let expand = |patterns: &[String]| -> BTreeSet<String> {
+                expand_column_patterns(patterns, &all_cols)
+                    .into_iter()
+                    .collect()
+            };
+            let denied = self
+                .column_deny_patterns
+                .get(&key)
+                .map(|p| expand(p))
+                .unwrap_or_default();
+            let allowed = self.column_allow_patterns.get(&key).map(|p| expand(p));
+            let visible =
+                |c: &String| !denied.contains(c) && allowed.as_ref().is_none_or(|a| a.contains(c));
+            let target = format!("{df_schema}.{table}");
+
+            for policy in session.deny_policies.iter().chain(&session.permit_policies) {
+                // Write policies never shape what a read returns.
+                if policy.policy_type.is_write() {
+                    continue;
+                }
+                let entries: Vec<&TargetEntry> = policy
+                    .targets
+                    .iter()
+                    .filter(|e| {
+                        e.matches_table(
+                            df_schema,
+                            table,
+                            &session.df_to_upstream,
+                            &session.catalog_tags,
+                        )
+                    })
+                    .collect();
+                if entries.is_empty() {
+                    continue;
+                }
+                if !fired(policy) {
+                    rows.push(row(
+                        Some(policy),
+                        None,
+                        "skipped",
+                        Some("decision function did not fire".to_string()),
+                    ));
+                    continue;
+                }
+                let columns = || {
+                    let patterns: Vec<String> = entries
+                        .iter()
+                        .flat_map(|e| {
+                            e.column_patterns(
+                                df_schema,
+                                table,
+                                &all_cols,
+                                &session.df_to_upstream,
+                                &session.catalog_tags,
+                            )
+                        })
+                        .collect();
+                    expand(&patterns)
+                };
+                let definition = |field: &str| {
+                    policy
+                        .definition
+                        .as_ref()
+                        .and_then(|d| d.get(field))
+                        .and_then(|v| v.as_str())
+                        .filter(|e| !e.is_empty())
+                };
+                if let Some(detail) = overridden(policy, &target) {
+                    rows.push(row(Some(policy), None, "overridden", Some(detail)));
+                    continue;
+                }
+                match policy.policy_type {
+                    PolicyType::TableDeny => rows.push(row(Some(policy), None, "denied", None)),
+                    PolicyType::ColumnDeny => {
+                        for column in columns() {
+                            rows.push(row(Some(policy), Some(&column), "removed", None));
+                        }
+                    }
+                    PolicyType::ColumnAllow => {
+                        let detail = if entries.iter().any(|e| e.columns.is_none()) {
+                            "all columns".to_string()
+                        } else {
+                            columns().into_iter().collect::<Vec<_>>().join(", ")
+                        };
+                        rows.push(row(Some(policy), None, "allowed", Some(detail)));
+                    }
+                    PolicyType::RowFilter => {
+                        let Some(template) = definition("filter_expression") else {
+                            continue;
+                        };
+                        let detail = match parse_filter_expr(
+                            session_context,
+                            template,
+                            user_vars,
+                            table,
+                            table_schema,
+                        )
+                        .await
+                        {
+                            Ok(filter) => sql(&filter).unwrap_or_else(|| template.to_string()),
+                            Err(e) => format!("cannot be planned, hides every row: {e}"),
+                        };
+                        rows.push(row(Some(policy), None, "filtered", Some(detail)));
+                    }
+                    PolicyType::ColumnMask => {
+                        for column in columns() {
+                            if !visible(&column) {
+                                continue;
+                            }
+                            if let Some(detail) = overridden(policy, &format!("{target}.{column}"))
+                            {
+                                rows.push(row(
+                                    Some(policy),
+                                    Some(&column),
+                                    "overridden",
+                                    Some(detail),
+                                ));
+                                continue;
+                            }
+                            let detail = self
+                                .column_masks
+                                .get(&(df_schema.clone(), table.clone(), column.clone()))
+                                .and_then(sql);
+                            rows.push(row(Some(policy), Some(&column), "masked", detail));
+                        }
+                    }
+                    PolicyType::AggregateOnly => {
+                        if let Some((k, name)) = self.aggregate_only.get(&key)
+                            && name == &policy.name
+                        {
+                            rows.push(row(
+                                Some(policy),
+                                None,
+                                "aggregate_only",
+                                Some(format!("aggregates over groups of at least {k} rows")),
+                            ));
+                        }
+                    }
+                    PolicyType::DifferentialPrivacy => {
+                        if let Some((def, name)) = self.differential_privacy.get(&key)
+                            && name == &policy.name
+                        {
+                            rows.push(row(
+                                Some(policy),
+                                None,
+                                "noised",
+                                Some(format!(
+                                    "{} noise, epsilon {} per aggregate",
+                                    def.mechanism.as_str(),
+                                    def.epsilon
+                                )),
+                            ));
+                        }
+                    }
+                    PolicyType::ResultLimit => {
+                        let detail = policy
+                            .definition
+                            .clone()
+                            .and_then(|d| serde_json::from_value::<ResultLimitDef>(d).ok())
+                            .map(|def| describe_caps(&def));
+                        rows.push(row(Some(policy), None, "limited", detail));
+                    }
+                    PolicyType::QueryGuard => rows.push(row(Some(policy), None, "guarded", None)),
+                    PolicyType::WriteAllow | PolicyType::WriteDeny => {}
+                    PolicyType::JoinOnly => {
+                        for column in columns() {
+                            let restricted_here = self.join_only.get(&(
+                                df_schema.clone(),
+                                table.clone(),
+                                column.clone(),
+                            )) == Some(&policy.name);
+                            if visible(&column) && restricted_here {
+                                rows.push(row(
+                                    Some(policy),
+                                    Some(&column),
+                                    "join_only",
+                                    Some(
+                                        "equality joins with another table's column only"
+                                            .to_string(),
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Columns outside every column_allow list.
+            if let Some(allowed) = &allowed {
+                for column in all_cols.iter().map(|c| c.to_string()) {
+                    if !allowed.contains(&column) && !denied.contains(&column) {
+                        rows.push(row(
+                            None,
+                            Some(&column),
+                            "removed",
+                            Some("not granted by any column_allow policy".to_string()),
+                        ));
+                    }
+                }
+            }
+            if session.access_mode == "policy_required" && !self.tables_with_permit.contains(&key) {
+                rows.push(row(
+                    None,
+                    None,
+                    "no_rows",
+                    Some("no column_allow policy grants this table".to_string()),
+                ));
+            }
         }
+
+        rows.extend(self.shadow_outcomes.iter().map(shadow_explanation));
+        rows
     }
+}
 
-    fn make_column_allow_policy(
-        name: &str,
-        priority: i32,
-        schema: &str,
-        table: &str,
-        columns: &[&str],
-    ) -> ResolvedPolicy {
-        ResolvedPolicy {
-            id: Uuid::now_v7(),
-            name: name.to_string(),
-            policy_type: PolicyType::ColumnAllow,
-            version: 1,
-            priority,
-            targets: vec![TargetEntry {
-                schemas: vec![schema.to_string()],
-                tables: vec![table.to_string()],
-                columns: Some(columns.iter().map(|c| c.to_string()).collect()),
-            }],
-            definition: None,
-            decision_function: None,
-        }
+/// What a shadow policy would have done, as an explanation row.
+fn shadow_explanation(outcome: &ShadowOutcome) -> PolicyExplanation {
+    PolicyExplanation {
+        table: Some(outcome.tables.join(", ")),
+        column: None,
+        policy: Some(outcome.name.clone()),
+        policy_type: Some(outcome.policy_type),
+        effect: outcome.outcome,
+        detail: outcome.expression.clone(),
     }
+}
 
-    fn make_column_deny_policy(
-        name: &str,
-        priority: i32,
-        schema: &str,
-        table: &str,
-        columns: &[&str],
-    ) -> ResolvedPolicy {
-        ResolvedPolicy {
-            id: Uuid::now_v7(),
-            name: name.to_string(),
-            policy_type: PolicyType::ColumnDeny,
-            version: 1,
-            priority,
-            targets: vec![TargetEntry {
-                schemas: vec![schema.to_string()],
-                tables: vec![table.to_string()],
-                columns: Some(columns.iter().map(|c| c.to_string()).collect()),
-            }],
-            definition: None,
-            decision_function: None,
+/// One row per decision function that ran: whether its policy fired, and at
+/// `Full` its error and console output.
+fn decision_explanations(
+    session: &SessionDataClone,
+    decision_results: &HashMap<Uuid, crate::decision::DecisionResult>,
+) -> Vec<PolicyExplanation> {
+    session
+        .permit_policies
+        .iter()
+        .chain(&session.deny_policies)
+        .chain(&session.shadow_policies)
+        .filter_map(|p| {
+            let result = decision_results.get(&p.id)?;
+            let mut detail = result.error.iter().cloned().collect::<Vec<_>>();
+            detail.extend(result.logs.iter().cloned());
+            Some(PolicyExplanation {
+                table: None,
+                column: None,
+                policy: Some(p.name.clone()),
+                policy_type: Some(p.policy_type),
+                effect: if result.fire {
+                    "decision_fired"
+                } else {
+                    "decision_not_fired"
+                },
+                detail: (!detail.is_empty()).then(|| detail.join("; ")),
+            })
+        })
+        .collect()
+}
+
+/// Reduce an explanation to what `level` may show. At `Summary`, removed columns
+/// are counted rather than named, and expressions, decision function output,
+/// shadow policies and the rewritten query are left out.
+fn redact_explanation(rows: Vec<PolicyExplanation>, level: ExplainLevel) -> Vec<PolicyExplanation> {
+    if level == ExplainLevel::Full {
+        return rows;
+    }
+    let mut redacted: Vec<PolicyExplanation> = Vec::new();
+    let mut removed: Vec<(PolicyExplanation, usize)> = Vec::new();
+    for mut row in rows {
+        match row.effect {
+            "rewritten_query" => {}
+            e if e.starts_with("would_") => {}
+            "removed" => {
+                row.column = None;
+                row.detail = None;
+                match removed.iter_mut().find(|(r, _)| r == &row) {
+                    Some((_, n)) => *n += 1,
+                    None => removed.push((row, 1)),
+                }
+            }
+            "aggregate_only" | "join_only" | "noised" | "limited" | "no_rows" | "overridden"
+            | "skipped" | "rejected" => redacted.push(row),
+            _ => {
+                row.detail = None;
+                redacted.push(row);
+            }
         }
     }
+    for (mut row, n) in removed {
+        row.detail = Some(format!("{n} column{}", if n == 1 { "" } else { "s" }));
+        redacted.push(row);
+    }
+    redacted
+}
+
+/// The explanation as the `EXPLAIN (POLICY)` result set.
+fn explanation_batch(
+    rows: &[PolicyExplanation],
+) -> datafusion::error::Result<datafusion::arrow::record_batch::RecordBatch> {
+    use datafusion::arrow::array::{ArrayRef, StringArray};
+
+    let schema = Arc::new(Schema::new(
+        [
+            "table_name",
+            "column_name",
+            "policy",
+            "policy_type",
+            "effect",
+            "detail",
+        ]
+        .map(|name| Field::new(name, DataType::Utf8, true))
+        .to_vec(),
+    ));
+    let column = |value: fn(&PolicyExplanation) -> Option<&str>| -> ArrayRef {
+        Arc::new(rows.iter().map(value).collect::<StringArray>())
+    };
+    Ok(datafusion::arrow::record_batch::RecordBatch::try_new(
+        schema,
+        vec![
+            column(|r| r.table.as_deref()),
+            column(|r| r.column.as_deref()),
+            column(|r| r.policy.as_deref()),
+            column(|r| r.policy_type.map(PolicyType::as_str)),
+            column(|r| Some(r.effect)),
+            column(|r| r.detail.as_deref()),
+        ],
+    )?)
+}
+
+/// Result of [`apply_policies`].
+#[derive(Debug)]
+struct AppliedPolicies {
+    /// The rewritten plan.
+    plan: LogicalPlan,
+    /// Whether any policy had an effect on the plan.
+    had_effects: bool,
+    /// Decision function results per policy, for the audit log.
+    decision_results: HashMap<Uuid, crate::decision::DecisionResult>,
+    /// What shadow policies would have done, for the audit log.
+    shadow_outcomes: Vec<ShadowOutcome>,
+    /// The privacy budget to charge, if the plan reads a `differential_privacy` table.
+    privacy_charge: Option<PrivacyCharge>,
+    /// The combined `result_limit` caps.
+    result_limit: Option<crate::result_limit::ResultLimit>,
+    /// The permit conflicts the combining strategies resolved.
+    policy_conflicts: Vec<PolicyConflict>,
+}
+
+async fn apply_policies(
+    session: &SessionDataClone,
+    session_context: &SessionContext,
+    logical_plan: LogicalPlan,
+    user_vars: &UserVars,
+    decision_eval: Option<&DecisionEvalContext<'_>>,
+) -> Result<AppliedPolicies, PolicyError> {
+    apply_policies_at(
+        session,
+        session_context,
+        logical_plan,
+        user_vars,
+        decision_eval,
+        0,
+        None,
+    )
+    .await
+}
+
+/// [`apply_policies`] for a plan `depth` policy subqueries down: 0 for the
+/// user's query, 1 for a subquery in one of its row filters or masks, and so on.
+/// With `explain`, the effects on the plan's own tables are also described
+/// there, before any deny is enforced.
+async fn apply_policies_at(
+    session: &SessionDataClone,
+    session_context: &SessionContext,
+    logical_plan: LogicalPlan,
+    user_vars: &UserVars,
+    decision_eval: Option<&DecisionEvalContext<'_>>,
+    depth: usize,
+    explain: Option<&mut Vec<PolicyExplanation>>,
+) -> Result<AppliedPolicies, PolicyError> {
+    // Read the session's default schema once — same value used in
+    // `PolicyEffects::collect` and passed to the scan walker. This is
+    // the single schema a bare reference resolves against.
+    let default_schema = session_context
+        .state()
+        .config_options()
+        .catalog
+        .default_schema
+        .clone();
+
+    let user_tables = collect_user_tables(&logical_plan, &default_schema);
+    let scan_schemas = collect_scan_schemas(&logical_plan, &default_schema);
+
+    let mut effects = PolicyEffects::collect(
+        session,
+        &scan_schemas,
+        user_vars,
+        session_context,
+        decision_eval,
+    )
+    .await;
+    if !session.shadow_policies.is_empty() {
+        effects
+            .collect_shadow(session, &scan_schemas, user_vars, decision_eval)
+            .await;
+    }
+    if let Some(explain) = explain {
+        explain.extend(
+            effects
+                .explain(session, &scan_schemas, user_vars, session_context)
+                .await,
+        );
+        explain.extend(decision_explanations(session, &effects.decision_results));
+    }
+
+    effects.check_deny()?;
+    effects.check_aggregate_only(&logical_plan)?;
+    effects.check_join_only(&logical_plan)?;
+    effects.apply_access_mode(&session.access_mode, &user_tables);
+
+    // Subqueries in filters and masks read other tables on the user's behalf;
+    // the user's policies on those tables apply to them too.
+    let scope = SubqueryScope {
+        session,
+        session_context,
+        user_vars,
+        decision_eval,
+        depth: depth + 1,
+    };
+    effects
+        .govern_policy_subqueries(&scope, &scan_schemas)
+        .await;
+
+    let had_effects = effects.has_effects();
+
+    // Pre-plan parent scans needed for column resolution. We do this here
+    // (not inside `apply_row_filters`, which must stay synchronous to run
+    // within `transform_up`) so the rewriter has ready-to-use `LogicalPlan`s.
+    let snapshot = Arc::clone(&session.relationship_snapshot);
+    let (parent_scans, deny_wins) = precompute_parent_scans(
+        session_context,
+        &effects.row_filters,
+        &snapshot,
+        &session.parent_scans_cache,
+    )
+    .await;
+
+    // Masks must be applied before row filters so that row filters evaluate
+    // against raw (unmasked) data. With transform_up, mask runs first and
+    // inserts Projection above TableScan; then row filter inserts Filter
+    // directly above the same TableScan (below the mask Projection).
+    // Result: TableScan → Filter(raw) → Projection(mask) — correct.
+    let plan = effects.apply_column_mask_at_scan(logical_plan)?;
+    let plan = effects.apply_row_filters(plan, &snapshot, &parent_scans, &deny_wins)?;
+    // Small groups are suppressed over filtered, masked rows: the count is of
+    // what the user could otherwise see.
+    // Noise goes on before small-group suppression so the threshold's count(*)
+    // is never itself noised or charged.
+    let (plan, privacy_charge) = effects.apply_differential_privacy(plan)?;
+    let plan = effects.apply_aggregate_only(plan)?;
+    // Clear masks after scan-level application to prevent double-masking in
+    // apply_projection_qualified (the scan-level mask is the primary enforcement;
+    // the top-level projection is defense-in-depth for allow/deny only).
+    effects.column_masks.clear();
+    let plan = effects.apply_projection_qualified(plan)?;
+
+    Ok(AppliedPolicies {
+        plan,
+        had_effects,
+        decision_results: effects.decision_results,
+        shadow_outcomes: effects.shadow_outcomes,
+        privacy_charge,
+        result_limit: effects.result_limit,
+        policy_conflicts: effects.policy_conflicts,
+    })
+}
+
+/// What governing a query did to it: the rewritten SQL, per policy the decision
+/// function results, and the permit conflicts resolved — what its audit row records.
+#[derive(Debug, Default)]
+pub struct GovernReport {
+    pub rewritten_query: Option<String>,
+    pub policies_applied: Vec<serde_json::Value>,
+    pub policy_conflicts: Vec<PolicyConflict>,
+}
+
+/// `explain_rows` output: the explanation, the decision function results, and
+/// the upstream the query would be served by.
+type ExplainedRows = (
+    Vec<PolicyExplanation>,
+    HashMap<Uuid, crate::decision::DecisionResult>,
+    Option<String>,
+);
+
+/// Outcome of `run_governed`'s labeled block: (result, status, error_message,
+/// rewritten_query, decision_results, shadow_outcomes, epsilon_spent) —
+/// everything the audit write needs.
+type QueryOutcome<T> = (
+    PgWireResult<T>,
+    &'static str,
+    Option<String>,
+    Option<String>,
+    HashMap<Uuid, crate::decision::DecisionResult>,
+    Vec<ShadowOutcome>,
+    Option<f64>,
+);
+
+/// Build the decision function context for a query: the user's session
+/// (attributes with defaults applied) and the query's metadata.
+fn decision_context(
+    session: &SessionDataClone,
+    user_id: Uuid,
+    username: &str,
+    logical_plan: &LogicalPlan,
+    default_schema: &str,
+) -> serde_json::Value {
+    // Use resolve_user_attribute_defaults to include defaults for missing attrs.
+    let resolved_attrs =
+        resolve_user_attribute_defaults(&session.user_attributes, &session.attribute_defs);
+    let json_attrs: HashMap<String, serde_json::Value> = resolved_attrs
+        .iter()
+        .map(|(k, ta)| {
+            let v = match ta.value_type.as_str() {
+                "null" => serde_json::Value::Null,
+                "list" => serde_json::from_str::<Vec<String>>(&ta.value)
+                    .map(|arr| serde_json::json!(arr))
+                    .unwrap_or_else(|_| serde_json::json!(&ta.value)),
+                "integer" => ta
+                    .value
+                    .parse::<i64>()
+                    .map(|n| serde_json::json!(n))
+                    .unwrap_or_else(|_| serde_json::json!(&ta.value)),
+                "boolean" => ta
+                    .value
+                    .parse::<bool>()
+                    .map(|b| serde_json::json!(b))
+                    .unwrap_or_else(|_| serde_json::json!(&ta.value)),
+                _ => serde_json::json!(&ta.value),
+            };
+            (k.clone(), v)
+        })
+        .collect();
+    let session_info = crate::decision::context::SessionInfo {
+        user_id,
+        username: username.to_string(),
+        roles: session.roles.clone(),
+        datasource_name: session.datasource_name.clone(),
+        access_mode: session.access_mode.clone(),
+        attributes: json_attrs,
+    };
+    let query_meta = extract_query_metadata(logical_plan, default_schema, &session.datasource_name);
+    crate::decision::context::build_query_context(&session_info, &query_meta)
+}
+
+/// `query_audit_log.policies_applied`: the permit, deny and shadow policies of
+/// the session, with their decision function results.
+fn policies_applied(
+    session: &SessionDataClone,
+    decision_results: &HashMap<Uuid, crate::decision::DecisionResult>,
+) -> Vec<serde_json::Value> {
+    session
+        .permit_policies
+        .iter()
+        .chain(session.deny_policies.iter())
+        .map(|p| (p, ActionStatus::Enforce))
+        .chain(
+            session
+                .shadow_policies
+                .iter()
+                .map(|p| (p, ActionStatus::Shadow)),
+        )
+        .map(|(p, action_status)| {
+            let mut entry = serde_json::json!({
+                "policy_id": p.id.to_string(),
+                "version": p.version,
+                "name": p.name,
+            });
+            if action_status == ActionStatus::Shadow {
+                entry["action_status"] = serde_json::json!(action_status);
+            }
+            if let Some(t) = &p.template {
+                entry["template"] = serde_json::json!({
+                    "id": t.id.to_string(),
+                    "name": t.name,
+                    "version": t.version,
+                });
+            }
+            if let Some(dr) = decision_results.get(&p.id) {
+                entry["decision"] = serde_json::json!({
+                    "result": {
+                        "fire": dr.fire,
+                        "fuel_consumed": dr.fuel_consumed,
+                        "time_us": dr.time_us,
+                    },
+                    "logs": dr.logs,
+                    "error": dr.error,
+                });
+            }
+            entry
+        })
+        .collect()
+}
+
+impl PolicyHook {
+    /// Plan, govern, execute and audit a single read query on behalf of `caller`.
+    ///
+    /// This is the shared enforcement path for every front-end: the statement is
+    /// planned against the caller's `SessionContext`, checked by
+    /// `check_query_guards`, rewritten by `apply_policies`, executed, and handed
+    /// to `consume` as a record batch stream metered by
+    /// [`crate::result_limit::meter`], along with any NOTICE messages for the
+    /// client. Exactly one
+    /// `query_audit_log` row is written whatever the outcome — for a result
+    /// stream, once the front-end drops it, so the rows and bytes it delivered
+    /// are known.
+    ///
+    /// Callers must only pass `Statement::Query` that is not system-only — the
+    /// `QueryHook` impl below shows the expected routing.
+    pub async fn run_governed<T, F, Fut>(
+        &self,
+        statement: &Statement,
+        session_context: &SessionContext,
+        caller: &QueryCaller,
+        consume: F,
+    ) -> PgWireResult<T>
+    where
+        F: FnOnce(SendableRecordBatchStream, Vec<String>) -> Fut,
+        Fut: Future<Output = PgWireResult<T>>,
+    {
+        let session = match self.get_session(caller).await {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!(error = %e, "PolicyHook: failed to load session");
+                return Err(PgWireError::ApiError(Box::new(std::io::Error::other(
+                    e.to_string(),
+                ))));
+            }
+        };
+        self.govern(statement, session_context, caller, session, None, consume)
+            .await
+            .0
+    }
+
+    /// Run the read query `sql` as `subject` for the admin `admin_id`: an
+    /// impersonation preview (`POST /impersonate`).
+    ///
+    /// The subject's policies are loaded fresh, bypassing the session cache, and
+    /// applied exactly as in [`Self::run_governed`], except that nothing is
+    /// charged to the subject's privacy budget or row quotas. The audit row is
+    /// written under the subject's identity with `impersonated_by` set to the admin.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn preview<T, F, Fut>(
+        &self,
+        subject: &Subject,
+        admin_id: Uuid,
+        datasource: &str,
+        sql: &str,
+        session_context: &SessionContext,
+        client_info: Option<String>,
+        consume: F,
+    ) -> (PgWireResult<T>, GovernReport)
+    where
+        F: FnOnce(SendableRecordBatchStream, Vec<String>) -> Fut,
+        Fut: Future<Output = PgWireResult<T>>,
+    {
+        let statement = match parse_single_statement(sql) {
+            Ok(s) if matches!(s, Statement::Query(_)) && !is_system_only_statement(&s) => s,
+            Ok(_) => {
+                let e = PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_owned(),
+                    "42601".to_owned(),
+                    "Only a query that reads user tables can be previewed".to_owned(),
+                )));
+                return (Err(e), GovernReport::default());
+            }
+            Err(e) => return (Err(e), GovernReport::default()),
+        };
+        let session = match self.load_session(subject, datasource, None).await {
+            Ok(s) => clone_session_data(&s),
+            Err(e) => {
+                tracing::error!(error = %e, "PolicyHook: failed to load session");
+                let e = PgWireError::ApiError(Box::new(std::io::Error::other(e.to_string())));
+                return (Err(e), GovernReport::default());
+            }
+        };
+        let caller = QueryCaller {
+            user_id: subject.user_id,
+            username: subject.username.clone(),
+            datasource: datasource.to_string(),
+            client_info,
+            purpose: subject.purpose.clone(),
+            justification: None,
+        };
+        self.govern(
+            &statement,
+            session_context,
+            &caller,
+            session,
+            Some(admin_id),
+            consume,
+        )
+        .await
+    }
+
+    /// [`Self::run_governed`] under an already loaded `session`. With
+    /// `impersonated_by`, quotas and privacy budgets are left untouched.
+    async fn govern<T, F, Fut>(
+        &self,
+        statement: &Statement,
+        session_context: &SessionContext,
+        caller: &QueryCaller,
+        session: SessionDataRef,
+        impersonated_by: Option<Uuid>,
+        consume: F,
+    ) -> (PgWireResult<T>, GovernReport)
+    where
+        F: FnOnce(SendableRecordBatchStream, Vec<String>) -> Fut,
+        Fut: Future<Output = PgWireResult<T>>,
+    {
+        let user_id = caller.user_id;
+        let username = caller.username.clone();
+        let client_info = caller.client_info.clone();
+
+        let user_vars = UserVars {
+            username: username.clone(),
+            user_id: user_id.to_string(),
+            attributes: session.user_attributes.clone(),
+            attribute_defs: session.attribute_defs.clone(),
+        };
+
+        let query_start = std::time::Instant::now();
+        let original_query = statement.to_string();
+        // Upstream endpoint the planner routed this query's tables to (audit only).
+        let served_by: Option<String>;
+        // Totals reported by the metered result stream, and the row quotas they count against.
+        let mut result_totals = None;
+        let mut result_windows = Vec::new();
+        // Permit conflicts resolved while governing, recorded with the audit row.
+        let mut policy_conflicts = Vec::new();
+
+        // --- labeled block: returns (result, status, error_message, rewritten_query, decision_results, shadow_outcomes) ---
+        // This single block captures all outcome paths so the audit write is in one place.
+        let outcome: QueryOutcome<T> = 'query: {
+            // Build logical plan
+            let df_stmt =
+                datafusion::sql::parser::Statement::Statement(Box::new(statement.clone()));
+            let (planned, routed_to) =
+                crate::engine::track_served_by(session_context.state().statement_to_plan(df_stmt))
+                    .await;
+            served_by = routed_to;
+            let logical_plan = match planned {
+                Ok(p) => p,
+                Err(e) => {
+                    tracing::error!(error = %e, "PolicyHook: failed to build plan");
+                    let msg = e.to_string();
+                    break 'query (
+                        Err(PgWireError::ApiError(Box::new(e))),
+                        "error",
+                        Some(msg),
+                        None,
+                        HashMap::new(),
+                        Vec::new(),
+                        None,
+                    );
+                }
+            };
+
+            // Read the session's default schema for the metadata extraction,
+            // so bare references appear as `public.orders` (not `orders`) in
+            // `ctx.query.tables`. Same value `apply_policies` reads below.
+            let default_schema = session_context
+                .state()
+                .config_options()
+                .catalog
+                .default_schema
+                .clone();
+            let decision_ctx =
+                decision_context(&session, user_id, &username, &logical_plan, &default_schema);
+            let decision_eval = DecisionEvalContext {
+                wasm_runtime: &self.wasm_runtime,
+                decision_ctx,
+            };
+
+            let (guard_decisions, guard_outcomes, notices) = match check_query_guards(
+                &session,
+                statement,
+                &logical_plan,
+                &default_schema,
+                Some(&decision_eval),
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::error!(error = %e, "PolicyHook: policy error");
+                    let msg = e.to_string();
+                    break 'query (
+                        Err(e.into_pgwire_error()),
+                        "denied",
+                        Some(msg),
+                        None,
+                        HashMap::new(),
+                        Vec::new(),
+                        None,
+                    );
+                }
+            };
+
+            let AppliedPolicies {
+                plan: final_plan,
+                mut had_effects,
+                mut decision_results,
+                mut shadow_outcomes,
+                privacy_charge,
+                mut result_limit,
+                policy_conflicts: conflicts,
+            } = match apply_policies(
+                &session,
+                session_context,
+                logical_plan,
+                &user_vars,
+                Some(&decision_eval),
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::error!(error = %e, "PolicyHook: policy error");
+                    let (status, msg) = match &e {
+                        PolicyError::DeniedByPolicy { policy_name } => {
+                            ("denied", format!("Access denied by policy '{policy_name}'"))
+                        }
+                        PolicyError::AllColumnsDenied { columns } => (
+                            "denied",
+                            format!(
+                                "Column{} {} restricted by policy",
+                                if columns.len() == 1 { "" } else { "s" },
+                                columns.join(", ")
+                            ),
+                        ),
+                        PolicyError::AggregateRequired { .. }
+                        | PolicyError::JoinKeyExposed { .. }
+                        | PolicyError::QueryBlocked { .. }
+                        | PolicyError::PrivacyBudgetExhausted { .. }
+                        | PolicyError::RowQuotaExhausted { .. }
+                        | PolicyError::WriteDenied { .. }
+                        | PolicyError::WriteRefused { .. }
+                        | PolicyError::WriteUnsupported(_) => ("denied", e.to_string()),
+                        PolicyError::PlanTransformation(inner) => ("error", inner.to_string()),
+                    };
+                    break 'query (
+                        Err(e.into_pgwire_error()),
+                        status,
+                        Some(msg),
+                        None,
+                        HashMap::new(),
+                        Vec::new(),
+                        None,
+                    );
+                }
+            };
+            decision_results.extend(guard_decisions);
+            shadow_outcomes.extend(guard_outcomes);
+            policy_conflicts = conflicts;
+
+            // Lower the row cap to what is left of each window quota; a used-up
+            // quota refuses the query before any epsilon is charged.
+            if let Some(limit) = result_limit.as_mut()
+                && impersonated_by.is_none()
+            {
+                for window in limit.windows.clone() {
+                    let used =
+                        match crate::result_limit::rows_used(&self.db, &window, user_id).await {
+                            Ok(used) => used,
+                            Err(e) => {
+                                tracing::error!(error = %e, "PolicyHook: row quota lookup failed");
+                                let msg = format!("Row quota unavailable: {e}");
+                                break 'query (
+                                    Err(PgWireError::ApiError(Box::new(std::io::Error::other(
+                                        msg.clone(),
+                                    )))),
+                                    "error",
+                                    Some(msg),
+                                    None,
+                                    decision_results,
+                                    shadow_outcomes,
+                                    None,
+                                );
+                            }
+                        };
+                    if used >= window.rows {
+                        let e = PolicyError::RowQuotaExhausted {
+                            policy_name: window.policy_name.clone(),
+                            rows: window.rows,
+                            window_secs: window.secs,
+                        };
+                        let msg = e.to_string();
+                        break 'query (
+                            Err(e.into_pgwire_error()),
+                            "denied",
+                            Some(msg),
+                            None,
+                            decision_results,
+                            shadow_outcomes,
+                            None,
+                        );
+                    }
+                    limit.cap_rows(window.rows - used, &window.policy_name);
+                }
+                result_windows = limit.windows.clone();
+            }
+            let final_plan = match result_limit.as_ref() {
+                Some(limit) if limit.max_rows.is_some() => {
+                    had_effects = true;
+                    match crate::result_limit::limit_plan(final_plan, limit) {
+                        Ok(plan) => plan,
+                        Err(e) => {
+                            let msg = e.to_string();
+                            break 'query (
+                                Err(PgWireError::ApiError(Box::new(e))),
+                                "error",
+                                Some(msg),
+                                None,
+                                decision_results,
+                                shadow_outcomes,
+                                None,
+                            );
+                        }
+                    }
+                }
+                _ => final_plan,
+            };
+
+            // Charge noised aggregates to the user's privacy budget before anything runs.
+            let epsilon_spent = match privacy_charge.filter(|_| impersonated_by.is_none()) {
+                None => None,
+                Some(charge) => match crate::privacy::charge(
+                    &self.db,
+                    user_id,
+                    session.datasource_id,
+                    charge.epsilon,
+                    charge.budget,
+                )
+                .await
+                {
+                    Ok(crate::privacy::Charge::Charged) => Some(charge.epsilon),
+                    Ok(crate::privacy::Charge::Exhausted { spent }) => {
+                        let e = PolicyError::PrivacyBudgetExhausted {
+                            cost: charge.epsilon,
+                            spent,
+                            budget: charge.budget,
+                        };
+                        let msg = e.to_string();
+                        break 'query (
+                            Err(e.into_pgwire_error()),
+                            "denied",
+                            Some(msg),
+                            None,
+                            decision_results,
+                            shadow_outcomes,
+                            None,
+                        );
+                    }
+                    Err(e) => {
+                        tracing::error!(error = %e, "PolicyHook: privacy budget charge failed");
+                        let msg = format!("Privacy budget unavailable: {e}");
+                        break 'query (
+                            Err(PgWireError::ApiError(Box::new(std::io::Error::other(
+                                msg.clone(),
+                            )))),
+                            "error",
+                            Some(msg),
+                            None,
+                            decision_results,
+                            shadow_outcomes,
+                            None,
+                        );
+                    }
+                },
+            };
+
+            // Unparse the rewritten plan back to SQL when policy effects were applied.
+            let rewritten_query = if had_effects {
+                let unparser = Unparser::new(&BetweenRowsPostgresDialect);
+                match unparser.plan_to_sql(&final_plan) {
+                    Ok(sql) => Some(sql.to_string()),
+                    Err(_) => Some(format!("/* plan-to-sql failed */ {original_query}")),
+                }
+            } else {
+                None
+            };
+
+            // Execute the plan.
+            let df = match session_context.execute_logical_plan(final_plan).await {
+                Ok(df) => df,
+                Err(e) => {
+                    tracing::error!(error = %e, "PolicyHook: execution failed");
+                    let msg = e.to_string();
+                    break 'query (
+                        Err(PgWireError::ApiError(Box::new(e))),
+                        "error",
+                        Some(msg),
+                        rewritten_query,
+                        decision_results,
+                        shadow_outcomes,
+                        epsilon_spent,
+                    );
+                }
+            };
+
+            let stream = match df.execute_stream().await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::error!(error = %e, "PolicyHook: execution failed");
+                    let msg = e.to_string();
+                    break 'query (
+                        Err(PgWireError::ApiError(Box::new(e))),
+                        "error",
+                        Some(msg),
+                        rewritten_query,
+                        decision_results,
+                        shadow_outcomes,
+                        epsilon_spent,
+                    );
+                }
+            };
+            // Count (and cap) what reaches the client.
+            let (stream, totals) = crate::result_limit::meter(stream, result_limit.as_ref());
+            result_totals = Some(totals);
+
+            // Hand the stream to the front-end (pgwire encodes rows as they are
+            // pulled; Flight SQL and the query API read batches).
+            let response = match consume(stream, notices).await {
+                Ok(r) => r,
+                Err(e) => {
+                    tracing::error!(error = %e, "PolicyHook: encoding error");
+                    let msg = e.to_string();
+                    break 'query (
+                        Err(e),
+                        "error",
+                        Some(msg),
+                        rewritten_query,
+                        decision_results,
+                        shadow_outcomes,
+                        epsilon_spent,
+                    );
+                }
+            };
+
+            (
+                Ok(response),
+                "success",
+                None,
+                rewritten_query,
+                decision_results,
+                shadow_outcomes,
+                epsilon_spent,
+            )
+        };
+
+        let (
+            result,
+            audit_status,
+            audit_error,
+            audit_rewritten,
+            decision_results,
+            shadow_outcomes,
+            epsilon_spent,
+        ) = outcome;
+
+        // Duration measured after the labeled block — covers planning + execution + `consume`.
+        let elapsed_ms = query_start.elapsed().as_millis() as i64;
+
+        // Async audit log — runs on all paths (success, error, denied).
+        let policies_applied = policies_applied(&session, &decision_results);
+
+        let report = GovernReport {
+            rewritten_query: audit_rewritten.clone(),
+            policies_applied: policies_applied.clone(),
+            policy_conflicts: policy_conflicts.clone(),
+        };
+
+        let db = self.db.clone();
+        let audit_user_id = user_id;
+        let audit_username = username;
+        let audit_ds_id = session.datasource_id;
+        let audit_ds_name = session.datasource_name.clone();
+        let audit_orig_q = original_query;
+        let audit_policies = serde_json::to_string(&policies_applied).unwrap_or_default();
+        let audit_shadow = (!shadow_outcomes.is_empty())
+            .then(|| serde_json::to_string(&shadow_outcomes).unwrap_or_default());
+        let audit_conflicts = (!policy_conflicts.is_empty())
+            .then(|| serde_json::to_string(&policy_conflicts).unwrap_or_default());
+        let audit_info = client_info;
+        let audit_purpose = caller.purpose.clone();
+        let audit_justification = caller.justification.clone();
+        let audit_status_owned = audit_status.to_string();
+
+        tokio::spawn(async move {
+            // A result stream reports what it delivered once the front-end drops it.
+            let totals = match result_totals {
+                Some(rx) => rx.await.ok(),
+                None => None,
+            };
+            if let Some(totals) = totals.as_ref().filter(|t| t.rows > 0) {
+                for window in &result_windows {
+                    if let Err(e) =
+                        crate::result_limit::record_rows(&db, window, audit_user_id, totals.rows)
+                            .await
+                    {
+                        tracing::error!(error = %e, policy = %window.policy_name, "Failed to record result rows");
+                    }
+                }
+            }
+            // A `deny` cap that failed the stream turns the query into a denial.
+            let (audit_status_owned, audit_error) =
+                match totals.as_ref().and_then(|t| t.exceeded.clone()) {
+                    Some(msg) => ("denied".to_string(), Some(msg)),
+                    None => (audit_status_owned, audit_error),
+                };
+            let now = Utc::now().naive_utc();
+            let entry = query_audit_log::ActiveModel {
+                id: sea_orm::Set(Uuid::now_v7()),
+                user_id: sea_orm::Set(audit_user_id),
+                username: sea_orm::Set(audit_username),
+                data_source_id: sea_orm::Set(audit_ds_id),
+                datasource_name: sea_orm::Set(audit_ds_name),
+                original_query: sea_orm::Set(audit_orig_q),
+                rewritten_query: sea_orm::Set(audit_rewritten),
+                policies_applied: sea_orm::Set(audit_policies),
+                execution_time_ms: sea_orm::Set(Some(elapsed_ms)),
+                client_ip: sea_orm::Set(None),
+                client_info: sea_orm::Set(audit_info),
+                created_at: sea_orm::Set(now),
+                status: sea_orm::Set(audit_status_owned),
+                error_message: sea_orm::Set(audit_error),
+                served_by: sea_orm::Set(served_by),
+                shadow_outcomes: sea_orm::Set(audit_shadow),
+                epsilon_spent: sea_orm::Set(epsilon_spent),
+                rows_returned: sea_orm::Set(totals.as_ref().map(|t| t.rows as i64)),
+                bytes_returned: sea_orm::Set(totals.as_ref().map(|t| t.bytes as i64)),
+                impersonated_by: sea_orm::Set(impersonated_by),
+                purpose: sea_orm::Set(audit_purpose),
+                justification: sea_orm::Set(audit_justification),
+                policy_conflicts: sea_orm::Set(audit_conflicts),
+                rows_affected: sea_orm::Set(None),
+                affected_rows: sea_orm::Set(None),
+            };
+            if let Err(e) = sea_orm::ActiveModelTrait::insert(entry, &db).await {
+                tracing::error!(error = %e, "Failed to write audit log entry");
+            }
+        });
+
+        (result, report)
+    }
+
+    /// Explain `EXPLAIN (POLICY) <target>` for `caller`: which policies apply to
+    /// each table and column the query reads and what they do, as one row each.
+    ///
+    /// The query is planned and governed like [`Self::run_governed`] but never
+    /// executed, so nothing is charged to a privacy budget or row quota. Admins
+    /// see everything; other users see what the configured [`ExplainLevel`]
+    /// allows. One `query_audit_log` row records the request.
+    pub async fn explain_policies(
+        &self,
+        statement: &Statement,
+        target: &Statement,
+        session_context: &SessionContext,
+        caller: &QueryCaller,
+    ) -> PgWireResult<datafusion::arrow::record_batch::RecordBatch> {
+        use crate::entity::proxy_user;
+
+        if !matches!(target, Statement::Query(_)) || is_system_only_statement(target) {
+            return Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                "ERROR".to_owned(),
+                "42601".to_owned(),
+                "EXPLAIN (POLICY) explains a query that reads user tables".to_owned(),
+            ))));
+        }
+        let is_admin = proxy_user::Entity::find_by_id(caller.user_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| PgWireError::ApiError(Box::new(e)))?
+            .is_some_and(|u| u.is_admin);
+        let level = if is_admin {
+            ExplainLevel::Full
+        } else {
+            self.explain_level
+        };
+        if level == ExplainLevel::Off {
+            return Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                "ERROR".to_owned(),
+                "42501".to_owned(),
+                "EXPLAIN (POLICY) is not enabled".to_owned(),
+            ))));
+        }
+
+        let session = self.get_session(caller).await.map_err(|e| {
+            tracing::error!(error = %e, "PolicyHook: failed to load session");
+            PgWireError::ApiError(Box::new(std::io::Error::other(e.to_string())))
+        })?;
+        let query_start = std::time::Instant::now();
+        let (rows, decision_results, served_by) = self
+            .explain_rows(
+                &session,
+                session_context,
+                target,
+                caller.user_id,
+                &caller.username,
+                level,
+            )
+            .await?;
+        let rows = redact_explanation(rows, level);
+        let batch = explanation_batch(&rows).map_err(|e| PgWireError::ApiError(Box::new(e)))?;
+
+        let db = self.db.clone();
+        let entry = query_audit_log::ActiveModel {
+            id: sea_orm::Set(Uuid::now_v7()),
+            user_id: sea_orm::Set(caller.user_id),
+            username: sea_orm::Set(caller.username.clone()),
+            data_source_id: sea_orm::Set(session.datasource_id),
+            datasource_name: sea_orm::Set(session.datasource_name.clone()),
+            original_query: sea_orm::Set(statement.to_string()),
+            rewritten_query: sea_orm::Set(None),
+            policies_applied: sea_orm::Set(
+                serde_json::to_string(&policies_applied(&session, &decision_results))
+                    .unwrap_or_default(),
+            ),
+            execution_time_ms: sea_orm::Set(Some(query_start.elapsed().as_millis() as i64)),
+            client_ip: sea_orm::Set(None),
+            client_info: sea_orm::Set(caller.client_info.clone()),
+            created_at: sea_orm::Set(Utc::now().naive_utc()),
+            status: sea_orm::Set("success".to_string()),
+            error_message: sea_orm::Set(None),
+            served_by: sea_orm::Set(served_by),
+            shadow_outcomes: sea_orm::Set(None),
+            epsilon_spent: sea_orm::Set(None),
+            rows_returned: sea_orm::Set(Some(batch.num_rows() as i64)),
+            bytes_returned: sea_orm::Set(None),
+            impersonated_by: sea_orm::Set(None),
+            purpose: sea_orm::Set(caller.purpose.clone()),
+            justification: sea_orm::Set(caller.justification.clone()),
+            policy_conflicts: sea_orm::Set(None),
+            rows_affected: sea_orm::Set(None),
+            affected_rows: sea_orm::Set(None),
+        };
+        tokio::spawn(async move {
+            if let Err(e) = sea_orm::ActiveModelTrait::insert(entry, &db).await {
+                tracing::error!(error = %e, "Failed to write audit log entry for EXPLAIN (POLICY)");
+            }
+        });
+
+        Ok(batch)
+    }
+
+    /// The `EXPLAIN (POLICY)` rows for `target` under `session`, with the
+    /// decision function results and the upstream that would serve it. A query
+    /// that plans but is refused by its policies yields a `rejected` row; one
+    /// that does not plan is an error.
+    async fn explain_rows(
+        &self,
+        session: &SessionDataClone,
+        session_context: &SessionContext,
+        target: &Statement,
+        user_id: Uuid,
+        username: &str,
+        level: ExplainLevel,
+    ) -> PgWireResult<ExplainedRows> {
+        let user_vars = UserVars {
+            username: username.to_string(),
+            user_id: user_id.to_string(),
+            attributes: session.user_attributes.clone(),
+            attribute_defs: session.attribute_defs.clone(),
+        };
+        let df_stmt = datafusion::sql::parser::Statement::Statement(Box::new(target.clone()));
+        let (planned, served_by) =
+            crate::engine::track_served_by(session_context.state().statement_to_plan(df_stmt))
+                .await;
+        let logical_plan = planned.map_err(|e| PgWireError::ApiError(Box::new(e)))?;
+        let default_schema = session_context
+            .state()
+            .config_options()
+            .catalog
+            .default_schema
+            .clone();
+        let decision_eval = DecisionEvalContext {
+            wasm_runtime: &self.wasm_runtime,
+            decision_ctx: decision_context(
+                session,
+                user_id,
+                username,
+                &logical_plan,
+                &default_schema,
+            ),
+        };
+
+        let mut rows = Vec::new();
+        let mut decision_results = HashMap::new();
+        let rejected = |e: &PolicyError| match e {
+            PolicyError::PlanTransformation(_) if level != ExplainLevel::Full => {
+                "the query's policies cannot be applied to it".to_string()
+            }
+            e => e.to_string(),
+        };
+        match check_query_guards(
+            session,
+            target,
+            &logical_plan,
+            &default_schema,
+            Some(&decision_eval),
+        )
+        .await
+        {
+            Ok((decisions, shadows, _)) => {
+                rows.extend(decision_explanations(session, &decisions));
+                rows.extend(shadows.iter().map(shadow_explanation));
+                decision_results.extend(decisions);
+            }
+            Err(e) => rows.push(PolicyExplanation::query("rejected", rejected(&e))),
+        }
+        match apply_policies_at(
+            session,
+            session_context,
+            logical_plan,
+            &user_vars,
+            Some(&decision_eval),
+            0,
+            Some(&mut rows),
+        )
+        .await
+        {
+            Ok(AppliedPolicies {
+                plan,
+                had_effects,
+                decision_results: decisions,
+                ..
+            }) => {
+                decision_results.extend(decisions);
+                if had_effects {
+                    let sql = Unparser::new(&BetweenRowsPostgresDialect)
+                        .plan_to_sql(&plan)
+                        .map(|sql| sql.to_string())
+                        .unwrap_or_else(|e| format!("/* plan-to-sql failed: {e} */"));
+                    rows.push(PolicyExplanation::query("rewritten_query", sql));
+                }
+            }
+            Err(e) => rows.push(PolicyExplanation::query("rejected", rejected(&e))),
+        }
+        Ok((rows, decision_results, served_by))
+    }
+
+    /// The `EXPLAIN (POLICY)` rows `statement` gets for `subject` under the
+    /// current policies or, with `change`, under the proposed ones. Plan only:
+    /// nothing is executed and policy subquery lookups are skipped, so no
+    /// upstream data is read. A statement that does not plan yields a single
+    /// `rejected` row.
+    pub(crate) async fn simulate(
+        &self,
+        subject: &Subject,
+        datasource: &str,
+        statement: &Statement,
+        session_context: &SessionContext,
+        change: Option<&PolicyChange>,
+    ) -> Result<Vec<PolicyExplanation>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut session =
+            clone_session_data(&self.load_session(subject, datasource, change).await?);
+        session.plan_only = true;
+        match self
+            .explain_rows(
+                &session,
+                session_context,
+                statement,
+                subject.user_id,
+                &subject.username,
+                ExplainLevel::Full,
+            )
+            .await
+        {
+            Ok((rows, ..)) => Ok(rows),
+            Err(PgWireError::ApiError(e)) => {
+                Ok(vec![PolicyExplanation::query("rejected", e.to_string())])
+            }
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    /// Parse, route and (for user-table reads) govern a single SQL string,
+    /// returning the result as a record-batch stream.
+    ///
+    /// Entry point for the non-pgwire front-ends (Arrow Flight SQL, the HTTP query
+    /// API). Routing mirrors the pgwire hook pipeline: writes are audited and
+    /// rejected with `25006` (governed writes on `read_write` datasources are
+    /// pgwire-only), user-table reads go through [`Self::run_governed`],
+    /// and catalog/utility statements run directly against `session_context`.
+    pub async fn stream_sql(
+        &self,
+        sql: &str,
+        session_context: &SessionContext,
+        caller: &QueryCaller,
+    ) -> PgWireResult<SendableRecordBatchStream> {
+        let statement = parse_single_statement(sql)?;
+
+        if !is_allowed_statement(&statement) {
+            self.audit_rejected(&statement, caller).await;
+            return Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                "ERROR".to_owned(),
+                "25006".to_owned(),
+                "only read-only queries are allowed".to_owned(),
+            ))));
+        }
+
+        if let Some(target) = policy_explain_target(&statement) {
+            let batch = self
+                .explain_policies(&statement, target, session_context, caller)
+                .await?;
+            let stream = datafusion::physical_plan::memory::MemoryStream::try_new(
+                vec![batch.clone()],
+                batch.schema(),
+                None,
+            )
+            .map_err(|e| PgWireError::ApiError(Box::new(e)))?;
+            Ok(Box::pin(stream))
+        } else if matches!(statement, Statement::Query(_)) && !is_system_only_statement(&statement)
+        {
+            self.run_governed(
+                &statement,
+                session_context,
+                caller,
+                |stream, _notices| async move { Ok(stream) },
+            )
+            .await
+        } else {
+            let df = session_context
+                .sql(&statement.to_string())
+                .await
+                .map_err(|e| PgWireError::ApiError(Box::new(e)))?;
+            df.execute_stream()
+                .await
+                .map_err(|e| PgWireError::ApiError(Box::new(e)))
+        }
+    }
+}
+
+/// Parse `sql` as exactly one statement, normalised by `rewrite_statement`.
+pub(crate) fn parse_single_statement(sql: &str) -> PgWireResult<Statement> {
+    let mut statements = Parser::parse_sql(&PostgreSqlDialect {}, sql).map_err(|e| {
+        PgWireError::UserError(Box::new(ErrorInfo::new(
+            "ERROR".to_owned(),
+            "42601".to_owned(),
+            e.to_string(),
+        )))
+    })?;
+    if statements.len() != 1 {
+        return Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+            "ERROR".to_owned(),
+            "42601".to_owned(),
+            "Exactly one statement per query is accepted".to_owned(),
+        ))));
+    }
+    let mut statement = statements.remove(0);
+    rewrite_statement(&mut statement);
+    Ok(statement)
+}
+
+/// Encode a governed result stream as a pgwire text response, like
+/// `arrow_pg`'s `encode_dataframe`, except that a result that breaks a `deny`
+/// cap is reported with SQLSTATE 54000 instead of as an internal error.
+fn encode_stream(stream: SendableRecordBatchStream) -> PgWireResult<QueryResponse> {
+    use futures::StreamExt;
+    use pgwire::messages::data::DataRow;
+
+    let fields = Arc::new(arrow_schema_to_pg_fields(
+        stream.schema().as_ref(),
+        &Format::UnifiedText,
+        None,
+    )?);
+    let row_fields = fields.clone();
+    let rows = stream
+        .map(move |batch| {
+            let rows: Box<dyn Iterator<Item = PgWireResult<DataRow>> + Send + Sync> = match batch {
+                Ok(batch) => encode_recordbatch(row_fields.clone(), batch),
+                Err(e) => Box::new(std::iter::once(Err(result_error(e)))),
+            };
+            futures::stream::iter(rows)
+        })
+        .flatten();
+    Ok(QueryResponse::new(fields, rows))
+}
+
+fn result_error(e: datafusion::error::DataFusionError) -> PgWireError {
+    if let datafusion::error::DataFusionError::External(inner) = &e
+        && let Some(exceeded) = inner.downcast_ref::<crate::result_limit::LimitExceeded>()
+    {
+        return PgWireError::UserError(Box::new(ErrorInfo::new(
+            "ERROR".to_owned(),
+            "54000".to_owned(),
+            exceeded.to_string(),
+        )));
+    }
+    PgWireError::ApiError(Box::new(e))
+}
+
+// ---------- governed writes ----------
+
+/// Whether a `write_allow` policy grants `operation`.
+fn grants_operation(policy: &ResolvedPolicy, operation: WriteOperation) -> bool {
+    policy
+        .definition
+        .clone()
+        .and_then(|d| serde_json::from_value::<WriteAllowDef>(d).ok())
+        .is_some_and(|def| def.operations.contains(&operation))
+}
+
+/// The columns of `key` a write policy targets, or `None` when it does not
+/// target the table.
+fn write_policy_columns(
+    policy: &ResolvedPolicy,
+    session: &SessionDataClone,
+    (df_schema, table): &(String, String),
+    all_cols: &[&str],
+) -> Option<HashSet<String>> {
+    let entries: Vec<&TargetEntry> = policy
+        .targets
+        .iter()
+        .filter(|e| {
+            e.matches_table(
+                df_schema,
+                table,
+                &session.df_to_upstream,
+                &session.catalog_tags,
+            )
+        })
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    let patterns: Vec<String> = entries
+        .iter()
+        .flat_map(|e| {
+            e.column_patterns(
+                df_schema,
+                table,
+                all_cols,
+                &session.df_to_upstream,
+                &session.catalog_tags,
+            )
+        })
+        .collect();
+    Some(expand_column_patterns(&patterns, all_cols))
+}
+
+/// Check the write policies on the target table of a write.
+///
+/// A `write_deny` policy on any written column refuses it; a DELETE writes
+/// every column of the rows it removes. Otherwise the `write_allow` policies
+/// granting `operation` on the table must together cover every written column
+/// (a DELETE only needs the grant). Shadow write policies record what they
+/// would have decided.
+async fn check_write_grants(
+    session: &SessionDataClone,
+    key: &(String, String),
+    table_schema: &SchemaRef,
+    operation: WriteOperation,
+    columns: &[String],
+    decision_eval: Option<&DecisionEvalContext<'_>>,
+) -> Result<
+    (
+        HashMap<Uuid, crate::decision::DecisionResult>,
+        Vec<ShadowOutcome>,
+    ),
+    PolicyError,
+> {
+    let mut decision_results = HashMap::new();
+    let mut shadow_outcomes = Vec::new();
+    let (df_schema, table) = key;
+    let verb = operation.as_str().to_uppercase();
+    let all_cols: Vec<&str> = table_schema
+        .fields()
+        .iter()
+        .map(|f| f.name().as_str())
+        .collect();
+    let written: Vec<&str> = match operation {
+        WriteOperation::Delete => all_cols.clone(),
+        WriteOperation::Insert | WriteOperation::Update => {
+            columns.iter().map(String::as_str).collect()
+        }
+    };
+    let hits = |targeted: &HashSet<String>| -> Vec<String> {
+        written
+            .iter()
+            .filter(|c| targeted.contains(**c))
+            .map(|c| c.to_string())
+            .collect()
+    };
+
+    for policy in &session.deny_policies {
+        if policy.policy_type != PolicyType::WriteDeny {
+            continue;
+        }
+        let Some(targeted) = write_policy_columns(policy, session, key, &all_cols) else {
+            continue;
+        };
+        let hit = hits(&targeted);
+        if hit.is_empty() {
+            continue;
+        }
+        if !evaluate_decision_fn(policy, decision_eval, &mut decision_results).await {
+            continue;
+        }
+        let reason = match operation {
+            WriteOperation::Delete => format!(
+                "DELETE on {df_schema}.{table} would remove protected columns {}",
+                hit.join(", ")
+            ),
+            WriteOperation::Insert | WriteOperation::Update => format!(
+                "{verb} of {} on {df_schema}.{table} is not allowed",
+                hit.join(", ")
+            ),
+        };
+        return Err(PolicyError::WriteDenied {
+            policy_name: policy.name.clone(),
+            reason,
+        });
+    }
+
+    let mut granted: Option<HashSet<String>> = None;
+    for policy in &session.permit_policies {
+        if policy.policy_type != PolicyType::WriteAllow || !grants_operation(policy, operation) {
+            continue;
+        }
+        let Some(targeted) = write_policy_columns(policy, session, key, &all_cols) else {
+            continue;
+        };
+        if !evaluate_decision_fn(policy, decision_eval, &mut decision_results).await {
+            continue;
+        }
+        granted.get_or_insert_with(HashSet::new).extend(targeted);
+    }
+    let Some(granted) = granted else {
+        return Err(PolicyError::WriteRefused {
+            reason: format!("no write_allow policy grants {verb} on {df_schema}.{table}"),
+        });
+    };
+    if operation != WriteOperation::Delete {
+        let missing: Vec<&str> = columns
+            .iter()
+            .filter(|c| !granted.contains(*c))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(PolicyError::WriteRefused {
+                reason: format!(
+                    "no write_allow policy grants {verb} of {} on {df_schema}.{table}",
+                    missing.join(", ")
+                ),
+            });
+        }
+    }
+
+    for policy in &session.shadow_policies {
+        if !policy.policy_type.is_write() {
+            continue;
+        }
+        let Some(targeted) = write_policy_columns(policy, session, key, &all_cols) else {
+            continue;
+        };
+        let (outcome, matched) = if policy.policy_type == PolicyType::WriteDeny {
+            let hit = hits(&targeted);
+            if hit.is_empty() {
+                continue;
+            }
+            ("would_deny_write", hit)
+        } else {
+            if !grants_operation(policy, operation) {
+                continue;
+            }
+            let allowed = match operation {
+                WriteOperation::Delete => Vec::new(),
+                WriteOperation::Insert | WriteOperation::Update => hits(&targeted),
+            };
+            ("would_allow_write", allowed)
+        };
+        if !evaluate_decision_fn(policy, decision_eval, &mut decision_results).await {
+            continue;
+        }
+        shadow_outcomes.push(ShadowOutcome {
+            policy_id: policy.id,
+            name: policy.name.clone(),
+            version: policy.version,
+            policy_type: policy.policy_type,
+            outcome,
+            tables: vec![format!("{df_schema}.{table}")],
+            columns: matched
+                .into_iter()
+                .map(|c| format!("{df_schema}.{table}.{c}"))
+                .collect(),
+            expression: Some(verb.clone()),
+        });
+    }
+
+    Ok((decision_results, shadow_outcomes))
+}
+
+/// Why a governed write failed: the client error, and its audit status and message.
+struct WriteFailure {
+    error: PgWireError,
+    status: &'static str,
+    message: String,
+}
+
+impl From<PolicyError> for WriteFailure {
+    fn from(e: PolicyError) -> Self {
+        let status = match &e {
+            PolicyError::PlanTransformation(_) => "error",
+            _ => "denied",
+        };
+        Self {
+            message: e.to_string(),
+            error: e.into_pgwire_error(),
+            status,
+        }
+    }
+}
+
+impl WriteFailure {
+    fn error(e: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self {
+            message: e.to_string(),
+            error: PgWireError::ApiError(Box::new(e)),
+            status: "error",
+        }
+    }
+
+    /// An error from the upstream; its SQLSTATE reaches the client unchanged.
+    fn upstream(e: tokio_postgres::Error) -> Self {
+        match e.as_db_error() {
+            Some(db) => Self {
+                message: db.message().to_owned(),
+                error: PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_owned(),
+                    db.code().code().to_owned(),
+                    db.message().to_owned(),
+                ))),
+                status: "error",
+            },
+            None => Self::error(e),
+        }
+    }
+}
+
+/// Apply `masks` (column, mask expression) to a write's audit images: JSON
+/// objects of `columns`, typed as in `table_schema`.
+async fn mask_row_images(
+    ctx: &SessionContext,
+    table_schema: &SchemaRef,
+    columns: &[String],
+    masks: &[(String, datafusion::logical_expr::Expr)],
+    images: &[serde_json::Value],
+) -> datafusion::error::Result<Vec<serde_json::Value>> {
+    use datafusion::arrow::json::{ReaderBuilder, WriterBuilder, writer::JsonArray};
+    use datafusion::logical_expr::Expr;
+
+    let fields = columns
+        .iter()
+        .map(|c| table_schema.field_with_name(c).cloned())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut decoder = ReaderBuilder::new(Arc::new(Schema::new(fields))).build_decoder()?;
+    decoder.serialize(images)?;
+    let Some(batch) = decoder.flush()? else {
+        return Ok(Vec::new());
+    };
+    let exprs: Vec<Expr> = columns
+        .iter()
+        .map(|c| match masks.iter().find(|(column, _)| column == c) {
+            Some((_, mask)) => crate::write::unqualified(mask.clone()).alias(c),
+            None => Expr::Column(datafusion::common::Column::new_unqualified(c)),
+        })
+        .collect();
+    let batches = ctx.read_batch(batch)?.select(exprs)?.collect().await?;
+    let mut writer = WriterBuilder::new()
+        .with_explicit_nulls(true)
+        .build::<_, JsonArray>(Vec::new());
+    writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+    writer.finish()?;
+    let buf = writer.into_inner();
+    if buf.is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_slice(&buf).map_err(|e| datafusion::error::DataFusionError::External(e.into()))
+}
+
+/// What a governed write did, for its audit row.
+#[derive(Default)]
+struct WriteRecord {
+    upstream_sql: Option<String>,
+    decision_results: HashMap<Uuid, crate::decision::DecisionResult>,
+    shadow_outcomes: Vec<ShadowOutcome>,
+    served_by: Option<String>,
+    rows_affected: Option<i64>,
+    affected_rows: Vec<serde_json::Value>,
+    notices: Vec<String>,
+}
+
+impl PolicyHook {
+    /// Run an INSERT, UPDATE or DELETE, or refuse DDL, for `caller` on a
+    /// `read_write` datasource (see [`crate::write`]).
+    ///
+    /// Returns `None` when the datasource is `read_only` or the session cannot be
+    /// loaded; the statement then falls through to `ReadOnlyHook`. Otherwise
+    /// exactly one `query_audit_log` row records the write, the rows it changed
+    /// and their images, along with any NOTICE messages for the client.
+    pub async fn handle_write(
+        &self,
+        statement: &Statement,
+        session_context: &SessionContext,
+        caller: &QueryCaller,
+    ) -> Option<PgWireResult<(Response, Vec<String>)>> {
+        let session = match self.get_session(caller).await {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!(error = %e, "PolicyHook: failed to load session");
+                return None;
+            }
+        };
+        if session.write_mode != "read_write" {
+            return None;
+        }
+
+        let start = std::time::Instant::now();
+        let mut record = WriteRecord::default();
+        let result = self
+            .execute_write(statement, session_context, caller, &session, &mut record)
+            .await;
+        let elapsed_ms = start.elapsed().as_millis() as i64;
+
+        let (status, error_message) = match &result {
+            Ok(_) => ("success", None),
+            Err(failure) => {
+                tracing::warn!(error = %failure.message, "PolicyHook: write refused");
+                (failure.status, Some(failure.message.clone()))
+            }
+        };
+        let policies_applied = policies_applied(&session, &record.decision_results);
+        let entry = query_audit_log::ActiveModel {
+            id: sea_orm::Set(Uuid::now_v7()),
+            user_id: sea_orm::Set(caller.user_id),
+            username: sea_orm::Set(caller.username.clone()),
+            data_source_id: sea_orm::Set(session.datasource_id),
+            datasource_name: sea_orm::Set(session.datasource_name.clone()),
+            original_query: sea_orm::Set(statement.to_string()),
+            rewritten_query: sea_orm::Set(record.upstream_sql),
+            policies_applied: sea_orm::Set(
+                serde_json::to_string(&policies_applied).unwrap_or_default(),
+            ),
+            execution_time_ms: sea_orm::Set(Some(elapsed_ms)),
+            client_ip: sea_orm::Set(None),
+            client_info: sea_orm::Set(caller.client_info.clone()),
+            created_at: sea_orm::Set(Utc::now().naive_utc()),
+            status: sea_orm::Set(status.to_string()),
+            error_message: sea_orm::Set(error_message),
+            served_by: sea_orm::Set(record.served_by),
+            shadow_outcomes: sea_orm::Set(
+                (!record.shadow_outcomes.is_empty())
+                    .then(|| serde_json::to_string(&record.shadow_outcomes).unwrap_or_default()),
+            ),
+            epsilon_spent: sea_orm::Set(None),
+            rows_returned: sea_orm::Set(None),
+            bytes_returned: sea_orm::Set(None),
+            impersonated_by: sea_orm::Set(None),
+            purpose: sea_orm::Set(caller.purpose.clone()),
+            justification: sea_orm::Set(caller.justification.clone()),
+            policy_conflicts: sea_orm::Set(None),
+            rows_affected: sea_orm::Set(record.rows_affected),
+            affected_rows: sea_orm::Set(
+                record
+                    .rows_affected
+                    .map(|_| serde_json::to_string(&record.affected_rows).unwrap_or_default()),
+            ),
+        };
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            if let Err(e) = sea_orm::ActiveModelTrait::insert(entry, &db).await {
+                tracing::error!(error = %e, "Failed to write audit log entry for write");
+            }
+        });
+
+        Some(
+            result
+                .map(|response| (response, record.notices))
+                .map_err(|failure| failure.error),
+        )
+    }
+
+    /// Govern and run one write upstream, filling `record` as it goes.
+    async fn execute_write(
+        &self,
+        statement: &Statement,
+        session_context: &SessionContext,
+        caller: &QueryCaller,
+        session: &SessionDataClone,
+        record: &mut WriteRecord,
+    ) -> Result<Response, WriteFailure> {
+        use datafusion::common::tree_node::TreeNode;
+
+        if crate::write::is_ddl(statement) {
+            return Err(PolicyError::WriteRefused {
+                reason: "DDL statements are not allowed".to_string(),
+            }
+            .into());
+        }
+        let write = WriteStatement::parse(statement).map_err(PolicyError::WriteUnsupported)?;
+        let operation = write.operation;
+        let verb = operation.as_str().to_uppercase();
+
+        let user_vars = UserVars {
+            username: caller.username.clone(),
+            user_id: caller.user_id.to_string(),
+            attributes: session.user_attributes.clone(),
+            attribute_defs: session.attribute_defs.clone(),
+        };
+        let default_schema = session_context
+            .state()
+            .config_options()
+            .catalog
+            .default_schema
+            .clone();
+
+        // Plan what the write reads: its table, SET expressions and WHERE clause.
+        let plan_select = |sql: String| async move {
+            let statement = parse_single_statement(&sql).map_err(|e| WriteFailure {
+                message: e.to_string(),
+                error: e,
+                status: "error",
+            })?;
+            let df_stmt =
+                datafusion::sql::parser::Statement::Statement(Box::new(statement.clone()));
+            let plan = session_context
+                .state()
+                .statement_to_plan(df_stmt)
+                .await
+                .map_err(WriteFailure::error)?;
+            Ok::<_, WriteFailure>((statement, plan))
+        };
+        let (probe, probe_plan) = plan_select(write.probe_sql()).await?;
+        let scan_schemas = collect_scan_schemas(&probe_plan, &default_schema);
+        let [(key, table_schema)] = scan_schemas.as_slice() else {
+            return Err(PolicyError::WriteUnsupported(
+                "a write must target one user table".to_string(),
+            )
+            .into());
+        };
+        let (df_schema, table) = key;
+        if let Some(column) = write
+            .columns
+            .iter()
+            .find(|c| table_schema.field_with_name(c).is_err())
+        {
+            let message = format!("column \"{column}\" of relation \"{table}\" does not exist");
+            return Err(WriteFailure {
+                error: PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_owned(),
+                    "42703".to_owned(),
+                    message.clone(),
+                ))),
+                status: "error",
+                message,
+            });
+        }
+
+        let mut decision_ctx = decision_context(
+            session,
+            caller.user_id,
+            &caller.username,
+            &probe_plan,
+            &default_schema,
+        );
+        decision_ctx["query"]["statement_type"] = serde_json::json!(verb);
+        let decision_eval = DecisionEvalContext {
+            wasm_runtime: &self.wasm_runtime,
+            decision_ctx,
+        };
+
+        // An INSERT reads no rows; the query guards judge what UPDATE and DELETE select.
+        if operation != WriteOperation::Insert {
+            let (decisions, outcomes, notices) = check_query_guards(
+                session,
+                &probe,
+                &probe_plan,
+                &default_schema,
+                Some(&decision_eval),
+            )
+            .await?;
+            record.decision_results.extend(decisions);
+            record.shadow_outcomes.extend(outcomes);
+            record.notices.extend(notices);
+        }
+
+        // The read policies on the table: what the write may reference and which
+        // rows it may touch.
+        let mut effects = PolicyEffects::collect(
+            session,
+            &scan_schemas,
+            &user_vars,
+            session_context,
+            Some(&decision_eval),
+        )
+        .await;
+        if !session.shadow_policies.is_empty() {
+            effects
+                .collect_shadow(session, &scan_schemas, &user_vars, Some(&decision_eval))
+                .await;
+        }
+        record
+            .decision_results
+            .extend(std::mem::take(&mut effects.decision_results));
+        record
+            .shadow_outcomes
+            .extend(std::mem::take(&mut effects.shadow_outcomes));
+        effects.check_deny()?;
+        effects.check_aggregate_only(&probe_plan)?;
+        effects.check_join_only(&probe_plan)?;
+        effects.apply_access_mode(&session.access_mode, std::slice::from_ref(key));
+        let scope = SubqueryScope {
+            session,
+            session_context,
+            user_vars: &user_vars,
+            decision_eval: Some(&decision_eval),
+            depth: 1,
+        };
+        effects
+            .govern_policy_subqueries(&scope, &scan_schemas)
+            .await;
+        record
+            .decision_results
+            .extend(std::mem::take(&mut effects.decision_results));
+        record
+            .shadow_outcomes
+            .extend(std::mem::take(&mut effects.shadow_outcomes));
+
+        let (decisions, outcomes) = check_write_grants(
+            session,
+            key,
+            table_schema,
+            operation,
+            &write.columns,
+            Some(&decision_eval),
+        )
+        .await?;
+        record.decision_results.extend(decisions);
+        record.shadow_outcomes.extend(outcomes);
+
+        if has_subqueries(&probe_plan) {
+            return Err(PolicyError::WriteUnsupported(
+                "subqueries in a write are not supported".to_string(),
+            )
+            .into());
+        }
+
+        // Whatever the write reads must be readable, unmasked, by the user.
+        let (projection, predicate) = crate::write::probe_parts(&probe_plan);
+        let all_cols: Vec<&str> = table_schema
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect();
+        let denied = effects
+            .column_deny_patterns
+            .get(key)
+            .map(|patterns| expand_column_patterns(patterns, &all_cols))
+            .unwrap_or_default();
+        let allowed = effects
+            .column_allow_patterns
+            .get(key)
+            .map(|patterns| expand_column_patterns(patterns, &all_cols));
+        let mut read: Vec<String> = projection
+            .iter()
+            .chain(&predicate)
+            .flat_map(|e| e.column_refs())
+            .map(|c| c.name.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        read.retain(|c| {
+            denied.contains(c)
+                || allowed.as_ref().is_some_and(|a| !a.contains(c))
+                || effects
+                    .column_masks
+                    .contains_key(&(df_schema.clone(), table.clone(), c.clone()))
+        });
+        if !read.is_empty() {
+            return Err(PolicyError::WriteRefused {
+                reason: format!(
+                    "{verb} on {df_schema}.{table} reads restricted column{} {}",
+                    if read.len() == 1 { "" } else { "s" },
+                    read.join(", ")
+                ),
+            }
+            .into());
+        }
+
+        let rows = match operation {
+            WriteOperation::Update => vec![
+                projection
+                    .iter()
+                    .map(crate::write::expr_sql)
+                    .collect::<datafusion::error::Result<Vec<_>>>()
+                    .map_err(WriteFailure::error)?,
+            ],
+            WriteOperation::Delete => Vec::new(),
+            WriteOperation::Insert => {
+                let mut rows = Vec::new();
+                for sql in write.values_sql() {
+                    let (_, plan) = plan_select(sql).await?;
+                    if has_subqueries(&plan) {
+                        return Err(PolicyError::WriteUnsupported(
+                            "subqueries in a write are not supported".to_string(),
+                        )
+                        .into());
+                    }
+                    let (values, _) = crate::write::probe_parts(&plan);
+                    rows.push(
+                        values
+                            .iter()
+                            .map(crate::write::expr_sql)
+                            .collect::<datafusion::error::Result<Vec<_>>>()
+                            .map_err(WriteFailure::error)?,
+                    );
+                }
+                rows
+            }
+        };
+        let selection = predicate
+            .as_ref()
+            .map(crate::write::expr_sql)
+            .transpose()
+            .map_err(WriteFailure::error)?;
+
+        // The row filter is both the write's USING and its WITH CHECK clause. It
+        // must be decidable on the target row alone.
+        let check = match effects.row_filters.remove(key) {
+            None => None,
+            Some(filter) => {
+                let filter = crate::write::unqualified(filter);
+                let foreign = filter
+                    .column_refs()
+                    .into_iter()
+                    .find(|c| table_schema.field_with_name(&c.name).is_err())
+                    .map(|c| c.name.clone());
+                if let Some(column) = foreign {
+                    return Err(PolicyError::WriteUnsupported(format!(
+                        "the row filter on {df_schema}.{table} reads column {column} of another table"
+                    ))
+                    .into());
+                }
+                if filter.exists(|e| Ok(is_subquery(e))).unwrap_or(true) {
+                    return Err(PolicyError::WriteUnsupported(format!(
+                        "the row filter on {df_schema}.{table} has a subquery"
+                    ))
+                    .into());
+                }
+                Some(crate::write::expr_sql(&filter).map_err(WriteFailure::error)?)
+            }
+        };
+
+        // The audit image holds the columns the user can read, masked as they
+        // would see them.
+        let image: Vec<String> = all_cols
+            .iter()
+            .filter(|c| !denied.contains(**c) && allowed.as_ref().is_none_or(|a| a.contains(**c)))
+            .map(|c| c.to_string())
+            .collect();
+        let masks: Vec<(String, datafusion::logical_expr::Expr)> = image
+            .iter()
+            .filter_map(|c| {
+                effects
+                    .column_masks
+                    .get(&(df_schema.clone(), table.clone(), c.clone()))
+                    .map(|mask| (c.clone(), mask.clone()))
+            })
+            .collect();
+
+        let upstream_schema = session.df_to_upstream.get(df_schema).unwrap_or(df_schema);
+        let planned = PlannedWrite {
+            operation,
+            target: format!(
+                "{}.{}",
+                crate::write::quote_ident(upstream_schema),
+                crate::write::quote_ident(table)
+            ),
+            columns: write.columns.clone(),
+            rows,
+            selection,
+            check,
+            image: image.clone(),
+        };
+        let sql = planned.to_sql();
+        record.upstream_sql = Some(sql.clone());
+
+        // Run it in its own upstream transaction, rolled back if any changed row
+        // fails the row filter.
+        let writer = session_context
+            .state()
+            .config()
+            .get_extension::<crate::engine::UpstreamWriter>()
+            .ok_or_else(|| {
+                WriteFailure::error(std::io::Error::other("datasource has no upstream"))
+            })?;
+        let (mut conn, address) = writer
+            .connect()
+            .await
+            .map_err(|e| WriteFailure::error(std::io::Error::other(e)))?;
+        record.served_by = Some(address);
+        let client = conn
+            .client()
+            .map_err(|e| WriteFailure::error(std::io::Error::other(e)))?;
+        let transaction = client.transaction().await.map_err(WriteFailure::upstream)?;
+        let mut rows_affected = 0i64;
+        let mut violation = false;
+        {
+            use futures::TryStreamExt;
+            let stream = transaction
+                .query_raw(
+                    sql.as_str(),
+                    std::iter::empty::<&(dyn tokio_postgres::types::ToSql + Sync)>(),
+                )
+                .await
+                .map_err(WriteFailure::upstream)?;
+            futures::pin_mut!(stream);
+            while let Some(row) = stream.try_next().await.map_err(WriteFailure::upstream)? {
+                if !row.try_get::<_, bool>(0).unwrap_or(false) {
+                    violation = true;
+                    break;
+                }
+                rows_affected += 1;
+                if record.affected_rows.len() < crate::write::MAX_AUDITED_ROWS
+                    && let Ok(Some(image)) = row.try_get::<_, Option<String>>(1)
+                {
+                    record.affected_rows.push(
+                        serde_json::from_str(&image).unwrap_or(serde_json::Value::String(image)),
+                    );
+                }
+            }
+        }
+        if violation {
+            record.affected_rows.clear();
+            transaction
+                .rollback()
+                .await
+                .map_err(WriteFailure::upstream)?;
+            return Err(PolicyError::WriteRefused {
+                reason: format!("new row violates the row filter on {df_schema}.{table}"),
+            }
+            .into());
+        }
+        transaction.commit().await.map_err(WriteFailure::upstream)?;
+        record.rows_affected = Some(rows_affected);
+
+        if !masks.is_empty() && !record.affected_rows.is_empty() {
+            match mask_row_images(
+                session_context,
+                table_schema,
+                &image,
+                &masks,
+                &record.affected_rows,
+            )
+            .await
+            {
+                Ok(masked) => record.affected_rows = masked,
+                Err(e) => {
+                    // Never store what the masks hide: drop the masked columns.
+                    tracing::warn!(
+                        error = %e,
+                        table = %table,
+                        "Failed to mask write audit images; dropping masked columns"
+                    );
+                    for row in &mut record.affected_rows {
+                        if let Some(object) = row.as_object_mut() {
+                            for (column, _) in &masks {
+                                object.remove(column);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let rows = rows_affected as usize;
+        let tag = match operation {
+            WriteOperation::Insert => Tag::new("INSERT").with_oid(0).with_rows(rows),
+            WriteOperation::Update => Tag::new("UPDATE").with_rows(rows),
+            WriteOperation::Delete => Tag::new("DELETE").with_rows(rows),
+        };
+        Ok(Response::Execution(tag))
+    }
+}
+
+#[async_trait]
+impl QueryHook for PolicyHook {
+    async fn handle_query(
+        &self,
+        statement: &Statement,
+        session_context: &SessionContext,
+        client: &(dyn ClientInfo + Sync),
+        notices: &mut Vec<ErrorInfo>,
+    ) -> Option<PgWireResult<Response>> {
+        if let Some(target) = policy_explain_target(statement) {
+            let caller = match QueryCaller::from_metadata(client.metadata())? {
+                Ok(c) => c,
+                Err(e) => return Some(Err(e)),
+            };
+            let result = self
+                .explain_policies(statement, target, session_context, &caller)
+                .await;
+            return Some(result.and_then(|batch| {
+                let stream = datafusion::physical_plan::memory::MemoryStream::try_new(
+                    vec![batch.clone()],
+                    batch.schema(),
+                    None,
+                )
+                .map_err(|e| PgWireError::ApiError(Box::new(e)))?;
+                encode_stream(Box::pin(stream)).map(Response::Query)
+            }));
+        }
+        if !matches!(statement, Statement::Query(_)) {
+            // On a `read_write` datasource, writes run governed and DDL is refused here.
+            if (crate::write::is_write(statement) || crate::write::is_ddl(statement))
+                && let Some(Ok(caller)) = QueryCaller::from_metadata(client.metadata())
+                && let Some(result) = self.handle_write(statement, session_context, &caller).await
+            {
+                return Some(result.map(|(response, messages)| {
+                    notices.extend(
+                        messages
+                            .into_iter()
+                            .map(|m| ErrorInfo::new("NOTICE".to_owned(), "00000".to_owned(), m)),
+                    );
+                    response
+                }));
+            }
+            // Other statements that are not reads (and every write on a `read_only`
+            // datasource) will be rejected by ReadOnlyHook. Audit them here before
+            // passing through, so the denied attempt is on the record.
+            if !is_allowed_statement(statement) {
+                self.audit_write_rejected(statement, client).await;
+            }
+            return None;
+        }
+        if is_system_only_statement(statement) {
+            return None;
+        }
+
+        let caller = match QueryCaller::from_metadata(client.metadata())? {
+            Ok(c) => c,
+            Err(e) => return Some(Err(e)),
+        };
+
+        // Encode the DataFrame into a pgwire response (this is where rows are pulled).
+        let result = self
+            .run_governed(
+                statement,
+                session_context,
+                &caller,
+                |stream, messages| async move {
+                    encode_stream(stream).map(|response| (Response::Query(response), messages))
+                },
+            )
+            .await;
+        Some(result.map(|(response, messages)| {
+            notices.extend(
+                messages
+                    .into_iter()
+                    .map(|m| ErrorInfo::new("NOTICE".to_owned(), "00000".to_owned(), m)),
+            );
+            response
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{Array, Int32Array, Int64Array, StringArray};
+    use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::catalog::default_table_source::DefaultTableSource;
+    use datafusion::datasource::MemTable;
+    use datafusion::datasource::empty::EmptyTable;
+    use datafusion::logical_expr::LogicalPlanBuilder;
+    use datafusion::prelude::SessionContext;
+    use datafusion::sql::sqlparser::{dialect::PostgreSqlDialect, parser::Parser as SqlParser};
+    use std::sync::Arc;
+
+    // ---------- shared test helpers ----------
+
+    fn make_session(
+        permit_policies: Vec<ResolvedPolicy>,
+        deny_policies: Vec<ResolvedPolicy>,
+        access_mode: &str,
+        df_to_upstream: HashMap<String, String>,
+    ) -> SessionDataClone {
+        SessionDataClone {
+            permit_policies,
+            deny_policies,
+            shadow_policies: vec![],
+            access_mode: access_mode.to_string(),
+            write_mode: "read_only".to_string(),
+            combining: CombiningRules::default(),
+            df_to_upstream,
+            datasource_id: Uuid::nil(),
+            datasource_name: "test_ds".to_string(),
+            roles: vec![],
+            user_attributes: HashMap::new(),
+            attribute_defs: HashMap::new(),
+            relationship_snapshot: Arc::new(RelationshipSnapshot::default()),
+            catalog_tags: Arc::new(CatalogTags::default()),
+            parent_scans_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            lookup_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            plan_only: false,
+        }
+    }
+
+    fn make_row_filter_policy(
+        name: &str,
+        priority: i32,
+        schema: &str,
+        table: &str,
+        filter: &str,
+    ) -> ResolvedPolicy {
+        ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type: PolicyType::RowFilter,
+            version: 1,
+            priority,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec![schema.to_string()],
+                tables: vec![table.to_string()],
+                columns: None,
+            }],
+            definition: Some(serde_json::json!({"filter_expression": filter})),
+            decision_function: None,
+            template: None,
+        }
+    }
+
+    fn make_column_mask_policy(
+        name: &str,
+        priority: i32,
+        schema: &str,
+        table: &str,
+        column: &str,
+        mask: &str,
+    ) -> ResolvedPolicy {
+        ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type: PolicyType::ColumnMask,
+            version: 1,
+            priority,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec![schema.to_string()],
+                tables: vec![table.to_string()],
+                columns: Some(vec![column.to_string()]),
+            }],
+            definition: Some(serde_json::json!({"mask_expression": mask})),
+            decision_function: None,
+            template: None,
+        }
+    }
+
+    fn make_column_allow_policy(
+        name: &str,
+        priority: i32,
+        schema: &str,
+        table: &str,
+        columns: &[&str],
+    ) -> ResolvedPolicy {
+        ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type: PolicyType::ColumnAllow,
+            version: 1,
+            priority,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec![schema.to_string()],
+                tables: vec![table.to_string()],
+                columns: Some(columns.iter().map(|c| c.to_string()).collect()),
+            }],
+            definition: None,
+            decision_function: None,
+            template: None,
+        }
+    }
+
+    fn make_column_deny_policy(
+        name: &str,
+        priority: i32,
+        schema: &str,
+        table: &str,
+        columns: &[&str],
+    ) -> ResolvedPolicy {
+        ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type: PolicyType::ColumnDeny,
+            version: 1,
+            priority,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec![schema.to_string()],
+                tables: vec![table.to_string()],
+                columns: Some(columns.iter().map(|c| c.to_string()).collect()),
+            }],
+            definition: None,
+            decision_function: None,
+            template: None,
+        }
+    }
+
+    fn make_table_deny_policy(
+        name: &str,
+        priority: i32,
+        schema: &str,
+        table: &str,
+    ) -> ResolvedPolicy {
+        ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type: PolicyType::TableDeny,
+            version: 1,
+            priority,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec![schema.to_string()],
+                tables: vec![table.to_string()],
+                columns: None,
+            }],
+            definition: None,
+            decision_function: None,
+            template: None,
+        }
+    }
+
+    /// Build a scan plan over an `EmptyTable` (no real data; for plan-structure tests).
+    fn build_scan_plan(schema_table: &str, columns: Vec<(&str, DataType)>) -> LogicalPlan {
+        let fields: Vec<Field> = columns
+            .into_iter()
+            .map(|(name, dt)| Field::new(name, dt, true))
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        let table = Arc::new(EmptyTable::new(schema));
+        let source = Arc::new(DefaultTableSource::new(table));
+        LogicalPlanBuilder::scan(schema_table, source, None)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    fn default_vars() -> UserVars {
+        let mut attrs = HashMap::new();
+        attrs.insert(
+            "tenant".to_string(),
+            TypedAttribute {
+                value: "acme".to_string(),
+                value_type: "string".to_string(),
+            },
+        );
+        UserVars {
+            username: "alice".to_string(),
+            user_id: "00000000-0000-0000-0000-000000000001".to_string(),
+            attributes: attrs,
+            attribute_defs: HashMap::new(),
+        }
+    }
+
+    fn plan_display(plan: &LogicalPlan) -> String {
+        format!("{}", plan.display_indent())
+    }
+
+    fn assert_plan_contains(plan: &LogicalPlan, expected: &str) {
+        let display = plan_display(plan);
+        assert!(
+            display.contains(expected),
+            "Plan does not contain '{expected}':\n{display}"
+        );
+    }
+
+    // ---------- system-only detection ----------
+
+    fn parse_statement(sql: &str) -> Statement {
+        let mut statements =
+            SqlParser::parse_sql(&PostgreSqlDialect {}, sql).expect("Failed to parse SQL");
+        assert_eq!(statements.len(), 1);
+        crate::engine::rewrite::rewrite_statement(&mut statements[0]);
+        statements.remove(0)
+    }
+
+    #[test]
+    fn test_system_only_pg_catalog() {
+        let stmt = parse_statement("SELECT * FROM pg_catalog.pg_class");
+        assert!(is_system_only_statement(&stmt));
+    }
+
+    #[test]
+    fn test_system_only_information_schema() {
+        let stmt = parse_statement("SELECT * FROM information_schema.tables");
+        assert!(is_system_only_statement(&stmt));
+    }
+
+    #[test]
+    fn test_user_table_not_system_only() {
+        let stmt = parse_statement("SELECT * FROM users");
+        assert!(!is_system_only_statement(&stmt));
+    }
+
+    #[test]
+    fn test_select_no_from_is_system_only() {
+        let stmt = parse_statement("SELECT 1");
+        assert!(is_system_only_statement(&stmt));
+    }
+
+    // ---------- parse_expr_detached ----------
+
+    #[test]
+    fn test_parse_filter_simple_eq() {
+        let mut attrs = HashMap::new();
+        attrs.insert(
+            "tenant".to_string(),
+            TypedAttribute {
+                value: "acme".to_string(),
+                value_type: "string".to_string(),
+            },
+        );
+        let vars = UserVars {
+            username: "alice".to_string(),
+            user_id: "test-id".to_string(),
+            attributes: attrs,
+            attribute_defs: HashMap::new(),
+        };
+        let expr = parse_expr_detached("organization_id = {user.tenant}", &vars).unwrap();
+        let expr_str = format!("{expr:?}");
+        assert!(
+            expr_str.contains("acme"),
+            "Expected tenant value in expr: {expr_str}"
+        );
+    }
+
+    #[test]
+    fn test_parse_filter_always_true() {
+        let vars = UserVars {
+            username: "u".to_string(),
+            user_id: "i".to_string(),
+            attributes: HashMap::new(),
+            attribute_defs: HashMap::new(),
+        };
+        let expr = parse_expr_detached("1=1", &vars).unwrap();
+        let expr_str = format!("{expr:?}");
+        assert!(
+            expr_str.contains("true") || expr_str.contains("Boolean"),
+            "{expr_str}"
+        );
+    }
+
+    #[test]
+    fn test_mangle_vars() {
+        let mut attrs = HashMap::new();
+        attrs.insert(
+            "tenant".to_string(),
+            TypedAttribute {
+                value: "my-tenant".to_string(),
+                value_type: "string".to_string(),
+            },
+        );
+        let vars = UserVars {
+            username: "alice".to_string(),
+            user_id: "uid-1".to_string(),
+            attributes: attrs,
+            attribute_defs: HashMap::new(),
+        };
+        let (mangled, mappings) =
+            mangle_vars("org = {user.tenant} AND user = {user.username}", &vars).unwrap();
+        assert!(!mangled.contains("{user.tenant}"));
+        assert!(!mangled.contains("{user.username}"));
+        assert_eq!(mappings.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_filter_and() {
+        let mut attrs = HashMap::new();
+        attrs.insert(
+            "tenant".to_string(),
+            TypedAttribute {
+                value: "acme".to_string(),
+                value_type: "string".to_string(),
+            },
+        );
+        let vars = UserVars {
+            username: "alice".to_string(),
+            user_id: "uid".to_string(),
+            attributes: attrs,
+            attribute_defs: HashMap::new(),
+        };
+        let expr = parse_expr_detached(
+            "organization_id = {user.tenant} AND is_active = true",
+            &vars,
+        )
+        .unwrap();
+        let expr_str = format!("{expr:?}");
+        assert!(expr_str.contains("acme"));
+        assert!(expr_str.contains("true") || expr_str.contains("is_active"));
+    }
+
+    #[test]
+    fn test_parse_filter_datafusion_constructs() {
+        // Anything DataFusion's SQL planner supports parses, with and without
+        // placeholders.
+        let mut attrs = HashMap::new();
+        attrs.insert(
+            "region".to_string(),
+            TypedAttribute {
+                value: "eu".to_string(),
+                value_type: "string".to_string(),
+            },
+        );
+        let vars = UserVars {
+            username: "alice".to_string(),
+            user_id: "uid".to_string(),
+            attributes: attrs,
+            attribute_defs: HashMap::new(),
+        };
+        for template in [
+            "name ILIKE 'a%'",
+            "owner IS DISTINCT FROM {user.username}",
+            "flag IS NOT TRUE",
+            "EXTRACT(HOUR FROM created_at) BETWEEN 9 AND 17",
+            "SUBSTRING(code FROM 1 FOR 2) = {user.region}",
+            "TRIM(code) <> '' AND POSITION('-' IN code) > 0",
+            "created_at >= DATE '2024-01-01'",
+            "created_at > now() - INTERVAL '7 days'",
+            "(payload->>'region') = {user.region}",
+            "region IN (SELECT region FROM regions WHERE owner = {user.username})",
+            "EXISTS (SELECT 1 FROM teams t WHERE t.id = team_id AND t.lead = {user.id})",
+            "upper(name) LIKE 'A%'",
+            "name SIMILAR TO 'a%'",
+            "TRIM(BOTH ' ' FROM code)::int > 3",
+            "created_at AT TIME ZONE 'UTC' > TIMESTAMP '2024-01-01 00:00:00'",
+            "{user.region} = (SELECT max(region) FROM regions r WHERE r.code = code)",
+        ] {
+            let expr = parse_expr_detached(template, &vars);
+            assert!(expr.is_ok(), "{template} should parse: {expr:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_filter_value_cannot_inject_sql() {
+        // An attribute value is bound as one literal, however it is quoted.
+        let mut attrs = HashMap::new();
+        attrs.insert(
+            "tenant".to_string(),
+            TypedAttribute {
+                value: "acme' OR '1'='1".to_string(),
+                value_type: "string".to_string(),
+            },
+        );
+        let vars = UserVars {
+            username: "alice".to_string(),
+            user_id: "uid".to_string(),
+            attributes: attrs,
+            attribute_defs: HashMap::new(),
+        };
+        let expr = parse_expr_detached(
+            "EXISTS (SELECT 1 FROM tenants t WHERE t.name = {user.tenant})",
+            &vars,
+        )
+        .unwrap();
+        let datafusion::logical_expr::Expr::Exists(exists) = expr else {
+            panic!("expected EXISTS, got {expr:?}");
+        };
+        let plan = format!("{}", exists.subquery.subquery.display_indent());
+        assert!(
+            plan.contains(r#"t.name = Utf8("acme' OR '1'='1")"#),
+            "value must stay a single literal: {plan}"
+        );
+
+        let expr = parse_expr_detached("org = {user.tenant}", &vars).unwrap();
+        assert_eq!(
+            expr,
+            col("org").eq(lit("acme' OR '1'='1")),
+            "value must stay a single literal"
+        );
+    }
+
+    // ---------- collect_user_tables ----------
+
+    #[test]
+    fn test_collect_user_tables_skips_pg_catalog() {
+        let schema = Arc::new(Schema::new(vec![Field::new("oid", DataType::Int32, false)]));
+        let table = Arc::new(EmptyTable::new(schema));
+        let source = Arc::new(DefaultTableSource::new(table));
+
+        let plan = LogicalPlanBuilder::scan("pg_catalog.pg_class", source, None)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let tables = collect_user_tables(&plan, "public");
+        assert!(
+            tables.is_empty(),
+            "pg_catalog tables should be excluded: {tables:?}"
+        );
+    }
+
+    #[test]
+    fn test_collect_user_tables_includes_user_table() {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        let table = Arc::new(EmptyTable::new(schema));
+        let source = Arc::new(DefaultTableSource::new(table));
+
+        let plan = LogicalPlanBuilder::scan("public.orders", source, None)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let tables = collect_user_tables(&plan, "public");
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0], ("public".to_string(), "orders".to_string()));
+    }
+
+    #[test]
+    fn test_collect_user_tables_bare_reference_uses_default_schema() {
+        // Security invariant for vector #71: bare references must inherit the
+        // session's default schema, NOT empty string. Otherwise a policy
+        // targeting `schemas: ["public"]` would silently skip bare queries
+        // like `SELECT * FROM orders`.
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        let table = Arc::new(EmptyTable::new(schema));
+        let source = Arc::new(DefaultTableSource::new(table));
+
+        let plan = LogicalPlanBuilder::scan("orders", source, None)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let tables = collect_user_tables(&plan, "public");
+        assert_eq!(tables, vec![("public".to_string(), "orders".to_string())]);
+    }
+
+    #[test]
+    fn test_collect_user_tables_skips_information_schema() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "table_name",
+            DataType::Utf8,
+            false,
+        )]));
+        let table = Arc::new(EmptyTable::new(schema));
+        let source = Arc::new(DefaultTableSource::new(table));
+
+        let plan = LogicalPlanBuilder::scan("information_schema.tables", source, None)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let tables = collect_user_tables(&plan, "public");
+        assert!(
+            tables.is_empty(),
+            "information_schema should be excluded: {tables:?}"
+        );
+    }
+
+    // ---------- Tier 1: plan-structure tests (apply_policies with EmptyTable) ----------
+
+    #[tokio::test]
+    async fn test_row_filter_injected_below_table_scan() {
+        let session = make_session(
+            vec![make_row_filter_policy(
+                "p1",
+                1,
+                "public",
+                "orders",
+                "status = 'active'",
+            )],
+            vec![],
+            "open",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan(
+            "public.orders",
+            vec![("id", DataType::Int32), ("status", DataType::Utf8)],
+        );
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        assert_plan_contains(&result_plan, "Filter");
+    }
+
+    #[tokio::test]
+    async fn test_row_filters_and_within_policy() {
+        // Two row_filter policies on the same table → AND'd together.
+        let session = make_session(
+            vec![
+                make_row_filter_policy("p1", 1, "public", "orders", "status = 'active'"),
+                make_row_filter_policy("p1_b", 1, "public", "orders", "amount > 0"),
+            ],
+            vec![],
+            "open",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan(
+            "public.orders",
+            vec![
+                ("id", DataType::Int32),
+                ("status", DataType::Utf8),
+                ("amount", DataType::Int64),
+            ],
+        );
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        let display = plan_display(&result_plan);
+        // Both filter expressions should appear
+        assert!(display.contains("Filter"), "Expected Filter: {display}");
+    }
+
+    #[tokio::test]
+    async fn test_row_filters_and_across_policies() {
+        // Same table filtered by two permit policies → AND'd together (intersection).
+        let session = make_session(
+            vec![
+                make_row_filter_policy("p1", 1, "public", "orders", "org = 'acme'"),
+                make_row_filter_policy("p2", 2, "public", "orders", "org = 'globex'"),
+            ],
+            vec![],
+            "open",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan(
+            "public.orders",
+            vec![("id", DataType::Int32), ("org", DataType::Utf8)],
+        );
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        assert_plan_contains(&result_plan, "Filter");
+        // AND semantics: both filter values appear in the expression (ANDed together).
+        let display = plan_display(&result_plan);
+        assert!(
+            display.contains("acme") && display.contains("globex"),
+            "Expected AND filter with both orgs: {display}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_column_deny_strips_column() {
+        let session = make_session(
+            vec![],
+            vec![make_column_deny_policy(
+                "p1",
+                1,
+                "public",
+                "customers",
+                &["ssn"],
+            )],
+            "open",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan(
+            "public.customers",
+            vec![
+                ("id", DataType::Int32),
+                ("name", DataType::Utf8),
+                ("ssn", DataType::Utf8),
+            ],
+        );
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        // ssn should be stripped from the projection
+        let display = plan_display(&result_plan);
+        assert!(!display.contains("ssn"), "ssn should be denied: {display}");
+        assert!(display.contains("name"), "name should remain: {display}");
+    }
+
+    #[tokio::test]
+    async fn test_column_deny_all_columns_error() {
+        let session = make_session(
+            vec![],
+            vec![make_column_deny_policy(
+                "p1",
+                1,
+                "public",
+                "customers",
+                &["id", "name"],
+            )],
+            "open",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan(
+            "public.customers",
+            vec![("id", DataType::Int32), ("name", DataType::Utf8)],
+        );
+
+        let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
+        assert!(
+            matches!(result, Err(PolicyError::AllColumnsDenied { .. })),
+            "Expected AllColumnsDenied: {result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deny_policy_row_filter_rejects() {
+        // table_deny on matching table → short-circuit error.
+        let session = make_session(
+            vec![],
+            vec![make_table_deny_policy("deny_p", 1, "public", "orders")],
+            "open",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
+
+        let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
+        assert!(
+            matches!(result, Err(PolicyError::DeniedByPolicy { .. })),
+            "Expected DeniedByPolicy: {result:?}"
+        );
+    }
 
-    fn make_table_deny_policy(
-        name: &str,
-        priority: i32,
-        schema: &str,
-        table: &str,
-    ) -> ResolvedPolicy {
-        ResolvedPolicy {
-            id: Uuid::now_v7(),
-            name: name.to_string(),
-            policy_type: PolicyType::TableDeny,
-            version: 1,
-            priority,
-            targets: vec![TargetEntry {
-                schemas: vec![schema.to_string()],
-                tables: vec![table.to_string()],
-                columns: None,
-            }],
-            definition: None,
-            decision_function: None,
-        }
+    #[tokio::test]
+    async fn test_deny_policy_row_filter_no_match() {
+        // table_deny on a DIFFERENT table → no error.
+        let session = make_session(
+            vec![],
+            vec![make_table_deny_policy("deny_p", 1, "public", "users")],
+            "open",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        // Query is on "orders", deny is on "users" → should pass through
+        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
+
+        let AppliedPolicies { had_effects, .. } =
+            apply_policies(&session, &ctx, plan, &default_vars(), None)
+                .await
+                .unwrap();
+        assert!(!had_effects, "No effects expected when deny doesn't match");
     }
 
-    /// Build a scan plan over an `EmptyTable` (no real data; for plan-structure tests).
-    fn build_scan_plan(schema_table: &str, columns: Vec<(&str, DataType)>) -> LogicalPlan {
-        let fields: Vec<Field> = columns
-            .into_iter()
-            .map(|(name, dt)| Field::new(name, dt, true))
-            .collect();
-        let schema = Arc::new(Schema::new(fields));
-        let table = Arc::new(EmptyTable::new(schema));
-        let source = Arc::new(DefaultTableSource::new(table));
-        LogicalPlanBuilder::scan(schema_table, source, None)
-            .unwrap()
-            .build()
-            .unwrap()
+    #[tokio::test]
+    async fn test_policy_required_no_permit_false_filter() {
+        // access_mode = "policy_required" with no permit → lit(false) injected.
+        let session = make_session(vec![], vec![], "policy_required", HashMap::new());
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        let display = plan_display(&result_plan);
+        assert!(
+            display.contains("false"),
+            "Expected lit(false) filter: {display}"
+        );
     }
 
-    fn default_vars() -> UserVars {
-        let mut attrs = HashMap::new();
-        attrs.insert(
-            "tenant".to_string(),
-            TypedAttribute {
-                value: "acme".to_string(),
-                value_type: "string".to_string(),
-            },
+    #[tokio::test]
+    async fn test_policy_required_with_permit_normal() {
+        // access_mode = "policy_required" with row_filter only → lit(false) injected.
+        // row_filter does not grant table access (zero-trust model); only column_access
+        // "allow" grants access. Without it the table is blocked.
+        let session = make_session(
+            vec![make_row_filter_policy(
+                "p1", 1, "public", "orders", "id > 0",
+            )],
+            vec![],
+            "policy_required",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        let display = plan_display(&result_plan);
+        // lit(false) injected because row_filter alone doesn't grant access
+        assert!(
+            display.contains("false"),
+            "Expected lit(false) filter (row_filter alone does not grant access): {display}"
         );
-        UserVars {
-            username: "alice".to_string(),
-            user_id: "00000000-0000-0000-0000-000000000001".to_string(),
-            attributes: attrs,
-            attribute_defs: HashMap::new(),
-        }
     }
 
-    fn plan_display(plan: &LogicalPlan) -> String {
-        format!("{}", plan.display_indent())
+    #[tokio::test]
+    async fn test_wildcard_schema_matches_all() {
+        // Policy with schema: "*" matches any schema name.
+        let session = make_session(
+            vec![make_row_filter_policy("p1", 1, "*", "orders", "id > 0")],
+            vec![],
+            "open",
+            HashMap::new(),
+        );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan("any_schema.orders", vec![("id", DataType::Int32)]);
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        assert_plan_contains(&result_plan, "Filter");
     }
 
-    fn assert_plan_contains(plan: &LogicalPlan, expected: &str) {
-        let display = plan_display(plan);
-        assert!(
-            display.contains(expected),
-            "Plan does not contain '{expected}':\n{display}"
+    #[tokio::test]
+    async fn test_wildcard_table_matches_all() {
+        // Policy with table: "*" matches any table in the schema.
+        let session = make_session(
+            vec![make_row_filter_policy("p1", 1, "public", "*", "id > 0")],
+            vec![],
+            "open",
+            HashMap::new(),
         );
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan("public.anything", vec![("id", DataType::Int32)]);
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        assert_plan_contains(&result_plan, "Filter");
     }
 
-    // ---------- system-only detection ----------
+    #[tokio::test]
+    async fn test_schema_alias_resolved() {
+        // df schema alias "sales" maps to upstream "public"; policy targets "public".
+        let mut df_to_upstream = HashMap::new();
+        df_to_upstream.insert("sales".to_string(), "public".to_string());
 
-    fn parse_statement(sql: &str) -> Statement {
-        let mut statements =
-            SqlParser::parse_sql(&PostgreSqlDialect {}, sql).expect("Failed to parse SQL");
-        assert_eq!(statements.len(), 1);
-        crate::engine::rewrite::rewrite_statement(&mut statements[0]);
-        statements.remove(0)
+        let session = make_session(
+            vec![make_row_filter_policy(
+                "p1", 1, "public", "orders", "id > 0",
+            )],
+            vec![],
+            "open",
+            df_to_upstream,
+        );
+        let ctx = SessionContext::new();
+        // Plan uses "sales" alias, which resolves to upstream "public"
+        let plan = build_scan_plan("sales.orders", vec![("id", DataType::Int32)]);
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(had_effects);
+        assert_plan_contains(&result_plan, "Filter");
     }
 
-    #[test]
-    fn test_system_only_pg_catalog() {
-        let stmt = parse_statement("SELECT * FROM pg_catalog.pg_class");
-        assert!(is_system_only_statement(&stmt));
+    #[tokio::test]
+    async fn test_deny_overrides_mask() {
+        // Column is both denied (via deny policy) and would be masked (via permit policy);
+        // deny takes priority — column is removed, mask expression never applied.
+        let session = make_session(
+            vec![make_column_mask_policy(
+                "p1",
+                1,
+                "public",
+                "customers",
+                "ssn",
+                "'***'",
+            )],
+            vec![make_column_deny_policy(
+                "deny_p",
+                2,
+                "public",
+                "customers",
+                &["ssn"],
+            )],
+            "open",
+            HashMap::new(),
+        );
+        // Register table so parse_mask_expr can resolve it
+        let schema: SchemaRef = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("ssn", DataType::Utf8, true),
+        ]));
+        let ctx = SessionContext::new();
+        let empty = RecordBatch::new_empty(schema.clone());
+        let table = MemTable::try_new(schema, vec![vec![empty]]).unwrap();
+        ctx.register_table("customers", Arc::new(table)).unwrap();
+
+        let plan = build_scan_plan(
+            "public.customers",
+            vec![("id", DataType::Int32), ("ssn", DataType::Utf8)],
+        );
+
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        let display = plan_display(&result_plan);
+        assert!(
+            !display.contains("ssn"),
+            "ssn should be denied (not masked): {display}"
+        );
+        assert!(
+            !display.contains("***"),
+            "mask expression must not appear when column is denied: {display}"
+        );
     }
 
-    #[test]
-    fn test_system_only_information_schema() {
-        let stmt = parse_statement("SELECT * FROM information_schema.tables");
-        assert!(is_system_only_statement(&stmt));
+    #[tokio::test]
+    async fn test_no_policies_no_effects() {
+        // No policies at all → plan is returned unchanged.
+        let session = make_session(vec![], vec![], "open", HashMap::new());
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
+
+        let AppliedPolicies { had_effects, .. } =
+            apply_policies(&session, &ctx, plan, &default_vars(), None)
+                .await
+                .unwrap();
+
+        assert!(!had_effects);
     }
 
-    #[test]
-    fn test_user_table_not_system_only() {
-        let stmt = parse_statement("SELECT * FROM users");
-        assert!(!is_system_only_statement(&stmt));
-    }
+    // ---------- shadow mode ----------
 
-    #[test]
-    fn test_select_no_from_is_system_only() {
-        let stmt = parse_statement("SELECT 1");
-        assert!(is_system_only_statement(&stmt));
+    fn make_shadow_session(shadow_policies: Vec<ResolvedPolicy>) -> SessionDataClone {
+        let mut session = make_session(vec![], vec![], "open", HashMap::new());
+        session.shadow_policies = shadow_policies;
+        session
     }
 
-    // ---------- parse_filter_expr ----------
-
-    #[test]
-    fn test_parse_filter_simple_eq() {
-        let mut attrs = HashMap::new();
-        attrs.insert(
-            "tenant".to_string(),
-            TypedAttribute {
-                value: "acme".to_string(),
-                value_type: "string".to_string(),
-            },
+    #[tokio::test]
+    async fn test_shadow_row_filter_recorded_not_applied() {
+        let policy = make_row_filter_policy("tenant", 1, "public", "orders", "status = 'active'");
+        let policy_id = policy.id;
+        let session = make_shadow_session(vec![policy]);
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan(
+            "public.orders",
+            vec![("id", DataType::Int32), ("status", DataType::Utf8)],
         );
-        let vars = UserVars {
-            username: "alice".to_string(),
-            user_id: "test-id".to_string(),
-            attributes: attrs,
-            attribute_defs: HashMap::new(),
-        };
-        let expr = parse_filter_expr("organization_id = {user.tenant}", &vars).unwrap();
-        let expr_str = format!("{expr:?}");
+
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            shadow_outcomes: shadow,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert!(!had_effects);
         assert!(
-            expr_str.contains("acme"),
-            "Expected tenant value in expr: {expr_str}"
+            !format!("{}", result_plan.display_indent()).contains("Filter"),
+            "shadow filter must not be applied"
         );
+        assert_eq!(shadow.len(), 1);
+        assert_eq!(shadow[0].policy_id, policy_id);
+        assert_eq!(shadow[0].outcome, "would_filter");
+        assert_eq!(shadow[0].tables, vec!["public.orders".to_string()]);
+        assert_eq!(shadow[0].expression.as_deref(), Some("status = 'active'"));
     }
 
-    #[test]
-    fn test_parse_filter_always_true() {
-        let vars = UserVars {
-            username: "u".to_string(),
-            user_id: "i".to_string(),
-            attributes: HashMap::new(),
-            attribute_defs: HashMap::new(),
-        };
-        let expr = parse_filter_expr("1=1", &vars).unwrap();
-        let expr_str = format!("{expr:?}");
-        assert!(
-            expr_str.contains("true") || expr_str.contains("Boolean"),
-            "{expr_str}"
-        );
+    #[tokio::test]
+    async fn test_shadow_table_deny_does_not_deny() {
+        let session = make_shadow_session(vec![make_table_deny_policy(
+            "no-orders",
+            1,
+            "public",
+            "orders",
+        )]);
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
+
+        let AppliedPolicies {
+            shadow_outcomes: shadow,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .expect("shadow table_deny must not reject the query");
+        assert_eq!(shadow.len(), 1);
+        assert_eq!(shadow[0].outcome, "would_deny");
     }
 
-    #[test]
-    fn test_mangle_vars() {
-        let mut attrs = HashMap::new();
-        attrs.insert(
-            "tenant".to_string(),
-            TypedAttribute {
-                value: "my-tenant".to_string(),
-                value_type: "string".to_string(),
-            },
+    #[tokio::test]
+    async fn test_shadow_column_deny_and_mask_list_matched_columns() {
+        let mut mask =
+            make_column_mask_policy("mask-email", 1, "public", "customers", "email", "'***'");
+        mask.version = 3;
+        let session = make_shadow_session(vec![
+            make_column_deny_policy("deny-ssn", 1, "public", "customers", &["ss*", "missing"]),
+            mask,
+            // Targets another table: no outcome.
+            make_table_deny_policy("no-orders", 1, "public", "orders"),
+        ]);
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan(
+            "public.customers",
+            vec![
+                ("id", DataType::Int32),
+                ("email", DataType::Utf8),
+                ("ssn", DataType::Utf8),
+            ],
         );
-        let vars = UserVars {
-            username: "alice".to_string(),
-            user_id: "uid-1".to_string(),
-            attributes: attrs,
-            attribute_defs: HashMap::new(),
-        };
-        let (mangled, mappings) =
-            mangle_vars("org = {user.tenant} AND user = {user.username}", &vars).unwrap();
-        assert!(!mangled.contains("{user.tenant}"));
-        assert!(!mangled.contains("{user.username}"));
-        assert_eq!(mappings.len(), 2);
-    }
 
-    #[test]
-    fn test_parse_filter_and() {
-        let mut attrs = HashMap::new();
-        attrs.insert(
-            "tenant".to_string(),
-            TypedAttribute {
-                value: "acme".to_string(),
-                value_type: "string".to_string(),
-            },
+        let AppliedPolicies {
+            plan: result_plan,
+            shadow_outcomes: shadow,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(result_plan.schema().fields().len(), 3);
+        assert_eq!(shadow.len(), 2);
+        assert_eq!(shadow[0].outcome, "would_deny_columns");
+        assert_eq!(shadow[0].columns, vec!["public.customers.ssn".to_string()]);
+        assert_eq!(shadow[1].outcome, "would_mask");
+        assert_eq!(shadow[1].version, 3);
+        assert_eq!(
+            shadow[1].columns,
+            vec!["public.customers.email".to_string()]
         );
-        let vars = UserVars {
-            username: "alice".to_string(),
-            user_id: "uid".to_string(),
-            attributes: attrs,
-            attribute_defs: HashMap::new(),
-        };
-        let expr = parse_filter_expr(
-            "organization_id = {user.tenant} AND is_active = true",
-            &vars,
-        )
-        .unwrap();
-        let expr_str = format!("{expr:?}");
-        assert!(expr_str.contains("acme"));
-        assert!(expr_str.contains("true") || expr_str.contains("is_active"));
     }
 
-    // ---------- collect_user_tables ----------
-
-    #[test]
-    fn test_collect_user_tables_skips_pg_catalog() {
-        let schema = Arc::new(Schema::new(vec![Field::new("oid", DataType::Int32, false)]));
-        let table = Arc::new(EmptyTable::new(schema));
-        let source = Arc::new(DefaultTableSource::new(table));
+    #[tokio::test]
+    async fn test_shadow_policies_do_not_change_enforced_result() {
+        let mut session = make_session(
+            vec![make_row_filter_policy(
+                "enforced",
+                1,
+                "public",
+                "orders",
+                "status = 'active'",
+            )],
+            vec![],
+            "open",
+            HashMap::new(),
+        );
+        session.shadow_policies =
+            vec![make_table_deny_policy("shadow-deny", 1, "public", "orders")];
+        let ctx = SessionContext::new();
+        let plan = build_scan_plan(
+            "public.orders",
+            vec![("id", DataType::Int32), ("status", DataType::Utf8)],
+        );
 
-        let plan = LogicalPlanBuilder::scan("pg_catalog.pg_class", source, None)
-            .unwrap()
-            .build()
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            shadow_outcomes: shadow,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
             .unwrap();
+        assert!(had_effects);
+        assert_plan_contains(&result_plan, "Filter");
+        assert_eq!(shadow.len(), 1);
+        assert_eq!(shadow[0].outcome, "would_deny");
+    }
 
-        let tables = collect_user_tables(&plan, "public");
-        assert!(
-            tables.is_empty(),
-            "pg_catalog tables should be excluded: {tables:?}"
-        );
+    // ---------- tag-based targets ----------
+
+    fn make_tagged_session(
+        permit: Vec<ResolvedPolicy>,
+        deny: Vec<ResolvedPolicy>,
+        tags: CatalogTags,
+    ) -> SessionDataClone {
+        let mut session = make_session(permit, deny, "open", HashMap::new());
+        session.catalog_tags = Arc::new(tags);
+        session
     }
 
-    #[test]
-    fn test_collect_user_tables_includes_user_table() {
-        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
-        let table = Arc::new(EmptyTable::new(schema));
-        let source = Arc::new(DefaultTableSource::new(table));
+    fn customers_plan() -> LogicalPlan {
+        build_scan_plan(
+            "public.customers",
+            vec![
+                ("id", DataType::Int32),
+                ("email", DataType::Utf8),
+                ("ssn", DataType::Utf8),
+            ],
+        )
+    }
 
-        let plan = LogicalPlanBuilder::scan("public.orders", source, None)
-            .unwrap()
-            .build()
+    #[tokio::test]
+    async fn test_tag_selector_masks_every_tagged_column() {
+        let mut tags = CatalogTags::default();
+        tags.set_table("public", "customers", vec!["pii".to_string()]);
+        tags.set_column("public", "customers", "id", vec!["!pii".to_string()]);
+        let session = make_tagged_session(
+            vec![make_column_mask_policy(
+                "mask-pii", 1, "*", "*", "tag:pii", "'***'",
+            )],
+            vec![],
+            tags,
+        );
+        let ctx = SessionContext::new();
+
+        let AppliedPolicies {
+            plan, had_effects, ..
+        } = apply_policies(&session, &ctx, customers_plan(), &default_vars(), None)
+            .await
             .unwrap();
 
-        let tables = collect_user_tables(&plan, "public");
-        assert_eq!(tables.len(), 1);
-        assert_eq!(tables[0], ("public".to_string(), "orders".to_string()));
+        assert!(had_effects);
+        let display = format!("{}", plan.display_indent());
+        assert!(display.contains("AS email"), "email masked: {display}");
+        assert!(display.contains("AS ssn"), "ssn masked: {display}");
+        assert!(!display.contains("AS id"), "id opted out of pii: {display}");
     }
 
-    #[test]
-    fn test_collect_user_tables_bare_reference_uses_default_schema() {
-        // Security invariant for vector #71: bare references must inherit the
-        // session's default schema, NOT empty string. Otherwise a policy
-        // targeting `schemas: ["public"]` would silently skip bare queries
-        // like `SELECT * FROM orders`.
-        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
-        let table = Arc::new(EmptyTable::new(schema));
-        let source = Arc::new(DefaultTableSource::new(table));
+    #[tokio::test]
+    async fn test_tag_selector_table_deny_by_table_tag() {
+        let mut tags = CatalogTags::default();
+        tags.set_schema("public", vec!["restricted".to_string()]);
+        let session = make_tagged_session(
+            vec![],
+            vec![make_table_deny_policy(
+                "no-restricted",
+                1,
+                "*",
+                "tag:restricted",
+            )],
+            tags,
+        );
+        let ctx = SessionContext::new();
 
-        let plan = LogicalPlanBuilder::scan("orders", source, None)
-            .unwrap()
-            .build()
-            .unwrap();
+        let result = apply_policies(&session, &ctx, customers_plan(), &default_vars(), None).await;
+        assert!(result.is_err(), "inherited schema tag must match the table");
+    }
 
-        let tables = collect_user_tables(&plan, "public");
-        assert_eq!(tables, vec![("public".to_string(), "orders".to_string())]);
+    #[tokio::test]
+    async fn test_tag_selector_column_allow_without_tagged_columns_hides_all() {
+        let session = make_tagged_session(
+            vec![make_column_allow_policy(
+                "allow-public",
+                1,
+                "public",
+                "customers",
+                &["tag:public"],
+            )],
+            vec![],
+            CatalogTags::default(),
+        );
+        let ctx = SessionContext::new();
+
+        let result = apply_policies(&session, &ctx, customers_plan(), &default_vars(), None).await;
+        assert!(
+            matches!(result, Err(PolicyError::AllColumnsDenied { .. })),
+            "an allow list matching no tagged column must fail closed"
+        );
+    }
+
+    // ---------- Tier 2: execution tests (apply_policies with MemTable + real data) ----------
+
+    /// 5-row customers table: 3 acme, 2 globex. Columns: id, org_id, name, ssn, credit_card.
+    async fn setup_customers_ctx() -> SessionContext {
+        let schema: SchemaRef = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("org_id", DataType::Utf8, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("ssn", DataType::Utf8, true),
+            Field::new("credit_card", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3, 4, 5])),
+                Arc::new(StringArray::from(vec![
+                    "acme", "acme", "acme", "globex", "globex",
+                ])),
+                Arc::new(StringArray::from(vec![
+                    "Alice", "Bob", "Charlie", "Dave", "Eve",
+                ])),
+                Arc::new(StringArray::from(vec![
+                    "123-45-6789",
+                    "234-56-7890",
+                    "345-67-8901",
+                    "456-78-9012",
+                    "567-89-0123",
+                ])),
+                Arc::new(StringArray::from(vec![
+                    "4111111111111111",
+                    "4222222222222222",
+                    "4333333333333333",
+                    "4444444444444444",
+                    "4555555555555555",
+                ])),
+            ],
+        )
+        .unwrap();
+        let ctx = SessionContext::new();
+        let table = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        ctx.register_table("customers", Arc::new(table)).unwrap();
+        ctx
     }
 
-    #[test]
-    fn test_collect_user_tables_skips_information_schema() {
-        let schema = Arc::new(Schema::new(vec![Field::new(
-            "table_name",
-            DataType::Utf8,
-            false,
-        )]));
-        let table = Arc::new(EmptyTable::new(schema));
-        let source = Arc::new(DefaultTableSource::new(table));
-
-        let plan = LogicalPlanBuilder::scan("information_schema.tables", source, None)
+    async fn exec_plan(ctx: &SessionContext, plan: LogicalPlan) -> Vec<RecordBatch> {
+        ctx.execute_logical_plan(plan)
+            .await
             .unwrap()
-            .build()
-            .unwrap();
+            .collect()
+            .await
+            .unwrap()
+    }
 
-        let tables = collect_user_tables(&plan, "public");
-        assert!(
-            tables.is_empty(),
-            "information_schema should be excluded: {tables:?}"
-        );
+    fn total_rows(batches: &[RecordBatch]) -> usize {
+        batches.iter().map(|b| b.num_rows()).sum()
     }
 
-    // ---------- Tier 1: plan-structure tests (apply_policies with EmptyTable) ----------
+    fn column_names(batches: &[RecordBatch]) -> Vec<String> {
+        if batches.is_empty() {
+            return vec![];
+        }
+        batches[0]
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect()
+    }
 
     #[tokio::test]
-    async fn test_row_filter_injected_below_table_scan() {
+    async fn test_exec_permit_row_filter() {
+        // row_filter "org_id = 'acme'" → only 3 of 5 rows returned.
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
             vec![make_row_filter_policy(
                 "p1",
                 1,
-                "public",
-                "orders",
-                "status = 'active'",
+                "*",
+                "customers",
+                "org_id = 'acme'",
             )],
             vec![],
             "open",
             HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan(
-            "public.orders",
-            vec![("id", DataType::Int32), ("status", DataType::Utf8)],
-        );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
-        assert_plan_contains(&result_plan, "Filter");
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(total_rows(&batches), 3, "Only acme rows expected");
     }
 
     #[tokio::test]
-    async fn test_row_filters_and_within_policy() {
-        // Two row_filter policies on the same table → AND'd together.
+    async fn test_exec_permit_column_deny() {
+        // column_access deny on ssn → output has 4 columns (not 5), ssn absent.
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![
-                make_row_filter_policy("p1", 1, "public", "orders", "status = 'active'"),
-                make_row_filter_policy("p1_b", 1, "public", "orders", "amount > 0"),
-            ],
             vec![],
+            vec![make_column_deny_policy("p1", 1, "*", "customers", &["ssn"])],
             "open",
             HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan(
-            "public.orders",
-            vec![
-                ("id", DataType::Int32),
-                ("status", DataType::Utf8),
-                ("amount", DataType::Int64),
-            ],
-        );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
-        let display = plan_display(&result_plan);
-        // Both filter expressions should appear
-        assert!(display.contains("Filter"), "Expected Filter: {display}");
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(total_rows(&batches), 5);
+        let cols = column_names(&batches);
+        assert!(
+            !cols.contains(&"ssn".to_string()),
+            "ssn should not appear: {cols:?}"
+        );
+        assert_eq!(cols.len(), 4, "Expected 4 columns: {cols:?}");
     }
 
     #[tokio::test]
-    async fn test_row_filters_and_across_policies() {
-        // Same table filtered by two permit policies → AND'd together (intersection).
+    async fn test_exec_deny_row_filter_rejects() {
+        // table_deny on matching table → error returned.
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![
-                make_row_filter_policy("p1", 1, "public", "orders", "org = 'acme'"),
-                make_row_filter_policy("p2", 2, "public", "orders", "org = 'globex'"),
-            ],
             vec![],
+            vec![make_table_deny_policy("deny_p", 1, "*", "customers")],
             "open",
             HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan(
-            "public.orders",
-            vec![("id", DataType::Int32), ("org", DataType::Utf8)],
-        );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
 
-        assert!(had_effects);
-        assert_plan_contains(&result_plan, "Filter");
-        // AND semantics: both filter values appear in the expression (ANDed together).
-        let display = plan_display(&result_plan);
         assert!(
-            display.contains("acme") && display.contains("globex"),
-            "Expected AND filter with both orgs: {display}"
+            matches!(result, Err(PolicyError::DeniedByPolicy { .. })),
+            "Expected DeniedByPolicy: {result:?}"
         );
     }
 
     #[tokio::test]
-    async fn test_column_deny_strips_column() {
-        let session = make_session(
-            vec![],
-            vec![make_column_deny_policy(
-                "p1",
-                1,
-                "public",
-                "customers",
-                &["ssn"],
-            )],
-            "open",
-            HashMap::new(),
-        );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan(
-            "public.customers",
-            vec![
-                ("id", DataType::Int32),
-                ("name", DataType::Utf8),
-                ("ssn", DataType::Utf8),
-            ],
-        );
+    async fn test_exec_policy_required_no_permit_empty() {
+        // policy_required + no permit → lit(false) filter → 0 rows returned.
+        let ctx = setup_customers_ctx().await;
+        let session = make_session(vec![], vec![], "policy_required", HashMap::new());
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
-        // ssn should be stripped from the projection
-        let display = plan_display(&result_plan);
-        assert!(!display.contains("ssn"), "ssn should be denied: {display}");
-        assert!(display.contains("name"), "name should remain: {display}");
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(
+            total_rows(&batches),
+            0,
+            "No rows expected with policy_required + no permit"
+        );
     }
 
     #[tokio::test]
-    async fn test_column_deny_all_columns_error() {
+    async fn test_exec_policy_required_with_permit_normal() {
+        // policy_required + permit with row_filter only → 0 rows (zero-trust: row_filter alone
+        // doesn't grant access; only column_access "allow" does).
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![],
-            vec![make_column_deny_policy(
+            vec![make_row_filter_policy(
                 "p1",
                 1,
-                "public",
+                "*",
                 "customers",
-                &["id", "name"],
+                "org_id = 'acme'",
             )],
-            "open",
+            vec![],
+            "policy_required",
             HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan(
-            "public.customers",
-            vec![("id", DataType::Int32), ("name", DataType::Utf8)],
-        );
 
-        let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
-        assert!(
-            matches!(result, Err(PolicyError::AllColumnsDenied { .. })),
-            "Expected AllColumnsDenied: {result:?}"
+        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(
+            total_rows(&batches),
+            0,
+            "row_filter alone does not grant access in policy_required mode"
         );
     }
 
     #[tokio::test]
-    async fn test_deny_policy_row_filter_rejects() {
-        // table_deny on matching table → short-circuit error.
+    async fn test_exec_two_permits_row_filter_and() {
+        // Policy A: org = 'acme', Policy B: org = 'globex' → AND → 0 rows (disjoint sets).
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
+            vec![
+                make_row_filter_policy("p_acme", 1, "*", "customers", "org_id = 'acme'"),
+                make_row_filter_policy("p_globex", 2, "*", "customers", "org_id = 'globex'"),
+            ],
             vec![],
-            vec![make_table_deny_policy("deny_p", 1, "public", "orders")],
             "open",
             HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
 
-        let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
-        assert!(
-            matches!(result, Err(PolicyError::DeniedByPolicy { .. })),
-            "Expected DeniedByPolicy: {result:?}"
+        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(
+            total_rows(&batches),
+            0,
+            "AND semantics: disjoint filters produce 0 rows"
         );
     }
 
     #[tokio::test]
-    async fn test_deny_policy_row_filter_no_match() {
-        // table_deny on a DIFFERENT table → no error.
+    async fn test_exec_two_permits_row_filter_and_overlapping() {
+        // Policy A: org_id = 'acme' (rows 1,2,3).
+        // Policy B: name != 'Charlie' (rows 1,2,4,5).
+        // AND intersection: acme rows where name != 'Charlie' → rows 1 (Alice), 2 (Bob) → 2 rows.
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
+            vec![
+                make_row_filter_policy("p_acme", 1, "*", "customers", "org_id = 'acme'"),
+                make_row_filter_policy("p_not_charlie", 2, "*", "customers", "name != 'Charlie'"),
+            ],
             vec![],
-            vec![make_table_deny_policy("deny_p", 1, "public", "users")],
             "open",
             HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        // Query is on "orders", deny is on "users" → should pass through
-        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
 
-        let (_, had_effects, _) = apply_policies(&session, &ctx, plan, &default_vars(), None)
+        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
             .await
             .unwrap();
-        assert!(!had_effects, "No effects expected when deny doesn't match");
+
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(
+            total_rows(&batches),
+            2,
+            "AND intersection: acme AND not-Charlie → Alice + Bob only"
+        );
     }
 
-    #[tokio::test]
-    async fn test_policy_required_no_permit_false_filter() {
-        // access_mode = "policy_required" with no permit → lit(false) injected.
-        let session = make_session(vec![], vec![], "policy_required", HashMap::new());
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
+    /// Three permits of one type reaching the user through each scope: all users
+    /// and a role at priority 10, and the user's own assignment at priority 50.
+    fn three_scopes(
+        make: impl Fn(&str, i32, &str) -> ResolvedPolicy,
+        [everyone, analysts, own]: [&str; 3],
+    ) -> Vec<ResolvedPolicy> {
+        [
+            ("everyone", 10, AssignmentScope::All, everyone),
+            ("analysts", 10, AssignmentScope::Role, analysts),
+            ("own", 50, AssignmentScope::User, own),
+        ]
+        .into_iter()
+        .map(|(name, priority, scope, definition)| ResolvedPolicy {
+            scope,
+            ..make(name, priority, definition)
+        })
+        .collect()
+    }
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
+    #[tokio::test]
+    async fn test_exec_row_filter_combining_strategies_across_scopes() {
+        // everyone: acme (1,2,3); analysts: not Charlie (1,2,4,5); own: id = 3.
+        let ctx = setup_customers_ctx().await;
+        let policies = three_scopes(
+            |name, priority, filter| {
+                make_row_filter_policy(name, priority, "*", "customers", filter)
+            },
+            ["org_id = 'acme'", "name != 'Charlie'", "id = 3"],
+        );
+        for (strategy, names, applied, overridden) in [
+            (
+                CombineStrategy::Intersection,
+                vec![],
+                vec!["analysts", "everyone", "own"],
+                vec![],
+            ),
+            (
+                CombineStrategy::Union,
+                vec!["Alice", "Bob", "Charlie", "Dave", "Eve"],
+                vec!["analysts", "everyone", "own"],
+                vec![],
+            ),
+            // Priority 10 ties; the role assignment is more specific than all users.
+            (
+                CombineStrategy::FirstMatch,
+                vec!["Alice", "Bob", "Dave", "Eve"],
+                vec!["analysts"],
+                vec!["everyone", "own"],
+            ),
+            // The user's own policy replaces the role and all-users ones.
+            (
+                CombineStrategy::MostSpecific,
+                vec!["Charlie"],
+                vec!["own"],
+                vec!["analysts", "everyone"],
+            ),
+        ] {
+            let mut session = make_session(policies.clone(), vec![], "open", HashMap::new());
+            session.combining.row_filter = strategy;
+            let plan = ctx
+                .sql("SELECT name FROM customers ORDER BY id")
+                .await
+                .unwrap()
+                .logical_plan()
+                .clone();
+            let AppliedPolicies {
+                plan: result_plan,
+                policy_conflicts: conflicts,
+                ..
+            } = apply_policies(&session, &ctx, plan, &default_vars(), None)
                 .await
                 .unwrap();
-
-        assert!(had_effects);
-        let display = plan_display(&result_plan);
-        assert!(
-            display.contains("false"),
-            "Expected lit(false) filter: {display}"
-        );
+            let batches = exec_plan(&ctx, result_plan).await;
+            let got: Vec<String> = string_column(&batches, "name")
+                .into_iter()
+                .flatten()
+                .collect();
+            assert_eq!(got, names, "{strategy}");
+            assert_eq!(conflicts.len(), 1, "{strategy}");
+            assert_eq!(conflicts[0].policy_type, PolicyType::RowFilter);
+            assert_eq!(conflicts[0].strategy, strategy);
+            assert_eq!(conflicts[0].applied, applied, "{strategy}");
+            assert_eq!(conflicts[0].overridden, overridden, "{strategy}");
+        }
     }
 
     #[tokio::test]
-    async fn test_policy_required_with_permit_normal() {
-        // access_mode = "policy_required" with row_filter only → lit(false) injected.
-        // row_filter does not grant table access (zero-trust model); only column_access
-        // "allow" grants access. Without it the table is blocked.
-        let session = make_session(
-            vec![make_row_filter_policy(
-                "p1", 1, "public", "orders", "id > 0",
-            )],
-            vec![],
-            "policy_required",
-            HashMap::new(),
+    async fn test_exec_column_mask_combining_strategies_across_scopes() {
+        let ctx = setup_customers_ctx().await;
+        let policies = three_scopes(
+            |name, priority, mask| {
+                make_column_mask_policy(name, priority, "*", "customers", "ssn", mask)
+            },
+            ["'ALL'", "'ROLE'", "'USER'"],
         );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
-
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
+        for (strategy, expected, winner) in [
+            (CombineStrategy::FirstMatch, Some("ROLE"), vec!["analysts"]),
+            (CombineStrategy::MostSpecific, Some("USER"), vec!["own"]),
+            // Masks that disagree hide the value entirely.
+            (
+                CombineStrategy::MostRestrictive,
+                None,
+                vec!["analysts", "everyone", "own"],
+            ),
+        ] {
+            let mut session = make_session(policies.clone(), vec![], "open", HashMap::new());
+            session.combining.column_mask = strategy;
+            let plan = ctx
+                .sql("SELECT ssn FROM customers")
+                .await
+                .unwrap()
+                .logical_plan()
+                .clone();
+            let AppliedPolicies {
+                plan: result_plan,
+                policy_conflicts: conflicts,
+                ..
+            } = apply_policies(&session, &ctx, plan, &default_vars(), None)
                 .await
                 .unwrap();
+            let batches = exec_plan(&ctx, result_plan).await;
+            let ssn = string_column(&batches, "ssn");
+            assert_eq!(ssn.len(), 5);
+            assert!(
+                ssn.iter().all(|v| v.as_deref() == expected),
+                "{strategy}: {ssn:?}"
+            );
+            assert_eq!(conflicts.len(), 1, "{strategy}");
+            assert!(
+                conflicts[0].target.ends_with("customers.ssn"),
+                "{:?}",
+                conflicts[0]
+            );
+            assert_eq!(conflicts[0].applied, winner, "{strategy}");
+        }
 
-        assert!(had_effects);
-        let display = plan_display(&result_plan);
-        // lit(false) injected because row_filter alone doesn't grant access
+        // Identical masks agree, so most_restrictive keeps them.
+        let same = three_scopes(
+            |name, priority, mask| {
+                make_column_mask_policy(name, priority, "*", "customers", "ssn", mask)
+            },
+            ["'***'", "'***'", "'***'"],
+        );
+        let mut session = make_session(same, vec![], "open", HashMap::new());
+        session.combining.column_mask = CombineStrategy::MostRestrictive;
+        let plan = ctx
+            .sql("SELECT ssn FROM customers")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+        let batches = exec_plan(&ctx, result_plan).await;
         assert!(
-            display.contains("false"),
-            "Expected lit(false) filter (row_filter alone does not grant access): {display}"
+            string_column(&batches, "ssn")
+                .iter()
+                .all(|v| v.as_deref() == Some("***"))
         );
     }
 
     #[tokio::test]
-    async fn test_wildcard_schema_matches_all() {
-        // Policy with schema: "*" matches any schema name.
+    async fn test_exec_permit_column_mask() {
+        // column_mask with a literal → SSN shows 'REDACTED' instead of actual value.
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![make_row_filter_policy("p1", 1, "*", "orders", "id > 0")],
+            vec![make_column_mask_policy(
+                "p1",
+                1,
+                "*",
+                "customers",
+                "ssn",
+                "'REDACTED'",
+            )],
             vec![],
             "open",
             HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan("any_schema.orders", vec![("id", DataType::Int32)]);
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
-        assert_plan_contains(&result_plan, "Filter");
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(total_rows(&batches), 5);
+        let cols = column_names(&batches);
+        assert!(
+            cols.contains(&"ssn".to_string()),
+            "ssn should be present (masked, not denied): {cols:?}"
+        );
+        // Verify all SSN values are the mask value, not original data.
+        let ssn_idx = batches[0].schema().index_of("ssn").unwrap();
+        let ssn_array = batches[0]
+            .column(ssn_idx)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        for i in 0..ssn_array.len() {
+            let val = ssn_array.value(i);
+            assert_eq!(val, "REDACTED", "SSN row {i} should be masked, got: {val}");
+        }
     }
 
     #[tokio::test]
-    async fn test_wildcard_table_matches_all() {
-        // Policy with table: "*" matches any table in the schema.
+    async fn test_exec_column_mask_with_br_udf() {
+        // br_* masking UDFs registered on the session are usable from column_mask.
+        let ctx = setup_customers_ctx().await;
+        crate::masking::register_udfs(&ctx, &crate::masking::MaskingKeyring::new([0u8; 32]));
         let session = make_session(
-            vec![make_row_filter_policy("p1", 1, "public", "*", "id > 0")],
+            vec![make_column_mask_policy(
+                "p1",
+                1,
+                "*",
+                "customers",
+                "ssn",
+                "br_mask_partial({col}, 0, 4)",
+            )],
             vec![],
             "open",
             HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan("public.anything", vec![("id", DataType::Int32)]);
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let base_plan = ctx.sql("SELECT ssn FROM customers").await.unwrap();
+        let plan = base_plan.logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
-        assert_plan_contains(&result_plan, "Filter");
+        let batches = exec_plan(&ctx, result_plan).await;
+        let ssn_array = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        for i in 0..ssn_array.len() {
+            let val = ssn_array.value(i);
+            assert!(
+                val.starts_with("*******") && !val.ends_with('*'),
+                "SSN row {i} should keep only the last 4 characters, got: {val}"
+            );
+        }
     }
 
-    #[tokio::test]
-    async fn test_schema_alias_resolved() {
-        // df schema alias "sales" maps to upstream "public"; policy targets "public".
-        let mut df_to_upstream = HashMap::new();
-        df_to_upstream.insert("sales".to_string(), "public".to_string());
+    fn make_aggregate_only_policy(name: &str, table: &str, min_group_size: i64) -> ResolvedPolicy {
+        ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type: PolicyType::AggregateOnly,
+            version: 1,
+            priority: 1,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec!["*".to_string()],
+                tables: vec![table.to_string()],
+                columns: None,
+            }],
+            definition: Some(serde_json::json!({"min_group_size": min_group_size})),
+            decision_function: None,
+            template: None,
+        }
+    }
 
+    #[tokio::test]
+    async fn test_exec_aggregate_only_suppresses_small_groups() {
+        // k = 3: acme (3 rows) survives, globex (2 rows) is suppressed.
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![make_row_filter_policy(
-                "p1", 1, "public", "orders", "id > 0",
-            )],
+            vec![make_aggregate_only_policy("agg", "customers", 3)],
             vec![],
             "open",
-            df_to_upstream,
+            HashMap::new(),
         );
-        let ctx = SessionContext::new();
-        // Plan uses "sales" alias, which resolves to upstream "public"
-        let plan = build_scan_plan("sales.orders", vec![("id", DataType::Int32)]);
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let plan = ctx
+            .sql("SELECT org_id, count(*) AS n FROM customers GROUP BY org_id")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
-        assert_plan_contains(&result_plan, "Filter");
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(total_rows(&batches), 1);
+        assert_eq!(column_names(&batches), vec!["org_id", "n"]);
+        let org = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(org.value(0), "acme");
+
+        // A global aggregate over fewer than k rows returns nothing.
+        let plan = ctx
+            .sql("SELECT count(*) FROM customers WHERE org_id = 'globex'")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+        assert_eq!(total_rows(&exec_plan(&ctx, result_plan).await), 0);
     }
 
     #[tokio::test]
-    async fn test_deny_overrides_mask() {
-        // Column is both denied (via deny policy) and would be masked (via permit policy);
-        // deny takes priority — column is removed, mask expression never applied.
+    async fn test_aggregate_only_rejects_row_level_reads() {
+        let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![make_column_mask_policy(
-                "p1",
-                1,
-                "public",
-                "customers",
-                "ssn",
-                "'***'",
-            )],
-            vec![make_column_deny_policy(
-                "deny_p",
-                2,
-                "public",
-                "customers",
-                &["ssn"],
-            )],
+            vec![make_aggregate_only_policy("agg", "customers", 3)],
+            vec![],
             "open",
             HashMap::new(),
         );
-        // Register table so parse_mask_expr can resolve it
-        let schema: SchemaRef = Arc::new(Schema::new(vec![
-            Field::new("id", DataType::Int32, true),
-            Field::new("ssn", DataType::Utf8, true),
-        ]));
-        let ctx = SessionContext::new();
-        let empty = RecordBatch::new_empty(schema.clone());
-        let table = MemTable::try_new(schema, vec![vec![empty]]).unwrap();
-        ctx.register_table("customers", Arc::new(table)).unwrap();
-
-        let plan = build_scan_plan(
-            "public.customers",
-            vec![("id", DataType::Int32), ("ssn", DataType::Utf8)],
-        );
-
-        let (result_plan, _, _) = apply_policies(&session, &ctx, plan, &default_vars(), None)
-            .await
-            .unwrap();
 
-        let display = plan_display(&result_plan);
-        assert!(
-            !display.contains("ssn"),
-            "ssn should be denied (not masked): {display}"
-        );
+        for sql in [
+            "SELECT * FROM customers",
+            "SELECT name, count(*) OVER () FROM customers",
+            "SELECT string_agg(name, ',') FROM customers",
+            "SELECT count(*) FROM customers WHERE id IN (SELECT id FROM customers WHERE ssn = '123-45-6789')",
+            "SELECT c.name, x.n FROM customers c CROSS JOIN (SELECT count(*) AS n FROM customers) x",
+            "SELECT a.name, count(*) FROM customers a CROSS JOIN customers b GROUP BY a.name",
+            "SELECT name, count(*) FROM customers CROSS JOIN generate_series(1, 10) GROUP BY name",
+            "SELECT name, count(*) FROM (SELECT name FROM customers UNION ALL SELECT name FROM customers) GROUP BY name",
+        ] {
+            let plan = ctx.sql(sql).await.unwrap().logical_plan().clone();
+            let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
+            assert!(
+                matches!(result, Err(PolicyError::AggregateRequired { .. })),
+                "{sql} should be rejected"
+            );
+        }
+
+        // Aggregates over an aggregate subquery are fine.
+        let plan = ctx
+            .sql("SELECT max(n) FROM (SELECT org_id, count(*) AS n FROM customers GROUP BY org_id)")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
         assert!(
-            !display.contains("***"),
-            "mask expression must not appear when column is denied: {display}"
+            apply_policies(&session, &ctx, plan, &default_vars(), None)
+                .await
+                .is_ok()
         );
     }
 
     #[tokio::test]
-    async fn test_no_policies_no_effects() {
-        // No policies at all → plan is returned unchanged.
-        let session = make_session(vec![], vec![], "open", HashMap::new());
-        let ctx = SessionContext::new();
-        let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
+    async fn test_join_only_column_is_usable_as_a_join_key_only() {
+        let ctx = setup_customers_ctx().await;
+        let policy = ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: "blind_ssn".to_string(),
+            policy_type: PolicyType::JoinOnly,
+            version: 1,
+            priority: 1,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec!["*".to_string()],
+                tables: vec!["customers".to_string()],
+                columns: Some(vec!["ssn".to_string()]),
+            }],
+            definition: None,
+            decision_function: None,
+            template: None,
+        };
+        let session = make_session(vec![policy], vec![], "open", HashMap::new());
 
-        let (_, had_effects, _) = apply_policies(&session, &ctx, plan, &default_vars(), None)
+        let plan = ctx
+            .sql("SELECT a.name FROM customers a JOIN customers b ON a.ssn = b.ssn ORDER BY a.name")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
             .await
             .unwrap();
-
-        assert!(!had_effects);
+        assert_eq!(total_rows(&exec_plan(&ctx, result_plan).await), 5);
+
+        for sql in [
+            "SELECT ssn FROM customers",
+            "SELECT id FROM customers WHERE ssn = '123-45-6789'",
+            "SELECT count(DISTINCT ssn) FROM customers",
+        ] {
+            let plan = ctx.sql(sql).await.unwrap().logical_plan().clone();
+            let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
+            assert!(
+                matches!(
+                    &result,
+                    Err(PolicyError::JoinKeyExposed { policy_name, .. }) if policy_name == "blind_ssn"
+                ),
+                "{sql} should be rejected"
+            );
+        }
     }
 
-    // ---------- Tier 2: execution tests (apply_policies with MemTable + real data) ----------
-
-    /// 5-row customers table: 3 acme, 2 globex. Columns: id, org_id, name, ssn, credit_card.
-    async fn setup_customers_ctx() -> SessionContext {
-        let schema: SchemaRef = Arc::new(Schema::new(vec![
-            Field::new("id", DataType::Int32, false),
-            Field::new("org_id", DataType::Utf8, false),
-            Field::new("name", DataType::Utf8, false),
-            Field::new("ssn", DataType::Utf8, true),
-            Field::new("credit_card", DataType::Utf8, true),
-        ]));
-        let batch = RecordBatch::try_new(
-            schema.clone(),
-            vec![
-                Arc::new(Int32Array::from(vec![1, 2, 3, 4, 5])),
-                Arc::new(StringArray::from(vec![
-                    "acme", "acme", "acme", "globex", "globex",
-                ])),
-                Arc::new(StringArray::from(vec![
-                    "Alice", "Bob", "Charlie", "Dave", "Eve",
-                ])),
-                Arc::new(StringArray::from(vec![
-                    "123-45-6789",
-                    "234-56-7890",
-                    "345-67-8901",
-                    "456-78-9012",
-                    "567-89-0123",
-                ])),
-                Arc::new(StringArray::from(vec![
-                    "4111111111111111",
-                    "4222222222222222",
-                    "4333333333333333",
-                    "4444444444444444",
-                    "4555555555555555",
-                ])),
-            ],
-        )
-        .unwrap();
-        let ctx = SessionContext::new();
-        let table = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
-        ctx.register_table("customers", Arc::new(table)).unwrap();
-        ctx
+    fn make_dp_policy(name: &str, table: &str, definition: serde_json::Value) -> ResolvedPolicy {
+        ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type: PolicyType::DifferentialPrivacy,
+            version: 1,
+            priority: 1,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec!["*".to_string()],
+                tables: vec![table.to_string()],
+                columns: None,
+            }],
+            definition: Some(definition),
+            decision_function: None,
+            template: None,
+        }
     }
 
-    async fn exec_plan(ctx: &SessionContext, plan: LogicalPlan) -> Vec<RecordBatch> {
-        ctx.execute_logical_plan(plan)
+    #[tokio::test]
+    async fn test_exec_differential_privacy_noises_aggregates() {
+        let ctx = setup_customers_ctx().await;
+        let session = make_session(
+            vec![make_dp_policy(
+                "dp",
+                "customers",
+                serde_json::json!({"epsilon": 5.0, "budget": 100.0, "bounds": {"id": [0, 10]}}),
+            )],
+            vec![],
+            "open",
+            HashMap::new(),
+        );
+
+        let plan = ctx
+            .sql("SELECT org_id, count(*) AS n, sum(id) AS total, avg(id) FROM customers GROUP BY org_id ORDER BY org_id")
             .await
             .unwrap()
-            .collect()
+            .logical_plan()
+            .clone();
+        let original_schema = plan.schema().clone();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            privacy_charge: charge,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
             .await
-            .unwrap()
-    }
+            .unwrap();
 
-    fn total_rows(batches: &[RecordBatch]) -> usize {
-        batches.iter().map(|b| b.num_rows()).sum()
-    }
+        assert!(had_effects);
+        // One charge of epsilon per released aggregate.
+        assert_eq!(
+            charge,
+            Some(PrivacyCharge {
+                epsilon: 15.0,
+                budget: 100.0
+            })
+        );
+        let sql = Unparser::new(&BetweenRowsPostgresDialect)
+            .plan_to_sql(&result_plan)
+            .unwrap()
+            .to_string();
+        assert!(sql.contains(crate::privacy::NOISE_FUNCTION), "{sql}");
 
-    fn column_names(batches: &[RecordBatch]) -> Vec<String> {
-        if batches.is_empty() {
-            return vec![];
-        }
-        batches[0]
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(total_rows(&batches), 2);
+        assert_eq!(
+            batches[0].schema().fields().len(),
+            original_schema.fields().len()
+        );
+        for (field, expected) in batches[0]
             .schema()
             .fields()
             .iter()
-            .map(|f| f.name().clone())
-            .collect()
+            .zip(original_schema.fields())
+        {
+            assert_eq!(field.name(), expected.name());
+            assert_eq!(field.data_type(), expected.data_type());
+        }
+        // Laplace scale is 0.2 for counts and 2 for sums: values stay near the truth.
+        let counts = batches[0]
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert!((counts.value(0) - 3).abs() <= 3, "{}", counts.value(0));
+        let totals = batches[0]
+            .column(2)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert!((totals.value(0) - 6).abs() <= 40, "{}", totals.value(0));
     }
 
     #[tokio::test]
-    async fn test_exec_permit_row_filter() {
-        // row_filter "org_id = 'acme'" → only 3 of 5 rows returned.
+    async fn test_differential_privacy_rejects_unsupported_aggregates() {
         let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![make_row_filter_policy(
-                "p1",
-                1,
-                "*",
+            vec![make_dp_policy(
+                "dp",
                 "customers",
-                "org_id = 'acme'",
+                serde_json::json!({"epsilon": 1.0, "budget": 10.0, "bounds": {"id": [0, 10]}}),
             )],
             vec![],
             "open",
             HashMap::new(),
         );
 
-        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
-        let plan = base_plan.logical_plan().clone();
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        for sql in [
+            "SELECT * FROM customers",
+            "SELECT max(id) FROM customers",
+            "SELECT count(DISTINCT org_id) FROM customers",
+            "SELECT sum(length(name)) FROM customers",
+            "SELECT count(*) FROM customers a JOIN customers b ON a.org_id = b.org_id",
+            "SELECT sum(id) FROM (SELECT id FROM customers UNION ALL SELECT id FROM customers)",
+        ] {
+            let plan = ctx.sql(sql).await.unwrap().logical_plan().clone();
+            let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
+            assert!(
+                matches!(result, Err(PolicyError::AggregateRequired { .. })),
+                "{sql} should be rejected"
+            );
+        }
 
-        assert!(had_effects);
-        let batches = exec_plan(&ctx, result_plan).await;
-        assert_eq!(total_rows(&batches), 3, "Only acme rows expected");
+        // Post-processing a noised aggregate is free and allowed.
+        let plan = ctx
+            .sql("SELECT max(n) FROM (SELECT org_id, count(*) AS n FROM customers GROUP BY org_id)")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            privacy_charge: charge,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+        assert_eq!(charge.map(|c| c.epsilon), Some(1.0));
+    }
+
+    fn make_result_limit_policy(
+        name: &str,
+        table: &str,
+        definition: serde_json::Value,
+    ) -> ResolvedPolicy {
+        ResolvedPolicy {
+            policy_type: PolicyType::ResultLimit,
+            ..make_dp_policy(name, table, definition)
+        }
     }
 
     #[tokio::test]
-    async fn test_exec_permit_column_deny() {
-        // column_access deny on ssn → output has 4 columns (not 5), ssn absent.
+    async fn test_exec_result_limit_caps_rows() {
         let ctx = setup_customers_ctx().await;
         let session = make_session(
+            vec![
+                make_result_limit_policy(
+                    "customers_cap",
+                    "customers",
+                    serde_json::json!({"max_rows": 2, "max_bytes": 1048576}),
+                ),
+                make_result_limit_policy(
+                    "orders_cap",
+                    "orders",
+                    serde_json::json!({"max_rows": 1, "on_exceed": "deny"}),
+                ),
+            ],
             vec![],
-            vec![make_column_deny_policy("p1", 1, "*", "customers", &["ssn"])],
             "open",
             HashMap::new(),
         );
 
-        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
-        let plan = base_plan.logical_plan().clone();
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
+        let plan = ctx
+            .sql("SELECT id FROM customers ORDER BY id")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan,
+            result_limit: limit,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+        // Only the policy targeting a table the query reads applies.
+        let limit = limit.expect("customers_cap matches");
+        assert_eq!(limit.max_rows.as_ref().unwrap().limit, 2);
+        assert_eq!(
+            limit.max_rows.as_ref().unwrap().policy_name,
+            "customers_cap"
+        );
+        assert_eq!(limit.on_exceed, crate::policy_match::LimitAction::Truncate);
+
+        let limited = crate::result_limit::limit_plan(result_plan, &limit).unwrap();
+        let batches = ctx
+            .execute_logical_plan(limited)
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 2);
+    }
+
+    #[tokio::test]
+    async fn test_query_guard_blocks_or_warns() {
+        let ctx = setup_customers_ctx().await;
+        let guard = ResolvedPolicy {
+            policy_type: PolicyType::QueryGuard,
+            ..make_dp_policy(
+                "customers_guard",
+                "customers",
+                serde_json::json!({"require_predicate_on": ["org_id"]}),
+            )
+        };
+        let check = |session: SessionDataClone, sql: &'static str| {
+            let ctx = ctx.clone();
+            async move {
+                let statement = Parser::parse_sql(&PostgreSqlDialect {}, sql)
+                    .unwrap()
+                    .remove(0);
+                let plan = ctx.sql(sql).await.unwrap().logical_plan().clone();
+                check_query_guards(&session, &statement, &plan, "public", None).await
+            }
+        };
+
+        let enforced = || make_session(vec![guard.clone()], vec![], "open", HashMap::new());
+        let err = check(enforced(), "SELECT id FROM customers")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, PolicyError::QueryBlocked { violation, .. } if violation.rule == "require_predicate_on"),
+            "{err}"
+        );
+        let (_, outcomes, notices) =
+            check(enforced(), "SELECT id FROM customers WHERE org_id = 'acme'")
                 .await
                 .unwrap();
+        assert!(outcomes.is_empty() && notices.is_empty());
 
-        assert!(had_effects);
-        let batches = exec_plan(&ctx, result_plan).await;
-        assert_eq!(total_rows(&batches), 5);
-        let cols = column_names(&batches);
+        let shadow = make_shadow_session(vec![guard]);
+        let (_, outcomes, notices) = check(shadow, "SELECT id FROM customers").await.unwrap();
+        assert_eq!(outcomes[0].outcome, "would_block_query");
+        assert_eq!(outcomes[0].tables, vec!["public.customers".to_string()]);
         assert!(
-            !cols.contains(&"ssn".to_string()),
-            "ssn should not appear: {cols:?}"
+            notices[0].contains("(shadow) would block"),
+            "{}",
+            notices[0]
         );
-        assert_eq!(cols.len(), 4, "Expected 4 columns: {cols:?}");
+    }
+
+    fn make_write_policy(
+        name: &str,
+        policy_type: PolicyType,
+        table: &str,
+        columns: &[&str],
+        operations: &[&str],
+    ) -> ResolvedPolicy {
+        ResolvedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type,
+            version: 1,
+            priority: 100,
+            scope: AssignmentScope::All,
+            targets: vec![TargetEntry {
+                schemas: vec!["public".to_string()],
+                tables: vec![table.to_string()],
+                columns: Some(columns.iter().map(|c| c.to_string()).collect()),
+            }],
+            definition: (policy_type == PolicyType::WriteAllow)
+                .then(|| serde_json::json!({"operations": operations})),
+            decision_function: None,
+            template: None,
+        }
     }
 
     #[tokio::test]
-    async fn test_exec_deny_row_filter_rejects() {
-        // table_deny on matching table → error returned.
-        let ctx = setup_customers_ctx().await;
-        let session = make_session(
-            vec![],
-            vec![make_table_deny_policy("deny_p", 1, "*", "customers")],
-            "open",
-            HashMap::new(),
+    async fn test_write_grants_cover_written_columns() {
+        let schema: SchemaRef = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("city", DataType::Utf8, true),
+            Field::new("ssn", DataType::Utf8, true),
+        ]));
+        let key = ("public".to_string(), "customers".to_string());
+        let allow = make_write_policy(
+            "support_edits",
+            PolicyType::WriteAllow,
+            "customers",
+            &["city", "ssn"],
+            &["update"],
+        );
+        let deny = make_write_policy(
+            "protect_ssn",
+            PolicyType::WriteDeny,
+            "customers",
+            &["ssn"],
+            &[],
+        );
+        let check = |session: SessionDataClone, operation, columns: &[&str]| {
+            let schema = schema.clone();
+            let key = key.clone();
+            let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+            async move { check_write_grants(&session, &key, &schema, operation, &columns, None).await }
+        };
+
+        let session = || {
+            make_session(
+                vec![allow.clone()],
+                vec![deny.clone()],
+                "open",
+                HashMap::new(),
+            )
+        };
+        assert!(
+            check(session(), WriteOperation::Update, &["city"])
+                .await
+                .is_ok()
+        );
+        let err = check(session(), WriteOperation::Update, &["city", "ssn"])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, PolicyError::WriteDenied { policy_name, .. } if policy_name == "protect_ssn"),
+            "{err}"
+        );
+        // DELETE removes the protected column along with the row.
+        let err = check(session(), WriteOperation::Delete, &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PolicyError::WriteDenied { .. }), "{err}");
+
+        let session = || make_session(vec![allow.clone()], vec![], "open", HashMap::new());
+        let err = check(session(), WriteOperation::Update, &["id"])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("UPDATE of id"), "{err}");
+        let err = check(session(), WriteOperation::Insert, &["city"])
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("no write_allow policy grants INSERT on public.customers"),
+            "{err}"
         );
+    }
 
-        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
-        let plan = base_plan.logical_plan().clone();
-        let result = apply_policies(&session, &ctx, plan, &default_vars(), None).await;
+    #[tokio::test]
+    async fn test_shadow_write_policies_record_outcomes() {
+        let schema: SchemaRef = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("city", DataType::Utf8, true),
+        ]));
+        let key = ("public".to_string(), "customers".to_string());
+        let allow = make_write_policy(
+            "support_edits",
+            PolicyType::WriteAllow,
+            "customers",
+            &["city"],
+            &["update"],
+        );
+        let shadow_deny = make_write_policy(
+            "freeze_city",
+            PolicyType::WriteDeny,
+            "customers",
+            &["city"],
+            &[],
+        );
+        let mut session = make_session(vec![allow], vec![], "open", HashMap::new());
+        session.shadow_policies = vec![shadow_deny];
+
+        let (_, outcomes) = check_write_grants(
+            &session,
+            &key,
+            &schema,
+            WriteOperation::Update,
+            &["city".to_string()],
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].outcome, "would_deny_write");
+        assert_eq!(
+            outcomes[0].columns,
+            vec!["public.customers.city".to_string()]
+        );
+        assert_eq!(outcomes[0].expression.as_deref(), Some("UPDATE"));
+    }
 
-        assert!(
-            matches!(result, Err(PolicyError::DeniedByPolicy { .. })),
-            "Expected DeniedByPolicy: {result:?}"
+    #[tokio::test]
+    async fn test_write_audit_images_are_masked() {
+        let ctx = setup_customers_ctx().await;
+        let table_schema = ctx.table_provider("customers").await.unwrap().schema();
+        let columns = vec!["id".to_string(), "name".to_string(), "ssn".to_string()];
+        let masks = vec![(
+            "ssn".to_string(),
+            datafusion::functions::string::expr_fn::concat(vec![
+                lit("***-"),
+                datafusion::functions::unicode::expr_fn::right(col("customers.ssn"), lit(4)),
+            ]),
+        )];
+        let images = vec![
+            serde_json::json!({"id": 1, "name": "Alice", "ssn": "123-45-6789"}),
+            serde_json::json!({"id": 2, "name": "Bob", "ssn": null}),
+        ];
+
+        let masked = mask_row_images(&ctx, &table_schema, &columns, &masks, &images)
+            .await
+            .unwrap();
+        assert_eq!(
+            masked,
+            vec![
+                serde_json::json!({"id": 1, "name": "Alice", "ssn": "***-6789"}),
+                serde_json::json!({"id": 2, "name": "Bob", "ssn": "***-"}),
+            ]
         );
     }
 
     #[tokio::test]
-    async fn test_exec_policy_required_no_permit_empty() {
-        // policy_required + no permit → lit(false) filter → 0 rows returned.
+    async fn test_exec_column_mask_with_row_filter() {
+        // row_filter "org_id = 'acme'" (3 rows) + column_mask on ssn → 3 rows with masked SSN.
         let ctx = setup_customers_ctx().await;
-        let session = make_session(vec![], vec![], "policy_required", HashMap::new());
+        let session = make_session(
+            vec![
+                make_row_filter_policy("p1", 1, "*", "customers", "org_id = 'acme'"),
+                make_column_mask_policy("p1_mask", 1, "*", "customers", "ssn", "'***'"),
+            ],
+            vec![],
+            "open",
+            HashMap::new(),
+        );
 
         let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
         let plan = base_plan.logical_plan().clone();
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let batches = exec_plan(&ctx, result_plan).await;
-        assert_eq!(
-            total_rows(&batches),
-            0,
-            "No rows expected with policy_required + no permit"
-        );
+        assert_eq!(total_rows(&batches), 3, "Only acme rows expected");
+        let ssn_idx = batches[0].schema().index_of("ssn").unwrap();
+        let ssn_array = batches[0]
+            .column(ssn_idx)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        for i in 0..ssn_array.len() {
+            assert_eq!(ssn_array.value(i), "***", "SSN row {i} should be masked");
+        }
+    }
+
+    fn string_column(batches: &[RecordBatch], name: &str) -> Vec<Option<String>> {
+        batches
+            .iter()
+            .flat_map(|b| {
+                let array = b
+                    .column(b.schema().index_of(name).unwrap())
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap()
+                    .clone();
+                (0..array.len())
+                    .map(|i| array.is_valid(i).then(|| array.value(i).to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
     }
 
     #[tokio::test]
-    async fn test_exec_policy_required_with_permit_normal() {
-        // policy_required + permit with row_filter only → 0 rows (zero-trust: row_filter alone
-        // doesn't grant access; only column_access "allow" does).
+    async fn test_exec_column_mask_reads_sibling_column() {
+        // The mask reads org_id, which the query does not select.
         let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![make_row_filter_policy(
-                "p1",
+            vec![make_column_mask_policy(
+                "ssn_outside_tenant",
                 1,
                 "*",
                 "customers",
-                "org_id = 'acme'",
+                "ssn",
+                "CASE WHEN org_id = {user.tenant} THEN ssn ELSE '***' END",
             )],
             vec![],
-            "policy_required",
+            "open",
             HashMap::new(),
         );
 
-        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
-        let plan = base_plan.logical_plan().clone();
-        let (result_plan, _, _) = apply_policies(&session, &ctx, plan, &default_vars(), None)
+        let plan = ctx
+            .sql("SELECT ssn FROM customers ORDER BY id")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
             .await
             .unwrap();
-
         let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(batches[0].num_columns(), 1, "org_id must not be output");
         assert_eq!(
-            total_rows(&batches),
-            0,
-            "row_filter alone does not grant access in policy_required mode"
+            string_column(&batches, "ssn"),
+            ["123-45-6789", "234-56-7890", "345-67-8901", "***", "***"]
+                .map(|v| Some(v.to_string()))
+        );
+
+        // A scan projected down to ssn is widened to read org_id, which stays
+        // out of the output.
+        let provider = ctx.table_provider("customers").await.unwrap();
+        let ssn = provider.schema().index_of("ssn").unwrap();
+        let plan = LogicalPlanBuilder::scan(
+            "customers",
+            datafusion::datasource::provider_as_source(provider),
+            Some(vec![ssn]),
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+        assert_eq!(result_plan.schema().fields().len(), 1);
+        let batches = exec_plan(&ctx, result_plan).await;
+        let masked = string_column(&batches, "ssn");
+        assert_eq!(
+            masked
+                .iter()
+                .filter(|v| v.as_deref() == Some("***"))
+                .count(),
+            2
         );
     }
 
     #[tokio::test]
-    async fn test_exec_two_permits_row_filter_and() {
-        // Policy A: org = 'acme', Policy B: org = 'globex' → AND → 0 rows (disjoint sets).
+    async fn test_exec_column_mask_on_denied_sibling_is_null() {
+        // Deny beats mask: a mask cannot read a column the user may not see.
         let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![
-                make_row_filter_policy("p_acme", 1, "*", "customers", "org_id = 'acme'"),
-                make_row_filter_policy("p_globex", 2, "*", "customers", "org_id = 'globex'"),
-            ],
-            vec![],
+            vec![make_column_mask_policy(
+                "ssn_outside_tenant",
+                1,
+                "*",
+                "customers",
+                "ssn",
+                "CASE WHEN org_id = {user.tenant} THEN ssn ELSE '***' END",
+            )],
+            vec![make_column_deny_policy(
+                "no_org",
+                1,
+                "*",
+                "customers",
+                &["org_id"],
+            )],
             "open",
             HashMap::new(),
         );
 
-        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
-        let plan = base_plan.logical_plan().clone();
-        let (result_plan, _, _) = apply_policies(&session, &ctx, plan, &default_vars(), None)
+        let plan = ctx
+            .sql("SELECT id, ssn FROM customers")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
             .await
             .unwrap();
-
         let batches = exec_plan(&ctx, result_plan).await;
-        assert_eq!(
-            total_rows(&batches),
-            0,
-            "AND semantics: disjoint filters produce 0 rows"
-        );
+        assert_eq!(total_rows(&batches), 5);
+        assert!(string_column(&batches, "ssn").iter().all(Option::is_none));
+    }
+
+    /// Register an `orgs` table beside `customers`: acme is active, globex is not.
+    fn register_orgs(ctx: &SessionContext) {
+        let schema: SchemaRef = Arc::new(Schema::new(vec![
+            Field::new("org_id", DataType::Utf8, false),
+            Field::new("active", DataType::Boolean, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["acme", "globex"])),
+                Arc::new(datafusion::arrow::array::BooleanArray::from(vec![
+                    true, false,
+                ])),
+            ],
+        )
+        .unwrap();
+        let table = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        ctx.register_table("orgs", Arc::new(table)).unwrap();
     }
 
     #[tokio::test]
-    async fn test_exec_two_permits_row_filter_and_overlapping() {
-        // Policy A: org_id = 'acme' (rows 1,2,3).
-        // Policy B: name != 'Charlie' (rows 1,2,4,5).
-        // AND intersection: acme rows where name != 'Charlie' → rows 1 (Alice), 2 (Bob) → 2 rows.
+    async fn test_exec_row_filter_datafusion_expressions() {
+        // Filters are planned by DataFusion over the table's real schema, so SQL
+        // the old hand-written converter rejected runs, subqueries included.
+        let ctx = setup_customers_ctx().await;
+        register_orgs(&ctx);
+        for (filter, expected) in [
+            ("name ILIKE 'a%'", 1),
+            ("org_id IS DISTINCT FROM 'acme'", 2),
+            ("SUBSTRING(ssn FROM 1 FOR 3) IN ('123', '456')", 2),
+            ("org_id IN (SELECT org_id FROM orgs WHERE active)", 3),
+            (
+                "EXISTS (SELECT 1 FROM orgs o WHERE o.org_id = customers.org_id AND NOT o.active)",
+                2,
+            ),
+        ] {
+            let session = make_session(
+                vec![make_row_filter_policy("p1", 1, "*", "customers", filter)],
+                vec![],
+                "open",
+                HashMap::new(),
+            );
+            let plan = ctx
+                .sql("SELECT id FROM customers")
+                .await
+                .unwrap()
+                .logical_plan()
+                .clone();
+            let AppliedPolicies {
+                plan: result_plan, ..
+            } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+                .await
+                .unwrap();
+            let batches = exec_plan(&ctx, result_plan).await;
+            assert_eq!(total_rows(&batches), expected, "{filter}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_column_mask_with_subquery() {
         let ctx = setup_customers_ctx().await;
+        register_orgs(&ctx);
         let session = make_session(
-            vec![
-                make_row_filter_policy("p_acme", 1, "*", "customers", "org_id = 'acme'"),
-                make_row_filter_policy("p_not_charlie", 2, "*", "customers", "name != 'Charlie'"),
-            ],
+            vec![make_column_mask_policy(
+                "ssn_inactive_orgs",
+                1,
+                "*",
+                "customers",
+                "ssn",
+                "CASE WHEN (SELECT bool_or(active) FROM orgs o WHERE o.org_id = customers.org_id) \
+                 THEN {col} ELSE '***' END",
+            )],
             vec![],
             "open",
             HashMap::new(),
         );
-
-        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
-        let plan = base_plan.logical_plan().clone();
-        let (result_plan, _, _) = apply_policies(&session, &ctx, plan, &default_vars(), None)
+        let plan = ctx
+            .sql("SELECT ssn FROM customers ORDER BY id")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
             .await
             .unwrap();
-
         let batches = exec_plan(&ctx, result_plan).await;
         assert_eq!(
-            total_rows(&batches),
-            2,
-            "AND intersection: acme AND not-Charlie → Alice + Bob only"
+            string_column(&batches, "ssn"),
+            ["123-45-6789", "234-56-7890", "345-67-8901", "***", "***"]
+                .map(|v| Some(v.to_string()))
         );
     }
 
     #[tokio::test]
-    async fn test_exec_permit_column_mask() {
-        // column_mask with a literal → SSN shows 'REDACTED' instead of actual value.
+    async fn test_exec_unplannable_row_filter_denies_rows() {
+        // A filter whose subquery reads a table missing from the catalog hides
+        // every row instead of being dropped.
         let ctx = setup_customers_ctx().await;
         let session = make_session(
-            vec![make_column_mask_policy(
+            vec![make_row_filter_policy(
                 "p1",
                 1,
                 "*",
                 "customers",
-                "ssn",
-                "'REDACTED'",
+                "org_id IN (SELECT org_id FROM no_such_table)",
             )],
             vec![],
             "open",
             HashMap::new(),
         );
+        let plan = ctx
+            .sql("SELECT id FROM customers")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+        let batches = exec_plan(&ctx, result_plan).await;
+        assert_eq!(total_rows(&batches), 0);
+    }
 
-        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
-        let plan = base_plan.logical_plan().clone();
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+    fn register_user_orgs(ctx: &SessionContext, rows: &[(&str, &str)]) {
+        let schema: SchemaRef = Arc::new(Schema::new(vec![
+            Field::new("username", DataType::Utf8, false),
+            Field::new("org_id", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.1))),
+            ],
+        )
+        .unwrap();
+        let table = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        ctx.deregister_table("user_orgs").unwrap();
+        ctx.register_table("user_orgs", Arc::new(table)).unwrap();
+    }
 
-        assert!(had_effects);
-        let batches = exec_plan(&ctx, result_plan).await;
-        assert_eq!(total_rows(&batches), 5);
-        let cols = column_names(&batches);
-        assert!(
-            cols.contains(&"ssn".to_string()),
-            "ssn should be present (masked, not denied): {cols:?}"
-        );
-        // Verify all SSN values are the mask value, not original data.
-        let ssn_idx = batches[0].schema().index_of("ssn").unwrap();
-        let ssn_array = batches[0]
-            .column(ssn_idx)
-            .as_any()
-            .downcast_ref::<StringArray>()
+    async fn count_with_policies(
+        ctx: &SessionContext,
+        session: &SessionDataClone,
+        sql: &str,
+    ) -> usize {
+        let plan = ctx.sql(sql).await.unwrap().logical_plan().clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(session, ctx, plan, &default_vars(), None)
+            .await
             .unwrap();
-        for i in 0..ssn_array.len() {
-            let val = ssn_array.value(i);
-            assert_eq!(val, "REDACTED", "SSN row {i} should be masked, got: {val}");
+        total_rows(&exec_plan(ctx, result_plan).await)
+    }
+
+    #[tokio::test]
+    async fn test_exec_row_filter_lookup_table_policies_apply() {
+        // A row filter reading a lookup table gets the user's policies on that
+        // table too: the subquery cannot see more than the user could.
+        let ctx = setup_customers_ctx().await;
+        register_user_orgs(
+            &ctx,
+            &[("alice", "acme"), ("alice", "globex"), ("bob", "globex")],
+        );
+        for filter in [
+            "org_id IN (SELECT org_id FROM user_orgs WHERE username = {user.username})",
+            "EXISTS (SELECT 1 FROM user_orgs u \
+             WHERE u.org_id = customers.org_id AND u.username = {user.username})",
+        ] {
+            let customers = make_row_filter_policy("p1", 1, "*", "customers", filter);
+            let session = make_session(vec![customers.clone()], vec![], "open", HashMap::new());
+            assert_eq!(
+                count_with_policies(&ctx, &session, "SELECT id FROM customers").await,
+                5,
+                "{filter}"
+            );
+
+            let lookup_filter =
+                make_row_filter_policy("p2", 1, "*", "user_orgs", "org_id <> 'globex'");
+            let session = make_session(
+                vec![customers.clone(), lookup_filter],
+                vec![],
+                "open",
+                HashMap::new(),
+            );
+            assert_eq!(
+                count_with_policies(&ctx, &session, "SELECT id FROM customers").await,
+                3,
+                "{filter}"
+            );
+
+            // A lookup table the user may not read fails closed.
+            let session = make_session(
+                vec![customers],
+                vec![make_table_deny_policy("d1", 1, "*", "user_orgs")],
+                "open",
+                HashMap::new(),
+            );
+            assert_eq!(
+                count_with_policies(&ctx, &session, "SELECT id FROM customers").await,
+                0,
+                "{filter}"
+            );
         }
     }
 
     #[tokio::test]
-    async fn test_exec_column_mask_with_row_filter() {
-        // row_filter "org_id = 'acme'" (3 rows) + column_mask on ssn → 3 rows with masked SSN.
+    async fn test_lookup_subquery_inlined_and_cached() {
         let ctx = setup_customers_ctx().await;
+        register_user_orgs(&ctx, &[("alice", "acme"), ("bob", "globex")]);
         let session = make_session(
-            vec![
-                make_row_filter_policy("p1", 1, "*", "customers", "org_id = 'acme'"),
-                make_column_mask_policy("p1_mask", 1, "*", "customers", "ssn", "'***'"),
-            ],
+            vec![make_row_filter_policy(
+                "p1",
+                1,
+                "*",
+                "customers",
+                "org_id IN (SELECT org_id FROM user_orgs WHERE username = {user.username})",
+            )],
             vec![],
             "open",
             HashMap::new(),
         );
+        let plan = ctx
+            .sql("SELECT id FROM customers")
+            .await
+            .unwrap()
+            .logical_plan()
+            .clone();
+        let AppliedPolicies {
+            plan: result_plan, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
+        // The uncorrelated subquery was run once and inlined as an IN list.
+        assert_plan_contains(&result_plan, "customers.org_id IN ([Utf8(\"acme\")])");
+        assert_eq!(session.lookup_cache.read().await.len(), 1);
 
-        let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
-        let plan = base_plan.logical_plan().clone();
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        // Later queries reuse the cached rows until the TTL runs out.
+        register_user_orgs(&ctx, &[]);
+        assert_eq!(
+            count_with_policies(&ctx, &session, "SELECT id FROM customers").await,
+            3
+        );
+        session.lookup_cache.write().await.clear();
+        assert_eq!(
+            count_with_policies(&ctx, &session, "SELECT id FROM customers").await,
+            0
+        );
+    }
 
-        assert!(had_effects);
-        let batches = exec_plan(&ctx, result_plan).await;
-        assert_eq!(total_rows(&batches), 3, "Only acme rows expected");
-        let ssn_idx = batches[0].schema().index_of("ssn").unwrap();
-        let ssn_array = batches[0]
-            .column(ssn_idx)
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .unwrap();
-        for i in 0..ssn_array.len() {
-            assert_eq!(ssn_array.value(i), "***", "SSN row {i} should be masked");
-        }
+    #[tokio::test]
+    async fn test_exec_cyclic_policy_subqueries_fail_closed() {
+        // customers' filter reads user_orgs, whose filter reads customers: the
+        // nesting stops at MAX_POLICY_SUBQUERY_DEPTH and denies every row.
+        let ctx = setup_customers_ctx().await;
+        register_user_orgs(&ctx, &[("alice", "acme")]);
+        let session = make_session(
+            vec![
+                make_row_filter_policy(
+                    "p1",
+                    1,
+                    "*",
+                    "customers",
+                    "org_id IN (SELECT org_id FROM user_orgs)",
+                ),
+                make_row_filter_policy(
+                    "p2",
+                    1,
+                    "*",
+                    "user_orgs",
+                    "org_id IN (SELECT org_id FROM customers)",
+                ),
+            ],
+            vec![],
+            "open",
+            HashMap::new(),
+        );
+        assert_eq!(
+            count_with_policies(&ctx, &session, "SELECT id FROM customers").await,
+            0
+        );
     }
 
     #[tokio::test]
@@ -3871,10 +9670,13 @@ mod tests {
 
         let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
         let plan = base_plan.logical_plan().clone();
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let batches = exec_plan(&ctx, result_plan).await;
@@ -3910,10 +9712,13 @@ mod tests {
 
         let base_plan = ctx.sql("SELECT * FROM customers").await.unwrap();
         let plan = base_plan.logical_plan().clone();
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let batches = exec_plan(&ctx, result_plan).await;
@@ -3925,6 +9730,207 @@ mod tests {
         );
     }
 
+    // ---------- policy explanation ----------
+
+    async fn explain(
+        session: &SessionDataClone,
+        ctx: &SessionContext,
+        sql: &str,
+    ) -> Vec<PolicyExplanation> {
+        let plan = ctx.sql(sql).await.unwrap().logical_plan().clone();
+        let mut rows = Vec::new();
+        let _ = apply_policies_at(
+            session,
+            ctx,
+            plan,
+            &default_vars(),
+            None,
+            0,
+            Some(&mut rows),
+        )
+        .await;
+        rows
+    }
+
+    /// (policy, column, effect, detail) of an explanation row.
+    type Effect<'a> = (Option<&'a str>, Option<&'a str>, &'a str, Option<&'a str>);
+
+    fn effects_of(rows: &[PolicyExplanation]) -> Vec<Effect<'_>> {
+        rows.iter()
+            .map(|r| {
+                (
+                    r.policy.as_deref(),
+                    r.column.as_deref(),
+                    r.effect,
+                    r.detail.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn explain_target_requires_policy_option() {
+        let parse = |sql: &str| {
+            SqlParser::parse_sql(&PostgreSqlDialect {}, sql)
+                .unwrap()
+                .remove(0)
+        };
+        let explain = parse("EXPLAIN (POLICY) SELECT * FROM customers");
+        assert!(matches!(
+            policy_explain_target(&explain),
+            Some(Statement::Query(_))
+        ));
+        assert!(policy_explain_target(&parse("EXPLAIN (policy) SELECT 1")).is_some());
+        assert!(policy_explain_target(&parse("EXPLAIN SELECT * FROM customers")).is_none());
+        assert!(policy_explain_target(&parse("EXPLAIN (VERBOSE) SELECT 1")).is_none());
+        assert!(policy_explain_target(&parse("SELECT 1")).is_none());
+    }
+
+    #[tokio::test]
+    async fn explain_lists_filters_masks_and_removed_columns() {
+        let ctx = setup_customers_ctx().await;
+        let session = make_session(
+            vec![
+                make_row_filter_policy("tenant", 1, "*", "customers", "org_id = {user.tenant}"),
+                make_column_mask_policy("mask_cc", 1, "*", "customers", "credit_card", "'****'"),
+                make_column_mask_policy("mask_ssn", 1, "*", "customers", "ssn", "'***'"),
+            ],
+            vec![make_column_deny_policy(
+                "hide_ssn",
+                1,
+                "*",
+                "customers",
+                &["ssn"],
+            )],
+            "open",
+            HashMap::new(),
+        );
+
+        let rows = explain(&session, &ctx, "SELECT * FROM customers").await;
+        assert!(
+            rows.iter()
+                .all(|r| r.table.as_deref() == Some("public.customers"))
+        );
+        assert_eq!(
+            effects_of(&rows),
+            vec![
+                (Some("hide_ssn"), Some("ssn"), "removed", None),
+                (
+                    Some("tenant"),
+                    None,
+                    "filtered",
+                    Some("(\"org_id\" = 'acme')")
+                ),
+                (
+                    Some("mask_cc"),
+                    Some("credit_card"),
+                    "masked",
+                    Some("'****'")
+                ),
+            ],
+            "a mask on a denied column is not listed"
+        );
+    }
+
+    #[tokio::test]
+    async fn explain_describes_a_denied_table_without_failing() {
+        let ctx = setup_customers_ctx().await;
+        let session = make_session(
+            vec![],
+            vec![make_table_deny_policy("deny_p", 1, "*", "customers")],
+            "open",
+            HashMap::new(),
+        );
+
+        let rows = explain(&session, &ctx, "SELECT name FROM customers").await;
+        assert_eq!(
+            effects_of(&rows),
+            vec![(Some("deny_p"), None, "denied", None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn explain_shows_ungranted_columns_in_policy_required_mode() {
+        let ctx = setup_customers_ctx().await;
+        let session = make_session(
+            vec![make_column_allow_policy(
+                "basic",
+                1,
+                "*",
+                "customers",
+                &["id", "name"],
+            )],
+            vec![],
+            "policy_required",
+            HashMap::new(),
+        );
+
+        let rows = explain(&session, &ctx, "SELECT * FROM customers").await;
+        let effects = effects_of(&rows);
+        assert_eq!(
+            effects[0],
+            (Some("basic"), None, "allowed", Some("id, name"))
+        );
+        let removed: Vec<_> = effects
+            .iter()
+            .filter(|(_, _, effect, _)| *effect == "removed")
+            .map(|(_, column, _, _)| column.unwrap())
+            .collect();
+        assert_eq!(removed, vec!["org_id", "ssn", "credit_card"]);
+        assert!(!effects.iter().any(|(_, _, effect, _)| *effect == "no_rows"));
+    }
+
+    #[test]
+    fn summary_redaction_hides_expressions_and_removed_column_names() {
+        let row =
+            |column: Option<&str>, policy: &str, effect: &'static str, detail: Option<&str>| {
+                PolicyExplanation {
+                    table: Some("public.customers".to_string()),
+                    column: column.map(str::to_string),
+                    policy: Some(policy.to_string()),
+                    policy_type: None,
+                    effect,
+                    detail: detail.map(str::to_string),
+                }
+            };
+        let rows = vec![
+            row(None, "tenant", "filtered", Some("(org_id = 'acme')")),
+            row(Some("ssn"), "hide", "removed", None),
+            row(Some("salary"), "hide", "removed", None),
+            row(Some("credit_card"), "mask_cc", "masked", Some("'****'")),
+            row(None, "cap", "limited", Some("max 100 rows")),
+            row(
+                None,
+                "shadow_filter",
+                "would_filter_rows",
+                Some("(region = 'eu')"),
+            ),
+            PolicyExplanation::query("rewritten_query", "SELECT ...".to_string()),
+        ];
+
+        assert_eq!(redact_explanation(rows.clone(), ExplainLevel::Full), rows);
+        assert_eq!(
+            effects_of(&redact_explanation(rows, ExplainLevel::Summary)),
+            vec![
+                (Some("tenant"), None, "filtered", None),
+                (Some("mask_cc"), Some("credit_card"), "masked", None),
+                (Some("cap"), None, "limited", Some("max 100 rows")),
+                (Some("hide"), None, "removed", Some("2 columns")),
+            ]
+        );
+    }
+
+    #[test]
+    fn explanation_batch_has_one_row_per_explanation() {
+        let rows = vec![PolicyExplanation::query("rejected", "blocked".to_string())];
+        let batch = explanation_batch(&rows).unwrap();
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.schema().field(4).name(), "effect");
+        assert_eq!(ExplainLevel::default(), ExplainLevel::Summary);
+        assert_eq!("full".parse::<ExplainLevel>(), Ok(ExplainLevel::Full));
+        assert!("verbose".parse::<ExplainLevel>().is_err());
+    }
+
     // ---------- apply_projection glob pattern tests ----------
 
     #[tokio::test]
@@ -3953,10 +9959,13 @@ mod tests {
             ],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let schema = result_plan.schema();
@@ -4026,10 +10035,13 @@ mod tests {
             vec![("id", DataType::Int32), ("secret_val", DataType::Utf8)],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let schema = result_plan.schema();
@@ -4110,10 +10122,13 @@ mod tests {
             ],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let schema = result_plan.schema();
@@ -4160,10 +10175,13 @@ mod tests {
             ],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
         assert!(had_effects);
         let schema = result_plan.schema();
         let col_names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
@@ -4195,7 +10213,7 @@ mod tests {
             vec![("id", DataType::Int32), ("ssn", DataType::Utf8)],
         );
 
-        let tables = collect_user_tables(&plan, "public");
+        let tables = collect_scan_schemas(&plan, "public");
         let vars = default_vars();
         let effects = PolicyEffects::collect(&session, &tables, &vars, &ctx, None).await;
 
@@ -4237,10 +10255,13 @@ mod tests {
             ],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let schema = result_plan.schema();
@@ -4274,10 +10295,13 @@ mod tests {
             vec![("id", DataType::Int32), ("name", DataType::Utf8)],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let display = plan_display(&result_plan);
@@ -4309,10 +10333,13 @@ mod tests {
             ],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let display = plan_display(&result_plan);
@@ -4352,10 +10379,13 @@ mod tests {
             vec![("id", DataType::Int32), ("ssn", DataType::Utf8)],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let display = plan_display(&result_plan);
@@ -4394,10 +10424,13 @@ mod tests {
             ],
         );
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let schema = result_plan.schema();
@@ -4529,6 +10562,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -4536,6 +10570,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -4552,10 +10587,14 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (result_plan, had_effects, decision_results) =
-            apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            decision_results,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
+            .await
+            .unwrap();
 
         assert!(had_effects, "Policy with fire:true should apply effects");
         let display = plan_display(&result_plan);
@@ -4584,6 +10623,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -4591,6 +10631,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -4607,10 +10648,14 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (result_plan, had_effects, decision_results) =
-            apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            decision_results,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
+            .await
+            .unwrap();
 
         assert!(
             !had_effects,
@@ -4637,9 +10682,10 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
         );
 
         // Pass None for decision_eval — backward compatibility
-        let (_, had_effects, _) = apply_policies(&session, &ctx, plan, &default_vars(), None)
-            .await
-            .unwrap();
+        let AppliedPolicies { had_effects, .. } =
+            apply_policies(&session, &ctx, plan, &default_vars(), None)
+                .await
+                .unwrap();
         assert!(had_effects, "Policy without decision fn should always fire");
     }
 
@@ -4660,6 +10706,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -4667,6 +10714,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -4683,7 +10731,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (_, had_effects, _) =
+        let AppliedPolicies { had_effects, .. } =
             apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
                 .await
                 .unwrap();
@@ -4711,6 +10759,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -4718,6 +10767,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -4734,10 +10784,13 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (_, had_effects, decision_results) =
-            apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            had_effects,
+            decision_results,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
+            .await
+            .unwrap();
         assert!(had_effects, "on_error=deny should fire the policy");
         let dr = decision_results.values().next().unwrap();
         assert!(dr.fire, "on_error=deny: fire should be true");
@@ -4761,6 +10814,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -4768,6 +10822,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -4784,10 +10839,13 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (_, had_effects, decision_results) =
-            apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            had_effects,
+            decision_results,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
+            .await
+            .unwrap();
         assert!(!had_effects, "on_error=skip should not fire the policy");
         let dr = decision_results.values().next().unwrap();
         assert!(!dr.fire, "on_error=skip: fire should be false");
@@ -4819,6 +10877,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -4826,6 +10885,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -4865,10 +10925,13 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (_, had_effects, decision_results) =
-            apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            had_effects,
+            decision_results,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
+            .await
+            .unwrap();
         assert!(
             had_effects,
             "Query-mode fn should fire when query context is available"
@@ -4901,6 +10964,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -4908,6 +10972,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -4934,10 +10999,13 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (_, had_effects, decision_results) =
-            apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            had_effects,
+            decision_results,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
+            .await
+            .unwrap();
         assert!(
             had_effects,
             "Session-mode fn should fire with session context"
@@ -4973,6 +11041,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -4980,6 +11049,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -4996,10 +11066,13 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (_, had_effects, decision_results) =
-            apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            had_effects,
+            decision_results,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
+            .await
+            .unwrap();
         assert!(had_effects, "Config threshold=5 <= 10, should fire");
         let dr = decision_results.values().next().unwrap();
         assert!(dr.fire);
@@ -5022,6 +11095,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::TableDeny,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -5029,6 +11103,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: None,
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![], vec![policy], "open", HashMap::new());
@@ -5067,6 +11142,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             policy_type: PolicyType::RowFilter,
             version: 1,
             priority: 1,
+            scope: AssignmentScope::All,
             targets: vec![TargetEntry {
                 schemas: vec!["public".to_string()],
                 tables: vec!["orders".to_string()],
@@ -5074,6 +11150,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             }],
             definition: Some(serde_json::json!({"filter_expression": "status = 'active'"})),
             decision_function: Some(df),
+            template: None,
         };
 
         let session = make_session(vec![policy], vec![], "open", HashMap::new());
@@ -5090,10 +11167,11 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             decision_ctx,
         };
 
-        let (_, _, decision_results) =
-            apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            decision_results, ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), Some(&eval))
+            .await
+            .unwrap();
 
         assert!(
             decision_results.contains_key(&policy_id),
@@ -5430,14 +11508,13 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
     }
 
     #[test]
-    fn test_typed_lit_null() {
-        let expr = typed_lit("", "null");
-        match expr {
-            datafusion::logical_expr::Expr::Literal(sv, _) => {
-                assert!(sv.is_null(), "null sentinel should produce NULL literal");
-            }
-            _ => panic!("expected Literal, got {expr:?}"),
-        }
+    fn test_typed_value_null() {
+        let expr = typed_value("", "null");
+        assert_eq!(
+            expr.to_string(),
+            "NULL",
+            "null sentinel should produce NULL literal"
+        );
     }
 
     #[test]
@@ -5456,7 +11533,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             attributes: attrs,
             attribute_defs: HashMap::new(),
         };
-        let expr = parse_filter_expr("department IN ({user.departments})", &vars);
+        let expr = parse_expr_detached("department IN ({user.departments})", &vars);
         assert!(expr.is_ok(), "list IN clause should parse: {expr:?}");
         let expr = expr.unwrap();
         let display = format!("{expr}");
@@ -5482,7 +11559,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             attributes: attrs,
             attribute_defs: HashMap::new(),
         };
-        let expr = parse_filter_expr("department IN ({user.departments})", &vars);
+        let expr = parse_expr_detached("department IN ({user.departments})", &vars);
         assert!(expr.is_ok(), "empty list IN clause should parse: {expr:?}");
     }
 
@@ -5528,11 +11605,19 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
         assert_eq!(vars.get("user.unknown"), None);
     }
 
-    // ---------- ABAC: typed_lit ----------
+    // ---------- ABAC: typed_value ----------
+
+    /// Plan a bound placeholder literal the way a policy expression would be.
+    fn plan_value(value: SqlExpr) -> datafusion::logical_expr::Expr {
+        let ctx = SessionContext::new();
+        PolicyContextProvider::synthetic(ctx.state(), &value)
+            .plan(value, "", &Schema::empty())
+            .unwrap()
+    }
 
     #[test]
-    fn test_typed_lit_string() {
-        let expr = typed_lit("hello", "string");
+    fn test_typed_value_string() {
+        let expr = plan_value(typed_value("hello", "string"));
         let debug = format!("{expr:?}");
         assert!(
             debug.contains("Utf8"),
@@ -5541,8 +11626,8 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
     }
 
     #[test]
-    fn test_typed_lit_integer() {
-        let expr = typed_lit("42", "integer");
+    fn test_typed_value_integer() {
+        let expr = plan_value(typed_value("42", "integer"));
         let debug = format!("{expr:?}");
         assert!(
             debug.contains("Int64"),
@@ -5551,8 +11636,8 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
     }
 
     #[test]
-    fn test_typed_lit_boolean() {
-        let expr = typed_lit("true", "boolean");
+    fn test_typed_value_boolean() {
+        let expr = plan_value(typed_value("true", "boolean"));
         let debug = format!("{expr:?}");
         assert!(
             debug.contains("Boolean"),
@@ -5561,9 +11646,9 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
     }
 
     #[test]
-    fn test_typed_lit_integer_fallback() {
+    fn test_typed_value_integer_fallback() {
         // Invalid integer falls back to string
-        let expr = typed_lit("abc", "integer");
+        let expr = plan_value(typed_value("abc", "integer"));
         let debug = format!("{expr:?}");
         assert!(
             debug.contains("Utf8"),
@@ -5571,7 +11656,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
         );
     }
 
-    // ---------- ABAC: parse_filter_expr with integer attribute ----------
+    // ---------- ABAC: parse_expr_detached with integer attribute ----------
 
     #[test]
     fn test_parse_filter_integer_comparison() {
@@ -5589,7 +11674,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             attributes: attrs,
             attribute_defs: HashMap::new(),
         };
-        let expr = parse_filter_expr("level >= {user.clearance}", &vars).unwrap();
+        let expr = parse_expr_detached("level >= {user.clearance}", &vars).unwrap();
         let debug = format!("{expr:?}");
         assert!(
             debug.contains("Int64(3)"),
@@ -5602,7 +11687,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
     #[test]
     fn test_parse_case_when_expression() {
         let vars = default_vars();
-        let expr = parse_filter_expr(
+        let expr = parse_expr_detached(
             "CASE WHEN status = 'active' THEN true ELSE false END",
             &vars,
         )
@@ -5676,7 +11761,7 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
             attributes,
             attribute_defs: HashMap::new(),
         };
-        let expr = parse_filter_expr("organization_id = {user.tenant}", &vars).unwrap();
+        let expr = parse_expr_detached("organization_id = {user.tenant}", &vars).unwrap();
         let debug = format!("{expr:?}");
         assert!(
             debug.contains("acme"),
@@ -5708,10 +11793,13 @@ Javy.IO.writeSync(1, new TextEncoder().encode(JSON.stringify(result)));
         // and has no anchor defined (empty relationship_snapshot).
         let plan = build_scan_plan("public.orders", vec![("id", DataType::Int32)]);
 
-        let (result_plan, had_effects, _) =
-            apply_policies(&session, &ctx, plan, &default_vars(), None)
-                .await
-                .unwrap();
+        let AppliedPolicies {
+            plan: result_plan,
+            had_effects,
+            ..
+        } = apply_policies(&session, &ctx, plan, &default_vars(), None)
+            .await
+            .unwrap();
 
         assert!(had_effects);
         let display = plan_display(&result_plan);
@@ -25,6 +25,9 @@ pub fn is_allowed_statement(statement: &Statement) -> bool {
 
 /// Rejects any non-read SQL statement at the wire protocol level,
 /// before DataFusion or RLS processing.
+///
+/// Writes on a `read_write` datasource never get here: `PolicyHook` runs them
+/// under the user's write policies (see [`crate::write`]).
 #[derive(Default)]
 pub struct ReadOnlyHook;
 
@@ -41,6 +44,7 @@ impl QueryHook for ReadOnlyHook {
         statement: &Statement,
         _session_context: &SessionContext,
         _client: &(dyn ClientInfo + Sync),
+        _notices: &mut Vec<ErrorInfo>,
     ) -> Option<PgWireResult<Response>> {
         // SECURITY: This is an allowlist — any new Statement variant must be reviewed before
         // adding here AND to `is_allowed_statement` above (they must stay in sync).
@@ -0,0 +1,593 @@
+//! Lineage check for `join_only` policies.
+//!
+//! A `join_only` column is a join key: a query may match it against a column
+//! of another table, but its values must never reach the client or be computed
+//! on. The check walks the plan as the user wrote it, before `apply_policies`
+//! rewrites it, and tracks for every output column whether it carries a
+//! join-only value and whether it is a plain column of a table scan.
+//!
+//! | Use of a join-only column | Outcome |
+//! |---|---|
+//! | `a.key = b.col` in `JOIN ... ON` or `WHERE`, `b.col` a column of another scan | allowed |
+//! | `a.key IN (SELECT b.col FROM ...)`, `EXISTS (... WHERE b.col = a.key)` | allowed |
+//! | passed through `SELECT`, subqueries and aliases unchanged | allowed |
+//! | returned by the query | rejected |
+//! | compared with a literal, a `VALUES` row or a computed value; `<>`, `<`, `LIKE`, `OR` | rejected |
+//! | in any other expression (`CASE`, function calls), `GROUP BY`, aggregates, `HAVING`, `ORDER BY`, window functions | rejected |
+//!
+//! Each rule closes one of the inference paths of security vectors 59–65:
+//! predicate probing, aggregate inference, plan output, `HAVING`, string
+//! aggregation, `CASE` smuggling and window ordering.
+
+use std::collections::HashMap;
+
+use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+use datafusion::common::{Column, DFSchema};
+use datafusion::logical_expr::expr::InSubquery;
+use datafusion::logical_expr::utils::split_conjunction;
+use datafusion::logical_expr::{BinaryExpr, Expr, LogicalPlan, Operator};
+
+use crate::hooks::policy::scan_policy_key;
+
+/// Join-only columns keyed by `(df_schema, table, column)`, with the policy
+/// that restricts each.
+pub type JoinOnlyColumns = HashMap<(String, String, String), String>;
+
+/// A use of a join-only column the policy does not allow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub policy_name: String,
+    pub message: String,
+}
+
+const RETURNED: &str = "cannot be returned";
+const COMPARED: &str = "can only be compared for equality with a column of another table";
+const COMPUTED: &str = "cannot be used in an expression";
+const AGGREGATED: &str = "cannot be aggregated or grouped on";
+const SORTED: &str = "cannot be sorted on";
+const WINDOWED: &str = "cannot be used in a window function";
+const OTHER: &str = "cannot be used here";
+
+/// Reject `plan` if it uses a join-only column other than as a join key.
+pub fn check(
+    plan: &LogicalPlan,
+    columns: &JoinOnlyColumns,
+    default_schema: &str,
+) -> Result<(), Violation> {
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let mut checker = Checker {
+        columns,
+        default_schema,
+        keys: Vec::new(),
+        scans: 0,
+    };
+    let output = checker.lineage(plan, None)?;
+    checker.returned(&output)
+}
+
+/// Where an output column's values come from.
+#[derive(Debug, Clone, Copy, Default)]
+struct Origin {
+    /// Index into `Checker::keys` when the values are a join-only column's.
+    key: Option<usize>,
+    /// The scan the column was read from, while it is passed through unchanged.
+    scan: Option<usize>,
+}
+
+struct Checker<'a> {
+    columns: &'a JoinOnlyColumns,
+    default_schema: &'a str,
+    /// Join-only columns read by the query: policy and `schema.table.column`.
+    keys: Vec<(&'a str, String)>,
+    scans: usize,
+}
+
+/// Columns an expression can reference: the inputs of its plan node, then the
+/// query blocks around it for correlated subqueries.
+struct Scope<'s> {
+    inputs: Vec<(&'s DFSchema, &'s [Origin])>,
+    outer: Option<&'s Scope<'s>>,
+}
+
+impl Scope<'_> {
+    fn resolve(&self, column: &Column, outer_only: bool) -> Origin {
+        if !outer_only {
+            for (schema, origins) in &self.inputs {
+                if let Ok(i) = schema.index_of_column(column) {
+                    return origins[i];
+                }
+            }
+        }
+        self.outer
+            .map(|outer| outer.resolve(column, false))
+            .unwrap_or_default()
+    }
+
+    /// Origin of a bare column reference, `None` for any other expression.
+    fn column(&self, expr: &Expr) -> Option<Origin> {
+        match expr {
+            Expr::Column(c) => Some(self.resolve(c, false)),
+            Expr::OuterReferenceColumn(_, c) => Some(self.resolve(c, true)),
+            _ => None,
+        }
+    }
+}
+
+/// A subquery inside an expression, and whether its values flow into it.
+struct NestedQuery {
+    plan: std::sync::Arc<LogicalPlan>,
+    yields_values: bool,
+}
+
+impl<'a> Checker<'a> {
+    fn violation(&self, key: usize, what: &str) -> Violation {
+        let (policy_name, column) = &self.keys[key];
+        Violation {
+            policy_name: policy_name.to_string(),
+            message: format!("{column} is join-only and {what}"),
+        }
+    }
+
+    fn returned(&self, output: &[Origin]) -> Result<(), Violation> {
+        match output.iter().find_map(|o| o.key) {
+            Some(key) => Err(self.violation(key, RETURNED)),
+            None => Ok(()),
+        }
+    }
+
+    /// Origins of `plan`'s output columns, rejecting any use of a join-only
+    /// column on the way.
+    fn lineage(
+        &mut self,
+        plan: &LogicalPlan,
+        outer: Option<&Scope<'_>>,
+    ) -> Result<Vec<Origin>, Violation> {
+        let inputs = plan.inputs();
+        let lineages = inputs
+            .iter()
+            .map(|input| self.lineage(input, outer))
+            .collect::<Result<Vec<_>, _>>()?;
+        let scope = Scope {
+            inputs: inputs
+                .iter()
+                .zip(&lineages)
+                .map(|(input, origins)| (input.schema().as_ref(), origins.as_slice()))
+                .collect(),
+            outer,
+        };
+
+        let output = match plan {
+            LogicalPlan::TableScan(scan) => {
+                let (schema, table) = scan_policy_key(scan, self.default_schema);
+                let id = self.scans;
+                self.scans += 1;
+                scan.projected_schema
+                    .fields()
+                    .iter()
+                    .map(|field| {
+                        let key = self
+                            .columns
+                            .get_key_value(&(schema.clone(), table.clone(), field.name().clone()))
+                            .map(|((s, t, c), policy)| {
+                                self.keys.push((policy.as_str(), format!("{s}.{t}.{c}")));
+                                self.keys.len() - 1
+                            });
+                        Origin {
+                            key,
+                            scan: Some(id),
+                        }
+                    })
+                    .collect()
+            }
+            LogicalPlan::Projection(projection) => {
+                let mut output = Vec::with_capacity(projection.expr.len());
+                for expr in &projection.expr {
+                    let mut inner = expr;
+                    while let Expr::Alias(alias) = inner {
+                        inner = &alias.expr;
+                    }
+                    match scope.column(inner) {
+                        Some(origin) => output.push(origin),
+                        None => {
+                            self.forbid(expr, &scope, COMPUTED)?;
+                            output.push(Origin::default());
+                        }
+                    }
+                }
+                output
+            }
+            LogicalPlan::SubqueryAlias(_) => lineages[0].clone(),
+            LogicalPlan::Filter(filter) => self
+                .condition(&[], Some(&filter.predicate), &scope)?
+                .swap_remove(0),
+            LogicalPlan::Join(join) => {
+                let origins = self.condition(&join.on, join.filter.as_ref(), &scope)?;
+                let scope = Scope {
+                    inputs: scope
+                        .inputs
+                        .iter()
+                        .zip(&origins)
+                        .map(|((schema, _), origins)| (*schema, origins.as_slice()))
+                        .collect(),
+                    outer,
+                };
+                inherit(plan.schema(), &scope, false)
+            }
+            LogicalPlan::Aggregate(agg) => {
+                for expr in agg.group_expr.iter().chain(&agg.aggr_expr) {
+                    self.forbid(expr, &scope, AGGREGATED)?;
+                }
+                inherit(plan.schema(), &scope, false)
+            }
+            LogicalPlan::Window(window) => {
+                for expr in &window.window_expr {
+                    self.forbid(expr, &scope, WINDOWED)?;
+                }
+                inherit(plan.schema(), &scope, false)
+            }
+            LogicalPlan::Sort(sort) => {
+                for expr in &sort.expr {
+                    self.forbid(&expr.expr, &scope, SORTED)?;
+                }
+                lineages[0].clone()
+            }
+            // Columns of a UNION mix values from every branch, so none is a
+            // plain table column any more.
+            LogicalPlan::Union(_) | LogicalPlan::RecursiveQuery(_) => {
+                (0..plan.schema().fields().len())
+                    .map(|i| Origin {
+                        key: lineages.iter().find_map(|l| l.get(i).and_then(|o| o.key)),
+                        scan: None,
+                    })
+                    .collect()
+            }
+            // EXPLAIN shows the plan, and with ANALYZE runs it: the explained
+            // query is held to the same rules as a top-level one.
+            LogicalPlan::Explain(_) | LogicalPlan::Analyze(_) => {
+                for origins in &lineages {
+                    self.returned(origins)?;
+                }
+                vec![Origin::default(); plan.schema().fields().len()]
+            }
+            _ => {
+                for expr in plan.expressions() {
+                    self.forbid(&expr, &scope, OTHER)?;
+                }
+                inherit(plan.schema(), &scope, true)
+            }
+        };
+        Ok(output)
+    }
+
+    /// Check a `WHERE` or join condition: each conjunct that touches a
+    /// join-only column must be an equality between it and a column of
+    /// another scan, or `IN` a subquery returning one.
+    ///
+    /// A column equated with a join-only column holds the same values once the
+    /// condition applies, so it becomes join-only too — `p.key` after
+    /// `ON c.key = p.key` can no more be returned or probed than `c.key`.
+    /// Returns the origins of the node's inputs with that applied.
+    fn condition(
+        &mut self,
+        on: &[(Expr, Expr)],
+        filter: Option<&Expr>,
+        scope: &Scope<'_>,
+    ) -> Result<Vec<Vec<Origin>>, Violation> {
+        let mut pairs: Vec<(&Expr, &Expr)> = on.iter().map(|(l, r)| (l, r)).collect();
+        let mut rest = Vec::new();
+        for conjunct in filter.map(split_conjunction).unwrap_or_default() {
+            match conjunct {
+                Expr::BinaryExpr(BinaryExpr {
+                    left,
+                    op: Operator::Eq,
+                    right,
+                }) => pairs.push((left, right)),
+                other => rest.push(other),
+            }
+        }
+
+        let mut origins: Vec<Vec<Origin>> = scope.inputs.iter().map(|(_, o)| o.to_vec()).collect();
+        let locate = |column: &Column| {
+            scope
+                .inputs
+                .iter()
+                .enumerate()
+                .find_map(|(i, (schema, _))| schema.index_of_column(column).ok().map(|j| (i, j)))
+        };
+        loop {
+            let mut changed = false;
+            for (left, right) in &pairs {
+                for (from, to) in [(left, right), (right, left)] {
+                    let Expr::Column(target) = to else {
+                        continue;
+                    };
+                    let key = match from {
+                        Expr::Column(c) => locate(c).and_then(|(i, j)| origins[i][j].key),
+                        Expr::OuterReferenceColumn(_, c) => scope.resolve(c, true).key,
+                        _ => None,
+                    };
+                    if let (Some(key), Some((i, j))) = (key, locate(target))
+                        && origins[i][j].key.is_none()
+                    {
+                        origins[i][j].key = Some(key);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let scope = Scope {
+            inputs: scope
+                .inputs
+                .iter()
+                .zip(&origins)
+                .map(|((schema, _), origins)| (*schema, origins.as_slice()))
+                .collect(),
+            outer: scope.outer,
+        };
+        for (left, right) in pairs {
+            self.equality(left, right, &scope)?;
+        }
+        for conjunct in rest {
+            match conjunct {
+                Expr::InSubquery(InSubquery { expr, subquery, .. })
+                    if scope.column(expr).is_some() =>
+                {
+                    let origin = scope.column(expr).unwrap_or_default();
+                    let output = self.lineage(&subquery.subquery, Some(&scope))?;
+                    self.compare(origin, output.first().copied().unwrap_or_default())?;
+                }
+                other => self.forbid(other, &scope, COMPARED)?,
+            }
+        }
+        Ok(origins)
+    }
+
+    fn equality(&mut self, left: &Expr, right: &Expr, scope: &Scope<'_>) -> Result<(), Violation> {
+        match (scope.column(left), scope.column(right)) {
+            (Some(l), Some(r)) => self.compare(l, r),
+            _ => {
+                self.forbid(left, scope, COMPARED)?;
+                self.forbid(right, scope, COMPARED)
+            }
+        }
+    }
+
+    /// Both sides of an equality must be plain columns of different scans once
+    /// either is join-only; a literal or `VALUES` row would turn the join into
+    /// a probe.
+    fn compare(&self, left: Origin, right: Origin) -> Result<(), Violation> {
+        let Some(key) = left.key.or(right.key) else {
+            return Ok(());
+        };
+        match (left.scan, right.scan) {
+            (Some(l), Some(r)) if l != r => Ok(()),
+            _ => Err(self.violation(key, COMPARED)),
+        }
+    }
+
+    /// Reject `expr` if it references a join-only column, directly or through
+    /// a subquery whose values it uses.
+    fn forbid(&mut self, expr: &Expr, scope: &Scope<'_>, what: &str) -> Result<(), Violation> {
+        let mut origins = Vec::new();
+        let mut nested = Vec::new();
+        let _ = expr.apply(|e| {
+            match e {
+                Expr::Column(_) | Expr::OuterReferenceColumn(..) => {
+                    origins.extend(scope.column(e));
+                }
+                Expr::ScalarSubquery(subquery) => nested.push(NestedQuery {
+                    plan: subquery.subquery.clone(),
+                    yields_values: true,
+                }),
+                Expr::InSubquery(in_subquery) => nested.push(NestedQuery {
+                    plan: in_subquery.subquery.subquery.clone(),
+                    yields_values: true,
+                }),
+                Expr::Exists(exists) => nested.push(NestedQuery {
+                    plan: exists.subquery.subquery.clone(),
+                    yields_values: false,
+                }),
+                _ => {}
+            }
+            Ok(TreeNodeRecursion::Continue)
+        });
+        if let Some(key) = origins.iter().find_map(|o| o.key) {
+            return Err(self.violation(key, what));
+        }
+        for query in nested {
+            let output = self.lineage(&query.plan, Some(scope))?;
+            if query.yields_values
+                && let Some(key) = output.iter().find_map(|o| o.key)
+            {
+                return Err(self.violation(key, what));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Origins of a node's output columns found by name among its inputs' columns.
+/// With `taint_unknown`, columns the node made up itself are treated as
+/// carrying any join-only value it reads, since nothing says they do not.
+fn inherit(schema: &DFSchema, scope: &Scope<'_>, taint_unknown: bool) -> Vec<Origin> {
+    let read = scope
+        .inputs
+        .iter()
+        .flat_map(|(_, origins)| origins.iter())
+        .find_map(|o| o.key);
+    schema
+        .iter()
+        .map(|(qualifier, field)| {
+            let column = Column::new(qualifier.cloned(), field.name());
+            scope
+                .inputs
+                .iter()
+                .find_map(|(schema, origins)| {
+                    schema.index_of_column(&column).ok().map(|i| origins[i])
+                })
+                .unwrap_or(Origin {
+                    key: read.filter(|_| taint_unknown),
+                    scan: None,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::datasource::MemTable;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    /// The violation `sql` breaks when `customers.email_hash` is join-only.
+    async fn violation(sql: &str) -> Option<String> {
+        let ctx = SessionContext::new();
+        for (name, columns) in [
+            ("customers", ["id", "email_hash", "name"]),
+            ("partner", ["id", "email_hash", "segment"]),
+        ] {
+            let schema = Arc::new(Schema::new(
+                columns
+                    .into_iter()
+                    .map(|c| Field::new(c, DataType::Utf8, true))
+                    .collect::<Vec<_>>(),
+            ));
+            let table = MemTable::try_new(schema, vec![vec![]]).unwrap();
+            ctx.register_table(name, Arc::new(table)).unwrap();
+        }
+        let plan = ctx.state().create_logical_plan(sql).await.unwrap();
+        let columns = JoinOnlyColumns::from([(
+            (
+                "public".to_string(),
+                "customers".to_string(),
+                "email_hash".to_string(),
+            ),
+            "blind".to_string(),
+        )]);
+        check(&plan, &columns, "public").err().map(|v| {
+            assert_eq!(v.policy_name, "blind");
+            v.message
+        })
+    }
+
+    #[tokio::test]
+    async fn equality_joins_are_allowed() {
+        for ok in [
+            "SELECT p.segment, count(*) FROM customers c JOIN partner p \
+             ON c.email_hash = p.email_hash GROUP BY p.segment",
+            "SELECT c.name FROM customers c, partner p WHERE c.email_hash = p.email_hash",
+            "SELECT name FROM customers WHERE email_hash IN (SELECT email_hash FROM partner)",
+            "SELECT name FROM customers c \
+             WHERE EXISTS (SELECT 1 FROM partner p WHERE p.email_hash = c.email_hash)",
+            "SELECT x.name FROM (SELECT name, email_hash FROM customers) x \
+             JOIN partner p ON x.email_hash = p.email_hash",
+            "SELECT c.name FROM customers c JOIN customers d \
+             ON c.email_hash = d.email_hash AND c.id <> d.id",
+            "SELECT c.id FROM customers c JOIN partner p USING (email_hash)",
+        ] {
+            assert_eq!(violation(ok).await, None, "{ok}");
+        }
+    }
+
+    #[tokio::test]
+    async fn returning_the_key_is_rejected() {
+        for bad in [
+            "SELECT email_hash FROM customers",
+            "SELECT * FROM customers",
+            "SELECT x.h FROM (SELECT email_hash AS h FROM customers) x",
+            "SELECT h FROM (SELECT email_hash AS h FROM customers \
+             UNION ALL SELECT email_hash FROM partner) u",
+            "EXPLAIN SELECT email_hash FROM customers",
+            "SELECT p.email_hash FROM customers c JOIN partner p ON c.email_hash = p.email_hash",
+        ] {
+            let message = violation(bad).await.unwrap_or_else(|| panic!("{bad}"));
+            assert!(message.ends_with(RETURNED), "{bad}: {message}");
+        }
+    }
+
+    #[tokio::test]
+    async fn probes_are_rejected() {
+        // Vector 59: literals, correlated probes and VALUES rows.
+        for bad in [
+            "SELECT id FROM customers WHERE email_hash = 'abc'",
+            "SELECT count(*) FROM partner p WHERE EXISTS \
+             (SELECT 1 FROM customers c WHERE c.email_hash = 'abc' AND c.id = p.id)",
+            "SELECT c.id FROM customers c JOIN (VALUES ('abc')) AS v(h) ON c.email_hash = v.h",
+            "SELECT c.name FROM customers c JOIN partner p ON c.email_hash > p.email_hash",
+            "SELECT c.name FROM customers c JOIN partner p ON lower(c.email_hash) = p.email_hash",
+            "SELECT c.name FROM customers c JOIN partner p \
+             ON c.email_hash = p.email_hash OR c.id = p.id",
+            "SELECT name FROM customers WHERE email_hash = name",
+            "SELECT name FROM customers WHERE email_hash LIKE 'a%'",
+            // Probing the column the key was equated with.
+            "SELECT c.id FROM customers c JOIN partner p \
+             ON c.email_hash = p.email_hash AND p.email_hash = 'abc'",
+            "SELECT c.id FROM customers c JOIN partner p \
+             ON c.email_hash = p.email_hash WHERE p.email_hash = 'abc'",
+        ] {
+            let message = violation(bad).await.unwrap_or_else(|| panic!("{bad}"));
+            assert!(message.ends_with(COMPARED), "{bad}: {message}");
+        }
+    }
+
+    #[tokio::test]
+    async fn computing_on_the_key_is_rejected() {
+        // Vectors 60 and 62–65.
+        for (bad, what) in [
+            (
+                "SELECT count(DISTINCT email_hash) FROM customers",
+                AGGREGATED,
+            ),
+            (
+                "SELECT count(*) FROM customers GROUP BY email_hash",
+                AGGREGATED,
+            ),
+            (
+                "SELECT name FROM customers GROUP BY name HAVING max(email_hash) > 'a'",
+                AGGREGATED,
+            ),
+            (
+                "SELECT string_agg(email_hash, ',') FROM customers",
+                AGGREGATED,
+            ),
+            (
+                "SELECT CASE WHEN email_hash LIKE 'a%' THEN 1 ELSE 0 END FROM customers",
+                COMPUTED,
+            ),
+            ("SELECT length(email_hash) FROM customers", COMPUTED),
+            (
+                "SELECT id, row_number() OVER (ORDER BY email_hash) FROM customers",
+                WINDOWED,
+            ),
+            (
+                "SELECT c.name FROM customers c JOIN partner p \
+                 ON c.email_hash = p.email_hash ORDER BY c.email_hash",
+                SORTED,
+            ),
+            (
+                "SELECT (SELECT email_hash FROM customers LIMIT 1)",
+                COMPUTED,
+            ),
+        ] {
+            let message = violation(bad).await.unwrap_or_else(|| panic!("{bad}"));
+            assert!(message.ends_with(what), "{bad}: {message}");
+        }
+    }
+
+    #[tokio::test]
+    async fn queries_without_the_key_are_untouched() {
+        assert_eq!(violation("SELECT id, name FROM customers").await, None);
+        assert_eq!(
+            violation("SELECT segment FROM partner WHERE email_hash = 'abc'").await,
+            None
+        );
+    }
+}
@@ -9,9 +9,23 @@ pub mod decision;
 pub mod discovery;
 pub mod engine;
 pub mod entity;
+pub mod flight;
 pub mod handler;
 pub mod hooks;
+pub mod join_only;
+pub mod masking;
+pub mod mcp;
+pub mod policy_analysis;
+pub mod policy_combining;
 pub mod policy_match;
+pub mod policy_simulation;
+pub mod policy_template;
+pub mod privacy;
+pub mod purpose;
+pub mod query_guard;
 pub mod resolution;
+pub mod result_limit;
 pub mod role_resolver;
 pub mod server;
+pub mod subject;
+pub mod write;
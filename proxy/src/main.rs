@@ -1,10 +1,12 @@
 use clap::{Parser, Subcommand};
 use migration::{Migrator, MigratorTrait};
+use proxy::admin::query_handlers::QueryApi;
 use proxy::admin::{AdminState, admin_router};
 use proxy::auth::Auth;
 use proxy::engine::EngineCache;
+use proxy::flight::FlightSqlServer;
 use proxy::handler::ProxyHandler;
-use proxy::hooks::policy::PolicyHook;
+use proxy::hooks::policy::{ExplainLevel, PolicyHook};
 use proxy::server::process_socket_with_idle_timeout;
 use rand_core::RngCore;
 use sea_orm::{ColumnTrait, ConnectOptions, Database, EntityTrait, QueryFilter};
@@ -24,11 +26,18 @@ struct Cli {
 enum Commands {
     /// Start the proxy server (default)
     Serve,
+    /// Serve MCP over stdio for a local agent (identity from BR_MCP_API_KEY)
+    Mcp,
     /// Manage proxy users
     User {
         #[command(subcommand)]
         action: UserAction,
     },
+    /// Inspect policies
+    Policy {
+        #[command(subcommand)]
+        action: PolicyAction,
+    },
 }
 
 #[derive(Subcommand)]
@@ -44,14 +53,33 @@ enum UserAction {
     },
 }
 
+#[derive(Subcommand)]
+enum PolicyAction {
+    /// Report conflicting, dead and uncovered policies on a data source
+    Analyze {
+        /// Data source name
+        #[arg(long)]
+        datasource: String,
+        /// Print the report as JSON
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        json: bool,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
     // Init structured logging (respects RUST_LOG; defaults to info)
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
-        )
-        .init();
+    let logging = tracing_subscriber::fmt().with_env_filter(
+        tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+    );
+    if matches!(cli.command, Some(Commands::Mcp | Commands::Policy { .. })) {
+        // stdout carries the MCP protocol or the report; logs must not interleave with it.
+        logging.with_writer(std::io::stderr).init();
+    } else {
+        logging.init();
+    }
 
     // Load .env if present
     dotenvy::dotenv().ok();
@@ -71,8 +99,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     eprintln!("  v{} — Data Access Governance", env!("CARGO_PKG_VERSION"));
     eprintln!();
 
-    let cli = Cli::parse();
-
     // Check data directory persistence before DB connect creates any files
     let data_dir = data_dir_from_database_url();
     let state_dir = data_dir.join(".betweenrows");
@@ -113,9 +139,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         None | Some(Commands::Serve) => {
             serve(auth, db, master_key, &state_dir).await?;
         }
+        Some(Commands::Mcp) => {
+            serve_mcp(auth, db, master_key, &state_dir).await?;
+        }
         Some(Commands::User { action }) => {
             handle_user_action(auth, action).await?;
         }
+        Some(Commands::Policy { action }) => {
+            handle_policy_action(&db, action).await?;
+        }
     }
 
     Ok(())
@@ -405,7 +437,7 @@ async fn serve(
     let engine_cache = EngineCache::new(db.clone(), master_key, wasm_runtime.clone());
 
     // ── Policy hook (shared between admin API and proxy handler) ──────────────
-    let policy_hook = PolicyHook::new(db.clone(), wasm_runtime.clone());
+    let policy_hook = PolicyHook::new(db.clone(), wasm_runtime.clone(), explain_level_from_env());
 
     // ── Admin REST API ────────────────────────────────────────────────────────
     let jwt_secret = resolve_string_secret("BR_ADMIN_JWT_SECRET", &state_dir.join("jwt_secret"));
@@ -438,6 +470,7 @@ async fn serve(
         policy_hook: Some(policy_hook.clone()),
         proxy_handler: Some(handler.clone()),
         wasm_runtime: wasm_runtime.clone(),
+        query_api: Arc::new(query_api_from_env()),
     };
 
     let admin_listener = TcpListener::bind(&admin_bind_addr).await?;
@@ -462,6 +495,35 @@ async fn serve(
         "Idle connection timeout configured"
     );
 
+    // ── Arrow Flight SQL front-end (shares auth, policies and audit with pgwire) ──
+    let flight_bind_addr =
+        std::env::var("BR_FLIGHT_BIND_ADDR").unwrap_or_else(|_| "127.0.0.1:5436".to_string());
+    let flight_server = FlightSqlServer::new(
+        auth.clone(),
+        engine_cache.clone(),
+        policy_hook.clone(),
+        handler.clone(),
+        idle_timeout,
+    );
+    let flight_listener = TcpListener::bind(&flight_bind_addr).await?;
+    tracing::info!(addr = %flight_bind_addr, "Flight SQL online");
+
+    let sweeper = flight_server.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            sweeper.sweep_expired();
+        }
+    });
+    tokio::spawn(async move {
+        tonic::transport::Server::builder()
+            .add_service(flight_server.into_service())
+            .serve_with_incoming(tonic::transport::server::TcpIncoming::from(flight_listener))
+            .await
+            .expect("Flight SQL server failed");
+    });
+
     let keepalive = TcpKeepalive::new()
         .with_time(Duration::from_secs(60))
         .with_interval(Duration::from_secs(10));
@@ -503,6 +565,77 @@ async fn serve(
     }
 }
 
+/// HTTP query API limits (a request may lower these, never raise them).
+fn query_api_from_env() -> QueryApi {
+    let query_max_rows: usize = std::env::var("BR_QUERY_MAX_ROWS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10_000);
+    let query_timeout_secs: u64 = std::env::var("BR_QUERY_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    QueryApi::new(query_max_rows, Duration::from_secs(query_timeout_secs))
+}
+
+/// What `EXPLAIN (POLICY)` shows to users who are not admins.
+fn explain_level_from_env() -> ExplainLevel {
+    match std::env::var("BR_EXPLAIN_POLICY") {
+        Ok(v) => v.trim().to_lowercase().parse().unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Ignoring BR_EXPLAIN_POLICY");
+            ExplainLevel::default()
+        }),
+        Err(_) => ExplainLevel::default(),
+    }
+}
+
+/// Serve MCP over stdio. No listeners are opened; the agent acts as the owner
+/// of `BR_MCP_API_KEY`, with the same policies and audit as the HTTP query API.
+async fn serve_mcp(
+    auth: Arc<Auth>,
+    db: sea_orm::DatabaseConnection,
+    master_key: [u8; 32],
+    state_dir: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let api_key = match std::env::var("BR_MCP_API_KEY") {
+        Ok(k) if !k.trim().is_empty() => k.trim().to_string(),
+        _ => {
+            eprintln!("FATAL: BR_MCP_API_KEY is not set. Create an API key for the agent's user.");
+            std::process::exit(1);
+        }
+    };
+    let Some(caller) = proxy::mcp::caller_for_api_key(&db, &api_key).await? else {
+        eprintln!("FATAL: BR_MCP_API_KEY is invalid, expired, or its user is inactive.");
+        std::process::exit(1);
+    };
+    tracing::info!(username = %caller.user.username, "MCP stdio session starting");
+
+    let wasm_runtime = Arc::new(
+        proxy::decision::wasm::WasmDecisionRuntime::new().expect("Failed to create WASM runtime"),
+    );
+    let engine_cache = EngineCache::new(db.clone(), master_key, wasm_runtime.clone());
+    let policy_hook = PolicyHook::new(db.clone(), wasm_runtime.clone(), explain_level_from_env());
+    let jwt_secret = resolve_string_secret("BR_ADMIN_JWT_SECRET", &state_dir.join("jwt_secret"));
+
+    let state = AdminState {
+        auth,
+        db,
+        jwt_secret,
+        jwt_expiry_hours: 1,
+        engine_cache,
+        master_key,
+        job_store: Arc::new(tokio::sync::Mutex::new(
+            proxy::admin::discovery_job::JobStore::new(),
+        )),
+        policy_hook: Some(policy_hook),
+        proxy_handler: None,
+        wasm_runtime,
+        query_api: Arc::new(query_api_from_env()),
+    };
+    proxy::mcp::serve_stdio(proxy::mcp::McpServer::new(state), api_key).await?;
+    Ok(())
+}
+
 async fn handle_user_action(
     auth: Arc<Auth>,
     action: UserAction,
@@ -520,6 +653,32 @@ async fn handle_user_action(
     Ok(())
 }
 
+async fn handle_policy_action(
+    db: &sea_orm::DatabaseConnection,
+    action: PolicyAction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        PolicyAction::Analyze { datasource, json } => {
+            use proxy::entity::data_source;
+
+            let ds = data_source::Entity::find()
+                .filter(data_source::Column::Name.eq(&datasource))
+                .one(db)
+                .await?
+                .ok_or_else(|| format!("data source '{datasource}' not found"))?;
+            let report = proxy::policy_analysis::analyze_datasource(db, ds.id)
+                .await?
+                .ok_or_else(|| format!("data source '{datasource}' not found"))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{report}");
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Recompile any decision functions that have JS source but no WASM bytecode.
 /// This happens after the migration clears old static WASM, ensuring all functions
 /// are compiled in dynamic mode and ready for query-time evaluation.
@@ -0,0 +1,1043 @@
+//! Built-in masking function library (`br_*` scalar UDFs).
+//!
+//! Registered on every `SessionContext` built by `EngineCache::build_user_context`
+//! (and on the throwaway context used for save-time validation), so `column_mask`
+//! expressions can call them like any other scalar function:
+//!
+//! | Function | Result |
+//! |---|---|
+//! | `br_mask_email(v)` | `a***@example.com` |
+//! | `br_mask_partial(v, keep_start, keep_end [, mask_char])` | `12*****89` |
+//! | `br_hash(v, key_ref)` | hex HMAC-SHA256 of `v` under a masking key |
+//! | `br_fpe_encrypt(v, key_ref)` | FF1 (AES-256, radix 10) over the digits of `v`; other characters stay in place |
+//! | `br_shift_date(d, subject, max_days [, key_ref])` | `d` shifted by a non-zero offset in `[-max_days, max_days]` derived from `subject` |
+//! | `br_generalize_number(n, bucket)` | lower bound of `n`'s bucket |
+//! | `br_pseudonymize(v, key_ref)` | `tok_` + 32 hex chars, stable for `v` under the key |
+//!
+//! Keyed functions (`br_hash`, `br_fpe_encrypt`, `br_shift_date`, `br_pseudonymize`) resolve
+//! `key_ref` by name against the [`MaskingKeyring`]; the key material lives
+//! encrypted in the `masking_key` table and never appears in policy text. They
+//! only run when planned from a policy expression ([`for_policy`]) — calling
+//! them directly in user SQL fails, so users cannot hash or encrypt guesses to
+//! reverse masked values. Only FPE output and pseudonyms are reversible, and only
+//! through the admin API for keys marked `reversible`: FPE decrypts with the key,
+//! while pseudonyms are one-way and are resolved from the `pseudonym_vault` table,
+//! which `br_pseudonymize` fills in the background for reversible keys.
+//!
+//! All functions are declared `Volatile` so DataFusion never pushes predicates
+//! that contain them down to the upstream database, which does not have them.
+
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use aes::Aes256;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use datafusion::arrow::array::{
+    Array, ArrayRef, Float64Array, Int32Array, Int64Array, StringArray,
+};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, TimeUnit};
+use datafusion::common::cast::{as_float64_array, as_int64_array, as_string_array};
+use datafusion::common::{Result, ScalarValue, exec_err, plan_err};
+use datafusion::logical_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl, Signature, Volatility,
+};
+use datafusion::prelude::SessionContext;
+use fpe::ff1::{FF1, FlexibleNumeralString};
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{DatabaseConnection, EntityTrait, Set};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::entity::{masking_key, pseudonym_vault};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Length of every masking key (AES-256 for FF1, HMAC-SHA256 for hashing and date shifting).
+pub const KEY_LEN: usize = 32;
+
+/// FF1 over radix 10 needs at least 6 numerals (radix^minlen ≥ 1,000,000).
+const FPE_MIN_DIGITS: usize = 6;
+
+/// Prefix of every `br_pseudonymize` token.
+pub const TOKEN_PREFIX: &str = "tok_";
+
+/// Upper bound on remembered vault entries before the dedupe set is reset.
+const VAULT_DEDUPE_CAP: usize = 100_000;
+
+// ---------- keyring ----------
+
+#[derive(Clone)]
+struct KeyMaterial {
+    id: Uuid,
+    version: i32,
+    reversible: bool,
+    secret: [u8; KEY_LEN],
+}
+
+/// A token → value mapping for the pseudonym vault.
+struct VaultEntry {
+    key_id: Uuid,
+    key_version: i32,
+    token: String,
+    value: String,
+}
+
+/// Decrypted masking keys by name, shared by every session's `br_*` UDFs.
+///
+/// UDFs look keys up at execution time, so reloading after an admin change
+/// takes effect on the next query without rebuilding sessions.
+pub struct MaskingKeyring {
+    master_key: [u8; 32],
+    keys: RwLock<HashMap<String, KeyMaterial>>,
+    /// Used by `br_shift_date` when no `key_ref` is given; derived from the master key.
+    default_shift_key: [u8; KEY_LEN],
+    loaded: AtomicBool,
+    /// Background writer for `pseudonym_vault`; unset until a DB is available.
+    vault: OnceLock<mpsc::UnboundedSender<VaultEntry>>,
+    /// `(key_id, token)` pairs already sent to the vault writer.
+    vaulted: Mutex<HashSet<(Uuid, String)>>,
+}
+
+impl MaskingKeyring {
+    pub fn new(master_key: [u8; 32]) -> Arc<Self> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"br_shift_date:");
+        hasher.update(master_key);
+        Arc::new(Self {
+            master_key,
+            keys: RwLock::new(HashMap::new()),
+            default_shift_key: hasher.finalize().into(),
+            loaded: AtomicBool::new(false),
+            vault: OnceLock::new(),
+            vaulted: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Reload every key from the admin DB. Keys that fail to decrypt are skipped
+    /// (and logged), so expressions referencing them fail closed.
+    pub async fn reload(&self, db: &DatabaseConnection) -> std::result::Result<(), String> {
+        let rows = masking_key::Entity::find()
+            .all(db)
+            .await
+            .map_err(|e| format!("DB error loading masking keys: {e}"))?;
+        let mut keys = HashMap::with_capacity(rows.len());
+        for row in rows {
+            match decrypt_secret(&row.secret_encrypted, &self.master_key) {
+                Ok(secret) => {
+                    keys.insert(
+                        row.name,
+                        KeyMaterial {
+                            id: row.id,
+                            version: row.version,
+                            reversible: row.reversible,
+                            secret,
+                        },
+                    );
+                }
+                Err(e) => {
+                    tracing::error!(key = %row.name, error = %e, "Failed to decrypt masking key")
+                }
+            }
+        }
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys;
+        // Rotation or a reversible flip changes what must be vaulted.
+        self.vaulted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self.loaded.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Load keys on first use and start the pseudonym vault writer.
+    pub async fn ensure_loaded(&self, db: &DatabaseConnection) -> std::result::Result<(), String> {
+        self.vault
+            .get_or_init(|| spawn_vault_writer(db.clone(), self.master_key));
+        if self.loaded.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        self.reload(db).await
+    }
+
+    /// Queue a token for the vault unless it was already queued. No-op without a writer.
+    fn record_pseudonym(&self, key: &KeyMaterial, token: &str, value: &str) {
+        let Some(tx) = self.vault.get() else {
+            return;
+        };
+        {
+            let mut vaulted = self.vaulted.lock().unwrap_or_else(|e| e.into_inner());
+            if vaulted.len() >= VAULT_DEDUPE_CAP {
+                vaulted.clear();
+            }
+            if !vaulted.insert((key.id, token.to_string())) {
+                return;
+            }
+        }
+        let _ = tx.send(VaultEntry {
+            key_id: key.id,
+            key_version: key.version,
+            token: token.to_string(),
+            value: value.to_string(),
+        });
+    }
+}
+
+/// Persist vault entries in the background so masking never waits on the admin DB.
+/// Existing `(key, token)` rows are left alone.
+fn spawn_vault_writer(
+    db: DatabaseConnection,
+    master_key: [u8; 32],
+) -> mpsc::UnboundedSender<VaultEntry> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<VaultEntry>();
+    tokio::spawn(async move {
+        while let Some(entry) = rx.recv().await {
+            let value_encrypted =
+                match crate::crypto::encrypt_json(&serde_json::json!(entry.value), &master_key) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        tracing::error!(error = %e, "Failed to encrypt pseudonym vault entry");
+                        continue;
+                    }
+                };
+            let row = pseudonym_vault::ActiveModel {
+                id: Set(Uuid::now_v7()),
+                masking_key_id: Set(entry.key_id),
+                key_version: Set(entry.key_version),
+                token: Set(entry.token),
+                value_encrypted: Set(value_encrypted),
+                created_at: Set(chrono::Utc::now().naive_utc()),
+            };
+            let result = pseudonym_vault::Entity::insert(row)
+                .on_conflict(
+                    OnConflict::columns([
+                        pseudonym_vault::Column::MaskingKeyId,
+                        pseudonym_vault::Column::Token,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .exec_without_returning(&db)
+                .await;
+            if let Err(e) = result {
+                tracing::error!(error = %e, "Failed to write pseudonym vault entry");
+            }
+        }
+    });
+    tx
+}
+
+/// Expand a `column_mask` definition in `pseudonymize` mode,
+/// `{"pseudonymize": {"key": "crm"}}`, into the equivalent inline definition
+/// `{"mask_expression": "br_pseudonymize({col}, 'crm')"}`. Any other definition
+/// is returned unchanged.
+pub fn expand_mask_mode(definition: Value) -> Value {
+    if definition.get("mask_expression").is_some() {
+        return definition;
+    }
+    match definition
+        .get("pseudonymize")
+        .and_then(|m| m.get("key"))
+        .and_then(Value::as_str)
+    {
+        Some(key) => serde_json::json!({
+            "mask_expression": format!(
+                "br_pseudonymize({}, '{}')",
+                crate::policy_template::COLUMN_PLACEHOLDER,
+                key.replace('\'', "''")
+            )
+        }),
+        None => definition,
+    }
+}
+
+/// Generate fresh random key material.
+pub fn generate_secret() -> [u8; KEY_LEN] {
+    let mut secret = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Decode admin-supplied key material (base64, exactly 32 bytes).
+pub fn decode_secret(encoded: &str) -> std::result::Result<[u8; KEY_LEN], String> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| "secret must be base64-encoded".to_string())?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("secret must decode to exactly {KEY_LEN} bytes"))
+}
+
+/// Encrypt key material for storage in `masking_key.secret_encrypted`.
+pub fn encrypt_secret(
+    secret: &[u8; KEY_LEN],
+    master_key: &[u8; 32],
+) -> std::result::Result<String, String> {
+    crate::crypto::encrypt_json(&serde_json::json!(STANDARD.encode(secret)), master_key)
+        .map_err(|e| e.to_string())
+}
+
+/// Decrypt key material stored in `masking_key.secret_encrypted`.
+pub fn decrypt_secret(
+    encrypted: &str,
+    master_key: &[u8; 32],
+) -> std::result::Result<[u8; KEY_LEN], String> {
+    let value = crate::crypto::decrypt_json(encrypted, master_key).map_err(|e| e.to_string())?;
+    let encoded = value
+        .as_str()
+        .ok_or_else(|| "stored masking key is not a string".to_string())?;
+    decode_secret(encoded)
+}
+
+// ---------- pure helpers ----------
+
+fn mask_email(value: &str) -> String {
+    match value.rsplit_once('@') {
+        Some((local, domain)) if !local.is_empty() => {
+            let first = local.chars().next().unwrap_or('*');
+            format!("{first}***@{domain}")
+        }
+        _ => "*".repeat(value.chars().count()),
+    }
+}
+
+/// Keep `keep_start` leading and `keep_end` trailing characters. Values too
+/// short to hide anything are masked entirely rather than shown in full.
+fn mask_partial(value: &str, keep_start: usize, keep_end: usize, mask: char) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if keep_start + keep_end >= chars.len() {
+        return mask.to_string().repeat(chars.len());
+    }
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            if i < keep_start || i >= chars.len() - keep_end {
+                *c
+            } else {
+                mask
+            }
+        })
+        .collect()
+}
+
+fn hmac(key: &[u8; KEY_LEN], value: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(value);
+    mac.finalize().into_bytes().into()
+}
+
+fn hash_hex(key: &[u8; KEY_LEN], value: &str) -> String {
+    hmac(key, value.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Pseudonym for `value`: a truncated HMAC, domain-separated from `br_hash` so
+/// the two outputs cannot be linked even under the same key.
+fn pseudonym_token(key: &[u8; KEY_LEN], value: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(b"br_pseudonymize:");
+    mac.update(value.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest[..16].iter().map(|b| format!("{b:02x}")).collect();
+    format!("{TOKEN_PREFIX}{hex}")
+}
+
+/// Apply FF1 to the digits of `value`, leaving every other character in place.
+/// Returns `None` when there are too few digits for FF1.
+fn fpe_digits(
+    ff1: &FF1<Aes256>,
+    value: &str,
+    decrypt: bool,
+) -> std::result::Result<Option<String>, String> {
+    let digits: Vec<u16> = value
+        .chars()
+        .filter_map(|c| c.to_digit(10).map(|d| d as u16))
+        .collect();
+    if digits.len() < FPE_MIN_DIGITS {
+        return Ok(None);
+    }
+    let input = FlexibleNumeralString::from(digits);
+    let output: Vec<u16> = if decrypt {
+        ff1.decrypt(&[], &input)
+    } else {
+        ff1.encrypt(&[], &input)
+    }
+    .map_err(|e| e.to_string())?
+    .into();
+    let mut out = output.into_iter();
+    Ok(Some(
+        value
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(_) => char::from_digit(u32::from(out.next().unwrap_or(0)), 10).unwrap_or(c),
+                None => c,
+            })
+            .collect(),
+    ))
+}
+
+fn new_ff1(key: &[u8; KEY_LEN]) -> FF1<Aes256> {
+    FF1::<Aes256>::new(key, 10).expect("radix 10 is valid for FF1")
+}
+
+/// Reverse `br_fpe_encrypt` for the admin reveal endpoint.
+pub fn fpe_decrypt(
+    key: &[u8; KEY_LEN],
+    value: &str,
+) -> std::result::Result<Option<String>, String> {
+    fpe_digits(&new_ff1(key), value, true)
+}
+
+/// Deterministic non-zero day offset in `[-max_days, max_days]` for `subject`.
+fn shift_days(key: &[u8; KEY_LEN], subject: &str, max_days: i64) -> i64 {
+    let digest = hmac(key, subject.as_bytes());
+    let n = u64::from_be_bytes(digest[..8].try_into().expect("8 bytes"));
+    let magnitude = 1 + (n % max_days as u64) as i64;
+    if digest[8] & 1 == 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+// ---------- UDFs ----------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MaskFn {
+    MaskEmail,
+    MaskPartial,
+    Hash,
+    FpeEncrypt,
+    ShiftDate,
+    GeneralizeNumber,
+    Pseudonymize,
+}
+
+impl MaskFn {
+    const ALL: [MaskFn; 7] = [
+        MaskFn::MaskEmail,
+        MaskFn::MaskPartial,
+        MaskFn::Hash,
+        MaskFn::FpeEncrypt,
+        MaskFn::ShiftDate,
+        MaskFn::GeneralizeNumber,
+        MaskFn::Pseudonymize,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            MaskFn::MaskEmail => "br_mask_email",
+            MaskFn::MaskPartial => "br_mask_partial",
+            MaskFn::Hash => "br_hash",
+            MaskFn::FpeEncrypt => "br_fpe_encrypt",
+            MaskFn::ShiftDate => "br_shift_date",
+            MaskFn::GeneralizeNumber => "br_generalize_number",
+            MaskFn::Pseudonymize => "br_pseudonymize",
+        }
+    }
+
+    fn is_keyed(self) -> bool {
+        matches!(
+            self,
+            MaskFn::Hash | MaskFn::FpeEncrypt | MaskFn::ShiftDate | MaskFn::Pseudonymize
+        )
+    }
+}
+
+#[derive(Clone)]
+struct MaskingUdf {
+    func: MaskFn,
+    signature: Signature,
+    keyring: Arc<MaskingKeyring>,
+    /// Set only on instances planned from a policy expression; keyed functions
+    /// refuse to run otherwise.
+    trusted: bool,
+}
+
+impl std::fmt::Debug for MaskingUdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaskingUdf")
+            .field("func", &self.func)
+            .field("trusted", &self.trusted)
+            .finish()
+    }
+}
+
+impl PartialEq for MaskingUdf {
+    fn eq(&self, other: &Self) -> bool {
+        self.func == other.func
+            && self.trusted == other.trusted
+            && Arc::ptr_eq(&self.keyring, &other.keyring)
+    }
+}
+
+impl Eq for MaskingUdf {}
+
+impl std::hash::Hash for MaskingUdf {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.func.hash(state);
+        self.trusted.hash(state);
+    }
+}
+
+fn is_integer(t: &DataType) -> bool {
+    t.is_integer() || matches!(t, DataType::Null)
+}
+
+impl MaskingUdf {
+    fn new(func: MaskFn, keyring: Arc<MaskingKeyring>) -> Self {
+        Self {
+            func,
+            signature: Signature::user_defined(Volatility::Volatile),
+            keyring,
+            trusted: false,
+        }
+    }
+
+    fn check_arity(&self, n: usize, allowed: &[usize]) -> Result<()> {
+        if allowed.contains(&n) {
+            return Ok(());
+        }
+        let expected = allowed
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join(" or ");
+        plan_err!("{} expects {expected} arguments, got {n}", self.func.name())
+    }
+
+    fn key<'a>(
+        &self,
+        keys: &'a HashMap<String, KeyMaterial>,
+        name: &str,
+    ) -> Result<&'a [u8; KEY_LEN]> {
+        Ok(&self.key_material(keys, name)?.secret)
+    }
+
+    fn key_material<'a>(
+        &self,
+        keys: &'a HashMap<String, KeyMaterial>,
+        name: &str,
+    ) -> Result<&'a KeyMaterial> {
+        match keys.get(name) {
+            Some(k) => Ok(k),
+            None => exec_err!("Unknown masking key '{name}' in {}", self.func.name()),
+        }
+    }
+
+    fn evaluate(&self, args: &[ArrayRef], return_type: &DataType) -> Result<ArrayRef> {
+        if self.func.is_keyed() && !self.trusted {
+            return exec_err!(
+                "{} can only be used in column mask policies",
+                self.func.name()
+            );
+        }
+        let keys = self.keyring.keys.read().unwrap_or_else(|e| e.into_inner());
+        match self.func {
+            MaskFn::MaskEmail => {
+                let values = utf8(&args[0])?;
+                let out: StringArray = values.iter().map(|v| v.map(mask_email)).collect();
+                Ok(Arc::new(out))
+            }
+            MaskFn::MaskPartial => {
+                let values = utf8(&args[0])?;
+                let starts = as_int64_array(&args[1])?;
+                let ends = as_int64_array(&args[2])?;
+                let masks = args.get(3).map(utf8).transpose()?;
+                let mut out = Vec::with_capacity(values.len());
+                for i in 0..values.len() {
+                    if values.is_null(i) || starts.is_null(i) || ends.is_null(i) {
+                        out.push(None);
+                        continue;
+                    }
+                    let (start, end) = (starts.value(i), ends.value(i));
+                    if start < 0 || end < 0 {
+                        return exec_err!("br_mask_partial keep counts must not be negative");
+                    }
+                    let mask = match &masks {
+                        Some(m) if !m.is_null(i) => {
+                            let mut chars = m.value(i).chars();
+                            match (chars.next(), chars.next()) {
+                                (Some(c), None) => c,
+                                _ => {
+                                    return exec_err!(
+                                        "br_mask_partial mask_char must be a single character"
+                                    );
+                                }
+                            }
+                        }
+                        _ => '*',
+                    };
+                    out.push(Some(mask_partial(
+                        values.value(i),
+                        start as usize,
+                        end as usize,
+                        mask,
+                    )));
+                }
+                Ok(Arc::new(StringArray::from(out)))
+            }
+            MaskFn::Hash => {
+                let values = utf8(&args[0])?;
+                let refs = utf8(&args[1])?;
+                let mut out = Vec::with_capacity(values.len());
+                for i in 0..values.len() {
+                    if values.is_null(i) {
+                        out.push(None);
+                        continue;
+                    }
+                    if refs.is_null(i) {
+                        return exec_err!("br_hash key_ref must not be NULL");
+                    }
+                    let key = self.key(&keys, refs.value(i))?;
+                    out.push(Some(hash_hex(key, values.value(i))));
+                }
+                Ok(Arc::new(StringArray::from(out)))
+            }
+            MaskFn::Pseudonymize => {
+                let values = utf8(&args[0])?;
+                let refs = utf8(&args[1])?;
+                let mut out = Vec::with_capacity(values.len());
+                for i in 0..values.len() {
+                    if values.is_null(i) {
+                        out.push(None);
+                        continue;
+                    }
+                    if refs.is_null(i) {
+                        return exec_err!("br_pseudonymize key_ref must not be NULL");
+                    }
+                    let key = self.key_material(&keys, refs.value(i))?;
+                    let token = pseudonym_token(&key.secret, values.value(i));
+                    if key.reversible {
+                        self.keyring.record_pseudonym(key, &token, values.value(i));
+                    }
+                    out.push(Some(token));
+                }
+                Ok(Arc::new(StringArray::from(out)))
+            }
+            MaskFn::FpeEncrypt => {
+                let values = utf8(&args[0])?;
+                let refs = utf8(&args[1])?;
+                let mut ciphers: HashMap<&str, FF1<Aes256>> = HashMap::new();
+                let mut out = Vec::with_capacity(values.len());
+                for i in 0..values.len() {
+                    if values.is_null(i) {
+                        out.push(None);
+                        continue;
+                    }
+                    if refs.is_null(i) {
+                        return exec_err!("br_fpe_encrypt key_ref must not be NULL");
+                    }
+                    let name = refs.value(i);
+                    if !ciphers.contains_key(name) {
+                        let key = self.key(&keys, name)?;
+                        ciphers.insert(name, new_ff1(key));
+                    }
+                    let encrypted =
+                        fpe_digits(&ciphers[name], values.value(i), false).map_err(|e| {
+                            datafusion::error::DataFusionError::Execution(format!(
+                                "br_fpe_encrypt: {e}"
+                            ))
+                        })?;
+                    out.push(encrypted);
+                }
+                Ok(Arc::new(StringArray::from(out)))
+            }
+            MaskFn::ShiftDate => {
+                let subjects = utf8(&args[1])?;
+                let max_days = as_int64_array(&args[2])?;
+                let refs = args.get(3).map(utf8).transpose()?;
+                let per_day: i64 = match return_type {
+                    DataType::Date32 => 1,
+                    DataType::Date64 => 86_400_000,
+                    DataType::Timestamp(TimeUnit::Second, _) => 86_400,
+                    DataType::Timestamp(TimeUnit::Millisecond, _) => 86_400_000,
+                    DataType::Timestamp(TimeUnit::Microsecond, _) => 86_400_000_000,
+                    DataType::Timestamp(TimeUnit::Nanosecond, _) => 86_400_000_000_000,
+                    other => return exec_err!("br_shift_date does not support {other}"),
+                };
+                let raw = if matches!(return_type, DataType::Date32) {
+                    cast(&cast(&args[0], &DataType::Int32)?, &DataType::Int64)?
+                } else {
+                    cast(&args[0], &DataType::Int64)?
+                };
+                let raw = as_int64_array(&raw)?;
+                let mut out = Vec::with_capacity(raw.len());
+                for i in 0..raw.len() {
+                    if raw.is_null(i) || subjects.is_null(i) || max_days.is_null(i) {
+                        out.push(None);
+                        continue;
+                    }
+                    let max = max_days.value(i);
+                    if max < 1 {
+                        return exec_err!("br_shift_date max_days must be at least 1");
+                    }
+                    let key = match &refs {
+                        Some(r) if !r.is_null(i) => self.key(&keys, r.value(i))?,
+                        _ => &self.keyring.default_shift_key,
+                    };
+                    let days = shift_days(key, subjects.value(i), max);
+                    out.push(Some(raw.value(i) + days * per_day));
+                }
+                if matches!(return_type, DataType::Date32) {
+                    let days: Int32Array = out.into_iter().map(|v| v.map(|d| d as i32)).collect();
+                    Ok(cast(&(Arc::new(days) as ArrayRef), return_type)?)
+                } else {
+                    Ok(cast(
+                        &(Arc::new(Int64Array::from(out)) as ArrayRef),
+                        return_type,
+                    )?)
+                }
+            }
+            MaskFn::GeneralizeNumber => match return_type {
+                DataType::Int64 => {
+                    let values = as_int64_array(&args[0])?;
+                    let buckets = as_int64_array(&args[1])?;
+                    let mut out = Vec::with_capacity(values.len());
+                    for i in 0..values.len() {
+                        if values.is_null(i) || buckets.is_null(i) {
+                            out.push(None);
+                            continue;
+                        }
+                        let bucket = buckets.value(i);
+                        if bucket <= 0 {
+                            return exec_err!("br_generalize_number bucket must be positive");
+                        }
+                        out.push(Some(values.value(i).div_euclid(bucket) * bucket));
+                    }
+                    Ok(Arc::new(Int64Array::from(out)))
+                }
+                _ => {
+                    let values = as_float64_array(&args[0])?;
+                    let buckets = as_float64_array(&args[1])?;
+                    let mut out = Vec::with_capacity(values.len());
+                    for i in 0..values.len() {
+                        if values.is_null(i) || buckets.is_null(i) {
+                            out.push(None);
+                            continue;
+                        }
+                        let bucket = buckets.value(i);
+                        if bucket <= 0.0 || !bucket.is_finite() {
+                            return exec_err!("br_generalize_number bucket must be positive");
+                        }
+                        out.push(Some((values.value(i) / bucket).floor() * bucket));
+                    }
+                    Ok(Arc::new(Float64Array::from(out)))
+                }
+            },
+        }
+    }
+}
+
+/// View an argument as a Utf8 array (after coercion it already is one).
+fn utf8(array: &ArrayRef) -> Result<StringArray> {
+    let cast_array = cast(array, &DataType::Utf8)?;
+    Ok(as_string_array(&cast_array)?.clone())
+}
+
+impl ScalarUDFImpl for MaskingUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.func.name()
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let n = arg_types.len();
+        match self.func {
+            MaskFn::MaskEmail => {
+                self.check_arity(n, &[1])?;
+                Ok(vec![DataType::Utf8])
+            }
+            MaskFn::MaskPartial => {
+                self.check_arity(n, &[3, 4])?;
+                let mut types = vec![DataType::Utf8, DataType::Int64, DataType::Int64];
+                if n == 4 {
+                    types.push(DataType::Utf8);
+                }
+                Ok(types)
+            }
+            MaskFn::Hash | MaskFn::FpeEncrypt | MaskFn::Pseudonymize => {
+                self.check_arity(n, &[2])?;
+                Ok(vec![DataType::Utf8, DataType::Utf8])
+            }
+            MaskFn::ShiftDate => {
+                self.check_arity(n, &[3, 4])?;
+                let date_type = match &arg_types[0] {
+                    t @ (DataType::Date32 | DataType::Date64 | DataType::Timestamp(_, _)) => {
+                        t.clone()
+                    }
+                    DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View | DataType::Null => {
+                        DataType::Date32
+                    }
+                    other => {
+                        return plan_err!("br_shift_date expects a date or timestamp, got {other}");
+                    }
+                };
+                let mut types = vec![date_type, DataType::Utf8, DataType::Int64];
+                if n == 4 {
+                    types.push(DataType::Utf8);
+                }
+                Ok(types)
+            }
+            MaskFn::GeneralizeNumber => {
+                self.check_arity(n, &[2])?;
+                if is_integer(&arg_types[0]) && is_integer(&arg_types[1]) {
+                    Ok(vec![DataType::Int64, DataType::Int64])
+                } else if arg_types
+                    .iter()
+                    .all(|t| t.is_numeric() || matches!(t, DataType::Null))
+                {
+                    Ok(vec![DataType::Float64, DataType::Float64])
+                } else {
+                    plan_err!("br_generalize_number expects numeric arguments")
+                }
+            }
+        }
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        match self.func {
+            MaskFn::ShiftDate | MaskFn::GeneralizeNumber => Ok(arg_types[0].clone()),
+            _ => Ok(DataType::Utf8),
+        }
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> Result<ColumnarValue> {
+        let all_scalar = args
+            .args
+            .iter()
+            .all(|a| matches!(a, ColumnarValue::Scalar(_)));
+        let arrays = ColumnarValue::values_to_arrays(&args.args)?;
+        let out = self.evaluate(&arrays, args.return_field.data_type())?;
+        if all_scalar {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(&out, 0)?))
+        } else {
+            Ok(ColumnarValue::Array(out))
+        }
+    }
+}
+
+/// Register the `br_*` functions on a session context.
+pub fn register_udfs(ctx: &SessionContext, keyring: &Arc<MaskingKeyring>) {
+    for func in MaskFn::ALL {
+        ctx.register_udf(ScalarUDF::new_from_impl(MaskingUdf::new(
+            func,
+            Arc::clone(keyring),
+        )));
+    }
+}
+
+/// Return the policy-trusted variant of a `br_*` UDF (any other UDF is returned
+/// unchanged). Called when planning mask expressions so keyed functions run only
+/// where an admin put them.
+pub fn for_policy(udf: Arc<ScalarUDF>) -> Arc<ScalarUDF> {
+    match udf.inner().as_any().downcast_ref::<MaskingUdf>() {
+        Some(m) if !m.trusted => Arc::new(ScalarUDF::new_from_impl(MaskingUdf {
+            trusted: true,
+            ..m.clone()
+        })),
+        _ => udf,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::logical_expr::registry::FunctionRegistry;
+
+    fn keyring_with(name: &str, secret: [u8; KEY_LEN]) -> Arc<MaskingKeyring> {
+        let keyring = MaskingKeyring::new([7u8; 32]);
+        keyring.keys.write().unwrap().insert(
+            name.to_string(),
+            KeyMaterial {
+                id: Uuid::nil(),
+                version: 1,
+                reversible: false,
+                secret,
+            },
+        );
+        keyring
+    }
+
+    async fn run(ctx: &SessionContext, sql: &str) -> Vec<Option<String>> {
+        let batches = ctx.sql(sql).await.unwrap().collect().await.unwrap();
+        let mut out = vec![];
+        for b in batches {
+            let col = cast(b.column(0), &DataType::Utf8).unwrap();
+            let col = as_string_array(&col).unwrap();
+            for i in 0..col.len() {
+                out.push((!col.is_null(i)).then(|| col.value(i).to_string()));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn mask_email_keeps_first_char_and_domain() {
+        assert_eq!(mask_email("alice@example.com"), "a***@example.com");
+        assert_eq!(mask_email("x@y.io"), "x***@y.io");
+        assert_eq!(mask_email("not-an-email"), "************");
+        assert_eq!(mask_email("@example.com"), "************");
+    }
+
+    #[test]
+    fn mask_partial_fails_closed_on_short_values() {
+        assert_eq!(mask_partial("123456789", 2, 2, '*'), "12*****89");
+        assert_eq!(mask_partial("abcd", 0, 4, '#'), "####");
+        assert_eq!(mask_partial("abcd", 0, 0, '*'), "****");
+    }
+
+    #[test]
+    fn fpe_preserves_format_and_round_trips() {
+        let key = [3u8; KEY_LEN];
+        let ff1 = new_ff1(&key);
+        let enc = fpe_digits(&ff1, "123-45-6789", false).unwrap().unwrap();
+        assert_ne!(enc, "123-45-6789");
+        assert_eq!(enc.len(), 11);
+        assert_eq!(&enc[3..4], "-");
+        assert_eq!(&enc[6..7], "-");
+        assert!(
+            enc.chars()
+                .filter(|c| *c != '-')
+                .all(|c| c.is_ascii_digit())
+        );
+        assert_eq!(
+            fpe_decrypt(&key, &enc).unwrap().as_deref(),
+            Some("123-45-6789")
+        );
+        assert_eq!(fpe_digits(&ff1, "12-34", false).unwrap(), None);
+    }
+
+    #[test]
+    fn shift_days_is_deterministic_nonzero_and_bounded() {
+        let key = [9u8; KEY_LEN];
+        for subject in ["a", "b", "patient-42", ""] {
+            let d = shift_days(&key, subject, 30);
+            assert_eq!(d, shift_days(&key, subject, 30));
+            assert!(d != 0 && d.abs() <= 30, "offset {d} out of range");
+        }
+    }
+
+    #[test]
+    fn pseudonym_tokens_are_stable_keyed_and_unlinkable_to_hashes() {
+        let (a, b) = ([1u8; KEY_LEN], [2u8; KEY_LEN]);
+        let token = pseudonym_token(&a, "alice@example.com");
+        assert_eq!(token, pseudonym_token(&a, "alice@example.com"));
+        assert_ne!(token, pseudonym_token(&b, "alice@example.com"));
+        assert_ne!(token, pseudonym_token(&a, "bob@example.com"));
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + 32);
+        assert!(!hash_hex(&a, "alice@example.com").contains(&token[TOKEN_PREFIX.len()..]));
+    }
+
+    #[test]
+    fn pseudonymize_mode_expands_to_mask_expression() {
+        assert_eq!(
+            expand_mask_mode(serde_json::json!({"pseudonymize": {"key": "crm"}})),
+            serde_json::json!({"mask_expression": "br_pseudonymize({col}, 'crm')"})
+        );
+        let inline = serde_json::json!({"mask_expression": "'***'"});
+        assert_eq!(expand_mask_mode(inline.clone()), inline);
+    }
+
+    #[test]
+    fn secrets_round_trip_through_encryption() {
+        let master = [1u8; 32];
+        let secret = generate_secret();
+        let stored = encrypt_secret(&secret, &master).unwrap();
+        assert_eq!(decrypt_secret(&stored, &master).unwrap(), secret);
+        assert!(decode_secret("c2hvcnQ=").is_err());
+    }
+
+    #[tokio::test]
+    async fn keyless_functions_run_in_user_sql() {
+        let ctx = SessionContext::new();
+        register_udfs(&ctx, &MaskingKeyring::new([0u8; 32]));
+        assert_eq!(
+            run(&ctx, "SELECT br_mask_email('bob@corp.io')").await,
+            vec![Some("b***@corp.io".to_string())]
+        );
+        assert_eq!(
+            run(&ctx, "SELECT br_mask_partial('4111111111111111', 0, 4)").await,
+            vec![Some("************1111".to_string())]
+        );
+        assert_eq!(
+            run(&ctx, "SELECT br_generalize_number(37, 10)").await,
+            vec![Some("30".to_string())]
+        );
+        assert_eq!(
+            run(&ctx, "SELECT br_generalize_number(-1.5, 1)").await,
+            vec![Some("-2.0".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn keyed_functions_require_policy_planning() {
+        let ctx = SessionContext::new();
+        register_udfs(&ctx, &keyring_with("salt", [5u8; KEY_LEN]));
+        let err = ctx
+            .sql("SELECT br_hash('x', 'salt')")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("can only be used in column mask policies"),
+            "{err}"
+        );
+
+        let udf = for_policy(ctx.udf("br_hash").unwrap());
+        let expr = udf.call(vec![
+            datafusion::prelude::lit("x"),
+            datafusion::prelude::lit("salt"),
+        ]);
+        let df = ctx
+            .sql("SELECT 1")
+            .await
+            .unwrap()
+            .select(vec![expr])
+            .unwrap();
+        let batches = df.collect().await.unwrap();
+        let out = as_string_array(batches[0].column(0))
+            .unwrap()
+            .value(0)
+            .to_string();
+        assert_eq!(out, hash_hex(&[5u8; KEY_LEN], "x"));
+
+        let missing = for_policy(ctx.udf("br_hash").unwrap()).call(vec![
+            datafusion::prelude::lit("x"),
+            datafusion::prelude::lit("nope"),
+        ]);
+        let err = ctx
+            .sql("SELECT 1")
+            .await
+            .unwrap()
+            .select(vec![missing])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Unknown masking key 'nope'"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,1009 @@
+//! Embedded Model Context Protocol (MCP) server.
+//!
+//! Lets AI agents query governed data without a PostgreSQL driver. The server
+//! speaks JSON-RPC 2.0 over two transports:
+//! - **stdio** — `proxy mcp`, launched by the agent as a subprocess; the caller is
+//!   the owner of the API key in `BR_MCP_API_KEY`.
+//! - **streamable HTTP** — `POST /api/v1/mcp` on the admin listener, authenticated
+//!   like the HTTP query API (API key or query token). Responses are plain JSON;
+//!   no server-initiated SSE stream is offered.
+//!
+//! Query tools (`list_datasources`, `describe_schema`, `execute_query`) run under the
+//! caller's own identity through the same code as `POST /api/v1/query`, so every
+//! statement goes through `PolicyHook` and is audited with
+//! `client_info = "mcp:<agent>"`, where `<agent>` is the `clientInfo.name` sent in
+//! `initialize`.
+//!
+//! Admin tools are only listed for admin users calling with an API key that carries
+//! the matching scope (see `api_key_handlers::ADMIN_SCOPES`). They are dispatched
+//! in-process to the admin REST API under a short-lived admin token for the key
+//! owner, so validation, cache invalidation and the admin audit log are exactly
+//! those of the REST endpoints.
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderMap, HeaderValue, Method, Request, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::Utc;
+use rand_core::{OsRng, RngCore};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde_json::{Map, Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tower::ServiceExt;
+
+use crate::admin::api_key_handlers::{
+    SCOPE_ADMIN_AUDIT, SCOPE_ADMIN_DISCOVERY, SCOPE_ADMIN_POLICIES,
+};
+use crate::admin::jwt::{Claims, QueryUser, encode_jwt};
+use crate::admin::query_handlers::{
+    describe_visible_schema, execute_capped, json_rows, result_columns,
+};
+use crate::admin::{AdminState, api_v1_router};
+use crate::entity::{data_source, proxy_user};
+
+/// Protocol revision this server implements.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Older revisions whose tool surface is identical to ours.
+const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &[PROTOCOL_VERSION, "2025-03-26", "2024-11-05"];
+
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Default row cap for `execute_query` (agents rarely need more; the server cap still applies).
+const DEFAULT_TOOL_MAX_ROWS: usize = 500;
+
+/// Lifetime of the admin token minted for one admin tool call.
+const ADMIN_TOKEN_TTL_SECS: u64 = 60;
+
+const MAX_AGENT_NAME_LEN: usize = 64;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+/// Server-defined: the stdio API key stopped being valid mid-session.
+const UNAUTHORIZED: i64 = -32001;
+
+/// The authenticated principal behind an MCP session.
+pub struct McpCaller {
+    pub user: proxy_user::Model,
+    /// API-key scopes; empty for query tokens.
+    pub scopes: Vec<String>,
+}
+
+/// An admin tool: a thin mapping onto one admin REST endpoint.
+struct AdminTool {
+    name: &'static str,
+    description: &'static str,
+    scope: &'static str,
+    method: Method,
+    /// Path under `/api/v1`, with `{param}` placeholders taken from the tool arguments.
+    path: &'static str,
+}
+
+const ADMIN_TOOLS: &[AdminTool] = &[
+    AdminTool {
+        name: "admin_list_policies",
+        description: "List policies. Optional `query` object: page, page_size, search.",
+        scope: SCOPE_ADMIN_POLICIES,
+        method: Method::GET,
+        path: "/policies",
+    },
+    AdminTool {
+        name: "admin_get_policy",
+        description: "Get one policy by id, including its assignments.",
+        scope: SCOPE_ADMIN_POLICIES,
+        method: Method::GET,
+        path: "/policies/{id}",
+    },
+    AdminTool {
+        name: "admin_create_policy",
+        description: "Create a policy. `body` is the POST /policies request.",
+        scope: SCOPE_ADMIN_POLICIES,
+        method: Method::POST,
+        path: "/policies",
+    },
+    AdminTool {
+        name: "admin_update_policy",
+        description: "Update a policy. `body` is the PUT /policies/{id} request (include `version`).",
+        scope: SCOPE_ADMIN_POLICIES,
+        method: Method::PUT,
+        path: "/policies/{id}",
+    },
+    AdminTool {
+        name: "admin_delete_policy",
+        description: "Delete a policy and all of its assignments.",
+        scope: SCOPE_ADMIN_POLICIES,
+        method: Method::DELETE,
+        path: "/policies/{id}",
+    },
+    AdminTool {
+        name: "admin_assign_policy",
+        description: "Assign a policy on a datasource. `body` is the POST /datasources/{id}/policies request.",
+        scope: SCOPE_ADMIN_POLICIES,
+        method: Method::POST,
+        path: "/datasources/{datasource_id}/policies",
+    },
+    AdminTool {
+        name: "admin_unassign_policy",
+        description: "Remove a policy assignment from a datasource.",
+        scope: SCOPE_ADMIN_POLICIES,
+        method: Method::DELETE,
+        path: "/datasources/{datasource_id}/policies/{assignment_id}",
+    },
+    AdminTool {
+        name: "admin_list_datasources",
+        description: "List datasources with their ids.",
+        scope: SCOPE_ADMIN_DISCOVERY,
+        method: Method::GET,
+        path: "/datasources",
+    },
+    AdminTool {
+        name: "admin_discover",
+        description: "Start a catalog discovery job. `body` is the POST /datasources/{id}/discover request.",
+        scope: SCOPE_ADMIN_DISCOVERY,
+        method: Method::POST,
+        path: "/datasources/{datasource_id}/discover",
+    },
+    AdminTool {
+        name: "admin_discovery_status",
+        description: "Get the status and result of a discovery job.",
+        scope: SCOPE_ADMIN_DISCOVERY,
+        method: Method::GET,
+        path: "/datasources/{datasource_id}/discover/{job_id}",
+    },
+    AdminTool {
+        name: "admin_search_query_audit",
+        description: "Search the query audit log. Optional `query` object: user_id, datasource_id, status, from, to, page, page_size.",
+        scope: SCOPE_ADMIN_AUDIT,
+        method: Method::GET,
+        path: "/audit/queries",
+    },
+    AdminTool {
+        name: "admin_search_admin_audit",
+        description: "Search the admin audit log. Optional `query` object: resource_type, resource_id, actor_id, from, to, page, page_size.",
+        scope: SCOPE_ADMIN_AUDIT,
+        method: Method::GET,
+        path: "/audit/admin",
+    },
+];
+
+impl AdminTool {
+    fn path_params(&self) -> impl Iterator<Item = &'static str> {
+        self.path
+            .split('/')
+            .filter_map(|seg| seg.strip_prefix('{').and_then(|s| s.strip_suffix('}')))
+    }
+
+    fn has_body(&self) -> bool {
+        self.method == Method::POST || self.method == Method::PUT
+    }
+
+    fn definition(&self) -> Value {
+        let mut properties = Map::new();
+        let mut required: Vec<&str> = Vec::new();
+        for param in self.path_params() {
+            properties.insert(param.into(), json!({"type": "string", "format": "uuid"}));
+            required.push(param);
+        }
+        if self.has_body() {
+            properties.insert("body".into(), json!({"type": "object"}));
+            required.push("body");
+        } else if self.method == Method::GET {
+            properties.insert("query".into(), json!({"type": "object"}));
+        }
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "inputSchema": {
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            },
+        })
+    }
+}
+
+pub struct McpServer {
+    state: AdminState,
+}
+
+impl McpServer {
+    pub fn new(state: AdminState) -> Self {
+        Self { state }
+    }
+
+    /// Handle one JSON-RPC message. Returns `None` for notifications and responses.
+    ///
+    /// `agent` holds the client name captured from `initialize`; it is updated here
+    /// and read when building the audit `client_info`.
+    pub async fn handle_message(
+        &self,
+        caller: &McpCaller,
+        agent: &mut Option<String>,
+        message: Value,
+    ) -> Option<Value> {
+        let Value::Object(message) = message else {
+            return Some(rpc_error(
+                Value::Null,
+                INVALID_REQUEST,
+                "Expected a single JSON-RPC object",
+            ));
+        };
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response to a server request (we never send any) — ignore.
+            return None;
+        };
+        let Some(id) = message.get("id").cloned() else {
+            // Notification (`notifications/initialized`, `notifications/cancelled`, …).
+            return None;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => {
+                let name = params
+                    .pointer("/clientInfo/name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                *agent = Some(sanitize_agent_name(name));
+                Ok(self.initialize_result(&params))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": self.tool_definitions(caller) })),
+            "tools/call" => {
+                let agent = agent.clone().unwrap_or_else(|| "unknown".to_string());
+                self.call_tool(caller, &agent, &params).await
+            }
+            other => Err((METHOD_NOT_FOUND, format!("Method not found: {other}"))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, message)) => rpc_error(id, code, &message),
+        })
+    }
+
+    fn initialize_result(&self, params: &Value) -> Value {
+        let requested = params
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .unwrap_or(PROTOCOL_VERSION);
+        let version = if SUPPORTED_PROTOCOL_VERSIONS.contains(&requested) {
+            requested
+        } else {
+            PROTOCOL_VERSION
+        };
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": "betweenrows", "version": env!("CARGO_PKG_VERSION") },
+            "instructions": "Governed SQL access. Call list_datasources, then describe_schema, \
+                then execute_query with PostgreSQL-dialect read-only SQL. Results are already \
+                filtered and masked by the caller's policies.",
+        })
+    }
+
+    /// Query tools for everyone; admin tools only for admins holding the scope.
+    fn tool_definitions(&self, caller: &McpCaller) -> Vec<Value> {
+        let mut tools = vec![
+            json!({
+                "name": "list_datasources",
+                "description": "List the datasources you can query.",
+                "inputSchema": { "type": "object", "properties": {} },
+            }),
+            json!({
+                "name": "describe_schema",
+                "description": "List the schemas, tables and columns you can see on a datasource.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": { "datasource": { "type": "string" } },
+                    "required": ["datasource"],
+                },
+            }),
+            json!({
+                "name": "execute_query",
+                "description": "Run one read-only SQL query (PostgreSQL dialect) on a datasource. \
+                    Returns rows as JSON objects; `truncated` is true when `max_rows` was hit.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "datasource": { "type": "string" },
+                        "sql": { "type": "string" },
+                        "max_rows": { "type": "integer", "minimum": 1 },
+                        "purpose": {
+                            "type": "string",
+                            "description": "Why you are querying; must be one of the datasource's purposes.",
+                        },
+                        "justification": {
+                            "type": "string",
+                            "description": "Justification for `purpose`, if the purpose requires one.",
+                        },
+                    },
+                    "required": ["datasource", "sql"],
+                },
+            }),
+        ];
+        tools.extend(
+            ADMIN_TOOLS
+                .iter()
+                .filter(|t| admin_tool_allowed(caller, t))
+                .map(AdminTool::definition),
+        );
+        tools
+    }
+
+    async fn call_tool(
+        &self,
+        caller: &McpCaller,
+        agent: &str,
+        params: &Value,
+    ) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "tools/call requires `name`".to_string()))?;
+        let args = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        let outcome = match name {
+            "list_datasources" => self.list_datasources(caller).await,
+            "describe_schema" => {
+                let datasource = string_arg(&args, "datasource").map_err(invalid_params)?;
+                describe_visible_schema(&self.state, caller.user.id, datasource)
+                    .await
+                    .map_err(|e| e.message().to_string())
+                    .and_then(|d| serde_json::to_value(d).map_err(|e| e.to_string()))
+            }
+            "execute_query" => {
+                let datasource = string_arg(&args, "datasource").map_err(invalid_params)?;
+                let sql = string_arg(&args, "sql").map_err(invalid_params)?;
+                let max_rows = args
+                    .get("max_rows")
+                    .and_then(Value::as_u64)
+                    .map_or(DEFAULT_TOOL_MAX_ROWS, |n| n as usize);
+                let purpose = optional_string_arg(&args, "purpose").map_err(invalid_params)?;
+                let justification =
+                    optional_string_arg(&args, "justification").map_err(invalid_params)?;
+                self.execute_query(
+                    caller,
+                    agent,
+                    &datasource,
+                    &sql,
+                    max_rows,
+                    purpose,
+                    justification,
+                )
+                .await
+            }
+            other => {
+                let tool = ADMIN_TOOLS
+                    .iter()
+                    .find(|t| t.name == other && admin_tool_allowed(caller, t))
+                    .ok_or((INVALID_PARAMS, format!("Unknown tool: {other}")))?;
+                self.call_admin_tool(caller, tool, &args).await
+            }
+        };
+
+        Ok(match outcome {
+            Ok(value) => json!({
+                "content": [{ "type": "text", "text": value.to_string() }],
+                "structuredContent": value,
+                "isError": false,
+            }),
+            Err(message) => json!({
+                "content": [{ "type": "text", "text": message }],
+                "isError": true,
+            }),
+        })
+    }
+
+    async fn list_datasources(&self, caller: &McpCaller) -> Result<Value, String> {
+        let datasources = data_source::Entity::find()
+            .filter(data_source::Column::IsActive.eq(true))
+            .order_by_asc(data_source::Column::Name)
+            .all(&self.state.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut visible = Vec::new();
+        for ds in datasources {
+            let allowed = crate::role_resolver::resolve_datasource_access(
+                &self.state.db,
+                caller.user.id,
+                ds.id,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            if allowed {
+                visible.push(json!({ "name": ds.name, "type": ds.ds_type }));
+            }
+        }
+        Ok(json!({ "datasources": visible }))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_query(
+        &self,
+        caller: &McpCaller,
+        agent: &str,
+        datasource: &str,
+        sql: &str,
+        max_rows: usize,
+        purpose: Option<String>,
+        justification: Option<String>,
+    ) -> Result<Value, String> {
+        let result = execute_capped(
+            &self.state,
+            &caller.user,
+            datasource,
+            sql,
+            format!("mcp:{agent}"),
+            purpose,
+            justification,
+            Some(max_rows),
+            None,
+        )
+        .await
+        .map_err(|e| e.message().to_string())?;
+        let row_count: usize = result.batches.iter().map(|b| b.num_rows()).sum();
+        Ok(json!({
+            "columns": result_columns(&result.schema),
+            "rows": json_rows(&result.batches).map_err(|e| e.message().to_string())?,
+            "row_count": row_count,
+            "truncated": result.truncated,
+        }))
+    }
+
+    /// Dispatch an admin tool to the in-process admin REST router.
+    async fn call_admin_tool(
+        &self,
+        caller: &McpCaller,
+        tool: &AdminTool,
+        args: &Value,
+    ) -> Result<Value, String> {
+        let mut path = tool.path.to_string();
+        for param in tool.path_params() {
+            let value = string_arg(args, param)?;
+            if uuid::Uuid::parse_str(&value).is_err() {
+                return Err(format!("`{param}` must be a UUID"));
+            }
+            path = path.replace(&format!("{{{param}}}"), &value);
+        }
+        if let Some(Value::Object(query)) = args.get("query") {
+            let qs = encode_query(query);
+            if !qs.is_empty() {
+                path = format!("{path}?{qs}");
+            }
+        }
+
+        let claims = Claims {
+            sub: caller.user.id,
+            username: caller.user.username.clone(),
+            is_admin: true,
+            exp: (Utc::now().timestamp() as u64) + ADMIN_TOKEN_TTL_SECS,
+        };
+        let token = encode_jwt(&claims, &self.state.jwt_secret).map_err(|e| e.to_string())?;
+
+        let body = if tool.has_body() {
+            let body = args
+                .get("body")
+                .filter(|b| b.is_object())
+                .ok_or_else(|| "`body` must be an object".to_string())?;
+            Body::from(body.to_string())
+        } else {
+            Body::empty()
+        };
+        let request = Request::builder()
+            .method(tool.method.clone())
+            .uri(path)
+            .header("authorization", format!("Bearer {token}"))
+            .header("content-type", "application/json")
+            .body(body)
+            .map_err(|e| e.to_string())?;
+
+        let response = api_v1_router(self.state.clone())
+            .oneshot(request)
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|e| e.to_string())?;
+        let value: Value = if bytes.is_empty() {
+            json!({})
+        } else {
+            serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+        };
+
+        if status.is_success() {
+            // structuredContent must be an object.
+            Ok(match value {
+                Value::Object(_) => value,
+                other => json!({ "data": other }),
+            })
+        } else {
+            let message = value
+                .get("error")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| value.to_string());
+            Err(format!("{} {}", status.as_u16(), message))
+        }
+    }
+}
+
+/// Resolve the caller behind a raw API key: the key must be valid and unexpired
+/// and its owner active.
+pub async fn caller_for_api_key(
+    db: &sea_orm::DatabaseConnection,
+    raw: &str,
+) -> Result<Option<McpCaller>, sea_orm::DbErr> {
+    let Some(key) = crate::admin::api_key_handlers::verify_api_key(db, raw).await? else {
+        return Ok(None);
+    };
+    let user = proxy_user::Entity::find_by_id(key.user_id)
+        .one(db)
+        .await?
+        .filter(|u| u.is_active);
+    Ok(user.map(|user| McpCaller {
+        user,
+        scopes: key.scope_list(),
+    }))
+}
+
+/// Serve MCP over stdin/stdout (newline-delimited JSON-RPC) until stdin closes.
+///
+/// The key is re-checked for every message so revoking it (or deactivating its
+/// owner) ends access for a running agent, not just for the next launch.
+pub async fn serve_stdio(server: McpServer, api_key: String) -> std::io::Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    let mut agent = None;
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                write_line(
+                    &mut stdout,
+                    &rpc_error(Value::Null, PARSE_ERROR, &e.to_string()),
+                )
+                .await?;
+                continue;
+            }
+        };
+        let response = match caller_for_api_key(&server.state.db, &api_key).await {
+            Ok(Some(caller)) => server.handle_message(&caller, &mut agent, message).await,
+            Ok(None) => message
+                .get("id")
+                .cloned()
+                .map(|id| rpc_error(id, UNAUTHORIZED, "API key is invalid, expired or revoked")),
+            Err(e) => message
+                .get("id")
+                .cloned()
+                .map(|id| rpc_error(id, INTERNAL_ERROR, &e.to_string())),
+        };
+        if let Some(response) = response {
+            write_line(&mut stdout, &response).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn write_line(stdout: &mut tokio::io::Stdout, value: &Value) -> std::io::Result<()> {
+    stdout.write_all(value.to_string().as_bytes()).await?;
+    stdout.write_all(b"\n").await?;
+    stdout.flush().await
+}
+
+/// `POST /api/v1/mcp` — the streamable HTTP transport.
+///
+/// Sessions are stateless on the server: the `Mcp-Session-Id` returned by
+/// `initialize` encodes the agent name, so any replica can serve later requests.
+pub async fn handle_http(
+    QueryUser { user, scopes }: QueryUser,
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Json(message): Json<Value>,
+) -> Response {
+    let caller = McpCaller { user, scopes };
+    let mut agent = headers
+        .get(SESSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(agent_from_session_id);
+    let is_initialize = message.get("method").and_then(Value::as_str) == Some("initialize");
+
+    let server = McpServer::new(state);
+    let Some(response) = server.handle_message(&caller, &mut agent, message).await else {
+        return StatusCode::ACCEPTED.into_response();
+    };
+
+    let mut response = Json(response).into_response();
+    if is_initialize
+        && let Some(agent) = &agent
+        && let Ok(value) = HeaderValue::from_str(&new_session_id(agent))
+    {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+// ---------- helpers ----------
+
+fn admin_tool_allowed(caller: &McpCaller, tool: &AdminTool) -> bool {
+    caller.user.is_admin && caller.scopes.iter().any(|s| s == tool.scope)
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn invalid_params(message: String) -> (i64, String) {
+    (INVALID_PARAMS, message)
+}
+
+fn string_arg(args: &Value, name: &str) -> Result<String, String> {
+    args.get(name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("Missing string argument `{name}`"))
+}
+
+/// A string argument that may be left out (or `null`).
+fn optional_string_arg(args: &Value, name: &str) -> Result<Option<String>, String> {
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(format!("Argument `{name}` must be a string")),
+    }
+}
+
+/// Keep agent names short and printable; they end up in `query_audit_log.client_info`.
+fn sanitize_agent_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .take(MAX_AGENT_NAME_LEN)
+        .collect();
+    if cleaned.is_empty() {
+        "unknown".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// `<base64url(agent)>.<random hex>`.
+fn new_session_id(agent: &str) -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let nonce: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!("{}.{nonce}", URL_SAFE_NO_PAD.encode(agent))
+}
+
+fn agent_from_session_id(session_id: &str) -> Option<String> {
+    let (encoded, _) = session_id.split_once('.')?;
+    let decoded = URL_SAFE_NO_PAD.decode(encoded).ok()?;
+    Some(sanitize_agent_name(&String::from_utf8(decoded).ok()?))
+}
+
+/// Encode a flat JSON object as a URL query string.
+fn encode_query(query: &Map<String, Value>) -> String {
+    query
+        .iter()
+        .filter(|(_, v)| !v.is_null())
+        .map(|(k, v)| {
+            let v = match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            format!("{}={}", percent_encode(k), percent_encode(&v))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::{discovery_job, query_handlers::QueryApi};
+    use crate::auth::Auth;
+    use crate::engine::EngineCache;
+    use migration::MigratorTrait as _;
+    use sea_orm::{Database, DatabaseConnection};
+    use std::sync::{Arc, OnceLock};
+    use uuid::Uuid;
+
+    fn shared_wasm_runtime() -> Arc<crate::decision::wasm::WasmDecisionRuntime> {
+        static RUNTIME: OnceLock<Arc<crate::decision::wasm::WasmDecisionRuntime>> = OnceLock::new();
+        RUNTIME
+            .get_or_init(|| Arc::new(crate::decision::wasm::WasmDecisionRuntime::new().unwrap()))
+            .clone()
+    }
+
+    async fn make_server() -> McpServer {
+        let db: DatabaseConnection = Database::connect("sqlite::memory:").await.unwrap();
+        migration::Migrator::up(&db, None).await.unwrap();
+        let wasm_runtime = shared_wasm_runtime();
+        let engine_cache = EngineCache::new(db.clone(), [0u8; 32], wasm_runtime.clone());
+        McpServer::new(AdminState {
+            auth: Arc::new(Auth::new(db.clone())),
+            db,
+            jwt_secret: "test-jwt-secret-key-32-chars-pad".to_string(),
+            jwt_expiry_hours: 1,
+            engine_cache,
+            master_key: [0u8; 32],
+            job_store: Arc::new(tokio::sync::Mutex::new(discovery_job::JobStore::new())),
+            policy_hook: None,
+            proxy_handler: None,
+            wasm_runtime,
+            query_api: Arc::new(QueryApi::default()),
+        })
+    }
+
+    fn caller(is_admin: bool, scopes: &[&str]) -> McpCaller {
+        let now = Utc::now().naive_utc();
+        McpCaller {
+            user: proxy_user::Model {
+                id: Uuid::now_v7(),
+                username: "agent-owner".into(),
+                password_hash: String::new(),
+                is_admin,
+                is_active: true,
+                email: None,
+                display_name: None,
+                last_login_at: None,
+                created_at: now,
+                updated_at: now,
+                attributes: "{}".into(),
+            },
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn tool_names(response: &Value) -> Vec<String> {
+        response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn initialize_negotiates_version_and_captures_agent() {
+        let server = make_server().await;
+        let mut agent = None;
+        let resp = server
+            .handle_message(
+                &caller(false, &[]),
+                &mut agent,
+                json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": {
+                        "protocolVersion": "2025-03-26",
+                        "clientInfo": { "name": "claude code!", "version": "1" },
+                    },
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(resp["result"]["serverInfo"]["name"], "betweenrows");
+        assert_eq!(agent.as_deref(), Some("claudecode"));
+    }
+
+    #[tokio::test]
+    async fn notifications_and_responses_get_no_reply() {
+        let server = make_server().await;
+        let mut agent = None;
+        let c = caller(false, &[]);
+        assert!(
+            server
+                .handle_message(
+                    &c,
+                    &mut agent,
+                    json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+                )
+                .await
+                .is_none()
+        );
+        assert!(
+            server
+                .handle_message(
+                    &c,
+                    &mut agent,
+                    json!({"jsonrpc": "2.0", "id": 7, "result": {}})
+                )
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_method_is_method_not_found() {
+        let server = make_server().await;
+        let resp = server
+            .handle_message(
+                &caller(false, &[]),
+                &mut None,
+                json!({"jsonrpc": "2.0", "id": "x", "method": "resources/list"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(resp["id"], "x");
+    }
+
+    #[tokio::test]
+    async fn admin_tools_require_admin_user_and_scope() {
+        let server = make_server().await;
+        let list = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"});
+
+        let plain = server
+            .handle_message(&caller(false, &[]), &mut None, list.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            tool_names(&plain),
+            vec!["list_datasources", "describe_schema", "execute_query"]
+        );
+
+        // A scope on a non-admin's key (or an admin without scopes) unlocks nothing.
+        let non_admin_scoped = server
+            .handle_message(
+                &caller(false, &[SCOPE_ADMIN_POLICIES]),
+                &mut None,
+                list.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(tool_names(&non_admin_scoped).len(), 3);
+        let admin_unscoped = server
+            .handle_message(&caller(true, &[]), &mut None, list.clone())
+            .await
+            .unwrap();
+        assert_eq!(tool_names(&admin_unscoped).len(), 3);
+
+        let admin_audit = server
+            .handle_message(&caller(true, &[SCOPE_ADMIN_AUDIT]), &mut None, list)
+            .await
+            .unwrap();
+        let names = tool_names(&admin_audit);
+        assert!(names.contains(&"admin_search_query_audit".to_string()));
+        assert!(!names.contains(&"admin_create_policy".to_string()));
+    }
+
+    #[tokio::test]
+    async fn unscoped_admin_tool_call_is_rejected() {
+        let server = make_server().await;
+        let resp = server
+            .handle_message(
+                &caller(true, &[SCOPE_ADMIN_AUDIT]),
+                &mut None,
+                json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                    "params": { "name": "admin_list_policies", "arguments": {} },
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn admin_tool_dispatches_to_rest_router() {
+        let server = make_server().await;
+        let resp = server
+            .handle_message(
+                &caller(true, &[SCOPE_ADMIN_POLICIES]),
+                &mut None,
+                json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                    "params": {
+                        "name": "admin_list_policies",
+                        "arguments": { "query": { "page": 1, "page_size": 5 } },
+                    },
+                }),
+            )
+            .await
+            .unwrap();
+        let result = &resp["result"];
+        assert_eq!(result["isError"], false, "{resp}");
+        assert_eq!(result["structuredContent"]["total"], 0);
+
+        let resp = server
+            .handle_message(
+                &caller(true, &[SCOPE_ADMIN_POLICIES]),
+                &mut None,
+                json!({
+                    "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {
+                        "name": "admin_get_policy",
+                        "arguments": { "id": Uuid::now_v7().to_string() },
+                    },
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp["result"]["isError"], true);
+        assert!(
+            resp["result"]["content"][0]["text"]
+                .as_str()
+                .unwrap()
+                .starts_with("404")
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_query_reports_tool_error_without_policy_hook() {
+        let server = make_server().await;
+        let resp = server
+            .handle_message(
+                &caller(false, &[]),
+                &mut Some("agent".into()),
+                json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                    "params": {
+                        "name": "execute_query",
+                        "arguments": { "datasource": "nope", "sql": "SELECT 1" },
+                    },
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp["result"]["isError"], true);
+    }
+
+    #[test]
+    fn session_id_round_trips_agent_name() {
+        let id = new_session_id("cursor-agent");
+        assert_eq!(agent_from_session_id(&id).as_deref(), Some("cursor-agent"));
+        assert_eq!(agent_from_session_id("garbage"), None);
+    }
+
+    #[test]
+    fn sanitize_agent_name_strips_and_truncates() {
+        assert_eq!(sanitize_agent_name("my agent/1.0"), "myagent1.0");
+        assert_eq!(sanitize_agent_name("   "), "unknown");
+        assert_eq!(
+            sanitize_agent_name(&"a".repeat(200)).len(),
+            MAX_AGENT_NAME_LEN
+        );
+    }
+
+    #[test]
+    fn query_encoding_escapes_reserved_characters() {
+        let mut query = Map::new();
+        query.insert("search".into(), json!("a b&c"));
+        query.insert("page".into(), json!(2));
+        query.insert("skip".into(), Value::Null);
+        assert_eq!(encode_query(&query), "page=2&search=a%20b%26c");
+    }
+}
@@ -0,0 +1,1670 @@
+//! Static analysis of the policies on one datasource.
+//!
+//! [`analyze_datasource`] reads the datasource's selected catalog, the policies
+//! assigned to it and every active user's roles, resolved through
+//! [`RoleGraph`], and reports what would otherwise take reading every policy side
+//! by side:
+//!
+//! | Finding | Meaning |
+//! |---|---|
+//! | `conflict` | two policies that reach the same user contradict each other; the deny wins |
+//! | `redundant` | a policy never takes effect for some users, because another one already hides what it targets |
+//! | `overlap` | several masks reach the same column of a user; the `column_mask` strategy decides what applies |
+//! | `dead_policy` | a policy's targets match no table, or no column, of the catalog |
+//! | `uncovered_table` | in `policy_required` mode, no `column_allow` grants the table to anyone |
+//! | `uncovered_columns` | the `column_allow` policies on a table never grant these columns |
+//! | `peer_deviation` | a user's effective access differs from the users with the same roles |
+//!
+//! Per-user findings follow enforcement: only enabled policies whose policy and
+//! effective assignment are both `enforce` count, a target's first matching entry
+//! is the one applied, and permits that meet on a target are combined with
+//! [`combine`] under the datasource's [`CombiningRules`]. A permit a strategy
+//! sets aside is `redundant` for the user. The analysis is static — decision
+//! functions are assumed to fire and expressions are not run.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use uuid::Uuid;
+
+use crate::entity::{
+    data_source, data_source_access, discovered_column, discovered_schema, discovered_table,
+    policy, policy_assignment, proxy_user, role_member,
+};
+use crate::policy_combining::{
+    AssignmentScope, CombineStrategy, CombiningRules, Contender, PolicyConflict, Precedence,
+    combine,
+};
+use crate::policy_match::{
+    ActionStatus, AggregateOnlyDef, CatalogTags, DifferentialPrivacyDef, PolicyType, TargetEntry,
+    expand_column_patterns,
+};
+use crate::role_resolver::{RoleGraph, has_datasource_access, select_effective_assignments};
+
+/// What a finding reports. See the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    Conflict,
+    DeadPolicy,
+    UncoveredTable,
+    Redundant,
+    Overlap,
+    UncoveredColumns,
+    PeerDeviation,
+}
+
+impl FindingKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Conflict => "conflict",
+            Self::DeadPolicy => "dead_policy",
+            Self::UncoveredTable => "uncovered_table",
+            Self::Redundant => "redundant",
+            Self::Overlap => "overlap",
+            Self::UncoveredColumns => "uncovered_columns",
+            Self::PeerDeviation => "peer_deviation",
+        }
+    }
+
+    /// Warnings point at policies that do not do what their author meant; the
+    /// rest are worth a look but often intended.
+    pub fn severity(self) -> Severity {
+        match self {
+            Self::Conflict | Self::DeadPolicy | Self::UncoveredTable => Severity::Warning,
+            Self::Redundant | Self::Overlap | Self::UncoveredColumns | Self::PeerDeviation => {
+                Severity::Info
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub struct PolicyRef {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub severity: Severity,
+    pub message: String,
+    pub policies: Vec<PolicyRef>,
+    /// `schema.table` (upstream names) the finding is about.
+    pub table: Option<String>,
+    pub columns: Vec<String>,
+    /// Users the finding applies to. Empty when it is about the policies alone.
+    pub users: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AnalysisReport {
+    pub datasource_id: Uuid,
+    pub datasource_name: String,
+    pub access_mode: String,
+    /// Active users with access to the datasource.
+    pub users_analyzed: usize,
+    /// Warnings first, then by kind and table.
+    pub findings: Vec<Finding>,
+}
+
+impl std::fmt::Display for AnalysisReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Policy analysis for datasource '{}' (access_mode: {}, {} user{} with access)",
+            self.datasource_name,
+            self.access_mode,
+            self.users_analyzed,
+            if self.users_analyzed == 1 { "" } else { "s" }
+        )?;
+        for finding in &self.findings {
+            let severity = match finding.severity {
+                Severity::Warning => "WARNING",
+                Severity::Info => "INFO",
+            };
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{severity:<8} {:<17} {}",
+                finding.kind.as_str(),
+                finding.message
+            )?;
+            if !finding.users.is_empty() {
+                writeln!(f, "{:26} users: {}", "", finding.users.join(", "))?;
+            }
+        }
+        let warnings = self
+            .findings
+            .iter()
+            .filter(|f| f.severity == Severity::Warning)
+            .count();
+        writeln!(f)?;
+        write!(
+            f,
+            "{} finding{}, {warnings} warning{}",
+            self.findings.len(),
+            if self.findings.len() == 1 { "" } else { "s" },
+            if warnings == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Analyze the policies of a datasource. `None` when the datasource does not exist.
+pub async fn analyze_datasource(
+    db: &DatabaseConnection,
+    datasource_id: Uuid,
+) -> Result<Option<AnalysisReport>, DbErr> {
+    let Some(ds) = data_source::Entity::find_by_id(datasource_id)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    // Catalog: selected schemas, tables and columns, as the engine builds it.
+    let schemas = discovered_schema::Entity::find()
+        .filter(discovered_schema::Column::DataSourceId.eq(ds.id))
+        .all(db)
+        .await?;
+    let tags = crate::hooks::policy::load_catalog_tags(db, &schemas).await?;
+    let mut df_to_upstream = HashMap::new();
+    for s in &schemas {
+        let alias = s.schema_alias.as_deref().unwrap_or(&s.schema_name);
+        df_to_upstream.insert(alias.to_string(), s.schema_name.clone());
+    }
+    let selected: HashMap<Uuid, &discovered_schema::Model> = schemas
+        .iter()
+        .filter(|s| s.is_selected)
+        .map(|s| (s.id, s))
+        .collect();
+    let tables = discovered_table::Entity::find()
+        .filter(discovered_table::Column::DiscoveredSchemaId.is_in(selected.keys().copied()))
+        .filter(discovered_table::Column::IsSelected.eq(true))
+        .all(db)
+        .await?;
+    let mut columns_by_table: HashMap<Uuid, Vec<discovered_column::Model>> = HashMap::new();
+    for c in discovered_column::Entity::find()
+        .filter(discovered_column::Column::DiscoveredTableId.is_in(tables.iter().map(|t| t.id)))
+        .all(db)
+        .await?
+    {
+        columns_by_table
+            .entry(c.discovered_table_id)
+            .or_default()
+            .push(c);
+    }
+    let mut catalog = Vec::new();
+    for t in &tables {
+        let Some(schema) = selected.get(&t.discovered_schema_id) else {
+            continue;
+        };
+        let columns = columns_by_table.remove(&t.id).unwrap_or_default();
+        catalog.push(CatalogTable {
+            df_schema: schema
+                .schema_alias
+                .clone()
+                .unwrap_or_else(|| schema.schema_name.clone()),
+            schema: schema.schema_name.clone(),
+            table: t.table_name.clone(),
+            columns: crate::engine::build_arrow_schema(&columns)
+                .fields()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect(),
+        });
+    }
+    catalog.sort_by(|a, b| (&a.schema, &a.table).cmp(&(&b.schema, &b.table)));
+
+    // Policies assigned to the datasource.
+    let assignments = policy_assignment::Entity::find()
+        .filter(policy_assignment::Column::DataSourceId.eq(ds.id))
+        .all(db)
+        .await?;
+    let policies = policy::Entity::find()
+        .filter(policy::Column::Id.is_in(assignments.iter().map(|a| a.policy_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|p| {
+            Some(AnalyzedPolicy {
+                id: p.id,
+                policy_type: p.policy_type.parse().ok()?,
+                targets: serde_json::from_str(&p.targets).unwrap_or_default(),
+                definition: p
+                    .definition
+                    .as_deref()
+                    .and_then(|d| serde_json::from_str(d).ok()),
+                is_enabled: p.is_enabled,
+                enforced: p.action_status == ActionStatus::Enforce.as_str(),
+                name: p.name,
+            })
+        })
+        .collect();
+
+    // Users, with the roles and assignments that reach them.
+    let graph = RoleGraph::load(db).await?;
+    let mut direct_roles: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for m in role_member::Entity::find().all(db).await? {
+        direct_roles.entry(m.user_id).or_default().push(m.role_id);
+    }
+    let accesses = data_source_access::Entity::find()
+        .filter(data_source_access::Column::DataSourceId.eq(ds.id))
+        .all(db)
+        .await?;
+    let users = proxy_user::Entity::find()
+        .filter(proxy_user::Column::IsActive.eq(true))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|u| {
+            let role_ids = graph.resolve(direct_roles.get(&u.id).map_or(&[], Vec::as_slice));
+            let role_set: HashSet<Uuid> = role_ids.iter().copied().collect();
+            let mut roles: Vec<String> = role_ids
+                .iter()
+                .filter_map(|id| graph.role_name(*id).map(str::to_string))
+                .collect();
+            roles.sort();
+            AnalyzedUser {
+                has_access: has_datasource_access(&accesses, u.id, &role_set),
+                assignments: select_effective_assignments(
+                    assignments.iter().cloned(),
+                    u.id,
+                    &role_set,
+                ),
+                roles,
+                username: u.username,
+            }
+        })
+        .collect();
+
+    let input = AnalysisInput {
+        access_mode: ds.access_mode.clone(),
+        combining: CombiningRules::parse(ds.policy_combining.as_deref()),
+        catalog,
+        df_to_upstream,
+        tags,
+        policies,
+        assignments,
+        users,
+    };
+    Ok(Some(AnalysisReport {
+        datasource_id: ds.id,
+        datasource_name: ds.name,
+        access_mode: ds.access_mode,
+        users_analyzed: input.users.iter().filter(|u| u.has_access).count(),
+        findings: input.analyze(),
+    }))
+}
+
+// ---------- analysis ----------
+
+/// One table of the datasource's selected catalog.
+struct CatalogTable {
+    df_schema: String,
+    /// Upstream schema name.
+    schema: String,
+    table: String,
+    columns: Vec<String>,
+}
+
+impl CatalogTable {
+    fn name(&self) -> String {
+        format!("{}.{}", self.schema, self.table)
+    }
+}
+
+struct AnalyzedPolicy {
+    id: Uuid,
+    name: String,
+    policy_type: PolicyType,
+    targets: Vec<TargetEntry>,
+    definition: Option<serde_json::Value>,
+    is_enabled: bool,
+    /// The policy itself is `enforce`, not `shadow`.
+    enforced: bool,
+}
+
+impl AnalyzedPolicy {
+    fn to_ref(&self) -> PolicyRef {
+        PolicyRef {
+            id: self.id,
+            name: self.name.clone(),
+        }
+    }
+
+    fn label(&self) -> String {
+        format!("{} '{}'", self.policy_type, self.name)
+    }
+
+    /// How strict the policy is for `most_restrictive` / `least_restrictive`,
+    /// higher being stricter. `None` when its definition does not parse; the
+    /// engine skips such a policy.
+    fn strictness(&self) -> Option<f64> {
+        let definition = || self.definition.clone();
+        match self.policy_type {
+            PolicyType::AggregateOnly => serde_json::from_value::<AggregateOnlyDef>(definition()?)
+                .ok()
+                .map(|d| d.min_group_size as f64),
+            // A smaller epsilon is stricter.
+            PolicyType::DifferentialPrivacy => {
+                serde_json::from_value::<DifferentialPrivacyDef>(definition()?)
+                    .ok()
+                    .map(|d| -d.epsilon)
+            }
+            _ => Some(0.0),
+        }
+    }
+}
+
+struct AnalyzedUser {
+    username: String,
+    /// Names of the user's resolved roles, sorted.
+    roles: Vec<String>,
+    has_access: bool,
+    /// The user's effective assignment of each policy on the datasource.
+    assignments: Vec<policy_assignment::Model>,
+}
+
+struct AnalysisInput {
+    access_mode: String,
+    combining: CombiningRules,
+    catalog: Vec<CatalogTable>,
+    df_to_upstream: HashMap<String, String>,
+    tags: CatalogTags,
+    policies: Vec<AnalyzedPolicy>,
+    /// Every assignment on the datasource.
+    assignments: Vec<policy_assignment::Model>,
+    users: Vec<AnalyzedUser>,
+}
+
+/// A policy that reaches a user and matches a table, through `entry`.
+struct Reach<'a> {
+    policy: &'a AnalyzedPolicy,
+    entry: &'a TargetEntry,
+    precedence: &'a Precedence,
+}
+
+impl Reach<'_> {
+    fn contender<T>(&self, value: T) -> Contender<T> {
+        Contender {
+            precedence: self.precedence.clone(),
+            value,
+        }
+    }
+}
+
+/// What findings are merged on: kind, table, policies and columns.
+type FindingKey = (FindingKind, Option<String>, Vec<PolicyRef>, Vec<String>);
+
+/// A user's access to the datasource: whether they may connect, and the name of
+/// each policy that reaches them with whether it is only shadowed.
+type UserAccess = (bool, BTreeSet<(String, bool)>);
+
+/// Findings being collected, merged across users by everything but the user.
+#[derive(Default)]
+struct Findings {
+    /// Message and users of each finding.
+    by_key: BTreeMap<FindingKey, (String, BTreeSet<String>)>,
+}
+
+impl Findings {
+    fn add(
+        &mut self,
+        kind: FindingKind,
+        message: String,
+        policies: &[&AnalyzedPolicy],
+        table: Option<&CatalogTable>,
+        columns: &BTreeSet<String>,
+        user: Option<&str>,
+    ) {
+        let key = (
+            kind,
+            table.map(CatalogTable::name),
+            policies.iter().map(|p| p.to_ref()).collect(),
+            columns.iter().cloned().collect(),
+        );
+        let (_, users) = self
+            .by_key
+            .entry(key)
+            .or_insert_with(|| (message, BTreeSet::new()));
+        users.extend(user.map(str::to_string));
+    }
+
+    fn into_sorted(self) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = self
+            .by_key
+            .into_iter()
+            .map(
+                |((kind, table, policies, columns), (message, users))| Finding {
+                    kind,
+                    severity: kind.severity(),
+                    message,
+                    policies,
+                    table,
+                    columns,
+                    users: users.into_iter().collect(),
+                },
+            )
+            .collect();
+        findings.sort_by(|a, b| {
+            (a.severity, a.kind, &a.table, &a.message)
+                .cmp(&(b.severity, b.kind, &b.table, &b.message))
+        });
+        findings
+    }
+}
+
+fn column_list(columns: &BTreeSet<String>) -> String {
+    columns.iter().cloned().collect::<Vec<_>>().join(", ")
+}
+
+/// `a`, `a and b`, `a, b and c`.
+fn and_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+impl AnalysisInput {
+    fn analyze(&self) -> Vec<Finding> {
+        let mut findings = Findings::default();
+        self.dead_policies(&mut findings);
+        self.coverage(&mut findings);
+        for user in self.users.iter().filter(|u| u.has_access) {
+            self.user_conflicts(user, &mut findings);
+        }
+        self.peer_deviations(&mut findings);
+        findings.into_sorted()
+    }
+
+    fn policy(&self, id: Uuid) -> Option<&AnalyzedPolicy> {
+        self.policies.iter().find(|p| p.id == id)
+    }
+
+    /// The first of the policy's target entries that matches the table — the one
+    /// enforcement applies.
+    fn matching_entry<'a>(
+        &self,
+        policy: &'a AnalyzedPolicy,
+        table: &CatalogTable,
+    ) -> Option<&'a TargetEntry> {
+        policy.targets.iter().find(|entry| {
+            entry.matches_table(
+                &table.df_schema,
+                &table.table,
+                &self.df_to_upstream,
+                &self.tags,
+            )
+        })
+    }
+
+    /// The table's columns an entry's column selectors match.
+    fn entry_columns(&self, entry: &TargetEntry, table: &CatalogTable) -> BTreeSet<String> {
+        let columns: Vec<&str> = table.columns.iter().map(String::as_str).collect();
+        let patterns = entry.column_patterns(
+            &table.df_schema,
+            &table.table,
+            &columns,
+            &self.df_to_upstream,
+            &self.tags,
+        );
+        expand_column_patterns(&patterns, &columns)
+            .into_iter()
+            .collect()
+    }
+
+    /// Columns an entry names other than through a bare `*`, which grants the
+    /// whole table rather than picking columns.
+    fn named_columns(&self, entry: &TargetEntry, table: &CatalogTable) -> BTreeSet<String> {
+        let columns: Vec<&str> = table.columns.iter().map(String::as_str).collect();
+        let patterns: Vec<String> = entry
+            .column_patterns(
+                &table.df_schema,
+                &table.table,
+                &columns,
+                &self.df_to_upstream,
+                &self.tags,
+            )
+            .into_iter()
+            .filter(|p| p != "*")
+            .collect();
+        expand_column_patterns(&patterns, &columns)
+            .into_iter()
+            .collect()
+    }
+
+    /// Enabled, enforced policies with at least one enforcing assignment here.
+    fn enforced_policies(&self) -> impl Iterator<Item = &AnalyzedPolicy> {
+        self.policies.iter().filter(|p| {
+            p.is_enabled
+                && p.enforced
+                && self.assignments.iter().any(|a| {
+                    a.policy_id == p.id && a.action_status == ActionStatus::Enforce.as_str()
+                })
+        })
+    }
+
+    /// The enabled, enforced policies that reach `user`, each with where its
+    /// effective assignment stands among them.
+    fn effective(&self, user: &AnalyzedUser) -> Vec<(Precedence, &AnalyzedPolicy)> {
+        let mut effective: Vec<(Precedence, &AnalyzedPolicy)> = user
+            .assignments
+            .iter()
+            .filter(|a| a.action_status == ActionStatus::Enforce.as_str())
+            .filter_map(|a| {
+                let p = self.policy(a.policy_id)?;
+                (p.is_enabled && p.enforced).then(|| {
+                    let precedence = Precedence {
+                        name: p.name.clone(),
+                        priority: a.priority,
+                        scope: AssignmentScope::parse(&a.assignment_scope),
+                    };
+                    (precedence, p)
+                })
+            })
+            .collect();
+        effective.sort_by(|a, b| (a.0.priority, &a.0.name).cmp(&(b.0.priority, &b.0.name)));
+        effective
+    }
+
+    /// The effective policies that match `table`.
+    fn reaching<'a>(
+        &self,
+        effective: &'a [(Precedence, &'a AnalyzedPolicy)],
+        table: &CatalogTable,
+    ) -> Vec<Reach<'a>> {
+        effective
+            .iter()
+            .filter_map(|(precedence, p)| {
+                self.matching_entry(p, table).map(|entry| Reach {
+                    policy: p,
+                    entry,
+                    precedence,
+                })
+            })
+            .collect()
+    }
+
+    /// Columns a `column_allow` entry grants; one without columns grants them all.
+    fn granted_columns(&self, entry: &TargetEntry, table: &CatalogTable) -> BTreeSet<String> {
+        if entry.columns.is_none() {
+            table.columns.iter().cloned().collect()
+        } else {
+            self.entry_columns(entry, table)
+        }
+    }
+
+    /// The `column_allow` policies that reach one user on `table`, combined
+    /// under the datasource's strategy: the ones that apply, the columns they
+    /// grant together, and the conflict when several met.
+    fn combine_allows<'r, 'a>(
+        &self,
+        allows: &[&'r Reach<'a>],
+        table: &CatalogTable,
+    ) -> (Vec<&'r Reach<'a>>, BTreeSet<String>, Option<PolicyConflict>) {
+        let strategy = self.combining.column_allow;
+        let combined = combine(
+            PolicyType::ColumnAllow,
+            table.name(),
+            strategy,
+            allows.iter().map(|r| r.contender(*r)).collect(),
+            None,
+        );
+        let mut grants = combined
+            .values
+            .iter()
+            .map(|r| self.granted_columns(r.entry, table));
+        let columns = if strategy == CombineStrategy::Intersection {
+            // Only columns every allow list grants.
+            let first = grants.next().unwrap_or_default();
+            grants.fold(first, |acc, g| acc.intersection(&g).cloned().collect())
+        } else {
+            grants.flatten().collect()
+        };
+        (combined.values, columns, combined.conflict)
+    }
+
+    /// Report the policies `conflict` set aside on `table` as redundant: a
+    /// single winner applies in their place.
+    fn report_overridden(
+        &self,
+        conflict: &PolicyConflict,
+        reaching: &[Reach],
+        table: &CatalogTable,
+        username: Option<&str>,
+        findings: &mut Findings,
+    ) {
+        let find = |name: &String| reaching.iter().find(|r| &r.policy.name == name);
+        let [winner] = conflict.applied.as_slice() else {
+            return;
+        };
+        let Some(winner) = find(winner) else {
+            return;
+        };
+        for loser in conflict.overridden.iter().filter_map(find) {
+            findings.add(
+                FindingKind::Redundant,
+                format!(
+                    "{} never applies to {}: {} wins under {}",
+                    loser.policy.label(),
+                    table.name(),
+                    winner.policy.label(),
+                    conflict.strategy
+                ),
+                &[loser.policy, winner.policy],
+                Some(table),
+                &BTreeSet::new(),
+                username,
+            );
+        }
+    }
+
+    /// Enabled policies whose targets match nothing in the catalog.
+    fn dead_policies(&self, findings: &mut Findings) {
+        for policy in self.policies.iter().filter(|p| p.is_enabled) {
+            let matched: Vec<(&CatalogTable, &TargetEntry)> = self
+                .catalog
+                .iter()
+                .filter_map(|t| self.matching_entry(policy, t).map(|e| (t, e)))
+                .collect();
+            let message = if matched.is_empty() {
+                format!("{} matches no table in the catalog", policy.label())
+            } else if matches!(
+                policy.policy_type,
+                PolicyType::ColumnAllow | PolicyType::ColumnDeny | PolicyType::ColumnMask
+            ) && matched
+                .iter()
+                .all(|(t, e)| e.columns.is_some() && self.entry_columns(e, t).is_empty())
+            {
+                format!(
+                    "{} matches {} table{} but none of their columns",
+                    policy.label(),
+                    matched.len(),
+                    if matched.len() == 1 { "" } else { "s" }
+                )
+            } else {
+                continue;
+            };
+            findings.add(
+                FindingKind::DeadPolicy,
+                message,
+                &[policy],
+                None,
+                &BTreeSet::new(),
+                None,
+            );
+        }
+    }
+
+    /// Tables and columns no enforced `column_allow` grants to anyone.
+    fn coverage(&self, findings: &mut Findings) {
+        let allows: Vec<&AnalyzedPolicy> = self
+            .enforced_policies()
+            .filter(|p| p.policy_type == PolicyType::ColumnAllow)
+            .collect();
+        for table in &self.catalog {
+            let granting: Vec<(&AnalyzedPolicy, &TargetEntry)> = allows
+                .iter()
+                .filter_map(|p| self.matching_entry(p, table).map(|e| (*p, e)))
+                .collect();
+            if granting.is_empty() {
+                if self.access_mode == "policy_required" {
+                    findings.add(
+                        FindingKind::UncoveredTable,
+                        format!(
+                            "No column_allow policy grants {}; no user can read it",
+                            table.name()
+                        ),
+                        &[],
+                        Some(table),
+                        &BTreeSet::new(),
+                        None,
+                    );
+                }
+                continue;
+            }
+            let strategy = self.combining.column_allow;
+            let granted: BTreeSet<String> = if strategy == CombineStrategy::Union {
+                granting
+                    .iter()
+                    .flat_map(|(_, e)| self.granted_columns(e, table))
+                    .collect()
+            } else {
+                // A user reached by several allows gets what the strategy keeps.
+                let mut granted = BTreeSet::new();
+                for user in self.users.iter().filter(|u| u.has_access) {
+                    let effective = self.effective(user);
+                    let reaching = self.reaching(&effective, table);
+                    let allows: Vec<&Reach> = reaching
+                        .iter()
+                        .filter(|r| r.policy.policy_type == PolicyType::ColumnAllow)
+                        .collect();
+                    granted.extend(self.combine_allows(&allows, table).1);
+                }
+                granted
+            };
+            let uncovered: BTreeSet<String> = table
+                .columns
+                .iter()
+                .filter(|c| !granted.contains(*c))
+                .cloned()
+                .collect();
+            if !uncovered.is_empty() {
+                let policies: Vec<&AnalyzedPolicy> = granting.iter().map(|(p, _)| *p).collect();
+                let them = if uncovered.len() == 1 { "it" } else { "them" };
+                let message = if strategy == CombineStrategy::Union {
+                    format!(
+                        "No column_allow policy grants {} on {}; no user can read {them}",
+                        column_list(&uncovered),
+                        table.name()
+                    )
+                } else {
+                    format!(
+                        "The column_allow policies on {}, combined under {strategy}, grant no user {}; no user can read {them}",
+                        table.name(),
+                        column_list(&uncovered)
+                    )
+                };
+                findings.add(
+                    FindingKind::UncoveredColumns,
+                    message,
+                    &policies,
+                    Some(table),
+                    &uncovered,
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Contradictory, redundant and overlapping policies among those that reach
+    /// one user.
+    fn user_conflicts(&self, user: &AnalyzedUser, findings: &mut Findings) {
+        let rules = self.combining;
+        let effective = self.effective(user);
+        let username = Some(user.username.as_str());
+        let none = BTreeSet::new();
+
+        for table in &self.catalog {
+            let reaching = self.reaching(&effective, table);
+            let of_type =
+                |t: PolicyType| reaching.iter().filter(move |r| r.policy.policy_type == t);
+
+            // A denied table makes every other policy on it moot.
+            if let Some(deny) = of_type(PolicyType::TableDeny).next() {
+                for r in reaching
+                    .iter()
+                    .filter(|r| r.policy.policy_type != PolicyType::TableDeny)
+                {
+                    let (kind, message) = if r.policy.policy_type == PolicyType::ColumnAllow {
+                        (
+                            FindingKind::Conflict,
+                            format!(
+                                "{} hides {}, overriding {}",
+                                deny.policy.label(),
+                                table.name(),
+                                r.policy.label()
+                            ),
+                        )
+                    } else {
+                        (
+                            FindingKind::Redundant,
+                            format!(
+                                "{} never applies to {}: {} hides the table",
+                                r.policy.label(),
+                                table.name(),
+                                deny.policy.label()
+                            ),
+                        )
+                    };
+                    findings.add(
+                        kind,
+                        message,
+                        &[deny.policy, r.policy],
+                        Some(table),
+                        &none,
+                        username,
+                    );
+                }
+                continue;
+            }
+
+            // Row filters, aggregate_only and differential_privacy policies a
+            // winner-takes-all strategy sets aside.
+            for (policy_type, strategy, stricter) in [
+                (PolicyType::RowFilter, rules.row_filter, None),
+                (
+                    PolicyType::AggregateOnly,
+                    rules.aggregate_only,
+                    Some(f64::total_cmp as fn(&f64, &f64) -> Ordering),
+                ),
+                (
+                    PolicyType::DifferentialPrivacy,
+                    rules.differential_privacy,
+                    Some(f64::total_cmp as fn(&f64, &f64) -> Ordering),
+                ),
+            ] {
+                let contenders = of_type(policy_type)
+                    .filter_map(|r| Some(r.contender(r.policy.strictness()?)))
+                    .collect();
+                let combined = combine(policy_type, table.name(), strategy, contenders, stricter);
+                if let Some(conflict) = combined.conflict {
+                    self.report_overridden(&conflict, &reaching, table, username, findings);
+                }
+            }
+
+            let allows: Vec<&Reach> = of_type(PolicyType::ColumnAllow).collect();
+            if allows.is_empty() && self.access_mode == "policy_required" {
+                // The table is hidden from this user; coverage reports it.
+                continue;
+            }
+            let (applied, allowed, conflict) = if allows.is_empty() {
+                (Vec::new(), table.columns.iter().cloned().collect(), None)
+            } else {
+                self.combine_allows(&allows, table)
+            };
+            if let Some(conflict) = conflict {
+                self.report_overridden(&conflict, &reaching, table, username, findings);
+            }
+            let restricting: Vec<&Reach> = applied
+                .into_iter()
+                .filter(|r| r.entry.columns.is_some())
+                .collect();
+
+            // column_deny against column_allow.
+            let mut denied_by: BTreeMap<String, &AnalyzedPolicy> = BTreeMap::new();
+            for deny in of_type(PolicyType::ColumnDeny).filter(|r| r.entry.columns.is_some()) {
+                let columns = self.entry_columns(deny.entry, table);
+                for allow in &restricting {
+                    let granted: BTreeSet<String> = self
+                        .named_columns(allow.entry, table)
+                        .intersection(&columns)
+                        .filter(|c| allowed.contains(*c))
+                        .cloned()
+                        .collect();
+                    if !granted.is_empty() {
+                        findings.add(
+                            FindingKind::Conflict,
+                            format!(
+                                "{} hides {} on {}, which {} grants; the deny wins",
+                                deny.policy.label(),
+                                column_list(&granted),
+                                table.name(),
+                                allow.policy.label()
+                            ),
+                            &[deny.policy, allow.policy],
+                            Some(table),
+                            &granted,
+                            username,
+                        );
+                    }
+                }
+                let ungranted: BTreeSet<String> = columns.difference(&allowed).cloned().collect();
+                if !ungranted.is_empty() {
+                    findings.add(
+                        FindingKind::Redundant,
+                        format!(
+                            "{} is redundant for {} on {}: no column_allow grants {}",
+                            deny.policy.label(),
+                            column_list(&ungranted),
+                            table.name(),
+                            if ungranted.len() == 1 { "it" } else { "them" }
+                        ),
+                        &[deny.policy],
+                        Some(table),
+                        &ungranted,
+                        username,
+                    );
+                }
+                for column in columns {
+                    denied_by.entry(column).or_insert(deny.policy);
+                }
+            }
+
+            // Masks on hidden columns, and masks that meet on the same column.
+            let mut meeting: BTreeMap<String, Vec<&Reach>> = BTreeMap::new();
+            for mask in of_type(PolicyType::ColumnMask) {
+                let mut hidden_by: BTreeMap<Uuid, (&AnalyzedPolicy, BTreeSet<String>)> =
+                    BTreeMap::new();
+                let mut ungranted = BTreeSet::new();
+                for column in self.entry_columns(mask.entry, table) {
+                    if let Some(deny) = denied_by.get(&column) {
+                        hidden_by
+                            .entry(deny.id)
+                            .or_insert((deny, BTreeSet::new()))
+                            .1
+                            .insert(column);
+                    } else if !allowed.contains(&column) {
+                        ungranted.insert(column);
+                    } else {
+                        meeting.entry(column).or_default().push(mask);
+                    }
+                }
+                for (deny, columns) in hidden_by.values() {
+                    findings.add(
+                        FindingKind::Redundant,
+                        format!(
+                            "{} never applies to {} on {}: {} hides {}",
+                            mask.policy.label(),
+                            column_list(columns),
+                            table.name(),
+                            deny.label(),
+                            if columns.len() == 1 { "it" } else { "them" }
+                        ),
+                        &[mask.policy, deny],
+                        Some(table),
+                        columns,
+                        username,
+                    );
+                }
+                if !ungranted.is_empty() {
+                    findings.add(
+                        FindingKind::Redundant,
+                        format!(
+                            "{} never applies to {} on {}: no column_allow grants {}",
+                            mask.policy.label(),
+                            column_list(&ungranted),
+                            table.name(),
+                            if ungranted.len() == 1 { "it" } else { "them" }
+                        ),
+                        &[mask.policy],
+                        Some(table),
+                        &ungranted,
+                        username,
+                    );
+                }
+            }
+            // Columns where the same masks met with the same outcome are reported together.
+            let mut overlaps: BTreeMap<(Vec<String>, Vec<String>), BTreeSet<String>> =
+                BTreeMap::new();
+            for (column, masks) in meeting {
+                let combined = combine(
+                    PolicyType::ColumnMask,
+                    format!("{}.{column}", table.name()),
+                    rules.column_mask,
+                    masks.iter().map(|r| r.contender(())).collect(),
+                    None,
+                );
+                if let Some(conflict) = combined.conflict {
+                    overlaps
+                        .entry((conflict.applied, conflict.overridden))
+                        .or_default()
+                        .insert(column);
+                }
+            }
+            for ((applied, overridden), columns) in overlaps {
+                let policies: Vec<&AnalyzedPolicy> = applied
+                    .iter()
+                    .chain(&overridden)
+                    .filter_map(|name| reaching.iter().find(|r| &r.policy.name == name))
+                    .map(|r| r.policy)
+                    .collect();
+                let labels: Vec<String> = policies.iter().map(|p| p.label()).collect();
+                let outcome = match applied.as_slice() {
+                    [winner] if !overridden.is_empty() => {
+                        format!("'{winner}' wins under {}", rules.column_mask)
+                    }
+                    // most_restrictive keeps every mask; disagreeing ones yield NULL.
+                    _ => format!(
+                        "under {} {} NULL unless the masks are identical",
+                        rules.column_mask,
+                        if columns.len() == 1 {
+                            "it is"
+                        } else {
+                            "they are"
+                        }
+                    ),
+                };
+                findings.add(
+                    FindingKind::Overlap,
+                    format!(
+                        "{} {} mask {} on {}; {outcome}",
+                        and_list(&labels),
+                        if labels.len() == 2 { "both" } else { "all" },
+                        column_list(&columns),
+                        table.name()
+                    ),
+                    &policies,
+                    Some(table),
+                    &columns,
+                    username,
+                );
+            }
+        }
+    }
+
+    /// Users whose access differs from the most common access among the users
+    /// with exactly the same roles.
+    fn peer_deviations(&self, findings: &mut Findings) {
+        let access = |user: &AnalyzedUser| -> UserAccess {
+            let policies = user
+                .assignments
+                .iter()
+                .filter_map(|a| {
+                    let p = self.policy(a.policy_id)?;
+                    let shadow = !p.enforced || a.action_status == ActionStatus::Shadow.as_str();
+                    p.is_enabled.then(|| (p.name.clone(), shadow))
+                })
+                .collect();
+            (user.has_access, policies)
+        };
+        let mut groups: BTreeMap<&[String], Vec<&AnalyzedUser>> = BTreeMap::new();
+        for user in &self.users {
+            groups.entry(user.roles.as_slice()).or_default().push(user);
+        }
+
+        for (roles, users) in groups.into_iter().filter(|(_, u)| u.len() > 1) {
+            let accesses: Vec<_> = users.iter().map(|u| access(u)).collect();
+            let mut counts: BTreeMap<&UserAccess, usize> = BTreeMap::new();
+            for a in &accesses {
+                *counts.entry(a).or_default() += 1;
+            }
+            // The most common access; ties go to the first in order.
+            let Some((&baseline, &peers)) = counts.iter().rev().max_by_key(|(_, n)| **n) else {
+                continue;
+            };
+            let label = if roles.is_empty() {
+                "no roles".to_string()
+            } else {
+                format!("roles {}", roles.join(", "))
+            };
+            for (user, a) in users.iter().zip(&accesses) {
+                if a == baseline {
+                    continue;
+                }
+                let describe = |(name, shadow): &(String, bool)| {
+                    if *shadow {
+                        format!("'{name}' (shadow)")
+                    } else {
+                        format!("'{name}'")
+                    }
+                };
+                let mut differences = Vec::new();
+                if a.0 != baseline.0 {
+                    differences.push(if a.0 {
+                        "has access to the datasource".to_string()
+                    } else {
+                        "has no access to the datasource".to_string()
+                    });
+                }
+                let extra: Vec<String> = a.1.difference(&baseline.1).map(describe).collect();
+                if !extra.is_empty() {
+                    differences.push(format!("also gets {}", extra.join(", ")));
+                }
+                let missing: Vec<String> = baseline.1.difference(&a.1).map(describe).collect();
+                if !missing.is_empty() {
+                    differences.push(format!("lacks {}", missing.join(", ")));
+                }
+                let policies: Vec<&AnalyzedPolicy> =
+                    a.1.symmetric_difference(&baseline.1)
+                        .filter_map(|(name, _)| self.policies.iter().find(|p| &p.name == name))
+                        .collect();
+                findings.add(
+                    FindingKind::PeerDeviation,
+                    format!(
+                        "{} differs from {peers} peer{} with {label}: {}",
+                        user.username,
+                        if peers == 1 { "" } else { "s" },
+                        differences.join("; ")
+                    ),
+                    &policies,
+                    None,
+                    &BTreeSet::new(),
+                    Some(&user.username),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn table(schema: &str, name: &str, columns: &[&str]) -> CatalogTable {
+        CatalogTable {
+            df_schema: schema.to_string(),
+            schema: schema.to_string(),
+            table: name.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    fn policy(name: &str, policy_type: PolicyType, targets: serde_json::Value) -> AnalyzedPolicy {
+        AnalyzedPolicy {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            policy_type,
+            targets: serde_json::from_value(targets).unwrap(),
+            definition: None,
+            is_enabled: true,
+            enforced: true,
+        }
+    }
+
+    fn assign(
+        policy: &AnalyzedPolicy,
+        user_id: Option<Uuid>,
+        priority: i32,
+    ) -> policy_assignment::Model {
+        let now = Utc::now().naive_utc();
+        policy_assignment::Model {
+            id: Uuid::now_v7(),
+            policy_id: policy.id,
+            data_source_id: Uuid::nil(),
+            user_id,
+            role_id: None,
+            assignment_scope: if user_id.is_some() { "user" } else { "all" }.to_string(),
+            priority,
+            action_status: "enforce".to_string(),
+            purpose: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// `(username, roles, user-scoped assignments)`, assignments as
+    /// `(policy index, priority)`.
+    type TestUser<'a> = (&'a str, &'a [&'a str], &'a [(usize, i32)]);
+
+    /// `all` assignments reach every user.
+    fn input(
+        access_mode: &str,
+        catalog: Vec<CatalogTable>,
+        policies: Vec<AnalyzedPolicy>,
+        all: Vec<(usize, i32)>,
+        users: &[TestUser],
+    ) -> AnalysisInput {
+        let mut assignments: Vec<_> = all
+            .iter()
+            .map(|(i, priority)| assign(&policies[*i], None, *priority))
+            .collect();
+        let user_ids: Vec<Uuid> = users.iter().map(|_| Uuid::now_v7()).collect();
+        for ((_, _, own), user_id) in users.iter().zip(&user_ids) {
+            assignments.extend(
+                own.iter()
+                    .map(|(i, priority)| assign(&policies[*i], Some(*user_id), *priority)),
+            );
+        }
+        let users = users
+            .iter()
+            .zip(&user_ids)
+            .map(|((username, roles, _), user_id)| AnalyzedUser {
+                username: username.to_string(),
+                roles: roles.iter().map(|r| r.to_string()).collect(),
+                has_access: true,
+                assignments: select_effective_assignments(
+                    assignments.iter().cloned(),
+                    *user_id,
+                    &HashSet::new(),
+                ),
+            })
+            .collect();
+        AnalysisInput {
+            access_mode: access_mode.to_string(),
+            combining: CombiningRules::default(),
+            catalog,
+            df_to_upstream: HashMap::new(),
+            tags: CatalogTags::default(),
+            policies,
+            assignments,
+            users,
+        }
+    }
+
+    fn of_kind(findings: &[Finding], kind: FindingKind) -> Vec<&Finding> {
+        findings.iter().filter(|f| f.kind == kind).collect()
+    }
+
+    fn customers() -> CatalogTable {
+        table("public", "customers", &["id", "name", "email", "ssn"])
+    }
+
+    #[test]
+    fn test_column_deny_conflicts_with_column_allow() {
+        let input = input(
+            "open",
+            vec![customers()],
+            vec![
+                policy(
+                    "allow-contact",
+                    PolicyType::ColumnAllow,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["id", "name", "ssn"]}]),
+                ),
+                policy(
+                    "deny-pii",
+                    PolicyType::ColumnDeny,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn", "email"]}]),
+                ),
+            ],
+            vec![(0, 100), (1, 100)],
+            &[("alice", &[], &[])],
+        );
+        let findings = input.analyze();
+
+        let conflicts = of_kind(&findings, FindingKind::Conflict);
+        assert_eq!(conflicts.len(), 1, "{findings:#?}");
+        assert_eq!(conflicts[0].columns, vec!["ssn"]);
+        assert_eq!(conflicts[0].users, vec!["alice"]);
+        assert_eq!(conflicts[0].severity, Severity::Warning);
+        // email is never granted, so denying it does nothing.
+        let redundant = of_kind(&findings, FindingKind::Redundant);
+        assert_eq!(redundant.len(), 1, "{findings:#?}");
+        assert_eq!(redundant[0].columns, vec!["email"]);
+    }
+
+    #[test]
+    fn test_wildcard_allow_does_not_conflict_with_deny() {
+        let input = input(
+            "open",
+            vec![customers()],
+            vec![
+                policy(
+                    "allow-all",
+                    PolicyType::ColumnAllow,
+                    serde_json::json!([{"schemas": ["*"], "tables": ["*"], "columns": ["*"]}]),
+                ),
+                policy(
+                    "deny-ssn",
+                    PolicyType::ColumnDeny,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn"]}]),
+                ),
+            ],
+            vec![(0, 100), (1, 100)],
+            &[("alice", &[], &[])],
+        );
+        assert!(input.analyze().is_empty());
+    }
+
+    #[test]
+    fn test_table_deny_overrides_allow_and_masks() {
+        let input = input(
+            "open",
+            vec![customers()],
+            vec![
+                policy(
+                    "allow-customers",
+                    PolicyType::ColumnAllow,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"]}]),
+                ),
+                policy(
+                    "hide-customers",
+                    PolicyType::TableDeny,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"]}]),
+                ),
+                policy(
+                    "mask-ssn",
+                    PolicyType::ColumnMask,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn"]}]),
+                ),
+            ],
+            vec![(0, 100), (1, 100), (2, 100)],
+            &[("alice", &[], &[]), ("bob", &[], &[])],
+        );
+        let findings = input.analyze();
+
+        let conflicts = of_kind(&findings, FindingKind::Conflict);
+        assert_eq!(conflicts.len(), 1, "{findings:#?}");
+        assert!(
+            conflicts[0]
+                .message
+                .contains("overriding column_allow 'allow-customers'")
+        );
+        assert_eq!(conflicts[0].users, vec!["alice", "bob"]);
+        let redundant = of_kind(&findings, FindingKind::Redundant);
+        assert_eq!(redundant.len(), 1, "{findings:#?}");
+        assert_eq!(redundant[0].policies[1].name, "mask-ssn");
+    }
+
+    #[test]
+    fn test_masks_on_same_column_overlap() {
+        let input = input(
+            "open",
+            vec![customers()],
+            vec![
+                policy(
+                    "mask-ssn-strict",
+                    PolicyType::ColumnMask,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn"]}]),
+                ),
+                policy(
+                    "mask-pii",
+                    PolicyType::ColumnMask,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn", "email"]}]),
+                ),
+            ],
+            vec![(0, 10), (1, 100)],
+            &[("alice", &[], &[])],
+        );
+        let findings = input.analyze();
+
+        assert_eq!(findings.len(), 1, "{findings:#?}");
+        assert_eq!(findings[0].kind, FindingKind::Overlap);
+        assert_eq!(findings[0].columns, vec!["ssn"]);
+        assert!(
+            findings[0]
+                .message
+                .ends_with("'mask-ssn-strict' wins under first_match")
+        );
+    }
+
+    /// Two `column_mask` policies on `ssn`: one for everyone at priority 10, and
+    /// alice's own at 100.
+    fn competing_masks() -> Vec<AnalyzedPolicy> {
+        vec![
+            policy(
+                "mask-ssn-everyone",
+                PolicyType::ColumnMask,
+                serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn"]}]),
+            ),
+            policy(
+                "mask-ssn-alice",
+                PolicyType::ColumnMask,
+                serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn"]}]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_most_specific_mask_wins_over_priority() {
+        let mut input = input(
+            "open",
+            vec![customers()],
+            competing_masks(),
+            vec![(0, 10)],
+            &[("alice", &[], &[(1, 100)])],
+        );
+        input.combining.column_mask = CombineStrategy::MostSpecific;
+        let findings = input.analyze();
+
+        assert_eq!(findings.len(), 1, "{findings:#?}");
+        assert_eq!(findings[0].kind, FindingKind::Overlap);
+        assert_eq!(findings[0].policies[0].name, "mask-ssn-alice");
+        assert!(
+            findings[0]
+                .message
+                .ends_with("'mask-ssn-alice' wins under most_specific"),
+            "{}",
+            findings[0].message
+        );
+    }
+
+    #[test]
+    fn test_most_restrictive_masks_all_apply() {
+        let mut input = input(
+            "open",
+            vec![customers()],
+            competing_masks(),
+            vec![(0, 10)],
+            &[("alice", &[], &[(1, 100)])],
+        );
+        input.combining.column_mask = CombineStrategy::MostRestrictive;
+        let findings = input.analyze();
+
+        assert_eq!(findings.len(), 1, "{findings:#?}");
+        assert_eq!(findings[0].kind, FindingKind::Overlap);
+        assert_eq!(findings[0].policies.len(), 2);
+        assert!(
+            findings[0]
+                .message
+                .ends_with("under most_restrictive it is NULL unless the masks are identical"),
+            "{}",
+            findings[0].message
+        );
+    }
+
+    #[test]
+    fn test_intersection_allows_grant_only_shared_columns() {
+        let policies = || {
+            vec![
+                policy(
+                    "allow-ssn",
+                    PolicyType::ColumnAllow,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["id", "name", "ssn"]}]),
+                ),
+                policy(
+                    "allow-email",
+                    PolicyType::ColumnAllow,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["id", "name", "email"]}]),
+                ),
+                policy(
+                    "deny-ssn",
+                    PolicyType::ColumnDeny,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn"]}]),
+                ),
+            ]
+        };
+        let all = vec![(0, 100), (1, 100), (2, 100)];
+        let users: &[TestUser] = &[("alice", &[], &[])];
+
+        // Under the default union, allow-ssn grants ssn and the deny overrides it.
+        let findings = input(
+            "policy_required",
+            vec![customers()],
+            policies(),
+            all.clone(),
+            users,
+        )
+        .analyze();
+        assert_eq!(
+            of_kind(&findings, FindingKind::Conflict).len(),
+            1,
+            "{findings:#?}"
+        );
+        assert!(of_kind(&findings, FindingKind::UncoveredColumns).is_empty());
+
+        let mut input = input("policy_required", vec![customers()], policies(), all, users);
+        input.combining.column_allow = CombineStrategy::Intersection;
+        let findings = input.analyze();
+        assert!(
+            of_kind(&findings, FindingKind::Conflict).is_empty(),
+            "{findings:#?}"
+        );
+        let redundant = of_kind(&findings, FindingKind::Redundant);
+        assert_eq!(redundant.len(), 1, "{findings:#?}");
+        assert_eq!(redundant[0].policies[0].name, "deny-ssn");
+        let uncovered = of_kind(&findings, FindingKind::UncoveredColumns);
+        assert_eq!(uncovered.len(), 1, "{findings:#?}");
+        assert_eq!(uncovered[0].columns, vec!["email", "ssn"]);
+        assert!(uncovered[0].message.contains("combined under intersection"));
+    }
+
+    #[test]
+    fn test_union_row_filters_all_apply() {
+        let policies = || {
+            vec![
+                policy(
+                    "region-filter",
+                    PolicyType::RowFilter,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"]}]),
+                ),
+                policy(
+                    "support-filter",
+                    PolicyType::RowFilter,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"]}]),
+                ),
+            ]
+        };
+        let users: &[TestUser] = &[("alice", &[], &[])];
+
+        let mut union = input(
+            "open",
+            vec![customers()],
+            policies(),
+            vec![(0, 10), (1, 100)],
+            users,
+        );
+        union.combining.row_filter = CombineStrategy::Union;
+        let findings = union.analyze();
+        assert!(findings.is_empty(), "{findings:#?}");
+
+        // Under first_match only the lower priority applies.
+        let mut first = input(
+            "open",
+            vec![customers()],
+            policies(),
+            vec![(0, 10), (1, 100)],
+            users,
+        );
+        first.combining.row_filter = CombineStrategy::FirstMatch;
+        let findings = first.analyze();
+        assert_eq!(findings.len(), 1, "{findings:#?}");
+        assert_eq!(findings[0].kind, FindingKind::Redundant);
+        assert_eq!(
+            findings[0].message,
+            "row_filter 'support-filter' never applies to public.customers: row_filter 'region-filter' wins under first_match"
+        );
+    }
+
+    #[test]
+    fn test_dead_policies() {
+        let input = input(
+            "open",
+            vec![customers()],
+            vec![
+                policy(
+                    "orders-filter",
+                    PolicyType::RowFilter,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["orders"]}]),
+                ),
+                policy(
+                    "mask-phone",
+                    PolicyType::ColumnMask,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["phone"]}]),
+                ),
+                policy(
+                    "customers-filter",
+                    PolicyType::RowFilter,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"]}]),
+                ),
+            ],
+            vec![(0, 100), (1, 100), (2, 100)],
+            &[("alice", &[], &[])],
+        );
+        let findings = input.analyze();
+
+        let dead: Vec<&str> = of_kind(&findings, FindingKind::DeadPolicy)
+            .iter()
+            .map(|f| f.policies[0].name.as_str())
+            .collect();
+        assert_eq!(dead.len(), 2, "{findings:#?}");
+        assert!(dead.contains(&"orders-filter"));
+        assert!(dead.contains(&"mask-phone"));
+    }
+
+    #[test]
+    fn test_policy_required_coverage() {
+        let input = input(
+            "policy_required",
+            vec![customers(), table("public", "orders", &["id", "total"])],
+            vec![policy(
+                "allow-contact",
+                PolicyType::ColumnAllow,
+                serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["id", "name"]}]),
+            )],
+            vec![(0, 100)],
+            &[("alice", &[], &[])],
+        );
+        let findings = input.analyze();
+
+        let uncovered = of_kind(&findings, FindingKind::UncoveredTable);
+        assert_eq!(uncovered.len(), 1, "{findings:#?}");
+        assert_eq!(uncovered[0].table.as_deref(), Some("public.orders"));
+        let columns = of_kind(&findings, FindingKind::UncoveredColumns);
+        assert_eq!(columns.len(), 1, "{findings:#?}");
+        assert_eq!(columns[0].columns, vec!["email", "ssn"]);
+    }
+
+    #[test]
+    fn test_open_mode_tables_are_covered() {
+        let input = input(
+            "open",
+            vec![customers()],
+            vec![],
+            vec![],
+            &[("alice", &[], &[])],
+        );
+        assert!(input.analyze().is_empty());
+    }
+
+    #[test]
+    fn test_peer_deviation() {
+        let input = input(
+            "open",
+            vec![customers()],
+            vec![
+                policy(
+                    "region-filter",
+                    PolicyType::RowFilter,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"]}]),
+                ),
+                policy(
+                    "mask-ssn",
+                    PolicyType::ColumnMask,
+                    serde_json::json!([{"schemas": ["public"], "tables": ["customers"], "columns": ["ssn"]}]),
+                ),
+            ],
+            vec![(0, 100)],
+            &[
+                ("alice", &["analyst"], &[(1, 100)]),
+                ("bob", &["analyst"], &[(1, 100)]),
+                ("carol", &["analyst"], &[]),
+                ("dave", &["admin"], &[]),
+            ],
+        );
+        let findings = input.analyze();
+
+        assert_eq!(findings.len(), 1, "{findings:#?}");
+        assert_eq!(findings[0].kind, FindingKind::PeerDeviation);
+        assert_eq!(findings[0].users, vec!["carol"]);
+        assert_eq!(findings[0].policies[0].name, "mask-ssn");
+        assert!(
+            findings[0]
+                .message
+                .contains("differs from 2 peers with roles analyst: lacks 'mask-ssn'"),
+            "{}",
+            findings[0].message
+        );
+    }
+
+    #[test]
+    fn test_report_display() {
+        let report = AnalysisReport {
+            datasource_id: Uuid::nil(),
+            datasource_name: "warehouse".to_string(),
+            access_mode: "policy_required".to_string(),
+            users_analyzed: 1,
+            findings: input(
+                "policy_required",
+                vec![customers()],
+                vec![],
+                vec![],
+                &[("alice", &[], &[])],
+            )
+            .analyze(),
+        };
+        let text = report.to_string();
+        assert!(text.starts_with(
+            "Policy analysis for datasource 'warehouse' (access_mode: policy_required, 1 user with access)"
+        ));
+        assert!(
+            text.contains(
+                "WARNING  uncovered_table   No column_allow policy grants public.customers"
+            )
+        );
+        assert!(text.ends_with("1 finding, 1 warning"));
+    }
+}
@@ -0,0 +1,457 @@
+//! How permit policies that hit the same target are combined.
+//!
+//! Deny policies always win and always combine (vector 38), but two permits can
+//! disagree: two `column_mask` policies on one column, a user-scoped row filter
+//! that should replace the role-wide one, two `aggregate_only` thresholds. Each
+//! datasource picks a [`CombineStrategy`] per policy type in its
+//! `policy_combining` setting ([`CombiningRules`]); unset types keep the
+//! defaults below, which are the behaviour before strategies were configurable.
+//!
+//! | Policy type | Default | Also accepted |
+//! |---|---|---|
+//! | `row_filter` | `intersection` (AND) | `union` (OR), `first_match`, `most_specific` |
+//! | `column_mask` | `first_match` | `most_specific`, `most_restrictive` (disagreeing masks yield `NULL`) |
+//! | `column_allow` | `union` | `intersection`, `first_match`, `most_specific` |
+//! | `aggregate_only` | `most_restrictive` (largest `min_group_size`) | `least_restrictive`, `first_match`, `most_specific` |
+//! | `differential_privacy` | `most_restrictive` (smallest `epsilon`) | `least_restrictive`, `first_match`, `most_specific` |
+//!
+//! Precedence is total, so the outcome never depends on load order:
+//!
+//! - `first_match` — lowest assignment `priority`, then the more specific
+//!   scope (user, role, all), then policy name.
+//! - `most_specific` — the more specific scope first, then `priority`, then
+//!   policy name. A user-scoped permit replaces a role-scoped one, which
+//!   replaces one assigned to all users.
+//!
+//! `most_restrictive` / `least_restrictive` pick by value and break ties by
+//! `first_match` precedence. Every target where two or more policies met is
+//! reported as a [`PolicyConflict`] and recorded in
+//! `query_audit_log.policy_conflicts`.
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::policy_match::PolicyType;
+
+/// How the permit policies of one type that match the same target combine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CombineStrategy {
+    /// Only the policy with the lowest assignment priority applies.
+    FirstMatch,
+    /// Only the policy with the most specific assignment scope applies.
+    MostSpecific,
+    /// The strictest value applies.
+    MostRestrictive,
+    /// The most permissive value applies.
+    LeastRestrictive,
+    /// Every policy applies (row filters ANDed, allow lists intersected).
+    Intersection,
+    /// Any policy suffices (row filters ORed, allow lists merged).
+    Union,
+}
+
+impl CombineStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FirstMatch => "first_match",
+            Self::MostSpecific => "most_specific",
+            Self::MostRestrictive => "most_restrictive",
+            Self::LeastRestrictive => "least_restrictive",
+            Self::Intersection => "intersection",
+            Self::Union => "union",
+        }
+    }
+
+    /// Sort key under which the first contender takes precedence.
+    fn precedence(self, c: &Precedence) -> (u8, i32, u8) {
+        match self {
+            Self::MostSpecific => (c.scope as u8, c.priority, 0),
+            _ => (0, c.priority, c.scope as u8),
+        }
+    }
+}
+
+impl std::fmt::Display for CombineStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The scope of the assignment that brings a policy to a user, most specific first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum AssignmentScope {
+    User,
+    Role,
+    #[default]
+    All,
+}
+
+impl AssignmentScope {
+    /// Parse `policy_assignment.assignment_scope`; unknown values rank as `all`.
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "user" => Self::User,
+            "role" => Self::Role,
+            _ => Self::All,
+        }
+    }
+}
+
+/// Where a policy stands among the user's policies: its effective assignment's
+/// priority and scope, and its name as the final tie-breaker.
+#[derive(Debug, Clone)]
+pub struct Precedence {
+    pub name: String,
+    pub priority: i32,
+    pub scope: AssignmentScope,
+}
+
+/// Combination strategies per policy type, from `data_source.policy_combining`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CombiningRules {
+    pub row_filter: CombineStrategy,
+    pub column_mask: CombineStrategy,
+    pub column_allow: CombineStrategy,
+    pub aggregate_only: CombineStrategy,
+    pub differential_privacy: CombineStrategy,
+}
+
+impl Default for CombiningRules {
+    fn default() -> Self {
+        Self {
+            row_filter: CombineStrategy::Intersection,
+            column_mask: CombineStrategy::FirstMatch,
+            column_allow: CombineStrategy::Union,
+            aggregate_only: CombineStrategy::MostRestrictive,
+            differential_privacy: CombineStrategy::MostRestrictive,
+        }
+    }
+}
+
+impl CombiningRules {
+    /// Parse and validate the `policy_combining` object of a datasource request.
+    /// Types left out keep their default.
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, String> {
+        let rules: Self = serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid policy_combining: {e}"))?;
+        for (policy_type, strategy) in rules.entries() {
+            if !allowed(policy_type).contains(&strategy) {
+                return Err(format!(
+                    "policy_combining.{policy_type} does not support '{strategy}' (expected one of: {})",
+                    allowed(policy_type)
+                        .iter()
+                        .map(|s| s.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Rules stored on a datasource. `None` or a value that no longer validates
+    /// falls back to the defaults.
+    pub fn parse(stored: Option<&str>) -> Self {
+        let Some(stored) = stored else {
+            return Self::default();
+        };
+        match serde_json::from_str(stored)
+            .map_err(|e| e.to_string())
+            .and_then(|v| Self::from_json(&v))
+        {
+            Ok(rules) => rules,
+            Err(e) => {
+                tracing::error!(error = %e, "Invalid stored policy_combining; using defaults");
+                Self::default()
+            }
+        }
+    }
+
+    /// The strategy for `policy_type`; `None` for types that are not combined
+    /// by a configurable strategy.
+    pub fn strategy(&self, policy_type: PolicyType) -> Option<CombineStrategy> {
+        self.entries()
+            .into_iter()
+            .find(|(t, _)| *t == policy_type)
+            .map(|(_, s)| s)
+    }
+
+    fn entries(&self) -> [(PolicyType, CombineStrategy); 5] {
+        [
+            (PolicyType::RowFilter, self.row_filter),
+            (PolicyType::ColumnMask, self.column_mask),
+            (PolicyType::ColumnAllow, self.column_allow),
+            (PolicyType::AggregateOnly, self.aggregate_only),
+            (PolicyType::DifferentialPrivacy, self.differential_privacy),
+        ]
+    }
+}
+
+/// The strategies a policy type can be combined with.
+pub fn allowed(policy_type: PolicyType) -> &'static [CombineStrategy] {
+    use CombineStrategy::*;
+    match policy_type {
+        PolicyType::RowFilter | PolicyType::ColumnAllow => {
+            &[Intersection, Union, FirstMatch, MostSpecific]
+        }
+        PolicyType::ColumnMask => &[FirstMatch, MostSpecific, MostRestrictive],
+        PolicyType::AggregateOnly | PolicyType::DifferentialPrivacy => {
+            &[MostRestrictive, LeastRestrictive, FirstMatch, MostSpecific]
+        }
+        _ => &[],
+    }
+}
+
+/// One policy's effect on a target, competing with other policies of its type.
+pub struct Contender<T> {
+    pub precedence: Precedence,
+    pub value: T,
+}
+
+/// What [`combine`] kept: the values to apply, in precedence order, and the
+/// conflict to audit when more than one policy competed.
+pub struct Combined<T> {
+    pub values: Vec<T>,
+    pub conflict: Option<PolicyConflict>,
+}
+
+/// Permit policies of one type that met on one target, and which of them applied.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyConflict {
+    pub policy_type: PolicyType,
+    /// `schema.table`, or `schema.table.column` for column policies.
+    pub target: String,
+    pub strategy: CombineStrategy,
+    /// Policies whose effect applies, in precedence order.
+    pub applied: Vec<String>,
+    /// Policies that matched the target but were set aside.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overridden: Vec<String>,
+}
+
+/// Combine the contenders for one target under `strategy`.
+///
+/// `stricter` orders two values by restrictiveness (`Greater` = stricter) for
+/// `most_restrictive` / `least_restrictive`. Without it those strategies keep
+/// every value, for the caller to merge (disagreeing masks become `NULL`), as
+/// `intersection` and `union` do.
+pub fn combine<T>(
+    policy_type: PolicyType,
+    target: String,
+    strategy: CombineStrategy,
+    mut contenders: Vec<Contender<T>>,
+    stricter: Option<fn(&T, &T) -> Ordering>,
+) -> Combined<T> {
+    contenders.sort_by(|a, b| {
+        strategy
+            .precedence(&a.precedence)
+            .cmp(&strategy.precedence(&b.precedence))
+            .then_with(|| a.precedence.name.cmp(&b.precedence.name))
+    });
+    let winner = match (strategy, stricter) {
+        (CombineStrategy::FirstMatch | CombineStrategy::MostSpecific, _) => Some(0),
+        (CombineStrategy::MostRestrictive, Some(stricter)) => {
+            pick(&contenders, |a, b| stricter(a, b) == Ordering::Greater)
+        }
+        (CombineStrategy::LeastRestrictive, Some(stricter)) => {
+            pick(&contenders, |a, b| stricter(a, b) == Ordering::Less)
+        }
+        _ => None,
+    };
+    let conflict = (contenders.len() > 1).then(|| {
+        let (applied, overridden) = contenders
+            .iter()
+            .enumerate()
+            .partition::<Vec<_>, _>(|(i, _)| winner.is_none_or(|w| w == *i));
+        PolicyConflict {
+            policy_type,
+            target,
+            strategy,
+            applied: applied
+                .into_iter()
+                .map(|(_, c)| c.precedence.name.clone())
+                .collect(),
+            overridden: overridden
+                .into_iter()
+                .map(|(_, c)| c.precedence.name.clone())
+                .collect(),
+        }
+    });
+    let values = match winner {
+        Some(w) => contenders
+            .into_iter()
+            .nth(w)
+            .map(|c| c.value)
+            .into_iter()
+            .collect(),
+        None => contenders.into_iter().map(|c| c.value).collect(),
+    };
+    Combined { values, conflict }
+}
+
+/// Index of the first contender no later one `beats`.
+fn pick<T>(contenders: &[Contender<T>], beats: impl Fn(&T, &T) -> bool) -> Option<usize> {
+    if contenders.is_empty() {
+        return None;
+    }
+    let mut best = 0;
+    for (i, c) in contenders.iter().enumerate().skip(1) {
+        if beats(&c.value, &contenders[best].value) {
+            best = i;
+        }
+    }
+    Some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contender(name: &str, priority: i32, scope: AssignmentScope, value: i64) -> Contender<i64> {
+        Contender {
+            precedence: Precedence {
+                name: name.to_string(),
+                priority,
+                scope,
+            },
+            value,
+        }
+    }
+
+    /// A role-wide policy at priority 10, a user's own at 50 and an all-users
+    /// one at 10 — the three scopes together.
+    fn contenders() -> Vec<Contender<i64>> {
+        vec![
+            contender("user-own", 50, AssignmentScope::User, 5),
+            contender("everyone", 10, AssignmentScope::All, 20),
+            contender("analysts", 10, AssignmentScope::Role, 10),
+        ]
+    }
+
+    fn run(
+        strategy: CombineStrategy,
+        stricter: Option<fn(&i64, &i64) -> Ordering>,
+    ) -> Combined<i64> {
+        combine(
+            PolicyType::AggregateOnly,
+            "public.patients".to_string(),
+            strategy,
+            contenders(),
+            stricter,
+        )
+    }
+
+    #[test]
+    fn first_match_prefers_priority_then_scope() {
+        let combined = run(CombineStrategy::FirstMatch, Some(i64::cmp));
+        assert_eq!(combined.values, vec![10]);
+        let conflict = combined.conflict.unwrap();
+        assert_eq!(conflict.applied, vec!["analysts"]);
+        assert_eq!(conflict.overridden, vec!["everyone", "user-own"]);
+    }
+
+    #[test]
+    fn most_specific_prefers_user_then_role_then_all() {
+        let combined = run(CombineStrategy::MostSpecific, Some(i64::cmp));
+        assert_eq!(combined.values, vec![5]);
+        assert_eq!(
+            combined.conflict.unwrap().overridden,
+            vec!["analysts", "everyone"]
+        );
+
+        let mut without_user = contenders();
+        without_user.remove(0);
+        let combined = combine(
+            PolicyType::AggregateOnly,
+            "public.patients".to_string(),
+            CombineStrategy::MostSpecific,
+            without_user,
+            None,
+        );
+        assert_eq!(combined.values, vec![10]);
+    }
+
+    #[test]
+    fn restrictiveness_picks_by_value() {
+        let combined = run(CombineStrategy::MostRestrictive, Some(i64::cmp));
+        assert_eq!(combined.values, vec![20]);
+        assert_eq!(combined.conflict.unwrap().applied, vec!["everyone"]);
+        assert_eq!(
+            run(CombineStrategy::LeastRestrictive, Some(i64::cmp)).values,
+            vec![5]
+        );
+
+        // Ties go to first_match precedence.
+        let tied = vec![
+            contender("b", 1, AssignmentScope::All, 7),
+            contender("a", 1, AssignmentScope::All, 7),
+        ];
+        let combined = combine(
+            PolicyType::AggregateOnly,
+            "public.patients".to_string(),
+            CombineStrategy::MostRestrictive,
+            tied,
+            Some(i64::cmp),
+        );
+        assert_eq!(combined.conflict.unwrap().applied, vec!["a"]);
+    }
+
+    #[test]
+    fn intersection_and_union_keep_every_value_in_order() {
+        for strategy in [CombineStrategy::Intersection, CombineStrategy::Union] {
+            let combined = run(strategy, None);
+            assert_eq!(combined.values, vec![10, 20, 5]);
+            let conflict = combined.conflict.unwrap();
+            assert_eq!(conflict.applied, vec!["analysts", "everyone", "user-own"]);
+            assert!(conflict.overridden.is_empty());
+        }
+    }
+
+    #[test]
+    fn a_single_policy_is_not_a_conflict() {
+        let combined = combine(
+            PolicyType::RowFilter,
+            "public.orders".to_string(),
+            CombineStrategy::FirstMatch,
+            vec![contender("only", 1, AssignmentScope::User, 1)],
+            None,
+        );
+        assert_eq!(combined.values, vec![1]);
+        assert!(combined.conflict.is_none());
+    }
+
+    #[test]
+    fn rules_validate_per_type() {
+        assert_eq!(
+            CombiningRules::from_json(&serde_json::json!({})).unwrap(),
+            CombiningRules::default()
+        );
+        let rules = CombiningRules::from_json(&serde_json::json!({
+            "row_filter": "union",
+            "column_mask": "most_specific",
+        }))
+        .unwrap();
+        assert_eq!(rules.row_filter, CombineStrategy::Union);
+        assert_eq!(
+            rules.strategy(PolicyType::ColumnMask),
+            Some(CombineStrategy::MostSpecific)
+        );
+        assert_eq!(rules.strategy(PolicyType::JoinOnly), None);
+
+        for bad in [
+            serde_json::json!({"column_mask": "union"}),
+            serde_json::json!({"row_filter": "least_restrictive"}),
+            serde_json::json!({"column_deny": "union"}),
+            serde_json::json!({"row_filter": "sometimes"}),
+        ] {
+            assert!(CombiningRules::from_json(&bad).is_err(), "{bad}");
+        }
+        assert_eq!(
+            CombiningRules::parse(Some("not json")),
+            CombiningRules::default()
+        );
+    }
+}
@@ -12,6 +12,26 @@ pub enum PolicyType {
     ColumnAllow,
     ColumnDeny,
     TableDeny,
+    /// Target tables may only be read through aggregate queries, with groups
+    /// smaller than `min_group_size` suppressed.
+    AggregateOnly,
+    /// Aggregates over target tables get calibrated noise, charged against a
+    /// per-user privacy budget.
+    DifferentialPrivacy,
+    /// Caps the rows and bytes a query returns, and the rows a user receives
+    /// per time window, when the query reads a target table.
+    ResultLimit,
+    /// Refuses queries on target tables whose shape breaks a rule (`SELECT *`,
+    /// missing partition predicates, cross joins, too many joins).
+    QueryGuard,
+    /// Target columns are join keys: they may only be compared for equality
+    /// with a column of another table, never returned or computed on.
+    JoinOnly,
+    /// Grants `INSERT`, `UPDATE` or `DELETE` on target tables of a datasource in
+    /// write mode; INSERT and UPDATE may only set the target columns.
+    WriteAllow,
+    /// Target columns may never be written, whatever `write_allow` grants.
+    WriteDeny,
 }
 
 impl PolicyType {
@@ -22,12 +42,24 @@ impl PolicyType {
             Self::ColumnAllow => "column_allow",
             Self::ColumnDeny => "column_deny",
             Self::TableDeny => "table_deny",
+            Self::AggregateOnly => "aggregate_only",
+            Self::DifferentialPrivacy => "differential_privacy",
+            Self::ResultLimit => "result_limit",
+            Self::QueryGuard => "query_guard",
+            Self::JoinOnly => "join_only",
+            Self::WriteAllow => "write_allow",
+            Self::WriteDeny => "write_deny",
         }
     }
 
-    /// Returns true for deny-type policies (ColumnDeny, TableDeny).
+    /// Returns true for deny-type policies (ColumnDeny, TableDeny, WriteDeny).
     pub fn is_deny(self) -> bool {
-        matches!(self, Self::ColumnDeny | Self::TableDeny)
+        matches!(self, Self::ColumnDeny | Self::TableDeny | Self::WriteDeny)
+    }
+
+    /// Returns true for policy types that only govern write statements.
+    pub fn is_write(self) -> bool {
+        matches!(self, Self::WriteAllow | Self::WriteDeny)
     }
 
     /// Returns true for policy types that affect catalog-level visibility
@@ -54,16 +86,64 @@ impl std::str::FromStr for PolicyType {
             "column_allow" => Ok(Self::ColumnAllow),
             "column_deny" => Ok(Self::ColumnDeny),
             "table_deny" => Ok(Self::TableDeny),
+            "aggregate_only" => Ok(Self::AggregateOnly),
+            "differential_privacy" => Ok(Self::DifferentialPrivacy),
+            "result_limit" => Ok(Self::ResultLimit),
+            "query_guard" => Ok(Self::QueryGuard),
+            "join_only" => Ok(Self::JoinOnly),
+            "write_allow" => Ok(Self::WriteAllow),
+            "write_deny" => Ok(Self::WriteDeny),
             other => Err(format!("Unknown policy_type: '{other}'")),
         }
     }
 }
 
+/// Whether a policy (or one assignment of it) is applied or only evaluated.
+///
+/// A policy runs in shadow mode when the policy itself or the assignment that
+/// brings it into scope is `Shadow`: its effect is computed and written to the
+/// query audit log as a counterfactual, but the query is not changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionStatus {
+    #[default]
+    Enforce,
+    Shadow,
+}
+
+impl ActionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Enforce => "enforce",
+            Self::Shadow => "shadow",
+        }
+    }
+}
+
+impl std::fmt::Display for ActionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ActionStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "enforce" => Ok(Self::Enforce),
+            "shadow" => Ok(Self::Shadow),
+            other => Err(format!("Unknown action_status: '{other}'")),
+        }
+    }
+}
+
 // ---------- resource targeting ----------
 
 /// A resource targeting entry: which schemas, tables, and optionally columns a policy applies to.
 ///
-/// Supports `"*"` and prefix/suffix globs (`"prefix*"`, `"*suffix"`) in any field.
+/// Supports `"*"` and prefix/suffix globs (`"prefix*"`, `"*suffix"`) in any field, and
+/// `"tag:<name>"` selectors that match catalog objects by their (inherited) tags.
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct TargetEntry {
     pub schemas: Vec<String>,
@@ -73,24 +153,218 @@ pub struct TargetEntry {
 }
 
 impl TargetEntry {
+    /// Returns true if any schema selector matches the given DataFusion schema.
+    ///
+    /// Resolves df_schema aliases via `df_to_upstream` before matching; `tag:` selectors
+    /// are checked against the schema's tags in `tags`.
+    pub fn matches_schema(
+        &self,
+        df_schema: &str,
+        df_to_upstream: &HashMap<String, String>,
+        tags: &CatalogTags,
+    ) -> bool {
+        let upstream = upstream_schema(df_schema, df_to_upstream);
+        self.schemas
+            .iter()
+            .any(|sp| matches_selector(sp, upstream, || tags.schema_tags(upstream)))
+    }
+
     /// Returns true if this entry matches the given (df_schema, table) pair.
     ///
-    /// Resolves df_schema aliases via `df_to_upstream` before matching.
+    /// Resolves df_schema aliases via `df_to_upstream` before matching; `tag:` selectors
+    /// are checked against the effective tags in `tags`.
     pub fn matches_table(
         &self,
         df_schema: &str,
         table: &str,
         df_to_upstream: &HashMap<String, String>,
+        tags: &CatalogTags,
     ) -> bool {
-        let upstream = df_to_upstream
-            .get(df_schema)
-            .map(|s| s.as_str())
-            .unwrap_or(df_schema);
-        self.schemas.iter().any(|sp| matches_pattern(sp, upstream))
-            && self.tables.iter().any(|tp| matches_pattern(tp, table))
+        let upstream = upstream_schema(df_schema, df_to_upstream);
+        self.matches_schema(df_schema, df_to_upstream, tags)
+            && self
+                .tables
+                .iter()
+                .any(|tp| matches_selector(tp, table, || tags.table_tags(upstream, table)))
+    }
+
+    /// Column patterns of this entry for one table, with every `tag:` selector replaced
+    /// by the names of the table's columns that carry the tag.
+    ///
+    /// Name patterns (including globs) are returned unchanged for
+    /// [`expand_column_patterns`]. Empty when the entry has no `columns`.
+    pub fn column_patterns(
+        &self,
+        df_schema: &str,
+        table: &str,
+        table_columns: &[&str],
+        df_to_upstream: &HashMap<String, String>,
+        tags: &CatalogTags,
+    ) -> Vec<String> {
+        let Some(patterns) = &self.columns else {
+            return vec![];
+        };
+        let upstream = upstream_schema(df_schema, df_to_upstream);
+        let mut resolved = Vec::new();
+        for pattern in patterns {
+            match pattern.strip_prefix(TAG_SELECTOR_PREFIX) {
+                Some(selector) => resolved.extend(
+                    table_columns
+                        .iter()
+                        .filter(|c| has_tag(&tags.column_tags(upstream, table, c), selector))
+                        .map(|c| c.to_string()),
+                ),
+                None => resolved.push(pattern.clone()),
+            }
+        }
+        resolved
     }
 }
 
+// ---------- catalog tags ----------
+
+/// Prefix marking a target selector as a tag lookup (`"tag:pii"`) instead of a name pattern.
+pub const TAG_SELECTOR_PREFIX: &str = "tag:";
+
+/// Tags attached to the catalog objects of one datasource, keyed by upstream names.
+///
+/// Each level holds only the tags set on that object. Effective tags are resolved on
+/// lookup: schema tags flow down to tables and table tags down to columns. A nearer
+/// `key:value` tag replaces an inherited tag with the same key, and `!name` drops an
+/// inherited `name` (or every inherited `name:*`).
+#[derive(Debug, Clone, Default)]
+pub struct CatalogTags {
+    schemas: HashMap<String, Vec<String>>,
+    tables: HashMap<(String, String), Vec<String>>,
+    columns: HashMap<(String, String, String), Vec<String>>,
+}
+
+impl CatalogTags {
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty() && self.tables.is_empty() && self.columns.is_empty()
+    }
+
+    pub fn set_schema(&mut self, schema: &str, tags: Vec<String>) {
+        if !tags.is_empty() {
+            self.schemas.insert(schema.to_string(), tags);
+        }
+    }
+
+    pub fn set_table(&mut self, schema: &str, table: &str, tags: Vec<String>) {
+        if !tags.is_empty() {
+            self.tables
+                .insert((schema.to_string(), table.to_string()), tags);
+        }
+    }
+
+    pub fn set_column(&mut self, schema: &str, table: &str, column: &str, tags: Vec<String>) {
+        if !tags.is_empty() {
+            self.columns.insert(
+                (schema.to_string(), table.to_string(), column.to_string()),
+                tags,
+            );
+        }
+    }
+
+    /// Effective tags of an upstream schema.
+    pub fn schema_tags(&self, schema: &str) -> Vec<String> {
+        let own = self.schemas.get(schema).map_or(&[][..], Vec::as_slice);
+        inherit_tags(&[], own)
+    }
+
+    /// Effective tags of a table: its schema's tags overridden by its own.
+    pub fn table_tags(&self, schema: &str, table: &str) -> Vec<String> {
+        let key = (schema.to_string(), table.to_string());
+        let own = self.tables.get(&key).map_or(&[][..], Vec::as_slice);
+        inherit_tags(&self.schema_tags(schema), own)
+    }
+
+    /// Effective tags of a column: its table's effective tags overridden by its own.
+    pub fn column_tags(&self, schema: &str, table: &str, column: &str) -> Vec<String> {
+        let key = (schema.to_string(), table.to_string(), column.to_string());
+        let own = self.columns.get(&key).map_or(&[][..], Vec::as_slice);
+        inherit_tags(&self.table_tags(schema, table), own)
+    }
+}
+
+/// Parse the JSON array stored in a catalog row's `tags` column. `None` or invalid JSON = no tags.
+pub fn parse_tags(raw: Option<&str>) -> Vec<String> {
+    raw.and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or_default()
+}
+
+/// Validate a tag set on a catalog object: `name`, `key:value`, or `!name` to drop an
+/// inherited tag. Parts use ASCII letters, digits, `_`, `-` and `.`.
+pub fn validate_tag(tag: &str) -> Result<(), String> {
+    let body = tag.strip_prefix('!').unwrap_or(tag);
+    let valid_part = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    };
+    let valid = match body.split_once(':') {
+        Some((key, value)) => valid_part(key) && valid_part(value),
+        None => valid_part(body),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid tag '{tag}': expected 'name', 'key:value' or '!name' \
+             (letters, digits, '_', '-', '.')"
+        ))
+    }
+}
+
+/// The key of a tag: `sensitivity` for `sensitivity:high`, the tag itself otherwise.
+fn tag_key(tag: &str) -> &str {
+    tag.split_once(':').map_or(tag, |(key, _)| key)
+}
+
+/// Apply one level's own tags on top of the tags inherited from its parent.
+fn inherit_tags(inherited: &[String], own: &[String]) -> Vec<String> {
+    let mut tags = inherited.to_vec();
+    for tag in own {
+        if let Some(removed) = tag.strip_prefix('!') {
+            tags.retain(|t| t != removed && tag_key(t) != removed);
+        } else {
+            let key = tag_key(tag);
+            if key != tag {
+                tags.retain(|t| tag_key(t) != key);
+            }
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+    }
+    tags
+}
+
+/// Whether `tags` satisfies a tag selector (the part after `tag:`).
+///
+/// The selector is matched like a name pattern against each tag; a bare key
+/// (`sensitivity`) also matches any `key:value` tag with that key.
+fn has_tag(tags: &[String], selector: &str) -> bool {
+    tags.iter()
+        .any(|t| matches_pattern(selector, t) || tag_key(t) == selector)
+}
+
+/// Match a target selector: `tag:<selector>` against the value's effective tags
+/// (computed lazily), anything else as a name pattern against the value.
+fn matches_selector(pattern: &str, value: &str, tags: impl FnOnce() -> Vec<String>) -> bool {
+    match pattern.strip_prefix(TAG_SELECTOR_PREFIX) {
+        Some(selector) => has_tag(&tags(), selector),
+        None => matches_pattern(pattern, value),
+    }
+}
+
+fn upstream_schema<'a>(df_schema: &'a str, df_to_upstream: &'a HashMap<String, String>) -> &'a str {
+    df_to_upstream
+        .get(df_schema)
+        .map(|s| s.as_str())
+        .unwrap_or(df_schema)
+}
+
 // ---------- policy definitions ----------
 
 /// Parsed definition for a `row_filter` policy.
@@ -105,6 +379,135 @@ pub struct ColumnMaskDef {
     pub mask_expression: String,
 }
 
+/// Parsed definition for an `aggregate_only` policy.
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct AggregateOnlyDef {
+    /// Smallest group (`count(*)`) an aggregate over a target table may return.
+    pub min_group_size: i64,
+}
+
+/// Noise distribution used by a `differential_privacy` policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseMechanism {
+    #[default]
+    Laplace,
+    Gaussian,
+}
+
+impl NoiseMechanism {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Laplace => "laplace",
+            Self::Gaussian => "gaussian",
+        }
+    }
+}
+
+/// Parsed definition for a `differential_privacy` policy.
+#[derive(Debug, serde::Deserialize, Clone, PartialEq)]
+pub struct DifferentialPrivacyDef {
+    /// Privacy loss charged for each noised aggregate in a query.
+    pub epsilon: f64,
+    /// Total epsilon a user may spend per datasource.
+    pub budget: f64,
+    #[serde(default)]
+    pub mechanism: NoiseMechanism,
+    /// Failure probability for the Gaussian mechanism (required for it).
+    #[serde(default)]
+    pub delta: Option<f64>,
+    /// Contribution bounds `[lower, upper]` per column; `SUM` / `AVG` inputs are
+    /// clamped to them and only columns listed here may be summed or averaged.
+    #[serde(default)]
+    pub bounds: HashMap<String, [f64; 2]>,
+}
+
+/// What a `result_limit` policy does with a result that goes past a cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitAction {
+    /// Return the result up to the cap and drop the rest.
+    #[default]
+    Truncate,
+    /// Fail the query.
+    Deny,
+}
+
+/// Parsed definition for a `result_limit` policy. At least one cap is set.
+#[derive(Debug, serde::Deserialize, Clone, PartialEq)]
+pub struct ResultLimitDef {
+    /// Most rows a single query may return.
+    #[serde(default)]
+    pub max_rows: Option<u64>,
+    /// Most bytes (Arrow data size) a single query may return.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Most rows a user may receive per `window_secs`, across queries.
+    #[serde(default)]
+    pub window_rows: Option<u64>,
+    /// Length of the fixed window `window_rows` is counted over.
+    #[serde(default)]
+    pub window_secs: Option<u64>,
+    #[serde(default)]
+    pub on_exceed: LimitAction,
+}
+
+/// Parsed definition for a `query_guard` policy. At least one rule is set.
+#[derive(Debug, serde::Deserialize, Clone, PartialEq, Default)]
+pub struct QueryGuardDef {
+    /// Reject `SELECT *` and `t.*` over a target table.
+    #[serde(default)]
+    pub forbid_select_star: bool,
+    /// With `forbid_select_star`, only tables with at least this many columns
+    /// count as wide; narrower tables may still be read with `*`.
+    #[serde(default)]
+    pub select_star_min_columns: Option<usize>,
+    /// Columns every query must filter a target table on (for example its
+    /// partition key). Columns the table does not have are ignored.
+    #[serde(default)]
+    pub require_predicate_on: Vec<String>,
+    /// Reject joins without a join condition that involve a target table.
+    #[serde(default)]
+    pub forbid_cross_join: bool,
+    /// Most joins a query on a target table may contain.
+    #[serde(default)]
+    pub max_joins: Option<usize>,
+}
+
+/// A write statement a `write_allow` policy can grant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteOperation {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl WriteOperation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Insert => "insert",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+impl std::fmt::Display for WriteOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parsed definition for a `write_allow` policy.
+#[derive(Debug, serde::Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WriteAllowDef {
+    /// Statements granted on the target tables. `insert` and `update` may only
+    /// set the target columns; `delete` removes whole rows.
+    pub operations: Vec<WriteOperation>,
+}
+
 // ---------- pattern matching ----------
 
 /// Check whether a pattern matches a value.
@@ -463,6 +866,13 @@ mod tests {
         assert!(!PolicyType::ColumnAllow.is_deny());
         assert!(PolicyType::ColumnDeny.is_deny());
         assert!(PolicyType::TableDeny.is_deny());
+        assert!(!PolicyType::AggregateOnly.is_deny());
+        assert!(!PolicyType::DifferentialPrivacy.is_deny());
+        assert!(!PolicyType::ResultLimit.is_deny());
+        assert!(!PolicyType::QueryGuard.is_deny());
+        assert!(!PolicyType::JoinOnly.is_deny());
+        assert!(!PolicyType::WriteAllow.is_deny());
+        assert!(PolicyType::WriteDeny.is_deny());
     }
 
     #[test]
@@ -472,6 +882,13 @@ mod tests {
         assert!(PolicyType::ColumnAllow.affects_visibility());
         assert!(PolicyType::ColumnDeny.affects_visibility());
         assert!(PolicyType::TableDeny.affects_visibility());
+        assert!(!PolicyType::AggregateOnly.affects_visibility());
+        assert!(!PolicyType::DifferentialPrivacy.affects_visibility());
+        assert!(!PolicyType::ResultLimit.affects_visibility());
+        assert!(!PolicyType::QueryGuard.affects_visibility());
+        assert!(!PolicyType::JoinOnly.affects_visibility());
+        assert!(!PolicyType::WriteAllow.affects_visibility());
+        assert!(!PolicyType::WriteDeny.affects_visibility());
     }
 
     #[test]
@@ -481,43 +898,141 @@ mod tests {
         assert_eq!(PolicyType::ColumnAllow.as_str(), "column_allow");
         assert_eq!(PolicyType::ColumnDeny.as_str(), "column_deny");
         assert_eq!(PolicyType::TableDeny.as_str(), "table_deny");
+        assert_eq!(PolicyType::AggregateOnly.as_str(), "aggregate_only");
+        assert_eq!(
+            PolicyType::DifferentialPrivacy.as_str(),
+            "differential_privacy"
+        );
+        assert_eq!(PolicyType::ResultLimit.as_str(), "result_limit");
+        assert_eq!(PolicyType::QueryGuard.as_str(), "query_guard");
+        assert_eq!(PolicyType::JoinOnly.as_str(), "join_only");
+        assert_eq!(PolicyType::WriteAllow.as_str(), "write_allow");
+        assert_eq!(PolicyType::WriteDeny.as_str(), "write_deny");
     }
 
     #[test]
     fn test_target_entry_matches_table() {
         let map = HashMap::new();
+        let tags = CatalogTags::default();
         let entry = TargetEntry {
             schemas: vec!["public".to_string()],
             tables: vec!["customers".to_string(), "employees".to_string()],
             columns: None,
         };
-        assert!(entry.matches_table("public", "customers", &map));
-        assert!(entry.matches_table("public", "employees", &map));
-        assert!(!entry.matches_table("public", "orders", &map));
-        assert!(!entry.matches_table("private", "customers", &map));
+        assert!(entry.matches_table("public", "customers", &map, &tags));
+        assert!(entry.matches_table("public", "employees", &map, &tags));
+        assert!(!entry.matches_table("public", "orders", &map, &tags));
+        assert!(!entry.matches_table("private", "customers", &map, &tags));
     }
 
     #[test]
     fn test_target_entry_matches_table_wildcard() {
         let map = HashMap::new();
+        let tags = CatalogTags::default();
         let entry = TargetEntry {
             schemas: vec!["*".to_string()],
             tables: vec!["*".to_string()],
             columns: None,
         };
-        assert!(entry.matches_table("any_schema", "any_table", &map));
+        assert!(entry.matches_table("any_schema", "any_table", &map, &tags));
     }
 
     #[test]
     fn test_target_entry_matches_table_alias() {
         let mut map = HashMap::new();
         map.insert("sales".to_string(), "public".to_string());
+        let tags = CatalogTags::default();
         let entry = TargetEntry {
             schemas: vec!["public".to_string()],
             tables: vec!["orders".to_string()],
             columns: None,
         };
-        assert!(entry.matches_table("sales", "orders", &map));
-        assert!(!entry.matches_table("private", "orders", &map));
+        assert!(entry.matches_table("sales", "orders", &map, &tags));
+        assert!(!entry.matches_table("private", "orders", &map, &tags));
+    }
+
+    // --- catalog tags ---
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_catalog_tags_inherit_down_the_hierarchy() {
+        let mut catalog = CatalogTags::default();
+        catalog.set_schema("public", tags(&["financial"]));
+        catalog.set_table("public", "customers", tags(&["pii"]));
+        assert_eq!(catalog.schema_tags("public"), tags(&["financial"]));
+        assert_eq!(
+            catalog.table_tags("public", "customers"),
+            tags(&["financial", "pii"])
+        );
+        assert_eq!(
+            catalog.column_tags("public", "customers", "email"),
+            tags(&["financial", "pii"])
+        );
+        assert!(catalog.column_tags("other", "t", "c").is_empty());
+    }
+
+    #[test]
+    fn test_catalog_tags_key_value_overrides_inherited_key() {
+        let mut catalog = CatalogTags::default();
+        catalog.set_table("public", "customers", tags(&["sensitivity:high"]));
+        catalog.set_column("public", "customers", "country", tags(&["sensitivity:low"]));
+        assert_eq!(
+            catalog.column_tags("public", "customers", "country"),
+            tags(&["sensitivity:low"])
+        );
+        assert_eq!(
+            catalog.column_tags("public", "customers", "ssn"),
+            tags(&["sensitivity:high"])
+        );
+    }
+
+    #[test]
+    fn test_catalog_tags_negation_removes_inherited_tag() {
+        let mut catalog = CatalogTags::default();
+        catalog.set_table("public", "customers", tags(&["pii", "sensitivity:high"]));
+        catalog.set_column("public", "customers", "id", tags(&["!pii", "!sensitivity"]));
+        assert!(catalog.column_tags("public", "customers", "id").is_empty());
+    }
+
+    #[test]
+    fn test_target_entry_tag_selectors() {
+        let mut catalog = CatalogTags::default();
+        catalog.set_schema("public", tags(&["prod"]));
+        catalog.set_table("public", "customers", tags(&["financial"]));
+        catalog.set_column("public", "customers", "email", tags(&["pii"]));
+        catalog.set_column("public", "customers", "tier", tags(&["sensitivity:high"]));
+        let mut map = HashMap::new();
+        map.insert("sales".to_string(), "public".to_string());
+
+        let entry = TargetEntry {
+            schemas: vec!["tag:prod".to_string()],
+            tables: vec!["tag:financial".to_string()],
+            columns: Some(vec![
+                "tag:pii".to_string(),
+                "tag:sensitivity".to_string(),
+                "id".to_string(),
+            ]),
+        };
+        assert!(entry.matches_table("sales", "customers", &map, &catalog));
+        assert!(!entry.matches_table("sales", "orders", &map, &catalog));
+        assert!(!entry.matches_table("other", "customers", &map, &catalog));
+
+        let cols = ["id", "email", "tier", "name"];
+        let patterns = entry.column_patterns("sales", "customers", &cols, &map, &catalog);
+        assert_eq!(patterns, tags(&["email", "tier", "id"]));
+    }
+
+    #[test]
+    fn test_validate_tag() {
+        assert!(validate_tag("pii").is_ok());
+        assert!(validate_tag("sensitivity:high").is_ok());
+        assert!(validate_tag("!pii").is_ok());
+        assert!(validate_tag("").is_err());
+        assert!(validate_tag("a b").is_err());
+        assert!(validate_tag("key:").is_err());
+        assert!(validate_tag("!").is_err());
     }
 }
@@ -0,0 +1,653 @@
+//! What-if analysis of a policy change against past queries.
+//!
+//! [`simulate_datasource`] replays a sample of a datasource's query audit log:
+//! each distinct query is planned as the user who sent it, once under the
+//! current policies and once as if a [`PolicyChange`] had been saved, and the
+//! two `EXPLAIN (POLICY)` explanations are compared. Nothing is executed and
+//! policy subquery lookups are skipped, so no upstream data is read.
+//!
+//! | Change | Meaning |
+//! |---|---|
+//! | `newly_denied` / `no_longer_denied` | the query would be refused, or would no longer be |
+//! | `column_removed` / `column_restored` | a column the query reads would be hidden, or shown again |
+//! | `filter_added` / `filter_removed` / `filter_changed` | a row filter on a table would appear, go, or change |
+//! | `mask_added` / `mask_removed` / `mask_changed` | a column mask would appear, go, or change |
+//!
+//! Each query is replayed under the purpose its session declared. Queries by
+//! deleted users, by users who no longer have access to the datasource, and
+//! statements that are not reads of user tables are skipped. Impersonation
+//! previews are not replayed.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use datafusion::prelude::SessionContext;
+use datafusion::sql::sqlparser::ast::Statement;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::engine::EngineCache;
+use crate::entity::{data_source, policy, policy_assignment, query_audit_log};
+use crate::hooks::policy::{
+    PolicyExplanation, PolicyHook, is_system_only_statement, parse_single_statement,
+};
+use crate::subject::Subject;
+
+/// A policy as it would be saved, and optionally a new assignment of it. For
+/// an edit, `policy.id` is the edited policy's; for a new policy, a fresh id.
+#[derive(Debug, Clone)]
+pub struct PolicyChange {
+    pub policy: policy::Model,
+    pub assignment: Option<policy_assignment::Model>,
+}
+
+impl PolicyChange {
+    /// Add the proposed assignment to the assignments loaded for `datasource_id`.
+    pub(crate) fn apply_to_assignments(
+        &self,
+        assignments: &mut Vec<policy_assignment::Model>,
+        datasource_id: Uuid,
+    ) {
+        if let Some(a) = &self.assignment
+            && a.data_source_id == datasource_id
+        {
+            assignments.push(a.clone());
+        }
+    }
+
+    /// Replace the stored version of the policy among the enabled `policies`
+    /// loaded for `assigned` policy ids with the proposed one.
+    pub(crate) fn apply_to_policies(&self, policies: &mut Vec<policy::Model>, assigned: &[Uuid]) {
+        policies.retain(|p| p.id != self.policy.id);
+        if self.policy.is_enabled && assigned.contains(&self.policy.id) {
+            policies.push(self.policy.clone());
+        }
+    }
+}
+
+/// How one query's policies would change. See the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    NewlyDenied,
+    NoLongerDenied,
+    ColumnRemoved,
+    ColumnRestored,
+    FilterAdded,
+    FilterRemoved,
+    FilterChanged,
+    MaskAdded,
+    MaskRemoved,
+    MaskChanged,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryChange {
+    pub kind: ChangeKind,
+    /// `schema.table`, as the query names it.
+    pub table: Option<String>,
+    pub column: Option<String>,
+    /// The policy behind the change; for a query that would no longer plan,
+    /// the proposed policy.
+    pub policy: String,
+    /// The filter or mask expression, or why the query would be refused.
+    pub detail: Option<String>,
+    /// The expression it replaces, for `filter_changed` and `mask_changed`.
+    pub previous: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryImpact {
+    /// The replayed query audit entry (the user's most recent run of the query).
+    pub audit_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub query: String,
+    pub queried_at: NaiveDateTime,
+    pub changes: Vec<QueryChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserImpact {
+    pub user_id: Uuid,
+    pub username: String,
+    pub replayed: usize,
+    /// Replayed queries with at least one change.
+    pub changed: usize,
+    /// Changes by kind, over the user's queries.
+    pub changes: BTreeMap<ChangeKind, usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PolicyImpact {
+    pub policy: String,
+    pub queries: usize,
+    pub users: usize,
+    pub changes: BTreeMap<ChangeKind, usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    /// Query audit entries read.
+    pub sampled: usize,
+    /// Distinct (user, query) pairs planned under both policy sets.
+    pub replayed: usize,
+    /// Distinct pairs that could not be replayed (see the module docs).
+    pub skipped: usize,
+    /// Replayed pairs with at least one change.
+    pub changed: usize,
+    pub by_user: Vec<UserImpact>,
+    pub by_policy: Vec<PolicyImpact>,
+    /// The changed queries, most recent first.
+    pub queries: Vec<QueryImpact>,
+}
+
+/// A user whose queries can be replayed, with their catalog before and after the change.
+struct Replayer {
+    subject: Subject,
+    current: Arc<SessionContext>,
+    proposed: Arc<SessionContext>,
+}
+
+/// Replay the `sample_size` most recent queries on `datasource` (since `since`)
+/// under the current policies and under `change`. See the module docs.
+pub async fn simulate_datasource(
+    db: &DatabaseConnection,
+    engine_cache: &EngineCache,
+    policy_hook: &PolicyHook,
+    datasource: &data_source::Model,
+    change: &PolicyChange,
+    sample_size: u64,
+    since: Option<NaiveDateTime>,
+) -> Result<SimulationReport, Box<dyn std::error::Error + Send + Sync>> {
+    let mut query = query_audit_log::Entity::find()
+        .filter(query_audit_log::Column::DataSourceId.eq(datasource.id))
+        .filter(query_audit_log::Column::Status.is_in(["success", "denied"]))
+        .filter(query_audit_log::Column::ImpersonatedBy.is_null());
+    if let Some(since) = since {
+        query = query.filter(query_audit_log::Column::CreatedAt.gte(since));
+    }
+    let entries = query
+        .order_by_desc(query_audit_log::Column::CreatedAt)
+        .limit(sample_size)
+        .all(db)
+        .await?;
+
+    let mut seen: HashSet<(Uuid, Option<&str>, &str)> = HashSet::new();
+    let mut replayers: HashMap<(Uuid, Option<String>), Option<Replayer>> = HashMap::new();
+    let mut replayed: Vec<(Uuid, String, usize)> = Vec::new();
+    let mut skipped = 0;
+    let mut queries = Vec::new();
+
+    for entry in &entries {
+        let purpose = entry.purpose.as_deref();
+        if !seen.insert((entry.user_id, purpose, entry.original_query.as_str())) {
+            continue;
+        }
+        let Some(statement) = replayable(&entry.original_query) else {
+            skipped += 1;
+            continue;
+        };
+        let key = (entry.user_id, entry.purpose.clone());
+        if let Entry::Vacant(slot) = replayers.entry(key.clone()) {
+            slot.insert(
+                replayer(db, engine_cache, entry.user_id, purpose, datasource, change).await?,
+            );
+        }
+        let Some(Some(r)) = replayers.get(&key) else {
+            skipped += 1;
+            continue;
+        };
+
+        let current = policy_hook
+            .simulate(&r.subject, &datasource.name, &statement, &r.current, None)
+            .await?;
+        let proposed = policy_hook
+            .simulate(
+                &r.subject,
+                &datasource.name,
+                &statement,
+                &r.proposed,
+                Some(change),
+            )
+            .await?;
+        let changes = diff_explanations(&current, &proposed, &change.policy.name);
+
+        match replayed.iter_mut().find(|(id, ..)| *id == entry.user_id) {
+            Some((.., n)) => *n += 1,
+            None => replayed.push((entry.user_id, r.subject.username.clone(), 1)),
+        }
+        if !changes.is_empty() {
+            queries.push(QueryImpact {
+                audit_id: entry.id,
+                user_id: entry.user_id,
+                username: r.subject.username.clone(),
+                query: entry.original_query.clone(),
+                queried_at: entry.created_at,
+                changes,
+            });
+        }
+    }
+
+    let by_user = replayed
+        .into_iter()
+        .map(|(user_id, username, replayed)| {
+            let mine: Vec<&QueryImpact> = queries.iter().filter(|q| q.user_id == user_id).collect();
+            UserImpact {
+                user_id,
+                username,
+                replayed,
+                changed: mine.len(),
+                changes: count_kinds(mine.iter().flat_map(|q| &q.changes)),
+            }
+        })
+        .collect();
+
+    let mut policy_names: BTreeSet<&str> = BTreeSet::new();
+    for q in &queries {
+        policy_names.extend(q.changes.iter().map(|c| c.policy.as_str()));
+    }
+    let by_policy = policy_names
+        .into_iter()
+        .map(|name| {
+            let changes: Vec<(&QueryImpact, &QueryChange)> = queries
+                .iter()
+                .flat_map(|q| q.changes.iter().map(move |c| (q, c)))
+                .filter(|(_, c)| c.policy == name)
+                .collect();
+            let audit_ids: HashSet<Uuid> = changes.iter().map(|(q, _)| q.audit_id).collect();
+            let users: HashSet<Uuid> = changes.iter().map(|(q, _)| q.user_id).collect();
+            PolicyImpact {
+                policy: name.to_string(),
+                queries: audit_ids.len(),
+                users: users.len(),
+                changes: count_kinds(changes.iter().map(|(_, c)| *c)),
+            }
+        })
+        .collect();
+
+    Ok(SimulationReport {
+        sampled: entries.len(),
+        replayed: seen.len() - skipped,
+        skipped,
+        changed: queries.len(),
+        by_user,
+        by_policy,
+        queries,
+    })
+}
+
+/// The statement an audited query can be replayed as: a single read of user tables.
+fn replayable(sql: &str) -> Option<Statement> {
+    parse_single_statement(sql)
+        .ok()
+        .filter(|s| matches!(s, Statement::Query(_)) && !is_system_only_statement(s))
+}
+
+/// The user `user_id`, in a session that declared `purpose`, with their catalog
+/// before and after `change`, or `None` when the user was deleted or has no
+/// access to `datasource`.
+async fn replayer(
+    db: &DatabaseConnection,
+    engine_cache: &EngineCache,
+    user_id: Uuid,
+    purpose: Option<&str>,
+    datasource: &data_source::Model,
+    change: &PolicyChange,
+) -> Result<Option<Replayer>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(subject) = Subject::load(db, user_id).await? else {
+        return Ok(None);
+    };
+    let subject = subject.with_purpose(purpose.map(str::to_string));
+    if !subject.has_datasource_access(db, datasource.id).await? {
+        return Ok(None);
+    }
+    let current = engine_cache
+        .build_subject_context(&subject, &datasource.name, None)
+        .await?;
+    let proposed = engine_cache
+        .build_subject_context(&subject, &datasource.name, Some(change))
+        .await?;
+    Ok(Some(Replayer {
+        subject,
+        current,
+        proposed,
+    }))
+}
+
+fn count_kinds<'a>(changes: impl Iterator<Item = &'a QueryChange>) -> BTreeMap<ChangeKind, usize> {
+    let mut counts = BTreeMap::new();
+    for c in changes {
+        *counts.entry(c.kind).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Identifies an effect across the two explanations: (table, column or policy).
+type EffectKey = (Option<String>, Option<String>);
+
+fn by_column(r: &PolicyExplanation) -> EffectKey {
+    (r.table.clone(), r.column.clone())
+}
+
+fn by_policy(r: &PolicyExplanation) -> EffectKey {
+    (r.table.clone(), r.policy.clone())
+}
+
+/// The rows with one of `effects`, by `key`.
+fn keyed<'a>(
+    rows: &'a [PolicyExplanation],
+    effects: &[&str],
+    key: fn(&PolicyExplanation) -> EffectKey,
+) -> BTreeMap<EffectKey, &'a PolicyExplanation> {
+    rows.iter()
+        .filter(|r| effects.contains(&r.effect))
+        .map(|r| (key(r), r))
+        .collect()
+}
+
+fn denials(rows: &[PolicyExplanation]) -> Vec<&PolicyExplanation> {
+    rows.iter()
+        .filter(|r| matches!(r.effect, "denied" | "rejected"))
+        .collect()
+}
+
+/// How the policies of one query differ between its `current` and `proposed`
+/// explanations. A query refused under either is reported only by its denial.
+pub(crate) fn diff_explanations(
+    current: &[PolicyExplanation],
+    proposed: &[PolicyExplanation],
+    proposed_policy: &str,
+) -> Vec<QueryChange> {
+    let change = |kind, r: &PolicyExplanation, previous: Option<&PolicyExplanation>| QueryChange {
+        kind,
+        table: r.table.clone(),
+        column: r.column.clone(),
+        policy: r
+            .policy
+            .clone()
+            .unwrap_or_else(|| proposed_policy.to_string()),
+        detail: r.detail.clone(),
+        previous: previous.and_then(|p| p.detail.clone()),
+    };
+
+    match (denials(current).as_slice(), denials(proposed).as_slice()) {
+        ([], []) => {}
+        ([], now) => {
+            return now
+                .iter()
+                .map(|r| change(ChangeKind::NewlyDenied, r, None))
+                .collect();
+        }
+        (was, []) => {
+            return was
+                .iter()
+                .map(|r| change(ChangeKind::NoLongerDenied, r, None))
+                .collect();
+        }
+        _ => return vec![],
+    }
+
+    let mut changes = Vec::new();
+
+    // Removed columns.
+    let (before, after) = (
+        keyed(current, &["removed"], by_column),
+        keyed(proposed, &["removed"], by_column),
+    );
+    for (key, r) in &after {
+        if !before.contains_key(key) {
+            changes.push(change(ChangeKind::ColumnRemoved, r, None));
+        }
+    }
+    for (key, r) in &before {
+        if !after.contains_key(key) {
+            changes.push(change(ChangeKind::ColumnRestored, r, None));
+        }
+    }
+
+    // Row filters, one per (table, policy).
+    let (before, after) = (
+        keyed(current, &["filtered", "no_rows"], by_policy),
+        keyed(proposed, &["filtered", "no_rows"], by_policy),
+    );
+    for (key, r) in &after {
+        match before.get(key) {
+            None => changes.push(change(ChangeKind::FilterAdded, r, None)),
+            Some(old) if old.detail != r.detail || old.effect != r.effect => {
+                changes.push(change(ChangeKind::FilterChanged, r, Some(old)));
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, r) in &before {
+        if !after.contains_key(key) {
+            changes.push(change(ChangeKind::FilterRemoved, r, None));
+        }
+    }
+
+    // Masks, one per (table, column).
+    let (before, after) = (
+        keyed(current, &["masked"], by_column),
+        keyed(proposed, &["masked"], by_column),
+    );
+    for (key, r) in &after {
+        match before.get(key) {
+            None => changes.push(change(ChangeKind::MaskAdded, r, None)),
+            Some(old) if old.detail != r.detail || old.policy != r.policy => {
+                changes.push(change(ChangeKind::MaskChanged, r, Some(old)));
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, r) in &before {
+        if !after.contains_key(key) {
+            changes.push(change(ChangeKind::MaskRemoved, r, None));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn row(
+        table: &str,
+        column: Option<&str>,
+        policy: Option<&str>,
+        effect: &'static str,
+        detail: Option<&str>,
+    ) -> PolicyExplanation {
+        PolicyExplanation {
+            table: Some(table.to_string()),
+            column: column.map(str::to_string),
+            policy: policy.map(str::to_string),
+            policy_type: None,
+            effect,
+            detail: detail.map(str::to_string),
+        }
+    }
+
+    fn kinds(changes: &[QueryChange]) -> Vec<ChangeKind> {
+        changes.iter().map(|c| c.kind).collect()
+    }
+
+    fn policy(id: Uuid, is_enabled: bool) -> policy::Model {
+        let now = Utc::now().naive_utc();
+        policy::Model {
+            id,
+            name: "p".to_string(),
+            description: None,
+            policy_type: "row_filter".to_string(),
+            targets: "[]".to_string(),
+            definition: None,
+            is_enabled,
+            action_status: "enforce".to_string(),
+            version: 1,
+            decision_function_id: None,
+            template_id: None,
+            purpose: None,
+            created_by: Uuid::nil(),
+            updated_by: Uuid::nil(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn identical_explanations_have_no_changes() {
+        let rows = vec![
+            row(
+                "s.t",
+                None,
+                Some("tenant"),
+                "filtered",
+                Some("tenant = 'a'"),
+            ),
+            row("s.t", Some("ssn"), Some("mask"), "masked", Some("'***'")),
+        ];
+        assert!(diff_explanations(&rows, &rows, "proposed").is_empty());
+    }
+
+    #[test]
+    fn new_denial_is_reported_alone() {
+        let current = vec![row("s.t", None, Some("tenant"), "filtered", Some("x"))];
+        let proposed = vec![row("s.t", Some("ssn"), Some("no-ssn"), "denied", None)];
+        let changes = diff_explanations(&current, &proposed, "no-ssn");
+        assert_eq!(kinds(&changes), vec![ChangeKind::NewlyDenied]);
+        assert_eq!(changes[0].column.as_deref(), Some("ssn"));
+        assert_eq!(changes[0].policy, "no-ssn");
+    }
+
+    #[test]
+    fn rejection_without_policy_is_attributed_to_proposed_policy() {
+        let proposed = vec![PolicyExplanation {
+            table: None,
+            ..row("", None, None, "rejected", Some("column ssn not found"))
+        }];
+        let changes = diff_explanations(&[], &proposed, "hide-ssn");
+        assert_eq!(kinds(&changes), vec![ChangeKind::NewlyDenied]);
+        assert_eq!(changes[0].policy, "hide-ssn");
+        assert_eq!(changes[0].detail.as_deref(), Some("column ssn not found"));
+
+        let changes = diff_explanations(&proposed, &[], "hide-ssn");
+        assert_eq!(kinds(&changes), vec![ChangeKind::NoLongerDenied]);
+    }
+
+    #[test]
+    fn columns_filters_and_masks_are_compared() {
+        let current = vec![
+            row("s.t", Some("email"), Some("hide"), "removed", None),
+            row(
+                "s.t",
+                None,
+                Some("tenant"),
+                "filtered",
+                Some("tenant = 'a'"),
+            ),
+            row(
+                "s.t",
+                None,
+                Some("region"),
+                "filtered",
+                Some("region = 'eu'"),
+            ),
+            row("s.t", Some("ssn"), Some("mask"), "masked", Some("'***'")),
+            row("s.t", Some("phone"), Some("mask"), "masked", Some("'***'")),
+        ];
+        let proposed = vec![
+            row("s.t", Some("name"), Some("hide"), "removed", None),
+            row(
+                "s.t",
+                None,
+                Some("tenant"),
+                "filtered",
+                Some("tenant = 'b'"),
+            ),
+            row("s.t", None, Some("active"), "filtered", Some("active")),
+            row(
+                "s.t",
+                Some("ssn"),
+                Some("mask"),
+                "masked",
+                Some("right(ssn, 4)"),
+            ),
+            row("s.t", Some("card"), Some("mask"), "masked", Some("'***'")),
+        ];
+        let changes = diff_explanations(&current, &proposed, "proposed");
+        assert_eq!(
+            kinds(&changes),
+            vec![
+                ChangeKind::ColumnRemoved,
+                ChangeKind::ColumnRestored,
+                ChangeKind::FilterAdded,
+                ChangeKind::FilterChanged,
+                ChangeKind::FilterRemoved,
+                ChangeKind::MaskAdded,
+                ChangeKind::MaskChanged,
+                ChangeKind::MaskRemoved,
+            ]
+        );
+        let filter = &changes[3];
+        assert_eq!(filter.detail.as_deref(), Some("tenant = 'b'"));
+        assert_eq!(filter.previous.as_deref(), Some("tenant = 'a'"));
+        let mask = &changes[6];
+        assert_eq!(mask.column.as_deref(), Some("ssn"));
+        assert_eq!(mask.previous.as_deref(), Some("'***'"));
+    }
+
+    #[test]
+    fn filter_emptied_by_change_is_a_filter_change() {
+        let current = vec![row("s.t", None, Some("tenant"), "filtered", Some("x"))];
+        let proposed = vec![row("s.t", None, Some("tenant"), "no_rows", Some("x"))];
+        let changes = diff_explanations(&current, &proposed, "tenant");
+        assert_eq!(kinds(&changes), vec![ChangeKind::FilterChanged]);
+    }
+
+    #[test]
+    fn change_replaces_stored_policy_only_when_assigned_and_enabled() {
+        let id = Uuid::now_v7();
+        let other = Uuid::now_v7();
+        let mut proposed = policy(id, true);
+        proposed.definition = Some("{}".to_string());
+        let change = PolicyChange {
+            policy: proposed,
+            assignment: None,
+        };
+
+        let mut policies = vec![policy(id, true), policy(other, true)];
+        change.apply_to_policies(&mut policies, &[id, other]);
+        assert_eq!(policies.len(), 2);
+        assert!(
+            policies
+                .iter()
+                .any(|p| p.id == id && p.definition.is_some())
+        );
+
+        let mut policies = vec![policy(other, true)];
+        change.apply_to_policies(&mut policies, &[other]);
+        assert_eq!(policies.iter().map(|p| p.id).collect::<Vec<_>>(), [other]);
+
+        let disabled = PolicyChange {
+            policy: policy(id, false),
+            assignment: None,
+        };
+        let mut policies = vec![policy(id, true)];
+        disabled.apply_to_policies(&mut policies, &[id]);
+        assert!(policies.is_empty());
+    }
+
+    #[test]
+    fn only_reads_of_user_tables_are_replayed() {
+        assert!(replayable("SELECT id FROM s.t").is_some());
+        assert!(replayable("DELETE FROM s.t").is_none());
+        assert!(replayable("SELECT 1; SELECT 2").is_none());
+        assert!(replayable("not sql").is_none());
+    }
+}
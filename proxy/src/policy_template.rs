@@ -0,0 +1,383 @@
+//! Reusable policy templates: named, versioned filter and mask expressions.
+//!
+//! A template expression may use `{param.NAME}` placeholders for its typed
+//! parameters and, for `column_mask` templates, `{col}` for the masked column.
+//! A policy that references a template (`policy.template_id`) stores only the
+//! parameter values in its definition: `{"params": {"visible": 4}}`.
+//!
+//! Parameters are bound when the policy is loaded ([`resolve_definition`]) into the
+//! same definition shape an inline policy has, so the rest of the pipeline is
+//! unchanged. `{col}` survives that step and is bound per column by `parse_mask_expr`.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::entity::policy_template;
+use crate::policy_match::PolicyType;
+
+/// Placeholder for the masked column in a `column_mask` template (or inline mask expression).
+pub const COLUMN_PLACEHOLDER: &str = "{col}";
+
+/// Type of a template parameter; values are type-checked and rendered as SQL literals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateParamType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    /// A column name, rendered as a quoted identifier.
+    Column,
+}
+
+/// A declared template parameter. Parameters without a `default` are required.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateParam {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub param_type: TemplateParamType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Parse the JSON array stored in `policy_template.parameters`. Invalid JSON = no parameters.
+pub fn parse_parameters(raw: &str) -> Vec<TemplateParam> {
+    serde_json::from_str(raw).unwrap_or_default()
+}
+
+/// Regex for `{param.NAME}` placeholders. Compiled once.
+fn param_regex() -> &'static regex::Regex {
+    use std::sync::OnceLock;
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    RE.get_or_init(|| regex::Regex::new(r"\{param\.(\w+)\}").unwrap())
+}
+
+/// Validate a template definition at save time.
+///
+/// Checks the template type, parameter names and defaults, that every
+/// `{param.X}` is declared, that `{col}` is only used by mask templates, and that
+/// the expression parses once bound with defaults (or placeholder values).
+pub fn validate_template(
+    template_type: PolicyType,
+    expression: &str,
+    params: &[TemplateParam],
+) -> Result<(), String> {
+    let is_mask = match template_type {
+        PolicyType::ColumnMask => true,
+        PolicyType::RowFilter => false,
+        other => {
+            return Err(format!(
+                "template_type must be 'column_mask' or 'row_filter', got '{other}'"
+            ));
+        }
+    };
+    if expression.trim().is_empty() {
+        return Err("expression must not be empty".to_string());
+    }
+    if !is_mask && expression.contains(COLUMN_PLACEHOLDER) {
+        return Err(format!(
+            "'{COLUMN_PLACEHOLDER}' is only available in column_mask templates"
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    for p in params {
+        let valid_name = p
+            .name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && p.name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(format!(
+                "Invalid parameter name '{}': use letters, digits and '_'",
+                p.name
+            ));
+        }
+        if !seen.insert(p.name.as_str()) {
+            return Err(format!("Duplicate parameter '{}'", p.name));
+        }
+        if let Some(default) = &p.default {
+            render_value(p, default)?;
+        }
+    }
+    for cap in param_regex().captures_iter(expression) {
+        if !seen.contains(&cap[1]) {
+            return Err(format!(
+                "Expression references undeclared parameter '{}'",
+                &cap[1]
+            ));
+        }
+    }
+
+    // Bind every parameter (default, else a value of its type) and parse the result.
+    let mut sample = Map::new();
+    for p in params {
+        let value = p.default.clone().unwrap_or_else(|| match p.param_type {
+            TemplateParamType::String => Value::from("x"),
+            TemplateParamType::Integer => Value::from(0),
+            TemplateParamType::Float => Value::from(0.5),
+            TemplateParamType::Boolean => Value::from(false),
+            TemplateParamType::Column => Value::from("dummy_param_col"),
+        });
+        sample.insert(p.name.clone(), value);
+    }
+    let bound = bind_params(expression, params, Some(&sample))?;
+    crate::hooks::policy::validate_expression(&bound, is_mask)
+}
+
+/// Bind parameter values into a template expression. `{col}` is left in place.
+///
+/// Missing values fall back to the parameter's default; a required parameter with
+/// no value, an unknown argument, or a value of the wrong type is an error.
+pub fn bind_params(
+    expression: &str,
+    params: &[TemplateParam],
+    args: Option<&Map<String, Value>>,
+) -> Result<String, String> {
+    if let Some(args) = args
+        && let Some(unknown) = args.keys().find(|k| !params.iter().any(|p| &p.name == *k))
+    {
+        return Err(format!("Unknown template parameter '{unknown}'"));
+    }
+
+    let mut rendered = std::collections::HashMap::new();
+    for p in params {
+        let value = args
+            .and_then(|a| a.get(&p.name))
+            .or(p.default.as_ref())
+            .ok_or_else(|| format!("Missing value for template parameter '{}'", p.name))?;
+        rendered.insert(p.name.as_str(), render_value(p, value)?);
+    }
+    // Single pass, so a bound value is never scanned for further placeholders.
+    let bound = param_regex().replace_all(expression, |cap: &regex::Captures| {
+        rendered
+            .get(&cap[1])
+            .cloned()
+            .unwrap_or_else(|| cap[0].to_string())
+    });
+    Ok(bound.into_owned())
+}
+
+/// Render a parameter value as a SQL literal (or quoted identifier for `column`).
+fn render_value(param: &TemplateParam, value: &Value) -> Result<String, String> {
+    let type_error = || {
+        format!(
+            "Template parameter '{}' must be of type {}, got {value}",
+            param.name,
+            serde_json::to_value(param.param_type)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default()
+        )
+    };
+    match param.param_type {
+        TemplateParamType::String => value
+            .as_str()
+            .map(|s| format!("'{}'", s.replace('\'', "''")))
+            .ok_or_else(type_error),
+        TemplateParamType::Integer => value.as_i64().map(|n| n.to_string()).ok_or_else(type_error),
+        TemplateParamType::Float => value
+            .as_f64()
+            .map(|n| format!("{n:?}"))
+            .ok_or_else(type_error),
+        TemplateParamType::Boolean => value
+            .as_bool()
+            .map(|b| if b { "TRUE" } else { "FALSE" }.to_string())
+            .ok_or_else(type_error),
+        TemplateParamType::Column => match value.as_str() {
+            Some(name) if !name.is_empty() => Ok(quote_identifier(name)),
+            _ => Err(type_error()),
+        },
+    }
+}
+
+/// Replace `{col}` with the quoted column name.
+pub fn bind_column(expression: &str, column: &str) -> String {
+    expression.replace(COLUMN_PLACEHOLDER, &quote_identifier(column))
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Definition key holding the expression for a template-capable policy type.
+pub fn expression_key(policy_type: PolicyType) -> Option<&'static str> {
+    match policy_type {
+        PolicyType::RowFilter => Some("filter_expression"),
+        PolicyType::ColumnMask => Some("mask_expression"),
+        _ => None,
+    }
+}
+
+/// Effective definition of a template-backed policy: the template expression with
+/// the policy's `params` bound, under `filter_expression` / `mask_expression`.
+pub fn resolve_definition(
+    policy_type: PolicyType,
+    template: &policy_template::Model,
+    definition: Option<&Value>,
+) -> Result<Value, String> {
+    let key = expression_key(policy_type)
+        .ok_or_else(|| format!("'{policy_type}' policies cannot use a template"))?;
+    if template.template_type != policy_type.as_str() {
+        return Err(format!(
+            "Template '{}' is a {} template and cannot back a {policy_type} policy",
+            template.name, template.template_type
+        ));
+    }
+    let args = match definition.and_then(|d| d.get("params")) {
+        None | Some(Value::Null) => None,
+        Some(Value::Object(args)) => Some(args),
+        Some(_) => return Err("'params' must be an object".to_string()),
+    };
+    let params = parse_parameters(&template.parameters);
+    let bound = bind_params(&template.expression, &params, args)?;
+    Ok(serde_json::json!({ key: bound }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(name: &str, param_type: TemplateParamType, default: Option<Value>) -> TemplateParam {
+        TemplateParam {
+            name: name.to_string(),
+            param_type,
+            default,
+            description: None,
+        }
+    }
+
+    fn template(
+        template_type: &str,
+        expression: &str,
+        params: &[TemplateParam],
+    ) -> policy_template::Model {
+        let now = chrono::Utc::now().naive_utc();
+        policy_template::Model {
+            id: uuid::Uuid::now_v7(),
+            name: "t".to_string(),
+            description: None,
+            template_type: template_type.to_string(),
+            expression: expression.to_string(),
+            parameters: serde_json::to_string(params).unwrap(),
+            version: 3,
+            created_by: uuid::Uuid::nil(),
+            updated_by: uuid::Uuid::nil(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_bind_params_renders_typed_literals() {
+        let params = [
+            param("prefix", TemplateParamType::String, None),
+            param("visible", TemplateParamType::Integer, Some(Value::from(4))),
+            param("ratio", TemplateParamType::Float, None),
+            param("flag", TemplateParamType::Boolean, None),
+            param("other", TemplateParamType::Column, None),
+        ];
+        let args = serde_json::json!({
+            "prefix": "it's",
+            "ratio": 1.0,
+            "flag": true,
+            "other": "Region",
+        });
+        let bound = bind_params(
+            "{param.prefix} || RIGHT({col}, {param.visible}) {param.ratio} {param.flag} {param.other}",
+            &params,
+            args.as_object(),
+        )
+        .unwrap();
+        assert_eq!(bound, "'it''s' || RIGHT({col}, 4) 1.0 TRUE \"Region\"");
+    }
+
+    #[test]
+    fn test_bind_params_errors() {
+        let params = [param("visible", TemplateParamType::Integer, None)];
+        let err = bind_params("{param.visible}", &params, None).unwrap_err();
+        assert!(err.contains("Missing value"), "{err}");
+
+        let args = serde_json::json!({"visible": "4"});
+        let err = bind_params("{param.visible}", &params, args.as_object()).unwrap_err();
+        assert!(err.contains("must be of type integer"), "{err}");
+
+        let args = serde_json::json!({"visible": 4, "extra": 1});
+        let err = bind_params("{param.visible}", &params, args.as_object()).unwrap_err();
+        assert!(err.contains("Unknown template parameter 'extra'"), "{err}");
+    }
+
+    #[test]
+    fn test_bind_column_quotes_identifier() {
+        assert_eq!(bind_column("RIGHT({col}, 4)", "ssn"), "RIGHT(\"ssn\", 4)");
+        assert_eq!(bind_column("{col}", "a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn test_validate_template() {
+        let visible = [param(
+            "visible",
+            TemplateParamType::Integer,
+            Some(Value::from(4)),
+        )];
+        assert!(
+            validate_template(
+                PolicyType::ColumnMask,
+                "'***-**-' || RIGHT({col}, {param.visible})",
+                &visible
+            )
+            .is_ok()
+        );
+        assert!(
+            validate_template(PolicyType::RowFilter, "{col} = 1", &[])
+                .unwrap_err()
+                .contains("only available in column_mask")
+        );
+        assert!(
+            validate_template(PolicyType::ColumnMask, "{param.missing}", &[])
+                .unwrap_err()
+                .contains("undeclared parameter 'missing'")
+        );
+        assert!(
+            validate_template(PolicyType::TableDeny, "1", &[])
+                .unwrap_err()
+                .contains("template_type")
+        );
+        let bad_default = [param(
+            "n",
+            TemplateParamType::Integer,
+            Some(Value::from("x")),
+        )];
+        assert!(validate_template(PolicyType::ColumnMask, "{param.n}", &bad_default).is_err());
+    }
+
+    #[test]
+    fn test_resolve_definition() {
+        let t = template(
+            "column_mask",
+            "RIGHT({col}, {param.visible})",
+            &[param(
+                "visible",
+                TemplateParamType::Integer,
+                Some(Value::from(4)),
+            )],
+        );
+        let def = serde_json::json!({"params": {"visible": 2}});
+        assert_eq!(
+            resolve_definition(PolicyType::ColumnMask, &t, Some(&def)).unwrap(),
+            serde_json::json!({"mask_expression": "RIGHT({col}, 2)"})
+        );
+        assert_eq!(
+            resolve_definition(PolicyType::ColumnMask, &t, None).unwrap(),
+            serde_json::json!({"mask_expression": "RIGHT({col}, 4)"})
+        );
+        assert!(resolve_definition(PolicyType::RowFilter, &t, None).is_err());
+    }
+}
@@ -0,0 +1,615 @@
+//! Purpose-based access: why a session is reading a datasource.
+//!
+//! A client declares a purpose, and optionally a justification, with the
+//! `purpose` / `justification` startup parameters or with `SET br.purpose` /
+//! `SET br.justification`. The purpose must be in the datasource's catalog
+//! (`data_source_purpose`); a catalog entry can require a justification and
+//! check it with a [`JustificationValidator`]. A declaration that fails is
+//! refused and leaves the session as it was.
+//!
+//! Policies and policy assignments with a `purpose` only apply to sessions that
+//! declared that purpose ([`in_scope`]), so a purpose unlocks — or adds — a
+//! lens of policies on top of the ones that always apply. Both values are
+//! recorded on every query audit row.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use datafusion::sql::sqlparser::ast::{Expr, Set, Statement, Value};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper_rustls::ConfigBuilderExt;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entity::{data_source, data_source_purpose};
+
+/// Connection metadata keys (and startup parameters) holding the declaration.
+pub const PURPOSE_PARAM: &str = "purpose";
+pub const JUSTIFICATION_PARAM: &str = "justification";
+
+/// Session settings that change the declaration.
+pub const PURPOSE_SETTING: &str = "br.purpose";
+pub const JUSTIFICATION_SETTING: &str = "br.justification";
+
+/// How long a webhook validator is waited for when it sets no `timeout_ms`.
+const DEFAULT_WEBHOOK_TIMEOUT_MS: u64 = 3000;
+
+/// Upper bound on a webhook validator's `timeout_ms`.
+pub const MAX_WEBHOOK_TIMEOUT_MS: u64 = 30_000;
+
+/// Longest accepted justification, in bytes.
+pub const MAX_JUSTIFICATION_LEN: usize = 1000;
+
+/// Whether something restricted to `restricted_to` applies to a session that
+/// declared `declared`. Unrestricted always applies.
+pub fn in_scope(restricted_to: Option<&str>, declared: Option<&str>) -> bool {
+    restricted_to.is_none_or(|r| Some(r) == declared)
+}
+
+/// Checks a justification. Stored as JSON on the catalog entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JustificationValidator {
+    /// The justification must match `pattern` (unanchored; use `^…$` to match all of it).
+    Regex { pattern: String },
+    /// The declaration is POSTed to `url` as JSON; it is accepted when the
+    /// endpoint answers 2xx with `{"valid": true}`. Any other answer, an error
+    /// or a timeout rejects it.
+    Webhook {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_ms: Option<u64>,
+    },
+}
+
+/// What a webhook validator answers.
+#[derive(Debug, Deserialize)]
+struct WebhookVerdict {
+    valid: bool,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+impl JustificationValidator {
+    /// Check the validator itself when it is saved.
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Self::Regex { pattern } => regex::Regex::new(pattern)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid justification pattern: {e}")),
+            Self::Webhook { url, timeout_ms } => {
+                let uri: hyper::Uri = url
+                    .parse()
+                    .map_err(|e| format!("Invalid webhook url: {e}"))?;
+                if !matches!(uri.scheme_str(), Some("http" | "https")) || uri.host().is_none() {
+                    return Err("Webhook url must be an absolute http(s) URL".to_string());
+                }
+                if timeout_ms.is_some_and(|t| t == 0 || t > MAX_WEBHOOK_TIMEOUT_MS) {
+                    return Err(format!(
+                        "timeout_ms must be between 1 and {MAX_WEBHOOK_TIMEOUT_MS}"
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Accept or reject `declaration`'s justification, with the reason.
+    pub async fn validate(&self, declaration: &Declaration<'_>) -> Result<(), String> {
+        let justification = declaration.justification.unwrap_or_default();
+        match self {
+            Self::Regex { pattern } => {
+                let re = regex::Regex::new(pattern)
+                    .map_err(|e| format!("invalid justification pattern: {e}"))?;
+                if re.is_match(justification) {
+                    Ok(())
+                } else {
+                    Err(format!("justification must match {pattern}"))
+                }
+            }
+            Self::Webhook { url, timeout_ms } => {
+                let timeout =
+                    Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_WEBHOOK_TIMEOUT_MS));
+                let verdict = call_webhook(url, declaration, timeout)
+                    .await
+                    .map_err(|e| format!("justification could not be validated: {e}"))?;
+                if verdict.valid {
+                    Ok(())
+                } else {
+                    Err(verdict
+                        .reason
+                        .unwrap_or_else(|| "justification rejected".to_string()))
+                }
+            }
+        }
+    }
+}
+
+async fn call_webhook(
+    url: &str,
+    declaration: &Declaration<'_>,
+    timeout: Duration,
+) -> Result<WebhookVerdict, String> {
+    let body = serde_json::to_vec(declaration).map_err(|e| e.to_string())?;
+    let request = hyper::Request::post(url)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .map_err(|e| e.to_string())?;
+
+    let tls = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| e.to_string())?
+    .with_webpki_roots()
+    .with_no_client_auth();
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(tls)
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client = Client::builder(TokioExecutor::new()).build(connector);
+
+    let exchange = async {
+        let response = client.request(request).await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        let bytes = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| e.to_string())?
+            .to_bytes();
+        Ok::<_, String>((status, bytes))
+    };
+    let (status, bytes) = tokio::time::timeout(timeout, exchange)
+        .await
+        .map_err(|_| format!("no answer within {} ms", timeout.as_millis()))??;
+    if !status.is_success() {
+        return Err(format!("webhook answered {status}"));
+    }
+    serde_json::from_slice(&bytes).map_err(|e| format!("invalid webhook answer: {e}"))
+}
+
+/// A purpose declared by a user on a datasource. Also the webhook payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct Declaration<'a> {
+    pub user_id: Uuid,
+    pub username: &'a str,
+    pub datasource: &'a str,
+    pub purpose: &'a str,
+    pub justification: Option<&'a str>,
+}
+
+#[derive(Debug)]
+pub enum DeclarationError {
+    /// The declaration is not acceptable; the message is for the client.
+    Rejected(String),
+    Internal(String),
+}
+
+impl std::fmt::Display for DeclarationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rejected(msg) | Self::Internal(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for DeclarationError {}
+
+/// Check `declaration` against the datasource's purpose catalog and the
+/// purpose's justification rules.
+pub async fn validate_declaration<C: ConnectionTrait>(
+    db: &C,
+    declaration: &Declaration<'_>,
+) -> Result<(), DeclarationError> {
+    let internal = |e: sea_orm::DbErr| DeclarationError::Internal(e.to_string());
+    let ds = data_source::Entity::find()
+        .filter(data_source::Column::Name.eq(declaration.datasource))
+        .one(db)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| {
+            DeclarationError::Rejected(format!(
+                "Data source '{}' not found",
+                declaration.datasource
+            ))
+        })?;
+    let entry = data_source_purpose::Entity::find()
+        .filter(data_source_purpose::Column::DataSourceId.eq(ds.id))
+        .filter(data_source_purpose::Column::Name.eq(declaration.purpose))
+        .one(db)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| {
+            DeclarationError::Rejected(format!(
+                "Purpose '{}' is not allowed on data source '{}'",
+                declaration.purpose, declaration.datasource
+            ))
+        })?;
+
+    let justification = declaration.justification.map(str::trim).unwrap_or("");
+    if justification.len() > MAX_JUSTIFICATION_LEN {
+        return Err(DeclarationError::Rejected(format!(
+            "Justification must be at most {MAX_JUSTIFICATION_LEN} bytes"
+        )));
+    }
+    let validator = entry
+        .justification_validator
+        .as_deref()
+        .map(serde_json::from_str::<JustificationValidator>)
+        .transpose()
+        .map_err(|e| DeclarationError::Internal(format!("Invalid justification validator: {e}")))?;
+    if (entry.require_justification || validator.is_some()) && justification.is_empty() {
+        return Err(DeclarationError::Rejected(format!(
+            "Purpose '{}' requires a justification",
+            entry.name
+        )));
+    }
+    if let Some(validator) = validator {
+        validator.validate(declaration).await.map_err(|reason| {
+            DeclarationError::Rejected(format!(
+                "Justification for purpose '{}' rejected: {reason}",
+                entry.name
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+/// A `SET` or `SHOW` of a purpose setting.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionSetting {
+    /// `SET br.purpose` / `SET br.justification`; `None` clears it
+    /// (`= ''` or `TO DEFAULT`). Carries the metadata key it writes.
+    Set(&'static str, Option<String>),
+    /// `SHOW br.purpose` / `SHOW br.justification`.
+    Show(&'static str),
+}
+
+/// The purpose setting `statement` sets or shows, if it is one.
+pub fn session_setting(statement: &Statement) -> Option<SessionSetting> {
+    match statement {
+        Statement::Set(Set::SingleAssignment {
+            variable, values, ..
+        }) => {
+            let key = setting_key(&variable.to_string())?;
+            let value = match values.as_slice() {
+                [Expr::Value(v)] => match &v.value {
+                    Value::SingleQuotedString(s) | Value::DoubleQuotedString(s) => s.clone(),
+                    other => other.to_string(),
+                },
+                [Expr::Identifier(ident)] if ident.value.eq_ignore_ascii_case("default") => {
+                    String::new()
+                }
+                [Expr::Identifier(ident)] => ident.value.clone(),
+                [other] => other.to_string(),
+                _ => return None,
+            };
+            let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+            Some(SessionSetting::Set(key, value))
+        }
+        Statement::ShowVariable { variable } => {
+            let name = variable
+                .iter()
+                .map(|i| i.value.as_str())
+                .collect::<Vec<_>>()
+                .join(".");
+            setting_key(&name).map(SessionSetting::Show)
+        }
+        _ => None,
+    }
+}
+
+fn setting_key(name: &str) -> Option<&'static str> {
+    if name.eq_ignore_ascii_case(PURPOSE_SETTING) {
+        Some(PURPOSE_PARAM)
+    } else if name.eq_ignore_ascii_case(JUSTIFICATION_SETTING) {
+        Some(JUSTIFICATION_PARAM)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use datafusion::sql::sqlparser::{dialect::PostgreSqlDialect, parser::Parser};
+    use migration::MigratorTrait as _;
+    use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, Set};
+
+    fn parse(sql: &str) -> Statement {
+        Parser::parse_sql(&PostgreSqlDialect {}, sql)
+            .unwrap()
+            .remove(0)
+    }
+
+    fn declaration(justification: Option<&'static str>) -> Declaration<'static> {
+        Declaration {
+            user_id: Uuid::nil(),
+            username: "alice",
+            datasource: "warehouse",
+            purpose: "fraud_investigation",
+            justification,
+        }
+    }
+
+    /// A stand-in justification webhook: accepts justifications that mention a
+    /// ticket, answers 500 to "crash", and never answers "hang".
+    async fn webhook() -> String {
+        use axum::{Json, Router, http::StatusCode, routing::post};
+        async fn verdict(
+            Json(body): Json<serde_json::Value>,
+        ) -> Result<Json<serde_json::Value>, StatusCode> {
+            match body["justification"].as_str().unwrap_or_default() {
+                "crash" => Err(StatusCode::INTERNAL_SERVER_ERROR),
+                "hang" => {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    Err(StatusCode::GATEWAY_TIMEOUT)
+                }
+                j if j.contains("TICKET-") => Ok(Json(serde_json::json!({"valid": true}))),
+                _ => Ok(Json(
+                    serde_json::json!({"valid": false, "reason": "no ticket referenced"}),
+                )),
+            }
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/check", post(verdict)))
+                .await
+                .unwrap();
+        });
+        format!("http://{addr}/check")
+    }
+
+    async fn catalog(
+        require_justification: bool,
+        validator: Option<&JustificationValidator>,
+    ) -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        migration::Migrator::up(&db, None).await.unwrap();
+        let now = Utc::now().naive_utc();
+        let ds_id = Uuid::now_v7();
+        data_source::ActiveModel {
+            id: Set(ds_id),
+            name: Set("warehouse".to_string()),
+            ds_type: Set("postgres".to_string()),
+            config: Set("{}".to_string()),
+            secure_config: Set(String::new()),
+            is_active: Set(true),
+            access_mode: Set("open".to_string()),
+            policy_combining: Set(None),
+            write_mode: Set("read_only".to_string()),
+            last_sync_at: Set(None),
+            last_sync_result: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        data_source_purpose::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            data_source_id: Set(ds_id),
+            name: Set("fraud_investigation".to_string()),
+            description: Set(None),
+            require_justification: Set(require_justification),
+            justification_validator: Set(validator.map(|v| serde_json::to_string(v).unwrap())),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn unrestricted_always_applies() {
+        assert!(in_scope(None, None));
+        assert!(in_scope(None, Some("support")));
+        assert!(in_scope(Some("support"), Some("support")));
+        assert!(!in_scope(Some("support"), None));
+        assert!(!in_scope(Some("support"), Some("fraud_investigation")));
+    }
+
+    #[test]
+    fn parses_purpose_settings() {
+        assert_eq!(
+            session_setting(&parse("SET br.purpose = 'fraud_investigation'")),
+            Some(SessionSetting::Set(
+                PURPOSE_PARAM,
+                Some("fraud_investigation".to_string())
+            ))
+        );
+        assert_eq!(
+            session_setting(&parse("SET BR.PURPOSE TO support")),
+            Some(SessionSetting::Set(
+                PURPOSE_PARAM,
+                Some("support".to_string())
+            ))
+        );
+        assert_eq!(
+            session_setting(&parse("SET br.justification = 'TICKET-42 chargeback'")),
+            Some(SessionSetting::Set(
+                JUSTIFICATION_PARAM,
+                Some("TICKET-42 chargeback".to_string())
+            ))
+        );
+        assert_eq!(
+            session_setting(&parse("SET br.purpose = ''")),
+            Some(SessionSetting::Set(PURPOSE_PARAM, None))
+        );
+        assert_eq!(
+            session_setting(&parse("SET br.purpose TO DEFAULT")),
+            Some(SessionSetting::Set(PURPOSE_PARAM, None))
+        );
+        assert_eq!(
+            session_setting(&parse("SHOW br.purpose")),
+            Some(SessionSetting::Show(PURPOSE_PARAM))
+        );
+        assert_eq!(session_setting(&parse("SET search_path = public")), None);
+        assert_eq!(session_setting(&parse("SHOW search_path")), None);
+        assert_eq!(session_setting(&parse("SELECT 1")), None);
+    }
+
+    #[test]
+    fn validators_are_checked_when_saved() {
+        let regex = |pattern: &str| JustificationValidator::Regex {
+            pattern: pattern.to_string(),
+        };
+        let webhook = |url: &str, timeout_ms| JustificationValidator::Webhook {
+            url: url.to_string(),
+            timeout_ms,
+        };
+        assert!(regex("^TICKET-[0-9]+").check().is_ok());
+        assert!(regex("TICKET-(").check().is_err());
+        assert!(
+            webhook("https://hooks.example.com/justify", None)
+                .check()
+                .is_ok()
+        );
+        assert!(
+            webhook("http://127.0.0.1:9000/justify", Some(500))
+                .check()
+                .is_ok()
+        );
+        assert!(webhook("/justify", None).check().is_err());
+        assert!(
+            webhook("ftp://hooks.example.com/justify", None)
+                .check()
+                .is_err()
+        );
+        assert!(
+            webhook("https://hooks.example.com/justify", Some(0))
+                .check()
+                .is_err()
+        );
+        assert!(
+            webhook(
+                "https://hooks.example.com/justify",
+                Some(MAX_WEBHOOK_TIMEOUT_MS + 1)
+            )
+            .check()
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn regex_validator_matches_the_justification() {
+        let validator = JustificationValidator::Regex {
+            pattern: "^TICKET-[0-9]+".to_string(),
+        };
+        assert!(
+            validator
+                .validate(&declaration(Some("TICKET-42 chargeback")))
+                .await
+                .is_ok()
+        );
+        let err = validator
+            .validate(&declaration(Some("just curious")))
+            .await
+            .unwrap_err();
+        assert!(err.contains("^TICKET-[0-9]+"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn webhook_validator_fails_closed() {
+        let url = webhook().await;
+        let validator = JustificationValidator::Webhook {
+            url: url.clone(),
+            timeout_ms: Some(300),
+        };
+        assert!(
+            validator
+                .validate(&declaration(Some("TICKET-42 chargeback")))
+                .await
+                .is_ok()
+        );
+        let err = validator
+            .validate(&declaration(Some("just curious")))
+            .await
+            .unwrap_err();
+        assert_eq!(err, "no ticket referenced");
+        let err = validator
+            .validate(&declaration(Some("crash")))
+            .await
+            .unwrap_err();
+        assert!(err.contains("500"), "{err}");
+        let err = validator
+            .validate(&declaration(Some("hang")))
+            .await
+            .unwrap_err();
+        assert!(err.contains("300 ms"), "{err}");
+
+        let unreachable = JustificationValidator::Webhook {
+            url: url.replace("/check", "/missing"),
+            timeout_ms: None,
+        };
+        assert!(
+            unreachable
+                .validate(&declaration(Some("TICKET-42")))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn declarations_are_checked_against_the_catalog() {
+        let db = catalog(true, None).await;
+        assert!(
+            validate_declaration(&db, &declaration(Some("TICKET-42")))
+                .await
+                .is_ok()
+        );
+        let err = validate_declaration(&db, &declaration(Some("   ")))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, DeclarationError::Rejected(ref m) if m.contains("requires a justification"))
+        );
+
+        let unknown = Declaration {
+            purpose: "marketing",
+            ..declaration(Some("TICKET-42"))
+        };
+        let err = validate_declaration(&db, &unknown).await.unwrap_err();
+        assert!(matches!(err, DeclarationError::Rejected(ref m) if m.contains("not allowed")));
+
+        let long = "x".repeat(MAX_JUSTIFICATION_LEN + 1);
+        let err = validate_declaration(
+            &db,
+            &Declaration {
+                justification: Some(&long),
+                ..declaration(None)
+            },
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, DeclarationError::Rejected(_)));
+    }
+
+    #[tokio::test]
+    async fn a_validator_implies_a_justification() {
+        let validator = JustificationValidator::Regex {
+            pattern: "TICKET-".to_string(),
+        };
+        let db = catalog(false, Some(&validator)).await;
+        assert!(validate_declaration(&db, &declaration(None)).await.is_err());
+        assert!(
+            validate_declaration(&db, &declaration(Some("see TICKET-7")))
+                .await
+                .is_ok()
+        );
+        let err = validate_declaration(&db, &declaration(Some("because")))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DeclarationError::Rejected(ref m) if m.contains("rejected")));
+    }
+}
@@ -0,0 +1,611 @@
+//! Query-shape rules for `query_guard` policies.
+//!
+//! A `query_guard` policy never changes what a query returns; it refuses
+//! queries on its target tables whose shape breaks one of its rules:
+//!
+//! | Rule | Checked against |
+//! |---|---|
+//! | `forbid_select_star` | the SQL statement — DataFusion expands `*` while planning |
+//! | `require_predicate_on` | `Filter` nodes above each scan of a target table |
+//! | `forbid_cross_join` | `Join` nodes with no condition linking their two sides |
+//! | `max_joins` | the number of `Join` nodes |
+//!
+//! Plan rules run on the plan as the user wrote it, before `apply_policies`
+//! rewrites it, so joins injected for row-filter anchors never count.
+
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+use datafusion::common::{Column, DFSchema, JoinType};
+use datafusion::logical_expr::utils::split_conjunction;
+use datafusion::logical_expr::{BinaryExpr, Expr, LogicalPlan, Operator};
+use datafusion::sql::sqlparser::ast::{
+    Ident, ObjectName, ObjectNamePart, Query, Select, SelectItem, SelectItemQualifiedWildcardKind,
+    SetExpr, Statement, TableFactor, TableWithJoins, Visit, Visitor,
+};
+
+use crate::hooks::policy::scan_policy_key;
+use crate::policy_match::QueryGuardDef;
+
+/// A broken rule: the definition field that names it and what was wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rule '{}': {}", self.rule, self.message)
+    }
+}
+
+/// One scan of a table in the plan.
+#[derive(Debug)]
+struct Scan {
+    key: (String, String),
+    columns: Vec<String>,
+    /// Columns of this scan that a predicate above it bounds.
+    filtered: HashSet<String>,
+}
+
+/// The shape of one query, extracted once and checked against every
+/// `query_guard` policy that matches it.
+#[derive(Debug)]
+pub struct QueryShape {
+    scans: Vec<Scan>,
+    /// Tables read through `*` or `t.*`.
+    wildcard_tables: HashSet<(String, String)>,
+    /// Tables under each join that has no join condition.
+    cross_joins: Vec<Vec<(String, String)>>,
+    join_count: usize,
+}
+
+impl QueryShape {
+    pub fn new(statement: &Statement, plan: &LogicalPlan, default_schema: &str) -> Self {
+        let mut scans = Vec::new();
+        collect_scans(plan, Vec::new(), default_schema, &mut scans);
+        let mut shape = Self {
+            scans,
+            wildcard_tables: wildcard_tables(statement, default_schema),
+            cross_joins: Vec::new(),
+            join_count: 0,
+        };
+        shape.collect_joins(plan, Vec::new(), default_schema);
+        shape
+    }
+
+    /// Tables the query scans, keyed like `collect_user_tables`.
+    pub fn tables(&self) -> impl Iterator<Item = &(String, String)> {
+        self.scans.iter().map(|s| &s.key)
+    }
+
+    /// The first rule of `def` the query breaks, considering only the tables
+    /// `is_target` accepts.
+    pub fn check(
+        &self,
+        def: &QueryGuardDef,
+        is_target: impl Fn(&(String, String)) -> bool,
+    ) -> Option<Violation> {
+        let targets: Vec<&Scan> = self.scans.iter().filter(|s| is_target(&s.key)).collect();
+        if targets.is_empty() {
+            return None;
+        }
+
+        if def.forbid_select_star {
+            let min_columns = def.select_star_min_columns.unwrap_or(0);
+            if let Some(scan) = targets
+                .iter()
+                .find(|s| self.wildcard_tables.contains(&s.key) && s.columns.len() >= min_columns)
+            {
+                return Some(Violation {
+                    rule: "forbid_select_star",
+                    message: format!(
+                        "SELECT * is not allowed on {}.{} ({} columns); list the columns you need",
+                        scan.key.0,
+                        scan.key.1,
+                        scan.columns.len()
+                    ),
+                });
+            }
+        }
+
+        for scan in &targets {
+            for required in &def.require_predicate_on {
+                let Some(column) = scan
+                    .columns
+                    .iter()
+                    .find(|c| c.eq_ignore_ascii_case(required))
+                else {
+                    continue;
+                };
+                if !scan.filtered.contains(column) {
+                    return Some(Violation {
+                        rule: "require_predicate_on",
+                        message: format!(
+                            "queries on {}.{} must filter on {column}",
+                            scan.key.0, scan.key.1
+                        ),
+                    });
+                }
+            }
+        }
+
+        if def.forbid_cross_join
+            && let Some(key) = self
+                .cross_joins
+                .iter()
+                .find_map(|tables| tables.iter().find(|k| is_target(k)))
+        {
+            return Some(Violation {
+                rule: "forbid_cross_join",
+                message: format!(
+                    "cross join involving {}.{} has no join condition",
+                    key.0, key.1
+                ),
+            });
+        }
+
+        if let Some(max) = def.max_joins
+            && self.join_count > max
+        {
+            return Some(Violation {
+                rule: "max_joins",
+                message: format!("query has {} joins; at most {max} allowed", self.join_count),
+            });
+        }
+
+        None
+    }
+
+    /// Count joins and record those without a condition linking their sides.
+    /// `conditions` are the conjuncts of the filters directly above `plan`, which
+    /// is where `FROM a, b WHERE a.id = b.id` puts its join condition.
+    fn collect_joins<'a>(
+        &mut self,
+        plan: &'a LogicalPlan,
+        mut conditions: Vec<&'a Expr>,
+        default_schema: &str,
+    ) {
+        match plan {
+            LogicalPlan::Filter(filter) => {
+                conditions.extend(split_conjunction(&filter.predicate));
+                self.collect_joins(&filter.input, conditions, default_schema);
+            }
+            LogicalPlan::Join(join) => {
+                self.join_count += 1;
+                if let Some(f) = &join.filter {
+                    conditions.extend(split_conjunction(f));
+                }
+                let (left, right) = (join.left.schema(), join.right.schema());
+                if join.on.is_empty() && !conditions.iter().any(|c| links(c, left, right)) {
+                    let mut tables = Vec::new();
+                    scan_keys(plan, default_schema, &mut tables);
+                    self.cross_joins.push(tables);
+                }
+                self.collect_joins(&join.left, conditions.clone(), default_schema);
+                self.collect_joins(&join.right, conditions, default_schema);
+            }
+            _ => {
+                for input in plan.inputs() {
+                    self.collect_joins(input, Vec::new(), default_schema);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `condition` references columns from both sides of a join.
+fn links(condition: &Expr, left: &DFSchema, right: &DFSchema) -> bool {
+    let refs = condition.column_refs();
+    refs.iter().any(|c| left.has_column(c)) && refs.iter().any(|c| right.has_column(c))
+}
+
+fn scan_keys(plan: &LogicalPlan, default_schema: &str, out: &mut Vec<(String, String)>) {
+    if let LogicalPlan::TableScan(scan) = plan {
+        out.push(scan_policy_key(scan, default_schema));
+        return;
+    }
+    for input in plan.inputs() {
+        scan_keys(input, default_schema, out);
+    }
+}
+
+/// Walk down to every `TableScan`, carrying the columns that filters above it
+/// bound. Columns are tracked by their qualified name and renamed through
+/// `SubqueryAlias` and plain-column projections; anything else drops them, so a
+/// predicate on a derived value never satisfies `require_predicate_on`.
+fn collect_scans(
+    plan: &LogicalPlan,
+    filtered: Vec<Column>,
+    default_schema: &str,
+    out: &mut Vec<Scan>,
+) {
+    match plan {
+        LogicalPlan::TableScan(scan) => {
+            out.push(Scan {
+                key: scan_policy_key(scan, default_schema),
+                columns: scan
+                    .source
+                    .schema()
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().clone())
+                    .collect(),
+                filtered: filtered
+                    .into_iter()
+                    .filter(|c| c.relation.as_ref() == Some(&scan.table_name))
+                    .map(|c| c.name)
+                    .collect(),
+            });
+        }
+        LogicalPlan::Filter(filter) => {
+            let mut next = filtered;
+            let schema = filter.input.schema();
+            for conjunct in split_conjunction(&filter.predicate) {
+                for column in bounded_columns(conjunct) {
+                    if let Ok((qualifier, field)) = schema.qualified_field_from_column(column) {
+                        next.push(Column::new(qualifier.cloned(), field.name()));
+                    }
+                }
+            }
+            collect_scans(&filter.input, next, default_schema, out);
+        }
+        // An inner join's condition bounds both sides; an outer join's does
+        // not restrict the rows of its preserved side.
+        LogicalPlan::Join(join) if join.join_type == JoinType::Inner => {
+            let mut next = filtered;
+            for conjunct in join.filter.iter().flat_map(split_conjunction) {
+                for column in bounded_columns(conjunct) {
+                    if let Ok((qualifier, field)) = join.schema.qualified_field_from_column(column)
+                    {
+                        next.push(Column::new(qualifier.cloned(), field.name()));
+                    }
+                }
+            }
+            collect_scans(&join.left, next.clone(), default_schema, out);
+            collect_scans(&join.right, next, default_schema, out);
+        }
+        LogicalPlan::SubqueryAlias(alias) => {
+            let schema = alias.input.schema();
+            let next = filtered
+                .iter()
+                .filter(|c| c.relation.as_ref() == Some(&alias.alias))
+                .filter_map(|c| {
+                    let (qualifier, field) =
+                        schema.qualified_field_with_unqualified_name(&c.name).ok()?;
+                    Some(Column::new(qualifier.cloned(), field.name()))
+                })
+                .collect();
+            collect_scans(&alias.input, next, default_schema, out);
+        }
+        LogicalPlan::Projection(projection) => {
+            let schema = projection.input.schema();
+            let next = filtered
+                .iter()
+                .filter_map(|c| {
+                    let i = projection.schema.index_of_column(c).ok()?;
+                    let Expr::Column(inner) = projection.expr[i].clone().unalias() else {
+                        return None;
+                    };
+                    let (qualifier, field) = schema.qualified_field_from_column(&inner).ok()?;
+                    Some(Column::new(qualifier.cloned(), field.name()))
+                })
+                .collect();
+            collect_scans(&projection.input, next, default_schema, out);
+        }
+        _ => {
+            for input in plan.inputs() {
+                collect_scans(input, filtered.clone(), default_schema, out);
+            }
+        }
+    }
+}
+
+/// Columns a predicate cannot be satisfied without: for `OR`, only columns both
+/// branches reference, so `event_date > x OR true` bounds nothing.
+fn bounded_columns(expr: &Expr) -> HashSet<&Column> {
+    match expr {
+        Expr::BinaryExpr(BinaryExpr {
+            left,
+            op: Operator::Or,
+            right,
+        }) => {
+            let right = bounded_columns(right);
+            bounded_columns(left)
+                .into_iter()
+                .filter(|c| right.contains(c))
+                .collect()
+        }
+        Expr::BinaryExpr(BinaryExpr {
+            left,
+            op: Operator::And,
+            right,
+        }) => {
+            let mut columns = bounded_columns(left);
+            columns.extend(bounded_columns(right));
+            columns
+        }
+        _ => expr.column_refs(),
+    }
+}
+
+// ---------- SELECT * detection ----------
+
+/// Tables a `*` or `t.*` projection in `statement` reads, as `(schema, table)`
+/// with bare names resolved against `default_schema`. CTE names are skipped;
+/// the tables inside the CTE are checked where the CTE selects from them.
+fn wildcard_tables(statement: &Statement, default_schema: &str) -> HashSet<(String, String)> {
+    let mut visitor = WildcardVisitor {
+        default_schema,
+        ctes: HashSet::new(),
+        tables: HashSet::new(),
+    };
+    let _ = statement.visit(&mut visitor);
+    visitor.tables
+}
+
+struct WildcardVisitor<'a> {
+    default_schema: &'a str,
+    ctes: HashSet<String>,
+    tables: HashSet<(String, String)>,
+}
+
+impl Visitor for WildcardVisitor<'_> {
+    type Break = ();
+
+    // Every nested query (CTEs, derived tables, subqueries) is visited on its
+    // own, so `set_expr` does not descend into them.
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        if let Some(with) = &query.with {
+            self.ctes
+                .extend(with.cte_tables.iter().map(|cte| normalize(&cte.alias.name)));
+        }
+        self.set_expr(&query.body);
+        ControlFlow::Continue(())
+    }
+}
+
+impl WildcardVisitor<'_> {
+    fn set_expr(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => self.select(select),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.set_expr(left);
+                self.set_expr(right);
+            }
+            _ => {}
+        }
+    }
+
+    fn select(&mut self, select: &Select) {
+        let mut relations = Vec::new();
+        for from in &select.from {
+            relations_of(from, &mut relations);
+        }
+        for item in &select.projection {
+            let matching: Vec<&(Option<String>, &ObjectName)> = match item {
+                SelectItem::Wildcard(_) => relations.iter().collect(),
+                SelectItem::QualifiedWildcard(
+                    SelectItemQualifiedWildcardKind::ObjectName(q),
+                    _,
+                ) => {
+                    let Some(qualifier) = name_parts(q).pop() else {
+                        continue;
+                    };
+                    relations
+                        .iter()
+                        .filter(|(alias, name)| match alias {
+                            Some(alias) => *alias == qualifier,
+                            None => name_parts(name).last() == Some(&qualifier),
+                        })
+                        .collect()
+                }
+                _ => continue,
+            };
+            for (_, name) in matching {
+                if let Some(key) = self.table_key(name) {
+                    self.tables.insert(key);
+                }
+            }
+        }
+    }
+
+    fn table_key(&self, name: &ObjectName) -> Option<(String, String)> {
+        let mut parts = name_parts(name);
+        let table = parts.pop()?;
+        match parts.pop() {
+            Some(schema) => Some((schema, table)),
+            None if self.ctes.contains(&table) => None,
+            None => Some((self.default_schema.to_string(), table)),
+        }
+    }
+}
+
+/// Named relations of a `FROM` item and its joins, with their aliases.
+fn relations_of<'a>(from: &'a TableWithJoins, out: &mut Vec<(Option<String>, &'a ObjectName)>) {
+    for factor in std::iter::once(&from.relation).chain(from.joins.iter().map(|j| &j.relation)) {
+        match factor {
+            TableFactor::Table { name, alias, .. } => {
+                out.push((alias.as_ref().map(|a| normalize(&a.name)), name));
+            }
+            TableFactor::NestedJoin {
+                table_with_joins, ..
+            } => relations_of(table_with_joins, out),
+            _ => {}
+        }
+    }
+}
+
+fn name_parts(name: &ObjectName) -> Vec<String> {
+    name.0
+        .iter()
+        .filter_map(|part| match part {
+            ObjectNamePart::Identifier(ident) => Some(normalize(ident)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Identifier as DataFusion resolves it: unquoted names are lower-cased.
+fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::datasource::MemTable;
+    use datafusion::prelude::SessionContext;
+    use datafusion::sql::sqlparser::dialect::PostgreSqlDialect;
+    use datafusion::sql::sqlparser::parser::Parser;
+    use std::sync::Arc;
+
+    async fn shape(sql: &str) -> QueryShape {
+        let ctx = SessionContext::new();
+        for (name, columns) in [
+            ("events", vec!["id", "event_date", "user_id", "payload"]),
+            ("users", vec!["id", "name"]),
+        ] {
+            let schema = Arc::new(Schema::new(
+                columns
+                    .into_iter()
+                    .map(|c| Field::new(c, DataType::Int64, true))
+                    .collect::<Vec<_>>(),
+            ));
+            let table = MemTable::try_new(schema, vec![vec![]]).unwrap();
+            ctx.register_table(name, Arc::new(table)).unwrap();
+        }
+        let statement = Parser::parse_sql(&PostgreSqlDialect {}, sql)
+            .unwrap()
+            .remove(0);
+        let plan = ctx.state().create_logical_plan(sql).await.unwrap();
+        QueryShape::new(&statement, &plan, "public")
+    }
+
+    fn events(key: &(String, String)) -> bool {
+        key.1 == "events"
+    }
+
+    fn rule(violation: Option<Violation>) -> Option<&'static str> {
+        violation.map(|v| v.rule)
+    }
+
+    #[tokio::test]
+    async fn select_star_respects_min_columns() {
+        let def = QueryGuardDef {
+            forbid_select_star: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            rule(shape("SELECT * FROM events").await.check(&def, events)),
+            Some("forbid_select_star")
+        );
+        assert_eq!(
+            rule(
+                shape("SELECT e.* FROM events e JOIN users u ON e.user_id = u.id")
+                    .await
+                    .check(&def, events)
+            ),
+            Some("forbid_select_star")
+        );
+        assert_eq!(
+            shape("SELECT u.* FROM events e JOIN users u ON e.user_id = u.id")
+                .await
+                .check(&def, events),
+            None
+        );
+        assert_eq!(
+            shape("SELECT id, payload FROM events")
+                .await
+                .check(&def, events),
+            None
+        );
+        let wide_only = QueryGuardDef {
+            select_star_min_columns: Some(10),
+            ..def
+        };
+        assert_eq!(
+            shape("SELECT * FROM events")
+                .await
+                .check(&wide_only, events),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn required_predicate_must_bound_the_target() {
+        let def = QueryGuardDef {
+            require_predicate_on: vec!["event_date".to_string()],
+            ..Default::default()
+        };
+        for ok in [
+            "SELECT id FROM events WHERE event_date > 5",
+            "SELECT id FROM events e WHERE e.event_date = 1 AND id > 2",
+            "SELECT x.id FROM (SELECT id, event_date AS d FROM events) x WHERE x.d = 1",
+            "SELECT u.name FROM events e JOIN users u ON e.user_id = u.id WHERE e.event_date > 1",
+            "SELECT u.name FROM events e JOIN users u ON e.user_id = u.id AND e.event_date > 1",
+        ] {
+            assert_eq!(shape(ok).await.check(&def, events), None, "{ok}");
+        }
+        for bad in [
+            "SELECT id FROM events",
+            "SELECT id FROM events WHERE id = 1",
+            "SELECT id FROM events WHERE event_date > 5 OR id = 1",
+            "SELECT u.name FROM events e LEFT JOIN users u ON e.user_id = u.id AND e.event_date > 1",
+            "SELECT x.id FROM (SELECT id, event_date + 1 AS d FROM events) x WHERE x.d = 1",
+        ] {
+            assert_eq!(
+                rule(shape(bad).await.check(&def, events)),
+                Some("require_predicate_on"),
+                "{bad}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn cross_joins_and_join_count() {
+        let def = QueryGuardDef {
+            forbid_cross_join: true,
+            ..Default::default()
+        };
+        for bad in [
+            "SELECT e.id FROM events e CROSS JOIN users u",
+            "SELECT e.id FROM events e, users u WHERE e.id > 1",
+        ] {
+            assert_eq!(
+                rule(shape(bad).await.check(&def, events)),
+                Some("forbid_cross_join"),
+                "{bad}"
+            );
+        }
+        for ok in [
+            "SELECT e.id FROM events e, users u WHERE e.user_id = u.id",
+            "SELECT e.id FROM events e JOIN users u ON e.user_id = u.id",
+        ] {
+            assert_eq!(shape(ok).await.check(&def, events), None, "{ok}");
+        }
+
+        let max = QueryGuardDef {
+            max_joins: Some(1),
+            ..Default::default()
+        };
+        let two = "SELECT e.id FROM events e JOIN users u ON e.user_id = u.id \
+                   JOIN users v ON v.id = u.id";
+        assert_eq!(
+            rule(shape(two).await.check(&max, events)),
+            Some("max_joins")
+        );
+        // Rules only apply to queries that read a target table.
+        assert_eq!(
+            shape("SELECT u.id FROM users u CROSS JOIN users v")
+                .await
+                .check(&def, events),
+            None
+        );
+    }
+}
@@ -0,0 +1,549 @@
+//! Result caps for `result_limit` policies, and the meter every governed
+//! query's result stream runs through.
+//!
+//! A query that reads a target table of one or more `result_limit` policies gets
+//! their combined [`ResultLimit`]: the smallest `max_rows` and `max_bytes` win,
+//! and `deny` wins over `truncate`. Enforcement happens in two places:
+//!
+//! - **Rows** — [`limit_plan`] puts a `Limit` on top of the plan, so the cap is
+//!   pushed down to the upstream database. Under `deny` the limit is one row
+//!   past the cap, so the meter can tell the result was too large.
+//! - **Bytes** — the meter measures the Arrow data size of each batch and cuts
+//!   the stream (or fails it) at the cap.
+//!
+//! Under `deny` the meter holds the result back until the inner stream ends,
+//! so a query that goes over a cap sends no rows at all. The caps bound what
+//! is held: at most `max_rows + 1` rows and `max_bytes` bytes.
+//!
+//! Per-window row quotas live in the `result_quota` table, one row per policy
+//! and user. The quota left at planning time lowers the query's row cap; the
+//! rows actually returned are added when the stream ends, so queries that run
+//! concurrently can overshoot a quota by at most their own row caps.
+//!
+//! [`meter`] counts the rows and bytes sent to the client for every governed
+//! query, limited or not, and reports them when the stream is dropped — that is
+//! what `query_audit_log.rows_returned` / `bytes_returned` record.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::common::{DataFusionError, Result};
+use datafusion::execution::{RecordBatchStream, SendableRecordBatchStream};
+use datafusion::logical_expr::{LogicalPlan, LogicalPlanBuilder};
+use futures::{Stream, StreamExt};
+use sea_orm::sea_query::{Expr as DbExpr, OnConflict};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::entity::result_quota;
+use crate::policy_match::{LimitAction, ResultLimitDef};
+
+/// A single cap and the policy that set it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cap {
+    pub limit: u64,
+    pub policy_name: String,
+}
+
+/// A per-window row quota from one `result_limit` policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowWindow {
+    pub policy_id: Uuid,
+    pub policy_name: String,
+    pub rows: u64,
+    pub secs: u64,
+}
+
+/// The combined caps of every `result_limit` policy matching a query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResultLimit {
+    pub max_rows: Option<Cap>,
+    pub max_bytes: Option<Cap>,
+    pub windows: Vec<RowWindow>,
+    pub on_exceed: LimitAction,
+}
+
+impl ResultLimit {
+    /// Fold in one policy's caps: the smallest cap wins per dimension, and
+    /// `deny` wins over `truncate`.
+    pub fn add(&mut self, def: &ResultLimitDef, policy_id: Uuid, policy_name: &str) {
+        if let Some(rows) = def.max_rows {
+            lower(&mut self.max_rows, rows, policy_name);
+        }
+        if let Some(bytes) = def.max_bytes {
+            lower(&mut self.max_bytes, bytes, policy_name);
+        }
+        if let (Some(rows), Some(secs)) = (def.window_rows, def.window_secs) {
+            self.windows.push(RowWindow {
+                policy_id,
+                policy_name: policy_name.to_string(),
+                rows,
+                secs,
+            });
+        }
+        if def.on_exceed == LimitAction::Deny {
+            self.on_exceed = LimitAction::Deny;
+        }
+    }
+
+    /// Lower the row cap to what is left of a window's quota.
+    pub fn cap_rows(&mut self, remaining: u64, policy_name: &str) {
+        lower(&mut self.max_rows, remaining, policy_name);
+    }
+}
+
+fn lower(cap: &mut Option<Cap>, limit: u64, policy_name: &str) {
+    if cap.as_ref().is_none_or(|c| limit < c.limit) {
+        *cap = Some(Cap {
+            limit,
+            policy_name: policy_name.to_string(),
+        });
+    }
+}
+
+/// Put the row cap on top of `plan` as a `Limit`. Under `deny` the limit is one
+/// row past the cap so the meter can detect an oversized result.
+pub fn limit_plan(plan: LogicalPlan, limit: &ResultLimit) -> Result<LogicalPlan> {
+    let Some(cap) = &limit.max_rows else {
+        return Ok(plan);
+    };
+    let fetch = match limit.on_exceed {
+        LimitAction::Truncate => cap.limit,
+        LimitAction::Deny => cap.limit.saturating_add(1),
+    };
+    LogicalPlanBuilder::from(plan)
+        .limit(0, Some(fetch as usize))?
+        .build()
+}
+
+// ---------- stream meter ----------
+
+/// Rows and bytes a result stream delivered, reported when it is dropped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResultTotals {
+    pub rows: u64,
+    pub bytes: u64,
+    /// Set when a `deny` cap failed the stream.
+    pub exceeded: Option<String>,
+}
+
+/// Error that fails a result stream when a `deny` cap is exceeded. Front-ends
+/// report it with SQLSTATE 54000.
+#[derive(Debug)]
+pub struct LimitExceeded(pub String);
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// Wrap `stream` so its rows and bytes are counted (and capped by `limit`).
+/// The receiver resolves with the totals once the stream is dropped.
+pub fn meter(
+    stream: SendableRecordBatchStream,
+    limit: Option<&ResultLimit>,
+) -> (SendableRecordBatchStream, oneshot::Receiver<ResultTotals>) {
+    let (tx, rx) = oneshot::channel();
+    let metered = MeteredStream {
+        schema: stream.schema(),
+        inner: stream,
+        max_rows: limit.and_then(|l| l.max_rows.clone()),
+        max_bytes: limit.and_then(|l| l.max_bytes.clone()),
+        deny: limit.is_some_and(|l| l.on_exceed == LimitAction::Deny),
+        totals: ResultTotals::default(),
+        held: VecDeque::new(),
+        done: false,
+        report: Some(tx),
+    };
+    (Box::pin(metered), rx)
+}
+
+struct MeteredStream {
+    schema: SchemaRef,
+    inner: SendableRecordBatchStream,
+    max_rows: Option<Cap>,
+    max_bytes: Option<Cap>,
+    deny: bool,
+    totals: ResultTotals,
+    /// Under `deny`, batches waiting for the whole result to fit the caps.
+    held: VecDeque<RecordBatch>,
+    /// A cap was reached or the inner stream ended: the stream sends what is
+    /// held and ends.
+    done: bool,
+    report: Option<oneshot::Sender<ResultTotals>>,
+}
+
+impl MeteredStream {
+    /// Apply the caps to `batch`; returns the part of it that may be sent.
+    fn admit(&mut self, mut batch: RecordBatch) -> Result<RecordBatch> {
+        if let Some(cap) = &self.max_rows {
+            let allowed = cap.limit.saturating_sub(self.totals.rows);
+            if batch.num_rows() as u64 > allowed {
+                if self.deny {
+                    let msg = format!(
+                        "Result exceeds {} rows allowed by policy '{}'",
+                        cap.limit, cap.policy_name
+                    );
+                    return Err(self.exceed(msg));
+                }
+                batch = batch.slice(0, allowed as usize);
+                self.done = true;
+            }
+        }
+        let mut bytes = batch_bytes(&batch);
+        if let Some(cap) = &self.max_bytes {
+            let allowed = cap.limit.saturating_sub(self.totals.bytes);
+            if bytes > allowed {
+                if self.deny {
+                    let msg = format!(
+                        "Result exceeds {} bytes allowed by policy '{}'",
+                        cap.limit, cap.policy_name
+                    );
+                    return Err(self.exceed(msg));
+                }
+                // Largest prefix of the batch that fits.
+                let (mut lo, mut hi) = (0, batch.num_rows());
+                while lo < hi {
+                    let mid = (lo + hi).div_ceil(2);
+                    if batch_bytes(&batch.slice(0, mid)) <= allowed {
+                        lo = mid;
+                    } else {
+                        hi = mid - 1;
+                    }
+                }
+                batch = batch.slice(0, lo);
+                bytes = batch_bytes(&batch);
+                self.done = true;
+            }
+        }
+        self.totals.rows += batch.num_rows() as u64;
+        self.totals.bytes += bytes;
+        Ok(batch)
+    }
+
+    fn exceed(&mut self, msg: String) -> DataFusionError {
+        self.done = true;
+        // Nothing held back is sent.
+        self.held.clear();
+        self.totals.rows = 0;
+        self.totals.bytes = 0;
+        self.totals.exceeded = Some(msg.clone());
+        DataFusionError::External(Box::new(LimitExceeded(msg)))
+    }
+}
+
+impl Stream for MeteredStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.done {
+                return Poll::Ready(self.held.pop_front().map(Ok));
+            }
+            return match self.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(batch))) => match self.admit(batch) {
+                    Ok(batch) if self.deny => {
+                        self.held.push_back(batch);
+                        continue;
+                    }
+                    Ok(batch) if batch.num_rows() == 0 && self.done => Poll::Ready(None),
+                    result => Poll::Ready(Some(result)),
+                },
+                Poll::Ready(None) => {
+                    self.done = true;
+                    continue;
+                }
+                other => other,
+            };
+        }
+    }
+}
+
+impl RecordBatchStream for MeteredStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Drop for MeteredStream {
+    fn drop(&mut self) {
+        if let Some(tx) = self.report.take() {
+            let _ = tx.send(std::mem::take(&mut self.totals));
+        }
+    }
+}
+
+/// Arrow data size of `batch`, counting only the slice it covers.
+fn batch_bytes(batch: &RecordBatch) -> u64 {
+    batch
+        .columns()
+        .iter()
+        .map(|c| {
+            c.to_data()
+                .get_slice_memory_size()
+                .unwrap_or_else(|_| c.get_array_memory_size()) as u64
+        })
+        .sum()
+}
+
+// ---------- per-window quotas ----------
+
+/// Start of the fixed window of `secs` seconds containing `now`.
+fn window_start(now: DateTime<Utc>, secs: u64) -> NaiveDateTime {
+    let ts = now.timestamp();
+    let start = ts - ts.rem_euclid(secs as i64);
+    DateTime::from_timestamp(start, 0)
+        .unwrap_or(now)
+        .naive_utc()
+}
+
+/// Rows `user_id` has received in the current window of `window`.
+pub async fn rows_used(
+    db: &DatabaseConnection,
+    window: &RowWindow,
+    user_id: Uuid,
+) -> std::result::Result<u64, DbErr> {
+    let start = window_start(Utc::now(), window.secs);
+    let row = result_quota::Entity::find()
+        .filter(result_quota::Column::PolicyId.eq(window.policy_id))
+        .filter(result_quota::Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+    Ok(row
+        .filter(|r| r.window_start == start)
+        .map_or(0, |r| r.rows_used.max(0) as u64))
+}
+
+/// Add `rows` to `user_id`'s count for the current window of `window`,
+/// starting a new count when the stored one belongs to an older window.
+pub async fn record_rows(
+    db: &DatabaseConnection,
+    window: &RowWindow,
+    user_id: Uuid,
+    rows: u64,
+) -> std::result::Result<(), DbErr> {
+    let now = Utc::now();
+    let start = window_start(now, window.secs);
+    let rows = rows as i64;
+    let by_key = || {
+        result_quota::Entity::update_many()
+            .filter(result_quota::Column::PolicyId.eq(window.policy_id))
+            .filter(result_quota::Column::UserId.eq(user_id))
+            .col_expr(
+                result_quota::Column::UpdatedAt,
+                DbExpr::value(now.naive_utc()),
+            )
+    };
+    // Each step is a single conditional statement; a concurrent writer that
+    // wins a race makes the step match nothing, and the next attempt sees it.
+    for _ in 0..3 {
+        let added = by_key()
+            .filter(result_quota::Column::WindowStart.eq(start))
+            .col_expr(
+                result_quota::Column::RowsUsed,
+                DbExpr::col(result_quota::Column::RowsUsed).add(rows),
+            )
+            .exec(db)
+            .await?;
+        if added.rows_affected > 0 {
+            return Ok(());
+        }
+        let reset = by_key()
+            .filter(result_quota::Column::WindowStart.ne(start))
+            .col_expr(result_quota::Column::WindowStart, DbExpr::value(start))
+            .col_expr(result_quota::Column::RowsUsed, DbExpr::value(rows))
+            .exec(db)
+            .await?;
+        if reset.rows_affected > 0 {
+            return Ok(());
+        }
+        let inserted = result_quota::Entity::insert(result_quota::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            policy_id: Set(window.policy_id),
+            user_id: Set(user_id),
+            window_start: Set(start),
+            rows_used: Set(rows),
+            updated_at: Set(now.naive_utc()),
+        })
+        .on_conflict(
+            OnConflict::columns([result_quota::Column::PolicyId, result_quota::Column::UserId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        if inserted > 0 {
+            return Ok(());
+        }
+    }
+    Err(DbErr::Custom(
+        "result quota row could not be updated".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{Int64Array, StringArray};
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use std::sync::Arc;
+
+    fn batches(n: usize, per_batch: usize) -> SendableRecordBatchStream {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let items: Vec<Result<RecordBatch>> = (0..n)
+            .map(|b| {
+                let ids: Vec<i64> = (0..per_batch).map(|i| (b * per_batch + i) as i64).collect();
+                let names: Vec<String> = ids.iter().map(|i| format!("name-{i:04}")).collect();
+                Ok(RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int64Array::from(ids)),
+                        Arc::new(StringArray::from(names)),
+                    ],
+                )
+                .unwrap())
+            })
+            .collect();
+        Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            futures::stream::iter(items),
+        ))
+    }
+
+    fn limit(max_rows: Option<u64>, max_bytes: Option<u64>, deny: bool) -> ResultLimit {
+        let mut limit = ResultLimit::default();
+        let def = ResultLimitDef {
+            max_rows,
+            max_bytes,
+            window_rows: None,
+            window_secs: None,
+            on_exceed: if deny {
+                LimitAction::Deny
+            } else {
+                LimitAction::Truncate
+            },
+        };
+        limit.add(&def, Uuid::nil(), "cap");
+        limit
+    }
+
+    async fn drain(stream: SendableRecordBatchStream) -> (usize, Option<String>) {
+        let mut stream = stream;
+        let mut rows = 0;
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(b) => rows += b.num_rows(),
+                Err(e) => return (rows, Some(e.to_string())),
+            }
+        }
+        (rows, None)
+    }
+
+    #[test]
+    fn smallest_cap_and_deny_win() {
+        let mut combined = limit(Some(100), None, false);
+        combined.add(
+            &ResultLimitDef {
+                max_rows: Some(50),
+                max_bytes: Some(1000),
+                window_rows: Some(500),
+                window_secs: Some(3600),
+                on_exceed: LimitAction::Deny,
+            },
+            Uuid::nil(),
+            "strict",
+        );
+        combined.add(
+            &ResultLimitDef {
+                max_rows: Some(80),
+                max_bytes: None,
+                window_rows: None,
+                window_secs: None,
+                on_exceed: LimitAction::Truncate,
+            },
+            Uuid::nil(),
+            "loose",
+        );
+        assert_eq!(combined.max_rows.as_ref().unwrap().limit, 50);
+        assert_eq!(combined.max_rows.as_ref().unwrap().policy_name, "strict");
+        assert_eq!(combined.max_bytes.as_ref().unwrap().limit, 1000);
+        assert_eq!(combined.windows.len(), 1);
+        assert_eq!(combined.on_exceed, LimitAction::Deny);
+
+        combined.cap_rows(10, "strict");
+        assert_eq!(combined.max_rows.unwrap().limit, 10);
+    }
+
+    #[tokio::test]
+    async fn meter_counts_unlimited_streams() {
+        let (stream, totals) = meter(batches(3, 10), None);
+        assert_eq!(drain(stream).await, (30, None));
+        let totals = totals.await.unwrap();
+        assert_eq!(totals.rows, 30);
+        assert!(totals.bytes > 0);
+        assert_eq!(totals.exceeded, None);
+    }
+
+    #[tokio::test]
+    async fn row_cap_truncates_or_fails() {
+        let (stream, totals) = meter(batches(3, 10), Some(&limit(Some(15), None, false)));
+        assert_eq!(drain(stream).await, (15, None));
+        assert_eq!(totals.await.unwrap().rows, 15);
+
+        let (stream, totals) = meter(batches(3, 10), Some(&limit(Some(15), None, true)));
+        let (rows, err) = drain(stream).await;
+        assert_eq!(rows, 0, "no rows are sent before the error");
+        assert!(
+            err.unwrap()
+                .contains("exceeds 15 rows allowed by policy 'cap'")
+        );
+        let totals = totals.await.unwrap();
+        assert_eq!(totals.rows, 0);
+        assert!(totals.exceeded.is_some());
+
+        let (stream, totals) = meter(batches(3, 5), Some(&limit(Some(15), None, true)));
+        assert_eq!(drain(stream).await, (15, None));
+        assert_eq!(totals.await.unwrap().rows, 15);
+    }
+
+    #[tokio::test]
+    async fn byte_cap_truncates_within_budget() {
+        let (full, full_totals) = meter(batches(1, 100), None);
+        drain(full).await;
+        let per_row = full_totals.await.unwrap().bytes / 100;
+
+        let cap = per_row * 25;
+        let (stream, totals) = meter(batches(4, 100), Some(&limit(None, Some(cap), false)));
+        let (rows, err) = drain(stream).await;
+        assert!(err.is_none());
+        assert!(rows > 0 && rows < 100, "{rows} rows");
+        let totals = totals.await.unwrap();
+        assert!(totals.bytes <= cap, "{} > {cap}", totals.bytes);
+
+        let (stream, _) = meter(batches(4, 100), Some(&limit(None, Some(cap), true)));
+        let (rows, err) = drain(stream).await;
+        assert_eq!(rows, 0);
+        assert!(err.unwrap().contains("bytes allowed by policy 'cap'"));
+    }
+
+    #[test]
+    fn windows_are_aligned() {
+        let now = DateTime::from_timestamp(7_250, 0).unwrap();
+        assert_eq!(
+            window_start(now, 3600),
+            DateTime::from_timestamp(7_200, 0).unwrap().naive_utc()
+        );
+    }
+}
@@ -10,6 +10,7 @@ use uuid::Uuid;
 use crate::entity::{
     data_source_access, policy, policy_assignment, role, role_inheritance, role_member,
 };
+use crate::policy_match::ActionStatus;
 
 const MAX_INHERITANCE_DEPTH: usize = 10;
 
@@ -32,56 +33,81 @@ pub async fn resolve_user_roles<C: ConnectionTrait>(
     }
 
     // TODO: optimize for large deployments — load only reachable roles instead of all
-    let all_roles: HashMap<Uuid, role::Model> = role::Entity::find()
-        .all(db)
-        .await?
-        .into_iter()
-        .map(|r| (r.id, r))
-        .collect();
-
-    // Load all inheritance edges
-    let all_edges = role_inheritance::Entity::find().all(db).await?;
-    let mut parent_map: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
-    for edge in &all_edges {
-        parent_map
-            .entry(edge.child_role_id)
-            .or_default()
-            .push(edge.parent_role_id);
-    }
+    Ok(RoleGraph::load(db).await?.resolve(&direct_role_ids))
+}
 
-    // BFS from direct roles upward through inheritance, capped at MAX_INHERITANCE_DEPTH
-    let mut visited: HashSet<Uuid> = HashSet::new();
-    let mut result: Vec<Uuid> = Vec::new();
-    let mut queue: VecDeque<(Uuid, usize)> = VecDeque::new();
+/// Every role and inheritance edge, loaded once so that many users' roles can be
+/// resolved without a query per user.
+pub struct RoleGraph {
+    roles: HashMap<Uuid, role::Model>,
+    /// child role → its parent roles
+    parent_map: HashMap<Uuid, Vec<Uuid>>,
+}
 
-    for role_id in &direct_role_ids {
-        if let Some(r) = all_roles.get(role_id)
-            && r.is_active
-            && visited.insert(*role_id)
-        {
-            result.push(*role_id);
-            queue.push_back((*role_id, 1));
+impl RoleGraph {
+    pub async fn load<C: ConnectionTrait>(db: &C) -> Result<Self, DbErr> {
+        let roles: HashMap<Uuid, role::Model> = role::Entity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|r| (r.id, r))
+            .collect();
+
+        // Load all inheritance edges
+        let all_edges = role_inheritance::Entity::find().all(db).await?;
+        let mut parent_map: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for edge in &all_edges {
+            parent_map
+                .entry(edge.child_role_id)
+                .or_default()
+                .push(edge.parent_role_id);
         }
+        Ok(Self { roles, parent_map })
     }
 
-    while let Some((current_id, depth)) = queue.pop_front() {
-        if depth >= MAX_INHERITANCE_DEPTH {
-            continue;
+    /// Active roles reachable from `direct_role_ids` (the roles themselves and their
+    /// inherited ancestors), in BFS order. Inactive roles and their ancestors are
+    /// skipped.
+    pub fn resolve(&self, direct_role_ids: &[Uuid]) -> Vec<Uuid> {
+        // BFS from direct roles upward through inheritance, capped at MAX_INHERITANCE_DEPTH
+        let mut visited: HashSet<Uuid> = HashSet::new();
+        let mut result: Vec<Uuid> = Vec::new();
+        let mut queue: VecDeque<(Uuid, usize)> = VecDeque::new();
+
+        for role_id in direct_role_ids {
+            if let Some(r) = self.roles.get(role_id)
+                && r.is_active
+                && visited.insert(*role_id)
+            {
+                result.push(*role_id);
+                queue.push_back((*role_id, 1));
+            }
         }
-        if let Some(parents) = parent_map.get(&current_id) {
-            for &parent_id in parents {
-                if let Some(r) = all_roles.get(&parent_id)
-                    && r.is_active
-                    && visited.insert(parent_id)
-                {
-                    result.push(parent_id);
-                    queue.push_back((parent_id, depth + 1));
+
+        while let Some((current_id, depth)) = queue.pop_front() {
+            if depth >= MAX_INHERITANCE_DEPTH {
+                continue;
+            }
+            if let Some(parents) = self.parent_map.get(&current_id) {
+                for &parent_id in parents {
+                    if let Some(r) = self.roles.get(&parent_id)
+                        && r.is_active
+                        && visited.insert(parent_id)
+                    {
+                        result.push(parent_id);
+                        queue.push_back((parent_id, depth + 1));
+                    }
                 }
             }
         }
+
+        result
     }
 
-    Ok(result)
+    /// The role's name, if it exists.
+    pub fn role_name(&self, role_id: Uuid) -> Option<&str> {
+        self.roles.get(&role_id).map(|r| r.name.as_str())
+    }
 }
 
 /// Detect if adding an inheritance edge (parent_id ← child_id) would create a cycle.
@@ -166,6 +192,8 @@ pub async fn check_inheritance_depth<C: ConnectionTrait>(
 /// Resolve all effective policy assignments for a user on a given datasource.
 /// Returns assignments where scope='all' OR (scope='user' AND user_id=user) OR (scope='role' AND role_id in user's roles).
 /// Deduplicates: if the same policy_id appears from multiple sources, keeps the one with lowest priority.
+/// An `enforce` assignment always wins over a `shadow` one, so shadowing a policy for one
+/// role never relaxes it for a user who also receives it through an enforcing assignment.
 pub async fn resolve_effective_assignments<C: ConnectionTrait>(
     db: &C,
     user_id: Uuid,
@@ -179,10 +207,23 @@ pub async fn resolve_effective_assignments<C: ConnectionTrait>(
         .await?;
 
     let role_set: HashSet<Uuid> = user_roles.into_iter().collect();
+    Ok(select_effective_assignments(
+        all_assignments,
+        user_id,
+        &role_set,
+    ))
+}
 
+/// The assignments among `assignments` that reach a user with the resolved roles
+/// `role_set`, one per policy — the rules of [`resolve_effective_assignments`].
+pub fn select_effective_assignments(
+    assignments: impl IntoIterator<Item = policy_assignment::Model>,
+    user_id: Uuid,
+    role_set: &HashSet<Uuid>,
+) -> Vec<policy_assignment::Model> {
     let mut best_by_policy: HashMap<Uuid, policy_assignment::Model> = HashMap::new();
 
-    for a in all_assignments {
+    for a in assignments {
         let matches = match a.assignment_scope.as_str() {
             "all" => true,
             "user" => a.user_id == Some(user_id),
@@ -194,7 +235,10 @@ pub async fn resolve_effective_assignments<C: ConnectionTrait>(
             best_by_policy
                 .entry(a.policy_id)
                 .and_modify(|existing| {
-                    if a.priority < existing.priority {
+                    let rank = |m: &policy_assignment::Model| {
+                        (m.action_status == ActionStatus::Shadow.as_str(), m.priority)
+                    };
+                    if rank(&a) < rank(existing) {
                         *existing = a.clone();
                     }
                 })
@@ -202,7 +246,7 @@ pub async fn resolve_effective_assignments<C: ConnectionTrait>(
         }
     }
 
-    Ok(best_by_policy.into_values().collect())
+    best_by_policy.into_values().collect()
 }
 
 /// Check if a user has access to a datasource (direct, role-based, or all-scoped).
@@ -219,20 +263,22 @@ pub async fn resolve_datasource_access<C: ConnectionTrait>(
         .await?;
 
     let role_set: HashSet<Uuid> = user_roles.into_iter().collect();
+    Ok(has_datasource_access(&accesses, user_id, &role_set))
+}
 
-    for a in &accesses {
-        let matches = match a.assignment_scope.as_str() {
-            "all" => true,
-            "user" => a.user_id == Some(user_id),
-            "role" => a.role_id.is_some_and(|rid| role_set.contains(&rid)),
-            _ => false,
-        };
-        if matches {
-            return Ok(true);
-        }
-    }
-
-    Ok(false)
+/// Whether any of a datasource's `accesses` grants it to a user with the resolved
+/// roles `role_set`.
+pub fn has_datasource_access(
+    accesses: &[data_source_access::Model],
+    user_id: Uuid,
+    role_set: &HashSet<Uuid>,
+) -> bool {
+    accesses.iter().any(|a| match a.assignment_scope.as_str() {
+        "all" => true,
+        "user" => a.user_id == Some(user_id),
+        "role" => a.role_id.is_some_and(|rid| role_set.contains(&rid)),
+        _ => false,
+    })
 }
 
 /// Resolve all user IDs that are members of a role (direct + inherited via child subtree).
@@ -1081,4 +1127,80 @@ mod tests {
         let c_ancestors = resolve_ancestor_roles(&db, c.id).await.unwrap();
         assert!(c_ancestors.is_empty());
     }
+
+    #[tokio::test]
+    async fn u19_enforcing_assignment_wins_over_shadow() {
+        let db = setup().await;
+        let user = create_user(&db, "alice").await;
+        let analysts = create_role(&db, "analysts", true).await;
+        add_member(&db, analysts.id, user.id).await;
+
+        let now = Utc::now().naive_utc();
+        let ds = crate::entity::data_source::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            name: Set("ds".to_string()),
+            ds_type: Set("postgres".to_string()),
+            config: Set("{}".to_string()),
+            secure_config: Set(String::new()),
+            is_active: Set(true),
+            access_mode: Set("open".to_string()),
+            policy_combining: Set(None),
+            write_mode: Set("read_only".to_string()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let p = policy::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            name: Set("tenant".to_string()),
+            policy_type: Set("row_filter".to_string()),
+            targets: Set("[]".to_string()),
+            is_enabled: Set(true),
+            action_status: Set("enforce".to_string()),
+            version: Set(1),
+            created_by: Set(user.id),
+            updated_by: Set(user.id),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let assign = |scope: &str, role_id: Option<Uuid>, priority: i32, status: &str| {
+            policy_assignment::ActiveModel {
+                id: Set(Uuid::now_v7()),
+                policy_id: Set(p.id),
+                data_source_id: Set(ds.id),
+                user_id: Set(None),
+                role_id: Set(role_id),
+                assignment_scope: Set(scope.to_string()),
+                priority: Set(priority),
+                action_status: Set(status.to_string()),
+                purpose: Set(None),
+                created_at: Set(now),
+                updated_at: Set(now),
+            }
+        };
+
+        // The shadow role assignment has the better priority, but the
+        // enforcing all-users assignment still wins.
+        assign("role", Some(analysts.id), 1, "shadow")
+            .insert(&db)
+            .await
+            .unwrap();
+        assign("all", None, 100, "enforce")
+            .insert(&db)
+            .await
+            .unwrap();
+        let effective = resolve_effective_assignments(&db, user.id, ds.id)
+            .await
+            .unwrap();
+        assert_eq!(effective.len(), 1);
+        assert_eq!(effective[0].action_status, "enforce");
+        assert_eq!(effective[0].priority, 100);
+    }
 }
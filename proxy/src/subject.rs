@@ -0,0 +1,130 @@
+//! The identity a query's policies are resolved for.
+//!
+//! Policy assignments, datasource access, decision function context and
+//! template variables all depend on who is asking: a user id, a username, a set
+//! of roles and a set of attributes — and the purpose the session declared. A
+//! [`Subject`] carries exactly that. It is normally loaded from a stored user;
+//! an admin's impersonation preview builds a hypothetical one from chosen roles
+//! and attributes instead.
+
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::entity::{data_source_access, policy_assignment, proxy_user, role};
+use crate::policy_simulation::PolicyChange;
+use crate::purpose;
+use crate::role_resolver::{
+    RoleGraph, has_datasource_access, resolve_user_roles, select_effective_assignments,
+};
+
+#[derive(Debug, Clone)]
+pub struct Subject {
+    /// `Uuid::nil()` for a hypothetical user, which no user-scoped assignment reaches.
+    pub user_id: Uuid,
+    pub username: String,
+    /// Active roles, direct and inherited.
+    pub role_ids: Vec<Uuid>,
+    /// Attributes in the `proxy_user.attributes` JSON format.
+    pub attributes: String,
+    /// The validated purpose of the session (see [`crate::purpose`]).
+    pub purpose: Option<String>,
+}
+
+impl Subject {
+    /// The stored user `user_id` with their current roles, if the user exists.
+    pub async fn load<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<Option<Self>, DbErr> {
+        let Some(user) = proxy_user::Entity::find_by_id(user_id).one(db).await? else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            user_id,
+            username: user.username,
+            role_ids: resolve_user_roles(db, user_id).await?,
+            attributes: user.attributes,
+            purpose: None,
+        }))
+    }
+
+    /// A user who does not exist, member of `direct_role_ids` (and so of their
+    /// ancestors) with `attributes`.
+    pub async fn hypothetical<C: ConnectionTrait>(
+        db: &C,
+        username: String,
+        direct_role_ids: &[Uuid],
+        attributes: String,
+    ) -> Result<Self, DbErr> {
+        Ok(Self {
+            user_id: Uuid::nil(),
+            username,
+            role_ids: RoleGraph::load(db).await?.resolve(direct_role_ids),
+            attributes,
+            purpose: None,
+        })
+    }
+
+    /// This subject in a session that declared `purpose`.
+    pub fn with_purpose(self, purpose: Option<String>) -> Self {
+        Self { purpose, ..self }
+    }
+
+    /// Names of [`Self::role_ids`], for decision function context.
+    pub async fn role_names<C: ConnectionTrait>(&self, db: &C) -> Result<Vec<String>, DbErr> {
+        if self.role_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(role::Entity::find()
+            .filter(role::Column::Id.is_in(self.role_ids.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|r| r.name)
+            .collect())
+    }
+
+    /// The policy assignments on `datasource_id` that reach this subject — see
+    /// [`crate::role_resolver::resolve_effective_assignments`] — including the
+    /// proposed assignment of `change`, if any. Assignments restricted to
+    /// another purpose are left out.
+    pub async fn effective_assignments<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        datasource_id: Uuid,
+        change: Option<&PolicyChange>,
+    ) -> Result<Vec<policy_assignment::Model>, DbErr> {
+        let mut assignments = policy_assignment::Entity::find()
+            .filter(policy_assignment::Column::DataSourceId.eq(datasource_id))
+            .all(db)
+            .await?;
+        if let Some(change) = change {
+            change.apply_to_assignments(&mut assignments, datasource_id);
+        }
+        assignments.retain(|a| purpose::in_scope(a.purpose.as_deref(), self.purpose.as_deref()));
+        Ok(select_effective_assignments(
+            assignments,
+            self.user_id,
+            &self.role_set(),
+        ))
+    }
+
+    /// Whether this subject has been granted `datasource_id`.
+    pub async fn has_datasource_access<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        datasource_id: Uuid,
+    ) -> Result<bool, DbErr> {
+        let accesses = data_source_access::Entity::find()
+            .filter(data_source_access::Column::DataSourceId.eq(datasource_id))
+            .all(db)
+            .await?;
+        Ok(has_datasource_access(
+            &accesses,
+            self.user_id,
+            &self.role_set(),
+        ))
+    }
+
+    fn role_set(&self) -> HashSet<Uuid> {
+        self.role_ids.iter().copied().collect()
+    }
+}
@@ -0,0 +1,645 @@
+//! Governed writes on `read_write` datasources.
+//!
+//! A write never reaches the upstream as the client sent it. [`WriteStatement::parse`]
+//! accepts one shape per operation and refuses every other one:
+//!
+//! | Statement | Accepted |
+//! |---|---|
+//! | `INSERT INTO t (cols) VALUES (...), ...` | constant values only; no `SELECT`, `ON CONFLICT` or `RETURNING` |
+//! | `UPDATE t SET col = expr, ... [WHERE ...]` | one table; no `FROM`, `RETURNING` or subqueries |
+//! | `DELETE FROM t [WHERE ...]` | one table; no `USING`, `RETURNING` or subqueries |
+//! | DDL (`CREATE`, `ALTER`, `DROP`, `TRUNCATE`, `GRANT`, ...) | never |
+//!
+//! The policy hook plans [`WriteStatement::probe_sql`] — a `SELECT` of the SET
+//! expressions over the statement's WHERE clause — against the user's session,
+//! so a write can only reference columns the user can read and the read
+//! policies on them still hold. The upstream statement is then rebuilt by
+//! [`PlannedWrite::to_sql`] from the planned expressions, with the table's row
+//! filter added to its WHERE clause. Its `RETURNING` list reports for every
+//! changed row whether it still passes the row filter (WITH CHECK) and its
+//! image for the audit log: the columns the user can read, to which the policy
+//! hook applies the user's masks before the image is stored.
+
+use datafusion::common::Column;
+use datafusion::common::tree_node::{Transformed, TreeNode};
+use datafusion::logical_expr::{Expr, LogicalPlan};
+use datafusion::sql::sqlparser::ast::{
+    self, AssignmentTarget, FromTable, Ident, ObjectNamePart, SetExpr, Statement, TableFactor,
+    TableObject, TableWithJoins,
+};
+use datafusion::sql::unparser::Unparser;
+
+use crate::engine::BetweenRowsPostgresDialect;
+use crate::policy_match::WriteOperation;
+
+/// Most row images one write records in `query_audit_log.affected_rows`.
+pub const MAX_AUDITED_ROWS: usize = 1000;
+
+/// Alias of the target table in the upstream statement.
+const TARGET_ALIAS: &str = "br_target";
+
+/// Whether `statement` is an INSERT, UPDATE or DELETE.
+pub fn is_write(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::Insert(_) | Statement::Update { .. } | Statement::Delete(_)
+    )
+}
+
+/// Whether `statement` changes the schema, permissions or bulk contents of the
+/// database. Refused on every datasource, `read_write` included.
+pub fn is_ddl(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::AlterConnector { .. }
+            | Statement::AlterIndex { .. }
+            | Statement::AlterPolicy { .. }
+            | Statement::AlterRole { .. }
+            | Statement::AlterSchema { .. }
+            | Statement::AlterTable { .. }
+            | Statement::AlterType { .. }
+            | Statement::AlterView { .. }
+            | Statement::Comment { .. }
+            | Statement::CreateConnector { .. }
+            | Statement::CreateDatabase { .. }
+            | Statement::CreateDomain { .. }
+            | Statement::CreateExtension { .. }
+            | Statement::CreateFunction { .. }
+            | Statement::CreateIndex { .. }
+            | Statement::CreateMacro { .. }
+            | Statement::CreatePolicy { .. }
+            | Statement::CreateProcedure { .. }
+            | Statement::CreateRole { .. }
+            | Statement::CreateSchema { .. }
+            | Statement::CreateSecret { .. }
+            | Statement::CreateSequence { .. }
+            | Statement::CreateServer { .. }
+            | Statement::CreateStage { .. }
+            | Statement::CreateTable { .. }
+            | Statement::CreateTrigger { .. }
+            | Statement::CreateType { .. }
+            | Statement::CreateUser { .. }
+            | Statement::CreateView { .. }
+            | Statement::CreateVirtualTable { .. }
+            | Statement::Deny { .. }
+            | Statement::Drop { .. }
+            | Statement::DropConnector { .. }
+            | Statement::DropDomain { .. }
+            | Statement::DropExtension { .. }
+            | Statement::DropFunction { .. }
+            | Statement::DropPolicy { .. }
+            | Statement::DropProcedure { .. }
+            | Statement::DropSecret { .. }
+            | Statement::DropTrigger { .. }
+            | Statement::Grant { .. }
+            | Statement::RenameTable { .. }
+            | Statement::Revoke { .. }
+            | Statement::Truncate { .. }
+    )
+}
+
+/// A write statement the proxy can govern, reduced to its parts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteStatement {
+    pub operation: WriteOperation,
+    /// The target table as written, with its alias, for the probe queries.
+    relation: String,
+    /// Written columns: INSERT's column list or UPDATE's SET targets.
+    pub columns: Vec<String>,
+    /// UPDATE: one row of SET expressions. INSERT: the VALUES rows.
+    rows: Vec<Vec<ast::Expr>>,
+    selection: Option<ast::Expr>,
+}
+
+impl WriteStatement {
+    /// Reduce `statement` to a governable write, or say why it cannot be one
+    /// (SQLSTATE `0A000`).
+    pub fn parse(statement: &Statement) -> Result<Self, String> {
+        match statement {
+            Statement::Insert(insert) => {
+                if insert.returning.is_some() {
+                    return Err("INSERT ... RETURNING is not supported".to_string());
+                }
+                if insert.on.is_some() {
+                    return Err("INSERT ... ON CONFLICT is not supported".to_string());
+                }
+                if insert.or.is_some()
+                    || insert.ignore
+                    || insert.overwrite
+                    || insert.replace_into
+                    || !insert.assignments.is_empty()
+                    || insert.partitioned.is_some()
+                {
+                    return Err("this form of INSERT is not supported".to_string());
+                }
+                let TableObject::TableName(name) = &insert.table else {
+                    return Err("INSERT into a table function is not supported".to_string());
+                };
+                if insert.columns.is_empty() {
+                    return Err("INSERT needs an explicit column list".to_string());
+                }
+                let rows = match insert.source.as_deref().map(|q| (q, q.body.as_ref())) {
+                    Some((query, SetExpr::Values(values)))
+                        if query.with.is_none()
+                            && query.order_by.is_none()
+                            && query.limit_clause.is_none() =>
+                    {
+                        values.rows.clone()
+                    }
+                    _ => {
+                        return Err(
+                            "INSERT accepts VALUES rows only, not a query or DEFAULT VALUES"
+                                .to_string(),
+                        );
+                    }
+                };
+                let columns: Vec<String> = insert.columns.iter().map(normalize_ident).collect();
+                if rows.iter().any(|row| row.len() != columns.len()) {
+                    return Err(format!(
+                        "every VALUES row must have {} values, one per listed column",
+                        columns.len()
+                    ));
+                }
+                let relation = match &insert.table_alias {
+                    Some(alias) => format!("{name} AS {alias}"),
+                    None => name.to_string(),
+                };
+                Ok(Self {
+                    operation: WriteOperation::Insert,
+                    relation,
+                    columns: unique(columns)?,
+                    rows,
+                    selection: None,
+                })
+            }
+            Statement::Update {
+                table,
+                assignments,
+                from,
+                selection,
+                returning,
+                or,
+                limit,
+            } => {
+                if returning.is_some() {
+                    return Err("UPDATE ... RETURNING is not supported".to_string());
+                }
+                if from.is_some() {
+                    return Err("UPDATE ... FROM is not supported".to_string());
+                }
+                if or.is_some() || limit.is_some() {
+                    return Err("this form of UPDATE is not supported".to_string());
+                }
+                let relation = single_table(table)?;
+                let mut columns = Vec::with_capacity(assignments.len());
+                let mut values = Vec::with_capacity(assignments.len());
+                for assignment in assignments {
+                    let column = match &assignment.target {
+                        AssignmentTarget::ColumnName(name) => match name.0.as_slice() {
+                            [ObjectNamePart::Identifier(ident)] => normalize_ident(ident),
+                            _ => {
+                                return Err(format!(
+                                    "SET target {name} must be a plain column name"
+                                ));
+                            }
+                        },
+                        AssignmentTarget::Tuple(_) => {
+                            return Err("SET (a, b) = ... is not supported".to_string());
+                        }
+                    };
+                    columns.push(column);
+                    values.push(assignment.value.clone());
+                }
+                Ok(Self {
+                    operation: WriteOperation::Update,
+                    relation,
+                    columns: unique(columns)?,
+                    rows: vec![values],
+                    selection: selection.clone(),
+                })
+            }
+            Statement::Delete(delete) => {
+                if delete.returning.is_some() {
+                    return Err("DELETE ... RETURNING is not supported".to_string());
+                }
+                if delete.using.is_some() {
+                    return Err("DELETE ... USING is not supported".to_string());
+                }
+                if !delete.tables.is_empty()
+                    || !delete.order_by.is_empty()
+                    || delete.limit.is_some()
+                {
+                    return Err("this form of DELETE is not supported".to_string());
+                }
+                let from = match &delete.from {
+                    FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from) => from,
+                };
+                let [table] = from.as_slice() else {
+                    return Err("DELETE must name exactly one table".to_string());
+                };
+                Ok(Self {
+                    operation: WriteOperation::Delete,
+                    relation: single_table(table)?,
+                    columns: Vec::new(),
+                    rows: Vec::new(),
+                    selection: delete.selection.clone(),
+                })
+            }
+            _ => Err("only INSERT, UPDATE and DELETE can write".to_string()),
+        }
+    }
+
+    /// The `SELECT` planned against the user's session to resolve the target
+    /// table and check the statement's expressions: UPDATE's SET expressions
+    /// and WHERE clause, DELETE's WHERE clause, or just INSERT's table.
+    pub fn probe_sql(&self) -> String {
+        let projection = match self.operation {
+            WriteOperation::Update => aliased(&self.rows[0]),
+            WriteOperation::Insert | WriteOperation::Delete => "1".to_string(),
+        };
+        let mut sql = format!("SELECT {projection} FROM {}", self.relation);
+        if let Some(selection) = &self.selection {
+            sql.push_str(&format!(" WHERE {selection}"));
+        }
+        sql
+    }
+
+    /// One `SELECT` per INSERT row, planned to check its values. Empty for
+    /// UPDATE and DELETE.
+    pub fn values_sql(&self) -> Vec<String> {
+        match self.operation {
+            WriteOperation::Insert => self
+                .rows
+                .iter()
+                .map(|row| format!("SELECT {}", aliased(row)))
+                .collect(),
+            WriteOperation::Update | WriteOperation::Delete => Vec::new(),
+        }
+    }
+}
+
+/// The one table of an UPDATE or DELETE, as written.
+fn single_table(table: &TableWithJoins) -> Result<String, String> {
+    if !table.joins.is_empty() {
+        return Err("a write must name exactly one table".to_string());
+    }
+    match &table.relation {
+        TableFactor::Table { args: None, .. } => Ok(table.relation.to_string()),
+        _ => Err("a write must target a table".to_string()),
+    }
+}
+
+/// Column name as Postgres resolves it: unquoted identifiers fold to lower case.
+fn normalize_ident(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn unique(columns: Vec<String>) -> Result<Vec<String>, String> {
+    for (i, column) in columns.iter().enumerate() {
+        if columns[..i].contains(column) {
+            return Err(format!("column \"{column}\" is written more than once"));
+        }
+    }
+    Ok(columns)
+}
+
+/// `expr AS br_0, expr AS br_1, ...`: planned names stay unique whatever the
+/// expressions are.
+fn aliased(exprs: &[ast::Expr]) -> String {
+    exprs
+        .iter()
+        .enumerate()
+        .map(|(i, e)| format!("{e} AS br_{i}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The projected expressions and the WHERE predicate of a planned probe.
+pub fn probe_parts(plan: &LogicalPlan) -> (Vec<Expr>, Option<Expr>) {
+    let mut projection = Vec::new();
+    let mut predicate = None;
+    let mut node = plan;
+    loop {
+        match node {
+            LogicalPlan::Projection(p) => {
+                projection = p.expr.iter().map(|e| e.clone().unalias()).collect();
+                node = &p.input;
+            }
+            LogicalPlan::Filter(f) => {
+                predicate = Some(f.predicate.clone());
+                node = &f.input;
+            }
+            LogicalPlan::SubqueryAlias(a) => node = &a.input,
+            _ => break,
+        }
+    }
+    (projection, predicate)
+}
+
+/// `expr` with its column references unqualified, so that they resolve against
+/// the upstream statement's single target table.
+pub fn unqualified(expr: Expr) -> Expr {
+    expr.transform(|e| {
+        Ok(match e {
+            Expr::Column(c) if c.relation.is_some() => {
+                Transformed::yes(Expr::Column(Column::new_unqualified(c.name)))
+            }
+            other => Transformed::no(other),
+        })
+    })
+    .map(|t| t.data)
+    .expect("infallible transform")
+}
+
+/// Postgres SQL for a planned expression.
+pub fn expr_sql(expr: &Expr) -> datafusion::error::Result<String> {
+    let expr = unqualified(expr.clone());
+    Ok(Unparser::new(&BetweenRowsPostgresDialect)
+        .expr_to_sql(&expr)?
+        .to_string())
+}
+
+/// `name` as a quoted Postgres identifier.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// `columns` of the target row as JSON text, or NULL without columns.
+/// `jsonb_build_object` takes at most 100 arguments, so wide images are merged
+/// from chunks of 50 columns.
+fn image_sql(columns: &[String]) -> String {
+    if columns.is_empty() {
+        return "NULL::text".to_string();
+    }
+    let objects: Vec<String> = columns
+        .chunks(50)
+        .map(|chunk| {
+            let pairs: Vec<String> = chunk
+                .iter()
+                .map(|c| {
+                    format!(
+                        "'{}', {}.{}",
+                        c.replace('\'', "''"),
+                        quote_ident(TARGET_ALIAS),
+                        quote_ident(c)
+                    )
+                })
+                .collect();
+            format!("jsonb_build_object({})", pairs.join(", "))
+        })
+        .collect();
+    format!("({})::text", objects.join(" || "))
+}
+
+/// A governed write, ready to run upstream. Every expression is SQL unparsed
+/// from a planned, policy-checked expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedWrite {
+    pub operation: WriteOperation,
+    /// Quoted upstream `"schema"."table"`.
+    pub target: String,
+    pub columns: Vec<String>,
+    /// UPDATE: one row of SET expressions. INSERT: the VALUES rows.
+    pub rows: Vec<Vec<String>>,
+    pub selection: Option<String>,
+    /// The table's row filter: UPDATE and DELETE only touch rows that pass it,
+    /// and every inserted or updated row must still pass it.
+    pub check: Option<String>,
+    /// Columns of each changed row's audit image: those the user can read.
+    pub image: Vec<String>,
+}
+
+impl PlannedWrite {
+    /// The upstream statement. Its `RETURNING` list yields, per changed row,
+    /// whether the row passes `check` and its `image` columns as JSON text.
+    pub fn to_sql(&self) -> String {
+        let target = format!("{} AS {}", self.target, quote_ident(TARGET_ALIAS));
+        let columns: Vec<String> = self.columns.iter().map(|c| quote_ident(c)).collect();
+        let check = match (&self.check, self.operation) {
+            (Some(check), WriteOperation::Insert | WriteOperation::Update) => {
+                format!("({check}) IS TRUE")
+            }
+            _ => "true".to_string(),
+        };
+        let returning = format!(" RETURNING {check}, {}", image_sql(&self.image));
+        let conditions: Vec<String> = match self.operation {
+            WriteOperation::Insert => Vec::new(),
+            WriteOperation::Update | WriteOperation::Delete => self
+                .selection
+                .iter()
+                .chain(&self.check)
+                .map(|c| format!("({c})"))
+                .collect(),
+        };
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+        match self.operation {
+            WriteOperation::Insert => {
+                let rows: Vec<String> = self
+                    .rows
+                    .iter()
+                    .map(|row| format!("({})", row.join(", ")))
+                    .collect();
+                format!(
+                    "INSERT INTO {target} ({}) VALUES {}{returning}",
+                    columns.join(", "),
+                    rows.join(", ")
+                )
+            }
+            WriteOperation::Update => {
+                let assignments: Vec<String> = columns
+                    .iter()
+                    .zip(self.rows.first().into_iter().flatten())
+                    .map(|(c, v)| format!("{c} = {v}"))
+                    .collect();
+                format!(
+                    "UPDATE {target} SET {}{filter}{returning}",
+                    assignments.join(", ")
+                )
+            }
+            WriteOperation::Delete => format!("DELETE FROM {target}{filter}{returning}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::datasource::MemTable;
+    use datafusion::prelude::SessionContext;
+    use datafusion::sql::sqlparser::{dialect::PostgreSqlDialect, parser::Parser};
+    use std::sync::Arc;
+
+    fn statement(sql: &str) -> Statement {
+        Parser::parse_sql(&PostgreSqlDialect {}, sql)
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap()
+    }
+
+    fn parse(sql: &str) -> Result<WriteStatement, String> {
+        WriteStatement::parse(&statement(sql))
+    }
+
+    #[test]
+    fn classifies_writes_and_ddl() {
+        for sql in [
+            "INSERT INTO t (a) VALUES (1)",
+            "UPDATE t SET a = 1",
+            "DELETE FROM t",
+        ] {
+            assert!(is_write(&statement(sql)), "{sql}");
+            assert!(!is_ddl(&statement(sql)), "{sql}");
+        }
+        for sql in [
+            "CREATE TABLE t (a int)",
+            "ALTER TABLE t ADD COLUMN b int",
+            "DROP TABLE t",
+            "TRUNCATE t",
+            "GRANT SELECT ON t TO bob",
+            "CREATE INDEX i ON t (a)",
+        ] {
+            assert!(is_ddl(&statement(sql)), "{sql}");
+            assert!(!is_write(&statement(sql)), "{sql}");
+        }
+        assert!(!is_ddl(&statement("SET search_path = public")));
+        assert!(!is_write(&statement("SELECT 1")));
+    }
+
+    #[test]
+    fn refuses_unsupported_shapes() {
+        for (sql, expected) in [
+            ("INSERT INTO t VALUES (1)", "explicit column list"),
+            ("INSERT INTO t (a) SELECT a FROM s", "VALUES rows only"),
+            ("INSERT INTO t (a) VALUES (1) RETURNING a", "RETURNING"),
+            (
+                "INSERT INTO t (a) VALUES (1) ON CONFLICT DO NOTHING",
+                "ON CONFLICT",
+            ),
+            ("INSERT INTO t (a, b) VALUES (1)", "one per listed column"),
+            ("INSERT INTO t (a, a) VALUES (1, 2)", "more than once"),
+            ("UPDATE t SET a = 1 FROM s WHERE s.id = t.id", "FROM"),
+            ("UPDATE t SET a = 1 RETURNING *", "RETURNING"),
+            ("UPDATE t SET (a, b) = (1, 2)", "SET (a, b)"),
+            ("DELETE FROM t USING s WHERE s.id = t.id", "USING"),
+            ("DELETE FROM t RETURNING *", "RETURNING"),
+            (
+                "MERGE INTO t USING s ON t.id = s.id WHEN MATCHED THEN DELETE",
+                "only INSERT",
+            ),
+        ] {
+            let err = parse(sql).expect_err(sql);
+            assert!(err.contains(expected), "{sql}: {err}");
+        }
+    }
+
+    #[test]
+    fn probes_select_what_the_write_reads() {
+        let update = parse("UPDATE public.customers AS c SET City = upper(c.city), \"Zip\" = '1' WHERE c.region = 'eu'").unwrap();
+        assert_eq!(update.operation, WriteOperation::Update);
+        assert_eq!(update.columns, vec!["city", "Zip"]);
+        assert_eq!(
+            update.probe_sql(),
+            "SELECT upper(c.city) AS br_0, '1' AS br_1 FROM public.customers AS c WHERE c.region = 'eu'"
+        );
+        assert!(update.values_sql().is_empty());
+
+        let delete = parse("DELETE FROM customers WHERE id = 7").unwrap();
+        assert_eq!(delete.probe_sql(), "SELECT 1 FROM customers WHERE id = 7");
+
+        let insert =
+            parse("INSERT INTO customers (id, city) VALUES (1, 'Oslo'), (2, 'Rome')").unwrap();
+        assert_eq!(insert.probe_sql(), "SELECT 1 FROM customers");
+        assert_eq!(
+            insert.values_sql(),
+            vec![
+                "SELECT 1 AS br_0, 'Oslo' AS br_1",
+                "SELECT 2 AS br_0, 'Rome' AS br_1"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn planned_expressions_unparse_unqualified() {
+        let ctx = SessionContext::new();
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("city", DataType::Utf8, true),
+            Field::new("region", DataType::Utf8, true),
+        ]));
+        ctx.register_table(
+            "customers",
+            Arc::new(MemTable::try_new(schema, vec![vec![]]).unwrap()),
+        )
+        .unwrap();
+        let update =
+            parse("UPDATE customers AS c SET city = upper(c.city) WHERE c.region = 'eu'").unwrap();
+        let plan = ctx
+            .state()
+            .create_logical_plan(&update.probe_sql())
+            .await
+            .unwrap();
+        let (projection, predicate) = probe_parts(&plan);
+        assert_eq!(expr_sql(&projection[0]).unwrap(), "upper(\"city\")");
+        assert_eq!(
+            expr_sql(&predicate.unwrap()).unwrap(),
+            "(\"region\" = 'eu')"
+        );
+    }
+
+    #[test]
+    fn upstream_sql_adds_the_row_filter_and_check() {
+        let mut write = PlannedWrite {
+            operation: WriteOperation::Update,
+            target: "\"public\".\"customers\"".to_string(),
+            columns: vec!["city".to_string()],
+            rows: vec![vec!["'Oslo'".to_string()]],
+            selection: Some("(\"id\" = 7)".to_string()),
+            check: Some("(\"region\" = 'eu')".to_string()),
+            image: vec!["id".to_string(), "city".to_string()],
+        };
+        assert_eq!(
+            write.to_sql(),
+            "UPDATE \"public\".\"customers\" AS \"br_target\" SET \"city\" = 'Oslo' \
+             WHERE ((\"id\" = 7)) AND ((\"region\" = 'eu')) \
+             RETURNING ((\"region\" = 'eu')) IS TRUE, \
+             (jsonb_build_object('id', \"br_target\".\"id\", 'city', \"br_target\".\"city\"))::text"
+        );
+
+        write.operation = WriteOperation::Delete;
+        write.selection = None;
+        assert_eq!(
+            write.to_sql(),
+            "DELETE FROM \"public\".\"customers\" AS \"br_target\" \
+             WHERE ((\"region\" = 'eu')) RETURNING true, \
+             (jsonb_build_object('id', \"br_target\".\"id\", 'city', \"br_target\".\"city\"))::text"
+        );
+
+        write.operation = WriteOperation::Insert;
+        write.columns = vec!["id".to_string(), "city".to_string()];
+        write.rows = vec![
+            vec!["1".to_string(), "'Oslo'".to_string()],
+            vec!["2".to_string(), "'Rome'".to_string()],
+        ];
+        write.check = None;
+        write.image = Vec::new();
+        assert_eq!(
+            write.to_sql(),
+            "INSERT INTO \"public\".\"customers\" AS \"br_target\" (\"id\", \"city\") \
+             VALUES (1, 'Oslo'), (2, 'Rome') RETURNING true, NULL::text"
+        );
+    }
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(quote_ident("a\"b"), "\"a\"\"b\"");
+    }
+}
@@ -0,0 +1,141 @@
+//! Aggregate-only (k-anonymity) policy integration tests.
+//!
+//! These tests verify that an `aggregate_only` policy rejects row-level reads
+//! with SQLSTATE 42501 (audited as `denied`), drops aggregate groups smaller
+//! than `min_group_size`, and rejects joins and unions that would repeat rows to
+//! pass it. Uses a real Postgres container.
+
+mod support;
+
+use serde_json::json;
+use support::TEST_PASS;
+
+async fn setup(server: &support::ProxyTestServer, schema: &str, ds_name: &str, username: &str) {
+    server
+        .setup_policy_fixture(
+            &format!(
+                "CREATE SCHEMA IF NOT EXISTS {schema};
+                 DROP TABLE IF EXISTS {schema}.patients;
+                 CREATE TABLE {schema}.patients (id INT, zip TEXT, age INT);
+                 INSERT INTO {schema}.patients VALUES
+                     (1, '10001', 30), (2, '10001', 41), (3, '10001', 52),
+                     (4, '94105', 35), (5, '94105', 38), (6, '94105', 60),
+                     (7, '99501', 77);"
+            ),
+            schema,
+            ds_name,
+            username,
+            &format!("{schema}-aggregate-only"),
+            "aggregate_only",
+            json!({"schemas": [schema], "tables": ["patients"]}),
+            Some(json!({"min_group_size": 3})),
+        )
+        .await;
+}
+
+#[tokio::test]
+async fn small_groups_are_suppressed() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "agg_only_groups";
+    setup(&server, schema, "ds_agg_groups", "agg_groups_user").await;
+
+    let client = server
+        .connect_as("agg_groups_user", TEST_PASS, "ds_agg_groups")
+        .await;
+    let rows = client
+        .simple_query(&format!(
+            "SELECT zip, COUNT(*) AS n, MAX(age) FROM {schema}.patients GROUP BY zip ORDER BY zip"
+        ))
+        .await
+        .unwrap();
+    let rows = support::extract_rows(&rows);
+    assert_eq!(
+        rows,
+        vec![
+            vec!["10001".to_string(), "3".to_string(), "52".to_string()],
+            vec!["94105".to_string(), "3".to_string(), "60".to_string()],
+        ],
+        "the single-member 99501 group must be dropped"
+    );
+
+    // A filtered global aggregate over fewer than k rows returns nothing.
+    let rows = client
+        .simple_query(&format!(
+            "SELECT AVG(age) FROM {schema}.patients WHERE zip = '99501'"
+        ))
+        .await
+        .unwrap();
+    assert!(support::extract_rows(&rows).is_empty());
+}
+
+#[tokio::test]
+async fn row_level_read_is_denied_and_audited() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "agg_only_rows";
+    setup(&server, schema, "ds_agg_rows", "agg_rows_user").await;
+
+    let client = server
+        .connect_as("agg_rows_user", TEST_PASS, "ds_agg_rows")
+        .await;
+    let err = client
+        .simple_query(&format!("SELECT * FROM {schema}.patients"))
+        .await
+        .expect_err("row-level read must be rejected");
+    let db_err = err.as_db_error().expect("Expected a DB error");
+    assert_eq!(db_err.code().code(), "42501");
+    assert!(
+        db_err
+            .message()
+            .contains("can only be queried through aggregates"),
+        "{}",
+        db_err.message()
+    );
+
+    let entries = server.audit_entries("agg_rows_user", 1).await;
+    assert_eq!(entries[0]["status"].as_str(), Some("denied"));
+}
+
+#[tokio::test]
+async fn joins_and_unions_cannot_inflate_groups() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "agg_only_inflate";
+    setup(&server, schema, "ds_agg_inflate", "agg_inflate_user").await;
+
+    let client = server
+        .connect_as("agg_inflate_user", TEST_PASS, "ds_agg_inflate")
+        .await;
+    for sql in [
+        format!(
+            "SELECT id, COUNT(*) FROM {schema}.patients CROSS JOIN generate_series(1, 10) GROUP BY id"
+        ),
+        format!(
+            "SELECT p.id, COUNT(*) FROM {schema}.patients p JOIN {schema}.patients q ON p.zip = q.zip GROUP BY p.id"
+        ),
+        format!(
+            "SELECT id, COUNT(*) FROM (SELECT id FROM {schema}.patients
+                 UNION ALL SELECT id FROM {schema}.patients
+                 UNION ALL SELECT id FROM {schema}.patients) u GROUP BY id"
+        ),
+    ] {
+        let err = client
+            .simple_query(&sql)
+            .await
+            .expect_err("inflated groups must be rejected");
+        let db_err = err.as_db_error().expect("Expected a DB error");
+        assert_eq!(db_err.code().code(), "42501", "{sql}");
+        assert!(db_err.message().contains("through"), "{}", db_err.message());
+    }
+
+    // Joining an already-thresholded aggregate is still allowed.
+    let rows = client
+        .simple_query(&format!(
+            "SELECT g.zip, g.n FROM (SELECT zip, COUNT(*) AS n FROM {schema}.patients GROUP BY zip) g
+             CROSS JOIN generate_series(1, 2) ORDER BY g.zip"
+        ))
+        .await
+        .unwrap();
+    assert_eq!(support::extract_rows(&rows).len(), 4);
+}
@@ -0,0 +1,173 @@
+//! Catalog tag integration tests.
+//!
+//! These tests verify that tags set via `PUT /datasources/{id}/catalog/tags`
+//! flow down from schema to table to column, and that policies targeting
+//! `tag:` selectors pick up newly tagged columns without a policy edit.
+//! Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+async fn set_tags(
+    server: &support::ProxyTestServer,
+    ds_id: uuid::Uuid,
+    body: Value,
+) -> axum_test::TestResponse {
+    server
+        .admin
+        .put(&format!("/api/v1/datasources/{ds_id}/catalog/tags"))
+        .authorization_bearer(&server.admin_token)
+        .json(&body)
+        .await
+}
+
+#[tokio::test]
+async fn tagged_columns_are_masked_without_policy_edits() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "tags_mask";
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.customers;
+             CREATE TABLE {schema}.customers (id INT, email TEXT, phone TEXT);
+             INSERT INTO {schema}.customers VALUES (1, 'a@x.io', '555-0100');"
+        ))
+        .await;
+    let ds_id = server.create_datasource("ds_tags_mask", "open").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user("tags_alice", TEST_PASS, ds_id).await;
+
+    let resp = set_tags(
+        &server,
+        ds_id,
+        json!({"schema": schema, "table": "customers", "column": "email", "tags": ["pii"]}),
+    )
+    .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["effective_tags"], json!(["pii"]));
+
+    server
+        .create_column_mask(
+            "mask-pii",
+            "*",
+            "*",
+            "tag:pii",
+            "'***'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+
+    let query = format!("SELECT id, email, phone FROM {schema}.customers");
+    let client = server
+        .connect_as("tags_alice", TEST_PASS, "ds_tags_mask")
+        .await;
+    let rows = client.query(&query, &[]).await.unwrap();
+    assert_eq!(rows[0].get::<_, String>(1), "***");
+    assert_eq!(rows[0].get::<_, String>(2), "555-0100");
+
+    // Tagging another column is enough — the policy is unchanged.
+    set_tags(
+        &server,
+        ds_id,
+        json!({"schema": schema, "table": "customers", "column": "phone", "tags": ["pii"]}),
+    )
+    .await
+    .assert_status_ok();
+    let client = server
+        .connect_as("tags_alice", TEST_PASS, "ds_tags_mask")
+        .await;
+    let rows = client.query(&query, &[]).await.unwrap();
+    assert_eq!(rows[0].get::<_, String>(2), "***");
+}
+
+#[tokio::test]
+async fn table_tags_are_inherited_and_can_be_dropped_per_column() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "tags_inherit";
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.accounts;
+             CREATE TABLE {schema}.accounts (id INT, balance INT);
+             INSERT INTO {schema}.accounts VALUES (1, 100);"
+        ))
+        .await;
+    let ds_id = server.create_datasource("ds_tags_inherit", "open").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user("tags_bob", TEST_PASS, ds_id).await;
+
+    set_tags(
+        &server,
+        ds_id,
+        json!({"schema": schema, "tags": ["financial"]}),
+    )
+    .await
+    .assert_status_ok();
+    let resp = set_tags(
+        &server,
+        ds_id,
+        json!({"schema": schema, "table": "accounts", "column": "id", "tags": ["!financial"]}),
+    )
+    .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["effective_tags"], json!([]));
+
+    server
+        .create_column_deny(
+            "deny-financial",
+            "*",
+            "*",
+            &["tag:financial"],
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+
+    let client = server
+        .connect_as("tags_bob", TEST_PASS, "ds_tags_inherit")
+        .await;
+    let rows = client
+        .query(&format!("SELECT * FROM {schema}.accounts"), &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        rows[0].len(),
+        1,
+        "balance inherits 'financial' and is denied"
+    );
+    assert_eq!(rows[0].get::<_, i32>(0), 1);
+
+    let catalog = server
+        .admin
+        .get(&format!("/api/v1/datasources/{ds_id}/catalog"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    let columns = &catalog["schemas"][0]["tables"][0]["columns"];
+    let balance = columns
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["column_name"] == "balance")
+        .unwrap();
+    assert_eq!(balance["effective_tags"], json!(["financial"]));
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let ds_id = server.create_datasource("ds_tags_invalid", "open").await;
+    let resp = set_tags(
+        &server,
+        ds_id,
+        json!({"schema": "public", "tags": ["bad tag"]}),
+    )
+    .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,155 @@
+//! Differential privacy policy integration tests.
+//!
+//! These tests verify that a `differential_privacy` policy releases noised
+//! aggregates with their original column types, charges epsilon per noised
+//! aggregate to the user's budget (recorded as `epsilon_spent` in the audit
+//! log), and refuses queries with SQLSTATE 53400 once the budget is spent.
+//! Uses a real Postgres container.
+
+mod support;
+
+use serde_json::json;
+use support::TEST_PASS;
+use uuid::Uuid;
+
+/// 20 employees with salaries of 1000 each, under a policy with epsilon 1 and a
+/// budget of 3.
+async fn setup(
+    server: &support::ProxyTestServer,
+    schema: &str,
+    ds_name: &str,
+    username: &str,
+) -> (Uuid, Uuid) {
+    let (ds_id, user_id, _) = server
+        .setup_policy_fixture(
+            &format!(
+                "CREATE SCHEMA IF NOT EXISTS {schema};
+                 DROP TABLE IF EXISTS {schema}.employees;
+                 CREATE TABLE {schema}.employees (id INT, dept TEXT, salary INT);
+                 INSERT INTO {schema}.employees
+                     SELECT i, CASE WHEN i % 2 = 0 THEN 'eng' ELSE 'ops' END, 1000
+                     FROM generate_series(1, 20) AS i;"
+            ),
+            schema,
+            ds_name,
+            username,
+            &format!("{schema}-dp"),
+            "differential_privacy",
+            json!({"schemas": [schema], "tables": ["employees"]}),
+            Some(json!({"epsilon": 1.0, "budget": 3.0, "bounds": {"salary": [0, 2000]}})),
+        )
+        .await;
+    (ds_id, user_id)
+}
+
+#[tokio::test]
+async fn noised_aggregates_stay_close_to_true_values() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "dp_values";
+    setup(&server, schema, "ds_dp_values", "dp_values_user").await;
+
+    let client = server
+        .connect_as("dp_values_user", TEST_PASS, "ds_dp_values")
+        .await;
+    let rows = client
+        .simple_query(&format!(
+            "SELECT count(*) AS n, sum(salary) AS total FROM {schema}.employees"
+        ))
+        .await
+        .unwrap();
+    let rows = support::extract_rows(&rows);
+    assert_eq!(rows.len(), 1);
+    // Both columns keep their integer types, so they parse as integers.
+    let n: i64 = rows[0][0].parse().expect("count stays an integer");
+    let total: i64 = rows[0][1].parse().expect("sum of INT stays an integer");
+    // Laplace scales are 1 (count) and 2000 (sum); the bounds below are
+    // exceeded with negligible probability.
+    assert!((n - 20).abs() <= 40, "noisy count {n} too far from 20");
+    assert!(
+        (total - 20_000).abs() <= 80_000,
+        "noisy sum {total} too far from 20000"
+    );
+
+    let err = client
+        .simple_query(&format!("SELECT * FROM {schema}.employees"))
+        .await
+        .expect_err("row-level read must be rejected");
+    let db_err = err.as_db_error().expect("Expected a DB error");
+    assert_eq!(db_err.code().code(), "42501");
+}
+
+#[tokio::test]
+async fn budget_is_charged_and_exhausted() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "dp_budget";
+    let (ds_id, user_id) = setup(&server, schema, "ds_dp_budget", "dp_budget_user").await;
+
+    let client = server
+        .connect_as("dp_budget_user", TEST_PASS, "ds_dp_budget")
+        .await;
+    // Two noised aggregates spend 2 of the budget of 3, one more spends the rest.
+    client
+        .simple_query(&format!(
+            "SELECT dept, count(*), avg(salary) FROM {schema}.employees GROUP BY dept"
+        ))
+        .await
+        .unwrap();
+    client
+        .simple_query(&format!("SELECT count(*) FROM {schema}.employees"))
+        .await
+        .unwrap();
+
+    let err = client
+        .simple_query(&format!("SELECT count(*) FROM {schema}.employees"))
+        .await
+        .expect_err("query past the budget must be refused");
+    let db_err = err.as_db_error().expect("Expected a DB error");
+    assert_eq!(db_err.code().code(), "53400");
+    assert!(
+        db_err.message().contains("Privacy budget exhausted"),
+        "{}",
+        db_err.message()
+    );
+
+    let entries = server.audit_entries("dp_budget_user", 3).await;
+    let mut spent: Vec<Option<f64>> = entries
+        .iter()
+        .filter(|e| e["status"].as_str() == Some("success"))
+        .map(|e| e["epsilon_spent"].as_f64())
+        .collect();
+    spent.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(spent, vec![Some(1.0), Some(2.0)]);
+    assert!(
+        entries
+            .iter()
+            .any(|e| e["status"].as_str() == Some("denied")),
+        "the refused query must be audited as denied"
+    );
+
+    let resp = server
+        .admin
+        .get(&format!("/api/v1/datasources/{ds_id}/privacy-budgets"))
+        .authorization_bearer(&server.admin_token)
+        .await;
+    resp.assert_status_ok();
+    let budgets = resp.json::<serde_json::Value>();
+    assert_eq!(budgets[0]["username"].as_str(), Some("dp_budget_user"));
+    assert_eq!(budgets[0]["epsilon_spent"].as_f64(), Some(3.0));
+    assert_eq!(budgets[0]["query_count"].as_i64(), Some(2));
+
+    // Resetting the budget lets the user query again.
+    server
+        .admin
+        .delete(&format!(
+            "/api/v1/datasources/{ds_id}/privacy-budgets/{user_id}"
+        ))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    client
+        .simple_query(&format!("SELECT count(*) FROM {schema}.employees"))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,116 @@
+//! `EXPLAIN (POLICY)` integration tests.
+//!
+//! These tests verify that `EXPLAIN (POLICY)` reports the policies on a query's
+//! tables without running it, that `BR_EXPLAIN_POLICY=summary` hides filter
+//! expressions and the names of removed columns from non-admin users (security
+//! vector 61), and that `off` refuses the statement. Uses a real Postgres container.
+
+mod support;
+
+use proxy::hooks::policy::ExplainLevel;
+use support::TEST_PASS;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn setup(server: &support::ProxyTestServer, schema: &str, ds_name: &str) -> uuid::Uuid {
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.customers;
+             CREATE TABLE {schema}.customers (id INT, tenant TEXT, name TEXT, ssn TEXT);
+             INSERT INTO {schema}.customers VALUES (1, 'acme', 'Ann', '111'), (2, 'globex', 'Bob', '222');"
+        ))
+        .await;
+    let ds_id = server.create_datasource(ds_name, "open").await;
+    server.discover(ds_id, &[schema]).await;
+    ds_id
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn non_admin_summary_hides_filter_and_column_names() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "explain_summary";
+    let ds_id = setup(&server, schema, "ds_explain_summary").await;
+    let user_id = server.create_user("explain_alice", TEST_PASS, ds_id).await;
+    server
+        .create_row_filter(
+            "tenant-filter",
+            schema,
+            "customers",
+            "tenant = 'acme'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+    server
+        .create_column_deny(
+            "hide-pii",
+            schema,
+            "customers",
+            &["ssn"],
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+
+    let client = server
+        .connect_as("explain_alice", TEST_PASS, "ds_explain_summary")
+        .await;
+    let msgs = client
+        .simple_query(&format!(
+            "EXPLAIN (POLICY) SELECT * FROM {schema}.customers"
+        ))
+        .await
+        .unwrap();
+    let rows = support::extract_rows(&msgs);
+
+    let table = format!("{schema}.customers");
+    let effect = |policy: &str| {
+        rows.iter()
+            .find(|r| r[2] == policy)
+            .unwrap_or_else(|| panic!("no row for {policy}: {rows:?}"))
+    };
+    let filter = effect("tenant-filter");
+    assert_eq!(filter[0], table);
+    assert_eq!(filter[4], "filtered");
+    let removed = effect("hide-pii");
+    assert_eq!(removed[4], "removed");
+    assert_eq!(removed[5], "1 column");
+    assert!(
+        rows.iter()
+            .flatten()
+            .all(|v| !v.contains("acme") && !v.contains("ssn")),
+        "summary must not show the filter or the removed column: {rows:?}"
+    );
+}
+
+#[tokio::test]
+async fn explain_off_is_refused() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start_with_explain_level(ExplainLevel::Off).await;
+    let schema = "explain_off";
+    let ds_id = setup(&server, schema, "ds_explain_off").await;
+    server.create_user("explain_bob", TEST_PASS, ds_id).await;
+
+    let client = server
+        .connect_as("explain_bob", TEST_PASS, "ds_explain_off")
+        .await;
+    let err = client
+        .simple_query(&format!(
+            "EXPLAIN (POLICY) SELECT * FROM {schema}.customers"
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.code().map(|c| c.code()),
+        Some("42501"),
+        "unexpected error: {err}"
+    );
+}
@@ -0,0 +1,238 @@
+//! Arrow Flight SQL integration tests.
+//!
+//! These tests verify that the Flight SQL front-end enforces the same
+//! authentication, datasource access, policies and auditing as pgwire,
+//! using a real Postgres container.
+
+mod support;
+
+use arrow_flight::sql::client::FlightSqlServiceClient;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::util::pretty::pretty_format_batches;
+use futures::TryStreamExt;
+use support::TEST_PASS;
+use tonic::transport::Channel;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Seed `{schema}.orders` and create an open-mode datasource with one assigned user.
+async fn setup_datasource(
+    server: &support::ProxyTestServer,
+    schema: &str,
+    ds_name: &str,
+    username: &str,
+) -> (uuid::Uuid, uuid::Uuid) {
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.orders;
+             CREATE TABLE {schema}.orders (id INT, tenant TEXT, email TEXT);
+             INSERT INTO {schema}.orders VALUES
+               (1, 'acme', 'a@acme.com'),
+               (2, 'globex', 'b@globex.com'),
+               (3, 'acme', 'c@acme.com');"
+        ))
+        .await;
+
+    let ds_id = server.create_datasource(ds_name, "open").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user(username, TEST_PASS, ds_id).await;
+    (ds_id, user_id)
+}
+
+/// Run a statement over Flight SQL and collect every endpoint's batches.
+async fn flight_query(
+    client: &mut FlightSqlServiceClient<Channel>,
+    sql: &str,
+) -> Result<Vec<RecordBatch>, datafusion::arrow::error::ArrowError> {
+    let info = client.execute(sql.to_string(), None).await?;
+    let mut batches = Vec::new();
+    for endpoint in info.endpoint {
+        let ticket = endpoint.ticket.expect("endpoint without ticket");
+        let stream = client.do_get(ticket).await?;
+        let mut got: Vec<RecordBatch> = stream
+            .try_collect()
+            .await
+            .map_err(|e| datafusion::arrow::error::ArrowError::ExternalError(Box::new(e)))?;
+        batches.append(&mut got);
+    }
+    Ok(batches)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn flight_enforces_row_filter_and_mask() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "fl_enforce";
+    let (ds_id, user_id) = setup_datasource(&server, schema, "ds_fl_enforce", "fl_alice").await;
+
+    server
+        .create_row_filter(
+            "fl-tenant",
+            schema,
+            "orders",
+            "tenant = 'acme'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+    server
+        .create_column_mask(
+            "fl-mask-email",
+            schema,
+            "orders",
+            "email",
+            "'***'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+
+    let mut client = server
+        .flight_connect_as("fl_alice", TEST_PASS, "ds_fl_enforce")
+        .await
+        .unwrap();
+    let batches = flight_query(
+        &mut client,
+        &format!("SELECT id, tenant, email FROM {schema}.orders ORDER BY id"),
+    )
+    .await
+    .unwrap();
+
+    let rendered = pretty_format_batches(&batches).unwrap().to_string();
+    assert!(rendered.contains("| 1  | acme   | ***   |"), "{rendered}");
+    assert!(rendered.contains("| 3  | acme   | ***   |"), "{rendered}");
+    assert!(
+        !rendered.contains("globex"),
+        "row filter leaked: {rendered}"
+    );
+    assert!(!rendered.contains("@acme.com"), "mask leaked: {rendered}");
+
+    let entry = server.audit_entries("fl_alice", 1).await.remove(0);
+    assert_eq!(entry["status"].as_str(), Some("success"));
+    assert_eq!(entry["client_info"].as_str(), Some("flight-sql"));
+    assert!(
+        !entry["rewritten_query"].as_str().unwrap_or("").is_empty(),
+        "rewritten_query must be recorded when policies apply"
+    );
+}
+
+#[tokio::test]
+async fn flight_table_deny_is_denied_and_audited() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "fl_deny";
+    let (ds_id, user_id) = setup_datasource(&server, schema, "ds_fl_deny", "fl_bob").await;
+    server
+        .create_table_deny("fl-deny", schema, "orders", ds_id, Some(user_id))
+        .await;
+
+    let mut client = server
+        .flight_connect_as("fl_bob", TEST_PASS, "ds_fl_deny")
+        .await
+        .unwrap();
+    let result = flight_query(&mut client, &format!("SELECT * FROM {schema}.orders")).await;
+    assert!(result.is_err(), "query on denied table must fail");
+
+    let entry = server.audit_entries("fl_bob", 1).await.remove(0);
+    assert_ne!(entry["status"].as_str(), Some("success"));
+}
+
+#[tokio::test]
+async fn flight_write_is_rejected_and_audited() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "fl_write";
+    setup_datasource(&server, schema, "ds_fl_write", "fl_carol").await;
+
+    let mut client = server
+        .flight_connect_as("fl_carol", TEST_PASS, "ds_fl_write")
+        .await
+        .unwrap();
+    let err = flight_query(&mut client, &format!("DELETE FROM {schema}.orders"))
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("read-only"),
+        "unexpected error: {err}"
+    );
+
+    let entry = server.audit_entries("fl_carol", 1).await.remove(0);
+    assert_eq!(entry["status"].as_str(), Some("denied"));
+    assert_eq!(entry["client_info"].as_str(), Some("flight-sql"));
+}
+
+#[tokio::test]
+async fn flight_handshake_rejects_bad_credentials_and_unassigned_users() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "fl_auth";
+    setup_datasource(&server, schema, "ds_fl_auth", "fl_dave").await;
+    server.create_user_unassigned("fl_eve", TEST_PASS).await;
+
+    assert!(
+        server
+            .flight_connect_as("fl_dave", "wrong-password", "ds_fl_auth")
+            .await
+            .is_err(),
+        "wrong password must fail the handshake"
+    );
+    assert!(
+        server
+            .flight_connect_as("fl_eve", TEST_PASS, "ds_fl_auth")
+            .await
+            .is_err(),
+        "unassigned user must fail the handshake"
+    );
+    assert!(
+        server
+            .flight_connect_as("fl_dave", TEST_PASS, "no_such_ds")
+            .await
+            .is_err(),
+        "unknown datasource must fail the handshake"
+    );
+}
+
+#[tokio::test]
+async fn flight_ticket_is_bound_to_session_and_single_use() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "fl_ticket";
+    let (ds_id, _) = setup_datasource(&server, schema, "ds_fl_ticket", "fl_frank").await;
+    server.create_user("fl_grace", TEST_PASS, ds_id).await;
+
+    let mut frank = server
+        .flight_connect_as("fl_frank", TEST_PASS, "ds_fl_ticket")
+        .await
+        .unwrap();
+    let mut grace = server
+        .flight_connect_as("fl_grace", TEST_PASS, "ds_fl_ticket")
+        .await
+        .unwrap();
+
+    let info = frank
+        .execute(format!("SELECT id FROM {schema}.orders"), None)
+        .await
+        .unwrap();
+    let ticket = info.endpoint[0].ticket.clone().unwrap();
+
+    // Another session cannot redeem it.
+    assert!(grace.do_get(ticket.clone()).await.is_err());
+
+    // The owning session can, exactly once.
+    let batches: Vec<RecordBatch> = frank
+        .do_get(ticket.clone())
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+    assert!(frank.do_get(ticket).await.is_err());
+}
@@ -0,0 +1,183 @@
+//! Impersonation preview integration tests.
+//!
+//! These tests verify that `POST /impersonate` runs a query under a stored
+//! user's policies or a hypothetical user's roles and attributes, returns the
+//! rewritten SQL and visible catalog alongside the rows, and records the
+//! preview in both audit logs. Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn setup(server: &support::ProxyTestServer, schema: &str, ds_name: &str) -> uuid::Uuid {
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.orders;
+             CREATE TABLE {schema}.orders (id INT, tenant TEXT, amount INT);
+             INSERT INTO {schema}.orders VALUES (1, 'acme', 100), (2, 'globex', 200), (3, 'acme', 300);"
+        ))
+        .await;
+    let ds_id = server.create_datasource(ds_name, "open").await;
+    server.discover(ds_id, &[schema]).await;
+    server
+        .create_attribute_definition("tenant", "user", "string", None)
+        .await;
+    server
+        .create_row_filter(
+            "tenant-filter",
+            schema,
+            "orders",
+            "tenant = {user.tenant}",
+            ds_id,
+            None,
+        )
+        .await;
+    ds_id
+}
+
+async fn impersonate(server: &support::ProxyTestServer, body: Value) -> Value {
+    let resp = server
+        .admin
+        .post("/api/v1/impersonate")
+        .authorization_bearer(&server.admin_token)
+        .json(&body)
+        .await;
+    resp.assert_status_ok();
+    resp.json::<Value>()
+}
+
+/// The query audit row for `username`, waiting for the asynchronous write.
+async fn query_audit_entry(server: &support::ProxyTestServer, username: &str) -> Value {
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let body = server
+            .admin
+            .get("/api/v1/audit/queries")
+            .authorization_bearer(&server.admin_token)
+            .await
+            .json::<Value>();
+        if let Some(entry) = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["username"] == username)
+        {
+            return entry.clone();
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "no query audit row for {username} within 5s"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
+fn ids(body: &Value) -> Vec<i64> {
+    body["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].as_i64().unwrap())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn preview_as_stored_user_applies_their_policies_and_is_audited() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "impersonate_stored";
+    let ds_id = setup(&server, schema, "ds_impersonate_stored").await;
+    let user_id = server
+        .create_user("impersonate_alice", TEST_PASS, ds_id)
+        .await;
+    server
+        .set_user_attributes(user_id, json!({"tenant": "acme"}))
+        .await;
+
+    let body = impersonate(
+        &server,
+        json!({
+            "datasource": "ds_impersonate_stored",
+            "sql": format!("SELECT id FROM {schema}.orders ORDER BY id"),
+            "user_id": user_id,
+            "max_rows": 1,
+        }),
+    )
+    .await;
+
+    assert_eq!(body["error"], Value::Null, "{body}");
+    assert_eq!(body["username"], "impersonate_alice");
+    assert_eq!(ids(&body), vec![1], "capped at max_rows: {body}");
+    assert_eq!(body["truncated"], true);
+    assert!(
+        body["rewritten_query"].as_str().unwrap().contains("acme"),
+        "{body}"
+    );
+    assert_eq!(body["catalog"][0]["name"], schema);
+    assert_eq!(body["catalog"][0]["tables"][0]["name"], "orders");
+
+    let entry = query_audit_entry(&server, "impersonate_alice").await;
+    assert!(entry["impersonated_by"].is_string(), "{entry}");
+
+    let admin_audit = server
+        .admin
+        .get("/api/v1/audit/admin")
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    assert!(
+        admin_audit["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|e| e["action"] == "impersonate" && e["resource_id"] == user_id.to_string()),
+        "no impersonate entry: {admin_audit}"
+    );
+}
+
+#[tokio::test]
+async fn preview_as_hypothetical_user_uses_given_roles_and_attributes() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "impersonate_hypothetical";
+    let ds_id = setup(&server, schema, "ds_impersonate_hypothetical").await;
+    let role_id = server.create_role("impersonate_analysts").await;
+    server.set_datasource_role_access(ds_id, &[role_id]).await;
+
+    let body = impersonate(
+        &server,
+        json!({
+            "datasource": "ds_impersonate_hypothetical",
+            "sql": format!("SELECT id FROM {schema}.orders ORDER BY id"),
+            "role_ids": [role_id],
+            "attributes": {"tenant": "globex"},
+        }),
+    )
+    .await;
+
+    assert_eq!(body["error"], Value::Null, "{body}");
+    assert_eq!(body["hypothetical"], true);
+    assert_eq!(body["roles"], json!(["impersonate_analysts"]));
+    assert_eq!(ids(&body), vec![2]);
+
+    let resp = server
+        .admin
+        .post("/api/v1/impersonate")
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({
+            "datasource": "ds_impersonate_hypothetical",
+            "sql": format!("SELECT id FROM {schema}.orders"),
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+}
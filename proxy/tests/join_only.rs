@@ -0,0 +1,111 @@
+//! Join-only (clean-room join key) policy integration tests.
+//!
+//! These tests verify that a `join_only` column can match a partner table in a
+//! join, and that returning, probing or aggregating it is rejected with SQLSTATE
+//! 42501 and audited as `denied`. Uses a real Postgres container.
+
+mod support;
+
+use serde_json::json;
+use support::TEST_PASS;
+
+async fn setup(server: &support::ProxyTestServer, schema: &str, ds_name: &str, username: &str) {
+    server
+        .setup_policy_fixture(
+            &format!(
+                "CREATE SCHEMA IF NOT EXISTS {schema};
+                 DROP TABLE IF EXISTS {schema}.customers;
+                 DROP TABLE IF EXISTS {schema}.partner_audience;
+                 CREATE TABLE {schema}.customers (id INT, email_hash TEXT, region TEXT);
+                 CREATE TABLE {schema}.partner_audience (email_hash TEXT, segment TEXT);
+                 INSERT INTO {schema}.customers VALUES
+                     (1, 'h1', 'eu'), (2, 'h2', 'eu'), (3, 'h3', 'us'), (4, 'h4', 'us');
+                 INSERT INTO {schema}.partner_audience VALUES
+                     ('h1', 'sports'), ('h3', 'sports'), ('h4', 'travel'), ('h9', 'travel');"
+            ),
+            schema,
+            ds_name,
+            username,
+            &format!("{schema}-blind-email"),
+            "join_only",
+            json!({"schemas": [schema], "tables": ["customers"], "columns": ["email_hash"]}),
+            None,
+        )
+        .await;
+}
+
+#[tokio::test]
+async fn join_key_matches_partner_rows() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "join_only_match";
+    setup(&server, schema, "ds_join_match", "join_match_user").await;
+
+    let client = server
+        .connect_as("join_match_user", TEST_PASS, "ds_join_match")
+        .await;
+    let rows = client
+        .simple_query(&format!(
+            "SELECT p.segment, c.region, COUNT(*) FROM {schema}.customers c
+             JOIN {schema}.partner_audience p ON c.email_hash = p.email_hash
+             GROUP BY p.segment, c.region ORDER BY p.segment, c.region"
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        support::extract_rows(&rows),
+        vec![
+            vec!["sports".to_string(), "eu".to_string(), "1".to_string()],
+            vec!["sports".to_string(), "us".to_string(), "1".to_string()],
+            vec!["travel".to_string(), "us".to_string(), "1".to_string()],
+        ]
+    );
+}
+
+#[tokio::test]
+async fn exposing_the_key_is_denied_and_audited() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "join_only_expose";
+    setup(&server, schema, "ds_join_expose", "join_expose_user").await;
+
+    let client = server
+        .connect_as("join_expose_user", TEST_PASS, "ds_join_expose")
+        .await;
+    for (sql, reason) in [
+        (
+            format!("SELECT id, email_hash FROM {schema}.customers"),
+            "cannot be returned",
+        ),
+        (
+            format!("SELECT id FROM {schema}.customers WHERE email_hash = 'h1'"),
+            "can only be compared for equality with a column of another table",
+        ),
+        (
+            format!("SELECT COUNT(DISTINCT email_hash) FROM {schema}.customers"),
+            "cannot be aggregated or grouped on",
+        ),
+        (
+            format!(
+                "SELECT p.email_hash FROM {schema}.customers c
+                 JOIN {schema}.partner_audience p ON c.email_hash = p.email_hash"
+            ),
+            "cannot be returned",
+        ),
+    ] {
+        let err = client
+            .simple_query(&sql)
+            .await
+            .expect_err("exposing a join-only column must be rejected");
+        let db_err = err.as_db_error().expect("Expected a DB error");
+        assert_eq!(db_err.code().code(), "42501", "{sql}");
+        assert!(
+            db_err.message().contains(reason),
+            "{sql}: {}",
+            db_err.message()
+        );
+    }
+
+    let entries = server.audit_entries("join_expose_user", 1).await;
+    assert_eq!(entries[0]["status"].as_str(), Some("denied"));
+}
@@ -0,0 +1,130 @@
+//! Built-in masking function integration tests.
+//!
+//! These tests verify that the `br_*` UDFs work inside `column_mask` policies
+//! end to end, that keyed functions resolve masking keys created through the
+//! admin API, that users cannot call keyed functions directly, and that FPE
+//! output can be revealed only under a reversible key.
+//! Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+async fn create_key(server: &support::ProxyTestServer, name: &str, reversible: bool) -> String {
+    let resp = server
+        .admin
+        .post("/api/v1/masking-keys")
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"name": name, "reversible": reversible}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let body = resp.json::<Value>();
+    assert!(
+        body.get("secret").is_none(),
+        "key material is never returned"
+    );
+    body["id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn br_functions_mask_columns_and_fpe_is_reversible_by_choice() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "br_udf";
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.patients;
+             CREATE TABLE {schema}.patients (
+                 id INT, email TEXT, ssn TEXT, age INT, admitted DATE
+             );
+             INSERT INTO {schema}.patients VALUES
+                 (1, 'alice@example.com', '123-45-6789', 37, '2024-03-10');"
+        ))
+        .await;
+    let ds_id = server.create_datasource("ds_br_udf", "open").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user("br_alice", TEST_PASS, ds_id).await;
+
+    let fpe_key = create_key(&server, "ssn_fpe", true).await;
+    let salt = create_key(&server, "email_salt", false).await;
+
+    for (name, column, mask) in [
+        ("br-email", "email", "br_hash({col}, 'email_salt')"),
+        ("br-ssn", "ssn", "br_fpe_encrypt({col}, 'ssn_fpe')"),
+        ("br-age", "age", "br_generalize_number({col}, 10)"),
+        (
+            "br-admitted",
+            "admitted",
+            "br_shift_date({col}, CAST(id AS TEXT), 30)",
+        ),
+    ] {
+        server
+            .create_column_mask(name, schema, "patients", column, mask, ds_id, Some(user_id))
+            .await;
+    }
+
+    let client = server.connect_as("br_alice", TEST_PASS, "ds_br_udf").await;
+    let rows = client
+        .simple_query(&format!(
+            "SELECT email, ssn, age, admitted FROM {schema}.patients"
+        ))
+        .await
+        .unwrap();
+    let rows = support::extract_rows(&rows);
+    let (email, ssn, age, admitted) = (&rows[0][0], &rows[0][1], &rows[0][2], &rows[0][3]);
+
+    assert_eq!(email.len(), 64, "hex HMAC-SHA256: {email}");
+    assert_ne!(ssn, "123-45-6789");
+    assert_eq!(ssn.len(), 11);
+    assert_eq!(
+        (&ssn[3..4], &ssn[6..7]),
+        ("-", "-"),
+        "format preserved: {ssn}"
+    );
+    assert_eq!(age, "30");
+    assert_ne!(admitted, "2024-03-10");
+
+    // Keyed functions refuse to run from user SQL.
+    let err = client
+        .simple_query("SELECT br_hash('alice@example.com', 'email_salt')")
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{err:?}").contains("can only be used in column mask policies"),
+        "{err:?}"
+    );
+    // Keyless ones are fine.
+    let ok = client
+        .simple_query("SELECT br_mask_email('bob@corp.io')")
+        .await
+        .unwrap();
+    assert_eq!(support::extract_rows(&ok)[0][0], "b***@corp.io");
+
+    // FPE output is revealed only under a reversible key.
+    let resp = server
+        .admin
+        .post(&format!("/api/v1/masking-keys/{fpe_key}/reveal"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"values": [ssn]}))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["values"], json!(["123-45-6789"]));
+
+    server
+        .admin
+        .post(&format!("/api/v1/masking-keys/{salt}/reveal"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"values": [email]}))
+        .await
+        .assert_status(axum::http::StatusCode::FORBIDDEN);
+
+    // Keys referenced by policies cannot be deleted.
+    server
+        .admin
+        .delete(&format!("/api/v1/masking-keys/{salt}"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+}
@@ -0,0 +1,225 @@
+//! MCP streamable-HTTP integration tests.
+//!
+//! These tests verify that `POST /api/v1/mcp` runs agent queries under the
+//! caller's own policies, audits them as `mcp:<agent>`, and only exposes admin
+//! tools to admin keys carrying the matching scope. Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn create_api_key(
+    server: &support::ProxyTestServer,
+    user_id: uuid::Uuid,
+    scopes: &[&str],
+) -> String {
+    let resp = server
+        .admin
+        .post(&format!("/api/v1/users/{user_id}/api-keys"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"name": "agent", "scopes": scopes}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    resp.json::<Value>()["api_key"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+/// Send one JSON-RPC request; returns the session header (if any) and the body.
+async fn rpc(
+    server: &support::ProxyTestServer,
+    key: &str,
+    session: Option<&str>,
+    id: u64,
+    method: &str,
+    params: Value,
+) -> (Option<String>, Value) {
+    let mut req = server
+        .admin
+        .post("/api/v1/mcp")
+        .authorization_bearer(key)
+        .json(&json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}));
+    if let Some(session) = session {
+        req = req.add_header("mcp-session-id", session);
+    }
+    let resp = req.await;
+    resp.assert_status_ok();
+    let session = resp
+        .maybe_header("mcp-session-id")
+        .map(|v| v.to_str().unwrap().to_string());
+    (session, resp.json::<Value>())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn mcp_query_tools_enforce_policies_and_audit_agent() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "mcp_enforce";
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.orders;
+             CREATE TABLE {schema}.orders (id INT, tenant TEXT);
+             INSERT INTO {schema}.orders VALUES (1, 'acme'), (2, 'globex'), (3, 'acme');"
+        ))
+        .await;
+    let ds_id = server.create_datasource("ds_mcp_enforce", "open").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user("mcp_alice", TEST_PASS, ds_id).await;
+    server
+        .create_row_filter(
+            "mcp-tenant",
+            schema,
+            "orders",
+            "tenant = 'acme'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+    let key = create_api_key(&server, user_id, &[]).await;
+
+    let (session, init) = rpc(
+        &server,
+        &key,
+        None,
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": "2025-06-18",
+            "capabilities": {},
+            "clientInfo": {"name": "test-agent", "version": "1.0"},
+        }),
+    )
+    .await;
+    assert_eq!(init["result"]["serverInfo"]["name"], "betweenrows");
+    let session = session.expect("initialize must return a session id");
+
+    let (_, list) = rpc(
+        &server,
+        &key,
+        Some(&session),
+        2,
+        "tools/call",
+        json!({
+            "name": "list_datasources", "arguments": {},
+        }),
+    )
+    .await;
+    assert_eq!(
+        list["result"]["structuredContent"]["datasources"],
+        json!([{"name": "ds_mcp_enforce", "type": "postgres"}])
+    );
+
+    let (_, result) = rpc(
+        &server,
+        &key,
+        Some(&session),
+        3,
+        "tools/call",
+        json!({
+            "name": "execute_query",
+            "arguments": {
+                "datasource": "ds_mcp_enforce",
+                "sql": format!("SELECT id FROM {schema}.orders ORDER BY id"),
+            },
+        }),
+    )
+    .await;
+    let content = &result["result"];
+    assert_eq!(content["isError"], false, "{result}");
+    assert_eq!(
+        content["structuredContent"]["rows"],
+        json!([{"id": 1}, {"id": 3}])
+    );
+
+    let entry = server.audit_entries("mcp_alice", 1).await.remove(0);
+    assert_eq!(entry["client_info"].as_str(), Some("mcp:test-agent"));
+    assert_eq!(entry["status"].as_str(), Some("success"));
+
+    // Writes come back as tool errors, not protocol errors.
+    let (_, result) = rpc(
+        &server,
+        &key,
+        Some(&session),
+        4,
+        "tools/call",
+        json!({
+            "name": "execute_query",
+            "arguments": {
+                "datasource": "ds_mcp_enforce",
+                "sql": format!("DELETE FROM {schema}.orders"),
+            },
+        }),
+    )
+    .await;
+    assert_eq!(result["result"]["isError"], true);
+}
+
+#[tokio::test]
+async fn mcp_admin_tools_require_scoped_admin_key() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let admin_id = server.create_user_unassigned("mcp_admin", TEST_PASS).await;
+    server
+        .admin
+        .put(&format!("/api/v1/users/{admin_id}"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"is_admin": true}))
+        .await
+        .assert_status_ok();
+
+    let unscoped = create_api_key(&server, admin_id, &[]).await;
+    let (_, tools) = rpc(&server, &unscoped, None, 1, "tools/list", json!({})).await;
+    assert!(
+        tools["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|t| !t["name"].as_str().unwrap().starts_with("admin_"))
+    );
+
+    let scoped = create_api_key(&server, admin_id, &["admin:audit"]).await;
+    let (_, result) = rpc(
+        &server,
+        &scoped,
+        None,
+        2,
+        "tools/call",
+        json!({
+            "name": "admin_search_admin_audit",
+            "arguments": {"query": {"resource_type": "api_key"}},
+        }),
+    )
+    .await;
+    assert_eq!(result["result"]["isError"], false, "{result}");
+    assert!(
+        result["result"]["structuredContent"]["total"]
+            .as_u64()
+            .unwrap()
+            >= 2
+    );
+
+    // The scope does not reach other admin areas.
+    let (_, result) = rpc(
+        &server,
+        &scoped,
+        None,
+        3,
+        "tools/call",
+        json!({
+            "name": "admin_list_policies", "arguments": {},
+        }),
+    )
+    .await;
+    assert_eq!(result["error"]["code"], -32602);
+}
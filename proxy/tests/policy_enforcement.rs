@@ -3369,6 +3369,77 @@ async fn row_filter_and_column_mask_same_column() {
     assert_eq!(rows[1][1], "***-**-4321", "ssn should be masked");
 }
 
+// Context-aware mask: the mask reads a sibling column the query does not
+// select, which stays out of the result; once that column is denied, the
+// masked column is NULL.
+#[tokio::test]
+async fn column_mask_reads_sibling_column() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "tc_fm02";
+
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.people;
+             CREATE TABLE {schema}.people (id INT, ssn TEXT, tenant TEXT);
+             INSERT INTO {schema}.people VALUES
+               (1, '123-45-6789', 'acme'),
+               (2, '987-65-4321', 'globex');"
+        ))
+        .await;
+
+    let ds_id = server.create_datasource("ds_fm02", "open").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user("user_fm02", "UserPass1!", ds_id).await;
+    server
+        .create_column_mask(
+            "mask-ssn-fm02",
+            schema,
+            "people",
+            "ssn",
+            "CASE WHEN tenant = 'acme' THEN ssn ELSE '***' END",
+            ds_id,
+            None,
+        )
+        .await;
+
+    let client = server
+        .connect_as("user_fm02", "UserPass1!", "ds_fm02")
+        .await;
+    let msgs = client
+        .simple_query(&format!("SELECT id, ssn FROM {schema}.people ORDER BY id"))
+        .await
+        .unwrap();
+    assert_eq!(
+        extract_rows(&msgs),
+        vec![vec!["1", "123-45-6789"], vec!["2", "***"]]
+    );
+
+    // Deny beats mask: the mask may not read a denied column.
+    server
+        .create_column_deny(
+            "deny-tenant-fm02",
+            schema,
+            "people",
+            &["tenant"],
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+    let client = server
+        .connect_as("user_fm02", "UserPass1!", "ds_fm02")
+        .await;
+    let msgs = client
+        .simple_query(&format!("SELECT id, ssn FROM {schema}.people ORDER BY id"))
+        .await
+        .unwrap();
+    assert_eq!(
+        extract_rows(&msgs),
+        vec![vec!["1", "NULL"], vec!["2", "NULL"]]
+    );
+}
+
 // ===========================================================================
 // RBAC Integration Tests
 // ===========================================================================
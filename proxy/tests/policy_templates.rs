@@ -0,0 +1,265 @@
+//! Policy template integration tests.
+//!
+//! These tests verify that `column_mask` policies backed by a template bind
+//! `{col}` and typed parameters at query time, that updating the template
+//! re-versions dependent policies and takes effect on the next session, and
+//! that the query audit log records which template version applied.
+//! Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+use uuid::Uuid;
+
+async fn create_template(server: &support::ProxyTestServer, body: Value) -> Value {
+    let resp = server
+        .admin
+        .post("/api/v1/policy-templates")
+        .authorization_bearer(&server.admin_token)
+        .json(&body)
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    resp.json::<Value>()
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn create_templated_mask(
+    server: &support::ProxyTestServer,
+    name: &str,
+    template_id: &str,
+    schema: &str,
+    column: &str,
+    params: Value,
+    ds_id: Uuid,
+    user_id: Uuid,
+) -> Uuid {
+    let resp = server
+        .admin
+        .post("/api/v1/policies")
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({
+            "name": name,
+            "policy_type": "column_mask",
+            "is_enabled": true,
+            "template_id": template_id,
+            "targets": [{"schemas": [schema], "tables": ["customers"], "columns": [column]}],
+            "definition": {"params": params},
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let policy_id: Uuid = resp.json::<Value>()["id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    server
+        .admin
+        .post(&format!("/api/v1/datasources/{ds_id}/policies"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"policy_id": policy_id, "user_id": user_id}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+    policy_id
+}
+
+async fn policy_version(server: &support::ProxyTestServer, policy_id: Uuid) -> i64 {
+    server
+        .admin
+        .get(&format!("/api/v1/policies/{policy_id}"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>()["version"]
+        .as_i64()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn template_masks_bind_column_and_params_and_updates_propagate() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "tmpl_mask";
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.customers;
+             CREATE TABLE {schema}.customers (id INT, ssn TEXT, phone TEXT);
+             INSERT INTO {schema}.customers VALUES (1, '123-45-6789', '555-0100');"
+        ))
+        .await;
+    let ds_id = server.create_datasource("ds_tmpl_mask", "open").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user("tmpl_alice", TEST_PASS, ds_id).await;
+
+    let template = create_template(
+        &server,
+        json!({
+            "name": "keep_last_n",
+            "template_type": "column_mask",
+            "expression": "{param.prefix} || RIGHT({col}, {param.n})",
+            "parameters": [
+                {"name": "prefix", "type": "string", "default": "***"},
+                {"name": "n", "type": "integer"}
+            ]
+        }),
+    )
+    .await;
+    let template_id = template["id"].as_str().unwrap().to_string();
+    assert_eq!(template["version"], 1);
+
+    let ssn_policy = create_templated_mask(
+        &server,
+        "mask-ssn",
+        &template_id,
+        schema,
+        "ssn",
+        json!({"n": 4}),
+        ds_id,
+        user_id,
+    )
+    .await;
+    create_templated_mask(
+        &server,
+        "mask-phone",
+        &template_id,
+        schema,
+        "phone",
+        json!({"n": 2, "prefix": "x-"}),
+        ds_id,
+        user_id,
+    )
+    .await;
+
+    let query = format!("SELECT id, ssn, phone FROM {schema}.customers");
+    let client = server
+        .connect_as("tmpl_alice", TEST_PASS, "ds_tmpl_mask")
+        .await;
+    let rows = client.query(&query, &[]).await.unwrap();
+    assert_eq!(rows[0].get::<_, String>(1), "***6789");
+    assert_eq!(rows[0].get::<_, String>(2), "x-00");
+
+    let before = policy_version(&server, ssn_policy).await;
+    let resp = server
+        .admin
+        .put(&format!("/api/v1/policy-templates/{template_id}"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({
+            "expression": "{param.prefix} || LEFT({col}, {param.n})",
+            "version": 1
+        }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["version"], 2);
+    assert_eq!(policy_version(&server, ssn_policy).await, before + 1);
+
+    let client = server
+        .connect_as("tmpl_alice", TEST_PASS, "ds_tmpl_mask")
+        .await;
+    let rows = client.query(&query, &[]).await.unwrap();
+    assert_eq!(rows[0].get::<_, String>(1), "***123-");
+
+    // Audit write is async — poll until the post-update entry appears.
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let body = server
+            .admin
+            .get("/api/v1/audit/queries")
+            .authorization_bearer(&server.admin_token)
+            .await
+            .json::<Value>();
+        let versions: Vec<i64> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|e| e["username"] == "tmpl_alice")
+            .flat_map(|e| {
+                e["policies_applied"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .filter_map(|p| p["template"]["version"].as_i64())
+            .collect();
+        if versions.contains(&2) {
+            assert!(versions.contains(&1), "pre-update queries keep version 1");
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "audit entry with template version 2 did not appear within 5s"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    // Referenced templates cannot be deleted.
+    server
+        .admin
+        .delete(&format!("/api/v1/policy-templates/{template_id}"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn template_updates_that_break_policies_are_rejected() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let template = create_template(
+        &server,
+        json!({
+            "name": "tenant_filter",
+            "template_type": "row_filter",
+            "expression": "tenant = {param.tenant}",
+            "parameters": [{"name": "tenant", "type": "string"}]
+        }),
+    )
+    .await;
+    let template_id = template["id"].as_str().unwrap();
+
+    // A missing required parameter is caught at policy create time.
+    server
+        .admin
+        .post("/api/v1/policies")
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({
+            "name": "tenant-a",
+            "policy_type": "row_filter",
+            "is_enabled": true,
+            "template_id": template_id,
+            "targets": [{"schemas": ["*"], "tables": ["*"]}],
+            "definition": {"params": {}},
+        }))
+        .await
+        .assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    server
+        .admin
+        .post("/api/v1/policies")
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({
+            "name": "tenant-a",
+            "policy_type": "row_filter",
+            "is_enabled": true,
+            "template_id": template_id,
+            "targets": [{"schemas": ["*"], "tables": ["*"]}],
+            "definition": {"params": {"tenant": "a"}},
+        }))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    // Adding a new required parameter would break the existing policy.
+    server
+        .admin
+        .put(&format!("/api/v1/policy-templates/{template_id}"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({
+            "expression": "tenant = {param.tenant} AND region = {param.region}",
+            "parameters": [
+                {"name": "tenant", "type": "string"},
+                {"name": "region", "type": "string"}
+            ],
+            "version": 1
+        }))
+        .await
+        .assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,445 @@
+//! Purpose-based access integration tests.
+//!
+//! These tests verify that a session unlocks purpose-restricted policies only
+//! after declaring an allowed purpose with a justification that passes the
+//! catalog entry's validator, that refused declarations leave the session as it
+//! was, that Flight SQL handshakes, the HTTP query API and MCP `execute_query`
+//! declare purposes the same way, and that the purpose and justification land
+//! on the query audit rows.
+//! Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// A `policy_required` datasource where `purpose_alice` may always read
+/// `claims.id, customer`, and `claims.ssn` only for `fraud_investigation`,
+/// which requires a justification naming a ticket. Returns the datasource and
+/// user ids.
+async fn setup(
+    server: &support::ProxyTestServer,
+    schema: &str,
+    ds_name: &str,
+) -> (uuid::Uuid, uuid::Uuid) {
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.claims;
+             CREATE TABLE {schema}.claims (id INT, customer TEXT, ssn TEXT);
+             INSERT INTO {schema}.claims VALUES (1, 'Ann', '111'), (2, 'Bob', '222');"
+        ))
+        .await;
+    let ds_id = server.create_datasource(ds_name, "policy_required").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user("purpose_alice", TEST_PASS, ds_id).await;
+    server
+        .create_column_allow(
+            &format!("{ds_name}-base"),
+            schema,
+            "claims",
+            &["id", "customer"],
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+
+    let resp = server
+        .admin
+        .post(&format!("/api/v1/datasources/{ds_id}/purposes"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({
+            "name": "fraud_investigation",
+            "description": "Investigating a reported fraud case",
+            "justification_validator": {"type": "regex", "pattern": "^TICKET-[0-9]+"},
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+
+    let resp = server
+        .admin
+        .post("/api/v1/policies")
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({
+            "name": format!("{ds_name}-fraud-lens"),
+            "policy_type": "column_allow",
+            "targets": [{"schemas": [schema], "tables": ["claims"], "columns": ["ssn"]}],
+            "purpose": "fraud_investigation",
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let policy_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let resp = server
+        .admin
+        .post(&format!("/api/v1/datasources/{ds_id}/policies"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"policy_id": policy_id, "user_id": user_id}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    (ds_id, user_id)
+}
+
+/// The query audit rows of `ds_id`, once at least `count` were written.
+async fn audit_rows(server: &support::ProxyTestServer, ds_id: uuid::Uuid, count: usize) -> Value {
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let body = server
+            .admin
+            .get(&format!("/api/v1/audit/queries?datasource_id={ds_id}"))
+            .authorization_bearer(&server.admin_token)
+            .await
+            .json::<Value>();
+        if body["data"].as_array().unwrap().len() >= count {
+            return body["data"].clone();
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "fewer than {count} query audit rows within 5s: {body}"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
+fn sqlstate(e: &tokio_postgres::Error) -> String {
+    e.as_db_error()
+        .map(|d| d.code().code().to_string())
+        .unwrap_or_default()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn declared_purpose_unlocks_its_policies_and_is_audited() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "purpose_unlock";
+    let (ds_id, _) = setup(&server, schema, "ds_purpose_unlock").await;
+    let client = server
+        .connect_as("purpose_alice", TEST_PASS, "ds_purpose_unlock")
+        .await;
+    let ssn_query = format!("SELECT id, ssn FROM {schema}.claims ORDER BY id");
+
+    assert!(
+        client.simple_query(&ssn_query).await.is_err(),
+        "ssn must stay hidden without a purpose"
+    );
+
+    // The purpose needs a justification, so it is set first.
+    let err = client
+        .simple_query("SET br.purpose = 'fraud_investigation'")
+        .await
+        .unwrap_err();
+    assert_eq!(sqlstate(&err), "42501", "{err:?}");
+    client
+        .simple_query("SET br.justification = 'TICKET-42 chargeback dispute'")
+        .await
+        .unwrap();
+    client
+        .simple_query("SET br.purpose = 'fraud_investigation'")
+        .await
+        .unwrap();
+
+    let shown = support::extract_rows(&client.simple_query("SHOW br.purpose").await.unwrap());
+    assert_eq!(shown, vec![vec!["fraud_investigation".to_string()]]);
+
+    let rows = support::extract_rows(&client.simple_query(&ssn_query).await.unwrap());
+    assert_eq!(rows, vec![vec!["1", "111"], vec!["2", "222"]]);
+
+    let audit = audit_rows(&server, ds_id, 1).await;
+    let row = audit
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["status"] == "success")
+        .unwrap_or_else(|| panic!("no successful query audited: {audit}"));
+    assert_eq!(row["purpose"], "fraud_investigation", "{row}");
+    assert_eq!(
+        row["justification"], "TICKET-42 chargeback dispute",
+        "{row}"
+    );
+
+    // Clearing the purpose locks the lens again.
+    client.simple_query("SET br.purpose = ''").await.unwrap();
+    assert!(client.simple_query(&ssn_query).await.is_err());
+}
+
+#[tokio::test]
+async fn refused_declarations_leave_the_session_unchanged() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "purpose_refused";
+    setup(&server, schema, "ds_purpose_refused").await;
+    let client = server
+        .connect_as("purpose_alice", TEST_PASS, "ds_purpose_refused")
+        .await;
+
+    let err = client
+        .simple_query("SET br.purpose = 'marketing'")
+        .await
+        .unwrap_err();
+    assert_eq!(sqlstate(&err), "42501", "{err:?}");
+
+    client
+        .simple_query("SET br.justification = 'just curious'")
+        .await
+        .unwrap();
+    let err = client
+        .simple_query("SET br.purpose = 'fraud_investigation'")
+        .await
+        .unwrap_err();
+    assert_eq!(sqlstate(&err), "42501", "{err:?}");
+    assert!(err.as_db_error().unwrap().message().contains("rejected"));
+
+    let shown = support::extract_rows(&client.simple_query("SHOW br.purpose").await.unwrap());
+    assert_eq!(shown, vec![vec![String::new()]]);
+    assert!(
+        client
+            .simple_query(&format!("SELECT ssn FROM {schema}.claims"))
+            .await
+            .is_err()
+    );
+    let rows = support::extract_rows(
+        &client
+            .simple_query(&format!("SELECT customer FROM {schema}.claims ORDER BY id"))
+            .await
+            .unwrap(),
+    );
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Bob"]]);
+}
+
+#[tokio::test]
+async fn flight_handshake_declares_a_validated_purpose() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "purpose_flight";
+    let (ds_id, _) = setup(&server, schema, "ds_purpose_flight").await;
+    let ssn_query = format!("SELECT id, ssn FROM {schema}.claims ORDER BY id");
+
+    let err = server
+        .flight_connect_with(
+            "purpose_alice",
+            TEST_PASS,
+            "ds_purpose_flight",
+            &[("purpose", "fraud_investigation")],
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("requires a justification"),
+        "{err}"
+    );
+    let err = server
+        .flight_connect_with(
+            "purpose_alice",
+            TEST_PASS,
+            "ds_purpose_flight",
+            &[("purpose", "marketing")],
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not allowed"), "{err}");
+
+    let mut plain = server
+        .flight_connect_as("purpose_alice", TEST_PASS, "ds_purpose_flight")
+        .await
+        .unwrap();
+    assert!(
+        plain.execute(ssn_query.clone(), None).await.is_err(),
+        "ssn must stay hidden without a purpose"
+    );
+
+    let mut client = server
+        .flight_connect_with(
+            "purpose_alice",
+            TEST_PASS,
+            "ds_purpose_flight",
+            &[
+                ("purpose", "fraud_investigation"),
+                ("justification", "TICKET-7 card testing"),
+            ],
+        )
+        .await
+        .unwrap();
+    client.execute(ssn_query, None).await.unwrap();
+
+    let audit = audit_rows(&server, ds_id, 2).await;
+    let row = audit
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["status"] == "success")
+        .unwrap_or_else(|| panic!("no successful query audited: {audit}"));
+    assert_eq!(row["purpose"], "fraud_investigation", "{row}");
+    assert_eq!(row["justification"], "TICKET-7 card testing", "{row}");
+}
+
+#[tokio::test]
+async fn query_api_declares_a_validated_purpose() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "purpose_http";
+    let (ds_id, _) = setup(&server, schema, "ds_purpose_http").await;
+    let ssn_query = format!("SELECT id, ssn FROM {schema}.claims ORDER BY id");
+
+    let resp = server
+        .admin
+        .post("/api/v1/query/token")
+        .json(&json!({"username": "purpose_alice", "password": TEST_PASS}))
+        .await;
+    resp.assert_status_ok();
+    let token = resp.json::<Value>()["token"].as_str().unwrap().to_string();
+    let query = |body: Value| {
+        server
+            .admin
+            .post("/api/v1/query")
+            .authorization_bearer(&token)
+            .json(&body)
+    };
+
+    let resp = query(json!({
+        "datasource": "ds_purpose_http",
+        "sql": ssn_query,
+        "purpose": "fraud_investigation",
+    }))
+    .await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+    assert!(resp.text().contains("requires a justification"));
+    let resp = query(json!({
+        "datasource": "ds_purpose_http",
+        "sql": ssn_query,
+        "purpose": "marketing",
+    }))
+    .await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+    assert!(resp.text().contains("not allowed"));
+
+    let resp = query(json!({"datasource": "ds_purpose_http", "sql": ssn_query})).await;
+    assert!(
+        !resp.status_code().is_success(),
+        "ssn must stay hidden without a purpose"
+    );
+
+    let resp = query(json!({
+        "datasource": "ds_purpose_http",
+        "sql": ssn_query,
+        "purpose": "fraud_investigation",
+        "justification": "TICKET-7 card testing",
+    }))
+    .await;
+    resp.assert_status_ok();
+    assert_eq!(
+        resp.json::<Value>()["rows"],
+        json!([{"id": 1, "ssn": "111"}, {"id": 2, "ssn": "222"}])
+    );
+
+    let audit = audit_rows(&server, ds_id, 2).await;
+    let row = audit
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["status"] == "success")
+        .unwrap_or_else(|| panic!("no successful query audited: {audit}"));
+    assert_eq!(row["purpose"], "fraud_investigation", "{row}");
+    assert_eq!(row["justification"], "TICKET-7 card testing", "{row}");
+}
+
+#[tokio::test]
+async fn mcp_execute_query_declares_a_validated_purpose() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "purpose_mcp";
+    let (ds_id, user_id) = setup(&server, schema, "ds_purpose_mcp").await;
+    let ssn_query = format!("SELECT id, ssn FROM {schema}.claims ORDER BY id");
+
+    let resp = server
+        .admin
+        .post(&format!("/api/v1/users/{user_id}/api-keys"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"name": "agent", "scopes": []}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let key = resp.json::<Value>()["api_key"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let rpc = |session: Option<String>, id: u64, method: &str, params: Value| {
+        let mut req = server
+            .admin
+            .post("/api/v1/mcp")
+            .authorization_bearer(&key)
+            .json(&json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}));
+        if let Some(session) = session {
+            req = req.add_header("mcp-session-id", session);
+        }
+        req
+    };
+
+    let resp = rpc(
+        None,
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": "2025-06-18",
+            "capabilities": {},
+            "clientInfo": {"name": "test-agent", "version": "1.0"},
+        }),
+    )
+    .await;
+    resp.assert_status_ok();
+    let session = resp
+        .maybe_header("mcp-session-id")
+        .map(|v| v.to_str().unwrap().to_string())
+        .expect("initialize must return a session id");
+    let execute = |id: u64, arguments: Value| {
+        rpc(
+            Some(session.clone()),
+            id,
+            "tools/call",
+            json!({"name": "execute_query", "arguments": arguments}),
+        )
+    };
+
+    let result = execute(
+        2,
+        json!({"datasource": "ds_purpose_mcp", "sql": ssn_query, "purpose": "marketing"}),
+    )
+    .await
+    .json::<Value>();
+    assert_eq!(result["result"]["isError"], true, "{result}");
+    assert!(
+        result["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("not allowed"),
+        "{result}"
+    );
+
+    let result = execute(
+        3,
+        json!({
+            "datasource": "ds_purpose_mcp",
+            "sql": ssn_query,
+            "purpose": "fraud_investigation",
+            "justification": "TICKET-7 card testing",
+        }),
+    )
+    .await
+    .json::<Value>();
+    assert_eq!(result["result"]["isError"], false, "{result}");
+    assert_eq!(
+        result["result"]["structuredContent"]["rows"],
+        json!([{"id": 1, "ssn": "111"}, {"id": 2, "ssn": "222"}])
+    );
+
+    let audit = audit_rows(&server, ds_id, 1).await;
+    let row = &audit[0];
+    assert_eq!(row["client_info"], "mcp:test-agent", "{row}");
+    assert_eq!(row["purpose"], "fraud_investigation", "{row}");
+    assert_eq!(row["justification"], "TICKET-7 card testing", "{row}");
+}
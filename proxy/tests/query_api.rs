@@ -0,0 +1,288 @@
+//! HTTP query API integration tests.
+//!
+//! These tests verify that `POST /api/v1/query` authenticates as a proxy user
+//! (API key or query token), enforces policies and audits exactly like pgwire,
+//! pages results, and that `GET /api/v1/query/schema` only exposes the
+//! caller's visible catalog. Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Seed `{schema}.orders` and `{schema}.secrets`, and create an open-mode
+/// datasource with one assigned user.
+async fn setup_datasource(
+    server: &support::ProxyTestServer,
+    schema: &str,
+    ds_name: &str,
+    username: &str,
+) -> (uuid::Uuid, uuid::Uuid) {
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.orders;
+             DROP TABLE IF EXISTS {schema}.secrets;
+             CREATE TABLE {schema}.orders (id INT, tenant TEXT, email TEXT);
+             INSERT INTO {schema}.orders VALUES
+               (1, 'acme', 'a@acme.com'),
+               (2, 'globex', 'b@globex.com'),
+               (3, 'acme', 'c@acme.com'),
+               (4, 'acme', 'd@acme.com'),
+               (5, 'acme', 'e@acme.com');
+             CREATE TABLE {schema}.secrets (id INT, value TEXT);"
+        ))
+        .await;
+
+    let ds_id = server.create_datasource(ds_name, "open").await;
+    server.discover(ds_id, &[schema]).await;
+    let user_id = server.create_user(username, TEST_PASS, ds_id).await;
+    (ds_id, user_id)
+}
+
+/// Exchange proxy-user credentials for a query token.
+async fn query_token(server: &support::ProxyTestServer, username: &str) -> String {
+    let resp = server
+        .admin
+        .post("/api/v1/query/token")
+        .json(&json!({"username": username, "password": TEST_PASS}))
+        .await;
+    resp.assert_status_ok();
+    resp.json::<Value>()["token"].as_str().unwrap().to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn query_enforces_policies_pages_and_audits() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "qa_enforce";
+    let (ds_id, user_id) = setup_datasource(&server, schema, "ds_qa_enforce", "qa_alice").await;
+    server
+        .create_row_filter(
+            "qa-tenant",
+            schema,
+            "orders",
+            "tenant = 'acme'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+    server
+        .create_column_mask(
+            "qa-mask",
+            schema,
+            "orders",
+            "email",
+            "'***'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+
+    let token = query_token(&server, "qa_alice").await;
+    let resp = server
+        .admin
+        .post("/api/v1/query")
+        .authorization_bearer(&token)
+        .json(&json!({
+            "datasource": "ds_qa_enforce",
+            "sql": format!("SELECT id, tenant, email FROM {schema}.orders ORDER BY id"),
+            "page_size": 2,
+            "max_rows": 3,
+        }))
+        .await;
+    resp.assert_status_ok();
+    let page1 = resp.json::<Value>();
+    assert_eq!(
+        page1["rows"],
+        json!([
+            {"id": 1, "tenant": "acme", "email": "***"},
+            {"id": 3, "tenant": "acme", "email": "***"},
+        ])
+    );
+    assert_eq!(page1["truncated"], true, "4 acme rows exceed max_rows=3");
+    let cursor = page1["next_cursor"].as_str().unwrap();
+
+    let resp = server
+        .admin
+        .get(&format!("/api/v1/query/cursors/{cursor}"))
+        .authorization_bearer(&token)
+        .await;
+    resp.assert_status_ok();
+    let page2 = resp.json::<Value>();
+    assert_eq!(
+        page2["rows"],
+        json!([{"id": 4, "tenant": "acme", "email": "***"}])
+    );
+    assert!(page2["next_cursor"].is_null());
+
+    let entry = server.audit_entries("qa_alice", 1).await.remove(0);
+    assert_eq!(entry["status"].as_str(), Some("success"));
+    assert_eq!(entry["client_info"].as_str(), Some("http-api"));
+}
+
+#[tokio::test]
+async fn query_rejects_writes_and_admin_tokens() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "qa_write";
+    setup_datasource(&server, schema, "ds_qa_write", "qa_bob").await;
+    let token = query_token(&server, "qa_bob").await;
+
+    let resp = server
+        .admin
+        .post("/api/v1/query")
+        .authorization_bearer(&token)
+        .json(&json!({"datasource": "ds_qa_write", "sql": format!("DELETE FROM {schema}.orders")}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+    let entry = server.audit_entries("qa_bob", 1).await.remove(0);
+    assert_eq!(entry["status"].as_str(), Some("denied"));
+
+    // Admin tokens are not proxy-user credentials.
+    let resp = server
+        .admin
+        .post("/api/v1/query")
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"datasource": "ds_qa_write", "sql": "SELECT 1"}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn api_key_authenticates_and_revocation_takes_effect() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "qa_key";
+    let (_, user_id) = setup_datasource(&server, schema, "ds_qa_key", "qa_carol").await;
+
+    let resp = server
+        .admin
+        .post(&format!("/api/v1/users/{user_id}/api-keys"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"name": "agent"}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let created = resp.json::<Value>();
+    let key = created["api_key"].as_str().unwrap().to_string();
+    let key_id = created["id"].as_str().unwrap().to_string();
+
+    let run = |key: String| {
+        let server = &server;
+        async move {
+            server
+                .admin
+                .post("/api/v1/query")
+                .authorization_bearer(&key)
+                .json(&json!({
+                    "datasource": "ds_qa_key",
+                    "sql": format!("SELECT count(*) AS n FROM {schema}.orders"),
+                    "format": "csv",
+                }))
+                .await
+        }
+    };
+
+    let resp = run(key.clone()).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.text(), "n\n5\n");
+
+    // Listing never returns the raw key.
+    let listed = server
+        .admin
+        .get(&format!("/api/v1/users/{user_id}/api-keys"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    assert!(listed[0].get("api_key").is_none());
+
+    server
+        .admin
+        .delete(&format!("/api/v1/users/{user_id}/api-keys/{key_id}"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    run(key)
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn describe_schema_hides_denied_tables_and_columns() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "qa_describe";
+    let (ds_id, user_id) = setup_datasource(&server, schema, "ds_qa_describe", "qa_dave").await;
+    server
+        .create_table_deny("qa-deny", schema, "secrets", ds_id, Some(user_id))
+        .await;
+    server
+        .create_column_deny("qa-col", schema, "orders", &["email"], ds_id, Some(user_id))
+        .await;
+
+    let token = query_token(&server, "qa_dave").await;
+    let resp = server
+        .admin
+        .get("/api/v1/query/schema")
+        .add_query_param("datasource", "ds_qa_describe")
+        .authorization_bearer(&token)
+        .await;
+    resp.assert_status_ok();
+    let body = resp.json::<Value>();
+    let schemas = body["schemas"].as_array().unwrap();
+    let ours = schemas
+        .iter()
+        .find(|s| s["name"] == schema)
+        .expect("schema missing");
+    let tables: Vec<&str> = ours["tables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(tables, vec!["orders"]);
+    let columns: Vec<&str> = ours["tables"][0]["columns"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(columns, vec!["id", "tenant"]);
+    assert!(
+        schemas
+            .iter()
+            .all(|s| s["name"] != "pg_catalog" && s["name"] != "information_schema")
+    );
+}
+
+#[tokio::test]
+async fn query_requires_datasource_access() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    setup_datasource(&server, "qa_access", "ds_qa_access", "qa_erin").await;
+    server.create_user_unassigned("qa_frank", TEST_PASS).await;
+
+    let token = query_token(&server, "qa_frank").await;
+    let resp = server
+        .admin
+        .post("/api/v1/query")
+        .authorization_bearer(&token)
+        .json(&json!({"datasource": "ds_qa_access", "sql": "SELECT 1"}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+
+    let resp = server
+        .admin
+        .post("/api/v1/query/token")
+        .json(&json!({"username": "qa_frank", "password": "wrong"}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,152 @@
+//! Query-guard policy integration tests.
+//!
+//! These tests verify that a `query_guard` policy refuses queries that break
+//! its rules with SQLSTATE 42501 and a message naming the rule, lets
+//! well-formed queries through unchanged, and that in shadow mode it blocks
+//! nothing but records `would_block_query` in `query_audit_log`. Uses a real
+//! Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+/// An `events` table with a `query_guard` policy on it, and a `users` table.
+/// Returns the policy id.
+async fn setup(
+    server: &support::ProxyTestServer,
+    schema: &str,
+    ds_name: &str,
+    username: &str,
+) -> uuid::Uuid {
+    let (_, _, policy_id) = server
+        .setup_policy_fixture(
+            &format!(
+                "CREATE SCHEMA IF NOT EXISTS {schema};
+                 DROP TABLE IF EXISTS {schema}.events;
+                 DROP TABLE IF EXISTS {schema}.users;
+                 CREATE TABLE {schema}.events (id INT, event_date DATE, user_id INT, payload TEXT);
+                 CREATE TABLE {schema}.users (id INT, name TEXT);
+                 INSERT INTO {schema}.events VALUES
+                     (1, '2026-01-01', 1, 'a'), (2, '2026-01-02', 2, 'b');
+                 INSERT INTO {schema}.users VALUES (1, 'alice'), (2, 'bob');"
+            ),
+            schema,
+            ds_name,
+            username,
+            &format!("{schema}-guard"),
+            "query_guard",
+            json!({"schemas": [schema], "tables": ["events"]}),
+            Some(json!({
+                "forbid_select_star": true,
+                "require_predicate_on": ["event_date"],
+                "forbid_cross_join": true,
+            })),
+        )
+        .await;
+    policy_id
+}
+
+#[tokio::test]
+async fn guard_blocks_unsafe_shapes() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "guard_enforce";
+    setup(&server, schema, "ds_guard_enforce", "guard_user").await;
+
+    let client = server
+        .connect_as("guard_user", TEST_PASS, "ds_guard_enforce")
+        .await;
+    for (sql, rule) in [
+        (
+            format!("SELECT * FROM {schema}.events WHERE event_date = '2026-01-01'"),
+            "forbid_select_star",
+        ),
+        (
+            format!("SELECT id FROM {schema}.events"),
+            "require_predicate_on",
+        ),
+        (
+            format!(
+                "SELECT e.id FROM {schema}.events e CROSS JOIN {schema}.users u \
+                 WHERE e.event_date = '2026-01-01'"
+            ),
+            "forbid_cross_join",
+        ),
+    ] {
+        let err = client
+            .simple_query(&sql)
+            .await
+            .expect_err("an unsafe query must be blocked");
+        let db_err = err.as_db_error().expect("Expected a DB error");
+        assert_eq!(db_err.code().code(), "42501");
+        assert!(
+            db_err.message().contains(&format!("rule '{rule}'")),
+            "{}",
+            db_err.message()
+        );
+    }
+
+    // A query that follows every rule runs unchanged.
+    let rows = client
+        .simple_query(&format!(
+            "SELECT e.id, u.name FROM {schema}.events e JOIN {schema}.users u \
+             ON e.user_id = u.id WHERE e.event_date = '2026-01-01'"
+        ))
+        .await
+        .unwrap();
+    let rows = support::extract_rows(&rows);
+    assert_eq!(rows, vec![vec!["1".to_string(), "alice".to_string()]]);
+
+    let entries = server.audit_entries("guard_user", 4).await;
+    assert_eq!(
+        entries
+            .iter()
+            .filter(|e| e["status"].as_str() == Some("denied"))
+            .count(),
+        3
+    );
+}
+
+#[tokio::test]
+async fn shadow_guard_records_without_blocking() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "guard_shadow";
+    let policy_id = setup(&server, schema, "ds_guard_shadow", "guard_shadow_user").await;
+    let policy = server
+        .admin
+        .get(&format!("/api/v1/policies/{policy_id}"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    server
+        .admin
+        .put(&format!("/api/v1/policies/{policy_id}"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"action_status": "shadow", "version": policy["version"]}))
+        .await
+        .assert_status_ok();
+
+    let client = server
+        .connect_as("guard_shadow_user", TEST_PASS, "ds_guard_shadow")
+        .await;
+    let rows = client
+        .simple_query(&format!("SELECT id FROM {schema}.events"))
+        .await
+        .expect("a shadow guard must not block the query");
+    assert_eq!(support::extract_rows(&rows).len(), 2);
+
+    let entries = server.audit_entries("guard_shadow_user", 1).await;
+    assert_eq!(entries[0]["status"].as_str(), Some("success"));
+    let outcomes = &entries[0]["shadow_outcomes"];
+    assert_eq!(outcomes[0]["outcome"], "would_block_query");
+    assert_eq!(outcomes[0]["tables"], json!([format!("{schema}.events")]));
+    assert!(
+        outcomes[0]["expression"]
+            .as_str()
+            .unwrap()
+            .contains("require_predicate_on"),
+        "{outcomes}"
+    );
+}
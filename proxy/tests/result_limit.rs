@@ -0,0 +1,182 @@
+//! Result-limit policy integration tests.
+//!
+//! These tests verify that a `result_limit` policy truncates results at
+//! `max_rows`, fails oversized results with SQLSTATE 54000 under
+//! `on_exceed: deny`, refuses queries with SQLSTATE 53400 once a per-window row
+//! quota is used up, and that `query_audit_log` records the rows and bytes each
+//! query returned. Uses a real Postgres container.
+
+mod support;
+
+use serde_json::json;
+use support::TEST_PASS;
+
+/// 50 customers and a `result_limit` policy with `definition` on the table.
+async fn setup(
+    server: &support::ProxyTestServer,
+    schema: &str,
+    ds_name: &str,
+    username: &str,
+    definition: serde_json::Value,
+) {
+    server
+        .setup_policy_fixture(
+            &format!(
+                "CREATE SCHEMA IF NOT EXISTS {schema};
+                 DROP TABLE IF EXISTS {schema}.customers;
+                 CREATE TABLE {schema}.customers (id INT, email TEXT);
+                 INSERT INTO {schema}.customers
+                     SELECT i, 'user' || i || '@example.com' FROM generate_series(1, 50) AS i;"
+            ),
+            schema,
+            ds_name,
+            username,
+            &format!("{schema}-limit"),
+            "result_limit",
+            json!({"schemas": [schema], "tables": ["customers"]}),
+            Some(definition),
+        )
+        .await;
+}
+
+#[tokio::test]
+async fn max_rows_truncates_and_audits_volume() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "limit_truncate";
+    setup(
+        &server,
+        schema,
+        "ds_limit_truncate",
+        "limit_truncate_user",
+        json!({"max_rows": 10}),
+    )
+    .await;
+
+    let client = server
+        .connect_as("limit_truncate_user", TEST_PASS, "ds_limit_truncate")
+        .await;
+    let rows = client
+        .simple_query(&format!("SELECT * FROM {schema}.customers ORDER BY id"))
+        .await
+        .unwrap();
+    let rows = support::extract_rows(&rows);
+    assert_eq!(rows.len(), 10);
+    assert_eq!(rows[9][0], "10");
+
+    let entries = server.audit_entries("limit_truncate_user", 1).await;
+    assert_eq!(entries[0]["status"].as_str(), Some("success"));
+    assert_eq!(entries[0]["rows_returned"].as_i64(), Some(10));
+    assert!(entries[0]["bytes_returned"].as_i64().unwrap() > 0);
+    assert!(
+        entries[0]["rewritten_query"]
+            .as_str()
+            .unwrap()
+            .contains("LIMIT 10"),
+        "{}",
+        entries[0]["rewritten_query"]
+    );
+}
+
+#[tokio::test]
+async fn deny_fails_oversized_results() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "limit_deny";
+    setup(
+        &server,
+        schema,
+        "ds_limit_deny",
+        "limit_deny_user",
+        json!({"max_rows": 10, "on_exceed": "deny"}),
+    )
+    .await;
+
+    let client = server
+        .connect_as("limit_deny_user", TEST_PASS, "ds_limit_deny")
+        .await;
+    let err = client
+        .simple_query(&format!("SELECT * FROM {schema}.customers"))
+        .await
+        .expect_err("a result over max_rows must fail");
+    let db_err = err.as_db_error().expect("Expected a DB error");
+    assert_eq!(db_err.code().code(), "54000");
+    assert!(
+        db_err.message().contains("exceeds 10 rows"),
+        "{}",
+        db_err.message()
+    );
+
+    // A result within the cap is returned in full.
+    let rows = client
+        .simple_query(&format!("SELECT id FROM {schema}.customers WHERE id <= 5"))
+        .await
+        .unwrap();
+    assert_eq!(support::extract_rows(&rows).len(), 5);
+
+    let entries = server.audit_entries("limit_deny_user", 2).await;
+    assert!(
+        entries
+            .iter()
+            .any(|e| e["status"].as_str() == Some("denied")),
+        "the oversized result must be audited as denied"
+    );
+    assert!(
+        entries
+            .iter()
+            .filter(|e| e["status"].as_str() == Some("denied"))
+            .all(|e| e["rows_returned"].as_i64() == Some(0)),
+        "no rows of the oversized result may be sent"
+    );
+    assert!(
+        entries
+            .iter()
+            .any(|e| e["rows_returned"].as_i64() == Some(5))
+    );
+}
+
+#[tokio::test]
+async fn window_quota_caps_and_refuses() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "limit_window";
+    setup(
+        &server,
+        schema,
+        "ds_limit_window",
+        "limit_window_user",
+        json!({"window_rows": 15, "window_secs": 3600}),
+    )
+    .await;
+
+    let client = server
+        .connect_as("limit_window_user", TEST_PASS, "ds_limit_window")
+        .await;
+    let rows = client
+        .simple_query(&format!("SELECT id FROM {schema}.customers WHERE id <= 10"))
+        .await
+        .unwrap();
+    assert_eq!(support::extract_rows(&rows).len(), 10);
+    // The quota is recorded when the audit entry is written.
+    server.audit_entries("limit_window_user", 1).await;
+
+    // Only 5 rows of the quota are left.
+    let rows = client
+        .simple_query(&format!("SELECT id FROM {schema}.customers"))
+        .await
+        .unwrap();
+    assert_eq!(support::extract_rows(&rows).len(), 5);
+    server.audit_entries("limit_window_user", 2).await;
+
+    let err = client
+        .simple_query(&format!("SELECT id FROM {schema}.customers"))
+        .await
+        .expect_err("a used-up quota must refuse the query");
+    let db_err = err.as_db_error().expect("Expected a DB error");
+    assert_eq!(db_err.code().code(), "53400");
+    assert!(
+        db_err.message().contains("Row quota exhausted"),
+        "{}",
+        db_err.message()
+    );
+}
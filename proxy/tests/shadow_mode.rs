@@ -0,0 +1,208 @@
+//! Shadow-mode integration tests.
+//!
+//! These tests verify that a policy in shadow mode (on the policy itself or on
+//! one assignment) does not change query results, that its counterfactual
+//! outcome is written to `query_audit_log.shadow_outcomes`, and that
+//! `GET /audit/shadow` aggregates the hits per policy. Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn setup(server: &support::ProxyTestServer, schema: &str, ds_name: &str) -> uuid::Uuid {
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.orders;
+             CREATE TABLE {schema}.orders (id INT, tenant TEXT, card TEXT);
+             INSERT INTO {schema}.orders VALUES (1, 'acme', '4111'), (2, 'globex', '4222');"
+        ))
+        .await;
+    let ds_id = server.create_datasource(ds_name, "open").await;
+    server.discover(ds_id, &[schema]).await;
+    ds_id
+}
+
+async fn set_policy_shadow(server: &support::ProxyTestServer, policy_id: uuid::Uuid) {
+    let policy = server
+        .admin
+        .get(&format!("/api/v1/policies/{policy_id}"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    server
+        .admin
+        .put(&format!("/api/v1/policies/{policy_id}"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"action_status": "shadow", "version": policy["version"]}))
+        .await
+        .assert_status_ok();
+}
+
+async fn wait_for_shadow_outcomes(server: &support::ProxyTestServer, username: &str) -> Value {
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let body = server
+            .admin
+            .get("/api/v1/audit/queries")
+            .authorization_bearer(&server.admin_token)
+            .await
+            .json::<Value>();
+        if let Some(e) = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["username"].as_str() == Some(username))
+        {
+            return e["shadow_outcomes"].clone();
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "audit entry for {username} did not appear within 5s"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn shadow_policies_are_audited_but_not_applied() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "shadow_basic";
+    let ds_id = setup(&server, schema, "ds_shadow_basic").await;
+    let user_id = server.create_user("shadow_alice", TEST_PASS, ds_id).await;
+
+    let filter_id = server
+        .create_row_filter(
+            "shadow-tenant",
+            schema,
+            "orders",
+            "tenant = 'acme'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+    set_policy_shadow(&server, filter_id).await;
+    let mask_id = server
+        .create_column_mask(
+            "shadow-card",
+            schema,
+            "orders",
+            "card",
+            "'****'",
+            ds_id,
+            Some(user_id),
+        )
+        .await;
+    set_policy_shadow(&server, mask_id).await;
+
+    let client = server
+        .connect_as("shadow_alice", TEST_PASS, "ds_shadow_basic")
+        .await;
+    let rows = client
+        .query(
+            &format!("SELECT id, card FROM {schema}.orders ORDER BY id"),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 2, "shadow row filter must not remove rows");
+    assert_eq!(
+        rows[0].get::<_, String>(1),
+        "4111",
+        "shadow mask must not mask"
+    );
+
+    let outcomes = wait_for_shadow_outcomes(&server, "shadow_alice").await;
+    let outcomes = outcomes.as_array().expect("shadow_outcomes recorded");
+    let by_policy = |id: uuid::Uuid| {
+        outcomes
+            .iter()
+            .find(|o| o["policy_id"] == id.to_string())
+            .unwrap_or_else(|| panic!("no outcome for {id}: {outcomes:?}"))
+    };
+    assert_eq!(by_policy(filter_id)["outcome"], "would_filter");
+    assert_eq!(by_policy(filter_id)["expression"], "tenant = 'acme'");
+    assert_eq!(by_policy(mask_id)["outcome"], "would_mask");
+    assert_eq!(
+        by_policy(mask_id)["columns"],
+        json!([format!("{schema}.orders.card")])
+    );
+
+    let report = server
+        .admin
+        .get(&format!("/api/v1/audit/shadow?datasource_id={ds_id}"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    let entry = report
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["policy_id"] == filter_id.to_string())
+        .expect("shadow report entry for the row filter");
+    assert_eq!(entry["hits"], 1);
+    assert_eq!(entry["would_filter"], 1);
+    assert_eq!(entry["users"], 1);
+}
+
+#[tokio::test]
+async fn shadow_assignment_does_not_deny() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "shadow_assign";
+    let ds_id = setup(&server, schema, "ds_shadow_assign").await;
+    let user_id = server.create_user("shadow_bob", TEST_PASS, ds_id).await;
+    let deny_id = server
+        .create_table_deny("shadow-deny", schema, "orders", ds_id, Some(user_id))
+        .await;
+
+    let assignments = server
+        .admin
+        .get(&format!("/api/v1/datasources/{ds_id}/policies"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    let assignment_id = assignments
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|a| a["policy_id"] == deny_id.to_string())
+        .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let resp = server
+        .admin
+        .put(&format!(
+            "/api/v1/datasources/{ds_id}/policies/{assignment_id}"
+        ))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"action_status": "shadow"}))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["action_status"], "shadow");
+
+    let client = server
+        .connect_as("shadow_bob", TEST_PASS, "ds_shadow_assign")
+        .await;
+    let rows = client
+        .query(&format!("SELECT id FROM {schema}.orders"), &[])
+        .await
+        .expect("shadow table_deny must not reject the query");
+    assert_eq!(rows.len(), 2);
+
+    let outcomes = wait_for_shadow_outcomes(&server, "shadow_bob").await;
+    assert_eq!(outcomes[0]["policy_id"], deny_id.to_string());
+    assert_eq!(outcomes[0]["outcome"], "would_deny");
+    assert_eq!(outcomes[0]["tables"], json!([format!("{schema}.orders")]));
+}
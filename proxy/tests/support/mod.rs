@@ -4,6 +4,7 @@
 //! - A shared Postgres container (one per test binary, via background thread)
 //! - An in-memory SQLite admin DB (per test)
 //! - A real `ProxyHandler` on a random TCP port (per test)
+//! - A real Arrow Flight SQL server on a random TCP port (per test)
 //! - An `axum_test::TestServer` wrapping the admin API (per test)
 
 use std::sync::{Arc, OnceLock};
@@ -53,10 +54,15 @@ use proxy::admin::discovery_job::JobStore;
 use proxy::admin::{AdminState, admin_router};
 use proxy::auth::Auth;
 use proxy::engine::EngineCache;
+use proxy::flight::FlightSqlServer;
 use proxy::handler::ProxyHandler;
 use proxy::hooks::policy::PolicyHook;
 use proxy::server::process_socket_with_idle_timeout;
 
+use arrow_flight::sql::client::FlightSqlServiceClient;
+use datafusion::arrow::error::ArrowError;
+use tonic::transport::Channel;
+
 // ---------------------------------------------------------------------------
 // Shared WASM runtime (one per test binary)
 // ---------------------------------------------------------------------------
@@ -182,9 +188,12 @@ macro_rules! require_javy {
 
 pub struct ProxyTestServer {
     pub admin: TestServer,
+    #[allow(dead_code)]
     pub proxy_port: u16,
+    pub flight_port: u16,
     pub admin_token: String,
     _accept_handle: JoinHandle<()>,
+    _flight_handle: JoinHandle<()>,
 }
 
 const JWT_SECRET: &str = "integration-test-jwt-secret-key!";
@@ -198,6 +207,13 @@ impl ProxyTestServer {
     /// `_schema_prefix` is currently unused but reserved for future per-test
     /// Postgres schema isolation (all tests already use unique schema names).
     pub async fn start() -> Self {
+        Self::start_with_explain_level(proxy::hooks::policy::ExplainLevel::default()).await
+    }
+
+    /// [`Self::start`] with `BR_EXPLAIN_POLICY` set to `explain_level`.
+    pub async fn start_with_explain_level(
+        explain_level: proxy::hooks::policy::ExplainLevel,
+    ) -> Self {
         // 1. In-memory SQLite admin DB
         let db = Database::connect("sqlite::memory:").await.unwrap();
         migration::Migrator::up(&db, None).await.unwrap();
@@ -211,7 +227,7 @@ impl ProxyTestServer {
         // 3. WASM runtime + engine cache + policy hook
         let wasm_runtime = shared_wasm_runtime();
         let engine_cache = EngineCache::new(db.clone(), MASTER_KEY, wasm_runtime.clone());
-        let policy_hook = PolicyHook::new(db.clone(), wasm_runtime.clone());
+        let policy_hook = PolicyHook::new(db.clone(), wasm_runtime.clone(), explain_level);
 
         // 4. ProxyHandler
         let handler = Arc::new(ProxyHandler::new(
@@ -232,6 +248,7 @@ impl ProxyTestServer {
             policy_hook: Some(policy_hook.clone()),
             proxy_handler: Some(handler.clone()),
             wasm_runtime,
+            query_api: Arc::new(proxy::admin::query_handlers::QueryApi::default()),
         };
 
         // 6. axum-test TestServer for the admin API
@@ -264,7 +281,24 @@ impl ProxyTestServer {
             }
         });
 
-        // 9. Login to get admin JWT
+        // 9. Arrow Flight SQL server on a random port (mirrors main.rs)
+        let flight_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let flight_port = flight_listener.local_addr().unwrap().port();
+        let flight_server = FlightSqlServer::new(
+            auth.clone(),
+            engine_cache.clone(),
+            policy_hook.clone(),
+            handler.clone(),
+            Duration::from_secs(300),
+        );
+        let flight_handle = tokio::spawn(async move {
+            let _ = tonic::transport::Server::builder()
+                .add_service(flight_server.into_service())
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(flight_listener))
+                .await;
+        });
+
+        // 10. Login to get admin JWT
         let login_resp: axum_test::TestResponse = admin
             .post("/api/v1/auth/login")
             .json(&json!({
@@ -281,8 +315,10 @@ impl ProxyTestServer {
         Self {
             admin,
             proxy_port,
+            flight_port,
             admin_token: token,
             _accept_handle: accept_handle,
+            _flight_handle: flight_handle,
         }
     }
 
@@ -558,6 +594,69 @@ impl ProxyTestServer {
         policy_id
     }
 
+    /// Seed the upstream with `seed_sql`, create an open-mode datasource over
+    /// `schema` with `username` assigned, and assign that user one policy.
+    /// Returns `(ds_id, user_id, policy_id)`.
+    #[allow(dead_code)]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn setup_policy_fixture(
+        &self,
+        seed_sql: &str,
+        schema: &str,
+        ds_name: &str,
+        username: &str,
+        policy_name: &str,
+        policy_type: &str,
+        target: Value,
+        definition: Option<Value>,
+    ) -> (Uuid, Uuid, Uuid) {
+        self.seed_upstream(seed_sql).await;
+        let ds_id = self.create_datasource(ds_name, "open").await;
+        self.discover(ds_id, &[schema]).await;
+        let user_id = self.create_user(username, TEST_PASS, ds_id).await;
+        let policy_id = self
+            .create_and_assign_policy(
+                policy_name,
+                policy_type,
+                vec![target],
+                definition,
+                ds_id,
+                Some(user_id),
+            )
+            .await;
+        (ds_id, user_id, policy_id)
+    }
+
+    /// Poll the query audit log until `count` entries for `username` appear.
+    #[allow(dead_code)]
+    pub async fn audit_entries(&self, username: &str, count: usize) -> Vec<Value> {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            let resp = self
+                .admin
+                .get("/api/v1/audit/queries")
+                .authorization_bearer(&self.admin_token)
+                .await;
+            resp.assert_status_ok();
+            let body = resp.json::<Value>();
+            let entries: Vec<Value> = body["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|e| e["username"].as_str() == Some(username))
+                .cloned()
+                .collect();
+            if entries.len() >= count {
+                return entries;
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "{count} audit entries for {username} did not appear within 5s"
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
     /// Shortcut: create a row_filter policy.
     #[allow(dead_code)]
     pub async fn create_row_filter(
@@ -681,6 +780,7 @@ impl ProxyTestServer {
     }
 
     /// Connect to the proxy as a specific user against a specific datasource.
+    #[allow(dead_code)]
     pub async fn connect_as(
         &self,
         username: &str,
@@ -701,6 +801,7 @@ impl ProxyTestServer {
     }
 
     /// Try to connect to the proxy; returns Err if the connection fails (auth, etc.)
+    #[allow(dead_code)]
     pub async fn try_connect_as(
         &self,
         username: &str,
@@ -720,6 +821,42 @@ impl ProxyTestServer {
         Ok(client)
     }
 
+    /// Open an Arrow Flight SQL session as a specific user against a specific datasource.
+    /// Returns Err if the handshake fails (auth, access, unknown datasource).
+    #[allow(dead_code)]
+    pub async fn flight_connect_as(
+        &self,
+        username: &str,
+        password: &str,
+        datasource: &str,
+    ) -> Result<FlightSqlServiceClient<Channel>, ArrowError> {
+        self.flight_connect_with(username, password, datasource, &[])
+            .await
+    }
+
+    /// Like `flight_connect_as`, sending extra handshake headers (e.g. `purpose`).
+    #[allow(dead_code)]
+    pub async fn flight_connect_with(
+        &self,
+        username: &str,
+        password: &str,
+        datasource: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<FlightSqlServiceClient<Channel>, ArrowError> {
+        let channel = Channel::from_shared(format!("http://127.0.0.1:{}", self.flight_port))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut client = FlightSqlServiceClient::new(channel);
+        client.set_header("database", datasource);
+        for (key, value) in headers {
+            client.set_header(*key, *value);
+        }
+        client.handshake(username, password).await?;
+        Ok(client)
+    }
+
     /// Create a non-admin user via the admin API WITHOUT assigning them to any datasource.
     /// Used for testing that unassigned users cannot access a datasource.
     #[allow(dead_code)]
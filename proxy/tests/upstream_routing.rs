@@ -0,0 +1,271 @@
+//! Read-replica routing and failover integration tests.
+//!
+//! These tests verify that a datasource with several upstream endpoints keeps
+//! serving queries when one endpoint is unreachable, that the serving endpoint
+//! is recorded in the audit log, and that `GET /datasources/{id}/upstreams`
+//! reports the failed endpoint. The "other" endpoint is a closed local port;
+//! the live one is the shared Postgres container. Uses a real Postgres container.
+
+mod support;
+
+use serde_json::{Value, json};
+use support::TEST_PASS;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// An address nothing listens on, so connections are refused immediately.
+const DEAD_HOST: &str = "127.0.0.1";
+const DEAD_PORT: u16 = 1;
+
+async fn setup(
+    server: &support::ProxyTestServer,
+    schema: &str,
+    ds_name: &str,
+    username: &str,
+) -> uuid::Uuid {
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP TABLE IF EXISTS {schema}.items;
+             CREATE TABLE {schema}.items (id INT);
+             INSERT INTO {schema}.items VALUES (1), (2);"
+        ))
+        .await;
+    let ds_id = server.create_datasource(ds_name, "open").await;
+    server.discover(ds_id, &[schema]).await;
+    server.create_user(username, TEST_PASS, ds_id).await;
+    ds_id
+}
+
+async fn update_config(server: &support::ProxyTestServer, ds_id: uuid::Uuid, config: Value) {
+    server
+        .admin
+        .put(&format!("/api/v1/datasources/{ds_id}"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({ "config": config }))
+        .await
+        .assert_status_ok();
+}
+
+async fn run_count(server: &support::ProxyTestServer, username: &str, ds_name: &str, schema: &str) {
+    let token = server
+        .admin
+        .post("/api/v1/query/token")
+        .json(&json!({"username": username, "password": TEST_PASS}))
+        .await
+        .json::<Value>()["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let resp = server
+        .admin
+        .post("/api/v1/query")
+        .authorization_bearer(&token)
+        .json(&json!({
+            "datasource": ds_name,
+            "sql": format!("SELECT count(*) AS n FROM {schema}.items"),
+        }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["rows"], json!([{"n": 2}]));
+}
+
+async fn wait_for_served_by(server: &support::ProxyTestServer, username: &str) -> String {
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let body = server
+            .admin
+            .get("/api/v1/audit/queries")
+            .authorization_bearer(&server.admin_token)
+            .await
+            .json::<Value>();
+        if let Some(e) = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["username"].as_str() == Some(username))
+        {
+            return e["served_by"].as_str().unwrap_or_default().to_string();
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "audit entry for {username} did not appear within 5s"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn unreachable_replica_falls_back_to_primary() {
+    let pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "up_replica";
+    let ds_id = setup(&server, schema, "ds_up_replica", "up_alice").await;
+    update_config(
+        &server,
+        ds_id,
+        json!({"replicas": format!("{DEAD_HOST}:{DEAD_PORT}"), "read_preference": "replica"}),
+    )
+    .await;
+
+    run_count(&server, "up_alice", "ds_up_replica", schema).await;
+    assert_eq!(
+        wait_for_served_by(&server, "up_alice").await,
+        format!("{}:{}", pg.host, pg.port)
+    );
+
+    let upstreams = server
+        .admin
+        .get(&format!("/api/v1/datasources/{ds_id}/upstreams"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    assert_eq!(upstreams[1]["address"], format!("{DEAD_HOST}:{DEAD_PORT}"));
+    assert_eq!(upstreams[1]["configured_role"], "replica");
+    assert_eq!(upstreams[1]["state"], "down");
+    assert_eq!(upstreams[1]["eligible"], false);
+}
+
+#[tokio::test]
+async fn unreachable_primary_fails_over_to_replica() {
+    let pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "up_failover";
+    let ds_id = setup(&server, schema, "ds_up_failover", "up_bob").await;
+    update_config(
+        &server,
+        ds_id,
+        json!({
+            "host": DEAD_HOST,
+            "port": DEAD_PORT,
+            "replicas": format!("{}:{}", pg.host, pg.port),
+            "read_preference": "primary",
+        }),
+    )
+    .await;
+
+    run_count(&server, "up_bob", "ds_up_failover", schema).await;
+    assert_eq!(
+        wait_for_served_by(&server, "up_bob").await,
+        format!("{}:{}", pg.host, pg.port)
+    );
+}
+
+#[tokio::test]
+async fn invalid_replica_list_is_rejected() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let ds_id = server.create_datasource("ds_up_invalid", "open").await;
+    let resp = server
+        .admin
+        .put(&format!("/api/v1/datasources/{ds_id}"))
+        .authorization_bearer(&server.admin_token)
+        .json(&json!({"config": {"replicas": "replica-1:not-a-port"}}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn pool_limits_apply_to_upstream_connections() {
+    let pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let schema = "up_limits";
+    server
+        .seed_upstream(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema};
+             DROP VIEW IF EXISTS {schema}.slow;
+             DROP TABLE IF EXISTS {schema}.items;
+             CREATE TABLE {schema}.items (id INT);
+             INSERT INTO {schema}.items VALUES (1), (2);
+             CREATE VIEW {schema}.slow AS SELECT id FROM {schema}.items, pg_sleep(2);"
+        ))
+        .await;
+    let ds_id = server.create_datasource("ds_up_limits", "open").await;
+    server.discover(ds_id, &[schema]).await;
+    server.create_user("up_carol", TEST_PASS, ds_id).await;
+    update_config(
+        &server,
+        ds_id,
+        json!({
+            "max_pool_size": 3,
+            "statement_timeout_ms": 200,
+            "application_name": "br-pool-limits",
+        }),
+    )
+    .await;
+
+    run_count(&server, "up_carol", "ds_up_limits", schema).await;
+
+    // Pooled connections stay open and carry the configured application_name.
+    let (client, conn) = tokio_postgres::connect(&pg.url, tokio_postgres::NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(conn);
+    let row = client
+        .query_one(
+            "SELECT count(*) FROM pg_stat_activity WHERE application_name = 'br-pool-limits'",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert!(row.get::<_, i64>(0) >= 1);
+
+    // The upstream statement_timeout cancels the 2s view scan.
+    let token = server
+        .admin
+        .post("/api/v1/query/token")
+        .json(&json!({"username": "up_carol", "password": TEST_PASS}))
+        .await
+        .json::<Value>()["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let resp = server
+        .admin
+        .post("/api/v1/query")
+        .authorization_bearer(&token)
+        .json(&json!({
+            "datasource": "ds_up_limits",
+            "sql": format!("SELECT count(*) FROM {schema}.slow"),
+        }))
+        .await;
+    assert!(!resp.status_code().is_success());
+
+    let upstreams = server
+        .admin
+        .get(&format!("/api/v1/datasources/{ds_id}/upstreams"))
+        .authorization_bearer(&server.admin_token)
+        .await
+        .json::<Value>();
+    let pool = &upstreams[0]["pool"];
+    assert_eq!(pool["open"], true);
+    assert_eq!(pool["max_size"], 3);
+    assert_eq!(pool["in_use"], 0);
+    assert!(pool["checkouts_total"].as_u64().unwrap() >= 2);
+}
+
+#[tokio::test]
+async fn invalid_pool_limits_are_rejected() {
+    let _pg = require_postgres!();
+    let server = support::ProxyTestServer::start().await;
+    let ds_id = server.create_datasource("ds_up_bad_limits", "open").await;
+    for config in [
+        json!({"max_pool_size": 0}),
+        json!({"statement_timeout_ms": "soon"}),
+        json!({"application_name": "x".repeat(64)}),
+    ] {
+        let resp = server
+            .admin
+            .put(&format!("/api/v1/datasources/{ds_id}"))
+            .authorization_bearer(&server.admin_token)
+            .json(&json!({ "config": config }))
+            .await;
+        resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}